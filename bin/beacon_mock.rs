@@ -1,79 +1,632 @@
-use alloy::primitives::B256;
-use axum::{Json, Router, extract::Path, routing::get};
-use eyre::Result;
-use lookahead::constants::PROPOSER_DUTIES_ROUTE;
-use lookahead::types::{ProposerDutiesResponse, ValidatorDuty};
-use lookahead::utils::{epoch_to_first_slot, epoch_to_last_slot};
-use tracing::info;
+use alloy::primitives::{B256, keccak256};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::Parser;
+use common::logging::LoggingConfig;
+use config::cli::{ConfigCommand, ServiceArgs, ServiceCommand};
+use config::{ConfigErrors, Validate};
+use eyre::{Result, WrapErr};
+use lookahead::constants::{
+	EVENTS_ROUTE, GENESIS_ROUTE, HEAD_HEADER_ROUTE, NODE_SYNCING_ROUTE, PROPOSER_DUTIES_ROUTE, SPEC_ROUTE,
+	VALIDATOR_STATUS_ROUTE,
+};
+use lookahead::types::{ProposerDutiesResponse, ValidatorDuty, ValidatorStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::{BroadcastStream, IntervalStream};
+use tracing::{info, warn};
+
+/// Admin route reading or replacing the duty schedule
+const ADMIN_SCHEDULE_ROUTE: &str = "admin/schedule";
+/// Admin route reading or replacing the injected faults
+const ADMIN_FAULTS_ROUTE: &str = "admin/faults";
+/// Admin route reorging the duty of a slot
+const ADMIN_REORG_ROUTE: &str = "admin/reorg";
+
+/// Proposer of the slots of missed epochs when the schedule sets none
+const DEFAULT_OTHER_PUBKEY: &str =
+	"0x879d322fb401a2638b6217cab6e9bf954e6df9b18e0c302f3bdc00551a8ac308459d8a79eb54f0f272e6b648ee4d03b3";
+
+/// Mock beacon node serving scriptable proposer duties, with faults injectable at runtime
+#[derive(Debug, Parser)]
+struct Args {
+	#[command(flatten)]
+	service: ServiceArgs,
+
+	#[command(subcommand)]
+	command: Option<ServiceCommand>,
+}
+
+/// Configuration for the mock beacon node, read from `--config` or from BEACON_HOST, BEACON_PORT and PROPOSER_KEY,
+/// with GENESIS_TIME, SECONDS_PER_SLOT and SCHEDULE_FILE optional
+#[derive(Debug, Deserialize)]
+struct BeaconMockConfig {
+	/// Host to listen on
+	host: String,
+	/// Port to listen on
+	port: u16,
+	/// BLS public key returned as the proposer of every slot the schedule does not assign to another key
+	proposer_key: String,
+	/// Unix timestamp in seconds of slot 0, the time the mock starts if not set
+	#[serde(default)]
+	genesis_time: Option<u64>,
+	/// Slot duration in seconds
+	#[serde(default = "default_seconds_per_slot")]
+	seconds_per_slot: u64,
+	/// Slots per epoch
+	#[serde(default = "default_slots_per_epoch")]
+	slots_per_epoch: u64,
+	/// Status of the proposer's validator, e.g. `active_slashed` or `exited_unslashed` to simulate a proposer that
+	/// can no longer propose
+	#[serde(default = "default_validator_status")]
+	validator_status: ValidatorStatus,
+	/// TOML or JSON duty schedule, by extension, overriding the proposer of some slots
+	#[serde(default)]
+	schedule_file: Option<PathBuf>,
+	/// Faults injected from startup, replaceable at runtime through the admin endpoint
+	#[serde(default)]
+	faults: Faults,
+}
+
+fn default_seconds_per_slot() -> u64 {
+	12
+}
+
+fn default_slots_per_epoch() -> u64 {
+	32
+}
+
+fn default_validator_status() -> ValidatorStatus {
+	ValidatorStatus::ActiveOngoing
+}
+
+impl BeaconMockConfig {
+	fn load(args: &ServiceArgs) -> Result<Self> {
+		if let Some(path) = &args.config {
+			return config::load_file(path, "FABRIC_BEACON_MOCK");
+		}
+		let optional = |name: &str| -> Result<Option<u64>> {
+			std::env::var(name)
+				.ok()
+				.map(|value| value.parse().wrap_err_with(|| format!("Invalid {}", name)))
+				.transpose()
+		};
+		let config = Self {
+			host: std::env::var("BEACON_HOST").wrap_err("No config file, pass --config or set BEACON_HOST")?,
+			port: std::env::var("BEACON_PORT")
+				.wrap_err("No config file, pass --config or set BEACON_PORT")?
+				.parse()
+				.wrap_err("Invalid BEACON_PORT")?,
+			proposer_key: std::env::var("PROPOSER_KEY")
+				.wrap_err("No config file, pass --config or set PROPOSER_KEY")?,
+			genesis_time: optional("GENESIS_TIME")?,
+			seconds_per_slot: optional("SECONDS_PER_SLOT")?.unwrap_or_else(default_seconds_per_slot),
+			slots_per_epoch: default_slots_per_epoch(),
+			validator_status: default_validator_status(),
+			schedule_file: std::env::var("SCHEDULE_FILE").ok().map(PathBuf::from),
+			faults: Faults::default(),
+		};
+		config.check("the environment")?;
+		Ok(config)
+	}
+}
+
+impl Validate for BeaconMockConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.host("host", &self.host);
+		errors.port("port", self.port);
+		errors.bls_public_key("proposer_key", &self.proposer_key);
+		errors.positive("seconds_per_slot", self.seconds_per_slot);
+		errors.positive("slots_per_epoch", self.slots_per_epoch);
+		if let Some(path) = &self.schedule_file {
+			errors.ensure(path.is_file(), "schedule_file", format!("{} is not a file", path.display()));
+		}
+		self.faults.validate(errors);
+	}
+}
+
+/// Proposer duties differing from the default of the proposer key proposing every slot
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct DutySchedule {
+	/// Epochs where the proposer key has no duty, their slots are proposed by `other_pubkey`
+	missed_epochs: Vec<u64>,
+	/// Proposer of the slots of missed epochs, a fixed unrelated key if not set
+	other_pubkey: Option<String>,
+	/// Proposers of single slots, later entries overriding earlier ones
+	slots: Vec<SlotDuty>,
+	/// Duties changing once the head reaches `at_slot`, e.g. in the middle of the slot's epoch
+	changes: Vec<DutyChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SlotDuty {
+	slot: u64,
+	pubkey: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DutyChange {
+	/// Head slot from which the change is visible
+	at_slot: u64,
+	slot: u64,
+	pubkey: String,
+}
+
+impl DutySchedule {
+	fn load(path: &std::path::Path) -> Result<Self> {
+		let content = std::fs::read_to_string(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+		let schedule: Self = if path.extension().is_some_and(|extension| extension == "json") {
+			serde_json::from_str(&content).wrap_err_with(|| format!("Failed to parse schedule {}", path.display()))?
+		} else {
+			toml::from_str(&content).wrap_err_with(|| format!("Failed to parse schedule {}", path.display()))?
+		};
+		schedule.check(&path.display().to_string())?;
+		Ok(schedule)
+	}
+
+	fn other_pubkey(&self) -> &str {
+		self.other_pubkey.as_deref().unwrap_or(DEFAULT_OTHER_PUBKEY)
+	}
+
+	/// Proposer of `slot` as seen from the `head` slot, `None` for the default proposer key
+	fn proposer(&self, slot: u64, head: u64, slots_per_epoch: u64) -> Option<&str> {
+		if let Some(change) = self.changes.iter().rev().find(|change| change.slot == slot && change.at_slot <= head) {
+			return Some(&change.pubkey);
+		}
+		if let Some(duty) = self.slots.iter().rev().find(|duty| duty.slot == slot) {
+			return Some(&duty.pubkey);
+		}
+		self.missed_epochs.contains(&(slot / slots_per_epoch)).then(|| self.other_pubkey())
+	}
+
+	/// A slot assigned to `pubkey`, used as its validator index
+	fn slot_of(&self, pubkey: &str, slots_per_epoch: u64) -> Option<u64> {
+		let scheduled = self.slots.iter().map(|duty| (duty.slot, &duty.pubkey));
+		let changed = self.changes.iter().map(|change| (change.slot, &change.pubkey));
+		scheduled.chain(changed).find(|(_, key)| key.eq_ignore_ascii_case(pubkey)).map(|(slot, _)| slot).or_else(|| {
+			let epoch = self.missed_epochs.first()?;
+			self.other_pubkey().eq_ignore_ascii_case(pubkey).then_some(epoch * slots_per_epoch)
+		})
+	}
+}
+
+impl Validate for DutySchedule {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		if let Some(other_pubkey) = &self.other_pubkey {
+			errors.bls_public_key("other_pubkey", other_pubkey);
+		}
+		for (i, duty) in self.slots.iter().enumerate() {
+			errors.bls_public_key(&format!("slots[{}].pubkey", i), &duty.pubkey);
+		}
+		for (i, change) in self.changes.iter().enumerate() {
+			errors.bls_public_key(&format!("changes[{}].pubkey", i), &change.pubkey);
+		}
+	}
+}
+
+/// Faults injected into the beacon API routes, admin routes are never affected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Faults {
+	/// Share of requests, from 0 to 1, answered with `error_status` instead of the route's response
+	error_rate: f64,
+	/// Status of the injected errors
+	error_status: u16,
+	/// Delay added to every request in milliseconds
+	latency_ms: u64,
+	/// Route fragments, e.g. `duties/proposer`, the faults are limited to, every route if empty
+	routes: Vec<String>,
+}
+
+impl Default for Faults {
+	fn default() -> Self {
+		Self { error_rate: 0.0, error_status: 500, latency_ms: 0, routes: Vec::new() }
+	}
+}
+
+impl Faults {
+	fn applies_to(&self, path: &str) -> bool {
+		self.routes.is_empty() || self.routes.iter().any(|route| path.contains(route.as_str()))
+	}
+}
+
+impl Validate for Faults {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.ensure((0.0..=1.0).contains(&self.error_rate), "faults.error_rate", "must be between 0 and 1");
+		errors.ensure(
+			StatusCode::from_u16(self.error_status)
+				.is_ok_and(|status| status.is_client_error() || status.is_server_error()),
+			"faults.error_status",
+			"must be an HTTP error status",
+		);
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct ReorgRequest {
+	/// Slot whose proposer changes
+	slot: u64,
+	/// New proposer of the slot
+	pubkey: String,
+	/// Depth reported in the `chain_reorg` event
+	#[serde(default = "default_reorg_depth")]
+	depth: u64,
+}
+
+fn default_reorg_depth() -> u64 {
+	1
+}
+
+/// Chain the mock simulates, slots progress with the host clock
+struct MockChain {
+	proposer_key: String,
+	genesis_time: u64,
+	seconds_per_slot: u64,
+	slots_per_epoch: u64,
+	validator_status: ValidatorStatus,
+	schedule: RwLock<DutySchedule>,
+	faults: RwLock<Faults>,
+	/// Data of the `chain_reorg` events of admin reorgs
+	reorgs: broadcast::Sender<Value>,
+}
+
+impl MockChain {
+	fn now_ms() -> u64 {
+		SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+	}
+
+	/// The current slot, the head of the mock chain, `0` before genesis
+	fn current_slot(&self) -> u64 {
+		(Self::now_ms() / 1000).saturating_sub(self.genesis_time) / self.seconds_per_slot
+	}
+
+	/// Time until the start of the next slot
+	fn until_next_slot(&self) -> Duration {
+		let next_slot_ms = (self.genesis_time + (self.current_slot() + 1) * self.seconds_per_slot) * 1000;
+		Duration::from_millis(next_slot_ms.saturating_sub(Self::now_ms()))
+	}
+
+	/// Root of the block of a slot, every slot has one
+	fn block_root(slot: u64) -> B256 {
+		keccak256(slot.to_be_bytes())
+	}
+
+	/// Proposer of a slot as currently scheduled
+	fn proposer(&self, slot: u64) -> String {
+		let schedule = self.schedule.read().expect("schedule lock poisoned");
+		schedule.proposer(slot, self.current_slot(), self.slots_per_epoch).unwrap_or(&self.proposer_key).to_string()
+	}
+
+	/// Duties of an epoch, the validator index of a duty is its slot
+	/// The dependent root commits to the proposers so it changes whenever the schedule does
+	fn duties(&self, epoch: u64) -> ProposerDutiesResponse {
+		let start_slot = epoch * self.slots_per_epoch;
+		let duties: Vec<ValidatorDuty> = (start_slot..start_slot + self.slots_per_epoch)
+			.map(|slot| ValidatorDuty {
+				validator_index: slot.to_string(),
+				pubkey: self.proposer(slot),
+				slot: slot.to_string(),
+			})
+			.collect();
+		let proposers = duties.iter().map(|duty| duty.pubkey.as_str()).collect::<Vec<_>>().concat();
+		let dependent_root = keccak256([epoch.to_be_bytes().as_slice(), proposers.as_bytes()].concat());
+
+		ProposerDutiesResponse { execution_optimistic: false, dependent_root, data: duties }
+	}
+
+	/// A validator by index, the proposer of that slot, or by the public key of a scheduled proposer
+	/// Only the proposer key's validator has the configured status, the others are active
+	fn validator(&self, id: &str) -> Option<Value> {
+		let (index, pubkey) = match id.parse::<u64>() {
+			Ok(index) => (index, self.proposer(index)),
+			Err(_) if id.eq_ignore_ascii_case(&self.proposer_key) => (0, self.proposer_key.clone()),
+			Err(_) => {
+				let schedule = self.schedule.read().expect("schedule lock poisoned");
+				(schedule.slot_of(id, self.slots_per_epoch)?, id.to_string())
+			}
+		};
+		let status = if pubkey.eq_ignore_ascii_case(&self.proposer_key) {
+			self.validator_status
+		} else {
+			ValidatorStatus::ActiveOngoing
+		};
+		Some(json!({
+			"index": index.to_string(),
+			"balance": "32000000000",
+			"status": status,
+			"validator": {
+				"pubkey": pubkey,
+				"withdrawal_credentials": B256::ZERO,
+				"effective_balance": "32000000000",
+				"slashed": status.is_slashed(),
+				"activation_eligibility_epoch": "0",
+				"activation_epoch": "0",
+				"exit_epoch": "18446744073709551615",
+				"withdrawable_epoch": "18446744073709551615"
+			}
+		}))
+	}
+}
 
 /// Handler for proposer duties endpoint
 async fn get_proposer_duties_handler(
 	Path(epoch): Path<u64>,
-	axum::extract::State(proposer_key): axum::extract::State<String>,
+	State(chain): State<Arc<MockChain>>,
 ) -> Json<ProposerDutiesResponse> {
-	// Calculate slot range for epoch (32 slots per epoch)
-	let start_slot = epoch_to_first_slot(epoch);
-	let end_slot = epoch_to_last_slot(epoch);
-
-	info!("Getting proposer duties for epoch {} from slot {} to slot {}", epoch, start_slot, end_slot);
-
-	// Generate alternating duties
-	// Even slots: use provided proposer key
-	// Odd slots: use default random key
-	let duties: Vec<ValidatorDuty> = (start_slot..=end_slot)
-		.map(|slot| {
-			// let is_even = slot % 2 == 0;
-			// let pubkey = if is_even {
-			// 	proposer_key.clone()
-			// } else {
-			// 	// Default random but valid BLS public key
-			// 	"0x879d322fb401a2638b6217cab6e9bf954e6df9b18e0c302f3bdc00551a8ac308459d8a79eb54f0f272e6b648ee4d03b3"
-			// 		.to_string()
-			// };
-
-			// ValidatorDuty { validator_index: slot.to_string(), pubkey, slot: slot.to_string() }
-			ValidatorDuty { validator_index: slot.to_string(), pubkey: proposer_key.clone(), slot: slot.to_string() }
-		})
-		.collect();
+	let duties = chain.duties(epoch);
+	info!("Getting proposer duties for epoch {} at dependent root {}", epoch, duties.dependent_root);
+	Json(duties)
+}
+
+async fn get_genesis_handler(State(chain): State<Arc<MockChain>>) -> Json<Value> {
+	Json(json!({
+		"data": {
+			"genesis_time": chain.genesis_time.to_string(),
+			"genesis_validators_root": B256::ZERO,
+			"genesis_fork_version": "0x00000000"
+		}
+	}))
+}
+
+async fn get_spec_handler(State(chain): State<Arc<MockChain>>) -> Json<Value> {
+	Json(json!({
+		"data": {
+			"SECONDS_PER_SLOT": chain.seconds_per_slot.to_string(),
+			"SLOTS_PER_EPOCH": chain.slots_per_epoch.to_string()
+		}
+	}))
+}
+
+async fn get_syncing_handler(State(chain): State<Arc<MockChain>>) -> Json<Value> {
+	Json(json!({
+		"data": {
+			"head_slot": chain.current_slot().to_string(),
+			"sync_distance": "0",
+			"is_syncing": false,
+			"is_optimistic": false,
+			"el_offline": false
+		}
+	}))
+}
+
+async fn get_head_header_handler(State(chain): State<Arc<MockChain>>) -> Json<Value> {
+	let slot = chain.current_slot();
+	Json(json!({
+		"execution_optimistic": false,
+		"finalized": false,
+		"data": {
+			"root": MockChain::block_root(slot),
+			"canonical": true,
+			"header": {
+				"message": {
+					"slot": slot.to_string(),
+					"proposer_index": slot.to_string(),
+					"parent_root": MockChain::block_root(slot.saturating_sub(1)),
+					"state_root": B256::ZERO,
+					"body_root": B256::ZERO
+				},
+				"signature": format!("0x{}", "00".repeat(96))
+			}
+		}
+	}))
+}
+
+async fn get_validator_handler(Path(id): Path<String>, State(chain): State<Arc<MockChain>>) -> Response {
+	match chain.validator(&id) {
+		Some(validator) => {
+			Json(json!({ "execution_optimistic": false, "finalized": false, "data": validator })).into_response()
+		}
+		None => (StatusCode::NOT_FOUND, Json(json!({ "code": 404, "message": "Validator not found" }))).into_response(),
+	}
+}
 
-	Json(ProposerDutiesResponse {
-		execution_optimistic: false,
-		dependent_root: B256::from_slice(&[0; 32]),
-		data: duties,
-	})
+#[derive(Debug, Deserialize)]
+struct ValidatorsQuery {
+	#[serde(default)]
+	id: Option<String>,
+}
+
+/// Validators by comma separated ids, unknown ones are left out
+async fn get_validators_handler(
+	Query(query): Query<ValidatorsQuery>,
+	State(chain): State<Arc<MockChain>>,
+) -> Json<Value> {
+	let ids = query.id.unwrap_or_default();
+	let validators: Vec<Value> = ids.split(',').filter_map(|id| chain.validator(id.trim())).collect();
+	Json(json!({ "execution_optimistic": false, "finalized": false, "data": validators }))
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+	topics: String,
+}
+
+/// `head` events at the start of every slot and `chain_reorg` events of admin reorgs, other topics are accepted but
+/// never sent
+async fn get_events_handler(
+	Query(query): Query<EventsQuery>,
+	State(chain): State<Arc<MockChain>>,
+) -> Sse<impl tokio_stream::Stream<Item = Result<Event, Infallible>>> {
+	let subscribed = |name: &str| query.topics.split(',').any(|topic| topic.trim() == name);
+	let (head, reorg) = (subscribed("head"), subscribed("chain_reorg"));
+	info!("Event subscription to {}", query.topics);
+
+	let reorgs = BroadcastStream::new(chain.reorgs.subscribe())
+		.filter_map(move |data| data.ok().filter(|_| reorg))
+		.map(|data| Ok(Event::default().event("chain_reorg").data(data.to_string())));
+
+	let start = tokio::time::Instant::now() + chain.until_next_slot();
+	let interval = tokio::time::interval_at(start, Duration::from_secs(chain.seconds_per_slot));
+	let heads = IntervalStream::new(interval).filter(move |_| head).map(move |_| {
+		let slot = chain.current_slot();
+		let data = json!({
+			"slot": slot.to_string(),
+			"block": MockChain::block_root(slot),
+			"state": B256::ZERO,
+			"epoch_transition": slot % chain.slots_per_epoch == 0,
+			"previous_duty_dependent_root": B256::ZERO,
+			"current_duty_dependent_root": B256::ZERO,
+			"execution_optimistic": false
+		});
+		Ok(Event::default().event("head").data(data.to_string()))
+	});
+	Sse::new(heads.merge(reorgs)).keep_alive(KeepAlive::default())
+}
+
+/// Delay and fail beacon API requests as the current faults say
+async fn inject_faults(State(chain): State<Arc<MockChain>>, request: Request, next: Next) -> Response {
+	let faults = chain.faults.read().expect("faults lock poisoned").clone();
+	if faults.applies_to(request.uri().path()) {
+		if faults.latency_ms > 0 {
+			tokio::time::sleep(Duration::from_millis(faults.latency_ms)).await;
+		}
+		if faults.error_rate > 0.0 && rand::random::<f64>() < faults.error_rate {
+			warn!("Injecting {} into {}", faults.error_status, request.uri().path());
+			let status = StatusCode::from_u16(faults.error_status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+			return (status, Json(json!({ "code": status.as_u16(), "message": "Injected fault" }))).into_response();
+		}
+	}
+	next.run(request).await
+}
+
+async fn get_schedule_handler(State(chain): State<Arc<MockChain>>) -> Json<DutySchedule> {
+	Json(chain.schedule.read().expect("schedule lock poisoned").clone())
+}
+
+async fn put_schedule_handler(State(chain): State<Arc<MockChain>>, Json(schedule): Json<DutySchedule>) -> Response {
+	if let Err(e) = schedule.check("the schedule") {
+		return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+	}
+	info!("Duty schedule replaced: {:?}", schedule);
+	*chain.schedule.write().expect("schedule lock poisoned") = schedule;
+	StatusCode::NO_CONTENT.into_response()
+}
+
+async fn get_faults_handler(State(chain): State<Arc<MockChain>>) -> Json<Faults> {
+	Json(chain.faults.read().expect("faults lock poisoned").clone())
+}
+
+async fn put_faults_handler(State(chain): State<Arc<MockChain>>, Json(faults): Json<Faults>) -> Response {
+	if let Err(e) = faults.check("the faults") {
+		return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+	}
+	info!("Faults replaced: {:?}", faults);
+	*chain.faults.write().expect("faults lock poisoned") = faults;
+	StatusCode::NO_CONTENT.into_response()
+}
+
+/// Reassign the duty of a slot from the current head, changing its epoch's dependent root, and announce the reorg
+/// to `chain_reorg` subscribers
+async fn post_reorg_handler(State(chain): State<Arc<MockChain>>, Json(reorg): Json<ReorgRequest>) -> Response {
+	let mut errors = ConfigErrors::default();
+	errors.bls_public_key("pubkey", &reorg.pubkey);
+	if let Err(e) = errors.into_result("the reorg") {
+		return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+	}
+
+	let head = chain.current_slot();
+	chain.schedule.write().expect("schedule lock poisoned").changes.push(DutyChange {
+		at_slot: head,
+		slot: reorg.slot,
+		pubkey: reorg.pubkey.clone(),
+	});
+	let epoch = reorg.slot / chain.slots_per_epoch;
+	let dependent_root = chain.duties(epoch).dependent_root;
+	info!("Reorged slot {} to {}, epoch {} dependent root {}", reorg.slot, reorg.pubkey, epoch, dependent_root);
+
+	let old_head_block = MockChain::block_root(head);
+	// No subscribers is not an error, the reorg still shows in the duties
+	let _ = chain.reorgs.send(json!({
+		"slot": head.to_string(),
+		"depth": reorg.depth.to_string(),
+		"old_head_block": old_head_block,
+		"new_head_block": keccak256(old_head_block),
+		"old_head_state": B256::ZERO,
+		"new_head_state": B256::ZERO,
+		"epoch": (head / chain.slots_per_epoch).to_string(),
+		"execution_optimistic": false
+	}));
+
+	Json(json!({ "epoch": epoch.to_string(), "dependent_root": dependent_root })).into_response()
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-	// Read env vars
-	let log_level = std::env::var("RUST_LOG").unwrap_or("info".to_string());
-	let host = std::env::var("BEACON_HOST").expect("BEACON_HOST environment variable not set");
-	let port = std::env::var("BEACON_PORT").expect("BEACON_PORT environment variable not set");
-	let proposer_key = std::env::var("PROPOSER_KEY").expect("PROPOSER_KEY environment variable not set");
-	common::logging::setup_logging(&log_level)?;
+	let args = Args::parse();
+	if let Some(ServiceCommand::Version) = args.command {
+		println!("beacon-mock {}", env!("CARGO_PKG_VERSION"));
+		return Ok(());
+	}
+
+	let config = BeaconMockConfig::load(&args.service)?;
+	if let Some(ServiceCommand::Config(ConfigCommand::Validate)) = args.command {
+		println!("Beacon mock config is valid");
+		return Ok(());
+	}
+
+	let _logging = common::logging::setup_logging_with_filter(&args.service.log_filter(), &LoggingConfig::default())?;
 
-	let bind_addr = format!("{}:{}", host, port);
+	let bind_addr = format!("{}:{}", config.host, config.port);
+	let schedule = match &config.schedule_file {
+		Some(path) => DutySchedule::load(path)?,
+		None => DutySchedule::default(),
+	};
+	let chain = Arc::new(MockChain {
+		proposer_key: config.proposer_key,
+		genesis_time: config.genesis_time.unwrap_or(MockChain::now_ms() / 1000),
+		seconds_per_slot: config.seconds_per_slot,
+		slots_per_epoch: config.slots_per_epoch,
+		validator_status: config.validator_status,
+		schedule: RwLock::new(schedule),
+		faults: RwLock::new(config.faults),
+		reorgs: broadcast::channel(16).0,
+	});
 
 	info!("Mock Beacon Node Server");
 	info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
 	info!("Listening on: {}", bind_addr);
-	info!("Proposer key: {}", proposer_key);
+	info!("Proposer key: {}", chain.proposer_key);
+	info!(
+		"Genesis: {}, {}s slots, {} slots per epoch, current slot {}",
+		chain.genesis_time,
+		chain.seconds_per_slot,
+		chain.slots_per_epoch,
+		chain.current_slot()
+	);
 	info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-	info!("Endpoint: GET /eth/v1/validator/duties/proposer/{{epoch}}");
-	info!("Pattern: Even slots = proposer key, Odd slots = random key 0x87d322...");
-
-	// Build router with proposer key as shared state
-	let app = Router::new()
-		.route(
-			format!("/{}/{{epoch}}", PROPOSER_DUTIES_ROUTE).as_str(),
-			// PROPOSER_DUTIES_ROUTE,
-			get(get_proposer_duties_handler),
-		)
-		.with_state(proposer_key);
+	info!("Endpoint: GET /{}/{{epoch}}", PROPOSER_DUTIES_ROUTE);
+	info!("Endpoint: GET /{}", GENESIS_ROUTE);
+	info!("Endpoint: GET /{}", SPEC_ROUTE);
+	info!("Endpoint: GET /{}", NODE_SYNCING_ROUTE);
+	info!("Endpoint: GET /{}", HEAD_HEADER_ROUTE);
+	info!("Endpoint: GET /{}/{{id}}", VALIDATOR_STATUS_ROUTE);
+	info!("Endpoint: GET /{}?topics=head,chain_reorg", EVENTS_ROUTE);
+	info!("Admin: GET, PUT /{}", ADMIN_SCHEDULE_ROUTE);
+	info!("Admin: GET, PUT /{}", ADMIN_FAULTS_ROUTE);
+	info!("Admin: POST /{}", ADMIN_REORG_ROUTE);
+
+	let app = router(chain);
 
 	// Bind to the specified address
 	let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
 
+	if args.service.dry_run {
+		info!("Dry run: listening address is free, exiting without serving");
+		return Ok(());
+	}
+
 	info!("Mock Beacon Node server ready");
 
 	// Start server
@@ -81,3 +634,261 @@ async fn main() -> Result<()> {
 
 	Ok(())
 }
+
+/// Beacon API and admin routes of the mock chain
+fn router(chain: Arc<MockChain>) -> Router {
+	// Admin routes are kept out of the fault injection so faults can always be cleared
+	let admin = Router::new()
+		.route(format!("/{}", ADMIN_SCHEDULE_ROUTE).as_str(), get(get_schedule_handler).put(put_schedule_handler))
+		.route(format!("/{}", ADMIN_FAULTS_ROUTE).as_str(), get(get_faults_handler).put(put_faults_handler))
+		.route(format!("/{}", ADMIN_REORG_ROUTE).as_str(), post(post_reorg_handler));
+
+	// Build router with the mock chain as shared state
+	Router::new()
+		.route(format!("/{}/{{epoch}}", PROPOSER_DUTIES_ROUTE).as_str(), get(get_proposer_duties_handler))
+		.route(format!("/{}", GENESIS_ROUTE).as_str(), get(get_genesis_handler))
+		.route(format!("/{}", SPEC_ROUTE).as_str(), get(get_spec_handler))
+		.route(format!("/{}", NODE_SYNCING_ROUTE).as_str(), get(get_syncing_handler))
+		.route(format!("/{}", HEAD_HEADER_ROUTE).as_str(), get(get_head_header_handler))
+		.route(format!("/{}", VALIDATOR_STATUS_ROUTE).as_str(), get(get_validators_handler))
+		.route(format!("/{}/{{id}}", VALIDATOR_STATUS_ROUTE).as_str(), get(get_validator_handler))
+		.route(format!("/{}", EVENTS_ROUTE).as_str(), get(get_events_handler))
+		.route_layer(middleware::from_fn_with_state(chain.clone(), inject_faults))
+		.merge(admin)
+		.with_state(chain)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PROPOSER_KEY: &str =
+		"0xaf6e96c0eccd8d4ae868be9299af737855a1b08d57bccb565ea7e69311a30baeebe08d493c3fea97077e8337e95ac5a6";
+	const KEY_A: &str =
+		"0xb3a22e4a673ac7a153ab5b3c17a4dbef55f7e47210b20c0cbb0e66df5b36bb49ef808577610b034172e955d2312a61b9";
+	const KEY_B: &str =
+		"0x883827193f7627cd04e621e1e8d56498362a52b2a30c9a1c72036eb935c4278dee23d38a24d2f7dda62689886f0c39f4";
+
+	/// A mock chain at slot 100
+	fn chain(seconds_per_slot: u64, schedule: DutySchedule) -> Arc<MockChain> {
+		Arc::new(MockChain {
+			proposer_key: PROPOSER_KEY.to_string(),
+			genesis_time: MockChain::now_ms() / 1000 - 100 * seconds_per_slot,
+			seconds_per_slot,
+			slots_per_epoch: 32,
+			validator_status: ValidatorStatus::ActiveSlashed,
+			schedule: RwLock::new(schedule),
+			faults: RwLock::new(Faults::default()),
+			reorgs: broadcast::channel(16).0,
+		})
+	}
+
+	/// Serve the mock chain on a free local port, returning its base URL
+	async fn serve(chain: Arc<MockChain>) -> String {
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		tokio::spawn(async move { axum::serve(listener, router(chain)).await });
+		format!("http://{addr}")
+	}
+
+	async fn get_json(url: String) -> Value {
+		let response = reqwest::get(url).await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		response.json().await.unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_serves_genesis_spec_and_head() {
+		let chain = chain(12, DutySchedule::default());
+		let url = serve(chain.clone()).await;
+
+		let genesis = get_json(format!("{url}/{GENESIS_ROUTE}")).await;
+		assert_eq!(genesis["data"]["genesis_time"], chain.genesis_time.to_string());
+
+		let spec = get_json(format!("{url}/{SPEC_ROUTE}")).await;
+		assert_eq!(spec["data"]["SECONDS_PER_SLOT"], "12");
+		assert_eq!(spec["data"]["SLOTS_PER_EPOCH"], "32");
+
+		let syncing = get_json(format!("{url}/{NODE_SYNCING_ROUTE}")).await;
+		assert_eq!(syncing["data"]["is_syncing"], false);
+
+		let header = get_json(format!("{url}/{HEAD_HEADER_ROUTE}")).await;
+		let message = &header["data"]["header"]["message"];
+		let slot: u64 = message["slot"].as_str().unwrap().parse().unwrap();
+		assert!(slot >= 100);
+		assert_eq!(header["data"]["root"], json!(MockChain::block_root(slot)));
+		assert_eq!(message["parent_root"], json!(MockChain::block_root(slot - 1)));
+	}
+
+	#[tokio::test]
+	async fn test_serves_validators() {
+		let url = serve(chain(12, DutySchedule::default())).await;
+
+		// The proposer key's validator has the configured status, by public key or by the index of a slot it proposes
+		let validator = get_json(format!("{url}/{VALIDATOR_STATUS_ROUTE}/{PROPOSER_KEY}")).await;
+		assert_eq!(validator["data"]["validator"]["pubkey"], PROPOSER_KEY);
+		assert_eq!(validator["data"]["status"], json!(ValidatorStatus::ActiveSlashed));
+		assert_eq!(validator["data"]["validator"]["slashed"], true);
+		let validator = get_json(format!("{url}/{VALIDATOR_STATUS_ROUTE}/7")).await;
+		assert_eq!(validator["data"]["index"], "7");
+		assert_eq!(validator["data"]["validator"]["pubkey"], PROPOSER_KEY);
+
+		let response = reqwest::get(format!("{url}/{VALIDATOR_STATUS_ROUTE}/{DEFAULT_OTHER_PUBKEY}")).await.unwrap();
+		assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+		// Unknown ids are left out of the batch
+		let validators =
+			get_json(format!("{url}/{VALIDATOR_STATUS_ROUTE}?id=3,{DEFAULT_OTHER_PUBKEY},{PROPOSER_KEY}")).await;
+		let indices: Vec<&str> = validators["data"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|validator| validator["index"].as_str().unwrap())
+			.collect();
+		assert_eq!(indices, ["3", "0"]);
+	}
+
+	#[tokio::test]
+	async fn test_events_announce_each_slot_head() {
+		let chain = chain(1, DutySchedule::default());
+		let url = serve(chain.clone()).await;
+
+		let mut events = reqwest::get(format!("{url}/{EVENTS_ROUTE}?topics=head")).await.unwrap();
+		assert_eq!(events.status(), StatusCode::OK);
+		let mut heads = Vec::new();
+		while heads.len() < 2 {
+			let chunk = tokio::time::timeout(Duration::from_secs(5), events.chunk()).await.unwrap().unwrap().unwrap();
+			let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+			if chunk.contains("event: head")
+				&& let Some(data) = chunk.split("data: ").nth(1)
+			{
+				let head: Value = serde_json::from_str(data.trim()).unwrap();
+				let slot: u64 = head["slot"].as_str().unwrap().parse().unwrap();
+				assert_eq!(head["block"], json!(MockChain::block_root(slot)));
+				heads.push(slot);
+			}
+		}
+		assert!(heads[0] >= 100);
+		assert!(heads[1] > heads[0]);
+	}
+
+	#[test]
+	fn test_schedule_overrides_the_proposer() {
+		let schedule = DutySchedule {
+			missed_epochs: vec![2],
+			other_pubkey: None,
+			slots: vec![
+				SlotDuty { slot: 5, pubkey: KEY_A.to_string() },
+				SlotDuty { slot: 5, pubkey: KEY_B.to_string() },
+			],
+			changes: vec![DutyChange { at_slot: 10, slot: 70, pubkey: KEY_A.to_string() }],
+		};
+		assert_eq!(schedule.proposer(3, 0, 32), None);
+		assert_eq!(schedule.proposer(5, 0, 32), Some(KEY_B));
+		assert_eq!(schedule.proposer(64, 0, 32), Some(DEFAULT_OTHER_PUBKEY));
+
+		// A change shows from its head slot on
+		assert_eq!(schedule.proposer(70, 9, 32), Some(DEFAULT_OTHER_PUBKEY));
+		assert_eq!(schedule.proposer(70, 10, 32), Some(KEY_A));
+
+		assert_eq!(schedule.slot_of(KEY_B, 32), Some(5));
+		assert_eq!(schedule.slot_of(DEFAULT_OTHER_PUBKEY, 32), Some(64));
+		assert_eq!(schedule.slot_of(PROPOSER_KEY, 32), None);
+	}
+
+	#[test]
+	fn test_schedule_loads_from_toml_and_json() {
+		let dir = tempfile::tempdir().unwrap();
+		let toml_path = dir.path().join("schedule.toml");
+		let schedule = format!(
+			r#"
+missed_epochs = [1]
+
+[[slots]]
+slot = 5
+pubkey = "{KEY_A}"
+
+[[changes]]
+at_slot = 2
+slot = 6
+pubkey = "{KEY_B}"
+"#
+		);
+		std::fs::write(&toml_path, schedule).unwrap();
+		let schedule = DutySchedule::load(&toml_path).unwrap();
+		assert_eq!(schedule.proposer(5, 0, 32), Some(KEY_A));
+		assert_eq!(schedule.proposer(6, 2, 32), Some(KEY_B));
+		assert_eq!(schedule.proposer(40, 0, 32), Some(DEFAULT_OTHER_PUBKEY));
+
+		let json_path = dir.path().join("schedule.json");
+		std::fs::write(&json_path, json!({ "other_pubkey": KEY_B, "missed_epochs": [0] }).to_string()).unwrap();
+		assert_eq!(DutySchedule::load(&json_path).unwrap().proposer(1, 0, 32), Some(KEY_B));
+
+		std::fs::write(&json_path, json!({ "slots": [{ "slot": 1, "pubkey": "0x1234" }] }).to_string()).unwrap();
+		let error = DutySchedule::load(&json_path).unwrap_err().to_string();
+		assert!(error.contains("slots[0].pubkey"), "{}", error);
+	}
+
+	#[tokio::test]
+	async fn test_faults_are_injected_and_cleared_through_admin() {
+		let url = serve(chain(12, DutySchedule::default())).await;
+		let client = reqwest::Client::new();
+		let put_faults = |faults: Value| client.put(format!("{url}/{ADMIN_FAULTS_ROUTE}")).json(&faults).send();
+		let status = |route: &str| client.get(format!("{url}/{route}")).send();
+
+		let faults = json!({ "error_rate": 1.0, "error_status": 503, "routes": ["duties/proposer"] });
+		assert_eq!(put_faults(faults).await.unwrap().status(), StatusCode::NO_CONTENT);
+		let duties = format!("{PROPOSER_DUTIES_ROUTE}/3");
+		assert_eq!(status(&duties).await.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+		assert_eq!(status(GENESIS_ROUTE).await.unwrap().status(), StatusCode::OK);
+
+		// Faults of every route leave the admin routes alone
+		assert_eq!(put_faults(json!({ "error_rate": 1.0 })).await.unwrap().status(), StatusCode::NO_CONTENT);
+		assert_eq!(status(GENESIS_ROUTE).await.unwrap().status(), StatusCode::INTERNAL_SERVER_ERROR);
+		assert_eq!(status(ADMIN_SCHEDULE_ROUTE).await.unwrap().status(), StatusCode::OK);
+		let faults: Value = status(ADMIN_FAULTS_ROUTE).await.unwrap().json().await.unwrap();
+		assert_eq!(faults["error_status"], 500);
+
+		assert_eq!(put_faults(json!({ "error_rate": 2.0 })).await.unwrap().status(), StatusCode::BAD_REQUEST);
+		assert_eq!(put_faults(json!({ "error_status": 200 })).await.unwrap().status(), StatusCode::BAD_REQUEST);
+		assert_eq!(put_faults(json!({})).await.unwrap().status(), StatusCode::NO_CONTENT);
+		assert_eq!(status(&duties).await.unwrap().status(), StatusCode::OK);
+	}
+
+	#[tokio::test]
+	async fn test_reorg_changes_the_duties_and_dependent_root() {
+		let url = serve(chain(12, DutySchedule::default())).await;
+		let client = reqwest::Client::new();
+		let url = &url;
+		let duties = |epoch: u64| async move {
+			serde_json::from_value::<ProposerDutiesResponse>(
+				get_json(format!("{url}/{PROPOSER_DUTIES_ROUTE}/{epoch}")).await,
+			)
+			.unwrap()
+		};
+		let mut events = reqwest::get(format!("{url}/{EVENTS_ROUTE}?topics=chain_reorg")).await.unwrap();
+
+		let before = duties(4).await;
+		let next_epoch_root = duties(5).await.dependent_root;
+		assert!(before.data.iter().all(|duty| duty.pubkey == PROPOSER_KEY));
+
+		let reorg = json!({ "slot": 130, "pubkey": KEY_A, "depth": 2 });
+		let response = client.post(format!("{url}/{ADMIN_REORG_ROUTE}")).json(&reorg).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		let reorged: Value = response.json().await.unwrap();
+		let after = duties(4).await;
+		assert_eq!(after.data[2].pubkey, KEY_A);
+		assert_ne!(after.dependent_root, before.dependent_root);
+		assert_eq!(reorged["dependent_root"], json!(after.dependent_root));
+		assert_eq!(duties(5).await.dependent_root, next_epoch_root);
+
+		let chunk = tokio::time::timeout(Duration::from_secs(5), events.chunk()).await.unwrap().unwrap().unwrap();
+		let chunk = String::from_utf8(chunk.to_vec()).unwrap();
+		assert!(chunk.contains("event: chain_reorg"), "{}", chunk);
+		assert!(chunk.contains(r#""depth":"2""#), "{}", chunk);
+
+		let invalid = json!({ "slot": 130, "pubkey": "0x1234" });
+		let response = client.post(format!("{url}/{ADMIN_REORG_ROUTE}")).json(&invalid).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	}
+}
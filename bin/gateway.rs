@@ -1,52 +1,211 @@
+use clap::Parser;
 use commit_boost::prelude::load_commit_module_config;
 use commitments::server::run_commitments_rpc_server;
 use common::storage::create_database;
-use eyre::Result;
-use inclusion::gateway::config::GatewayConfig;
+use config::cli::{ConfigCommand, MetricsArgs, ServiceArgs, ServiceCommand};
+use eyre::{Result, WrapErr};
+use inclusion::gateway::config::{GATEWAY_ENV_PREFIX, GatewayConfig};
 use inclusion::gateway::services::{
-	constraint_manager::ConstraintManager, delegation_manager::DelegationManager, rpc::GatewayRpc,
+	admin::build_admin_router, config_watcher::ConfigWatcher, constraint_manager::ConstraintManager,
+	delegation_manager::DelegationManager, fallback_builder::FallbackBuilder, inclusion_monitor::InclusionMonitor,
+	mempool_ingestor::MempoolIngestor, rpc::GatewayRpc,
 };
 use inclusion::gateway::state::GatewayState;
+use inclusion::gateway::utils::check_urc_registration;
+use signing::kms::KmsSigner;
+use signing::signer::CommitterSigner;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::{error, info};
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
-fn setup_state() -> Result<GatewayState> {
+/// Gateway service: commitments server and gateway tasks
+#[derive(Debug, Parser)]
+struct Args {
+	/// Start even if the gateway key is not registered and opted in on the URC
+	#[arg(long)]
+	allow_unregistered: bool,
+
+	#[command(flatten)]
+	service: ServiceArgs,
+
+	#[command(flatten)]
+	metrics: MetricsArgs,
+
+	#[command(subcommand)]
+	command: Option<ServiceCommand>,
+}
+
+/// Config of the module CB_MODULE_ID in the Commit-Boost config at `path`, overridden from the environment and
+/// validated
+fn load_config(path: &Path) -> Result<GatewayConfig> {
+	let module_id = std::env::var("CB_MODULE_ID").wrap_err("CB_MODULE_ID environment variable not set")?;
+	config::load_module(path, &module_id, GATEWAY_ENV_PREFIX)
+}
+
+async fn setup_state(config: GatewayConfig) -> Result<(GatewayState, GatewayConfig)> {
 	// Load gateway configuration using commit-boost's config loader
-	let commit_config = load_commit_module_config::<GatewayConfig>()
+	let mut commit_config = load_commit_module_config::<GatewayConfig>()
 		.map_err(|e| eyre::eyre!("Failed to load commit module config: {}", e))?;
+	commit_config.extra = config;
 
 	let config = commit_config.extra.clone();
 
 	// Initialize database
 	let db = create_database(config.db_path.as_str()).map_err(|e| eyre::eyre!("Failed to create database: {}", e))?;
 
-	Ok(GatewayState::new(db, commit_config))
+	let chain = commit_config.chain;
+	let mut state = GatewayState::new(db, commit_config).wrap_err("Failed to set up the gateway state")?;
+
+	// Slot timing of the chain comes from the beacon node, if one is configured
+	if let Err(e) = state.slot_clock.sync().await {
+		warn!(
+			"Failed to sync the slot clock with the beacon node, using the chain's genesis and mainnet timing: {}",
+			e
+		);
+	}
+
+	// The committer key stays in KMS, only signing requests leave the host
+	if let Some(kms) = &config.committer_kms {
+		let committer = KmsSigner::connect(kms, chain).await.wrap_err("Failed to connect to the committer KMS key")?;
+		if let Some(committer_address) = config.committer_address
+			&& committer.address() != committer_address
+		{
+			return Err(eyre::eyre!(
+				"KMS key {} is for {}, not the configured committer address {}",
+				kms.key_id,
+				committer.address(),
+				committer_address
+			));
+		}
+		state.signer_client = state.signer_client.with_committer(Arc::new(committer));
+	}
+
+	Ok((state, config))
+}
+
+fn main() -> Result<()> {
+	let args = Args::parse();
+	if let Some(ServiceCommand::Version) = args.command {
+		println!("gateway {}", env!("CARGO_PKG_VERSION"));
+		return Ok(());
+	}
+
+	// Commit-Boost's config loader reads the config from CB_CONFIG, exported before the runtime starts any thread
+	// SAFETY: the process is still single threaded
+	let config_path = unsafe { args.service.export_config_path("CB_CONFIG")? };
+	if let Some(ServiceCommand::Config(ConfigCommand::Validate)) = args.command {
+		load_config(&config_path)?;
+		println!("Gateway config {} is valid", config_path.display());
+		return Ok(());
+	}
+
+	tokio::runtime::Builder::new_multi_thread().enable_all().build()?.block_on(run(args, config_path))
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-	// Setup logging
-	common::logging::setup_logging(&std::env::var("RUST_LOG").expect("RUST_LOG environment variable not set"))?;
+async fn run(args: Args, config_path: PathBuf) -> Result<()> {
+	// Setup logging, as configured in the module config
+	let config = load_config(&config_path)?;
+	let _logging = common::logging::setup_logging_with_filter(&args.service.log_filter(), &config.logging)?;
 
 	info!("Starting gateway service (commitments server + gateway tasks)");
 
 	// Setup state
-	let state = Arc::new(setup_state()?);
+	let (mut state, config) = setup_state(config).await?;
+	if let Some(metrics_addr) = args.metrics.metrics_addr {
+		state.metrics_url = format!("http://{}", metrics_addr).parse().wrap_err("Invalid --metrics-addr")?;
+	}
+	let state = Arc::new(state);
+
+	// Verify the gateway can be held to its commitments before accepting any
+	if let Some(urc) = &config.urc {
+		match check_urc_registration(urc, &state.gateway_public_keys, config.committer_address).await {
+			Ok(registrations) => {
+				for (public_key, registration) in state.gateway_public_keys.iter().zip(registrations) {
+					info!(
+						"Gateway key {} is registered in the URC, registration root {}",
+						public_key, registration.registration_root
+					)
+				}
+			}
+			Err(e) if args.allow_unregistered || config.allow_unregistered => {
+				warn!("==========================================================================");
+				warn!("URC registration check failed: {}", e);
+				warn!("Starting anyway, commitments of this gateway cannot be enforced by slashing");
+				warn!("==========================================================================");
+			}
+			Err(e) => {
+				return Err(e).wrap_err("URC registration check failed, pass --allow-unregistered to start anyway");
+			}
+		}
+	}
 
 	// Create tasks
 	let rpc_server = GatewayRpc::new(Arc::clone(&state));
 	let delegation_manager = DelegationManager::new(Arc::clone(&state));
-	let constraint_manager = ConstraintManager::new(Arc::clone(&state));
+	let constraint_manager = Arc::new(ConstraintManager::new(Arc::clone(&state)));
+	let inclusion_monitor = InclusionMonitor::new(Arc::clone(&state));
+	let fallback_builder = match &config.fallback_builder {
+		Some(fallback_config) => Some(
+			FallbackBuilder::new(Arc::clone(&state), fallback_config)
+				.wrap_err("Failed to set up the fallback builder")?,
+		),
+		None => None,
+	};
+	let mempool_ingestor =
+		config.mempool_ingestion.clone().map(|mempool_config| MempoolIngestor::new(Arc::clone(&state), mempool_config));
 
-	// Spawn RPC server
-	let rpc_handle = tokio::spawn(async move {
-		if let Err(e) = run_commitments_rpc_server(rpc_server).await {
-			error!("Commitments RPC server exited with error: {e:?}");
-		} else {
-			info!("Commitments RPC server stopped");
+	// Reload settings from the same Commit-Boost config the module was started with
+	let module_id = std::env::var("CB_MODULE_ID").wrap_err("CB_MODULE_ID environment variable not set")?;
+	let config_watcher = ConfigWatcher::new(Arc::clone(&state), config_path.clone(), module_id, config.clone());
+
+	// Build the admin router if enabled
+	let admin = match state.admin_port {
+		Some(admin_port) => {
+			let api_key = state
+				.admin_api_key
+				.clone()
+				.ok_or_else(|| eyre::eyre!("admin_api_key must be set when admin_port is set"))?;
+			Some((
+				format!("{}:{}", config.rpc_host, admin_port),
+				build_admin_router(Arc::clone(&state), Arc::clone(&constraint_manager), api_key),
+			))
 		}
+		None => None,
+	};
+
+	if args.service.dry_run {
+		info!("Dry run: gateway config and state are set up, exiting without starting any task");
+		return Ok(());
+	}
+
+	// Bind the RPC, metrics and admin servers first, so a port already in use fails the startup before any task runs
+	let shutdown = CancellationToken::new();
+	let rpc_server = run_commitments_rpc_server(rpc_server, shutdown.clone()).await?;
+	let admin = match admin {
+		Some((admin_url, admin_router)) => {
+			info!("Starting gateway admin server on {}", admin_url);
+			Some((TcpListener::bind(admin_url).await?, admin_router))
+		}
+		None => None,
+	};
+
+	// Spawn leader election, commitments are refused until this instance is elected
+	let leader_handle = state.leader_election.clone().map(|leader_election| {
+		tokio::spawn(async move {
+			if let Err(e) = leader_election.run().await {
+				error!("Leader election exited with error: {e:?}");
+			} else {
+				info!("Leader election stopped");
+			}
+		})
 	});
 
+	// Spawn slot clock
+	let slot_clock = Arc::clone(&state.slot_clock);
+	let slot_clock_handle = tokio::spawn(async move { slot_clock.run().await });
+
 	// Spawn delegation task
 	let delegation_handle = tokio::spawn(async move {
 		if let Err(e) = delegation_manager.run().await {
@@ -57,22 +216,101 @@ async fn main() -> Result<()> {
 	});
 
 	// Spawn constraints task
+	let constraints_task = Arc::clone(&constraint_manager);
 	let constraints_handle = tokio::spawn(async move {
-		if let Err(e) = constraint_manager.run().await {
+		if let Err(e) = constraints_task.run().await {
 			error!("Constraints task exited with error: {e:?}");
 		} else {
 			info!("Constraints task stopped");
 		}
 	});
 
+	// Spawn inclusion monitor task
+	let monitor_handle = tokio::spawn(async move {
+		if let Err(e) = inclusion_monitor.run().await {
+			error!("Inclusion monitor exited with error: {e:?}");
+		} else {
+			info!("Inclusion monitor stopped");
+		}
+	});
+
+	// Spawn config watcher task
+	let config_watcher_handle = tokio::spawn(async move {
+		if let Err(e) = config_watcher.run().await {
+			error!("Config watcher exited with error: {e:?}");
+		} else {
+			info!("Config watcher stopped");
+		}
+	});
+
+	// Spawn fallback builder task, if enabled
+	let fallback_handle = fallback_builder.map(|fallback_builder| {
+		tokio::spawn(async move {
+			if let Err(e) = fallback_builder.run().await {
+				error!("Fallback builder exited with error: {e:?}");
+			} else {
+				info!("Fallback builder stopped");
+			}
+		})
+	});
+
+	// Spawn mempool ingestion task, if enabled
+	let mempool_handle = mempool_ingestor.map(|mempool_ingestor| {
+		tokio::spawn(async move {
+			if let Err(e) = mempool_ingestor.run().await {
+				error!("Mempool ingestion exited with error: {e:?}");
+			} else {
+				info!("Mempool ingestion stopped");
+			}
+		})
+	});
+
+	// Run admin server if enabled
+	let admin_handle = match admin {
+		Some((admin_listener, admin_router)) => Some(tokio::spawn(async move {
+			if let Err(e) = axum::serve(admin_listener, admin_router).await {
+				error!("Gateway admin server exited with error: {e:?}");
+			} else {
+				info!("Gateway admin server stopped");
+			}
+		})),
+		None => None,
+	};
+
 	// Wait for Docker shutdown signals (SIGINT/SIGTERM)
 	common::utils::wait_for_signal().await?;
 	info!("Shutdown signal received, stopping tasks");
 
+	// Hand the leadership over before stopping, instead of making the standby wait for the lease to expire
+	if let Some(leader_handle) = leader_handle {
+		leader_handle.abort();
+	}
+	if let Some(leader_election) = &state.leader_election
+		&& let Err(e) = leader_election.resign().await
+	{
+		warn!("Failed to resign the leadership: {}", e);
+	}
+
+	// Stop the RPC server, letting in-flight requests finish
+	shutdown.cancel();
+	rpc_server.stopped().await;
+	info!("Commitments RPC server stopped");
+
 	// Kill tasks
-	rpc_handle.abort();
+	slot_clock_handle.abort();
 	delegation_handle.abort();
 	constraints_handle.abort();
+	monitor_handle.abort();
+	config_watcher_handle.abort();
+	if let Some(fallback_handle) = fallback_handle {
+		fallback_handle.abort();
+	}
+	if let Some(mempool_handle) = mempool_handle {
+		mempool_handle.abort();
+	}
+	if let Some(admin_handle) = admin_handle {
+		admin_handle.abort();
+	}
 
 	Ok(())
 }
@@ -1,18 +1,48 @@
-use eyre::Result;
+use clap::Parser;
+use eyre::{Result, WrapErr};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::{error, info};
+use tokio::net::TcpListener;
+use tracing::{error, info, warn};
 
 use commit_boost::prelude::load_commit_module_config;
 
+use axum::routing::get;
+use common::health::health_router;
+use common::metrics::fabric_metrics_handler;
 use common::storage::create_database;
+use config::cli::{ConfigCommand, ServiceArgs, ServiceCommand};
 use constraints::client::ConstraintsClient;
-use lookahead::utils::current_slot;
-use proposer::{config::ProposerConfig, delegation_manager::DelegationManager, state::ProposerState};
+use proposer::{
+	auditor::ConstraintAuditor,
+	config::{PROPOSER_ENV_PREFIX, ProposerConfig},
+	delegation_manager::DelegationManager,
+	reconciler::DelegationReconciler,
+	state::ProposerState,
+};
+
+/// Proposer service: delegations to gateways and constraint audits
+#[derive(Debug, Parser)]
+struct Args {
+	#[command(flatten)]
+	service: ServiceArgs,
+
+	#[command(subcommand)]
+	command: Option<ServiceCommand>,
+}
+
+/// Config of the module CB_MODULE_ID in the Commit-Boost config at `path`, overridden from the environment and
+/// validated
+fn load_config(path: &Path) -> Result<ProposerConfig> {
+	let module_id = std::env::var("CB_MODULE_ID").wrap_err("CB_MODULE_ID environment variable not set")?;
+	config::load_module(path, &module_id, PROPOSER_ENV_PREFIX)
+}
 
-async fn setup_state() -> Result<ProposerState> {
+async fn setup_state(config: ProposerConfig) -> Result<ProposerState> {
 	// Load configuration using commit-boost's config loader
-	let commit_config = load_commit_module_config::<ProposerConfig>()
+	let mut commit_config = load_commit_module_config::<ProposerConfig>()
 		.map_err(|e| eyre::eyre!("Failed to load commit module config: {}", e))?;
+	commit_config.extra = config;
 
 	info!("Loaded config");
 
@@ -22,40 +52,134 @@ async fn setup_state() -> Result<ProposerState> {
 	let db = create_database(config.db_path.as_str()).map_err(|e| eyre::eyre!("Failed to create database: {}", e))?;
 
 	// Initialize state
-	let state = ProposerState::new(db, commit_config);
+	let state = ProposerState::new(db, commit_config).wrap_err("Failed to set up the proposer state")?;
+
+	// Slot timing of the chain comes from the beacon node
+	if let Err(e) = state.slot_clock.sync().await {
+		warn!(
+			"Failed to sync the slot clock with the beacon node, using the chain's genesis and mainnet timing: {}",
+			e
+		);
+	}
 
 	info!("Proposer configuration:");
-	info!("  Gateway BLS key: {}", state.gateway_public_key);
-	info!("  Gateway committer address (ECDSA): {}", state.gateway_address);
-	info!("  Constraints Server URL: {}", state.constraints_client.base_url);
+	for gateway in state.delegation_policy.gateways() {
+		info!(
+			"  Gateway {}: BLS key {}, committer address (ECDSA) {}",
+			gateway.name, gateway.public_key, gateway.address
+		);
+	}
+	for client in &state.constraints_clients {
+		info!("  Constraints Server URL: {}", client.base_url);
+	}
 	info!("  Beacon API URL: {}:{}", config.beacon_api_host, config.beacon_api_port);
 	info!("  Module signing ID: {}", config.module_signing_id);
 	info!("  Chain: {}", state.chain);
 	info!("  Delegation pollling interval: {} seconds", config.lookahead_check_interval_seconds);
 
-	// Test constraints server health
-	match state.constraints_client.health_check().await {
-		Ok(true) => info!("Relay health check passed"),
-		_ => return Err(eyre::eyre!("Relay health check failed")),
+	// Test constraints server health, delegations can still reach a quorum while some relays are down
+	let mut healthy = 0;
+	for client in &state.constraints_clients {
+		match client.health_check().await {
+			Ok(true) => {
+				info!("Relay {} health check passed", client.base_url);
+				healthy += 1;
+			}
+			_ => warn!("Relay {} health check failed", client.base_url),
+		}
+	}
+	if healthy < state.delegation_quorum {
+		return Err(eyre::eyre!(
+			"{} relay(s) passed the health check, below the delegation quorum of {}",
+			healthy,
+			state.delegation_quorum
+		));
 	}
 
 	Ok(state)
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-	// Setup logging
-	common::logging::setup_logging(&std::env::var("RUST_LOG").expect("RUST_LOG environment variable not set"))?;
+fn main() -> Result<()> {
+	let args = Args::parse();
+	if let Some(ServiceCommand::Version) = args.command {
+		println!("proposer {}", env!("CARGO_PKG_VERSION"));
+		return Ok(());
+	}
+
+	// Commit-Boost's config loader reads the config from CB_CONFIG, exported before the runtime starts any thread
+	// SAFETY: the process is still single threaded
+	let config_path = unsafe { args.service.export_config_path("CB_CONFIG")? };
+	if let Some(ServiceCommand::Config(ConfigCommand::Validate)) = args.command {
+		load_config(&config_path)?;
+		println!("Proposer config {} is valid", config_path.display());
+		return Ok(());
+	}
 
-	// Setup state
-	let state = setup_state().await?;
+	tokio::runtime::Builder::new_multi_thread().enable_all().build()?.block_on(run(args, config_path))
+}
+
+async fn run(args: Args, config_path: PathBuf) -> Result<()> {
+	// Setup logging, as configured in the module config
+	let config = load_config(&config_path)?;
+	let _logging = common::logging::setup_logging_with_filter(&args.service.log_filter(), &config.logging)?;
+
+	// Setup state, which checks the relays are reachable
+	let health_addr = config.health_port.map(|port| (config.health_host.clone(), port));
+	let state = setup_state(config).await?;
+	if args.service.dry_run {
+		info!("Dry run: proposer config and state are set up, exiting without starting any task");
+		return Ok(());
+	}
 
 	// Clone before move
-	let chain = state.chain.clone();
+	let slot_clock = Arc::clone(&state.slot_clock);
+	let beacon_client = state.beacon_client.clone();
+	let lookahead_cache = Arc::clone(&state.lookahead_cache);
 	let lookahead_check_interval_seconds = state.lookahead_check_interval_seconds;
 
 	// Launch delegation manager
-	let delegation_manager = DelegationManager::new(Arc::new(state));
+	let state = Arc::new(state);
+
+	// Serve liveness and readiness for orchestrators
+	if let Some((host, port)) = health_addr {
+		let listener = TcpListener::bind((host.as_str(), port)).await?;
+		// The proposer has no metrics server of its own, its fabric metrics are served next to the health checks
+		let router = health_router(Arc::clone(&state)).route("/metrics", get(fabric_metrics_handler));
+		info!("Health server listening on {}:{}", host, port);
+		tokio::spawn(async move {
+			if let Err(e) = axum::serve(listener, router).await {
+				error!("Health server error: {}", e);
+			}
+		});
+	}
+
+	let delegation_manager = DelegationManager::new(Arc::clone(&state));
+	let reconciler = DelegationReconciler::new(Arc::clone(&state));
+	let auditor = ConstraintAuditor::new(state);
+
+	// Keep the slot clock in sync with the beacon node
+	let clock = Arc::clone(&slot_clock);
+	tokio::spawn(async move { clock.run().await });
+
+	// Keep the beacon endpoint scores fresh
+	tokio::spawn(async move { beacon_client.run_probes().await });
+
+	// Prefetch proposer duties every slot
+	tokio::spawn(async move { lookahead_cache.run().await });
+
+	// Re-post delegations the relays lost
+	tokio::spawn(async move {
+		if let Err(e) = reconciler.run().await {
+			error!("Delegation reconciler exited with error: {e:?}");
+		}
+	});
+
+	// Audit the constraints of delegated slots before they start
+	tokio::spawn(async move {
+		if let Err(e) = auditor.run().await {
+			error!("Constraint auditor exited with error: {e:?}");
+		}
+	});
 
 	// Launch delegation manager loop
 	info!("Starting proposer delegation loop");
@@ -66,7 +190,7 @@ async fn main() -> Result<()> {
 	loop {
 		poll_interval.tick().await;
 
-		let current_slot = current_slot(&chain);
+		let current_slot = slot_clock.current_slot();
 		info!("Checking proposer duties for current slot: {}", current_slot);
 
 		// Process lookahead to find and post delegations
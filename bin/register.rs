@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use alloy::primitives::{Address, B256, U256};
+use alloy::signers::local::PrivateKeySigner;
+use clap::Parser;
+use common::logging::LoggingConfig;
+use config::cli::{ConfigCommand, ServiceArgs, ServiceCommand};
+use config::{ConfigErrors, Validate};
+use eyre::{Result, WrapErr};
+use reqwest::Url;
+use serde::Deserialize;
+use tracing::info;
+
+use urc::client::UrcClient;
+use urc::{SignedRegistration, URCRegisterInputs};
+
+const REGISTER_ENV_PREFIX: &str = "FABRIC_REGISTER";
+
+/// Register BLS keys in the URC, locking collateral from the keystore account
+#[derive(Debug, Parser)]
+struct Args {
+	#[command(flatten)]
+	service: ServiceArgs,
+
+	#[command(subcommand)]
+	command: Option<ServiceCommand>,
+}
+
+/// Configuration of a registration
+#[derive(Debug, Deserialize)]
+struct RegisterConfig {
+	/// Execution client RPC URL
+	rpc_url: Url,
+	/// Address of the URC registry contract
+	registry: Address,
+	/// Encrypted JSON keystore of the account sending the transaction, the password is read from KEYSTORE_PASSWORD
+	keystore: PathBuf,
+	/// Address allowed to manage the registration
+	owner: Address,
+	/// JSON file holding the signed registrations, a list of `{ pubkey, signature, nonce }`
+	registrations: PathBuf,
+	/// Signing ID the registrations were signed under
+	signing_id: B256,
+	/// Collateral to lock in wei, defaults to the registry minimum
+	#[serde(default)]
+	collateral_wei: Option<U256>,
+	/// Log format, module levels and log file
+	#[serde(default)]
+	logging: LoggingConfig,
+}
+
+impl Validate for RegisterConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.ensure(self.keystore.is_file(), "keystore", format!("{} is not a file", self.keystore.display()));
+		errors.ensure(
+			self.registrations.is_file(),
+			"registrations",
+			format!("{} is not a file", self.registrations.display()),
+		);
+	}
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+	let args = Args::parse();
+	if let Some(ServiceCommand::Version) = args.command {
+		println!("register {}", env!("CARGO_PKG_VERSION"));
+		return Ok(());
+	}
+
+	let config_path = args.service.config_path("CONFIG_PATH")?;
+	if let Some(ServiceCommand::Config(ConfigCommand::Validate)) = args.command {
+		config::load_file::<RegisterConfig>(&config_path, REGISTER_ENV_PREFIX)?;
+		println!("Register config {} is valid", config_path.display());
+		return Ok(());
+	}
+
+	// Load configuration
+	let config: RegisterConfig = config::load_file(&config_path, REGISTER_ENV_PREFIX)?;
+
+	// Setup logging
+	let _logging = common::logging::setup_logging_with_filter(&args.service.log_filter(), &config.logging)?;
+
+	let password = std::env::var("KEYSTORE_PASSWORD").wrap_err("KEYSTORE_PASSWORD environment variable not set")?;
+	let signer =
+		PrivateKeySigner::decrypt_keystore(&config.keystore, password).wrap_err("Failed to decrypt keystore")?;
+
+	let registrations_content =
+		std::fs::read_to_string(&config.registrations).wrap_err("Failed to read registrations file")?;
+	let registrations: Vec<SignedRegistration> =
+		serde_json::from_str(&registrations_content).wrap_err("Failed to parse registrations file")?;
+	if registrations.is_empty() {
+		return Err(eyre::eyre!("No registrations to submit"));
+	}
+
+	let client = UrcClient::new(config.rpc_url, config.registry, signer);
+	let collateral_wei = match config.collateral_wei {
+		Some(collateral_wei) => collateral_wei,
+		None => client.min_collateral_wei().await?,
+	};
+
+	info!("Registering {} keys for owner {}", registrations.len(), config.owner);
+	info!("  Registry: {}", config.registry);
+	info!("  Sender: {}", client.sender());
+	info!("  Collateral: {} wei", collateral_wei);
+
+	if args.service.dry_run {
+		info!("Dry run: registrations and collateral are set up, exiting without sending the transaction");
+		return Ok(());
+	}
+
+	let inputs = URCRegisterInputs { registrations, owner: config.owner, signing_id: config.signing_id };
+	let receipt = client.register(&inputs, collateral_wei).await?;
+
+	info!("Registered in transaction {:?}", receipt.tx_hash);
+	info!("  Registration root: {}", receipt.registration_root);
+	info!("  Collateral: {} wei", receipt.collateral_wei);
+	if let Some(block_number) = receipt.block_number {
+		info!("  Block: {}", block_number);
+	}
+
+	Ok(())
+}
@@ -1,50 +1,157 @@
+use axum::{Router, routing::get};
+use clap::Parser;
+use common::health::health_router;
 use common::storage::create_database;
+use config::cli::{ConfigCommand, MetricsArgs, ServiceArgs, ServiceCommand};
 use constraints::server::build_constraints_router_with_proxy;
-use eyre::Result;
+use eyre::{Result, WrapErr};
 use inclusion::relay::{
-	config::RelayConfig,
-	services::{lookahead_manager::LookaheadManager, server::RelayServer},
+	config::{RELAY_ENV_PREFIX, RelayConfig},
+	metrics::relay_metrics_handler,
+	services::{
+		admin::build_admin_router, downstream_health::DownstreamHealthManager, lookahead_manager::LookaheadManager,
+		server::RelayServer, urc_sync::UrcSyncManager,
+	},
 	state::RelayState,
 };
+use proposer::storage::DelegationsDbExt;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tracing::info;
+use tracing::{info, warn};
 
-fn setup_state(path: &str) -> Result<RelayState> {
-	// Read config .toml file
-	let content = std::fs::read_to_string(path)?;
-	let config: RelayConfig = toml::from_str(&content)?;
+/// Relay service: constraints API, lookahead and downstream relay proxy
+#[derive(Debug, Parser)]
+struct Args {
+	#[command(flatten)]
+	service: ServiceArgs,
 
-	info!("Loaded relay config");
+	#[command(flatten)]
+	metrics: MetricsArgs,
+
+	#[command(subcommand)]
+	command: Option<ServiceCommand>,
+}
 
+fn setup_state(config: RelayConfig) -> Result<RelayState> {
 	// Initialize database
 	let db = create_database(config.db_path.as_str()).map_err(|e| eyre::eyre!("Failed to create database: {}", e))?;
 
-	Ok(RelayState::new(db, config))
+	// Delegations used to be stored once per slot, move them under their delegate before serving them
+	let migrated = db.migrate_slot_delegations().wrap_err("Failed to migrate the stored delegations")?;
+	if migrated > 0 {
+		info!("Migrated {} delegation(s) to per-delegate storage", migrated);
+	}
+
+	RelayState::new(db, config).wrap_err("Failed to set up the relay state")
 }
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
+	let args = Args::parse();
+	if let Some(ServiceCommand::Version) = args.command {
+		println!("relay {}", env!("CARGO_PKG_VERSION"));
+		return Ok(());
+	}
+
 	// Get config path from command line arguments
-	let config_path = std::env::var("CONFIG_PATH").expect("CONFIG_PATH environment variable not set");
+	let config_path = args.service.config_path("CONFIG_PATH")?;
+	if let Some(ServiceCommand::Config(ConfigCommand::Validate)) = args.command {
+		config::load_file::<RelayConfig>(&config_path, RELAY_ENV_PREFIX)?;
+		println!("Relay config {} is valid", config_path.display());
+		return Ok(());
+	}
+
+	// Read config .toml file, overridden from the environment and validated
+	let config: RelayConfig = config::load_file(&config_path, RELAY_ENV_PREFIX)?;
 
 	// Setup logging
-	common::logging::setup_logging(&std::env::var("RUST_LOG").expect("RUST_LOG environment variable not set"))?;
+	let _logging = common::logging::setup_logging_with_filter(&args.service.log_filter(), &config.logging)?;
+	info!("Loaded relay config");
 
 	// Setup state
-	let state = Arc::new(setup_state(config_path.as_str())?);
+	let state = Arc::new(setup_state(config)?);
+
+	// Slot timing of the chain comes from the beacon node
+	if let Err(e) = state.slot_clock.sync().await {
+		warn!(
+			"Failed to sync the slot clock with the beacon node, using the chain's genesis and mainnet timing: {}",
+			e
+		);
+	}
 
 	// Copy before move
 	let server_url = format!("{}:{}", state.host, state.port);
+	let slot_clock = Arc::clone(&state.slot_clock);
+	let beacon_client = state.beacon_client.clone();
+	let lookahead_cache = Arc::clone(&state.lookahead_cache);
 
 	// Create lookahead manager
 	let lookahead_manager = LookaheadManager::new(Arc::clone(&state));
 
+	// Create downstream health manager
+	let downstream_health_manager = DownstreamHealthManager::new(Arc::clone(&state));
+
+	// Create URC sync manager if URC checks are enabled
+	let urc_sync_manager = state.urc_indexer.is_some().then(|| UrcSyncManager::new(Arc::clone(&state)));
+
+	// Copy metrics url before move, --metrics-addr serves metrics even without a configured metrics port
+	let metrics_url = match args.metrics.metrics_addr {
+		Some(metrics_addr) => Some(metrics_addr.to_string()),
+		None => state.metrics_port.map(|metrics_port| format!("{}:{}", state.host, metrics_port)),
+	};
+
+	// Build the admin router if enabled
+	let admin = match state.admin_port {
+		Some(admin_port) => {
+			let api_key = state
+				.admin_api_key
+				.clone()
+				.ok_or_else(|| eyre::eyre!("admin_api_key must be set when admin_port is set"))?;
+			Some((format!("{}:{}", state.host, admin_port), build_admin_router(Arc::clone(&state), api_key)))
+		}
+		None => None,
+	};
+
+	if args.service.dry_run {
+		info!("Dry run: relay config and state are set up, exiting without starting any task");
+		return Ok(());
+	}
+
+	// Liveness and readiness are served next to the constraints API
+	let health = health_router(Arc::clone(&state));
+
 	// Create relay server
 	let relay_server = RelayServer::new(state);
 
 	// Build constraints router with proxy fallback
-	let router = build_constraints_router_with_proxy(relay_server);
+	let router = build_constraints_router_with_proxy(relay_server).merge(health);
+
+	// Bind every server first, so a port already in use fails the startup before any task runs
+	info!("Starting relay server on {}", server_url);
+	let listener = TcpListener::bind(server_url).await?;
+	let metrics_listener = match metrics_url {
+		Some(metrics_url) => {
+			info!("Starting relay metrics server on {}", metrics_url);
+			Some(TcpListener::bind(metrics_url).await?)
+		}
+		None => None,
+	};
+	let admin = match admin {
+		Some((admin_url, admin_router)) => {
+			info!("Starting relay admin server on {}", admin_url);
+			Some((TcpListener::bind(admin_url).await?, admin_router))
+		}
+		None => None,
+	};
+
+	info!("Starting slot clock");
+	let slot_clock_handle = tokio::spawn(async move { slot_clock.run().await });
+
+	info!("Starting beacon endpoint probes");
+	let beacon_probes_handle = tokio::spawn(async move { beacon_client.run_probes().await });
+
+	info!("Starting proposer duties prefetch");
+	let lookahead_cache_handle = tokio::spawn(async move { lookahead_cache.run().await });
 
 	info!("Starting lookahead manager");
 	let lookahead_manager_handle = tokio::spawn(async move {
@@ -53,22 +160,72 @@ async fn main() -> eyre::Result<()> {
 		}
 	});
 
-	// Run relay server (this will block until shutdown)
-	info!("Starting relay server on {}", server_url);
-	let listener = TcpListener::bind(server_url).await?;
+	info!("Starting downstream health manager");
+	let downstream_health_manager_handle = tokio::spawn(async move {
+		if let Err(e) = downstream_health_manager.run().await {
+			tracing::error!("Downstream health manager error: {}", e);
+		}
+	});
+
+	let urc_sync_manager_handle = urc_sync_manager.map(|urc_sync_manager| {
+		info!("Starting URC sync manager");
+		tokio::spawn(async move {
+			if let Err(e) = urc_sync_manager.run().await {
+				tracing::error!("URC sync manager error: {}", e);
+			}
+		})
+	});
+
+	// Run relay server
 	let relay_server_handle = tokio::spawn(async move {
 		if let Err(e) = axum::serve(listener, router).await {
 			tracing::error!("Relay server error: {}", e);
 		}
 	});
 
+	// Run metrics server if enabled
+	let metrics_server_handle = match metrics_listener {
+		Some(metrics_listener) => {
+			let metrics_router = Router::new().route("/metrics", get(relay_metrics_handler));
+			Some(tokio::spawn(async move {
+				if let Err(e) = axum::serve(metrics_listener, metrics_router).await {
+					tracing::error!("Relay metrics server error: {}", e);
+				}
+			}))
+		}
+		None => None,
+	};
+
+	// Run admin server if enabled
+	let admin_server_handle = match admin {
+		Some((admin_listener, admin_router)) => Some(tokio::spawn(async move {
+			if let Err(e) = axum::serve(admin_listener, admin_router).await {
+				tracing::error!("Relay admin server error: {}", e);
+			}
+		})),
+		None => None,
+	};
+
 	// Wait for shutdown signals
 	common::utils::wait_for_signal().await?;
 	info!("Shutdown signal received, stopping tasks");
 
 	// Kill tasks
+	slot_clock_handle.abort();
+	beacon_probes_handle.abort();
+	lookahead_cache_handle.abort();
 	lookahead_manager_handle.abort();
+	downstream_health_manager_handle.abort();
 	relay_server_handle.abort();
+	if let Some(handle) = urc_sync_manager_handle {
+		handle.abort();
+	}
+	if let Some(handle) = metrics_server_handle {
+		handle.abort();
+	}
+	if let Some(handle) = admin_server_handle {
+		handle.abort();
+	}
 
 	Ok(())
 }
@@ -0,0 +1,381 @@
+use alloy::consensus::TxEnvelope;
+use alloy::eips::eip2718::Decodable2718;
+use alloy::primitives::B256;
+use alloy::rpc::types::beacon::relay::{ElectraSubmitBlockRequest, SubmitBlockRequest};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::Parser;
+use commit_boost::prelude::Chain;
+use common::logging::LoggingConfig;
+use config::cli::{ConfigCommand, ServiceArgs, ServiceCommand};
+use config::{ConfigErrors, Validate};
+use constraints::client::{ConstraintsClient, HttpConstraintsClient};
+use constraints::routes::{LEGACY_STATUS, LEGACY_SUBMIT_BLOCK};
+use constraints::types::{SignedConstraints, SubmitBlockRequestWithProofs};
+use eyre::{Result, eyre};
+use inclusion::constants::INCLUSION_CONSTRAINT_TYPE;
+use inclusion::proofs::prove_signed_constraints;
+use inclusion::types::InclusionPayload;
+use lookahead::slot_clock::SlotClock;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use signing::builder::{BuilderSigner, build_block};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// Env prefix overriding fields of the relay mock config
+const RELAY_MOCK_ENV_PREFIX: &str = "FABRIC_RELAY_MOCK";
+
+/// Builder API route registering validators with the relay
+const REGISTER_VALIDATORS_ROUTE: &str = "/eth/v1/builder/validators";
+/// Builder API route serving the best bid of a slot
+const GET_HEADER_ROUTE: &str = "/eth/v1/builder/header/{slot}/{parent_hash}/{pubkey}";
+/// Builder API route revealing the payload of a signed blinded block
+const GET_PAYLOAD_ROUTE: &str = "/eth/v1/builder/blinded_blocks";
+
+/// Mock downstream relay and builder closing the simulation loop behind the fabric relay
+#[derive(Debug, Parser)]
+struct Args {
+	#[command(flatten)]
+	service: ServiceArgs,
+
+	#[command(subcommand)]
+	command: Option<ServiceCommand>,
+}
+
+/// Configuration for the relay mock, read from `--config` or the file in CONFIG_PATH
+#[derive(Debug, Deserialize)]
+struct RelayMockConfig {
+	/// Chain spec
+	chain: Chain,
+	/// Host to listen on, the fabric relay's `downstream_relay_host`
+	host: String,
+	/// Port to listen on, the fabric relay's `downstream_relay_port`
+	port: u16,
+	/// BLS public key the bids are announced under, the key in the relay URL of the PBS module
+	relay_public_key: String,
+	/// Number of most recent slots whose best block is kept
+	#[serde(default = "default_retained_slots")]
+	retained_slots: usize,
+	/// Builder posting a block with the constrained transactions of each delegated slot to the fabric relay
+	#[serde(default)]
+	builder: Option<MockBuilderConfig>,
+}
+
+fn default_retained_slots() -> usize {
+	64
+}
+
+#[derive(Debug, Deserialize)]
+struct MockBuilderConfig {
+	/// Host of the fabric relay's constraints API
+	relay_host: String,
+	/// Port of the fabric relay's constraints API
+	relay_port: u16,
+	/// API key of the fabric relay, if it requires one
+	#[serde(default)]
+	relay_api_key: Option<String>,
+	/// Environment variable holding the hex-encoded BLS secret key the block bids are signed with
+	builder_key_env: String,
+	/// How long before the start of a delegated slot the block is built and submitted (in milliseconds)
+	#[serde(default = "default_build_offset_ms")]
+	build_offset_ms: i64,
+}
+
+fn default_build_offset_ms() -> i64 {
+	1_000
+}
+
+impl Validate for RelayMockConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.host("host", &self.host);
+		errors.port("port", self.port);
+		errors.bls_public_key("relay_public_key", &self.relay_public_key);
+		errors.positive("retained_slots", self.retained_slots as u64);
+		if let Some(builder) = &self.builder {
+			errors.host("builder.relay_host", &builder.relay_host);
+			errors.port("builder.relay_port", builder.relay_port);
+			errors.not_empty("builder.builder_key_env", &builder.builder_key_env);
+			errors.ensure(builder.build_offset_ms >= 0, "builder.build_offset_ms", "must not be negative");
+		}
+	}
+}
+
+/// Blocks the fabric relay forwarded, the best bid of each recent slot
+struct MockRelay {
+	relay_public_key: String,
+	retained_slots: usize,
+	blocks: RwLock<BTreeMap<u64, ElectraSubmitBlockRequest>>,
+}
+
+impl MockRelay {
+	/// Keep a block if it outbids the slot's current best, dropping the oldest slots beyond the retained ones
+	fn submit(&self, block: ElectraSubmitBlockRequest) -> bool {
+		let mut blocks = self.blocks.write().expect("blocks lock poisoned");
+		let slot = block.message.slot;
+		if blocks.get(&slot).is_some_and(|best| best.message.value >= block.message.value) {
+			return false;
+		}
+		blocks.insert(slot, block);
+		while blocks.len() > self.retained_slots {
+			blocks.pop_first();
+		}
+		true
+	}
+
+	/// The bid of a slot's best block, in the builder API's `getHeader` response format
+	/// Bids are not signed by the relay key and the header's SSZ roots are left zero, so the PBS module has to
+	/// run with `skip_sigverify`
+	fn header(&self, slot: u64, proposer: &str) -> Option<Value> {
+		let blocks = self.blocks.read().expect("blocks lock poisoned");
+		let block = blocks
+			.get(&slot)
+			.filter(|block| block.message.proposer_pubkey.to_string().eq_ignore_ascii_case(proposer))?;
+		let payload = &block.execution_payload.payload_inner.payload_inner;
+		Some(json!({
+			"version": "electra",
+			"data": {
+				"message": {
+					"header": {
+						"parent_hash": payload.parent_hash,
+						"fee_recipient": payload.fee_recipient,
+						"state_root": payload.state_root,
+						"receipts_root": payload.receipts_root,
+						"logs_bloom": payload.logs_bloom,
+						"prev_randao": payload.prev_randao,
+						"block_number": payload.block_number.to_string(),
+						"gas_limit": payload.gas_limit.to_string(),
+						"gas_used": payload.gas_used.to_string(),
+						"timestamp": payload.timestamp.to_string(),
+						"extra_data": payload.extra_data,
+						"base_fee_per_gas": payload.base_fee_per_gas.to_string(),
+						"block_hash": payload.block_hash,
+						"transactions_root": B256::ZERO,
+						"withdrawals_root": B256::ZERO,
+						"blob_gas_used": block.execution_payload.blob_gas_used.to_string(),
+						"excess_blob_gas": block.execution_payload.excess_blob_gas.to_string()
+					},
+					"blob_kzg_commitments": block.blobs_bundle.commitments,
+					"execution_requests": block.execution_requests,
+					"value": block.message.value.to_string(),
+					"pubkey": self.relay_public_key
+				},
+				"signature": format!("0x{}", "00".repeat(96))
+			}
+		}))
+	}
+
+	/// The payload of a stored block, in the builder API's `submitBlindedBlock` response format
+	fn payload(&self, block_hash: B256) -> Option<Value> {
+		let blocks = self.blocks.read().expect("blocks lock poisoned");
+		let block = blocks.values().find(|block| block.message.block_hash == block_hash)?;
+		Some(json!({
+			"version": "electra",
+			"data": {
+				"execution_payload": block.execution_payload,
+				"blobs_bundle": block.blobs_bundle
+			}
+		}))
+	}
+}
+
+async fn status_handler() -> StatusCode {
+	StatusCode::OK
+}
+
+async fn register_validators_handler(Json(registrations): Json<Vec<Value>>) -> StatusCode {
+	debug!("Registered {} validator(s)", registrations.len());
+	StatusCode::OK
+}
+
+/// Blocks forwarded by the fabric relay once their proofs were verified
+async fn submit_block_handler(State(relay): State<Arc<MockRelay>>, Json(block): Json<SubmitBlockRequest>) -> Response {
+	let SubmitBlockRequest::Electra(block) = block else {
+		return (StatusCode::BAD_REQUEST, "Only Electra blocks are supported").into_response();
+	};
+	let (slot, block_hash, value) = (block.message.slot, block.message.block_hash, block.message.value);
+	if relay.submit(block) {
+		info!("Received block {} for slot {} with value {}", block_hash, slot, value);
+	} else {
+		info!("Received block {} for slot {} with value {}, outbid", block_hash, slot, value);
+	}
+	StatusCode::OK.into_response()
+}
+
+async fn get_header_handler(
+	Path((slot, parent_hash, pubkey)): Path<(u64, B256, String)>,
+	State(relay): State<Arc<MockRelay>>,
+) -> Response {
+	// Blocks of the mock builder are not built on the real head, so the parent hash is not matched
+	match relay.header(slot, &pubkey) {
+		Some(bid) => {
+			info!("Serving bid for slot {} to {} on parent {}", slot, pubkey, parent_hash);
+			Json(bid).into_response()
+		}
+		None => StatusCode::NO_CONTENT.into_response(),
+	}
+}
+
+async fn get_payload_handler(State(relay): State<Arc<MockRelay>>, Json(signed_block): Json<Value>) -> Response {
+	let block_hash = signed_block
+		.pointer("/message/body/execution_payload_header/block_hash")
+		.and_then(|block_hash| serde_json::from_value::<B256>(block_hash.clone()).ok());
+	let Some(block_hash) = block_hash else {
+		return (StatusCode::BAD_REQUEST, "Missing execution payload header block hash").into_response();
+	};
+	match relay.payload(block_hash) {
+		Some(payload) => {
+			info!("Revealing payload of block {}", block_hash);
+			Json(payload).into_response()
+		}
+		None => (StatusCode::BAD_REQUEST, format!("Unknown block {}", block_hash)).into_response(),
+	}
+}
+
+/// Builder of one block per delegated slot, holding the slot's constrained transactions and posted with their
+/// proofs to the fabric relay, which forwards it back to the mock relay once verified
+struct MockBuilder {
+	client: HttpConstraintsClient,
+	signer: BuilderSigner,
+	slot_clock: SlotClock,
+	build_offset_ms: i64,
+}
+
+impl MockBuilder {
+	fn new(config: &MockBuilderConfig, chain: Chain) -> Result<Self> {
+		Ok(Self {
+			client: HttpConstraintsClient::new(
+				config.relay_host.clone(),
+				config.relay_port,
+				config.relay_api_key.clone(),
+			)?,
+			signer: BuilderSigner::from_env(&config.builder_key_env, chain)?,
+			slot_clock: SlotClock::new(&chain),
+			build_offset_ms: config.build_offset_ms,
+		})
+	}
+
+	async fn run(&self) {
+		info!(
+			"Starting mock builder with builder key {}, building {}ms before delegated slots",
+			self.signer.public_key(),
+			self.build_offset_ms
+		);
+		let mut slot = self.slot_clock.current_slot() + 1;
+		loop {
+			let wait_ms = self.slot_clock.slot_deadline(slot) - self.build_offset_ms;
+			tokio::time::sleep(Duration::from_millis(wait_ms.max(0) as u64)).await;
+			if let Err(e) = self.build_slot(slot).await {
+				error!("Mock block for slot {} failed: {}", slot, e);
+			}
+			slot = (slot + 1).max(self.slot_clock.current_slot() + 1);
+		}
+	}
+
+	/// Build and submit the block of `slot`, a no-op unless the slot is delegated and has constraints
+	async fn build_slot(&self, slot: u64) -> Result<()> {
+		let Some(delegation) = self.client.get_delegations(slot).await?.into_iter().next() else {
+			debug!("No delegation for slot {}, not building", slot);
+			return Ok(());
+		};
+		let signed_constraints = self.client.get_constraints(slot).await?;
+		if signed_constraints.is_empty() {
+			debug!("No constraints for slot {}, not building", slot);
+			return Ok(());
+		}
+
+		let transactions = constrained_transactions(&signed_constraints);
+		let message = build_block(slot, delegation.message.proposer, &transactions, &self.signer);
+		let report = prove_signed_constraints(&message, &signed_constraints)?;
+		if !report.is_complete() {
+			return Err(eyre!("{} constraint(s) left unsatisfied: {:?}", report.unsatisfied.len(), report.unsatisfied));
+		}
+
+		let block_hash = message.bid_trace().block_hash;
+		self.client
+			.post_blocks_with_proofs(&SubmitBlockRequestWithProofs { message, proofs: report.into_proofs()? })
+			.await?;
+		info!("Submitted mock block {} for slot {} with {} transaction(s)", block_hash, slot, transactions.len());
+		Ok(())
+	}
+}
+
+/// The transactions of the inclusion constraints, in constraint order
+fn constrained_transactions(signed_constraints: &[SignedConstraints]) -> Vec<TxEnvelope> {
+	signed_constraints
+		.iter()
+		.flat_map(|signed| &signed.message.constraints)
+		.filter(|constraint| constraint.constraint_type == INCLUSION_CONSTRAINT_TYPE)
+		.filter_map(|constraint| {
+			let decoded = InclusionPayload::abi_decode(&constraint.payload)
+				.and_then(|payload| Ok(TxEnvelope::decode_2718(&mut payload.signed_tx.as_ref())?));
+			decoded.inspect_err(|e| warn!("Skipping undecodable inclusion constraint: {}", e)).ok()
+		})
+		.collect()
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+	let args = Args::parse();
+	if let Some(ServiceCommand::Version) = args.command {
+		println!("relay-mock {}", env!("CARGO_PKG_VERSION"));
+		return Ok(());
+	}
+
+	let config_path = args.service.config_path("CONFIG_PATH")?;
+	let config: RelayMockConfig = config::load_file(&config_path, RELAY_MOCK_ENV_PREFIX)?;
+	if let Some(ServiceCommand::Config(ConfigCommand::Validate)) = args.command {
+		println!("Relay mock config {} is valid", config_path.display());
+		return Ok(());
+	}
+
+	let _logging = common::logging::setup_logging_with_filter(&args.service.log_filter(), &LoggingConfig::default())?;
+
+	let bind_addr = format!("{}:{}", config.host, config.port);
+	let builder = config.builder.as_ref().map(|builder| MockBuilder::new(builder, config.chain)).transpose()?;
+	let relay = Arc::new(MockRelay {
+		relay_public_key: config.relay_public_key,
+		retained_slots: config.retained_slots,
+		blocks: RwLock::new(BTreeMap::new()),
+	});
+
+	info!("Mock Relay Server");
+	info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+	info!("Listening on: {}", bind_addr);
+	info!("Relay key: {}", relay.relay_public_key);
+	info!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+	info!("Endpoint: POST {}", LEGACY_SUBMIT_BLOCK);
+	info!("Endpoint: GET {}", LEGACY_STATUS);
+	info!("Endpoint: POST {}", REGISTER_VALIDATORS_ROUTE);
+	info!("Endpoint: GET {}", GET_HEADER_ROUTE);
+	info!("Endpoint: POST {}", GET_PAYLOAD_ROUTE);
+
+	let app = Router::new()
+		.route(LEGACY_SUBMIT_BLOCK, post(submit_block_handler))
+		.route(LEGACY_STATUS, get(status_handler))
+		.route(REGISTER_VALIDATORS_ROUTE, post(register_validators_handler))
+		.route(GET_HEADER_ROUTE, get(get_header_handler))
+		.route(GET_PAYLOAD_ROUTE, post(get_payload_handler))
+		.with_state(relay);
+
+	let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
+
+	if args.service.dry_run {
+		info!("Dry run: listening address is free, exiting without serving");
+		return Ok(());
+	}
+
+	if let Some(builder) = builder {
+		tokio::spawn(async move { builder.run().await });
+	}
+
+	info!("Mock Relay server ready");
+	axum::serve(listener, app).await?;
+
+	Ok(())
+}
@@ -1,81 +1,503 @@
 use alloy::consensus::{SignableTransaction, Signed, TxEnvelope};
+use alloy::eips::BlockNumberOrTag;
 use alloy::eips::eip2718::Encodable2718;
 use alloy::network::{Ethereum, TransactionBuilder};
 use alloy::primitives::{Address, Bytes, TxHash, U256};
-use alloy::providers::{Provider, ProviderBuilder};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
 use alloy::signers::{SignerSync, local::PrivateKeySigner};
-use commitments::client::CommitmentsHttpClient;
-use eyre::{Result, WrapErr};
-use lookahead::utils::current_slot_estimate;
+use clap::Parser;
+use commitments::client::{CommitmentsClientConfig, CommitmentsHttpClient};
+use commitments::error::CommitmentsClientError;
+use common::logging::LoggingConfig;
+use config::cli::{ConfigCommand, ServiceArgs, ServiceCommand};
+use config::{ConfigErrors, Validate};
+use constraints::client::{ConstraintsClient, HttpConstraintsClient};
+use eyre::{Result, WrapErr, eyre};
+use lookahead::slot_clock::SlotClock;
 use reqwest::Url;
 use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tracing::{error, info};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{Instant, sleep, sleep_until};
+use tracing::{error, info, warn};
 
 use commit_boost::prelude::Chain;
 
-use commitments::types::{CommitmentRequest, SignedCommitment};
-use inclusion::constants::INCLUSION_COMMITMENT_TYPE;
+use commitments::types::{CommitmentRequest, SignedCommitment, SlotInfo};
+use inclusion::constants::{INCLUSION_COMMITMENT_TYPE, INCLUSION_CONSTRAINT_TYPE, LOOKAHEAD_WINDOW_SIZE};
+use inclusion::gateway::metrics::rejection_reason;
+use inclusion::gateway::pricing::{self, SlotDemand};
 use inclusion::types::InclusionPayload;
+use signing::signer::verify_ecdsa_commitment;
 
-pub const SLOTS_IN_FUTURE_TO_SEND_COMMITMENT_REQUEST: u64 = 2;
+/// Slots between the current slot and the earliest slot a commitment is requested for
+pub const MIN_SLOTS_AHEAD: u64 = 1;
+
+/// Gas of the plain transfers the spammer commits to
+const TRANSFER_GAS: u64 = 21_000;
+
+const SPAMMER_ENV_PREFIX: &str = "FABRIC_SPAMMER";
+
+/// Sends signed transactions to the gateway as commitment requests
+#[derive(Debug, Parser)]
+struct Args {
+	#[command(flatten)]
+	service: ServiceArgs,
+
+	#[command(subcommand)]
+	command: Option<ServiceCommand>,
+}
 
 /// Configuration for the spammer
 #[derive(Debug, Deserialize)]
 struct SpammerConfig {
-	/// Mode: "one-shot" or "continuous"
+	/// Mode: "one-shot", "continuous", "load" or "scenario"
 	mode: String,
 	/// Gateway RPC (commitments) host
 	gateway_host: String,
 	/// Gateway RPC (commitments) port
 	gateway_port: u16,
+	/// Gateway RPC (commitments) URLs to fail over to when the primary gateway is unreachable
+	#[serde(default)]
+	fallback_gateway_urls: Vec<Url>,
 	/// Execution client host
 	execution_client_host: String,
 	/// Execution client port
 	execution_client_port: u16,
 	/// Slasher contract address (optional, random if not provided)
 	slasher_address: Option<String>,
+	/// Gateway committer address, returned commitments are checked to be signed by it when set
+	committer_address: Option<Address>,
+	/// Minimum priority fee per gas the gateway charges, its `min_tip_per_gas_wei`
+	#[serde(default)]
+	min_tip_per_gas_wei: u64,
 	/// Chain spec
 	chain: Chain,
+	/// Closed-loop verification of every commitment sent, not run if not set
+	#[serde(default)]
+	verification: Option<VerificationConfig>,
+	/// Rate, concurrency and duration of the "load" mode
+	#[serde(default)]
+	load: Option<LoadConfig>,
+	/// Scenario file of the "scenario" mode
+	#[serde(default)]
+	scenario_file: Option<PathBuf>,
+	/// Log format, module levels and log file
+	#[serde(default)]
+	logging: LoggingConfig,
 }
 
-/// Generate a valid signed transaction, returning encoded bytes, tx hash, and nonce
-async fn generate_signed_transaction(
-	config: &SpammerConfig,
-	signer: &PrivateKeySigner,
-) -> Result<(Bytes, TxHash, u64)> {
-	// Create execution client
-	let execution_client_url =
-		Url::parse(format!("http://{}:{}", config.execution_client_host, config.execution_client_port).as_str())?;
-	let execution_client = ProviderBuilder::new().network::<Ethereum>().connect_http(execution_client_url);
-
-	let nonce = execution_client.get_transaction_count(signer.address()).await?;
-	let chain_id = config.chain.id().to::<u64>();
-
-	let latest_block = execution_client
-		.get_block_by_number(alloy::eips::BlockNumberOrTag::Latest)
-		.await?
-		.ok_or_else(|| eyre::eyre!("Failed to get latest block"))?;
-	let base_fee =
-		latest_block.header.base_fee_per_gas.ok_or_else(|| eyre::eyre!("No base fee in block (pre-EIP-1559?)"))?;
-	let max_fee_per_gas = base_fee * 100000; // A lot more to avoid getting outbid
-
-	let balance = execution_client.get_balance(signer.address()).await?;
-	info!("Sender address: {:?}", signer.address());
-	info!("Sender balance: {} wei", balance);
-	info!("Base fee: {} wei, max_fee_per_gas: {} wei", base_fee, max_fee_per_gas);
-	info!("Max cost: {} wei", U256::from(21000) * U256::from(max_fee_per_gas) + U256::from(1));
+/// Follows each commitment through the gateway, the relay and the chain
+#[derive(Debug, Clone, Deserialize)]
+struct VerificationConfig {
+	/// Address of the Relay server (constraints API) the gateway posts to
+	relay_host: String,
+	/// Port of the Relay server (constraints API)
+	relay_port: u16,
+	/// API key for the Relay server (constraints API)
+	#[serde(default)]
+	relay_api_key: Option<String>,
+	/// Slots after the target slot to wait for the transaction to be mined before it counts as not included
+	#[serde(default = "default_settle_slots")]
+	settle_slots: u64,
+}
+
+fn default_settle_slots() -> u64 {
+	2
+}
+
+/// Load generation against the commitments RPC
+#[derive(Debug, Clone, Deserialize)]
+struct LoadConfig {
+	/// Commitment requests per second once ramped up
+	requests_per_second: f64,
+	/// Requests in flight at most, the rate drops when the gateway cannot keep up
+	#[serde(default = "default_load_concurrency")]
+	concurrency: usize,
+	/// How long to send requests for, ramp-up included
+	duration_seconds: u64,
+	/// Time to reach `requests_per_second`, starting from one request per second
+	#[serde(default)]
+	ramp_up_seconds: u64,
+	/// How the rate rises during the ramp-up
+	#[serde(default)]
+	ramp_up_profile: RampProfile,
+	/// Number of equal steps of the "step" ramp-up profile
+	#[serde(default = "default_ramp_up_steps")]
+	ramp_up_steps: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RampProfile {
+	/// The rate rises continuously
+	#[default]
+	Linear,
+	/// The rate rises by `ramp_up_steps` equal steps
+	Step,
+}
 
+impl LoadConfig {
+	/// Requests per second `elapsed` into the run
+	fn rate(&self, elapsed: Duration) -> f64 {
+		let ramp_up = Duration::from_secs(self.ramp_up_seconds);
+		if elapsed >= ramp_up {
+			return self.requests_per_second;
+		}
+		let progress = elapsed.as_secs_f64() / ramp_up.as_secs_f64();
+		let fraction = match self.ramp_up_profile {
+			RampProfile::Linear => progress,
+			RampProfile::Step => {
+				let steps = self.ramp_up_steps as f64;
+				((progress * steps).floor() + 1.0) / steps
+			}
+		};
+		(self.requests_per_second * fraction).max(self.requests_per_second.min(1.0))
+	}
+}
+
+fn default_load_concurrency() -> usize {
+	16
+}
+
+fn default_ramp_up_steps() -> u32 {
+	4
+}
+
+impl Validate for SpammerConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.ensure(
+			matches!(self.mode.as_str(), "one-shot" | "continuous" | "load" | "scenario"),
+			"mode",
+			format!("'{}' must be 'one-shot', 'continuous', 'load' or 'scenario'", self.mode),
+		);
+		match &self.scenario_file {
+			Some(path) => errors.ensure(path.is_file(), "scenario_file", format!("{} is not a file", path.display())),
+			None => errors.ensure(self.mode != "scenario", "scenario_file", "must be set in 'scenario' mode"),
+		}
+		match &self.load {
+			Some(load) => {
+				errors.ensure(load.requests_per_second > 0.0, "load.requests_per_second", "must be greater than zero");
+				errors.positive("load.concurrency", load.concurrency as u64);
+				errors.positive("load.duration_seconds", load.duration_seconds);
+				errors.positive("load.ramp_up_steps", load.ramp_up_steps as u64);
+			}
+			None => errors.ensure(self.mode != "load", "load", "must be set in 'load' mode"),
+		}
+		errors.host("gateway_host", &self.gateway_host);
+		errors.port("gateway_port", self.gateway_port);
+		errors.host("execution_client_host", &self.execution_client_host);
+		errors.port("execution_client_port", self.execution_client_port);
+		if let Some(slasher_address) = &self.slasher_address {
+			errors.address("slasher_address", slasher_address);
+		}
+		if let Some(verification) = &self.verification {
+			errors.ensure(
+				self.committer_address.is_some(),
+				"committer_address",
+				"must be set to verify the commitment signatures",
+			);
+			errors.host("verification.relay_host", &verification.relay_host);
+			errors.port("verification.relay_port", verification.relay_port);
+		}
+		if let Err(e) = self.logging.check() {
+			errors.add("logging", e);
+		}
+	}
+}
+
+/// Checks run on every commitment in verification mode, in the order the commitment goes through them
+const CHECK_SIGNATURE: &str = "signature";
+const CHECK_COMMITMENT_RESULT: &str = "commitment_result";
+const CHECK_RELAY_CONSTRAINTS: &str = "relay_constraints";
+const CHECK_INCLUSION: &str = "inclusion";
+
+/// Times `commitment_result` is queried before the gateway counts as not having stored the commitment
+const COMMITMENT_RESULT_ATTEMPTS: u32 = 3;
+
+/// Interval between two queries of the gateway or the execution client while verifying
+const VERIFICATION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Verification results of the commitments sent so far
+#[derive(Debug, Default)]
+struct VerificationSummary {
+	passed: u64,
+	failed: u64,
+	/// Failed commitments by check, a commitment can fail several checks
+	failed_checks: BTreeMap<&'static str, u64>,
+}
+
+impl VerificationSummary {
+	fn add(&mut self, failures: &[(&'static str, String)]) {
+		if failures.is_empty() {
+			self.passed += 1;
+		} else {
+			self.failed += 1;
+		}
+		for (check, _) in failures {
+			*self.failed_checks.entry(check).or_default() += 1;
+		}
+	}
+}
+
+impl std::fmt::Display for VerificationSummary {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} passed, {} failed", self.passed, self.failed)?;
+		for (check, count) in &self.failed_checks {
+			write!(f, ", {} failed {}", count, check)?;
+		}
+		Ok(())
+	}
+}
+
+/// Follows commitments from the gateway's signature to their transaction on chain
+#[derive(Clone)]
+struct Verifier {
+	chain: Chain,
+	committer: Address,
+	execution_client: DynProvider<Ethereum>,
+	commitments_client: CommitmentsHttpClient,
+	constraints_client: HttpConstraintsClient,
+	slot_clock: Arc<SlotClock>,
+	settle_slots: u64,
+	summary: Arc<Mutex<VerificationSummary>>,
+}
+
+impl Verifier {
+	/// Run every check on a commitment and add it to the summary, returns whether it passed
+	async fn verify(&self, commitment: &SignedCommitment, tx_hash: TxHash, target_slot: u64) -> bool {
+		let request_hash = commitment.commitment.request_hash;
+		let mut failures = Vec::new();
+		let mut check = |name: &'static str, result: Result<()>| {
+			if let Err(e) = result {
+				failures.push((name, e.to_string()));
+			}
+		};
+
+		check(CHECK_SIGNATURE, verify_ecdsa_commitment(self.chain, commitment, &self.committer));
+		check(CHECK_COMMITMENT_RESULT, self.check_commitment_result(commitment).await);
+
+		// After its slot the relay serves the constraints to anyone, and the slot's block is known
+		let wait_ms = self.slot_clock.slot_deadline(target_slot + 1);
+		if wait_ms > 0 {
+			sleep(Duration::from_millis(wait_ms as u64)).await;
+		}
+		check(CHECK_RELAY_CONSTRAINTS, self.check_relay_constraints(tx_hash, target_slot).await);
+		check(CHECK_INCLUSION, self.check_inclusion(tx_hash, target_slot).await);
+
+		if failures.is_empty() {
+			info!("PASS commitment {:?} for slot {}", request_hash, target_slot);
+		} else {
+			for (name, error) in &failures {
+				error!("FAIL commitment {:?} for slot {}: {}: {}", request_hash, target_slot, name, error);
+			}
+		}
+		let mut summary = self.summary.lock().expect("verification summary lock poisoned");
+		summary.add(&failures);
+		info!("Verification: {}", summary);
+		failures.is_empty()
+	}
+
+	/// The gateway returns the commitment it signed when queried by request hash
+	async fn check_commitment_result(&self, commitment: &SignedCommitment) -> Result<()> {
+		let request_hash = commitment.commitment.request_hash;
+		let mut attempt = 1;
+		let stored = loop {
+			match self.commitments_client.commitment_result(request_hash).await {
+				Ok(stored) => break stored,
+				Err(e) if attempt >= COMMITMENT_RESULT_ATTEMPTS => return Err(e.into()),
+				Err(_) => {
+					attempt += 1;
+					sleep(VERIFICATION_POLL_INTERVAL).await;
+				}
+			}
+		};
+		if stored.signature != commitment.signature || stored.commitment.payload != commitment.commitment.payload {
+			return Err(eyre!("gateway returns a different commitment for request hash {:?}", request_hash));
+		}
+		Ok(())
+	}
+
+	/// The relay serves an inclusion constraint for the transaction in its slot
+	async fn check_relay_constraints(&self, tx_hash: TxHash, slot: u64) -> Result<()> {
+		let served = self.constraints_client.get_constraints(slot).await?;
+		let constrained = served
+			.iter()
+			.flat_map(|signed| &signed.message.constraints)
+			.filter(|constraint| constraint.constraint_type == INCLUSION_CONSTRAINT_TYPE)
+			.filter_map(|constraint| InclusionPayload::abi_decode(&constraint.payload).ok())
+			.any(|payload| payload.tx_hash().is_ok_and(|hash| hash == tx_hash));
+		match constrained {
+			true => Ok(()),
+			false => Err(eyre!(
+				"relay serves {} constraints message(s) for slot {}, none with the transaction",
+				served.len(),
+				slot
+			)),
+		}
+	}
+
+	/// The transaction was mined in the block of its slot
+	async fn check_inclusion(&self, tx_hash: TxHash, slot: u64) -> Result<()> {
+		let deadline_slot = slot + 1 + self.settle_slots;
+		let receipt = loop {
+			if let Some(receipt) = self.execution_client.get_transaction_receipt(tx_hash).await? {
+				break receipt;
+			}
+			if self.slot_clock.slot_deadline(deadline_slot) <= 0 {
+				return Err(eyre!("transaction {:?} not mined by slot {}", tx_hash, deadline_slot));
+			}
+			sleep(VERIFICATION_POLL_INTERVAL).await;
+		};
+
+		let block_number = receipt.block_number.ok_or_else(|| eyre!("receipt of {:?} has no block number", tx_hash))?;
+		let block = self
+			.execution_client
+			.get_block_by_number(BlockNumberOrTag::Number(block_number))
+			.await?
+			.ok_or_else(|| eyre!("block {} not found", block_number))?;
+		if block.header.timestamp != self.slot_clock.slot_timestamp(slot) {
+			return Err(eyre!(
+				"transaction {:?} mined in block {} at timestamp {}, not in slot {}",
+				tx_hash,
+				block_number,
+				block.header.timestamp,
+				slot
+			));
+		}
+		Ok(())
+	}
+}
+
+/// Requests of a scenario file, sent in order, each with the outcome the gateway must answer with
+#[derive(Debug, Deserialize)]
+struct Scenario {
+	steps: Vec<ScenarioStep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScenarioStep {
+	/// Name of the step in the report, its kind if not set
+	#[serde(default)]
+	name: Option<String>,
+	/// Request sent
+	kind: StepKind,
+	/// "accepted", a rejection reason such as "conflict" or "slot_elapsed", or a JSON-RPC error code
+	expect: Expectation,
+	/// Times the step is sent
+	#[serde(default = "default_step_repeat")]
+	repeat: u32,
+	/// Calldata size of an `oversized` transaction
+	#[serde(default = "default_oversized_bytes")]
+	size_bytes: usize,
+	/// Commitment type of an `unknown_type` request
+	#[serde(default = "default_unknown_commitment_type")]
+	commitment_type: u64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StepKind {
+	/// A valid inclusion request for the next slot the gateway offers
+	Inclusion,
+	/// The previous request sent again, same transaction hash
+	Duplicate,
+	/// A valid transaction for the slot before the current one
+	PastSlot,
+	/// A valid transaction for an upcoming slot the gateway is not delegated
+	UndelegatedSlot,
+	/// A transaction without priority fee
+	Underpriced,
+	/// A transfer carrying `size_bytes` of calldata within the gas of a plain transfer
+	Oversized,
+	/// A valid inclusion payload under `commitment_type`
+	UnknownType,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Expectation {
+	Code(i32),
+	Outcome(String),
+}
+
+impl Expectation {
+	fn matches(&self, result: &std::result::Result<SignedCommitment, CommitmentsClientError>) -> bool {
+		match (self, result) {
+			(Expectation::Outcome(outcome), Ok(_)) => outcome == ACCEPTED,
+			(Expectation::Outcome(outcome), Err(e)) => outcome == load_error_reason(e),
+			(Expectation::Code(code), Err(CommitmentsClientError::Rpc(e))) => *code == e.code(),
+			(Expectation::Code(_), _) => false,
+		}
+	}
+}
+
+impl std::fmt::Display for Expectation {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Expectation::Code(code) => write!(f, "error code {}", code),
+			Expectation::Outcome(outcome) => write!(f, "{}", outcome),
+		}
+	}
+}
+
+/// Outcome of a scenario request that the gateway accepted
+const ACCEPTED: &str = "accepted";
+
+/// Outcome of a scenario request as the report shows it
+fn outcome(result: &std::result::Result<SignedCommitment, CommitmentsClientError>) -> String {
+	match result {
+		Ok(_) => ACCEPTED.to_string(),
+		Err(CommitmentsClientError::Rpc(e)) => {
+			format!("{} (error code {}): {}", rejection_reason(e.code()), e.code(), e)
+		}
+		Err(e) => format!("{}: {}", load_error_reason(e), e),
+	}
+}
+
+fn default_step_repeat() -> u32 {
+	1
+}
+
+fn default_oversized_bytes() -> usize {
+	128 * 1024
+}
+
+fn default_unknown_commitment_type() -> u64 {
+	u64::MAX
+}
+
+/// Fee caps of the transfers sent for a slot
+#[derive(Debug, Clone, Copy)]
+struct TransferFees {
+	max_priority_fee_per_gas: u128,
+	max_fee_per_gas: u128,
+}
+
+/// Sign a transfer of 1 wei to a random recipient with `input` as calldata, returning encoded bytes and tx hash
+fn sign_transfer(
+	signer: &PrivateKeySigner,
+	chain_id: u64,
+	nonce: u64,
+	fees: TransferFees,
+	input: Bytes,
+) -> Result<(Bytes, TxHash)> {
 	// Create EIP-1559 transaction with random recipient
-	let tx = execution_client
-		.transaction_request()
+	let tx = TransactionRequest::default()
 		.from(signer.address())
 		.to(Address::random())
 		.value(U256::from(1))
-		.gas_limit(21000)
+		.input(input.into())
+		.gas_limit(TRANSFER_GAS)
 		.nonce(nonce)
-		.max_priority_fee_per_gas(max_fee_per_gas.into())
-		.max_fee_per_gas(max_fee_per_gas.into())
+		.max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
+		.max_fee_per_gas(fees.max_fee_per_gas)
 		.with_chain_id(chain_id)
 		.build_1559()?;
 
@@ -94,144 +516,735 @@ async fn generate_signed_transaction(
 	let mut encoded = Vec::new();
 	tx_envelope.encode_2718(&mut encoded);
 
-	Ok((Bytes::from(encoded), tx_hash, nonce))
+	Ok((Bytes::from(encoded), tx_hash))
 }
 
-/// Create a commitment request, returning the request and target slot
-fn create_commitment_request(config: &SpammerConfig, signed_tx: Bytes) -> Result<(CommitmentRequest, u64)> {
-	// Get current slot
-	let current_slot = current_slot_estimate(config.chain.genesis_time_sec());
-
-	// Send the commitment for a future slot
-	let target_slot = current_slot + SLOTS_IN_FUTURE_TO_SEND_COMMITMENT_REQUEST;
-
+/// Create a commitment request for a transaction in the target slot, naming a random slasher if none is given
+fn create_commitment_request(
+	slasher: Option<Address>,
+	signed_tx: Bytes,
+	target_slot: u64,
+) -> Result<CommitmentRequest> {
 	// Create inclusion payload
 	let inclusion_payload = InclusionPayload { slot: target_slot, signed_tx };
 
 	// ABI encode the payload
 	let payload = inclusion_payload.abi_encode().wrap_err("Failed to encode inclusion payload")?;
 
-	// Parse or generate slasher address
-	let slasher = if let Some(addr_str) = &config.slasher_address {
-		addr_str.parse::<Address>().wrap_err("Failed to parse slasher address")?
-	} else {
-		Address::random()
-	};
-
-	Ok((CommitmentRequest { commitment_type: INCLUSION_COMMITMENT_TYPE, payload, slasher }, target_slot))
+	let slasher = slasher.unwrap_or_else(Address::random);
+	Ok(CommitmentRequest { commitment_type: INCLUSION_COMMITMENT_TYPE, payload, slasher })
 }
 
-/// Send a commitment request via RPC
-async fn send_commitment_request(gateway_url: Url, request: &CommitmentRequest) -> Result<SignedCommitment> {
-	let commitments_client = CommitmentsHttpClient::new(gateway_url)?;
-	commitments_client.commitment_request(request.clone()).await
+/// Latency buckets of the load report in milliseconds, the last bucket holds the slower requests
+const LATENCY_BUCKETS_MS: [u64; 10] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+/// Latencies and errors of the commitment requests sent in load mode
+#[derive(Debug, Default)]
+struct LoadReport {
+	/// Latency of every accepted request
+	latencies: Vec<Duration>,
+	/// Latency of every failed request
+	failed_latencies: Vec<Duration>,
+	/// Failed requests by reason
+	errors: BTreeMap<&'static str, u64>,
 }
 
-async fn create_and_send_commitment_request(
-	config: &SpammerConfig,
-	signer: &PrivateKeySigner,
-) -> Result<(SignedCommitment, TxHash, u64, u64)> {
-	let gateway_url = Url::parse(format!("http://{}:{}", config.gateway_host, config.gateway_port).as_str())?;
-	let (tx, tx_hash, nonce) = generate_signed_transaction(config, signer).await?;
-	let (request, target_slot) = create_commitment_request(config, tx)?;
-	let response = send_commitment_request(gateway_url, &request).await?;
-	Ok((response, tx_hash, target_slot, nonce))
-}
-
-/// Run in one-shot mode
-async fn run_one_shot(config: &SpammerConfig, signer: &PrivateKeySigner) -> Result<()> {
-	match create_and_send_commitment_request(config, signer).await {
-		Ok((response, tx_hash, target_slot, nonce)) => {
-			info!("Commitment request successful!");
-			info!(
-				"Target slot: {}, nonce: {}, tx_hash: {:?}, request_hash: {:?}",
-				target_slot, nonce, tx_hash, response.commitment.request_hash
-			);
+impl LoadReport {
+	fn record(&mut self, latency: Duration, error: Option<&'static str>) {
+		match error {
+			None => self.latencies.push(latency),
+			Some(reason) => {
+				self.failed_latencies.push(latency);
+				*self.errors.entry(reason).or_default() += 1;
+			}
 		}
-		Err(e) => {
-			error!("✗ Failed to create and send commitment request: {}", e);
+	}
+
+	/// Count a request that failed before reaching the gateway
+	fn fail(&mut self, reason: &'static str) {
+		*self.errors.entry(reason).or_default() += 1;
+	}
+
+	/// Latency of the `p`th percentile of the accepted requests, the latencies must be sorted
+	fn percentile(&self, p: usize) -> Option<&Duration> {
+		self.latencies.get((self.latencies.len() * p / 100).min(self.latencies.len().saturating_sub(1)))
+	}
+
+	fn log(&mut self, elapsed: Duration) {
+		let failed: u64 = self.errors.values().sum();
+		let total = self.latencies.len() as u64 + failed;
+		info!(
+			"Load report: {} requests in {:.1}s ({:.1} per second), {} accepted, {} failed",
+			total,
+			elapsed.as_secs_f64(),
+			total as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+			self.latencies.len(),
+			failed
+		);
+
+		self.latencies.sort();
+		if let (Some(p50), Some(p90), Some(p99), Some(max)) =
+			(self.percentile(50), self.percentile(90), self.percentile(99), self.latencies.last())
+		{
+			info!("Accepted latency: p50 {:?}, p90 {:?}, p99 {:?}, max {:?}", p50, p90, p99, max);
+		}
+
+		let mut lower_ms = 0;
+		for upper_ms in LATENCY_BUCKETS_MS.iter().copied().map(Some).chain([None]) {
+			let in_bucket = |latency: &&Duration| {
+				let ms = latency.as_millis() as u64;
+				ms >= lower_ms && upper_ms.is_none_or(|upper| ms < upper)
+			};
+			let accepted = self.latencies.iter().filter(in_bucket).count();
+			let failed = self.failed_latencies.iter().filter(in_bucket).count();
+			let label = match upper_ms {
+				Some(upper) => format!("{}-{}ms", lower_ms, upper),
+				None => format!(">={}ms", lower_ms),
+			};
+			info!("  {:>12}: {} accepted, {} failed", label, accepted, failed);
+			lower_ms = upper_ms.unwrap_or_default();
+		}
+
+		for (reason, count) in &self.errors {
+			info!("Errors {}: {}", reason, count);
 		}
 	}
-	Ok(())
 }
 
-/// Run in continuous mode (one transaction per slot)
-async fn run_continuous(config: &SpammerConfig, signer: &PrivateKeySigner) -> Result<()> {
-	info!("Running in continuous mode (one transaction per slot)");
+/// Report label of a failed commitment request
+fn load_error_reason(error: &CommitmentsClientError) -> &'static str {
+	match error {
+		CommitmentsClientError::Connect(_) => "connect",
+		CommitmentsClientError::Transport(_) => "transport",
+		CommitmentsClientError::Timeout => "timeout",
+		CommitmentsClientError::Rpc(error) => rejection_reason(error.code()),
+		CommitmentsClientError::InvalidResponse(_) => "invalid_response",
+	}
+}
+
+/// A funded sender of load mode, its nonces are handed out in order to the requests it signs
+struct LoadSender {
+	signer: PrivateKeySigner,
+	next_nonce: AtomicU64,
+}
+
+/// Target slot and fees of the requests sent during one slot of load mode
+#[derive(Debug, Clone, Copy)]
+struct LoadTarget {
+	slot: u64,
+	target_slot: u64,
+	fees: TransferFees,
+}
+
+/// Nonce of the next transaction, reserved by commitments the chain has not caught up with yet
+#[derive(Debug, Clone, Copy)]
+struct ReservedNonce {
+	/// Nonce following the last committed transaction
+	next: u64,
+	/// Target slot of the last committed transaction, after which an unused reservation is given up
+	until_slot: u64,
+}
+
+/// Nonce of the next transaction given the sender's pending nonce: the reserved one while the last commitment's slot
+/// has not passed, after which nonces the chain did not use are free again
+fn next_nonce(pending: u64, reserved: Option<ReservedNonce>, current_slot: u64) -> u64 {
+	match reserved {
+		Some(reserved) if current_slot <= reserved.until_slot => pending.max(reserved.next),
+		_ => pending,
+	}
+}
+
+/// First slot from `earliest` offering inclusion commitments on the chain with room for a transfer
+fn first_open_slot(slots: &[SlotInfo], earliest: u64, chain_id: u64) -> Option<u64> {
+	slots
+		.iter()
+		.filter(|info| info.slot >= earliest)
+		.filter(|info| info.remaining_gas.is_none_or(|gas| gas >= TRANSFER_GAS))
+		.filter(|info| {
+			info.offerings.iter().any(|offering| {
+				offering.chain_id == chain_id && offering.commitment_types.contains(&INCLUSION_COMMITMENT_TYPE)
+			})
+		})
+		.map(|info| info.slot)
+		.min()
+}
+
+/// Signs transactions from the sender's on-chain state and requests commitments for them from the gateway
+struct Spammer {
+	config: SpammerConfig,
+	signer: PrivateKeySigner,
+	execution_client: DynProvider<Ethereum>,
+	commitments_client: CommitmentsHttpClient,
+	slot_clock: Arc<SlotClock>,
+	reserved_nonce: Option<ReservedNonce>,
+	verifier: Option<Verifier>,
+}
+
+impl Spammer {
+	fn new(config: SpammerConfig, signer: PrivateKeySigner) -> Result<Self> {
+		let execution_client_url =
+			Url::parse(format!("http://{}:{}", config.execution_client_host, config.execution_client_port).as_str())?;
+		let execution_client = ProviderBuilder::new().network::<Ethereum>().connect_http(execution_client_url).erased();
+
+		let gateway_url = Url::parse(format!("http://{}:{}", config.gateway_host, config.gateway_port).as_str())?;
+		let mut client_config = CommitmentsClientConfig::new(gateway_url);
+		client_config.endpoints.extend(config.fallback_gateway_urls.iter().cloned());
+		let commitments_client = CommitmentsHttpClient::new(client_config)?;
+
+		let slot_clock = Arc::new(SlotClock::new(&config.chain));
+		let verifier = match (&config.verification, config.committer_address) {
+			(Some(verification), Some(committer)) => Some(Verifier {
+				chain: config.chain,
+				committer,
+				execution_client: execution_client.clone(),
+				commitments_client: commitments_client.clone(),
+				constraints_client: HttpConstraintsClient::new(
+					verification.relay_host.clone(),
+					verification.relay_port,
+					verification.relay_api_key.clone(),
+				)?,
+				slot_clock: Arc::clone(&slot_clock),
+				settle_slots: verification.settle_slots,
+				summary: Arc::default(),
+			}),
+			_ => None,
+		};
+		Ok(Self { config, signer, execution_client, commitments_client, slot_clock, reserved_nonce: None, verifier })
+	}
+
+	/// Log the sender's chain state, checking the execution client serves the configured chain
+	async fn check_sender(&self) -> Result<()> {
+		let chain_id = self.execution_client.get_chain_id().await.wrap_err("Failed to get the chain id")?;
+		if chain_id != self.config.chain.id().to::<u64>() {
+			return Err(eyre!(
+				"Execution client is on chain {}, the config is for chain {}",
+				chain_id,
+				self.config.chain.id()
+			));
+		}
+		let address = self.signer.address();
+		let balance = self.execution_client.get_balance(address).await?;
+		let nonce = self.execution_client.get_transaction_count(address).pending().await?;
+		info!("Sender {:?}: balance {} wei, pending nonce {}", address, balance, nonce);
+		Ok(())
+	}
+
+	/// Nonce of the next transaction: the sender's pending nonce, unless earlier commitments not yet on chain reserved
+	/// the following ones
+	async fn next_nonce(&self) -> Result<u64> {
+		let pending = self.execution_client.get_transaction_count(self.signer.address()).pending().await?;
+		Ok(next_nonce(pending, self.reserved_nonce, self.slot_clock.current_slot()))
+	}
+
+	/// First slot at least MIN_SLOTS_AHEAD ahead that the gateway offers inclusion commitments on with room for a
+	/// transfer
+	async fn target_slot(&self) -> Result<u64> {
+		let earliest = self.slot_clock.current_slot() + MIN_SLOTS_AHEAD;
+		let response = self.commitments_client.slots().await?;
+		first_open_slot(&response.slots, earliest, self.config.chain.id().to::<u64>())
+			.ok_or_else(|| eyre!("Gateway offers no inclusion commitments from slot {}", earliest))
+	}
+
+	/// Fee caps of a transfer for the target slot at the current fee market
+	async fn transfer_fees(&self, target_slot: u64) -> Result<TransferFees> {
+		// Price the transfer like the gateway does, at the highest tip it can quote for the slot
+		let market = pricing::fetch_market_fees(&self.execution_client).await?;
+		let demand = SlotDemand {
+			slots_ahead: target_slot.saturating_sub(self.slot_clock.current_slot()),
+			committed_gas: 0,
+			gas_budget: 0,
+		};
+		let quote = pricing::price_commitment(TRANSFER_GAS, &market, &demand, self.config.min_tip_per_gas_wei);
+		let fees = TransferFees {
+			max_priority_fee_per_gas: quote.tip_per_gas,
+			max_fee_per_gas: quote.max_base_fee_per_gas as u128 + quote.tip_per_gas,
+		};
+		info!(
+			"Base fee: {} wei, max_priority_fee_per_gas: {} wei, max_fee_per_gas: {} wei",
+			market.pending_base_fee_per_gas, fees.max_priority_fee_per_gas, fees.max_fee_per_gas
+		);
+		Ok(fees)
+	}
+
+	/// Configured slasher, random per request if not set
+	fn slasher(&self) -> Result<Option<Address>> {
+		self.config
+			.slasher_address
+			.as_ref()
+			.map(|address| address.parse::<Address>().wrap_err("Failed to parse slasher address"))
+			.transpose()
+	}
+
+	async fn create_and_send_commitment_request(&mut self) -> Result<(SignedCommitment, TxHash, u64, u64)> {
+		let target_slot = self.target_slot().await?;
+		let nonce = self.next_nonce().await?;
+		let fees = self.transfer_fees(target_slot).await?;
+		let (tx, tx_hash) = sign_transfer(&self.signer, self.config.chain.id().to::<u64>(), nonce, fees, Bytes::new())?;
+		let request = create_commitment_request(self.slasher()?, tx, target_slot)?;
+		let response = self.commitments_client.commitment_request(request).await?;
+		// The verifier checks the signature with the rest of the commitment
+		if self.verifier.is_none()
+			&& let Some(committer) = &self.config.committer_address
+		{
+			verify_ecdsa_commitment(self.config.chain, &response, committer)
+				.wrap_err("Invalid commitment signature")?;
+		}
+
+		self.reserve_nonce(nonce, target_slot);
+		Ok((response, tx_hash, target_slot, nonce))
+	}
+
+	/// The transaction only reaches the chain with its slot, the next one must not reuse its nonce until then
+	fn reserve_nonce(&mut self, nonce: u64, target_slot: u64) {
+		self.reserved_nonce = Some(ReservedNonce { next: nonce + 1, until_slot: target_slot });
+	}
+
+	/// Run in one-shot mode
+	async fn run_one_shot(&mut self) -> Result<()> {
+		match self.create_and_send_commitment_request().await {
+			Ok((response, tx_hash, target_slot, nonce)) => {
+				info!("Commitment request successful!");
+				info!(
+					"Target slot: {}, nonce: {}, tx_hash: {:?}, request_hash: {:?}",
+					target_slot, nonce, tx_hash, response.commitment.request_hash
+				);
+				if let Some(verifier) = &self.verifier
+					&& !verifier.verify(&response, tx_hash, target_slot).await
+				{
+					return Err(eyre!("Commitment {:?} failed verification", response.commitment.request_hash));
+				}
+			}
+			Err(e) => {
+				error!("✗ Failed to create and send commitment request: {}", e);
+			}
+		}
+		Ok(())
+	}
+
+	/// Run in continuous mode (one transaction per slot)
+	async fn run_continuous(&mut self) -> Result<()> {
+		info!("Running in continuous mode (one transaction per slot)");
+
+		let mut last_sent_slot: Option<u64> = None;
+		let mut shutdown = Box::pin(common::utils::wait_for_signal());
+
+		loop {
+			let current_slot = self.slot_clock.current_slot();
 
-	let mut last_sent_slot: Option<u64> = None;
-	let mut shutdown = Box::pin(common::utils::wait_for_signal());
+			// Only send if we haven't sent for this slot yet
+			if last_sent_slot != Some(current_slot) {
+				match self.create_and_send_commitment_request().await {
+					Ok((response, tx_hash, target_slot, nonce)) => {
+						info!(
+							"Sent at slot {}, target slot {}: nonce {}, tx_hash {:?}, request_hash {:?}",
+							current_slot, target_slot, nonce, tx_hash, response.commitment.request_hash
+						);
+						last_sent_slot = Some(current_slot);
 
-	loop {
-		let current_slot = lookahead::utils::current_slot(&config.chain);
+						// Verification lasts beyond the target slot, the next commitments are sent meanwhile
+						if let Some(verifier) = self.verifier.clone() {
+							tokio::spawn(async move { verifier.verify(&response, tx_hash, target_slot).await });
+						}
+					}
+					Err(e) => {
+						error!("Slot {}: Failed: {}", current_slot, e);
+						// Still mark as sent to avoid retry spam on persistent errors
+						last_sent_slot = Some(current_slot);
+					}
+				}
+			}
 
-		// Only send if we haven't sent for this slot yet
-		if last_sent_slot != Some(current_slot) {
-			match create_and_send_commitment_request(config, signer).await {
-				Ok((response, tx_hash, target_slot, nonce)) => {
-					info!(
-						"Sent at slot {}, target slot {}: nonce {}, tx_hash {:?}, request_hash {:?}",
-						current_slot, target_slot, nonce, tx_hash, response.commitment.request_hash
-					);
-					last_sent_slot = Some(current_slot);
+			// Wait until the next slot
+			let sleep_ms = lookahead::utils::time_until_next_slot_ms(&self.config.chain);
+			let sleep_duration = Duration::from_millis(sleep_ms.max(100) as u64);
+
+			tokio::select! {
+				_ = tokio::time::sleep(sleep_duration) => {}
+				_ = &mut shutdown => {
+					info!("Shutdown signal received, stopping spammer loop");
+					break;
 				}
-				Err(e) => {
-					error!("Slot {}: Failed: {}", current_slot, e);
-					// Still mark as sent to avoid retry spam on persistent errors
-					last_sent_slot = Some(current_slot);
+			}
+		}
+
+		if let Some(verifier) = &self.verifier {
+			let summary = verifier.summary.lock().expect("verification summary lock poisoned");
+			info!("Verification summary, commitments still in flight excluded: {}", summary);
+		}
+		Ok(())
+	}
+
+	/// Target slot and fees of the requests sent in the current slot
+	async fn load_target(&self) -> Result<LoadTarget> {
+		let slot = self.slot_clock.current_slot();
+		let target_slot = self.target_slot().await?;
+		let fees = self.transfer_fees(target_slot).await?;
+		Ok(LoadTarget { slot, target_slot, fees })
+	}
+
+	/// Run in load mode: send requests at the configured rate from all senders, then report latencies and errors
+	async fn run_load(&self, signers: Vec<PrivateKeySigner>) -> Result<()> {
+		let load = self.config.load.clone().ok_or_else(|| eyre!("load mode needs a [load] config"))?;
+		info!(
+			"Running in load mode: {} requests per second from {} sender(s), {} in flight at most, for {}s",
+			load.requests_per_second,
+			signers.len(),
+			load.concurrency,
+			load.duration_seconds
+		);
+
+		let mut senders = Vec::with_capacity(signers.len());
+		for signer in signers {
+			let nonce = self.execution_client.get_transaction_count(signer.address()).pending().await?;
+			senders.push(Arc::new(LoadSender { signer, next_nonce: AtomicU64::new(nonce) }));
+		}
+		let chain_id = self.config.chain.id().to::<u64>();
+		let slasher = self.slasher()?;
+
+		let permits = Arc::new(Semaphore::new(load.concurrency));
+		let report = Arc::new(Mutex::new(LoadReport::default()));
+		let mut requests = JoinSet::new();
+		let mut shutdown = Box::pin(common::utils::wait_for_signal());
+		let mut target: Option<LoadTarget> = None;
+		let mut sent = 0;
+
+		let start = Instant::now();
+		let end = start + Duration::from_secs(load.duration_seconds);
+		let mut next_request = start;
+		while next_request < end {
+			tokio::select! {
+				_ = sleep_until(next_request) => {}
+				_ = &mut shutdown => {
+					info!("Shutdown signal received, stopping load");
+					break;
 				}
 			}
+			next_request += Duration::from_secs_f64(1.0 / load.rate(start.elapsed()));
+
+			// Every request of a slot shares its target and fees, so the gateway's slots() and the fee history are
+			// queried once per slot
+			if target.is_none_or(|target| target.slot != self.slot_clock.current_slot()) {
+				target = match self.load_target().await {
+					Ok(target) => Some(target),
+					Err(e) => {
+						warn!("Failed to get a target slot: {}", e);
+						report.lock().expect("load report lock poisoned").fail("target_slot");
+						continue;
+					}
+				};
+			}
+			let Some(target) = target else { continue };
+
+			// Waits when the gateway cannot keep up, the achieved rate is in the report
+			let permit = Arc::clone(&permits).acquire_owned().await?;
+			let sender = Arc::clone(&senders[sent % senders.len()]);
+			sent += 1;
+			let commitments_client = self.commitments_client.clone();
+			let report = Arc::clone(&report);
+			requests.spawn(async move {
+				let _permit = permit;
+				// Rejected requests leave a gap in the sender's nonces, load transactions benchmark the RPC path and
+				// are not meant to be included
+				let nonce = sender.next_nonce.fetch_add(1, Ordering::Relaxed);
+				let request = sign_transfer(&sender.signer, chain_id, nonce, target.fees, Bytes::new())
+					.and_then(|(tx, _)| create_commitment_request(slasher, tx, target.target_slot));
+				let request = match request {
+					Ok(request) => request,
+					Err(e) => {
+						warn!("Failed to create a commitment request: {}", e);
+						report.lock().expect("load report lock poisoned").fail("signing");
+						return;
+					}
+				};
+
+				let started = Instant::now();
+				let result = commitments_client.commitment_request(request).await;
+				let error = result.as_ref().err().map(load_error_reason);
+				report.lock().expect("load report lock poisoned").record(started.elapsed(), error);
+			});
 		}
 
-		// Wait until the next slot
-		let sleep_ms = lookahead::utils::time_until_next_slot_ms(&config.chain);
-		let sleep_duration = Duration::from_millis(sleep_ms.max(100) as u64);
+		// Requests in flight are part of the report
+		while requests.join_next().await.is_some() {}
+		report.lock().expect("load report lock poisoned").log(start.elapsed());
+		Ok(())
+	}
+
+	/// First upcoming slot of the lookahead window the gateway does not offer commitments on
+	async fn undelegated_slot(&self) -> Result<u64> {
+		let earliest = self.slot_clock.current_slot() + MIN_SLOTS_AHEAD;
+		let offered =
+			self.commitments_client.slots().await?.slots.into_iter().map(|info| info.slot).collect::<Vec<_>>();
+		(earliest..earliest + LOOKAHEAD_WINDOW_SIZE)
+			.find(|slot| !offered.contains(slot))
+			.ok_or_else(|| eyre!("Gateway is delegated every slot of the lookahead window"))
+	}
 
-		tokio::select! {
-			_ = tokio::time::sleep(sleep_duration) => {}
-			_ = &mut shutdown => {
-				info!("Shutdown signal received, stopping spammer loop");
-				break;
+	/// Build the request of a scenario step, returns it with its nonce and slot if it is a valid inclusion
+	async fn scenario_request(
+		&self,
+		step: &ScenarioStep,
+		previous: Option<&CommitmentRequest>,
+	) -> Result<(CommitmentRequest, Option<(u64, u64)>)> {
+		let slot = match step.kind {
+			StepKind::Duplicate => {
+				let previous = previous.ok_or_else(|| eyre!("No previous request to duplicate"))?;
+				return Ok((previous.clone(), None));
+			}
+			StepKind::PastSlot => self.slot_clock.current_slot().saturating_sub(1),
+			StepKind::UndelegatedSlot => self.undelegated_slot().await?,
+			_ => self.target_slot().await?,
+		};
+		let nonce = self.next_nonce().await?;
+		let mut fees = self.transfer_fees(slot).await?;
+		let mut input = Bytes::new();
+		match step.kind {
+			StepKind::Underpriced => {
+				fees.max_fee_per_gas -= fees.max_priority_fee_per_gas;
+				fees.max_priority_fee_per_gas = 0;
 			}
+			StepKind::Oversized => input = Bytes::from(vec![0xff; step.size_bytes]),
+			_ => {}
 		}
+
+		let (tx, _) = sign_transfer(&self.signer, self.config.chain.id().to::<u64>(), nonce, fees, input)?;
+		let mut request = create_commitment_request(self.slasher()?, tx, slot)?;
+		let valid = match step.kind {
+			StepKind::UnknownType => {
+				request.commitment_type = step.commitment_type;
+				None
+			}
+			StepKind::Inclusion => Some((nonce, slot)),
+			_ => None,
+		};
+		Ok((request, valid))
+	}
+
+	/// Run in scenario mode: send the steps of the scenario file and check the gateway's answers
+	async fn run_scenario(&mut self) -> Result<()> {
+		let path = self.config.scenario_file.clone().ok_or_else(|| eyre!("scenario mode needs a scenario_file"))?;
+		let scenario: Scenario = toml::from_str(
+			&std::fs::read_to_string(&path).wrap_err_with(|| format!("Failed to read {}", path.display()))?,
+		)
+		.wrap_err_with(|| format!("Failed to parse scenario {}", path.display()))?;
+		info!("Running scenario {} with {} step(s)", path.display(), scenario.steps.len());
+
+		let mut previous: Option<CommitmentRequest> = None;
+		let (mut passed, mut failed) = (0, 0);
+		for step in &scenario.steps {
+			let name = step.name.clone().unwrap_or_else(|| format!("{:?}", step.kind));
+			for _ in 0..step.repeat {
+				let (request, valid) = match self.scenario_request(step, previous.as_ref()).await {
+					Ok(request) => request,
+					Err(e) => {
+						error!("FAIL {}: could not build the request: {}", name, e);
+						failed += 1;
+						continue;
+					}
+				};
+				let result = self.commitments_client.commitment_request(request.clone()).await;
+				if let (Ok(_), Some((nonce, slot))) = (&result, valid) {
+					self.reserve_nonce(nonce, slot);
+				}
+				previous = Some(request);
+
+				if step.expect.matches(&result) {
+					info!("PASS {}: {}", name, outcome(&result));
+					passed += 1;
+				} else {
+					error!("FAIL {}: expected {}, got {}", name, step.expect, outcome(&result));
+					failed += 1;
+				}
+			}
+		}
+
+		info!("Scenario {}: {} passed, {} failed", path.display(), passed, failed);
+		if failed > 0 {
+			return Err(eyre!("{} scenario request(s) did not get the expected answer", failed));
+		}
+		Ok(())
 	}
-	Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-	// Setup logging
-	common::logging::setup_logging(&std::env::var("RUST_LOG").expect("RUST_LOG environment variable not set"))?;
+	let args = Args::parse();
+	if let Some(ServiceCommand::Version) = args.command {
+		println!("spammer {}", env!("CARGO_PKG_VERSION"));
+		return Ok(());
+	}
 
-	let sender_private_key =
-		std::env::var("SENDER_PRIVATE_KEY").expect("SENDER_PRIVATE_KEY environment variable not set");
+	let config_path = args.service.config_path("CONFIG_PATH")?;
+	if let Some(ServiceCommand::Config(ConfigCommand::Validate)) = args.command {
+		config::load_file::<SpammerConfig>(&config_path, SPAMMER_ENV_PREFIX)?;
+		println!("Spammer config {} is valid", config_path.display());
+		return Ok(());
+	}
 
-	let config_path = std::env::var("CONFIG_PATH").expect("CONFIG_PATH environment variable not set");
+	// Load configuration
+	let config: SpammerConfig = config::load_file(&config_path, SPAMMER_ENV_PREFIX)?;
 
-	info!("Loading configuration from: {}", config_path);
+	// Setup logging
+	let _logging = common::logging::setup_logging_with_filter(&args.service.log_filter(), &config.logging)?;
 
-	// Load configuration
-	let config_content = std::fs::read_to_string(config_path).wrap_err("Failed to read config file")?;
-	let config: SpammerConfig = toml::from_str(&config_content).wrap_err("Failed to parse config file")?;
+	// Load mode spreads its requests over SENDER_PRIVATE_KEYS, comma separated, when it is set
+	let sender_private_keys = match std::env::var("SENDER_PRIVATE_KEYS") {
+		Ok(keys) if config.mode == "load" => keys,
+		_ => std::env::var("SENDER_PRIVATE_KEY").wrap_err("SENDER_PRIVATE_KEY environment variable not set")?,
+	};
 
-	info!("Configuration loaded successfully");
+	info!("Configuration loaded from {}", config_path.display());
 	info!("  Mode: {}", config.mode);
 	info!("  Gateway URL: {}:{}", config.gateway_host, config.gateway_port);
 	info!("  Chain ID: {}", config.chain.id());
 
-	// Parse sender private key
-	let signer = sender_private_key.parse::<PrivateKeySigner>().wrap_err("Failed to parse sender private key")?;
-	let sender_address = signer.address();
-	info!("Sender address: {:?}", sender_address);
+	// Parse sender private keys
+	let signers = sender_private_keys
+		.split(',')
+		.map(|key| key.trim().parse::<PrivateKeySigner>().wrap_err("Failed to parse sender private key"))
+		.collect::<Result<Vec<_>>>()?;
+	for signer in &signers {
+		info!("Sender address: {:?}", signer.address());
+	}
+
+	let mode = config.mode.clone();
+	let mut spammer = Spammer::new(config, signers[0].clone())?;
+	spammer.check_sender().await?;
+
+	if args.service.dry_run {
+		info!(
+			"Dry run: spammer config, sender key and execution client are valid, exiting without sending any request"
+		);
+		return Ok(());
+	}
 
 	// Run based on mode
-	match config.mode.as_str() {
-		"one-shot" => run_one_shot(&config, &signer).await?,
-		"continuous" => run_continuous(&config, &signer).await?,
-		_ => {
-			return Err(eyre::eyre!("Invalid mode '{}'. Must be 'one-shot' or 'continuous'", config.mode));
-		}
+	match mode.as_str() {
+		"one-shot" => spammer.run_one_shot().await?,
+		"continuous" => spammer.run_continuous().await?,
+		"load" => spammer.run_load(signers).await?,
+		"scenario" => spammer.run_scenario().await?,
+		_ => unreachable!("mode is validated when the config is loaded"),
 	}
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::primitives::{B256, Signature};
+	use commitments::error::CommitmentsRpcError;
+	use commitments::types::{Commitment, Offering};
+
+	fn load(ramp_up_seconds: u64, ramp_up_profile: RampProfile) -> LoadConfig {
+		LoadConfig {
+			requests_per_second: 10.0,
+			concurrency: 16,
+			duration_seconds: 60,
+			ramp_up_seconds,
+			ramp_up_profile,
+			ramp_up_steps: 4,
+		}
+	}
+
+	#[test]
+	fn test_load_rate_ramps_up() {
+		let linear = load(10, RampProfile::Linear);
+		assert_eq!(linear.rate(Duration::ZERO), 1.0);
+		assert_eq!(linear.rate(Duration::from_secs(5)), 5.0);
+		assert_eq!(linear.rate(Duration::from_secs(10)), 10.0);
+		assert_eq!(linear.rate(Duration::from_secs(30)), 10.0);
+
+		let step = load(8, RampProfile::Step);
+		assert_eq!(step.rate(Duration::ZERO), 2.5);
+		assert_eq!(step.rate(Duration::from_secs(2)), 5.0);
+		assert_eq!(step.rate(Duration::from_millis(7_900)), 10.0);
+
+		// Without a ramp-up the full rate applies from the start
+		assert_eq!(load(0, RampProfile::Linear).rate(Duration::ZERO), 10.0);
+	}
+
+	#[test]
+	fn test_load_report_percentiles() {
+		let mut report = LoadReport::default();
+		assert_eq!(report.percentile(50), None);
+
+		for ms in (1..=100).rev() {
+			report.record(Duration::from_millis(ms), None);
+		}
+		report.record(Duration::from_millis(1), Some("timeout"));
+		report.fail("connect");
+		report.latencies.sort();
+
+		assert_eq!(report.percentile(50), Some(&Duration::from_millis(51)));
+		assert_eq!(report.percentile(90), Some(&Duration::from_millis(91)));
+		assert_eq!(report.percentile(99), Some(&Duration::from_millis(100)));
+		assert_eq!(report.percentile(100), Some(&Duration::from_millis(100)));
+		assert_eq!(report.failed_latencies.len(), 1);
+		assert_eq!(report.errors, BTreeMap::from([("connect", 1), ("timeout", 1)]));
+	}
+
+	#[test]
+	fn test_expectation_matches() {
+		let accepted = Ok(SignedCommitment {
+			commitment: Commitment {
+				commitment_type: INCLUSION_COMMITMENT_TYPE,
+				payload: Bytes::new(),
+				request_hash: B256::ZERO,
+				slasher: Address::ZERO,
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: Signature::test_signature(),
+		});
+		let over_capacity = Err(CommitmentsClientError::Rpc(CommitmentsRpcError::OverCapacity("full".to_string())));
+		let timeout = Err(CommitmentsClientError::Timeout);
+
+		assert!(Expectation::Outcome(ACCEPTED.to_string()).matches(&accepted));
+		assert!(!Expectation::Outcome(ACCEPTED.to_string()).matches(&over_capacity));
+		assert!(Expectation::Outcome("over_capacity".to_string()).matches(&over_capacity));
+		assert!(Expectation::Outcome("timeout".to_string()).matches(&timeout));
+
+		let code = CommitmentsRpcError::OverCapacity(String::new()).code();
+		assert!(Expectation::Code(code).matches(&over_capacity));
+		assert!(!Expectation::Code(code + 1).matches(&over_capacity));
+		assert!(!Expectation::Code(code).matches(&accepted));
+		assert!(!Expectation::Code(code).matches(&timeout));
+	}
+
+	#[test]
+	fn test_next_nonce_follows_reservations_until_their_slot() {
+		let reserved = Some(ReservedNonce { next: 8, until_slot: 100 });
+		assert_eq!(next_nonce(5, None, 90), 5);
+		assert_eq!(next_nonce(5, reserved, 90), 8);
+		assert_eq!(next_nonce(5, reserved, 100), 8);
+		// The chain caught up with the reservation
+		assert_eq!(next_nonce(9, reserved, 90), 9);
+		// The reservation's slot passed without its transaction
+		assert_eq!(next_nonce(5, reserved, 101), 5);
+	}
+
+	#[test]
+	fn test_first_open_slot() {
+		let slot = |slot: u64, chain_id: u64, commitment_types: Vec<u64>, remaining_gas: Option<u64>| SlotInfo {
+			slot,
+			offerings: vec![Offering { chain_id, commitment_types }],
+			remaining_gas,
+		};
+		let inclusion = vec![INCLUSION_COMMITMENT_TYPE];
+		let slots = vec![
+			slot(9, 1, inclusion.clone(), None),
+			slot(14, 1, inclusion.clone(), None),
+			slot(10, 2, inclusion.clone(), None),
+			slot(11, 1, vec![INCLUSION_COMMITMENT_TYPE + 1], None),
+			slot(12, 1, inclusion.clone(), Some(TRANSFER_GAS - 1)),
+			slot(13, 1, inclusion.clone(), Some(TRANSFER_GAS)),
+		];
+
+		assert_eq!(first_open_slot(&slots, 10, 1), Some(13));
+		assert_eq!(first_open_slot(&slots, 14, 1), Some(14));
+		assert_eq!(first_open_slot(&slots, 10, 2), Some(10));
+		assert_eq!(first_open_slot(&slots, 15, 1), None);
+	}
+}
@@ -0,0 +1,408 @@
+//! Python bindings of the Fabric core types and the URC signing roots.
+//!
+//! Relay and gateway tooling written in Python builds the same `CommitmentRequest`, `Commitment`,
+//! `SignedCommitment` and `Delegation` as the Rust services and hashes them with the Solidity compatible encoding
+//! of the `urc` crate instead of reimplementing it. Bytes, addresses, hashes, keys and signatures are `0x` hex
+//! strings, and every type converts to and from the JSON the services exchange. Signatures are over the
+//! Commit-Boost signing root of a chain, given by name or as the JSON of a custom chain. Build the module with
+//! `maturin develop -m crates/bindings/Cargo.toml`.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use alloy::primitives::hex;
+use commitments::types::{Commitment, CommitmentRequest, SignedCommitment};
+use constraints::types::{ConstraintsMessage, Delegation};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use urc::utils;
+use verify::Chain;
+
+fn parse<T>(name: &str, value: &str) -> PyResult<T>
+where
+	T: FromStr,
+	T::Err: Display,
+{
+	T::from_str(value).map_err(|e| PyValueError::new_err(format!("invalid {name} {value}: {e}")))
+}
+
+fn decode_json<T: DeserializeOwned>(json: &str) -> PyResult<T> {
+	serde_json::from_str(json).map_err(|e| PyValueError::new_err(format!("invalid JSON: {e}")))
+}
+
+/// A known chain by name, e.g. `Holesky`, or a custom chain as its JSON
+fn parse_chain(chain: &str) -> PyResult<Chain> {
+	serde_json::from_str(chain)
+		.or_else(|_| serde_json::from_value(serde_json::Value::String(chain.to_string())))
+		.map_err(|e| PyValueError::new_err(format!("invalid chain {chain}: {e}")))
+}
+
+fn encode_json<T: Serialize>(value: &T) -> PyResult<String> {
+	serde_json::to_string(value).map_err(|e| PyValueError::new_err(format!("failed to encode JSON: {e}")))
+}
+
+/// Request for a new SignedCommitment
+#[pyclass(name = "CommitmentRequest", module = "fabric_bindings", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyCommitmentRequest(pub CommitmentRequest);
+
+#[pymethods]
+impl PyCommitmentRequest {
+	#[new]
+	fn new(commitment_type: u64, payload: &str, slasher: &str) -> PyResult<Self> {
+		Ok(Self(CommitmentRequest {
+			commitment_type,
+			payload: parse("payload", payload)?,
+			slasher: parse("slasher", slasher)?,
+		}))
+	}
+
+	#[staticmethod]
+	fn from_json(json: &str) -> PyResult<Self> {
+		Ok(Self(decode_json(json)?))
+	}
+
+	fn to_json(&self) -> PyResult<String> {
+		encode_json(&self.0)
+	}
+
+	#[getter]
+	fn commitment_type(&self) -> u64 {
+		self.0.commitment_type
+	}
+
+	#[getter]
+	fn payload(&self) -> String {
+		self.0.payload.to_string()
+	}
+
+	#[getter]
+	fn slasher(&self) -> String {
+		self.0.slasher.to_string()
+	}
+
+	fn __repr__(&self) -> String {
+		format!("{:?}", self.0)
+	}
+}
+
+/// Commitment to a request, as signed by the committer
+#[pyclass(name = "Commitment", module = "fabric_bindings", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyCommitment(pub Commitment);
+
+#[pymethods]
+impl PyCommitment {
+	#[new]
+	fn new(commitment_type: u64, payload: &str, request_hash: &str, slasher: &str) -> PyResult<Self> {
+		Ok(Self(Commitment {
+			commitment_type,
+			payload: parse("payload", payload)?,
+			request_hash: parse("request hash", request_hash)?,
+			slasher: parse("slasher", slasher)?,
+		}))
+	}
+
+	/// The commitment a gateway makes for `request`, committing to the request's signing root
+	#[staticmethod]
+	fn from_request(request: &PyCommitmentRequest) -> Self {
+		let request = &request.0;
+		Self(Commitment {
+			commitment_type: request.commitment_type,
+			payload: request.payload.clone(),
+			request_hash: utils::get_commitment_request_signing_root(request),
+			slasher: request.slasher,
+		})
+	}
+
+	#[staticmethod]
+	fn from_json(json: &str) -> PyResult<Self> {
+		Ok(Self(decode_json(json)?))
+	}
+
+	fn to_json(&self) -> PyResult<String> {
+		encode_json(&self.0)
+	}
+
+	#[getter]
+	fn commitment_type(&self) -> u64 {
+		self.0.commitment_type
+	}
+
+	#[getter]
+	fn payload(&self) -> String {
+		self.0.payload.to_string()
+	}
+
+	#[getter]
+	fn request_hash(&self) -> String {
+		self.0.request_hash.to_string()
+	}
+
+	#[getter]
+	fn slasher(&self) -> String {
+		self.0.slasher.to_string()
+	}
+
+	fn __repr__(&self) -> String {
+		format!("{:?}", self.0)
+	}
+}
+
+/// A commitment with its ECDSA signature
+#[pyclass(name = "SignedCommitment", module = "fabric_bindings", frozen)]
+#[derive(Debug, Clone)]
+pub struct PySignedCommitment(pub SignedCommitment);
+
+#[pymethods]
+impl PySignedCommitment {
+	#[new]
+	fn new(commitment: &PyCommitment, nonce: u64, signing_id: &str, signature: &str) -> PyResult<Self> {
+		Ok(Self(SignedCommitment {
+			commitment: commitment.0.clone(),
+			nonce,
+			signing_id: parse("signing id", signing_id)?,
+			signature: parse("signature", signature)?,
+		}))
+	}
+
+	#[staticmethod]
+	fn from_json(json: &str) -> PyResult<Self> {
+		Ok(Self(decode_json(json)?))
+	}
+
+	fn to_json(&self) -> PyResult<String> {
+		encode_json(&self.0)
+	}
+
+	#[getter]
+	fn commitment(&self) -> PyCommitment {
+		PyCommitment(self.0.commitment.clone())
+	}
+
+	#[getter]
+	fn nonce(&self) -> u64 {
+		self.0.nonce
+	}
+
+	#[getter]
+	fn signing_id(&self) -> String {
+		self.0.signing_id.to_string()
+	}
+
+	#[getter]
+	fn signature(&self) -> String {
+		hex::encode_prefixed(self.0.signature.as_bytes())
+	}
+
+	/// Address of the committer that signed the commitment on `chain`
+	fn recover_committer(&self, chain: &str) -> PyResult<String> {
+		let committer = verify::recover_committer(parse_chain(chain)?, &self.0)
+			.map_err(|e| PyValueError::new_err(e.to_string()))?;
+		Ok(committer.to_string())
+	}
+
+	/// Raises `ValueError` unless the commitment commits to its own request and is signed by `committer` on `chain`
+	fn verify(&self, chain: &str, committer: &str) -> PyResult<()> {
+		verify::verify_commitment(parse_chain(chain)?, &self.0, &parse("committer", committer)?)
+			.map_err(|e| PyValueError::new_err(e.to_string()))
+	}
+
+	fn __repr__(&self) -> String {
+		format!("{:?}", self.0)
+	}
+}
+
+/// Delegation of a proposer's slot to a gateway's delegate and committer keys
+#[pyclass(name = "Delegation", module = "fabric_bindings", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyDelegation(pub Delegation);
+
+#[pymethods]
+impl PyDelegation {
+	#[new]
+	fn new(proposer: &str, delegate: &str, committer: &str, slot: u64, metadata: &str) -> PyResult<Self> {
+		Ok(Self(Delegation {
+			proposer: parse("proposer", proposer)?,
+			delegate: parse("delegate", delegate)?,
+			committer: parse("committer", committer)?,
+			slot,
+			metadata: parse("metadata", metadata)?,
+		}))
+	}
+
+	#[staticmethod]
+	fn from_json(json: &str) -> PyResult<Self> {
+		Ok(Self(decode_json(json)?))
+	}
+
+	fn to_json(&self) -> PyResult<String> {
+		encode_json(&self.0)
+	}
+
+	#[getter]
+	fn proposer(&self) -> String {
+		self.0.proposer.to_string()
+	}
+
+	#[getter]
+	fn delegate(&self) -> String {
+		self.0.delegate.to_string()
+	}
+
+	#[getter]
+	fn committer(&self) -> String {
+		self.0.committer.to_string()
+	}
+
+	#[getter]
+	fn slot(&self) -> u64 {
+		self.0.slot
+	}
+
+	#[getter]
+	fn metadata(&self) -> String {
+		self.0.metadata.to_string()
+	}
+
+	fn __repr__(&self) -> String {
+		format!("{:?}", self.0)
+	}
+}
+
+/// Hash of a commitment request, the `request_hash` of the commitments made for it
+#[pyfunction]
+pub fn get_commitment_request_signing_root(request: &PyCommitmentRequest) -> String {
+	utils::get_commitment_request_signing_root(&request.0).to_string()
+}
+
+/// Root the committer signs a commitment over
+#[pyfunction]
+pub fn get_commitment_signing_root(commitment: &PyCommitment) -> String {
+	utils::get_commitment_signing_root(&commitment.0).to_string()
+}
+
+/// Root the proposer signs a delegation over, raises `ValueError` for keys that are not G1 points
+#[pyfunction]
+pub fn get_delegation_signing_root(delegation: &PyDelegation) -> PyResult<String> {
+	let root = utils::get_delegation_signing_root(&delegation.0).map_err(|e| PyValueError::new_err(e.to_string()))?;
+	Ok(root.to_string())
+}
+
+/// Root a Commit-Boost signer signs for `message_hash` on `chain`, the digest behind every fabric signature
+#[pyfunction]
+pub fn commit_signing_root(chain: &str, message_hash: &str, signing_id: &str, nonce: u64) -> PyResult<String> {
+	let root = utils::commit_signing_root(
+		parse_chain(chain)?,
+		parse("message hash", message_hash)?,
+		&parse("signing id", signing_id)?,
+		nonce,
+	);
+	Ok(root.to_string())
+}
+
+/// Root the delegate signs a constraints message over, the message is given as its JSON
+#[pyfunction]
+pub fn get_constraints_message_signing_root(constraints_message: &str) -> PyResult<String> {
+	let message: ConstraintsMessage = decode_json(constraints_message)?;
+	let root =
+		utils::get_constraints_message_signing_root(&message).map_err(|e| PyValueError::new_err(e.to_string()))?;
+	Ok(root.to_string())
+}
+
+#[pymodule]
+fn fabric_bindings(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add_class::<PyCommitmentRequest>()?;
+	module.add_class::<PyCommitment>()?;
+	module.add_class::<PySignedCommitment>()?;
+	module.add_class::<PyDelegation>()?;
+	module.add_function(wrap_pyfunction!(get_commitment_request_signing_root, module)?)?;
+	module.add_function(wrap_pyfunction!(get_commitment_signing_root, module)?)?;
+	module.add_function(wrap_pyfunction!(get_delegation_signing_root, module)?)?;
+	module.add_function(wrap_pyfunction!(get_constraints_message_signing_root, module)?)?;
+	module.add_function(wrap_pyfunction!(commit_signing_root, module)?)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::primitives::{Address, B256};
+	use alloy::signers::local::PrivateKeySigner;
+	use signing::local::LocalSigner;
+	use urc::test_vectors;
+
+	#[test]
+	fn test_signing_roots_match_the_vectors() {
+		let vectors = test_vectors::bundled();
+		for vector in &vectors.commitment_requests {
+			let request = PyCommitmentRequest::from_json(&serde_json::to_string(&vector.input).unwrap()).unwrap();
+			assert_eq!(
+				get_commitment_request_signing_root(&request),
+				vector.signing_root.to_string(),
+				"{}",
+				vector.name
+			);
+		}
+		for vector in &vectors.commitments {
+			let commitment = PyCommitment::from_json(&serde_json::to_string(&vector.input).unwrap()).unwrap();
+			assert_eq!(get_commitment_signing_root(&commitment), vector.signing_root.to_string(), "{}", vector.name);
+		}
+		for vector in &vectors.delegations {
+			let delegation = PyDelegation::from_json(&serde_json::to_string(&vector.input).unwrap()).unwrap();
+			let root = get_delegation_signing_root(&delegation).unwrap();
+			assert_eq!(root, vector.signing_root.to_string(), "{}", vector.name);
+		}
+		for vector in &vectors.constraints_messages {
+			let root = get_constraints_message_signing_root(&serde_json::to_string(&vector.input).unwrap()).unwrap();
+			assert_eq!(root, vector.signing_root.to_string(), "{}", vector.name);
+		}
+	}
+
+	#[test]
+	fn test_hex_fields_round_trip() {
+		let request = PyCommitmentRequest::new(1, "0x0102", &Address::repeat_byte(0xab).to_string()).unwrap();
+		assert_eq!(request.payload(), "0x0102");
+		assert_eq!(request.slasher(), Address::repeat_byte(0xab).to_string());
+		let decoded = PyCommitmentRequest::from_json(&request.to_json().unwrap()).unwrap();
+		assert_eq!(get_commitment_request_signing_root(&decoded), get_commitment_request_signing_root(&request));
+
+		assert!(PyCommitmentRequest::new(1, "0x0", "0x00").is_err());
+		assert!(PyCommitment::new(1, "0x", "0x01", &Address::ZERO.to_string()).is_err());
+	}
+
+	#[test]
+	fn test_signed_commitment_verifies() {
+		let chain = serde_json::to_string(&Chain::Holesky).unwrap();
+		let mut signer = LocalSigner::new(Chain::Holesky);
+		let committer = signer.add_ecdsa_key(PrivateKeySigner::random());
+		let request = PyCommitmentRequest::new(1, "0x0102", &Address::repeat_byte(1).to_string()).unwrap();
+		let commitment = PyCommitment::from_request(&request);
+		assert_eq!(commitment.request_hash(), get_commitment_request_signing_root(&request));
+
+		let signing_id = B256::repeat_byte(7);
+		let message_hash = utils::get_commitment_signing_root(&commitment.0);
+		let signature = signer.sign_ecdsa(&committer, message_hash, &signing_id, 5).unwrap();
+		let signed = PySignedCommitment::new(
+			&commitment,
+			5,
+			&signing_id.to_string(),
+			&hex::encode_prefixed(signature.as_bytes()),
+		)
+		.unwrap();
+
+		// The signature is over the root `commit_signing_root` returns
+		let root = commit_signing_root(&chain, &message_hash.to_string(), &signing_id.to_string(), 5).unwrap();
+		let recovered = signature.recover_address_from_prehash(&root.parse::<B256>().unwrap()).unwrap();
+		assert_eq!(recovered, committer);
+
+		assert_eq!(signed.recover_committer(&chain).unwrap(), committer.to_string());
+		signed.verify(&chain, &committer.to_string()).unwrap();
+		assert!(signed.verify(&chain, &Address::repeat_byte(2).to_string()).is_err());
+		let other_chain = serde_json::to_string(&Chain::Mainnet).unwrap();
+		assert!(signed.verify(&other_chain, &committer.to_string()).is_err());
+		assert!(parse_chain("not a chain").is_err());
+	}
+}
@@ -0,0 +1,550 @@
+//! API key authentication, rate limiting and commitment quotas for the Commitments RPC server.
+//!
+//! Callers identify themselves with an `X-Api-Key` header. The HTTP middleware resolves the key
+//! to a `Caller`, applies the caller's request rate limit and stores the `Caller` in the request
+//! extensions so RPC handlers can enforce per-slot commitment quotas. A WebSocket connection is
+//! authenticated once on upgrade, its calls are then rate limited one by one by the RPC middleware.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use axum::http::StatusCode;
+use axum::http::header::UPGRADE;
+use jsonrpsee::MethodResponse;
+use jsonrpsee::core::middleware::{Batch, BatchEntry, BatchEntryErr, Notification, RpcServiceT};
+use jsonrpsee::server::{HttpBody, HttpRequest, HttpResponse};
+use jsonrpsee::types::{ErrorObject, Request};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::{CommitmentsError, RATE_LIMITED_CODE};
+
+/// Header carrying the caller's API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Number of slots behind the latest recorded slot for which commitment counts are kept
+const QUOTA_RETENTION_SLOTS: u64 = 128;
+
+/// Authentication settings for the Commitments RPC server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+	/// API keys accepted in the `X-Api-Key` header
+	#[serde(default)]
+	pub api_keys: Vec<ApiKeyConfig>,
+
+	/// Quota shared by all requests without an API key, anonymous requests are rejected when unset
+	#[serde(default)]
+	pub anonymous: Option<QuotaConfig>,
+}
+
+/// An integrator API key and its quota
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+	/// Name used in logs, so the key itself is never logged
+	pub name: String,
+
+	/// Secret sent by the integrator in the `X-Api-Key` header
+	pub key: String,
+
+	#[serde(flatten)]
+	pub quota: QuotaConfig,
+}
+
+/// Limits applied to a caller
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuotaConfig {
+	/// Sustained JSON-RPC requests per second, bursts up to the same number are allowed
+	pub requests_per_second: f64,
+
+	/// Commitments accepted per target slot
+	pub commitments_per_slot: u32,
+}
+
+/// Identity of an authenticated caller, stored in the request extensions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Caller {
+	/// A configured integrator, identified by the API key name
+	Integrator(String),
+	/// A request without an API key
+	Anonymous,
+}
+
+impl std::fmt::Display for Caller {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Caller::Integrator(name) => write!(f, "integrator {}", name),
+			Caller::Anonymous => write!(f, "anonymous caller"),
+		}
+	}
+}
+
+/// Reason a request was refused by the authenticator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthRejection {
+	/// Unknown API key, or no key while anonymous access is disabled
+	Unauthorized,
+	/// The caller exceeded its request rate
+	RateLimited,
+}
+
+/// Token bucket refilled at the caller's request rate
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+	tokens: f64,
+	last_refill: Instant,
+}
+
+/// Names of the configured API keys keyed by secret, their quotas keyed by name, and the anonymous quota
+#[derive(Debug)]
+struct Limits {
+	keys: HashMap<String, String>,
+	quotas: HashMap<String, QuotaConfig>,
+	anonymous: Option<QuotaConfig>,
+}
+
+impl From<AuthConfig> for Limits {
+	fn from(config: AuthConfig) -> Self {
+		let mut keys = HashMap::new();
+		let mut quotas = HashMap::new();
+		for api_key in config.api_keys {
+			quotas.insert(api_key.name.clone(), api_key.quota);
+			keys.insert(api_key.key, api_key.name);
+		}
+		Self { keys, quotas, anonymous: config.anonymous }
+	}
+}
+
+/// Resolves API keys and tracks per-caller rate limits and commitment quotas
+#[derive(Debug)]
+pub struct Authenticator {
+	limits: RwLock<Limits>,
+	buckets: Mutex<HashMap<Caller, TokenBucket>>,
+	commitments: Mutex<HashMap<(Caller, u64), u32>>,
+}
+
+/// A commitment counted against the caller's quota for a slot, released when dropped before `commit`
+#[derive(Debug)]
+pub struct QuotaReservation<'a> {
+	authenticator: &'a Authenticator,
+	caller: Caller,
+	slot: u64,
+	committed: bool,
+}
+
+impl QuotaReservation<'_> {
+	/// Keep the commitment counted, once it was signed
+	pub fn commit(mut self) {
+		self.committed = true;
+	}
+}
+
+impl Drop for QuotaReservation<'_> {
+	fn drop(&mut self) {
+		if self.committed {
+			return;
+		}
+		let mut commitments = self.authenticator.commitments.lock().expect("commitment quota lock poisoned");
+		if let Some(used) = commitments.get_mut(&(self.caller.clone(), self.slot)) {
+			*used = used.saturating_sub(1);
+		}
+	}
+}
+
+impl Authenticator {
+	pub fn new(config: AuthConfig) -> Self {
+		Self {
+			limits: RwLock::new(config.into()),
+			buckets: Mutex::new(HashMap::new()),
+			commitments: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Replace the API keys and quotas, keeping the usage already counted against them
+	/// Callers whose key was removed are rejected from their next request on
+	pub fn update(&self, config: AuthConfig) {
+		*self.limits.write().expect("auth limits lock poisoned") = config.into();
+	}
+
+	/// Resolve the caller of a request and consume one request from its rate limit
+	pub fn authenticate(&self, api_key: Option<&str>) -> Result<Caller, AuthRejection> {
+		self.authenticate_at(api_key, Instant::now())
+	}
+
+	fn authenticate_at(&self, api_key: Option<&str>, now: Instant) -> Result<Caller, AuthRejection> {
+		let caller = match api_key {
+			Some(key) => Caller::Integrator(
+				self.limits
+					.read()
+					.expect("auth limits lock poisoned")
+					.keys
+					.get(key)
+					.ok_or(AuthRejection::Unauthorized)?
+					.clone(),
+			),
+			None => Caller::Anonymous,
+		};
+		self.consume_at(&caller, 1, now)?;
+		Ok(caller)
+	}
+
+	/// Consume `requests` from the rate limit of an authenticated caller, all of them or none
+	pub fn consume(&self, caller: &Caller, requests: usize) -> Result<(), AuthRejection> {
+		self.consume_at(caller, requests, Instant::now())
+	}
+
+	fn consume_at(&self, caller: &Caller, requests: usize, now: Instant) -> Result<(), AuthRejection> {
+		let quota = self.quota(caller).ok_or(AuthRejection::Unauthorized)?;
+
+		let mut buckets = self.buckets.lock().expect("rate limit lock poisoned");
+		let bucket = buckets
+			.entry(caller.clone())
+			.or_insert(TokenBucket { tokens: quota.requests_per_second, last_refill: now });
+		let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+		bucket.tokens = (bucket.tokens + elapsed * quota.requests_per_second).min(quota.requests_per_second);
+		bucket.last_refill = now;
+
+		if bucket.tokens < requests as f64 {
+			return Err(AuthRejection::RateLimited);
+		}
+		bucket.tokens -= requests as f64;
+		Ok(())
+	}
+
+	/// Limits configured for a caller
+	pub fn quota(&self, caller: &Caller) -> Option<QuotaConfig> {
+		let limits = self.limits.read().expect("auth limits lock poisoned");
+		match caller {
+			Caller::Integrator(name) => limits.quotas.get(name).copied(),
+			Caller::Anonymous => limits.anonymous,
+		}
+	}
+
+	/// Count a commitment against the caller's quota for the slot, or error if the quota is used up
+	/// The check and the count happen under the same lock, so concurrent requests can not exceed the quota
+	pub fn reserve_commitment(&self, caller: &Caller, slot: u64) -> Result<QuotaReservation<'_>, CommitmentsError> {
+		let limit = self.quota(caller).map(|quota| quota.commitments_per_slot).unwrap_or_default();
+		let mut commitments = self.commitments.lock().expect("commitment quota lock poisoned");
+		commitments.retain(|(_, counted_slot), _| *counted_slot + QUOTA_RETENTION_SLOTS >= slot);
+		let used = commitments.entry((caller.clone(), slot)).or_default();
+		if *used >= limit {
+			return Err(CommitmentsError::QuotaExceeded { caller: caller.to_string(), limit, slot });
+		}
+		*used += 1;
+		Ok(QuotaReservation { authenticator: self, caller: caller.clone(), slot, committed: false })
+	}
+}
+
+/// Marks the requests of a WebSocket connection, whose calls are rate limited by `WebSocketRateLimit`
+#[derive(Debug, Clone, Copy)]
+struct WebSocketConnection;
+
+/// Whether a request asks to upgrade to a WebSocket connection
+fn is_websocket_upgrade<B>(request: &HttpRequest<B>) -> bool {
+	request
+		.headers()
+		.get(UPGRADE)
+		.and_then(|value| value.to_str().ok())
+		.is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+/// Tower layer that authenticates and rate limits every HTTP and WebSocket request
+#[derive(Debug, Clone)]
+pub struct ApiKeyLayer {
+	authenticator: Option<Arc<Authenticator>>,
+}
+
+impl ApiKeyLayer {
+	/// Requests pass through untouched when no authenticator is configured
+	pub fn new(authenticator: Option<Arc<Authenticator>>) -> Self {
+		Self { authenticator }
+	}
+}
+
+impl<S> tower::Layer<S> for ApiKeyLayer {
+	type Service = ApiKeyService<S>;
+
+	fn layer(&self, inner: S) -> Self::Service {
+		ApiKeyService { inner, authenticator: self.authenticator.clone() }
+	}
+}
+
+/// Service produced by `ApiKeyLayer`
+#[derive(Debug, Clone)]
+pub struct ApiKeyService<S> {
+	inner: S,
+	authenticator: Option<Arc<Authenticator>>,
+}
+
+impl<S, B> tower::Service<HttpRequest<B>> for ApiKeyService<S>
+where
+	S: tower::Service<HttpRequest<B>, Response = HttpResponse>,
+	S::Future: Send + 'static,
+	S::Error: Send + 'static,
+{
+	type Response = HttpResponse;
+	type Error = S::Error;
+	type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, mut request: HttpRequest<B>) -> Self::Future {
+		let Some(authenticator) = &self.authenticator else {
+			return Box::pin(self.inner.call(request));
+		};
+
+		let api_key = request.headers().get(API_KEY_HEADER).and_then(|value| value.to_str().ok());
+		match authenticator.authenticate(api_key) {
+			Ok(caller) => {
+				if is_websocket_upgrade(&request) {
+					request.extensions_mut().insert(WebSocketConnection);
+				}
+				request.extensions_mut().insert(caller);
+				Box::pin(self.inner.call(request))
+			}
+			Err(rejection) => {
+				let (status, message) = match rejection {
+					AuthRejection::Unauthorized => (StatusCode::UNAUTHORIZED, "invalid or missing API key"),
+					AuthRejection::RateLimited => (StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded"),
+				};
+				warn!("Rejected commitments RPC request to {}: {}", request.uri().path(), message);
+				let response = HttpResponse::builder()
+					.status(status)
+					.body(HttpBody::from(message.to_string()))
+					.expect("static response is valid");
+				Box::pin(async move { Ok(response) })
+			}
+		}
+	}
+}
+
+/// RPC middleware that rate limits every call made over a WebSocket connection
+/// HTTP requests were already limited by `ApiKeyService`, one request per HTTP request
+#[derive(Debug, Clone)]
+pub struct WebSocketRateLimit<S> {
+	inner: S,
+	authenticator: Option<Arc<Authenticator>>,
+}
+
+impl<S> WebSocketRateLimit<S> {
+	/// Calls pass through untouched when no authenticator is configured
+	pub fn new(inner: S, authenticator: Option<Arc<Authenticator>>) -> Self {
+		Self { inner, authenticator }
+	}
+
+	/// Consume `calls` from the rate limit of the WebSocket caller of `extensions`
+	fn consume(&self, extensions: &jsonrpsee::Extensions, calls: usize) -> Result<(), AuthRejection> {
+		let Some(authenticator) = &self.authenticator else {
+			return Ok(());
+		};
+		if calls == 0 || extensions.get::<WebSocketConnection>().is_none() {
+			return Ok(());
+		}
+		let caller = extensions.get::<Caller>().ok_or(AuthRejection::Unauthorized)?;
+		authenticator.consume(caller, calls)
+	}
+}
+
+fn rate_limited<'a>() -> ErrorObject<'a> {
+	ErrorObject::borrowed(RATE_LIMITED_CODE, "Rate limit exceeded", None)
+}
+
+impl<S> RpcServiceT for WebSocketRateLimit<S>
+where
+	S: RpcServiceT<
+			MethodResponse = MethodResponse,
+			BatchResponse = MethodResponse,
+			NotificationResponse = MethodResponse,
+		> + Send
+		+ Sync
+		+ Clone
+		+ 'static,
+{
+	type MethodResponse = S::MethodResponse;
+	type NotificationResponse = S::NotificationResponse;
+	type BatchResponse = S::BatchResponse;
+
+	fn call<'a>(&self, request: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+		let allowed = self.consume(request.extensions(), 1);
+		let inner = self.inner.clone();
+		async move {
+			match allowed {
+				Ok(()) => inner.call(request).await,
+				Err(_) => MethodResponse::error(request.id, rate_limited()),
+			}
+		}
+	}
+
+	fn batch<'a>(&self, mut batch: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+		// Every call of the batch counts, over the limit each of them is answered with the error
+		let calls = batch.iter().filter(|entry| matches!(entry, Ok(BatchEntry::Call(_)))).count();
+		let extensions = batch.iter().find_map(|entry| match entry {
+			Ok(BatchEntry::Call(request)) => Some(request.extensions().clone()),
+			_ => None,
+		});
+		let allowed = extensions.map_or(Ok(()), |extensions| self.consume(&extensions, calls));
+		if allowed.is_err() {
+			for entry in batch.iter_mut() {
+				if let Ok(BatchEntry::Call(request)) = entry {
+					*entry = Err(BatchEntryErr::new(request.id.clone(), rate_limited()));
+				}
+			}
+		}
+		self.inner.batch(batch)
+	}
+
+	fn notification<'a>(
+		&self,
+		notification: Notification<'a>,
+	) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+		self.inner.notification(notification)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	fn authenticator() -> Authenticator {
+		Authenticator::new(AuthConfig {
+			api_keys: vec![ApiKeyConfig {
+				name: "wallet".to_string(),
+				key: "secret".to_string(),
+				quota: QuotaConfig { requests_per_second: 2.0, commitments_per_slot: 2 },
+			}],
+			anonymous: Some(QuotaConfig { requests_per_second: 1.0, commitments_per_slot: 1 }),
+		})
+	}
+
+	#[test]
+	fn test_authenticate_resolves_callers() {
+		let auth = authenticator();
+		assert_eq!(auth.authenticate(Some("secret")), Ok(Caller::Integrator("wallet".to_string())));
+		assert_eq!(auth.authenticate(None), Ok(Caller::Anonymous));
+		assert_eq!(auth.authenticate(Some("wrong")), Err(AuthRejection::Unauthorized));
+
+		let closed = Authenticator::new(AuthConfig::default());
+		assert_eq!(closed.authenticate(None), Err(AuthRejection::Unauthorized));
+	}
+
+	#[test]
+	fn test_rate_limit_refills_over_time() {
+		let auth = authenticator();
+		let start = Instant::now();
+
+		assert!(auth.authenticate_at(Some("secret"), start).is_ok());
+		assert!(auth.authenticate_at(Some("secret"), start).is_ok());
+		assert_eq!(auth.authenticate_at(Some("secret"), start), Err(AuthRejection::RateLimited));
+
+		// Limits are tracked per caller
+		assert!(auth.authenticate_at(None, start).is_ok());
+
+		assert!(auth.authenticate_at(Some("secret"), start + Duration::from_millis(500)).is_ok());
+		assert_eq!(
+			auth.authenticate_at(Some("secret"), start + Duration::from_millis(500)),
+			Err(AuthRejection::RateLimited)
+		);
+	}
+
+	#[test]
+	fn test_rate_limit_consumes_all_or_nothing() {
+		let auth = authenticator();
+		let wallet = Caller::Integrator("wallet".to_string());
+		let start = Instant::now();
+
+		assert_eq!(auth.consume_at(&wallet, 3, start), Err(AuthRejection::RateLimited));
+		assert!(auth.consume_at(&wallet, 2, start).is_ok());
+		assert_eq!(auth.consume_at(&wallet, 1, start), Err(AuthRejection::RateLimited));
+		assert_eq!(
+			auth.consume_at(&Caller::Integrator("removed".to_string()), 1, start),
+			Err(AuthRejection::Unauthorized)
+		);
+	}
+
+	#[test]
+	fn test_websocket_calls_are_rate_limited() {
+		let limit = WebSocketRateLimit::new((), Some(Arc::new(authenticator())));
+		let mut extensions = jsonrpsee::Extensions::new();
+		extensions.insert(Caller::Anonymous);
+
+		// HTTP requests were already counted by the HTTP middleware
+		for _ in 0..3 {
+			assert!(limit.consume(&extensions, 1).is_ok());
+		}
+
+		extensions.insert(WebSocketConnection);
+		assert!(limit.consume(&extensions, 1).is_ok());
+		assert_eq!(limit.consume(&extensions, 1), Err(AuthRejection::RateLimited));
+		assert!(limit.consume(&extensions, 0).is_ok());
+	}
+
+	#[test]
+	fn test_websocket_upgrades_are_detected() {
+		let upgrade = axum::http::Request::builder().header(UPGRADE, "WebSocket").body(()).unwrap();
+		assert!(is_websocket_upgrade(&upgrade));
+		let request = axum::http::Request::builder().body(()).unwrap();
+		assert!(!is_websocket_upgrade(&request));
+	}
+
+	#[test]
+	fn test_commitment_quota_per_slot() {
+		let auth = authenticator();
+		let wallet = Caller::Integrator("wallet".to_string());
+
+		for _ in 0..2 {
+			auth.reserve_commitment(&wallet, 10).unwrap().commit();
+		}
+		assert!(auth.reserve_commitment(&wallet, 10).is_err());
+		assert!(auth.reserve_commitment(&wallet, 11).is_ok());
+
+		auth.reserve_commitment(&Caller::Anonymous, 10).unwrap().commit();
+		assert!(auth.reserve_commitment(&Caller::Anonymous, 10).is_err());
+	}
+
+	#[test]
+	fn test_commitment_quota_is_reserved() {
+		let auth = authenticator();
+		let wallet = Caller::Integrator("wallet".to_string());
+
+		// Requests in flight hold their share of the quota until they are signed or fail
+		let first = auth.reserve_commitment(&wallet, 10).unwrap();
+		let second = auth.reserve_commitment(&wallet, 10).unwrap();
+		assert!(auth.reserve_commitment(&wallet, 10).is_err());
+
+		drop(first);
+		second.commit();
+		auth.reserve_commitment(&wallet, 10).unwrap().commit();
+		assert!(auth.reserve_commitment(&wallet, 10).is_err());
+	}
+
+	#[test]
+	fn test_update_replaces_limits() {
+		let auth = authenticator();
+		let wallet = Caller::Integrator("wallet".to_string());
+		auth.reserve_commitment(&wallet, 10).unwrap().commit();
+		auth.reserve_commitment(&wallet, 10).unwrap().commit();
+
+		auth.update(AuthConfig {
+			api_keys: vec![ApiKeyConfig {
+				name: "wallet".to_string(),
+				key: "rotated".to_string(),
+				quota: QuotaConfig { requests_per_second: 2.0, commitments_per_slot: 3 },
+			}],
+			anonymous: None,
+		});
+
+		// Usage counted before the update still applies against the new quota
+		auth.reserve_commitment(&wallet, 10).unwrap().commit();
+		assert!(auth.reserve_commitment(&wallet, 10).is_err());
+
+		assert_eq!(auth.authenticate(Some("rotated")), Ok(wallet));
+		assert_eq!(auth.authenticate(Some("secret")), Err(AuthRejection::Unauthorized));
+		assert_eq!(auth.authenticate(None), Err(AuthRejection::Unauthorized));
+	}
+}
@@ -1,123 +1,246 @@
-use alloy::primitives::B256;
-use eyre::{Result, WrapErr};
-use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
-
-use crate::methods::{COMMITMENT_REQUEST_METHOD, COMMITMENT_RESULT_METHOD, FEE_METHOD, SLOTS_METHOD};
+use std::future::Future;
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, Signature};
+use jsonrpsee::core::ClientError;
+use jsonrpsee::http_client::{HeaderMap, HeaderValue, HttpClient, HttpClientBuilder};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::auth::API_KEY_HEADER;
+use crate::error::{CommitmentsClientError, CommitmentsError};
+use crate::methods::{
+	COMMITMENT_CANCEL_METHOD, COMMITMENT_REQUEST_METHOD, COMMITMENT_RESULT_METHOD, COMMITMENTS_BY_SENDER_METHOD,
+	FEE_METHOD, SLOTS_METHOD,
+};
 use crate::metrics::client_http_metrics;
 use crate::rpc::CommitmentsRpcClient;
 use crate::types::{CommitmentRequest, FeeInfo, SignedCommitment, SlotInfoResponse};
 
+/// Settings for `CommitmentsHttpClient`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitmentsClientConfig {
+	/// Gateway endpoints, the first is preferred and the others are tried in order when it fails
+	pub endpoints: Vec<Url>,
+
+	/// API key sent in the `X-Api-Key` header
+	#[serde(default)]
+	pub api_key: Option<String>,
+
+	/// Timeout of a single request to one endpoint in milliseconds
+	#[serde(default = "default_request_timeout_ms")]
+	pub request_timeout_ms: u64,
+
+	/// Extra passes over all endpoints when none could be reached. Timeouts, failed connections and RPC errors other
+	/// than a standby instance are never retried, the gateway may already have acted on the request
+	#[serde(default = "default_max_retries")]
+	pub max_retries: u32,
+
+	/// Delay before the first retry pass in milliseconds, doubled on every further pass
+	#[serde(default = "default_retry_backoff_ms")]
+	pub retry_backoff_ms: u64,
+
+	/// Disable Nagle's algorithm, so requests are sent without waiting for more data. Connections are pooled and
+	/// kept alive by the underlying jsonrpsee transport, which does not expose its pool settings
+	#[serde(default = "default_tcp_nodelay")]
+	pub tcp_nodelay: bool,
+}
+
+fn default_request_timeout_ms() -> u64 {
+	10_000
+}
+
+fn default_max_retries() -> u32 {
+	2
+}
+
+fn default_retry_backoff_ms() -> u64 {
+	200
+}
+
+fn default_tcp_nodelay() -> bool {
+	true
+}
+
+impl CommitmentsClientConfig {
+	/// Default settings for a single gateway endpoint
+	pub fn new(endpoint: Url) -> Self {
+		Self {
+			endpoints: vec![endpoint],
+			api_key: None,
+			request_timeout_ms: default_request_timeout_ms(),
+			max_retries: default_max_retries(),
+			retry_backoff_ms: default_retry_backoff_ms(),
+			tcp_nodelay: default_tcp_nodelay(),
+		}
+	}
+}
+
 /// Thin wrapper around `HttpClient` that exposes typed methods for the Commitments RPC API.
+///
+/// Calls fail over across the configured endpoints and are retried with backoff while no endpoint
+/// can be reached. A request that timed out or lost its connection is returned as an error rather
+/// than sent again, since the gateway may already have signed the commitment.
 #[derive(Clone)]
 pub struct CommitmentsHttpClient {
-	inner: HttpClient,
+	endpoints: Vec<(Url, HttpClient)>,
+	max_retries: u32,
+	retry_backoff: Duration,
 }
 
 impl CommitmentsHttpClient {
-	/// Create a new HTTP client pointed at the given base URL.
+	/// Create a new HTTP client from the given settings.
 	///
 	/// Example:
 	/// ```ignore
-	/// let client = CommitmentsHttpClient::new("http://127.0.0.1:8545")?;
+	/// let url = Url::parse("http://127.0.0.1:8545")?;
+	/// let client = CommitmentsHttpClient::new(CommitmentsClientConfig::new(url))?;
 	/// ```
-	pub fn new<S: AsRef<str>>(url: S) -> Result<Self> {
-		// Trim trailing slash from URL if present
-		let url = url.as_ref().trim_end_matches('/');
-		let inner = HttpClientBuilder::default()
-			.build(url)
-			.wrap_err_with(|| format!("failed to build HttpClient for url {}", url))?;
-
-		Ok(Self { inner })
-	}
-
-	/// Expose inner if needed
-	pub fn inner(&self) -> &HttpClient {
-		&self.inner
-	}
+	pub fn new(config: CommitmentsClientConfig) -> Result<Self, CommitmentsError> {
+		if config.endpoints.is_empty() {
+			return Err(CommitmentsError::InvalidConfig("At least one commitments endpoint is required".to_string()));
+		}
+		if config.request_timeout_ms == 0 {
+			return Err(CommitmentsError::InvalidConfig("Request timeout must be greater than zero".to_string()));
+		}
 
-	pub async fn commitment_request(&self, request: CommitmentRequest) -> Result<SignedCommitment> {
-		const ROLE: &str = "client";
-		const METHOD: &str = COMMITMENT_REQUEST_METHOD;
+		let mut headers = HeaderMap::new();
+		if let Some(api_key) = &config.api_key {
+			let api_key = HeaderValue::from_str(api_key)
+				.map_err(|e| CommitmentsError::InvalidConfig(format!("invalid API key header value: {e}")))?;
+			headers.insert(API_KEY_HEADER, api_key);
+		}
 
-		let metrics = client_http_metrics();
-		let start = metrics.start(ROLE, METHOD);
+		let endpoints = config
+			.endpoints
+			.into_iter()
+			.map(|endpoint| {
+				// Trim trailing slash from URL if present
+				let url = endpoint.as_str().trim_end_matches('/');
+				let client = HttpClientBuilder::default()
+					.set_headers(headers.clone())
+					.request_timeout(Duration::from_millis(config.request_timeout_ms))
+					.set_tcp_no_delay(config.tcp_nodelay)
+					.build(url)
+					.map_err(|e| {
+						CommitmentsError::InvalidConfig(format!("failed to build HttpClient for url {}: {}", url, e))
+					})?;
+				Ok((endpoint, client))
+			})
+			.collect::<Result<Vec<_>, CommitmentsError>>()?;
+
+		Ok(Self {
+			endpoints,
+			max_retries: config.max_retries,
+			retry_backoff: Duration::from_millis(config.retry_backoff_ms),
+		})
+	}
 
-		// Make the actual RPC call
-		let result = CommitmentsRpcClient::commitment_request(&self.inner, request).await;
+	/// Create a new HTTP client for a single endpoint that authenticates with an API key.
+	pub fn with_api_key(url: Url, api_key: &str) -> Result<Self, CommitmentsError> {
+		Self::new(CommitmentsClientConfig { api_key: Some(api_key.to_string()), ..CommitmentsClientConfig::new(url) })
+	}
 
-		match result {
-			Ok(resp) => {
-				metrics.finish_label(ROLE, METHOD, "ok", start);
-				Ok(resp.into())
-			}
-			Err(e) => {
-				// map your Error -> RpcError, etc
-				metrics.finish_label(ROLE, METHOD, format!("error: {e:?}").as_str(), start);
-				Err(e.into())
-			}
-		}
+	/// Expose the client of the preferred endpoint if needed
+	pub fn inner(&self) -> &HttpClient {
+		&self.endpoints[0].1
 	}
 
-	pub async fn commitment_result(&self, request_hash: B256) -> Result<SignedCommitment> {
+	/// Run a call against each endpoint in turn until one answers, retrying only while none can be reached
+	async fn call<T, F, Fut>(&self, method: &'static str, call: F) -> Result<T, CommitmentsClientError>
+	where
+		F: Fn(HttpClient) -> Fut,
+		Fut: Future<Output = Result<T, ClientError>>,
+	{
 		const ROLE: &str = "client";
-		const METHOD: &str = COMMITMENT_RESULT_METHOD;
 
 		let metrics = client_http_metrics();
-		let start = metrics.start(ROLE, METHOD);
-
-		let result = CommitmentsRpcClient::commitment_result(&self.inner, request_hash).await;
-
-		match result {
-			Ok(resp) => {
-				metrics.finish_label(ROLE, METHOD, "ok", start);
-				Ok(resp.into())
+		let start = metrics.start(ROLE, method);
+
+		let mut backoff = self.retry_backoff;
+		let mut attempt = 0;
+		let result = 'retry: loop {
+			let mut last_error = None;
+			for (endpoint, client) in &self.endpoints {
+				match call(client.clone()).await.map_err(CommitmentsClientError::from) {
+					Err(e) if e.is_retryable() => {
+						warn!("Commitments endpoint {} failed on {}: {}", endpoint, method, e);
+						last_error = Some(e);
+					}
+					result => break 'retry result,
+				}
 			}
-			Err(e) => {
-				metrics.finish_label(ROLE, METHOD, format!("error: {e:?}").as_str(), start);
-				Err(e.into())
+
+			// Every endpoint failed with a retryable error
+			let e = last_error.expect("at least one endpoint is configured");
+			if attempt >= self.max_retries {
+				break Err(e);
 			}
+			attempt += 1;
+			tokio::time::sleep(backoff).await;
+			backoff *= 2;
+		};
+
+		match &result {
+			Ok(_) => metrics.finish_label(ROLE, method, "ok", start),
+			Err(e) => metrics.finish_label(ROLE, method, format!("error: {e:?}").as_str(), start),
 		}
+		result
 	}
 
-	pub async fn slots(&self) -> Result<SlotInfoResponse> {
-		const ROLE: &str = "client";
-		const METHOD: &str = SLOTS_METHOD;
-
-		let metrics = client_http_metrics();
-		let start = metrics.start(ROLE, METHOD);
-
-		let result = CommitmentsRpcClient::slots(&self.inner).await;
+	pub async fn commitment_request(
+		&self,
+		request: CommitmentRequest,
+	) -> Result<SignedCommitment, CommitmentsClientError> {
+		self.call(COMMITMENT_REQUEST_METHOD, |client| {
+			let request = request.clone();
+			async move { CommitmentsRpcClient::commitment_request(&client, request).await }
+		})
+		.await
+	}
 
-		match result {
-			Ok(resp) => {
-				metrics.finish_label(ROLE, METHOD, "ok", start);
-				Ok(resp)
-			}
-			Err(e) => {
-				metrics.finish_label(ROLE, METHOD, format!("error: {e:?}").as_str(), start);
-				Err(e.into())
-			}
-		}
+	pub async fn commitment_result(&self, request_hash: B256) -> Result<SignedCommitment, CommitmentsClientError> {
+		self.call(COMMITMENT_RESULT_METHOD, |client| async move {
+			CommitmentsRpcClient::commitment_result(&client, request_hash).await
+		})
+		.await
 	}
 
-	pub async fn fee(&self, request: CommitmentRequest) -> Result<FeeInfo> {
-		const ROLE: &str = "client";
-		const METHOD: &str = FEE_METHOD;
+	pub async fn commitment_cancel(
+		&self,
+		request_hash: B256,
+		signature: Signature,
+	) -> Result<(), CommitmentsClientError> {
+		self.call(COMMITMENT_CANCEL_METHOD, |client| async move {
+			CommitmentsRpcClient::commitment_cancel(&client, request_hash, signature).await
+		})
+		.await
+	}
 
-		let metrics = client_http_metrics();
-		let start = metrics.start(ROLE, METHOD);
+	pub async fn commitments_by_sender(
+		&self,
+		sender: Address,
+		start_slot: u64,
+		end_slot: u64,
+		expiry: u64,
+		signature: Signature,
+	) -> Result<Vec<SignedCommitment>, CommitmentsClientError> {
+		self.call(COMMITMENTS_BY_SENDER_METHOD, |client| async move {
+			CommitmentsRpcClient::commitments_by_sender(&client, sender, start_slot, end_slot, expiry, signature).await
+		})
+		.await
+	}
 
-		let result = CommitmentsRpcClient::fee(&self.inner, request).await;
+	pub async fn slots(&self) -> Result<SlotInfoResponse, CommitmentsClientError> {
+		self.call(SLOTS_METHOD, |client| async move { CommitmentsRpcClient::slots(&client).await }).await
+	}
 
-		match result {
-			Ok(resp) => {
-				metrics.finish_label(ROLE, METHOD, "ok", start);
-				Ok(resp)
-			}
-			Err(e) => {
-				metrics.finish_label(ROLE, METHOD, format!("error: {e:?}").as_str(), start);
-				Err(e.into())
-			}
-		}
+	pub async fn fee(&self, request: CommitmentRequest) -> Result<FeeInfo, CommitmentsClientError> {
+		self.call(FEE_METHOD, |client| {
+			let request = request.clone();
+			async move { CommitmentsRpcClient::fee(&client, request).await }
+		})
+		.await
 	}
 }
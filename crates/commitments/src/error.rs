@@ -0,0 +1,354 @@
+//! Typed errors returned by the Commitments RPC server and `CommitmentsHttpClient`.
+//!
+//! Every `CommitmentsRpcError` variant maps to a stable JSON-RPC error code so clients can
+//! branch on the failure class instead of parsing the error message. The human readable detail
+//! travels in the error object's `data` field. `CommitmentsError` covers the library outside of
+//! RPC calls and converts into the RPC taxonomy when it surfaces from a method.
+
+use std::io::ErrorKind;
+
+use common::storage::StorageError;
+use jsonrpsee::core::ClientError;
+use jsonrpsee::types::{ErrorObject, ErrorObjectOwned};
+
+/// Malformed request or payload, same as the JSON-RPC invalid params code
+pub const INVALID_PAYLOAD_CODE: i32 = -32602;
+
+/// The gateway holds no delegation for the requested slot
+pub const NO_DELEGATION_CODE: i32 = -32001;
+
+/// The slot already started, or is too close for the commitment to be constrained
+pub const SLOT_ELAPSED_CODE: i32 = -32002;
+
+/// The gateway or the caller is out of capacity for the slot: gas budget or commitment quota
+pub const OVER_CAPACITY_CODE: i32 = -32003;
+
+/// The transaction does not pay the quoted fee
+pub const PAYMENT_REQUIRED_CODE: i32 = -32004;
+
+/// The request conflicts with an existing commitment
+pub const CONFLICT_CODE: i32 = -32005;
+
+/// No active commitment matches the request hash
+pub const NOT_FOUND_CODE: i32 = -32006;
+
+/// The gateway instance is a standby of a highly available gateway, another instance holds the leadership
+pub const STANDBY_CODE: i32 = -32007;
+
+/// Higher paying requests took the remaining capacity of the slot while the request waited for admission
+pub const OUTBID_CODE: i32 = -32008;
+
+/// Codes reserved for transactions that would not be includable, one per failure class
+pub const INVALID_TRANSACTION_CODES: std::ops::RangeInclusive<i32> = -32019..=-32010;
+
+/// A WebSocket call over the caller's request rate, HTTP requests are answered with a 429 instead
+pub const RATE_LIMITED_CODE: i32 = -32029;
+
+/// Unexpected server side failure, same as the JSON-RPC internal error code
+pub const INTERNAL_CODE: i32 = -32603;
+
+/// Error returned by Commitments RPC methods
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitmentsRpcError {
+	InvalidPayload(String),
+	NoDelegation(String),
+	SlotElapsed(String),
+	OverCapacity(String),
+	PaymentRequired(String),
+	Conflict(String),
+	NotFound(String),
+	Standby(String),
+	Outbid(String),
+	/// The transaction would not be includable, `code` identifies the failure class
+	InvalidTransaction {
+		code: i32,
+		message: String,
+		reason: String,
+	},
+	Internal(String),
+}
+
+impl CommitmentsRpcError {
+	/// Stable JSON-RPC error code
+	pub fn code(&self) -> i32 {
+		match self {
+			CommitmentsRpcError::InvalidPayload(_) => INVALID_PAYLOAD_CODE,
+			CommitmentsRpcError::NoDelegation(_) => NO_DELEGATION_CODE,
+			CommitmentsRpcError::SlotElapsed(_) => SLOT_ELAPSED_CODE,
+			CommitmentsRpcError::OverCapacity(_) => OVER_CAPACITY_CODE,
+			CommitmentsRpcError::PaymentRequired(_) => PAYMENT_REQUIRED_CODE,
+			CommitmentsRpcError::Conflict(_) => CONFLICT_CODE,
+			CommitmentsRpcError::NotFound(_) => NOT_FOUND_CODE,
+			CommitmentsRpcError::Standby(_) => STANDBY_CODE,
+			CommitmentsRpcError::Outbid(_) => OUTBID_CODE,
+			CommitmentsRpcError::InvalidTransaction { code, .. } => *code,
+			CommitmentsRpcError::Internal(_) => INTERNAL_CODE,
+		}
+	}
+
+	/// Short JSON-RPC error message
+	pub fn message(&self) -> &str {
+		match self {
+			CommitmentsRpcError::InvalidPayload(_) => "Invalid payload",
+			CommitmentsRpcError::NoDelegation(_) => "No delegation for slot",
+			CommitmentsRpcError::SlotElapsed(_) => "Slot elapsed",
+			CommitmentsRpcError::OverCapacity(_) => "Over capacity",
+			CommitmentsRpcError::PaymentRequired(_) => "Payment required",
+			CommitmentsRpcError::Conflict(_) => "Conflicting commitment",
+			CommitmentsRpcError::NotFound(_) => "Commitment not found",
+			CommitmentsRpcError::Standby(_) => "Gateway on standby",
+			CommitmentsRpcError::Outbid(_) => "Outbid",
+			CommitmentsRpcError::InvalidTransaction { message, .. } => message,
+			CommitmentsRpcError::Internal(_) => "Internal error",
+		}
+	}
+
+	/// Human readable detail, sent as the error data
+	pub fn reason(&self) -> &str {
+		match self {
+			CommitmentsRpcError::InvalidPayload(reason)
+			| CommitmentsRpcError::NoDelegation(reason)
+			| CommitmentsRpcError::SlotElapsed(reason)
+			| CommitmentsRpcError::OverCapacity(reason)
+			| CommitmentsRpcError::PaymentRequired(reason)
+			| CommitmentsRpcError::Conflict(reason)
+			| CommitmentsRpcError::NotFound(reason)
+			| CommitmentsRpcError::Standby(reason)
+			| CommitmentsRpcError::Outbid(reason)
+			| CommitmentsRpcError::InvalidTransaction { reason, .. }
+			| CommitmentsRpcError::Internal(reason) => reason,
+		}
+	}
+
+	/// Rebuild the typed error from an error object returned by a Commitments server
+	/// Unknown codes are reported as internal errors
+	pub fn from_error_object(error: &ErrorObject<'_>) -> Self {
+		let reason = error
+			.data()
+			.map(|data| serde_json::from_str::<String>(data.get()).unwrap_or_else(|_| data.get().to_string()))
+			.unwrap_or_else(|| error.message().to_string());
+		match error.code() {
+			INVALID_PAYLOAD_CODE => CommitmentsRpcError::InvalidPayload(reason),
+			NO_DELEGATION_CODE => CommitmentsRpcError::NoDelegation(reason),
+			SLOT_ELAPSED_CODE => CommitmentsRpcError::SlotElapsed(reason),
+			OVER_CAPACITY_CODE => CommitmentsRpcError::OverCapacity(reason),
+			PAYMENT_REQUIRED_CODE => CommitmentsRpcError::PaymentRequired(reason),
+			CONFLICT_CODE => CommitmentsRpcError::Conflict(reason),
+			NOT_FOUND_CODE => CommitmentsRpcError::NotFound(reason),
+			STANDBY_CODE => CommitmentsRpcError::Standby(reason),
+			OUTBID_CODE => CommitmentsRpcError::Outbid(reason),
+			code if INVALID_TRANSACTION_CODES.contains(&code) => {
+				CommitmentsRpcError::InvalidTransaction { code, message: error.message().to_string(), reason }
+			}
+			_ => CommitmentsRpcError::Internal(reason),
+		}
+	}
+}
+
+impl std::fmt::Display for CommitmentsRpcError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{} ({}): {}", self.message(), self.code(), self.reason())
+	}
+}
+
+impl std::error::Error for CommitmentsRpcError {}
+
+impl From<CommitmentsRpcError> for ErrorObjectOwned {
+	fn from(error: CommitmentsRpcError) -> Self {
+		ErrorObject::owned(error.code(), error.message().to_string(), Some(error.reason().to_string()))
+	}
+}
+
+/// Error returned by `CommitmentsHttpClient`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitmentsClientError {
+	/// The gateway could not be reached, the request was never sent
+	Connect(String),
+	/// The connection failed after the request may have reached the gateway
+	Transport(String),
+	/// The gateway did not answer within the request timeout
+	Timeout,
+	/// The gateway answered with a Commitments RPC error
+	Rpc(CommitmentsRpcError),
+	/// The gateway answered with a response the client could not process
+	InvalidResponse(String),
+}
+
+impl CommitmentsClientError {
+	/// Only requests the gateway did not act on are retried: unreachable endpoints and standby instances. A request
+	/// that timed out or lost its connection may have been accepted, and retrying it could commit twice
+	pub fn is_retryable(&self) -> bool {
+		matches!(
+			self,
+			CommitmentsClientError::Connect(_) | CommitmentsClientError::Rpc(CommitmentsRpcError::Standby(_))
+		)
+	}
+}
+
+/// Whether a transport error failed to open the connection, looking through its sources for the connector's error
+fn is_connect_error(error: &(dyn std::error::Error + 'static)) -> bool {
+	let mut source = Some(error);
+	while let Some(error) = source {
+		let refused = error.downcast_ref::<std::io::Error>().is_some_and(|io_error| {
+			matches!(
+				io_error.kind(),
+				ErrorKind::ConnectionRefused
+					| ErrorKind::HostUnreachable
+					| ErrorKind::NetworkUnreachable
+					| ErrorKind::AddrNotAvailable
+			)
+		});
+		if refused {
+			return true;
+		}
+		// Name resolution and connect failures of the HTTP connector, whose io errors carry no specific kind
+		let message = error.to_string();
+		if message.starts_with("dns error") || message.starts_with("tcp connect error") {
+			return true;
+		}
+		source = error.source();
+	}
+	false
+}
+
+impl std::fmt::Display for CommitmentsClientError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			CommitmentsClientError::Connect(reason) => write!(f, "connection error: {}", reason),
+			CommitmentsClientError::Transport(reason) => write!(f, "transport error: {}", reason),
+			CommitmentsClientError::Timeout => write!(f, "request timed out"),
+			CommitmentsClientError::Rpc(error) => write!(f, "{}", error),
+			CommitmentsClientError::InvalidResponse(reason) => write!(f, "invalid response: {}", reason),
+		}
+	}
+}
+
+impl std::error::Error for CommitmentsClientError {}
+
+impl From<ClientError> for CommitmentsClientError {
+	fn from(error: ClientError) -> Self {
+		match error {
+			ClientError::Call(error) => CommitmentsClientError::Rpc(CommitmentsRpcError::from_error_object(&error)),
+			ClientError::RequestTimeout => CommitmentsClientError::Timeout,
+			ClientError::Transport(ref transport) if is_connect_error(&**transport) => {
+				CommitmentsClientError::Connect(error.to_string())
+			}
+			ClientError::Transport(_) | ClientError::RestartNeeded(_) => {
+				CommitmentsClientError::Transport(error.to_string())
+			}
+			_ => CommitmentsClientError::InvalidResponse(error.to_string()),
+		}
+	}
+}
+
+/// Error of the Commitments library outside of RPC calls: client and server setup, and commitment quotas
+#[derive(Debug, thiserror::Error)]
+pub enum CommitmentsError {
+	/// Client or server settings that can not be used
+	#[error("invalid configuration: {0}")]
+	InvalidConfig(String),
+	/// The caller already used its commitment quota for the slot
+	#[error("{caller} reached its quota of {limit} commitments for slot {slot}")]
+	QuotaExceeded { caller: String, limit: u32, slot: u64 },
+	/// The server could not bind its listeners or start
+	#[error("{0}")]
+	Server(String),
+}
+
+/// Result of the Commitments library outside of RPC calls
+pub type Result<T, E = CommitmentsError> = std::result::Result<T, E>;
+
+impl From<CommitmentsError> for CommitmentsRpcError {
+	fn from(error: CommitmentsError) -> Self {
+		match error {
+			CommitmentsError::QuotaExceeded { .. } => CommitmentsRpcError::OverCapacity(error.to_string()),
+			CommitmentsError::InvalidConfig(_) | CommitmentsError::Server(_) => {
+				CommitmentsRpcError::Internal(error.to_string())
+			}
+		}
+	}
+}
+
+impl From<StorageError> for CommitmentsRpcError {
+	fn from(error: StorageError) -> Self {
+		CommitmentsRpcError::Internal(error.to_string())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_error_object_round_trip() {
+		let errors = vec![
+			CommitmentsRpcError::InvalidPayload("bad rlp".to_string()),
+			CommitmentsRpcError::NoDelegation("slot 10".to_string()),
+			CommitmentsRpcError::SlotElapsed("slot 10".to_string()),
+			CommitmentsRpcError::OverCapacity("gas budget".to_string()),
+			CommitmentsRpcError::PaymentRequired("underpriced".to_string()),
+			CommitmentsRpcError::Conflict("nonce 1".to_string()),
+			CommitmentsRpcError::NotFound("0x00".to_string()),
+			CommitmentsRpcError::Standby("leader is gateway-b".to_string()),
+			CommitmentsRpcError::Outbid("slot 10".to_string()),
+			CommitmentsRpcError::InvalidTransaction {
+				code: -32014,
+				message: "Nonce too low".to_string(),
+				reason: "nonce 1 is below account nonce 2".to_string(),
+			},
+			CommitmentsRpcError::Internal("db closed".to_string()),
+		];
+
+		for error in errors {
+			let object = ErrorObjectOwned::from(error.clone());
+			assert_eq!(object.code(), error.code());
+			assert_eq!(CommitmentsRpcError::from_error_object(&object), error);
+		}
+	}
+
+	#[test]
+	fn test_client_error_classification() {
+		let rpc = CommitmentsClientError::from(ClientError::Call(
+			CommitmentsRpcError::SlotElapsed("late".to_string()).into(),
+		));
+		assert_eq!(rpc, CommitmentsClientError::Rpc(CommitmentsRpcError::SlotElapsed("late".to_string())));
+		assert!(!rpc.is_retryable());
+
+		// The gateway may have acted on a request that timed out or lost its connection
+		assert_eq!(CommitmentsClientError::from(ClientError::RequestTimeout), CommitmentsClientError::Timeout);
+		assert!(!CommitmentsClientError::Timeout.is_retryable());
+		let reset = std::io::Error::new(ErrorKind::ConnectionReset, "reset by peer");
+		let reset = CommitmentsClientError::from(ClientError::Transport(reset.into()));
+		assert!(matches!(reset, CommitmentsClientError::Transport(_)));
+		assert!(!reset.is_retryable());
+
+		let refused = std::io::Error::new(ErrorKind::ConnectionRefused, "refused");
+		let refused = CommitmentsClientError::from(ClientError::Transport(refused.into()));
+		assert!(matches!(refused, CommitmentsClientError::Connect(_)));
+		assert!(refused.is_retryable());
+		let dns = std::io::Error::other("dns error: failed to lookup address information");
+		assert!(CommitmentsClientError::from(ClientError::Transport(dns.into())).is_retryable());
+
+		assert!(CommitmentsClientError::Rpc(CommitmentsRpcError::Standby("standby".to_string())).is_retryable());
+		assert!(!CommitmentsClientError::from(ClientError::Custom("bad".to_string())).is_retryable());
+	}
+
+	#[test]
+	fn test_library_errors_map_to_rpc_errors() {
+		let quota = CommitmentsError::QuotaExceeded { caller: "wallet".to_string(), limit: 2, slot: 10 };
+		assert_eq!(
+			CommitmentsRpcError::from(quota),
+			CommitmentsRpcError::OverCapacity("wallet reached its quota of 2 commitments for slot 10".to_string())
+		);
+		assert_eq!(CommitmentsRpcError::from(StorageError::Healthcheck).code(), INTERNAL_CODE);
+	}
+
+	#[test]
+	fn test_unknown_code_is_internal() {
+		let object = ErrorObject::owned(-32099, "Custom", Some("detail"));
+		assert_eq!(
+			CommitmentsRpcError::from_error_object(&object),
+			CommitmentsRpcError::Internal("detail".to_string())
+		);
+	}
+}
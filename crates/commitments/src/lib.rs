@@ -1,6 +1,14 @@
+#[cfg(feature = "full")]
+pub mod auth;
+#[cfg(feature = "full")]
 pub mod client;
+#[cfg(feature = "full")]
+pub mod error;
 pub mod methods;
+#[cfg(feature = "full")]
 pub mod metrics;
+#[cfg(feature = "full")]
 pub mod rpc;
+#[cfg(feature = "full")]
 pub mod server;
 pub mod types;
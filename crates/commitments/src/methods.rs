@@ -1,5 +1,10 @@
 pub const COMMITMENT_REQUEST_METHOD: &str = "commitmentRequest";
 pub const COMMITMENT_RESULT_METHOD: &str = "commitmentResult";
+pub const COMMITMENT_CANCEL_METHOD: &str = "commitmentCancel";
+pub const COMMITMENTS_BY_SENDER_METHOD: &str = "commitmentsBySender";
 pub const SLOTS_METHOD: &str = "slots";
 pub const FEE_METHOD: &str = "fee";
+pub const SUBSCRIBE_COMMITMENTS_METHOD: &str = "subscribeCommitments";
+pub const UNSUBSCRIBE_COMMITMENTS_METHOD: &str = "unsubscribeCommitments";
+pub const COMMITMENT_EVENT_NOTIFICATION: &str = "commitmentEvent";
 pub const GENERATE_PROXY_KEY_METHOD: &str = "generateProxyKey";
@@ -84,8 +84,12 @@ pub fn client_http_metrics() -> HttpMetrics {
 	}
 }
 
-pub async fn server_metrics_handler() -> Response {
-	let metric_families = COMMITMENTS_SERVER_METRICS_REGISTRY.gather();
+/// Serves the commitments server HTTP metrics together with the metrics of the given registries
+pub async fn server_metrics_handler(registries: Vec<Registry>) -> Response {
+	let mut metric_families = COMMITMENTS_SERVER_METRICS_REGISTRY.gather();
+	for registry in &registries {
+		metric_families.extend(registry.gather());
+	}
 	let mut buffer = Vec::new();
 	let encoder = TextEncoder::new();
 	if encoder.encode(&metric_families, &mut buffer).is_err() {
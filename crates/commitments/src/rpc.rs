@@ -3,17 +3,20 @@
 //! This module defines:
 //! - The wire-level RPC methods and their parameter / response types
 //! - The `CommitmentsRpc` spec trait (using jsonrpsee's #[rpc(server)] macro)
+//! - The `CommitmentsPubSub` spec trait for WebSocket subscriptions to commitment events
 //! - A reference handler struct `DefaultCommitmentsRpc<T>` that uses `CommitmentsServerState<T>`
 //!
 //! Implementations in other crates can:
 //! - Reuse the `CommitmentsRpc` trait and param/response types
 //! - Implement `CommitmentsRpcServer` for their own handler struct and state
 
-use alloy::primitives::B256;
-use jsonrpsee::core::RpcResult;
+use alloy::primitives::{Address, B256, Signature};
+use jsonrpsee::core::{RpcResult, SubscriptionResult};
 use jsonrpsee::proc_macros::rpc;
 
-use crate::types::{CommitmentRequest, FeeInfo, SignedCommitment, SlotInfoResponse};
+use crate::types::{
+	CommitmentEvent, CommitmentEventFilter, CommitmentRequest, FeeInfo, SignedCommitment, SlotInfoResponse,
+};
 
 /// JSON RPC spec for the Commitments service.
 /// Implementations are free to choose any internal state or dependencies.
@@ -21,13 +24,34 @@ use crate::types::{CommitmentRequest, FeeInfo, SignedCommitment, SlotInfoRespons
 #[rpc(server, client)]
 pub trait CommitmentsRpc {
 	/// Request a commitment.
-	#[method(name = "commitmentRequest")]
+	/// Handlers receive the request extensions, which carry the authenticated `Caller` when auth is enabled.
+	#[method(name = "commitmentRequest", with_extensions)]
 	async fn commitment_request(&self, request: CommitmentRequest) -> RpcResult<SignedCommitment>;
 
 	/// Query a previously created commitment result.
 	#[method(name = "commitmentResult")]
 	async fn commitment_result(&self, request_hash: B256) -> RpcResult<SignedCommitment>;
 
+	/// Cancel a pending commitment before its constraint is posted.
+	/// The signature must be an EIP-191 personal signature over `cancel_hash(request_hash, slot)` by the sender of the
+	/// committed transaction, with `slot` the target slot of the commitment.
+	#[method(name = "commitmentCancel")]
+	async fn commitment_cancel(&self, request_hash: B256, signature: Signature) -> RpcResult<()>;
+
+	/// Query the active commitments for transactions sent by an address, for target slots in [start_slot, end_slot].
+	/// The range spans at most `MAX_SENDER_QUERY_SLOTS` slots and the signature must be an EIP-191 personal
+	/// signature over `sender_query_hash(sender, start_slot, end_slot, expiry)` by the sender. `expiry`, in unix
+	/// seconds, must not have passed and be at most `MAX_SENDER_QUERY_VALIDITY_SECS` away.
+	#[method(name = "commitmentsBySender")]
+	async fn commitments_by_sender(
+		&self,
+		sender: Address,
+		start_slot: u64,
+		end_slot: u64,
+		expiry: u64,
+		signature: Signature,
+	) -> RpcResult<Vec<SignedCommitment>>;
+
 	/// Query slots information.
 	#[method(name = "slots")]
 	async fn slots(&self) -> RpcResult<SlotInfoResponse>;
@@ -36,3 +60,12 @@ pub trait CommitmentsRpc {
 	#[method(name = "fee")]
 	async fn fee(&self, request: CommitmentRequest) -> RpcResult<FeeInfo>;
 }
+
+/// WebSocket subscription spec for the Commitments service.
+/// Kept separate from `CommitmentsRpc` so the HTTP client does not need subscription support.
+#[rpc(server)]
+pub trait CommitmentsPubSub {
+	/// Subscribe to status transitions of the commitments matching the filter.
+	#[subscription(name = "subscribeCommitments" => "commitmentEvent", unsubscribe = "unsubscribeCommitments", item = CommitmentEvent)]
+	async fn subscribe_commitments(&self, filter: CommitmentEventFilter) -> SubscriptionResult;
+}
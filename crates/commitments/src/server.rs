@@ -1,10 +1,24 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
 use axum::{Router, routing::get};
-use eyre::Result;
-use jsonrpsee::server::{RpcModule, Server};
+use jsonrpsee::PendingSubscriptionSink;
+use jsonrpsee::core::SubscriptionResult;
+use jsonrpsee::server::middleware::rpc::RpcServiceBuilder;
+use jsonrpsee::server::{RpcModule, Server, ServerHandle};
+use prometheus::Registry;
 use reqwest::Url;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tower::ServiceBuilder;
+use tracing::warn;
 
 use super::metrics::server_metrics_handler;
-use crate::rpc::CommitmentsRpcServer;
+use crate::auth::{ApiKeyLayer, Authenticator, WebSocketRateLimit};
+use crate::error::{CommitmentsError, Result};
+use crate::rpc::{CommitmentsPubSubServer, CommitmentsRpcServer};
+use crate::types::{CommitmentEvent, CommitmentEventFilter};
 
 /// Extra info the server harness needs from a handler.
 ///
@@ -13,44 +27,262 @@ use crate::rpc::CommitmentsRpcServer;
 pub trait CommitmentsServerInfo {
 	fn server_url(&self) -> Url;
 	fn metrics_url(&self) -> Url;
+
+	/// Authenticator applied to every request, requests are not authenticated when `None`
+	fn authenticator(&self) -> Option<Arc<Authenticator>> {
+		None
+	}
+
+	/// Registries of business metrics served next to the HTTP metrics
+	fn metrics_registries(&self) -> Vec<Registry> {
+		Vec::new()
+	}
+
+	/// Routes served next to `/metrics` on the metrics url, e.g. health and readiness checks
+	fn metrics_server_routes(&self) -> Router {
+		Router::new()
+	}
+}
+
+/// Running Commitments RPC and metrics servers
+pub struct CommitmentsServerHandle {
+	/// Address the Commitments RPC server is bound to
+	pub server_addr: SocketAddr,
+	/// Address the metrics server is bound to
+	pub metrics_addr: SocketAddr,
+	rpc: ServerHandle,
+	metrics: JoinHandle<()>,
 }
 
-pub async fn run_commitments_rpc_server<H>(handlers: H) -> Result<()>
+impl CommitmentsServerHandle {
+	/// Wait until both servers stopped, which they do once the shutdown token is cancelled
+	pub async fn stopped(self) {
+		self.rpc.stopped().await;
+		if let Err(e) = self.metrics.await {
+			tracing::error!("metrics server task failed: {}", e);
+		}
+	}
+}
+
+/// Serve the Commitments RPC over HTTP and WebSocket on the server url,
+/// along with the commitment event subscriptions (WebSocket only).
+///
+/// Both listeners are bound before anything is spawned, so a port already in use is returned as an error.
+/// Port 0 binds an ephemeral port, the bound addresses are on the returned handle. The servers stop when
+/// `shutdown` is cancelled.
+pub async fn run_commitments_rpc_server<H>(handlers: H, shutdown: CancellationToken) -> Result<CommitmentsServerHandle>
 where
-	H: CommitmentsRpcServer + CommitmentsServerInfo + Clone + Send + Sync + 'static,
+	H: CommitmentsRpcServer + CommitmentsPubSubServer + CommitmentsServerInfo + Clone + Send + Sync + 'static,
 {
 	// Get urls from the handler
 	let server_url: Url = handlers.server_url();
 	let metrics_url: Url = handlers.metrics_url();
+	let metrics_registries = handlers.metrics_registries();
+	let metrics_server_routes = handlers.metrics_server_routes();
 
 	// Get socket addresses
-	let server_socket = server_url.socket_addrs(|| None)?;
-	let server_socket = server_socket.first().ok_or(eyre::eyre!("Failed to get first socket address"))?;
+	let socket_addr = |url: &Url| {
+		url.socket_addrs(|| None)
+			.ok()
+			.and_then(|addrs| addrs.first().copied())
+			.ok_or_else(|| CommitmentsError::InvalidConfig(format!("Failed to resolve a socket address for {}", url)))
+	};
+	let server_socket = socket_addr(&server_url)?;
+	let metrics_socket = socket_addr(&metrics_url)?;
 
-	let metrics_socket = metrics_url.socket_addrs(|| None)?;
-	let metrics_socket = *metrics_socket.first().ok_or(eyre::eyre!("Failed to get first socket address"))?;
+	// Bind both listeners before starting either server
+	let authenticator = handlers.authenticator();
+	let http_middleware = ServiceBuilder::new().layer(ApiKeyLayer::new(authenticator.clone()));
+	let rpc_middleware =
+		RpcServiceBuilder::new().layer_fn(move |service| WebSocketRateLimit::new(service, authenticator.clone()));
+	let server = Server::builder()
+		.set_http_middleware(http_middleware)
+		.set_rpc_middleware(rpc_middleware)
+		.build(server_socket)
+		.await
+		.map_err(|e| {
+			CommitmentsError::Server(format!("Failed to bind the Commitments RPC server to {}: {}", server_socket, e))
+		})?;
+	let metrics_listener = tokio::net::TcpListener::bind(metrics_socket).await.map_err(|e| {
+		CommitmentsError::Server(format!("Failed to bind the metrics server to {}: {}", metrics_socket, e))
+	})?;
 
-	let server = Server::builder().build(server_socket).await?;
-	let module: RpcModule<_> = handlers.into_rpc();
+	let mut module: RpcModule<_> = CommitmentsRpcServer::into_rpc(handlers.clone());
+	module
+		.merge(CommitmentsPubSubServer::into_rpc(handlers))
+		.map_err(|e| CommitmentsError::Server(format!("Failed to register the subscriptions: {}", e)))?;
 
-	let addr = server.local_addr()?;
-	tracing::info!("Starting Commitments RPC server on {}", addr);
+	let server_addr = server.local_addr().map_err(|e| CommitmentsError::Server(e.to_string()))?;
+	let metrics_addr = metrics_listener.local_addr().map_err(|e| CommitmentsError::Server(e.to_string()))?;
+	tracing::info!("Starting Commitments RPC server on {}", server_addr);
+	tracing::info!("Starting metrics server on {}", metrics_addr);
 
 	// Spawn metrics server
+	let app = Router::new()
+		.route("/metrics", get(move || server_metrics_handler(metrics_registries.clone())))
+		.merge(metrics_server_routes);
+	let metrics_shutdown = shutdown.clone();
+	let metrics = tokio::spawn(async move {
+		if let Err(e) =
+			axum::serve(metrics_listener, app).with_graceful_shutdown(metrics_shutdown.cancelled_owned()).await
+		{
+			tracing::error!("metrics server error: {}", e);
+		}
+	});
+
+	let rpc = server.start(module);
+	let stop_handle = rpc.clone();
 	tokio::spawn(async move {
-		let app = Router::new().route("/metrics", get(server_metrics_handler));
-		match tokio::net::TcpListener::bind(metrics_socket).await {
-			Ok(listener) => {
-				if let Err(e) = axum::serve(listener, app).await {
-					tracing::error!("metrics server error: {}", e);
+		shutdown.cancelled().await;
+		// Already stopped when the server shut down on its own
+		let _ = stop_handle.stop();
+	});
+
+	Ok(CommitmentsServerHandle { server_addr, metrics_addr, rpc, metrics })
+}
+
+/// Forward the commitment events matching the filter to a subscriber until it unsubscribes
+pub async fn pipe_commitment_events(
+	pending: PendingSubscriptionSink,
+	filter: CommitmentEventFilter,
+	mut events: broadcast::Receiver<CommitmentEvent>,
+) -> SubscriptionResult {
+	let sink = pending.accept().await?;
+
+	loop {
+		tokio::select! {
+			_ = sink.closed() => break,
+			event = events.recv() => match event {
+				Ok(event) => {
+					if !filter.matches(&event) {
+						continue;
+					}
+					let message = serde_json::value::to_raw_value(&event)?;
+					if sink.send(message.into()).await.is_err() {
+						break;
+					}
 				}
+				Err(broadcast::error::RecvError::Lagged(skipped)) => {
+					warn!("Commitment events subscriber lagged, skipped {} events", skipped);
+				}
+				Err(broadcast::error::RecvError::Closed) => break,
 			}
-			Err(e) => tracing::error!("failed to bind metrics listener: {}", e),
 		}
-	});
-
-	let handle = server.start(module);
-	handle.stopped().await;
+	}
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::primitives::{Address, B256, Signature};
+	use jsonrpsee::Extensions;
+	use jsonrpsee::core::{RpcResult, async_trait};
+	use std::net::TcpListener as StdTcpListener;
+	use std::time::Duration;
+
+	use crate::error::CommitmentsRpcError;
+	use crate::types::{CommitmentRequest, FeeInfo, SignedCommitment, SlotInfoResponse};
+
+	/// Handler serving no commitments, listening on the given urls
+	#[derive(Clone)]
+	struct TestHandler {
+		server_url: Url,
+		metrics_url: Url,
+	}
+
+	impl TestHandler {
+		fn new(server_port: u16, metrics_port: u16) -> Self {
+			Self {
+				server_url: Url::parse(&format!("http://127.0.0.1:{}", server_port)).unwrap(),
+				metrics_url: Url::parse(&format!("http://127.0.0.1:{}", metrics_port)).unwrap(),
+			}
+		}
+	}
+
+	fn not_found<T>() -> RpcResult<T> {
+		Err(CommitmentsRpcError::NotFound("not served".to_string()).into())
+	}
+
+	impl CommitmentsServerInfo for TestHandler {
+		fn server_url(&self) -> Url {
+			self.server_url.clone()
+		}
+		fn metrics_url(&self) -> Url {
+			self.metrics_url.clone()
+		}
+	}
+
+	#[async_trait]
+	impl CommitmentsRpcServer for TestHandler {
+		async fn commitment_request(&self, _: &Extensions, _: CommitmentRequest) -> RpcResult<SignedCommitment> {
+			not_found()
+		}
+		async fn commitment_result(&self, _: B256) -> RpcResult<SignedCommitment> {
+			not_found()
+		}
+		async fn commitment_cancel(&self, _: B256, _: Signature) -> RpcResult<()> {
+			not_found()
+		}
+		async fn commitments_by_sender(
+			&self,
+			_: Address,
+			_: u64,
+			_: u64,
+			_: u64,
+			_: Signature,
+		) -> RpcResult<Vec<SignedCommitment>> {
+			not_found()
+		}
+		async fn slots(&self) -> RpcResult<SlotInfoResponse> {
+			not_found()
+		}
+		async fn fee(&self, _: CommitmentRequest) -> RpcResult<FeeInfo> {
+			not_found()
+		}
+	}
+
+	#[async_trait]
+	impl CommitmentsPubSubServer for TestHandler {
+		async fn subscribe_commitments(
+			&self,
+			pending: PendingSubscriptionSink,
+			_: CommitmentEventFilter,
+		) -> SubscriptionResult {
+			pending.reject(CommitmentsRpcError::NotFound("not served".to_string())).await;
+			Ok(())
+		}
+	}
+
+	#[tokio::test]
+	async fn test_ephemeral_ports_and_shutdown() {
+		let shutdown = CancellationToken::new();
+		let server = run_commitments_rpc_server(TestHandler::new(0, 0), shutdown.clone()).await.unwrap();
+		assert_ne!(server.server_addr.port(), 0);
+		assert_ne!(server.metrics_addr.port(), 0);
+
+		let response = reqwest::get(format!("http://{}/metrics", server.metrics_addr)).await.unwrap();
+		assert!(response.status().is_success());
+
+		let (server_addr, metrics_addr) = (server.server_addr, server.metrics_addr);
+		shutdown.cancel();
+		tokio::time::timeout(Duration::from_secs(5), server.stopped()).await.unwrap();
+		assert!(tokio::net::TcpStream::connect(server_addr).await.is_err());
+		assert!(tokio::net::TcpStream::connect(metrics_addr).await.is_err());
+	}
+
+	#[tokio::test]
+	async fn test_port_in_use_is_returned() {
+		let taken = StdTcpListener::bind("127.0.0.1:0").unwrap();
+		let port = taken.local_addr().unwrap().port();
+
+		let error =
+			run_commitments_rpc_server(TestHandler::new(0, port), CancellationToken::new()).await.err().unwrap();
+		assert!(error.to_string().contains("Failed to bind the metrics server"), "{}", error);
+
+		let error =
+			run_commitments_rpc_server(TestHandler::new(port, 0), CancellationToken::new()).await.err().unwrap();
+		assert!(error.to_string().contains("Failed to bind the Commitments RPC server"), "{}", error);
+	}
+}
@@ -1,4 +1,4 @@
-use alloy::primitives::{Address, B256, Bytes, Signature};
+use alloy::primitives::{Address, B256, Bytes, Signature, eip191_hash_message, keccak256};
 use serde::{Deserialize, Serialize};
 
 /// Request for a new SignedCommitment
@@ -39,6 +39,9 @@ pub struct Offering {
 pub struct SlotInfo {
 	pub slot: u64,
 	pub offerings: Vec<Offering>,
+	/// Gas still available for new commitments in the slot, if the gateway enforces a budget
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub remaining_gas: Option<u64>,
 }
 
 /// Response containing slot information
@@ -53,3 +56,89 @@ pub struct FeeInfo {
 	pub fee_payload: Bytes, // opaque fee payload
 	pub commitment_type: u64,
 }
+
+/// Lifecycle status of a commitment pushed to subscribers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitmentStatus {
+	/// The commitment was signed and stored by the gateway
+	Accepted,
+	/// The constraint backing the commitment was posted to the relay
+	Constrained,
+	/// The committed transaction landed on chain, or the slot's block honored the commitment
+	Included,
+	/// The excluded transaction stayed out of the slot's block
+	Excluded,
+	/// The slot's block did not honor the commitment
+	Missed,
+}
+
+/// Status transition of a commitment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentEvent {
+	pub request_hash: B256,
+	/// Address the commitment was requested for, e.g. the sender of the committed transaction
+	pub sender: Address,
+	pub slot: u64,
+	pub status: CommitmentStatus,
+}
+
+/// Selects the commitment events a subscriber receives
+/// Every event matches when no field is set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommitmentEventFilter {
+	pub sender: Option<Address>,
+	pub request_hash: Option<B256>,
+}
+
+impl CommitmentEventFilter {
+	pub fn matches(&self, event: &CommitmentEvent) -> bool {
+		self.sender.is_none_or(|sender| sender == event.sender)
+			&& self.request_hash.is_none_or(|request_hash| request_hash == event.request_hash)
+	}
+}
+
+/// Most target slots a single `commitmentsBySender` query may span
+pub const MAX_SENDER_QUERY_SLOTS: u64 = 64;
+
+/// Domain tag of the `commitmentCancel` digest, so the signature authorizes nothing else
+pub const CANCEL_DOMAIN: &[u8] = b"fabric:commitmentCancel";
+
+/// Digest the sender of a committed transaction signs to cancel the commitment of `request_hash` for `slot`
+/// Layout: keccak256(CANCEL_DOMAIN || request_hash || slot_be). It is signed as an EIP-191 personal message, see
+/// `cancel_signing_hash`
+pub fn cancel_hash(request_hash: &B256, slot: u64) -> B256 {
+	let mut preimage = CANCEL_DOMAIN.to_vec();
+	preimage.extend_from_slice(request_hash.as_slice());
+	preimage.extend_from_slice(&slot.to_be_bytes());
+	keccak256(preimage)
+}
+
+/// Hash the sender's key signs for a `commitmentCancel`: the EIP-191 personal message hash of `cancel_hash`
+pub fn cancel_signing_hash(request_hash: &B256, slot: u64) -> B256 {
+	eip191_hash_message(cancel_hash(request_hash, slot))
+}
+
+/// Longest a `commitmentsBySender` signature may stay valid, its expiry is at most this far in the future
+pub const MAX_SENDER_QUERY_VALIDITY_SECS: u64 = 300;
+
+/// Domain tag of the `commitmentsBySender` digest, so the signature authorizes nothing else
+pub const SENDER_QUERY_DOMAIN: &[u8] = b"fabric:commitmentsBySender";
+
+/// Digest a sender signs to query its commitments for target slots in [start_slot, end_slot] until `expiry`
+/// Layout: keccak256(SENDER_QUERY_DOMAIN || sender (20 bytes) || start_slot_be || end_slot_be || expiry_be), with
+/// `expiry` in unix seconds. It is signed as an EIP-191 personal message, see `sender_query_signing_hash`
+pub fn sender_query_hash(sender: &Address, start_slot: u64, end_slot: u64, expiry: u64) -> B256 {
+	let mut preimage = SENDER_QUERY_DOMAIN.to_vec();
+	preimage.extend_from_slice(sender.as_slice());
+	preimage.extend_from_slice(&start_slot.to_be_bytes());
+	preimage.extend_from_slice(&end_slot.to_be_bytes());
+	preimage.extend_from_slice(&expiry.to_be_bytes());
+	keccak256(preimage)
+}
+
+/// Hash the sender's key signs for a `commitmentsBySender` query: the EIP-191 personal message hash of
+/// `sender_query_hash`, what `personal_sign` or `Signer::sign_message` produce over its 32 bytes
+pub fn sender_query_signing_hash(sender: &Address, start_slot: u64, end_slot: u64, expiry: u64) -> B256 {
+	eip191_hash_message(sender_query_hash(sender, start_slot, end_slot, expiry))
+}
@@ -0,0 +1,109 @@
+//! Liveness and readiness endpoints of the services
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::get};
+use eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+
+/// Liveness, answered while the process serves requests
+pub const HEALTHZ: &str = "/healthz";
+/// Readiness, answered with 503 while a dependency of the service is unavailable
+pub const READYZ: &str = "/readyz";
+
+/// Time each readiness check is given before it counts as failed
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Result of one dependency check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadinessCheck {
+	pub name: String,
+	pub ok: bool,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+/// Dependency checks of a service, ready when every check passed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Readiness {
+	pub ready: bool,
+	pub checks: Vec<ReadinessCheck>,
+}
+
+impl Default for Readiness {
+	fn default() -> Self {
+		Self { ready: true, checks: Vec::new() }
+	}
+}
+
+impl Readiness {
+	/// Record the outcome of the check `name`
+	pub fn add(&mut self, name: &str, result: Result<()>) {
+		let error = result.err().map(|e| e.to_string());
+		self.ready &= error.is_none();
+		self.checks.push(ReadinessCheck { name: name.to_string(), ok: error.is_none(), error });
+	}
+
+	/// Run the check `name`, which fails if it does not finish in time
+	pub async fn check(&mut self, name: &str, check: impl Future<Output = Result<()>>) {
+		let result = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+			Ok(result) => result,
+			Err(_) => Err(eyre!("timed out after {}ms", CHECK_TIMEOUT.as_millis())),
+		};
+		self.add(name, result);
+	}
+
+	/// Ok when ready, else an error naming the failed checks
+	pub fn into_result(self) -> Result<()> {
+		if self.ready {
+			return Ok(());
+		}
+		let failed = self
+			.checks
+			.iter()
+			.filter(|check| !check.ok)
+			.map(|check| format!("{}: {}", check.name, check.error.as_deref().unwrap_or("failed")))
+			.collect::<Vec<_>>();
+		Err(eyre!("Not ready: {}", failed.join(", ")))
+	}
+}
+
+/// Checks whether the dependencies of a service are available
+#[async_trait]
+pub trait ReadinessProbe: Send + Sync + 'static {
+	async fn readiness(&self) -> Readiness;
+}
+
+/// Router serving `/healthz` and `/readyz` for `probe`
+pub fn health_router<P: ReadinessProbe>(probe: Arc<P>) -> Router {
+	Router::new().route(HEALTHZ, get(|| async { StatusCode::OK })).route(READYZ, get(readyz::<P>)).with_state(probe)
+}
+
+async fn readyz<P: ReadinessProbe>(State(probe): State<Arc<P>>) -> impl IntoResponse {
+	let readiness = probe.readiness().await;
+	let status = if readiness.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+	(status, Json(readiness))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn test_readiness_fails_on_any_failed_or_slow_check() {
+		let mut readiness = Readiness::default();
+		readiness.check("db", async { Ok(()) }).await;
+		assert!(readiness.ready);
+
+		readiness.add("relay", Err(eyre!("connection refused")));
+		readiness.check("signer", std::future::pending()).await;
+		assert!(!readiness.ready);
+		assert_eq!(readiness.checks.iter().filter(|check| check.ok).count(), 1);
+
+		let error = readiness.into_result().unwrap_err().to_string();
+		assert!(error.contains("relay: connection refused"), "{}", error);
+		assert!(error.contains("signer: timed out"), "{}", error);
+	}
+}
@@ -0,0 +1,74 @@
+//! Connection settings of the HTTP clients between services
+use std::time::Duration;
+
+use eyre::Result;
+use reqwest::{Client, ClientBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Timeout, connection pool and socket settings of an HTTP client
+///
+/// The defaults keep reqwest's pooling with the 30 second timeout the clients always had. For latency-sensitive
+/// hops, such as the gateway posting constraints in the last second of a slot, keeping idle connections open and
+/// probing them with TCP keep-alive avoids a new TCP (and TLS) handshake on the critical path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpClientConfig {
+	/// Timeout of a whole request, from connecting to reading the response body (in milliseconds)
+	pub timeout_ms: u64,
+	/// Idle connections kept open per host, unlimited if not set
+	pub pool_max_idle_per_host: Option<usize>,
+	/// How long an idle connection is kept open (in seconds), idle connections are never closed if not set
+	pub pool_idle_timeout_secs: Option<u64>,
+	/// Interval of TCP keep-alive probes on open connections (in seconds), no probes are sent if not set
+	pub tcp_keepalive_secs: Option<u64>,
+	/// Disable Nagle's algorithm, so small requests are sent without waiting for more data
+	pub tcp_nodelay: bool,
+}
+
+impl Default for HttpClientConfig {
+	fn default() -> Self {
+		Self {
+			timeout_ms: 30_000,
+			pool_max_idle_per_host: None,
+			pool_idle_timeout_secs: Some(90),
+			tcp_keepalive_secs: None,
+			tcp_nodelay: true,
+		}
+	}
+}
+
+impl HttpClientConfig {
+	pub fn timeout(&self) -> Duration {
+		Duration::from_millis(self.timeout_ms)
+	}
+
+	/// Builder of a client with these settings, for callers adding their own
+	pub fn builder(&self) -> ClientBuilder {
+		let mut builder = Client::builder()
+			.timeout(self.timeout())
+			.pool_idle_timeout(self.pool_idle_timeout_secs.map(Duration::from_secs))
+			.tcp_keepalive(self.tcp_keepalive_secs.map(Duration::from_secs))
+			.tcp_nodelay(self.tcp_nodelay);
+		if let Some(max_idle) = self.pool_max_idle_per_host {
+			builder = builder.pool_max_idle_per_host(max_idle);
+		}
+		builder
+	}
+
+	pub fn client(&self) -> Result<Client> {
+		Ok(self.builder().build()?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_missing_fields_take_the_defaults() {
+		let config: HttpClientConfig = toml::from_str("tcp_keepalive_secs = 15").unwrap();
+		assert_eq!(config, HttpClientConfig { tcp_keepalive_secs: Some(15), ..HttpClientConfig::default() });
+		assert_eq!(config.timeout(), Duration::from_secs(30));
+		assert!(config.client().is_ok());
+	}
+}
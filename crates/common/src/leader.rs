@@ -0,0 +1,559 @@
+//! Leader election between instances of a service, on a lease-backed lock held in etcd
+//!
+//! The leader holds a key attached to an etcd lease it keeps alive. If the leader stops renewing, etcd drops the
+//! key when the lease expires and a standby instance takes it over. The lock lives outside the instances, so
+//! unlike a key in each instance's own database it is seen by all of them.
+//!
+//! An instance only considers itself the leader until its lease could have expired, counted from the last renewal
+//! etcd confirmed, so a stalled renewal never leaves two leaders. What the leader must not lose with it, e.g. the
+//! commitments it signed, is handed over through etcd under the lock key for the next leader to pick up.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use eyre::{Result, WrapErr, eyre};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Where the leadership lock lives and how this instance identifies in it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderElectionConfig {
+	/// Base URL of the etcd v3 JSON API, e.g. http://etcd:2379
+	pub etcd_url: Url,
+
+	/// Key of the lock, shared by every instance of the service
+	pub lock_key: String,
+
+	/// Name of this instance, stored as the value of the lock while it leads
+	pub instance_id: String,
+
+	/// Lease time to live (in seconds), how long a failed leader keeps the lock before a standby takes over
+	#[serde(default = "default_lease_ttl_seconds")]
+	pub lease_ttl_seconds: u64,
+}
+
+/// Lease-backed lock in etcd, through its v3 JSON gateway
+pub struct EtcdLock {
+	client: reqwest::Client,
+	url: Url,
+	key: String,
+	value: String,
+	ttl_seconds: u64,
+}
+
+impl EtcdLock {
+	/// Requests time out after a quarter of the lease TTL, so a renewal fails well before the lease could expire
+	pub fn new(config: &LeaderElectionConfig) -> Result<Self> {
+		let timeout = Duration::from_millis((config.lease_ttl_seconds * 1000 / 4).max(1));
+		Ok(Self {
+			client: reqwest::Client::builder().timeout(timeout).build().wrap_err("Failed to create the etcd client")?,
+			url: config.etcd_url.clone(),
+			key: BASE64.encode(&config.lock_key),
+			value: BASE64.encode(&config.instance_id),
+			ttl_seconds: config.lease_ttl_seconds,
+		})
+	}
+
+	/// Take the lock under a new lease if no instance holds it, returns the lease on success
+	pub async fn try_acquire(&self) -> Result<Option<i64>> {
+		let grant: LeaseGrant = self.call("v3/lease/grant", json!({ "TTL": self.ttl_seconds })).await?;
+		let lease = grant.id.parse::<i64>().wrap_err("Invalid lease ID")?;
+
+		// Put the key only if it does not exist, i.e. its create revision is zero
+		let txn: Txn = self
+			.call(
+				"v3/kv/txn",
+				json!({
+					"compare": [{ "key": self.key, "target": "CREATE", "create_revision": "0" }],
+					"success": [{ "request_put": { "key": self.key, "value": self.value, "lease": lease.to_string() } }],
+				}),
+			)
+			.await?;
+		if txn.succeeded {
+			return Ok(Some(lease));
+		}
+
+		// Do not leave the unused lease behind until it expires
+		self.revoke(lease).await?;
+		Ok(None)
+	}
+
+	/// Renew the lease the lock is held under, returns whether it is still alive
+	pub async fn keep_alive(&self, lease: i64) -> Result<bool> {
+		let response: KeepAlive = self.call("v3/lease/keepalive", json!({ "ID": lease.to_string() })).await?;
+		// An expired lease is answered with no TTL
+		Ok(response.result.and_then(|result| result.ttl).is_some_and(|ttl| ttl != "0"))
+	}
+
+	/// Revoke the lease, which deletes the lock if it is held under it
+	pub async fn revoke(&self, lease: i64) -> Result<()> {
+		self.call::<Value>("v3/lease/revoke", json!({ "ID": lease.to_string() })).await?;
+		Ok(())
+	}
+
+	/// Store `value` under `key`, outside of any lease so it outlives the instance that wrote it
+	pub async fn put(&self, key: &str, value: &[u8]) -> Result<()> {
+		self.call::<Value>("v3/kv/put", json!({ "key": BASE64.encode(key), "value": BASE64.encode(value) })).await?;
+		Ok(())
+	}
+
+	/// Values of the keys starting with `prefix`, in key order
+	pub async fn get_prefix(&self, prefix: &str) -> Result<Vec<Vec<u8>>> {
+		let range: Range = self
+			.call(
+				"v3/kv/range",
+				json!({ "key": BASE64.encode(prefix), "range_end": BASE64.encode(prefix_end(prefix)) }),
+			)
+			.await?;
+		range
+			.kvs
+			.iter()
+			.map(|kv| BASE64.decode(kv.value.as_deref().unwrap_or_default()).wrap_err("Invalid etcd value"))
+			.collect()
+	}
+
+	/// Delete `key`
+	pub async fn delete(&self, key: &str) -> Result<()> {
+		self.call::<Value>("v3/kv/deleterange", json!({ "key": BASE64.encode(key) })).await?;
+		Ok(())
+	}
+
+	/// Delete the keys from `start` up to, excluding, `end`
+	pub async fn delete_range(&self, start: &str, end: &[u8]) -> Result<()> {
+		self.call::<Value>(
+			"v3/kv/deleterange",
+			json!({ "key": BASE64.encode(start), "range_end": BASE64.encode(end) }),
+		)
+		.await?;
+		Ok(())
+	}
+
+	async fn call<T: DeserializeOwned>(&self, path: &str, body: Value) -> Result<T> {
+		let url = self.url.join(path).wrap_err("Invalid etcd URL")?;
+		let response =
+			self.client.post(url).json(&body).send().await.wrap_err_with(|| format!("etcd {} request failed", path))?;
+		let status = response.status();
+		if !status.is_success() {
+			let text = response.text().await.unwrap_or_default();
+			return Err(eyre!("etcd {} failed (status {}): {}", path, status, text));
+		}
+		response.json::<T>().await.wrap_err_with(|| format!("Invalid etcd {} response", path))
+	}
+}
+
+/// Tracks whether this instance is the leader, acquiring and renewing the lock in the background
+pub struct LeaderElection {
+	lock: EtcdLock,
+	instance_id: String,
+	/// Prefix of the keys the leader hands over to the next one
+	handover_prefix: String,
+	renew_interval: Duration,
+	lease_ttl_ms: u64,
+	/// Reference point of `leader_until_ms`
+	started: Instant,
+	/// Milliseconds after `started` until which the lease is certainly alive, 0 when not leading
+	leader_until_ms: AtomicU64,
+	lease: Mutex<Option<i64>>,
+}
+
+impl LeaderElection {
+	pub fn new(config: &LeaderElectionConfig) -> Result<Self> {
+		Ok(Self {
+			lock: EtcdLock::new(config)?,
+			instance_id: config.instance_id.clone(),
+			handover_prefix: format!("{}/handover/", config.lock_key),
+			// Renew well within the TTL so one failed renewal does not cost the leadership
+			renew_interval: Duration::from_millis(config.lease_ttl_seconds * 1000 / 3),
+			lease_ttl_ms: config.lease_ttl_seconds * 1000,
+			started: Instant::now(),
+			leader_until_ms: AtomicU64::new(0),
+			lease: Mutex::new(None),
+		})
+	}
+
+	/// Whether this instance currently holds the lock
+	/// Leadership lapses once the lease could have expired since the last confirmed renewal, even if the renewal
+	/// task is stalled
+	pub fn is_leader(&self) -> bool {
+		self.elapsed_ms() < self.leader_until_ms.load(Ordering::Acquire)
+	}
+
+	fn elapsed_ms(&self) -> u64 {
+		self.started.elapsed().as_millis() as u64
+	}
+
+	fn step_down(&self) {
+		self.leader_until_ms.store(0, Ordering::Release);
+	}
+
+	/// Acquire the lock as a standby, renew it as the leader
+	pub async fn run(&self) -> Result<()> {
+		info!("Starting leader election as {}", self.instance_id);
+		loop {
+			if let Err(e) = self.step().await {
+				warn!("Leader election step failed: {}", e);
+			}
+			tokio::time::sleep(self.renew_interval).await;
+		}
+	}
+
+	async fn step(&self) -> Result<()> {
+		let mut lease = self.lease.lock().await;
+		if let Some(held) = *lease {
+			// The lease lives for its TTL from when etcd received the renewal, which is after it was sent
+			let sent_ms = self.elapsed_ms();
+			// A failed renewal steps down at once, the lease may expire before the next attempt
+			let alive = self.lock.keep_alive(held).await.unwrap_or_else(|e| {
+				warn!("Failed to renew the leadership lease: {}", e);
+				false
+			});
+			if alive {
+				self.leader_until_ms.store(sent_ms + self.lease_ttl_ms, Ordering::Release);
+				return Ok(());
+			}
+			self.step_down();
+			*lease = None;
+			warn!("Instance {} lost the leadership", self.instance_id);
+		}
+
+		let sent_ms = self.elapsed_ms();
+		if let Some(acquired) = self.lock.try_acquire().await? {
+			*lease = Some(acquired);
+			self.leader_until_ms.store(sent_ms + self.lease_ttl_ms, Ordering::Release);
+			info!("Instance {} is now the leader", self.instance_id);
+		}
+		Ok(())
+	}
+
+	/// Hand `record` over to the next leader, under `id` among the records of `slot`
+	/// Stored before the record takes effect, e.g. before a signed commitment is returned, so a failed leader
+	/// leaves nothing behind
+	pub async fn hand_over(&self, slot: u64, id: &str, record: &[u8]) -> Result<()> {
+		self.lock.put(&self.handover_key(slot, id), record).await
+	}
+
+	/// Withdraw the record handed over under `id` for `slot`
+	pub async fn withdraw_handed_over(&self, slot: u64, id: &str) -> Result<()> {
+		self.lock.delete(&self.handover_key(slot, id)).await
+	}
+
+	/// Records handed over for `slot` by this and previous leaders
+	pub async fn handed_over(&self, slot: u64) -> Result<Vec<Vec<u8>>> {
+		self.lock.get_prefix(&self.handover_slot_prefix(slot)).await
+	}
+
+	/// Delete the records handed over for the slots before `slot`
+	pub async fn prune_handed_over(&self, slot: u64) -> Result<()> {
+		self.lock.delete_range(&self.handover_prefix, self.handover_slot_prefix(slot).as_bytes()).await
+	}
+
+	/// Slots are zero padded so the keys sort by slot
+	fn handover_slot_prefix(&self, slot: u64) -> String {
+		format!("{}{:020}/", self.handover_prefix, slot)
+	}
+
+	fn handover_key(&self, slot: u64, id: &str) -> String {
+		format!("{}{}", self.handover_slot_prefix(slot), id)
+	}
+
+	/// Give up the leadership, so a standby takes over without waiting for the lease to expire
+	pub async fn resign(&self) -> Result<()> {
+		self.step_down();
+		if let Some(lease) = self.lease.lock().await.take() {
+			self.lock.revoke(lease).await?;
+			info!("Instance {} resigned the leadership", self.instance_id);
+		}
+		Ok(())
+	}
+}
+
+#[derive(Deserialize)]
+struct LeaseGrant {
+	#[serde(rename = "ID")]
+	id: String,
+}
+
+#[derive(Deserialize)]
+struct Txn {
+	#[serde(default)]
+	succeeded: bool,
+}
+
+#[derive(Deserialize)]
+struct KeepAlive {
+	result: Option<KeepAliveResult>,
+}
+
+#[derive(Deserialize)]
+struct KeepAliveResult {
+	#[serde(rename = "TTL")]
+	ttl: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Range {
+	#[serde(default)]
+	kvs: Vec<KeyValue>,
+}
+
+#[derive(Deserialize)]
+struct KeyValue {
+	/// Omitted for empty values
+	value: Option<String>,
+}
+
+/// End of the range of keys starting with `prefix`, i.e. the prefix with its last byte incremented
+fn prefix_end(prefix: &str) -> Vec<u8> {
+	let mut end = prefix.as_bytes().to_vec();
+	while let Some(last) = end.pop() {
+		if last < u8::MAX {
+			end.push(last + 1);
+			return end;
+		}
+	}
+	// Every key, as etcd reads a range end of "\0"
+	vec![0]
+}
+
+fn default_lease_ttl_seconds() -> u64 {
+	10
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use axum::extract::State;
+	use axum::routing::post;
+	use axum::{Json, Router};
+	use std::collections::{BTreeMap, HashMap};
+	use std::sync::Arc;
+	use std::sync::atomic::AtomicBool;
+
+	/// In-memory etcd answering the lease and key-value calls of `EtcdLock`, with leases expiring in real time
+	#[derive(Default)]
+	struct MockEtcd {
+		state: std::sync::Mutex<MockEtcdState>,
+		/// Keep-alive requests hang past the client timeout and renew nothing
+		stall_keep_alive: AtomicBool,
+	}
+
+	#[derive(Default)]
+	struct MockEtcdState {
+		next_lease: i64,
+		leases: HashMap<i64, (Instant, u64)>,
+		kv: BTreeMap<Vec<u8>, (Vec<u8>, Option<i64>)>,
+	}
+
+	impl MockEtcdState {
+		fn expire_leases(&mut self) {
+			let now = Instant::now();
+			self.leases.retain(|_, (deadline, _)| *deadline > now);
+			let leases = &self.leases;
+			self.kv.retain(|_, (_, lease)| lease.is_none_or(|lease| leases.contains_key(&lease)));
+		}
+	}
+
+	fn decode(value: &Value) -> Vec<u8> {
+		BASE64.decode(value.as_str().unwrap_or_default()).unwrap()
+	}
+
+	fn lease_id(body: &Value) -> i64 {
+		body["ID"].as_str().unwrap().parse().unwrap()
+	}
+
+	/// Keys of a request, a single key without `range_end`
+	fn in_range(body: &Value, key: &[u8]) -> bool {
+		let start = decode(&body["key"]);
+		match body.get("range_end") {
+			Some(end) => start.as_slice() <= key && key < decode(end).as_slice(),
+			None => start == key,
+		}
+	}
+
+	async fn grant(State(etcd): State<Arc<MockEtcd>>, Json(body): Json<Value>) -> Json<Value> {
+		let ttl = body["TTL"].as_u64().unwrap();
+		let mut state = etcd.state.lock().unwrap();
+		state.next_lease += 1;
+		let lease = state.next_lease;
+		state.leases.insert(lease, (Instant::now() + Duration::from_secs(ttl), ttl));
+		Json(json!({ "ID": lease.to_string(), "TTL": ttl.to_string() }))
+	}
+
+	async fn txn(State(etcd): State<Arc<MockEtcd>>, Json(body): Json<Value>) -> Json<Value> {
+		let mut state = etcd.state.lock().unwrap();
+		state.expire_leases();
+		let key = decode(&body["compare"][0]["key"]);
+		if state.kv.contains_key(&key) {
+			return Json(json!({}));
+		}
+		let put = &body["success"][0]["request_put"];
+		let lease = put["lease"].as_str().unwrap().parse().unwrap();
+		state.kv.insert(key, (decode(&put["value"]), Some(lease)));
+		Json(json!({ "succeeded": true }))
+	}
+
+	async fn keep_alive(State(etcd): State<Arc<MockEtcd>>, Json(body): Json<Value>) -> Json<Value> {
+		let lease = lease_id(&body);
+		if etcd.stall_keep_alive.load(Ordering::Acquire) {
+			tokio::time::sleep(Duration::from_secs(2)).await;
+			return Json(json!({ "result": { "ID": lease.to_string() } }));
+		}
+		let mut state = etcd.state.lock().unwrap();
+		state.expire_leases();
+		match state.leases.get_mut(&lease) {
+			Some((deadline, ttl)) => {
+				*deadline = Instant::now() + Duration::from_secs(*ttl);
+				Json(json!({ "result": { "ID": lease.to_string(), "TTL": ttl.to_string() } }))
+			}
+			None => Json(json!({ "result": { "ID": lease.to_string() } })),
+		}
+	}
+
+	async fn revoke(State(etcd): State<Arc<MockEtcd>>, Json(body): Json<Value>) -> Json<Value> {
+		let lease = lease_id(&body);
+		let mut state = etcd.state.lock().unwrap();
+		state.leases.remove(&lease);
+		state.kv.retain(|_, (_, key_lease)| *key_lease != Some(lease));
+		Json(json!({}))
+	}
+
+	async fn put(State(etcd): State<Arc<MockEtcd>>, Json(body): Json<Value>) -> Json<Value> {
+		etcd.state.lock().unwrap().kv.insert(decode(&body["key"]), (decode(&body["value"]), None));
+		Json(json!({}))
+	}
+
+	async fn range(State(etcd): State<Arc<MockEtcd>>, Json(body): Json<Value>) -> Json<Value> {
+		let mut state = etcd.state.lock().unwrap();
+		state.expire_leases();
+		let kvs = state
+			.kv
+			.iter()
+			.filter(|(key, _)| in_range(&body, key))
+			.map(|(key, (value, _))| json!({ "key": BASE64.encode(key), "value": BASE64.encode(value) }))
+			.collect::<Vec<_>>();
+		Json(json!({ "kvs": kvs }))
+	}
+
+	async fn delete_range(State(etcd): State<Arc<MockEtcd>>, Json(body): Json<Value>) -> Json<Value> {
+		etcd.state.lock().unwrap().kv.retain(|key, _| !in_range(&body, key));
+		Json(json!({}))
+	}
+
+	/// Serve a mock etcd on a free port, returns it with its URL
+	async fn start_mock_etcd() -> (Arc<MockEtcd>, Url) {
+		let etcd = Arc::new(MockEtcd::default());
+		let router = Router::new()
+			.route("/v3/lease/grant", post(grant))
+			.route("/v3/kv/txn", post(txn))
+			.route("/v3/lease/keepalive", post(keep_alive))
+			.route("/v3/lease/revoke", post(revoke))
+			.route("/v3/kv/put", post(put))
+			.route("/v3/kv/range", post(range))
+			.route("/v3/kv/deleterange", post(delete_range))
+			.with_state(Arc::clone(&etcd));
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+		tokio::spawn(async move { axum::serve(listener, router).await });
+		(etcd, url)
+	}
+
+	fn election(url: &Url, instance_id: &str) -> LeaderElection {
+		LeaderElection::new(&LeaderElectionConfig {
+			etcd_url: url.clone(),
+			lock_key: "gateway".to_string(),
+			instance_id: instance_id.to_string(),
+			lease_ttl_seconds: 1,
+		})
+		.unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_renewal_step_down_and_takeover() {
+		let (etcd, url) = start_mock_etcd().await;
+		let leader = election(&url, "a");
+		let standby = election(&url, "b");
+
+		leader.step().await.unwrap();
+		standby.step().await.unwrap();
+		assert!(leader.is_leader());
+		assert!(!standby.is_leader());
+
+		// Renewals keep the leadership past the TTL
+		tokio::time::sleep(Duration::from_millis(600)).await;
+		leader.step().await.unwrap();
+		tokio::time::sleep(Duration::from_millis(600)).await;
+		assert!(leader.is_leader());
+		leader.step().await.unwrap();
+
+		// A stalled renewal times out and steps down, the standby takes over once the lease expired
+		etcd.stall_keep_alive.store(true, Ordering::Release);
+		leader.step().await.unwrap();
+		assert!(!leader.is_leader());
+		standby.step().await.unwrap();
+		assert!(!standby.is_leader());
+		tokio::time::sleep(Duration::from_millis(1_100)).await;
+		standby.step().await.unwrap();
+		assert!(standby.is_leader());
+
+		etcd.stall_keep_alive.store(false, Ordering::Release);
+		leader.step().await.unwrap();
+		assert!(!leader.is_leader());
+	}
+
+	#[tokio::test]
+	async fn test_leadership_lapses_without_renewal() {
+		let (_etcd, url) = start_mock_etcd().await;
+		let leader = election(&url, "a");
+
+		leader.step().await.unwrap();
+		assert!(leader.is_leader());
+		// The renewal task never ran again, the lease may have expired
+		tokio::time::sleep(Duration::from_millis(1_050)).await;
+		assert!(!leader.is_leader());
+
+		// Resigning frees the lock for a standby at once
+		leader.step().await.unwrap();
+		leader.resign().await.unwrap();
+		let standby = election(&url, "b");
+		standby.step().await.unwrap();
+		assert!(standby.is_leader());
+	}
+
+	#[tokio::test]
+	async fn test_hand_over() {
+		let (_etcd, url) = start_mock_etcd().await;
+		let leader = election(&url, "a");
+		leader.hand_over(10, "x", b"one").await.unwrap();
+		leader.hand_over(10, "y", b"two").await.unwrap();
+		leader.hand_over(100, "z", b"three").await.unwrap();
+
+		let next = election(&url, "b");
+		assert_eq!(next.handed_over(10).await.unwrap(), vec![b"one".to_vec(), b"two".to_vec()]);
+		next.withdraw_handed_over(10, "x").await.unwrap();
+		assert_eq!(next.handed_over(10).await.unwrap(), vec![b"two".to_vec()]);
+
+		next.prune_handed_over(100).await.unwrap();
+		assert!(next.handed_over(10).await.unwrap().is_empty());
+		assert_eq!(next.handed_over(100).await.unwrap(), vec![b"three".to_vec()]);
+	}
+
+	#[test]
+	fn test_etcd_responses() {
+		let grant: LeaseGrant = serde_json::from_str(r#"{"header":{},"ID":"7587862072907310864","TTL":"10"}"#).unwrap();
+		assert_eq!(grant.id.parse::<i64>().unwrap(), 7587862072907310864);
+
+		// A failed compare omits `succeeded`
+		let txn: Txn = serde_json::from_str(r#"{"header":{},"responses":[]}"#).unwrap();
+		assert!(!txn.succeeded);
+
+		let alive: KeepAlive = serde_json::from_str(r#"{"result":{"ID":"1","TTL":"10"}}"#).unwrap();
+		assert_eq!(alive.result.and_then(|result| result.ttl).as_deref(), Some("10"));
+		let expired: KeepAlive = serde_json::from_str(r#"{"result":{"ID":"1"}}"#).unwrap();
+		assert!(expired.result.and_then(|result| result.ttl).is_none());
+	}
+}
@@ -1,3 +1,6 @@
+pub mod health;
+pub mod http;
+pub mod leader;
 pub mod logging;
 pub mod metrics;
 pub mod storage;
@@ -1,7 +1,121 @@
 //! Logging setup helpers shared across binaries/services.
 
-use eyre::Result;
-use tracing_subscriber::EnvFilter;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use eyre::{Result, WrapErr, eyre};
+use serde::{Deserialize, Serialize};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry, filter::LevelFilter};
+
+/// Logging settings of a service, the `[logging]` table of its config
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+	/// Output format of stdout and the log file
+	pub format: LogFormat,
+	/// Level per module on top of the log level, e.g. `{ fabric_inclusion = "debug", hyper = "warn" }`
+	pub modules: BTreeMap<String, String>,
+	/// Rolling log file written next to stdout, not written if not set
+	pub file: Option<LogFileConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+	/// Human readable lines
+	#[default]
+	Text,
+	/// One JSON object per line, for log collectors such as Loki or ELK
+	Json,
+}
+
+/// Rolling log file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogFileConfig {
+	/// Directory of the log files
+	pub directory: PathBuf,
+	/// Name of the log files, followed by the date of their period and `.log`
+	pub prefix: String,
+	/// How often a new log file is started
+	#[serde(default)]
+	pub rotation: LogRotation,
+	/// Number of log files kept, older ones are deleted. All files are kept if not set
+	#[serde(default)]
+	pub max_files: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+	Minutely,
+	Hourly,
+	#[default]
+	Daily,
+	Never,
+}
+
+impl From<LogRotation> for Rotation {
+	fn from(rotation: LogRotation) -> Self {
+		match rotation {
+			LogRotation::Minutely => Rotation::MINUTELY,
+			LogRotation::Hourly => Rotation::HOURLY,
+			LogRotation::Daily => Rotation::DAILY,
+			LogRotation::Never => Rotation::NEVER,
+		}
+	}
+}
+
+impl LoggingConfig {
+	/// Ok if every module level is a level and the log file settings are usable
+	pub fn check(&self) -> Result<()> {
+		self.module_directives()?;
+		if let Some(file) = &self.file {
+			if file.directory.as_os_str().is_empty() {
+				return Err(eyre!("file.directory must not be empty"));
+			}
+			if file.prefix.trim().is_empty() {
+				return Err(eyre!("file.prefix must not be empty"));
+			}
+			if file.max_files == Some(0) {
+				return Err(eyre!("file.max_files must be greater than zero"));
+			}
+		}
+		Ok(())
+	}
+
+	/// `module=level` filter directives of `modules`
+	fn module_directives(&self) -> Result<Vec<String>> {
+		self.modules
+			.iter()
+			.map(|(module, level)| {
+				level
+					.parse::<LevelFilter>()
+					.map(|level| format!("{}={}", module, level))
+					.map_err(|_| eyre!("modules.{}: '{}' is not a log level", module, level))
+			})
+			.collect()
+	}
+
+	/// `filter` followed by the module levels
+	fn env_filter(&self, filter: &str) -> Result<EnvFilter> {
+		let mut env_filter = EnvFilter::try_new(filter).wrap_err_with(|| format!("Invalid log filter '{}'", filter))?;
+		for directive in self.module_directives()? {
+			env_filter = env_filter.add_directive(directive.parse()?);
+		}
+		Ok(env_filter)
+	}
+}
+
+/// Keeps the log file written until dropped, hold it for the lifetime of the service
+#[must_use = "the log file stops being written when the guard is dropped"]
+pub struct LoggingGuard {
+	_file: Option<WorkerGuard>,
+}
 
 /// Initialize tracing-subscriber with the provided log level.
 ///
@@ -9,13 +123,88 @@ use tracing_subscriber::EnvFilter;
 /// enables thread IDs and names, and disables target names for cleaner output.
 pub fn setup_logging(log_level: &str) -> Result<()> {
 	let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(log_level));
+	let _guard = init(filter, &LoggingConfig::default())?;
+	Ok(())
+}
+
+/// Initialize tracing-subscriber with the provided filter and the service's logging config, ignoring RUST_LOG.
+///
+/// Used when the level comes from a command line flag, which takes precedence over the environment.
+/// Logs go to stdout and, with `config.file`, to a rolling file written from a background thread.
+pub fn setup_logging_with_filter(filter: &str, config: &LoggingConfig) -> Result<LoggingGuard> {
+	init(config.env_filter(filter)?, config)
+}
 
-	tracing_subscriber::fmt()
-		.with_env_filter(filter)
-		.with_target(false)
-		.with_thread_ids(true)
-		.with_thread_names(true)
-		.init();
+fn init(filter: EnvFilter, config: &LoggingConfig) -> Result<LoggingGuard> {
+	let mut layers = vec![fmt_layer(config.format, std::io::stdout, true)];
+	let mut file_guard = None;
+	if let Some(file) = &config.file {
+		let mut builder = RollingFileAppender::builder()
+			.rotation(file.rotation.into())
+			.filename_prefix(&file.prefix)
+			.filename_suffix("log");
+		if let Some(max_files) = file.max_files {
+			builder = builder.max_log_files(max_files);
+		}
+		let appender = builder
+			.build(&file.directory)
+			.wrap_err_with(|| format!("Failed to open log files in {}", file.directory.display()))?;
+		let (writer, guard) = tracing_appender::non_blocking(appender);
+		layers.push(fmt_layer(config.format, writer, false));
+		file_guard = Some(guard);
+	}
 
-	Ok(())
+	tracing_subscriber::registry().with(layers.with_filter(filter)).try_init()?;
+
+	Ok(LoggingGuard { _file: file_guard })
+}
+
+fn fmt_layer<W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+	W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+	let layer = tracing_subscriber::fmt::layer().with_writer(writer).with_thread_ids(true).with_thread_names(true);
+	match format {
+		LogFormat::Text => layer.with_target(false).with_ansi(ansi).boxed(),
+		// Collectors filter on the target, so JSON keeps it
+		LogFormat::Json => layer.json().with_current_span(true).boxed(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_logging_config_from_toml() {
+		let config: LoggingConfig = toml::from_str(
+			r#"
+			format = "json"
+			modules = { fabric_inclusion = "debug", hyper = "warn" }
+			[file]
+			directory = "/var/log/fabric"
+			prefix = "relay"
+			rotation = "hourly"
+			max_files = 24
+			"#,
+		)
+		.unwrap();
+		assert_eq!(config.format, LogFormat::Json);
+		assert_eq!(config.module_directives().unwrap(), vec!["fabric_inclusion=debug", "hyper=warn"]);
+		let file = config.file.as_ref().unwrap();
+		assert_eq!(file.rotation, LogRotation::Hourly);
+		assert_eq!(file.max_files, Some(24));
+		config.check().unwrap();
+
+		assert_eq!(toml::from_str::<LoggingConfig>("").unwrap(), LoggingConfig::default());
+	}
+
+	#[test]
+	fn test_invalid_module_level_is_an_error() {
+		let config = LoggingConfig {
+			modules: BTreeMap::from([("fabric_inclusion".to_string(), "loud".to_string())]),
+			..Default::default()
+		};
+		assert!(config.check().unwrap_err().to_string().contains("modules.fabric_inclusion"));
+	}
 }
@@ -1,6 +1,137 @@
+use std::collections::BTreeMap;
 use std::time::Instant;
 
-use prometheus::{HistogramVec, IntCounterVec};
+use axum::response::{IntoResponse, Response};
+use lazy_static::lazy_static;
+use prometheus::{
+	Encoder, Gauge, HistogramVec, IntCounterVec, Registry, TextEncoder, register_gauge_with_registry,
+	register_int_counter_vec_with_registry,
+};
+
+pub const FABRIC_REGISTRY_NAME: &str = "fabric";
+
+lazy_static! {
+	/// Metrics describing the preconf pipeline end to end rather than a single service
+	pub static ref FABRIC_METRICS_REGISTRY: Registry =
+		Registry::new_custom(Some(FABRIC_REGISTRY_NAME.to_string()), None).unwrap();
+
+	pub static ref FABRIC_SLOT_COMMITMENTS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"slot_commitments_total",
+		"Total commitments of settled slots reaching each stage, counted in every stage they reached: made, constrained and included",
+		&["stage"],
+		FABRIC_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref FABRIC_COMMITMENT_FULFILLMENT_RATIO: Gauge = register_gauge_with_registry!(
+		"commitment_fulfillment_ratio",
+		"Fraction of the commitments made for the most recently settled slot that were included on-chain",
+		FABRIC_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref FABRIC_COMMITMENT_FAILURES_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"commitment_failures_total",
+		"Total unfulfilled commitments of settled slots by reason: not constrained, empty slot or not honored",
+		&["reason"],
+		FABRIC_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref FABRIC_CONSTRAINTS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"constraints_total",
+		"Total constraints by the stage of the service handling them: posted to the relay, proven in a block the relay forwarded or audited by the proposer",
+		&["stage"],
+		FABRIC_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref FABRIC_CONSTRAINT_AUDITS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"constraint_audits_total",
+		"Total audits by the proposer of the constraints a relay serves for a delegated slot, by result: clean or violation",
+		&["result"],
+		FABRIC_METRICS_REGISTRY
+	)
+	.unwrap();
+}
+
+/// Label values for FABRIC_SLOT_COMMITMENTS_TOTAL
+pub const STAGE_MADE: &str = "made";
+pub const STAGE_CONSTRAINED: &str = "constrained";
+pub const STAGE_INCLUDED: &str = "included";
+
+/// Label values for FABRIC_COMMITMENT_FAILURES_TOTAL
+pub const FAILURE_NOT_CONSTRAINED: &str = "not_constrained";
+pub const FAILURE_EMPTY_SLOT: &str = "empty_slot";
+pub const FAILURE_NOT_HONORED: &str = "not_honored";
+
+/// Label values for FABRIC_CONSTRAINTS_TOTAL
+pub const STAGE_POSTED: &str = "posted";
+pub const STAGE_PROVEN: &str = "proven";
+pub const STAGE_AUDITED: &str = "audited";
+
+/// Label values for FABRIC_CONSTRAINT_AUDITS_TOTAL
+pub const AUDIT_CLEAN: &str = "clean";
+pub const AUDIT_VIOLATION: &str = "violation";
+
+/// Commitments of one settled slot, from made to included
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlotFulfillment {
+	pub made: u64,
+	pub constrained: u64,
+	pub included: u64,
+	/// Unfulfilled commitments by reason
+	pub failures: BTreeMap<&'static str, u64>,
+}
+
+impl SlotFulfillment {
+	/// Count an unfulfilled commitment
+	pub fn fail(&mut self, reason: &'static str) {
+		*self.failures.entry(reason).or_default() += 1;
+	}
+
+	/// Count a commitment the slot's block missed, by how far it got: never constrained, constrained but the slot has
+	/// no block (or an empty one), or constrained and left out of the block
+	pub fn miss(&mut self, constrained: bool, empty_block: bool) {
+		self.fail(match (constrained, empty_block) {
+			(false, _) => FAILURE_NOT_CONSTRAINED,
+			(true, true) => FAILURE_EMPTY_SLOT,
+			(true, false) => FAILURE_NOT_HONORED,
+		});
+	}
+
+	/// Fraction of the commitments made that were included, None if no commitment was made
+	pub fn ratio(&self) -> Option<f64> {
+		(self.made > 0).then(|| self.included as f64 / self.made as f64)
+	}
+
+	/// Export the slot to the fabric metrics
+	pub fn record(&self) {
+		FABRIC_SLOT_COMMITMENTS_TOTAL.with_label_values(&[STAGE_MADE]).inc_by(self.made);
+		FABRIC_SLOT_COMMITMENTS_TOTAL.with_label_values(&[STAGE_CONSTRAINED]).inc_by(self.constrained);
+		FABRIC_SLOT_COMMITMENTS_TOTAL.with_label_values(&[STAGE_INCLUDED]).inc_by(self.included);
+		for (reason, count) in &self.failures {
+			FABRIC_COMMITMENT_FAILURES_TOTAL.with_label_values(&[reason]).inc_by(*count);
+		}
+		if let Some(ratio) = self.ratio() {
+			FABRIC_COMMITMENT_FULFILLMENT_RATIO.set(ratio);
+		}
+	}
+}
+
+/// Serves the fabric metrics, for services without a metrics server of their own
+pub async fn fabric_metrics_handler() -> Response {
+	let mut buffer = Vec::new();
+	let encoder = TextEncoder::new();
+	if encoder.encode(&FABRIC_METRICS_REGISTRY.gather(), &mut buffer).is_err() {
+		return axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+	}
+	Response::builder()
+		.status(axum::http::StatusCode::OK)
+		.header(axum::http::header::CONTENT_TYPE, encoder.format_type())
+		.body(axum::body::Body::from(buffer))
+		.unwrap()
+}
 
 /// Generic helper that knows how to record HTTP metrics:
 /// - requests_total
@@ -35,3 +166,47 @@ impl HttpMetrics {
 		self.responses.with_label_values(&[endpoint, method, status_label]).inc();
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_slot_fulfillment_ratio() {
+		assert_eq!(SlotFulfillment::default().ratio(), None);
+
+		let mut slot = SlotFulfillment { made: 4, constrained: 3, included: 2, ..Default::default() };
+		slot.fail(FAILURE_NOT_CONSTRAINED);
+		slot.fail(FAILURE_NOT_HONORED);
+		assert_eq!(slot.ratio(), Some(0.5));
+		assert_eq!(slot.failures.values().sum::<u64>(), slot.made - slot.included);
+	}
+
+	#[test]
+	fn test_slot_fulfillment_failure_reasons() {
+		let mut slot = SlotFulfillment::default();
+		slot.miss(false, false);
+		slot.miss(false, true);
+		slot.miss(true, true);
+		slot.miss(true, false);
+		slot.miss(true, false);
+		assert_eq!(
+			slot.failures,
+			BTreeMap::from([(FAILURE_EMPTY_SLOT, 1), (FAILURE_NOT_CONSTRAINED, 2), (FAILURE_NOT_HONORED, 2)])
+		);
+	}
+
+	#[test]
+	fn test_slot_fulfillment_is_recorded_cumulatively() {
+		// Only this test records to the fabric metrics of this crate
+		let count = |stage: &str| FABRIC_SLOT_COMMITMENTS_TOTAL.with_label_values(&[stage]).get();
+		let mut slot = SlotFulfillment { made: 3, constrained: 2, included: 1, ..Default::default() };
+		slot.miss(false, false);
+		slot.miss(true, false);
+		slot.record();
+		slot.record();
+		assert_eq!((count(STAGE_MADE), count(STAGE_CONSTRAINED), count(STAGE_INCLUDED)), (6, 4, 2));
+		assert_eq!(FABRIC_COMMITMENT_FAILURES_TOTAL.with_label_values(&[FAILURE_NOT_HONORED]).get(), 2);
+		assert_eq!(FABRIC_COMMITMENT_FULFILLMENT_RATIO.get(), 1.0 / 3.0);
+	}
+}
@@ -2,10 +2,11 @@ use std::fmt;
 use std::fmt::Write as _;
 use std::sync::Arc;
 
-use eyre::Result;
 use rocksdb::{DB, Direction, IteratorMode, WriteBatch};
 use serde::{Serialize, de::DeserializeOwned};
 
+use super::error::{Result, StorageError};
+
 /// Basic database operation used for batch writes.
 #[derive(Debug, Clone)]
 pub enum DbOp {
@@ -79,7 +80,7 @@ impl DatabaseContext {
 		self.inner.put(b"healthcheck", b"ok")?;
 		let value = self.inner.get(b"healthcheck")?;
 		if value != Some(b"ok".to_vec()) {
-			return Err(eyre::eyre!("Healthcheck failed"));
+			return Err(StorageError::Healthcheck);
 		}
 		self.inner.delete(b"healthcheck")?;
 		Ok(())
@@ -160,6 +161,51 @@ where
 	Ok(out)
 }
 
+/// Delete every key of the given kind whose slot lies in [start_slot, end_slot].
+///
+/// Keys only need to start with [ kind ][ slot_be ], any suffix is deleted as well.
+/// Returns the number of deleted keys.
+pub fn delete_slot_range_kind(db: &DatabaseContext, kind: u8, start_slot: u64, end_slot: u64) -> Result<usize> {
+	if start_slot > end_slot {
+		return Ok(0);
+	}
+
+	let start_key = slot_prefix(kind, start_slot);
+	let inner: &rocksdb::DB = &*db.inner();
+
+	let iter = inner.iterator(IteratorMode::From(&start_key, Direction::Forward));
+	let mut ops = Vec::new();
+
+	for item in iter {
+		let (key, _) = item?;
+
+		if key.len() < 1 + 8 {
+			continue;
+		}
+
+		if key[0] != kind {
+			break;
+		}
+
+		let mut slot_bytes = [0u8; 8];
+		slot_bytes.copy_from_slice(&key[1..9]);
+		let slot = u64::from_be_bytes(slot_bytes);
+
+		if slot < start_slot {
+			continue;
+		}
+		if slot > end_slot {
+			break;
+		}
+
+		ops.push(DbOp::Delete { key: key.to_vec() });
+	}
+
+	let deleted = ops.len();
+	db.batch_write_raw(ops)?;
+	Ok(deleted)
+}
+
 /// Extension trait for typed reads and writes using serde.
 ///
 /// Domain crates can choose their own encoding by defining their own
@@ -259,6 +305,33 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn delete_slot_range_kind_only_deletes_range() -> Result<()> {
+		let db = new_temp_db()?;
+
+		// Kind 'X' at slots 1..=5, with a suffix on slot 3, and kind 'Y' at slot 2
+		for slot in 1..=5u64 {
+			db.put_raw(&slot_prefix(b'X', slot), b"v")?;
+		}
+		let mut suffixed = slot_prefix(b'X', 3).to_vec();
+		suffixed.extend_from_slice(b"suffix");
+		db.put_raw(&suffixed, b"v")?;
+		db.put_raw(&slot_prefix(b'Y', 2), b"v")?;
+
+		let deleted = delete_slot_range_kind(&db, b'X', 2, 4)?;
+		assert_eq!(deleted, 4);
+
+		assert!(db.get_raw(&slot_prefix(b'X', 1))?.is_some());
+		assert!(db.get_raw(&slot_prefix(b'X', 2))?.is_none());
+		assert!(db.get_raw(&suffixed)?.is_none());
+		assert!(db.get_raw(&slot_prefix(b'X', 5))?.is_some());
+		assert!(db.get_raw(&slot_prefix(b'Y', 2))?.is_some());
+
+		// Empty range is a no-op
+		assert_eq!(delete_slot_range_kind(&db, b'X', 4, 2)?, 0);
+		Ok(())
+	}
+
 	#[test]
 	fn key_with_prefix_builds_expected_format() {
 		let key = key_with_prefix("commitment", [123u64, 456u64]);
@@ -287,6 +360,16 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn undecodable_value_is_a_serialization_error() -> Result<()> {
+		let db = new_temp_db()?;
+		db.put_raw(b"typed:broken", b"not json")?;
+
+		let err = db.get_json::<u64>(b"typed:broken").unwrap_err();
+		assert!(matches!(err, StorageError::Serialization(_)));
+		Ok(())
+	}
+
 	// ------------------------------------------------------------------------
 	// Simulated "extension crate" example
 	//
@@ -317,9 +400,9 @@ mod tests {
 		///
 		/// Callers import the trait and then call these methods directly on DatabaseContext.
 		pub trait DummyConstraintsDbExt {
-			fn store_constraint(&self, constraint: &DummyConstraint) -> Result<()>;
-			fn load_constraint(&self, id: u64) -> Result<Option<DummyConstraint>>;
-			fn delete_constraint(&self, id: u64) -> Result<()>;
+			fn store_constraint(&self, constraint: &DummyConstraint) -> Result<(), StorageError>;
+			fn load_constraint(&self, id: u64) -> Result<Option<DummyConstraint>, StorageError>;
+			fn delete_constraint(&self, id: u64) -> Result<(), StorageError>;
 		}
 
 		// Key encoding logic is local to the constraints crate.
@@ -328,17 +411,17 @@ mod tests {
 		}
 
 		impl DummyConstraintsDbExt for DatabaseContext {
-			fn store_constraint(&self, constraint: &DummyConstraint) -> Result<()> {
+			fn store_constraint(&self, constraint: &DummyConstraint) -> Result<(), StorageError> {
 				let key = constraint_key(constraint.id);
 				self.put_json(&key, constraint)
 			}
 
-			fn load_constraint(&self, id: u64) -> Result<Option<DummyConstraint>> {
+			fn load_constraint(&self, id: u64) -> Result<Option<DummyConstraint>, StorageError> {
 				let key = constraint_key(id);
 				self.get_json(&key)
 			}
 
-			fn delete_constraint(&self, id: u64) -> Result<()> {
+			fn delete_constraint(&self, id: u64) -> Result<(), StorageError> {
 				let key = constraint_key(id);
 				self.delete_raw(&key)
 			}
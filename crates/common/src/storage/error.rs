@@ -0,0 +1,27 @@
+//! Typed errors of the shared RocksDB storage and the extension traits built on it.
+
+/// Error returned by `DatabaseContext` and the storage extension traits of the domain crates
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+	/// The database directory could not be created
+	#[error("failed to create database directory {path}: {source}")]
+	CreateDirectory { path: String, source: std::io::Error },
+	/// RocksDB could not open the database
+	#[error("failed to open RocksDB database at {path}: {source}")]
+	Open { path: String, source: rocksdb::Error },
+	/// A read, write or iteration failed
+	#[error("database error: {0}")]
+	Database(#[from] rocksdb::Error),
+	/// A value could not be encoded for storage, or a stored value could not be decoded
+	#[error("failed to encode or decode a stored value: {0}")]
+	Serialization(#[from] serde_json::Error),
+	/// A stored value decoded, but does not hold what its key promises
+	#[error("corrupt stored value: {0}")]
+	Corrupt(String),
+	/// The database did not return the value written by the healthcheck
+	#[error("database healthcheck failed")]
+	Healthcheck,
+}
+
+/// Result of the storage operations
+pub type Result<T, E = StorageError> = std::result::Result<T, E>;
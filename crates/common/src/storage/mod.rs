@@ -1,16 +1,17 @@
 pub mod db;
+pub mod error;
 
-use eyre::{Context, Result};
 use rocksdb::{DB, Options};
 use std::sync::Arc;
 
 pub use db::DatabaseContext;
+pub use error::{Result, StorageError};
 
 /// Create a RocksDB database at the specified path
 pub fn create_database(database_path: &str) -> Result<DatabaseContext> {
 	// Create database directory if it doesn't exist
 	std::fs::create_dir_all(database_path)
-		.with_context(|| format!("Failed to create database directory: {}", database_path))?;
+		.map_err(|source| StorageError::CreateDirectory { path: database_path.to_string(), source })?;
 
 	// Configure RocksDB options
 	let mut opts = Options::default();
@@ -19,7 +20,7 @@ pub fn create_database(database_path: &str) -> Result<DatabaseContext> {
 
 	// Open the database
 	let db = DB::open(&opts, database_path)
-		.with_context(|| format!("Failed to open RocksDB database at: {}", database_path))?;
+		.map_err(|source| StorageError::Open { path: database_path.to_string(), source })?;
 
 	tracing::info!("RocksDB database opened successfully at: {}", database_path);
 
@@ -19,6 +19,11 @@ pub fn decode_address(address: &str) -> Result<Address> {
 	Ok(Address::new(address_bytes))
 }
 
+/// Compare two secrets in time independent of where they differ, e.g. an API key against a request's token
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
 #[cfg(unix)]
 pub async fn wait_for_signal() -> Result<()> {
 	let mut sigint = signal(SignalKind::interrupt())?;
@@ -81,6 +86,15 @@ mod tests {
 		assert!(result.is_err());
 	}
 
+	#[test]
+	fn test_constant_time_eq() {
+		assert!(constant_time_eq(b"secret", b"secret"));
+		assert!(!constant_time_eq(b"secret", b"secreT"));
+		assert!(!constant_time_eq(b"secret", b"secret2"));
+		assert!(!constant_time_eq(b"", b"secret"));
+		assert!(constant_time_eq(b"", b""));
+	}
+
 	#[test]
 	fn test_decode_address_wrong_length() {
 		// Too short (only 10 bytes / 20 hex chars)
@@ -0,0 +1,134 @@
+//! Command line flags and subcommands shared by the service binaries
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+use eyre::{Result, eyre};
+
+/// Flags of every service binary
+#[derive(Debug, Clone, Args)]
+pub struct ServiceArgs {
+	/// Config file, read from the binary's config environment variable if not set
+	#[arg(long, value_name = "PATH")]
+	pub config: Option<PathBuf>,
+
+	/// Log level or tracing filter, e.g. `debug` or `info,fabric_inclusion=trace`. Defaults to RUST_LOG, then `info`
+	#[arg(long, value_name = "FILTER")]
+	pub log_level: Option<String>,
+
+	/// Load the config and set up the service, then exit before serving anything
+	#[arg(long)]
+	pub dry_run: bool,
+}
+
+impl ServiceArgs {
+	/// `--config`, else the path in the environment variable `env_var`
+	pub fn config_path(&self, env_var: &str) -> Result<PathBuf> {
+		match &self.config {
+			Some(path) => Ok(path.clone()),
+			None => std::env::var_os(env_var)
+				.map(PathBuf::from)
+				.ok_or_else(|| eyre!("No config file, pass --config or set {}", env_var)),
+		}
+	}
+
+	/// [`Self::config_path`], exported as `env_var` for loaders reading the config path from the environment
+	///
+	/// # Safety
+	///
+	/// Writes the environment, so no other thread may be running, call it before starting the async runtime
+	pub unsafe fn export_config_path(&self, env_var: &str) -> Result<PathBuf> {
+		let path = self.config_path(env_var)?;
+		// SAFETY: upheld by the caller
+		unsafe { std::env::set_var(env_var, &path) };
+		Ok(path)
+	}
+
+	/// `--log-level`, else RUST_LOG, else `info`
+	pub fn log_filter(&self) -> String {
+		self.log_level.clone().or_else(|| std::env::var("RUST_LOG").ok()).unwrap_or_else(|| "info".to_string())
+	}
+}
+
+/// Flag of the services serving Prometheus metrics
+#[derive(Debug, Clone, Args)]
+pub struct MetricsArgs {
+	/// Address of the metrics server, overrides the metrics host and port of the config
+	#[arg(long, value_name = "HOST:PORT")]
+	pub metrics_addr: Option<SocketAddr>,
+}
+
+/// Subcommands of every service binary, which runs the service without one
+#[derive(Debug, Clone, Subcommand)]
+pub enum ServiceCommand {
+	/// Config file commands
+	#[command(subcommand)]
+	Config(ConfigCommand),
+	/// Print the version and exit
+	Version,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ConfigCommand {
+	/// Load the config with its environment overrides, report every invalid field and exit
+	Validate,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use clap::Parser;
+
+	#[derive(Debug, Parser)]
+	struct Cli {
+		#[command(flatten)]
+		service: ServiceArgs,
+		#[command(flatten)]
+		metrics: MetricsArgs,
+		#[command(subcommand)]
+		command: Option<ServiceCommand>,
+	}
+
+	#[test]
+	fn test_parse_flags_and_subcommands() {
+		let cli = Cli::try_parse_from([
+			"relay",
+			"--config",
+			"relay.toml",
+			"--log-level",
+			"debug",
+			"--dry-run",
+			"--metrics-addr",
+			"0.0.0.0:9090",
+		])
+		.unwrap();
+		assert_eq!(cli.service.config_path("FABRIC_TEST_CLI_CONFIG").unwrap(), PathBuf::from("relay.toml"));
+		assert_eq!(cli.service.log_filter(), "debug");
+		assert!(cli.service.dry_run);
+		assert_eq!(cli.metrics.metrics_addr.map(|addr| addr.port()), Some(9090));
+		assert!(cli.command.is_none());
+
+		let cli = Cli::try_parse_from(["relay", "config", "validate"]).unwrap();
+		assert!(matches!(cli.command, Some(ServiceCommand::Config(ConfigCommand::Validate))));
+		let missing = cli.service.config_path("FABRIC_TEST_CLI_CONFIG").unwrap_err();
+		assert!(missing.to_string().contains("FABRIC_TEST_CLI_CONFIG"));
+
+		let cli = Cli::try_parse_from(["relay", "version"]).unwrap();
+		assert!(matches!(cli.command, Some(ServiceCommand::Version)));
+	}
+
+	#[test]
+	fn test_export_config_path() {
+		// Only this test reads or writes the variable
+		let env_var = "FABRIC_TEST_CLI_EXPORTED_CONFIG";
+		let cli = Cli::try_parse_from(["gateway", "--config", "gateway.toml"]).unwrap();
+		let path = unsafe { cli.service.export_config_path(env_var) }.unwrap();
+		assert_eq!(path, PathBuf::from("gateway.toml"));
+		assert_eq!(std::env::var_os(env_var), Some("gateway.toml".into()));
+
+		// Without --config the exported path is kept
+		let cli = Cli::try_parse_from(["gateway"]).unwrap();
+		assert_eq!(unsafe { cli.service.export_config_path(env_var) }.unwrap(), PathBuf::from("gateway.toml"));
+		assert_eq!(std::env::var_os(env_var), Some("gateway.toml".into()));
+	}
+}
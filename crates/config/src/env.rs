@@ -0,0 +1,113 @@
+//! Environment variable overrides of config values
+use eyre::{Result, eyre};
+use toml::{Table, Value};
+
+/// Separates the prefix and the keys of nested tables in variable names
+const SEPARATOR: &str = "__";
+
+/// Override values of `table` with the variables named `{prefix}__{KEY}`, e.g. `FABRIC_GATEWAY__RPC_PORT=8080`
+/// Keys of nested tables are joined with `__` (`FABRIC_GATEWAY__URC__RPC_URL`). Values are read as TOML values,
+/// e.g. numbers, booleans or arrays, and as strings otherwise or when the overridden value is a string.
+/// Returns the overridden keys
+pub fn apply_env_overrides(table: &mut Table, prefix: &str) -> Result<Vec<String>> {
+	apply_overrides(table, prefix, std::env::vars())
+}
+
+/// `apply_env_overrides` with the given variables instead of the environment
+pub fn apply_overrides<I>(table: &mut Table, prefix: &str, vars: I) -> Result<Vec<String>>
+where
+	I: IntoIterator<Item = (String, String)>,
+{
+	let var_prefix = format!("{}{}", prefix, SEPARATOR);
+	let mut overridden = Vec::new();
+	for (name, raw) in vars {
+		let Some(path) = name.strip_prefix(&var_prefix) else { continue };
+		let keys = path.split(SEPARATOR).map(str::to_lowercase).collect::<Vec<_>>();
+		if keys.iter().any(String::is_empty) {
+			return Err(eyre!("Invalid config override {}: empty key", name));
+		}
+		set(table, &keys, &raw).map_err(|e| eyre!("Invalid config override {}: {}", name, e))?;
+		overridden.push(keys.join("."));
+	}
+	overridden.sort();
+	Ok(overridden)
+}
+
+fn set(table: &mut Table, keys: &[String], raw: &str) -> Result<()> {
+	let (key, rest) = keys.split_first().expect("override keys are never empty");
+	if rest.is_empty() {
+		let value = match table.get(key) {
+			Some(Value::String(_)) => Value::String(raw.to_string()),
+			_ => parse_value(raw),
+		};
+		table.insert(key.clone(), value);
+		return Ok(());
+	}
+
+	match table.entry(key.clone()).or_insert_with(|| Value::Table(Table::new())) {
+		Value::Table(nested) => set(nested, rest, raw),
+		_ => Err(eyre!("{} is not a table", key)),
+	}
+}
+
+/// A TOML value, or the raw string if it is not one
+fn parse_value(raw: &str) -> Value {
+	toml::from_str::<Table>(&format!("value = {}", raw))
+		.ok()
+		.and_then(|mut table| table.remove("value"))
+		.unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+		vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+	}
+
+	#[test]
+	fn test_overrides_replace_and_add_values() {
+		let mut table: Table = toml::from_str(
+			r#"
+			rpc_port = 8000
+			module_signing_id = "1111"
+			[urc]
+			rpc_url = "http://localhost:8545"
+			"#,
+		)
+		.unwrap();
+
+		let overridden = apply_overrides(
+			&mut table,
+			"FABRIC_GATEWAY",
+			vars(&[
+				("FABRIC_GATEWAY__RPC_PORT", "9000"),
+				("FABRIC_GATEWAY__MODULE_SIGNING_ID", "2222"),
+				("FABRIC_GATEWAY__URC__RPC_URL", "http://urc:8545"),
+				("FABRIC_GATEWAY__SIMULATE_COMMITMENTS", "true"),
+				("FABRIC_GATEWAY__CONSTRAINTS_RECEIVERS", r#"["0xab"]"#),
+				("FABRIC_GATEWAY__GATEWAY_PUBLIC_KEY", "0xab"),
+				("FABRIC_RELAY__PORT", "1"),
+			]),
+		)
+		.unwrap();
+
+		assert_eq!(table["rpc_port"].as_integer(), Some(9000));
+		// Kept a string even though it reads as a number
+		assert_eq!(table["module_signing_id"].as_str(), Some("2222"));
+		assert_eq!(table["urc"]["rpc_url"].as_str(), Some("http://urc:8545"));
+		assert_eq!(table["simulate_commitments"].as_bool(), Some(true));
+		assert_eq!(table["constraints_receivers"].as_array().map(Vec::len), Some(1));
+		assert_eq!(table["gateway_public_key"].as_str(), Some("0xab"));
+		assert_eq!(overridden.len(), 6);
+		assert!(!table.contains_key("port"));
+	}
+
+	#[test]
+	fn test_override_below_a_value_is_an_error() {
+		let mut table: Table = toml::from_str("rpc_port = 8000").unwrap();
+		let result = apply_overrides(&mut table, "FABRIC_GATEWAY", vars(&[("FABRIC_GATEWAY__RPC_PORT__X", "1")]));
+		assert!(result.unwrap_err().to_string().contains("FABRIC_GATEWAY__RPC_PORT__X"));
+	}
+}
@@ -0,0 +1,12 @@
+//! Loading and validation of the service configs
+//!
+//! Configs are read from TOML, overridden by environment variables and checked field by field before any service
+//! state is built from them, so a misconfiguration is reported with every invalid field at once instead of a panic
+//! deep inside a constructor.
+pub mod cli;
+pub mod env;
+pub mod load;
+pub mod validation;
+
+pub use load::{load_file, load_module};
+pub use validation::{ConfigErrors, Validate};
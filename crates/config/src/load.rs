@@ -0,0 +1,133 @@
+//! Reading configs from TOML files
+use std::path::Path;
+
+use eyre::{Result, WrapErr, eyre};
+use serde::de::DeserializeOwned;
+use toml::{Table, Value};
+use tracing::info;
+
+use crate::env::apply_env_overrides;
+use crate::validation::Validate;
+
+/// Read the config in the TOML file at `path`, overridden by the variables of `env_prefix`, and validate it
+pub fn load_file<T: DeserializeOwned + Validate>(path: impl AsRef<Path>, env_prefix: &str) -> Result<T> {
+	let path = path.as_ref();
+	from_table(read_table(path)?, env_prefix, &path.display().to_string())
+}
+
+/// Read the config of Commit-Boost module `module_id` from the Commit-Boost config file at `path`, overridden by
+/// the variables of `env_prefix`, and validate it
+/// Unlike Commit-Boost's loader this does not connect to the signer, so a config can be checked on its own
+pub fn load_module<T: DeserializeOwned + Validate>(
+	path: impl AsRef<Path>,
+	module_id: &str,
+	env_prefix: &str,
+) -> Result<T> {
+	let path = path.as_ref();
+	let module = read_module(path, module_id)?;
+	from_table(module, env_prefix, &format!("module '{}' of {}", module_id, path.display()))
+}
+
+/// Table of Commit-Boost module `module_id` in the Commit-Boost config file at `path`
+pub fn read_module(path: impl AsRef<Path>, module_id: &str) -> Result<Table> {
+	let path = path.as_ref();
+	let modules = match read_table(path)?.remove("modules") {
+		Some(Value::Array(modules)) => modules,
+		_ => return Err(eyre!("No [[modules]] in {}", path.display())),
+	};
+	modules
+		.into_iter()
+		.filter_map(|module| match module {
+			Value::Table(module) => Some(module),
+			_ => None,
+		})
+		.find(|module| module.get("id").and_then(Value::as_str) == Some(module_id))
+		.ok_or_else(|| eyre!("No module with id '{}' in {}", module_id, path.display()))
+}
+
+/// Deserialize and validate `table` after applying the overrides of `env_prefix`, `source` names it in errors
+pub fn from_table<T: DeserializeOwned + Validate>(table: Table, env_prefix: &str, source: &str) -> Result<T> {
+	let config: T = parse(table, env_prefix, source)?;
+	config.check(source)?;
+	Ok(config)
+}
+
+/// Deserialize `table` after applying the overrides of `env_prefix`, without validating it
+pub fn parse<T: DeserializeOwned>(mut table: Table, env_prefix: &str, source: &str) -> Result<T> {
+	let overridden = apply_env_overrides(&mut table, env_prefix)?;
+	if !overridden.is_empty() {
+		info!("Config values of {} overridden from the environment: {}", source, overridden.join(", "));
+	}
+	table.try_into().wrap_err_with(|| format!("Failed to parse the config in {}", source))
+}
+
+fn read_table(path: &Path) -> Result<Table> {
+	let content =
+		std::fs::read_to_string(path).wrap_err_with(|| format!("Failed to read config file {}", path.display()))?;
+	toml::from_str(&content).wrap_err_with(|| format!("Failed to parse config file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::validation::ConfigErrors;
+	use serde::Deserialize;
+	use std::io::Write;
+
+	#[derive(Debug, Deserialize)]
+	struct ServiceConfig {
+		host: String,
+		port: u16,
+	}
+
+	impl Validate for ServiceConfig {
+		fn validate(&self, errors: &mut ConfigErrors) {
+			errors.host("host", &self.host);
+			errors.port("port", self.port);
+		}
+	}
+
+	fn write_config(content: &str) -> tempfile::NamedTempFile {
+		let mut file = tempfile::NamedTempFile::new().unwrap();
+		file.write_all(content.as_bytes()).unwrap();
+		file
+	}
+
+	#[test]
+	fn test_load_module_picks_the_module_by_id() {
+		let file = write_config(
+			r#"
+			chain = "Holesky"
+
+			[[modules]]
+			id = "proposer"
+			type = "commit"
+			host = "10.0.0.1"
+			port = 1
+
+			[[modules]]
+			id = "gateway"
+			type = "commit"
+			host = "10.0.0.2"
+			port = 2
+			"#,
+		);
+		let path = file.path();
+
+		let config: ServiceConfig = load_module(path, "gateway", "FABRIC_TEST_LOAD_MODULE").unwrap();
+		assert_eq!(config.host, "10.0.0.2");
+		assert_eq!(config.port, 2);
+
+		let missing = load_module::<ServiceConfig>(path, "relay", "FABRIC_TEST_LOAD_MODULE").unwrap_err();
+		assert!(missing.to_string().contains("No module with id 'relay'"));
+	}
+
+	#[test]
+	fn test_load_file_reports_invalid_fields() {
+		let file = write_config("host = \"http://relay\"\nport = 0\n");
+		let error = load_file::<ServiceConfig>(file.path(), "FABRIC_TEST_LOAD_FILE").unwrap_err();
+		let message = error.to_string();
+		assert!(message.contains("(2 error(s))"), "{}", message);
+		assert!(message.contains("host: 'http://relay' must be a host without a scheme"), "{}", message);
+	}
+}
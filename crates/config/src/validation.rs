@@ -0,0 +1,165 @@
+//! Field checks collected into a single error
+use std::fmt::Display;
+
+use alloy::hex;
+use alloy::primitives::Address;
+use eyre::{Result, eyre};
+use reqwest::Url;
+
+/// A config whose values are checked beyond what deserializing them does
+pub trait Validate {
+	/// Add every invalid field to `errors`
+	fn validate(&self, errors: &mut ConfigErrors);
+
+	/// Ok if every field is valid, else an error listing the invalid ones
+	fn check(&self, source: &str) -> Result<()> {
+		let mut errors = ConfigErrors::default();
+		self.validate(&mut errors);
+		errors.into_result(source)
+	}
+}
+
+/// Invalid fields of a config, with what is wrong with each
+#[derive(Debug, Default)]
+pub struct ConfigErrors {
+	errors: Vec<(String, String)>,
+}
+
+impl ConfigErrors {
+	pub fn add(&mut self, field: &str, message: impl Display) {
+		self.errors.push((field.to_string(), message.to_string()));
+	}
+
+	/// Add `message` for `field` unless `valid`
+	pub fn ensure(&mut self, valid: bool, field: &str, message: impl Display) {
+		if !valid {
+			self.add(field, message);
+		}
+	}
+
+	pub fn not_empty(&mut self, field: &str, value: &str) {
+		self.ensure(!value.trim().is_empty(), field, "must not be empty");
+	}
+
+	pub fn positive(&mut self, field: &str, value: u64) {
+		self.ensure(value > 0, field, "must be greater than zero");
+	}
+
+	pub fn port(&mut self, field: &str, port: u16) {
+		self.ensure(port != 0, field, "must be a port between 1 and 65535");
+	}
+
+	/// A host name or IP address, without scheme or port
+	pub fn host(&mut self, field: &str, host: &str) {
+		if host.trim().is_empty() {
+			self.add(field, "must not be empty");
+		} else if host.contains("://") {
+			self.add(field, format!("'{}' must be a host without a scheme, e.g. 127.0.0.1", host));
+		} else if Url::parse(&format!("http://{}", host)).is_err() {
+			self.add(field, format!("'{}' is not a valid host", host));
+		}
+	}
+
+	pub fn url(&mut self, field: &str, url: &str) {
+		if let Err(e) = Url::parse(url) {
+			self.add(field, format!("'{}' is not a valid URL: {}", url, e));
+		}
+	}
+
+	/// A hex-encoded BLS public key that is a valid curve point
+	pub fn bls_public_key(&mut self, field: &str, public_key: &str) {
+		match hex::decode(public_key.trim()) {
+			Ok(bytes) if bytes.len() != 48 => {
+				self.add(field, format!("'{}' is {} bytes, a BLS public key is 48 bytes", public_key, bytes.len()))
+			}
+			Ok(bytes) => {
+				if let Err(e) = blst::min_pk::PublicKey::key_validate(&bytes) {
+					self.add(field, format!("'{}' is not a valid BLS public key: {:?}", public_key, e));
+				}
+			}
+			Err(e) => self.add(field, format!("'{}' is not hex: {}", public_key, e)),
+		}
+	}
+
+	pub fn address(&mut self, field: &str, address: &str) {
+		if let Err(e) = address.trim().parse::<Address>() {
+			self.add(field, format!("'{}' is not an address: {}", address, e));
+		}
+	}
+
+	/// 32 hex-encoded bytes, e.g. a module signing ID or a private key
+	pub fn bytes32(&mut self, field: &str, value: &str) {
+		match hex::decode(value.trim()) {
+			Ok(bytes) => self.ensure(bytes.len() == 32, field, format!("is {} bytes, expected 32", bytes.len())),
+			Err(e) => self.add(field, format!("is not hex: {}", e)),
+		}
+	}
+
+	/// Every port in `ports` is only used once
+	pub fn distinct_ports(&mut self, ports: &[(&str, Option<u16>)]) {
+		for (index, (field, port)) in ports.iter().enumerate() {
+			let Some(port) = port else { continue };
+			if let Some((other, _)) = ports[..index].iter().find(|(_, other)| *other == Some(*port)) {
+				self.add(field, format!("port {} is already used by {}", port, other));
+			}
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.errors.is_empty()
+	}
+
+	/// Ok without errors, else one error listing every invalid field of the config read from `source`
+	pub fn into_result(self, source: &str) -> Result<()> {
+		if self.errors.is_empty() {
+			return Ok(());
+		}
+		let fields =
+			self.errors.iter().map(|(field, message)| format!("\n  - {}: {}", field, message)).collect::<String>();
+		Err(eyre!("Invalid config in {} ({} error(s)):{}", source, self.errors.len(), fields))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PUBLIC_KEY: &str =
+		"0xaf6e96c0eccd8d4ae868be9299af737855a1b08d57bccb565ea7e69311a30baeebe08d493c3fea97077e8337e95ac5a6";
+
+	#[test]
+	fn test_config_errors_list_every_invalid_field() {
+		let mut errors = ConfigErrors::default();
+		errors.host("rpc_host", "127.0.0.1");
+		errors.host("relay_host", "http://relay");
+		errors.port("rpc_port", 0);
+		errors.bls_public_key("gateway_public_key", PUBLIC_KEY);
+		errors.bls_public_key("additional_public_keys[0]", &PUBLIC_KEY[..PUBLIC_KEY.len() - 2]);
+		errors.bls_public_key("additional_public_keys[1]", "0xzz");
+		errors.address("committer_address", "0x1234");
+		errors.bytes32("module_signing_id", "0x1111111111111111111111111111111111111111111111111111111111111111");
+		errors.distinct_ports(&[("rpc_port", Some(8000)), ("metrics_port", Some(8000)), ("admin_port", None)]);
+
+		let message = errors.into_result("gateway.toml").unwrap_err().to_string();
+		assert!(message.contains("(6 error(s))"), "{}", message);
+		for field in [
+			"relay_host",
+			"rpc_port",
+			"additional_public_keys[0]: '",
+			"additional_public_keys[1]",
+			"committer_address",
+			"metrics_port: port 8000 is already used by rpc_port",
+		] {
+			assert!(message.contains(field), "{} missing from {}", field, message);
+		}
+		assert!(!message.contains("gateway_public_key"));
+		assert!(!message.contains("module_signing_id"));
+	}
+
+	#[test]
+	fn test_bls_public_key_must_be_on_the_curve() {
+		let mut errors = ConfigErrors::default();
+		errors.bls_public_key("gateway_public_key", &format!("0x{}", "11".repeat(48)));
+		assert!(!errors.is_empty());
+	}
+}
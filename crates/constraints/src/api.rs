@@ -1,32 +1,50 @@
+use crate::error::Result;
+use crate::receiver_auth::{ReceiverAccess, ReceiverAuthorizer};
 use crate::types::{
-	AuthorizationContext, ConstraintCapabilities, ConstraintsResponse, DelegationsResponse, SignedConstraints,
-	SignedDelegation, SubmitBlockRequestWithProofs,
+	BlocksWithProofsResponse, ConstraintCapabilities, ConstraintsResponse, DelegationOffersResponse,
+	DelegationsResponse, ProofValidationReport, SignedConstraints, SignedDelegation, SignedDelegationOffer,
+	SignedReceipt, SubmitBlockRequestWithProofs,
 };
+use alloy::primitives::B256;
 use async_trait::async_trait;
 use axum::http::HeaderMap;
-use eyre::Result;
 
 /// Server side spec for the Constraints REST API.
 ///
 /// Any implementation can use any internal state (DB,
 /// RPC clients, etc) as long as it implements this.
+/// Read routes are authorized through its `ReceiverAuthorizer`
+/// before the API is called.
 #[async_trait]
-pub trait ConstraintsApi: Send + Sync + Clone + 'static {
+pub trait ConstraintsApi: ReceiverAuthorizer + Send + Sync + Clone + 'static {
 	/// GET /capabilities
 	async fn get_capabilities(&self) -> Result<ConstraintCapabilities>;
 
 	/// POST /constraints
-	async fn post_constraints(&self, signed_constraints: SignedConstraints) -> Result<()>;
+	/// Returns a receipt signed by the relay
+	async fn post_constraints(&self, signed_constraints: SignedConstraints) -> Result<SignedReceipt>;
 
 	/// GET /constraints
-	async fn get_constraints(&self, slot: u64, auth: AuthorizationContext) -> Result<ConstraintsResponse>;
+	/// Only returns the constraints `access` may read
+	async fn get_constraints(&self, slot: u64, access: ReceiverAccess) -> Result<ConstraintsResponse>;
 
 	/// POST /delegation
-	async fn post_delegation(&self, signed_delegation: SignedDelegation) -> Result<()>;
+	/// Returns a receipt signed by the relay
+	async fn post_delegation(&self, signed_delegation: SignedDelegation) -> Result<SignedReceipt>;
+
+	/// GET /receipts/{message_hash}
+	async fn get_receipt(&self, message_hash: B256) -> Result<Option<SignedReceipt>>;
 
 	/// GET /delegations/{slot}
 	async fn get_delegations(&self, slot: u64) -> Result<DelegationsResponse>;
 
+	/// POST /delegation_offers
+	/// Replaces the delegate's previous offer for the slot
+	async fn post_delegation_offer(&self, signed_offer: SignedDelegationOffer) -> Result<()>;
+
+	/// GET /delegation_offers/{slot}
+	async fn get_delegation_offers(&self, slot: u64) -> Result<DelegationOffersResponse>;
+
 	/// POST /blocks_with_proofs
 	async fn post_blocks_with_proofs(
 		&self,
@@ -34,6 +52,17 @@ pub trait ConstraintsApi: Send + Sync + Clone + 'static {
 		headers: HeaderMap,
 	) -> Result<()>;
 
+	/// GET /blocks_with_proofs/{slot}
+	/// Returns the records of the blocks accepted for the slot
+	async fn get_blocks_with_proofs(&self, slot: u64) -> Result<BlocksWithProofsResponse>;
+
+	/// POST /blocks_with_proofs/validate
+	/// Runs the same proof validation as POST /blocks_with_proofs without forwarding the block
+	async fn validate_blocks_with_proofs(
+		&self,
+		block_request: SubmitBlockRequestWithProofs,
+	) -> Result<ProofValidationReport>;
+
 	/// GET /health
 	async fn health_check(&self) -> Result<()>;
 }
@@ -1,13 +1,16 @@
+use alloy::primitives::B256;
 use async_trait::async_trait;
-use eyre::{Result, eyre};
+use common::http::HttpClientConfig;
 use reqwest::{Client, Url};
 use std::time::Duration;
 
+use crate::error::{ConstraintsError, Result};
 use crate::metrics::client_http_metrics;
 use crate::routes;
 use crate::types::{
-	ConstraintCapabilities, ConstraintsResponse, DelegationsResponse, SignedConstraints, SignedDelegation,
-	SubmitBlockRequestWithProofs,
+	AcceptedBlockWithProofs, AuthorizationContext, BlocksWithProofsResponse, ConstraintCapabilities,
+	ConstraintsResponse, DelegationOffersResponse, DelegationsResponse, ProofValidationReport, SignedConstraints,
+	SignedDelegation, SignedDelegationOffer, SignedReceipt, SubmitBlockRequestWithProofs,
 };
 
 /// Trait for a Constraints REST client (mockable for testing).
@@ -21,20 +24,40 @@ pub trait ConstraintsClient: Send + Sync {
 	async fn get_capabilities(&self) -> Result<ConstraintCapabilities>;
 
 	/// POST /constraints
-	async fn post_constraints(&self, signed_constraints: &SignedConstraints) -> Result<()>;
+	/// Returns the relay receipt, if the relay issues receipts
+	async fn post_constraints(&self, signed_constraints: &SignedConstraints) -> Result<Option<SignedReceipt>>;
 
 	/// GET /constraints/{slot}
 	async fn get_constraints(&self, slot: u64) -> Result<Vec<SignedConstraints>>;
 
 	/// POST /delegation
-	async fn post_delegation(&self, signed_delegation: &SignedDelegation) -> Result<()>;
+	/// Returns the relay receipt, if the relay issues receipts
+	async fn post_delegation(&self, signed_delegation: &SignedDelegation) -> Result<Option<SignedReceipt>>;
+
+	/// GET /receipts/{message_hash}
+	async fn get_receipt(&self, message_hash: B256) -> Result<Option<SignedReceipt>>;
 
 	/// GET /delegations/{slot}
 	async fn get_delegations(&self, slot: u64) -> Result<Vec<SignedDelegation>>;
 
+	/// POST /delegation_offers
+	async fn post_delegation_offer(&self, signed_offer: &SignedDelegationOffer) -> Result<()>;
+
+	/// GET /delegation_offers/{slot}
+	async fn get_delegation_offers(&self, slot: u64) -> Result<Vec<SignedDelegationOffer>>;
+
 	/// POST /blocks_with_proofs
 	async fn post_blocks_with_proofs(&self, blocks_with_proofs: &SubmitBlockRequestWithProofs) -> Result<()>;
 
+	/// GET /blocks_with_proofs/{slot}
+	async fn get_blocks_with_proofs(&self, slot: u64) -> Result<Vec<AcceptedBlockWithProofs>>;
+
+	/// POST /blocks_with_proofs/validate
+	async fn validate_blocks_with_proofs(
+		&self,
+		blocks_with_proofs: &SubmitBlockRequestWithProofs,
+	) -> Result<ProofValidationReport>;
+
 	/// GET /health
 	async fn health_check(&self) -> Result<bool>;
 }
@@ -49,12 +72,20 @@ pub struct HttpConstraintsClient {
 
 impl HttpConstraintsClient {
 	/// Create a new constraints client.
-	pub fn new(host: String, port: u16, api_key: Option<String>) -> Self {
-		let client = Client::builder().timeout(Duration::from_secs(30)).build().expect("Failed to create HTTP client");
+	pub fn new(host: String, port: u16, api_key: Option<String>) -> Result<Self> {
+		Self::with_http_config(host, port, api_key, &HttpClientConfig::default())
+	}
 
-		let base_url = Url::parse(format!("http://{}:{}", host, port).as_str()).expect("Failed to parse base URL");
+	/// Create a new constraints client with the given timeout and connection pool settings.
+	/// Fails if the host does not make a URL or the HTTP client cannot be built with the settings.
+	pub fn with_http_config(host: String, port: u16, api_key: Option<String>, http: &HttpClientConfig) -> Result<Self> {
+		let client =
+			http.client().map_err(|e| ConstraintsError::Internal(format!("Failed to create HTTP client: {e:#}")))?;
 
-		Self { client, base_url, api_key }
+		let base_url = Url::parse(format!("http://{}:{}", host, port).as_str())
+			.map_err(|e| ConstraintsError::InvalidRequest(format!("Invalid relay address {}:{}: {}", host, port, e)))?;
+
+		Ok(Self { client, base_url, api_key })
 	}
 
 	fn auth_header(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
@@ -66,6 +97,56 @@ impl HttpConstraintsClient {
 		let endpoint = endpoint.trim_start_matches('/');
 		format!("{}{}", self.base_url, endpoint)
 	}
+
+	/// GET /constraints/{slot} as a constraints receiver, authenticated by the receiver headers
+	/// Before the slot the relay only returns constraints restricted to a receivers list to the receivers on it
+	pub async fn get_constraints_as_receiver(
+		&self,
+		slot: u64,
+		auth: &AuthorizationContext,
+	) -> Result<Vec<SignedConstraints>> {
+		self.fetch_constraints(slot, Some(auth)).await
+	}
+
+	async fn fetch_constraints(
+		&self,
+		slot: u64,
+		auth: Option<&AuthorizationContext>,
+	) -> Result<Vec<SignedConstraints>> {
+		const ENDPOINT: &str = routes::CONSTRAINTS_SLOT;
+		const METHOD: &str = "GET";
+
+		let metrics = client_http_metrics();
+		let start = metrics.start(ENDPOINT, METHOD);
+
+		let path = ENDPOINT.replace("{slot}", &slot.to_string());
+		let url = self.full_url(&path);
+
+		let mut req = self.client.get(&url);
+		req = self.auth_header(req);
+		for (name, value) in auth.map(AuthorizationContext::to_headers).unwrap_or_default() {
+			req = req.header(name, value);
+		}
+
+		let resp = match req.send().await {
+			Ok(r) => r,
+			Err(e) => {
+				metrics.finish_label(ENDPOINT, METHOD, "error", start);
+				return Err(e.into());
+			}
+		};
+
+		let status = resp.status();
+		metrics.finish_status(ENDPOINT, METHOD, status.as_u16(), start);
+
+		if status.is_success() {
+			let result: ConstraintsResponse = resp.json().await?;
+			Ok(result.constraints)
+		} else {
+			let text = resp.text().await.unwrap_or_default();
+			Err(ConstraintsError::from_status(status, format!("Failed to get constraints for slot {slot}: {text}")))
+		}
+	}
 }
 
 #[async_trait]
@@ -98,11 +179,11 @@ impl ConstraintsClient for HttpConstraintsClient {
 			Ok(caps)
 		} else {
 			let text = resp.text().await.unwrap_or_default();
-			Err(eyre!("Failed to get capabilities (status {status}): {text}"))
+			Err(ConstraintsError::from_status(status, format!("Failed to get capabilities: {text}")))
 		}
 	}
 
-	async fn post_constraints(&self, signed_constraints: &SignedConstraints) -> Result<()> {
+	async fn post_constraints(&self, signed_constraints: &SignedConstraints) -> Result<Option<SignedReceipt>> {
 		const ENDPOINT: &str = routes::CONSTRAINTS;
 		const METHOD: &str = "POST";
 
@@ -126,15 +207,88 @@ impl ConstraintsClient for HttpConstraintsClient {
 		metrics.finish_status(ENDPOINT, METHOD, status.as_u16(), start);
 
 		if status.is_success() {
-			Ok(())
+			parse_receipt(resp).await
 		} else {
 			let text = resp.text().await.unwrap_or_default();
-			Err(eyre!("Failed to post constraints (status {status}): {text}"))
+			Err(ConstraintsError::from_status(status, format!("Failed to post constraints: {text}")))
 		}
 	}
 
 	async fn get_constraints(&self, slot: u64) -> Result<Vec<SignedConstraints>> {
-		const ENDPOINT: &str = routes::CONSTRAINTS_SLOT;
+		self.fetch_constraints(slot, None).await
+	}
+
+	async fn post_delegation(&self, signed_delegation: &SignedDelegation) -> Result<Option<SignedReceipt>> {
+		const ENDPOINT: &str = routes::DELEGATION;
+		const METHOD: &str = "POST";
+
+		let metrics = client_http_metrics();
+		let start = metrics.start(ENDPOINT, METHOD);
+
+		let url = self.full_url(ENDPOINT);
+
+		let mut req = self.client.post(&url).json(signed_delegation);
+		req = self.auth_header(req);
+
+		let resp = match req.send().await {
+			Ok(r) => r,
+			Err(e) => {
+				metrics.finish_label(ENDPOINT, METHOD, "error", start);
+				return Err(e.into());
+			}
+		};
+
+		let status = resp.status();
+		metrics.finish_status(ENDPOINT, METHOD, status.as_u16(), start);
+
+		if status.is_success() {
+			parse_receipt(resp).await
+		} else {
+			let text = resp.text().await.unwrap_or_default();
+			Err(ConstraintsError::from_status(status, format!("Failed to post delegation: {text}")))
+		}
+	}
+
+	async fn get_receipt(&self, message_hash: B256) -> Result<Option<SignedReceipt>> {
+		const ENDPOINT: &str = routes::RECEIPT;
+		const METHOD: &str = "GET";
+
+		let metrics = client_http_metrics();
+		let start = metrics.start(ENDPOINT, METHOD);
+
+		let path = ENDPOINT.replace("{message_hash}", &message_hash.to_string());
+		let url = self.full_url(&path);
+
+		let mut req = self.client.get(&url);
+		req = self.auth_header(req);
+
+		let resp = match req.send().await {
+			Ok(r) => r,
+			Err(e) => {
+				metrics.finish_label(ENDPOINT, METHOD, "error", start);
+				return Err(e.into());
+			}
+		};
+
+		let status = resp.status();
+		metrics.finish_status(ENDPOINT, METHOD, status.as_u16(), start);
+
+		if status.is_success() {
+			let receipt: SignedReceipt = resp.json().await?;
+			Ok(Some(receipt))
+		} else if status == reqwest::StatusCode::NOT_FOUND {
+			Ok(None)
+		} else {
+			let text = resp.text().await.unwrap_or_default();
+			Err(ConstraintsError::from_status(
+				status,
+				format!("Failed to get receipt for message {message_hash}: {text}"),
+			))
+		}
+	}
+
+	async fn get_delegations(&self, slot: u64) -> Result<Vec<SignedDelegation>> {
+		const ENDPOINT: &str = routes::DELEGATIONS_SLOT;
 		const METHOD: &str = "GET";
 
 		let metrics = client_http_metrics();
@@ -158,16 +312,16 @@ impl ConstraintsClient for HttpConstraintsClient {
 		metrics.finish_status(ENDPOINT, METHOD, status.as_u16(), start);
 
 		if status.is_success() {
-			let result: ConstraintsResponse = resp.json().await?;
-			Ok(result.constraints)
+			let result: DelegationsResponse = resp.json().await?;
+			Ok(result.delegations)
 		} else {
 			let text = resp.text().await.unwrap_or_default();
-			Err(eyre!("Failed to get constraints for slot {slot} (status {status}): {text}"))
+			Err(ConstraintsError::from_status(status, format!("Failed to get delegations for slot {slot}: {text}")))
 		}
 	}
 
-	async fn post_delegation(&self, signed_delegation: &SignedDelegation) -> Result<()> {
-		const ENDPOINT: &str = routes::DELEGATION;
+	async fn post_delegation_offer(&self, signed_offer: &SignedDelegationOffer) -> Result<()> {
+		const ENDPOINT: &str = routes::DELEGATION_OFFERS;
 		const METHOD: &str = "POST";
 
 		let metrics = client_http_metrics();
@@ -175,7 +329,7 @@ impl ConstraintsClient for HttpConstraintsClient {
 
 		let url = self.full_url(ENDPOINT);
 
-		let mut req = self.client.post(&url).json(signed_delegation);
+		let mut req = self.client.post(&url).json(signed_offer);
 		req = self.auth_header(req);
 
 		let resp = match req.send().await {
@@ -193,12 +347,12 @@ impl ConstraintsClient for HttpConstraintsClient {
 			Ok(())
 		} else {
 			let text = resp.text().await.unwrap_or_default();
-			Err(eyre!("Failed to post delegation (status {status}): {text}"))
+			Err(ConstraintsError::from_status(status, format!("Failed to post delegation offer: {text}")))
 		}
 	}
 
-	async fn get_delegations(&self, slot: u64) -> Result<Vec<SignedDelegation>> {
-		const ENDPOINT: &str = routes::DELEGATIONS_SLOT;
+	async fn get_delegation_offers(&self, slot: u64) -> Result<Vec<SignedDelegationOffer>> {
+		const ENDPOINT: &str = routes::DELEGATION_OFFERS_SLOT;
 		const METHOD: &str = "GET";
 
 		let metrics = client_http_metrics();
@@ -222,11 +376,50 @@ impl ConstraintsClient for HttpConstraintsClient {
 		metrics.finish_status(ENDPOINT, METHOD, status.as_u16(), start);
 
 		if status.is_success() {
-			let result: DelegationsResponse = resp.json().await?;
-			Ok(result.delegations)
+			let result: DelegationOffersResponse = resp.json().await?;
+			Ok(result.offers)
+		} else {
+			let text = resp.text().await.unwrap_or_default();
+			Err(ConstraintsError::from_status(
+				status,
+				format!("Failed to get delegation offers for slot {slot}: {text}"),
+			))
+		}
+	}
+
+	async fn get_blocks_with_proofs(&self, slot: u64) -> Result<Vec<AcceptedBlockWithProofs>> {
+		const ENDPOINT: &str = routes::BLOCKS_WITH_PROOFS_SLOT;
+		const METHOD: &str = "GET";
+
+		let metrics = client_http_metrics();
+		let start = metrics.start(ENDPOINT, METHOD);
+
+		let path = ENDPOINT.replace("{slot}", &slot.to_string());
+		let url = self.full_url(&path);
+
+		let mut req = self.client.get(&url);
+		req = self.auth_header(req);
+
+		let resp = match req.send().await {
+			Ok(r) => r,
+			Err(e) => {
+				metrics.finish_label(ENDPOINT, METHOD, "error", start);
+				return Err(e.into());
+			}
+		};
+
+		let status = resp.status();
+		metrics.finish_status(ENDPOINT, METHOD, status.as_u16(), start);
+
+		if status.is_success() {
+			let result: BlocksWithProofsResponse = resp.json().await?;
+			Ok(result.blocks)
 		} else {
 			let text = resp.text().await.unwrap_or_default();
-			Err(eyre!("Failed to get delegations for slot {slot} (status {status}): {text}"))
+			Err(ConstraintsError::from_status(
+				status,
+				format!("Failed to get blocks with proofs for slot {slot}: {text}"),
+			))
 		}
 	}
 
@@ -257,7 +450,41 @@ impl ConstraintsClient for HttpConstraintsClient {
 			Ok(())
 		} else {
 			let text = resp.text().await.unwrap_or_default();
-			Err(eyre!("Failed to post blocks_with_proofs (status {status}): {text}"))
+			Err(ConstraintsError::from_status(status, format!("Failed to post blocks_with_proofs: {text}")))
+		}
+	}
+
+	async fn validate_blocks_with_proofs(
+		&self,
+		blocks_with_proofs: &SubmitBlockRequestWithProofs,
+	) -> Result<ProofValidationReport> {
+		const ENDPOINT: &str = routes::BLOCKS_WITH_PROOFS_VALIDATE;
+		const METHOD: &str = "POST";
+
+		let metrics = client_http_metrics();
+		let start = metrics.start(ENDPOINT, METHOD);
+
+		let url = self.full_url(ENDPOINT);
+
+		let mut req = self.client.post(&url).json(blocks_with_proofs);
+		req = self.auth_header(req);
+
+		let resp = match req.send().await {
+			Ok(r) => r,
+			Err(e) => {
+				metrics.finish_label(ENDPOINT, METHOD, "error", start);
+				return Err(e.into());
+			}
+		};
+
+		let status = resp.status();
+		metrics.finish_status(ENDPOINT, METHOD, status.as_u16(), start);
+
+		if status.is_success() {
+			Ok(resp.json::<ProofValidationReport>().await?)
+		} else {
+			let text = resp.text().await.unwrap_or_default();
+			Err(ConstraintsError::from_status(status, format!("Failed to validate blocks_with_proofs: {text}")))
 		}
 	}
 
@@ -286,3 +513,30 @@ impl ConstraintsClient for HttpConstraintsClient {
 		Ok(status.is_success())
 	}
 }
+
+/// Parse the relay receipt from a successful POST response
+/// Relays that do not issue receipts respond with an empty body
+async fn parse_receipt(resp: reqwest::Response) -> Result<Option<SignedReceipt>> {
+	let body = resp.bytes().await?;
+	if body.is_empty() {
+		return Ok(None);
+	}
+
+	let receipt = serde_json::from_slice(&body)
+		.map_err(|e| ConstraintsError::Upstream(format!("Failed to parse the receipt: {e}")))?;
+	Ok(Some(receipt))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_client_of_an_invalid_address_is_an_error() {
+		let client = HttpConstraintsClient::new("127.0.0.1".to_string(), 4000, None).unwrap();
+		assert_eq!(client.base_url.as_str(), "http://127.0.0.1:4000/");
+
+		let result = HttpConstraintsClient::new("relay host".to_string(), 4000, None);
+		assert!(matches!(result, Err(ConstraintsError::InvalidRequest(_))));
+	}
+}
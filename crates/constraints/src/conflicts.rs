@@ -0,0 +1,317 @@
+//! Detection of constraint sets no block can satisfy.
+//!
+//! Each `ConstraintHandler` describes what its constraints demand of a block as `ConstraintEffect`s. The gateway
+//! checks its constraints before posting them, and the relay checks the merged constraints of every delegate of a
+//! slot, so a set that contradicts itself is rejected instead of leaving builders unable to produce a valid block.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::{ConstraintsError, Result};
+use crate::registry::ConstraintRegistry;
+use crate::types::Constraint;
+use alloy::primitives::B256;
+
+/// What a constraint demands of the block, in terms shared by every constraint type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintEffect {
+	/// The transaction is included, using up to `gas_limit` gas if known
+	Includes { tx_hash: B256, gas_limit: Option<u64> },
+	/// The transaction is not included
+	Excludes { tx_hash: B256 },
+	/// The transaction is included at or before `max_index`
+	AtOrBefore { tx_hash: B256, max_index: u64 },
+	/// The transactions are included at consecutive indices, in this order
+	Contiguous { tx_hashes: Vec<B256> },
+}
+
+impl ConstraintEffect {
+	/// Transactions the effect requires in the block
+	fn included(&self) -> Vec<B256> {
+		match self {
+			ConstraintEffect::Includes { tx_hash, .. } | ConstraintEffect::AtOrBefore { tx_hash, .. } => vec![*tx_hash],
+			ConstraintEffect::Contiguous { tx_hashes } => tx_hashes.clone(),
+			ConstraintEffect::Excludes { .. } => vec![],
+		}
+	}
+
+	/// Whether a block with these transaction hashes, in block order, does what the effect demands
+	pub fn is_satisfied_by(&self, block_tx_hashes: &[B256]) -> bool {
+		let position = |tx_hash: &B256| block_tx_hashes.iter().position(|hash| hash == tx_hash);
+		match self {
+			ConstraintEffect::Includes { tx_hash, .. } => position(tx_hash).is_some(),
+			ConstraintEffect::Excludes { tx_hash } => position(tx_hash).is_none(),
+			ConstraintEffect::AtOrBefore { tx_hash, max_index } => {
+				position(tx_hash).is_some_and(|index| index as u64 <= *max_index)
+			}
+			ConstraintEffect::Contiguous { tx_hashes } => {
+				!tx_hashes.is_empty() && block_tx_hashes.windows(tx_hashes.len()).any(|window| window == tx_hashes)
+			}
+		}
+	}
+}
+
+/// Constraints that can not all be satisfied by one block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+	/// Positions of the conflicting constraints in the checked set
+	pub constraints: Vec<usize>,
+	pub reason: String,
+}
+
+/// Every conflict found in a set of constraints
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConflictReport {
+	pub conflicts: Vec<Conflict>,
+}
+
+impl ConflictReport {
+	pub fn is_empty(&self) -> bool {
+		self.conflicts.is_empty()
+	}
+
+	/// Errors with the report if any conflict was found
+	pub fn into_result(self) -> Result<()> {
+		if self.is_empty() {
+			return Ok(());
+		}
+		Err(ConstraintsError::Conflict(self.to_string()))
+	}
+}
+
+impl fmt::Display for ConflictReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} conflict(s) between constraints", self.conflicts.len())?;
+		for conflict in &self.conflicts {
+			write!(f, "; constraints {:?}: {}", conflict.constraints, conflict.reason)?;
+		}
+		Ok(())
+	}
+}
+
+/// Find the constraints no block can satisfy together: a transaction both required and excluded, more ordering
+/// constraints than indices they allow, or included transactions whose gas exceeds `block_gas_limit`
+/// Constraints whose type describes no effects are never part of a conflict
+pub fn find_conflicts(
+	registry: &ConstraintRegistry,
+	constraints: &[Constraint],
+	block_gas_limit: Option<u64>,
+) -> Result<ConflictReport> {
+	let mut effects = Vec::new();
+	for (index, constraint) in constraints.iter().enumerate() {
+		let constraint_effects = registry.effects(constraint).map_err(|e| {
+			ConstraintsError::InvalidRequest(format!("Failed to read the effects of constraint {}: {}", index, e))
+		})?;
+		effects.extend(constraint_effects.into_iter().map(|effect| (index, effect)));
+	}
+
+	let mut report = ConflictReport::default();
+	excluded_and_included(&effects, &mut report);
+	ordering_overlaps(&effects, &mut report);
+	if let Some(block_gas_limit) = block_gas_limit {
+		gas_above_limit(&effects, block_gas_limit, &mut report);
+	}
+	Ok(report)
+}
+
+/// A transaction excluded by one constraint and required by another
+fn excluded_and_included(effects: &[(usize, ConstraintEffect)], report: &mut ConflictReport) {
+	for (excluding, effect) in effects {
+		let ConstraintEffect::Excludes { tx_hash } = effect else {
+			continue;
+		};
+		let mut including = effects
+			.iter()
+			.filter(|(_, other)| other.included().contains(tx_hash))
+			.map(|(index, _)| *index)
+			.collect::<Vec<_>>();
+		including.dedup();
+		if including.is_empty() {
+			continue;
+		}
+		let mut constraints = vec![*excluding];
+		constraints.extend(including);
+		report.conflicts.push(Conflict {
+			constraints,
+			reason: format!("transaction {} is both excluded and required in the block", tx_hash),
+		});
+	}
+}
+
+/// Ordering constraints of distinct transactions that need more indices than they allow, e.g. two transactions
+/// both at index 0
+fn ordering_overlaps(effects: &[(usize, ConstraintEffect)], report: &mut ConflictReport) {
+	// The tightest bound of each transaction, with the constraints setting its bounds
+	let mut bounds: HashMap<B256, (u64, Vec<usize>)> = HashMap::new();
+	for (index, effect) in effects {
+		if let ConstraintEffect::AtOrBefore { tx_hash, max_index } = effect {
+			let bound = bounds.entry(*tx_hash).or_insert((*max_index, vec![]));
+			bound.0 = bound.0.min(*max_index);
+			bound.1.push(*index);
+		}
+	}
+
+	let mut bounds = bounds.into_iter().collect::<Vec<_>>();
+	bounds.sort_by_key(|(tx_hash, (max_index, _))| (*max_index, *tx_hash));
+
+	// The n transactions with the lowest bounds need n indices at or before the highest of those bounds
+	for (position, (_, (max_index, _))) in bounds.iter().enumerate() {
+		if (position as u64) <= *max_index {
+			continue;
+		}
+		let mut constraints =
+			bounds[..=position].iter().flat_map(|(_, (_, indexes))| indexes.clone()).collect::<Vec<_>>();
+		constraints.sort_unstable();
+		constraints.dedup();
+		report.conflicts.push(Conflict {
+			constraints,
+			reason: format!("{} transactions must be at or before index {}", position + 1, max_index),
+		});
+		return;
+	}
+}
+
+/// Included transactions whose gas limits add up to more than the block's
+/// Transactions usually use less gas than their limit, so this rejects some sets a block could still satisfy
+fn gas_above_limit(effects: &[(usize, ConstraintEffect)], block_gas_limit: u64, report: &mut ConflictReport) {
+	let mut gas_limits: HashMap<B256, (u64, usize)> = HashMap::new();
+	for (index, effect) in effects {
+		if let ConstraintEffect::Includes { tx_hash, gas_limit: Some(gas_limit) } = effect {
+			gas_limits.entry(*tx_hash).or_insert((*gas_limit, *index));
+		}
+	}
+
+	let total = gas_limits.values().map(|(gas_limit, _)| *gas_limit).fold(0u64, u64::saturating_add);
+	if total > block_gas_limit {
+		let mut constraints = gas_limits.values().map(|(_, index)| *index).collect::<Vec<_>>();
+		constraints.sort_unstable();
+		report.conflicts.push(Conflict {
+			constraints,
+			reason: format!(
+				"included transactions use up to {} gas, above the block gas limit {}",
+				total, block_gas_limit
+			),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::registry::{BlockProofs, ConstraintHandler};
+	use alloy::primitives::Bytes;
+
+	/// Payload is a kind byte, a transaction hash byte and an argument byte
+	struct EffectHandler;
+
+	impl ConstraintHandler for EffectHandler {
+		fn validate_payload(&self, _payload: &Bytes) -> Result<()> {
+			Ok(())
+		}
+
+		fn check_proof(&self, _constraint: &Constraint, _proof: &Bytes) -> Result<()> {
+			Ok(())
+		}
+
+		fn verify_proof(&self, _constraint: &Constraint, _proof: &Bytes, _block: &dyn BlockProofs) -> Result<()> {
+			Ok(())
+		}
+
+		fn effects(&self, payload: &Bytes) -> Result<Vec<ConstraintEffect>> {
+			let tx_hash = B256::repeat_byte(payload[1]);
+			let argument = payload[2] as u64;
+			Ok(vec![match payload[0] {
+				0 => ConstraintEffect::Includes { tx_hash, gas_limit: Some(argument * 1_000) },
+				1 => ConstraintEffect::Excludes { tx_hash },
+				2 => ConstraintEffect::AtOrBefore { tx_hash, max_index: argument },
+				_ => ConstraintEffect::Contiguous { tx_hashes: vec![tx_hash, B256::repeat_byte(argument as u8)] },
+			}])
+		}
+	}
+
+	fn constraint(kind: u8, tx: u8, argument: u8) -> Constraint {
+		Constraint { constraint_type: 1, payload: Bytes::from(vec![kind, tx, argument]) }
+	}
+
+	fn conflicts(constraints: &[Constraint], block_gas_limit: Option<u64>) -> Vec<Conflict> {
+		let registry = ConstraintRegistry::new().register(1, EffectHandler);
+		find_conflicts(&registry, constraints, block_gas_limit).unwrap().conflicts
+	}
+
+	#[test]
+	fn test_consistent_constraints_have_no_conflicts() {
+		let constraints = [constraint(0, 1, 21), constraint(2, 2, 0), constraint(2, 3, 1), constraint(1, 4, 0)];
+		assert!(conflicts(&constraints, Some(100_000)).is_empty());
+
+		// The same transaction may be bounded twice, its tightest bound counts
+		assert!(conflicts(&[constraint(2, 1, 0), constraint(2, 1, 3)], None).is_empty());
+	}
+
+	#[test]
+	fn test_excluded_transaction_that_is_required() {
+		let found = conflicts(&[constraint(0, 1, 21), constraint(1, 1, 0)], None);
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].constraints, vec![1, 0]);
+
+		// A bundle member counts as required
+		let found = conflicts(&[constraint(3, 1, 2), constraint(1, 2, 0)], None);
+		assert_eq!(found[0].constraints, vec![1, 0]);
+	}
+
+	#[test]
+	fn test_ordering_constraints_demanding_the_same_index() {
+		let found = conflicts(&[constraint(2, 1, 0), constraint(0, 3, 21), constraint(2, 2, 0)], None);
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].constraints, vec![0, 2]);
+
+		// Three transactions can not all be within the first two indices
+		let found = conflicts(&[constraint(2, 1, 1), constraint(2, 2, 1), constraint(2, 3, 1)], None);
+		assert_eq!(found[0].constraints, vec![0, 1, 2]);
+	}
+
+	#[test]
+	fn test_gas_above_the_block_limit() {
+		let constraints = [constraint(0, 1, 30), constraint(0, 2, 30), constraint(2, 1, 0)];
+		assert!(conflicts(&constraints, Some(60_000)).is_empty());
+		assert!(conflicts(&constraints, None).is_empty());
+
+		let found = conflicts(&constraints, Some(59_999));
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].constraints, vec![0, 1]);
+	}
+
+	#[test]
+	fn test_effects_against_a_block() {
+		let (first, second, third) = (B256::repeat_byte(1), B256::repeat_byte(2), B256::repeat_byte(3));
+		let block = [first, second];
+
+		assert!(ConstraintEffect::Includes { tx_hash: second, gas_limit: None }.is_satisfied_by(&block));
+		assert!(!ConstraintEffect::Includes { tx_hash: third, gas_limit: None }.is_satisfied_by(&block));
+		assert!(ConstraintEffect::Excludes { tx_hash: third }.is_satisfied_by(&block));
+		assert!(!ConstraintEffect::Excludes { tx_hash: first }.is_satisfied_by(&block));
+		assert!(ConstraintEffect::AtOrBefore { tx_hash: second, max_index: 1 }.is_satisfied_by(&block));
+		assert!(!ConstraintEffect::AtOrBefore { tx_hash: second, max_index: 0 }.is_satisfied_by(&block));
+		assert!(ConstraintEffect::Contiguous { tx_hashes: vec![first, second] }.is_satisfied_by(&block));
+		assert!(!ConstraintEffect::Contiguous { tx_hashes: vec![second, first] }.is_satisfied_by(&block));
+		assert!(!ConstraintEffect::Contiguous { tx_hashes: vec![] }.is_satisfied_by(&block));
+
+		// Nothing is included in a missing block
+		assert!(!ConstraintEffect::Includes { tx_hash: first, gas_limit: None }.is_satisfied_by(&[]));
+		assert!(ConstraintEffect::Excludes { tx_hash: first }.is_satisfied_by(&[]));
+	}
+
+	#[test]
+	fn test_report_lists_every_conflict() {
+		let registry = ConstraintRegistry::new().register(1, EffectHandler);
+		let constraints = [constraint(1, 1, 0), constraint(0, 1, 21), constraint(2, 2, 0), constraint(2, 3, 0)];
+		let report = find_conflicts(&registry, &constraints, None).unwrap();
+		assert_eq!(report.conflicts.len(), 2);
+
+		let error = report.into_result().unwrap_err();
+		assert!(matches!(error, ConstraintsError::Conflict(_)));
+		let message = error.to_string();
+		assert!(message.contains("2 conflict(s)"));
+		assert!(message.contains("is both excluded and required"));
+		assert!(message.contains("must be at or before index 0"));
+	}
+}
@@ -0,0 +1,150 @@
+//! Typed errors of the Constraints API, its client and the constraint handlers.
+//!
+//! Every `ConstraintsError` variant maps to an HTTP status, so the server answers with the failure class and
+//! `HttpConstraintsClient` rebuilds the same variant from the response. Callers branch on the variant instead of
+//! parsing the error message.
+
+#[cfg(feature = "full")]
+use axum::http::StatusCode;
+#[cfg(feature = "full")]
+use axum::response::{IntoResponse, Response};
+#[cfg(feature = "full")]
+use common::storage::StorageError;
+
+#[cfg(feature = "full")]
+use crate::receiver_auth::ReceiverAuthError;
+
+/// Error returned by `ConstraintsApi`, `ConstraintsClient` and the constraint registry
+#[derive(Debug, thiserror::Error)]
+pub enum ConstraintsError {
+	/// Malformed request, or a message that fails validation
+	#[error("invalid request: {0}")]
+	InvalidRequest(String),
+	/// Missing authentication, or a signature that does not verify
+	#[error("not authenticated: {0}")]
+	Unauthenticated(String),
+	/// An authenticated caller that may not make the request
+	#[error("not authorized: {0}")]
+	Forbidden(String),
+	/// Nothing to serve for the request, e.g. no constraints for the slot
+	#[error("not found: {0}")]
+	NotFound(String),
+	/// The request contradicts accepted state, e.g. an existing delegation or unsatisfiable constraints
+	#[error("conflict: {0}")]
+	Conflict(String),
+	/// The proofs of a block do not prove its constraints
+	#[error("invalid proof: {0}")]
+	InvalidProof(String),
+	/// The server does not serve the request right now, e.g. while draining
+	#[error("unavailable: {0}")]
+	Unavailable(String),
+	/// A request to another server failed, or its response could not be read
+	#[error("upstream request failed: {0}")]
+	Upstream(String),
+	/// The server's storage failed
+	#[cfg(feature = "full")]
+	#[error(transparent)]
+	Storage(#[from] StorageError),
+	/// Unexpected failure
+	#[error("internal error: {0}")]
+	Internal(String),
+}
+
+/// Result of the Constraints API and the constraint registry
+pub type Result<T, E = ConstraintsError> = std::result::Result<T, E>;
+
+#[cfg(feature = "full")]
+impl ConstraintsError {
+	/// HTTP status the server answers with
+	pub fn status(&self) -> StatusCode {
+		match self {
+			ConstraintsError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+			ConstraintsError::Unauthenticated(_) => StatusCode::UNAUTHORIZED,
+			ConstraintsError::Forbidden(_) => StatusCode::FORBIDDEN,
+			ConstraintsError::NotFound(_) => StatusCode::NOT_FOUND,
+			ConstraintsError::Conflict(_) => StatusCode::CONFLICT,
+			ConstraintsError::InvalidProof(_) => StatusCode::UNPROCESSABLE_ENTITY,
+			ConstraintsError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+			ConstraintsError::Upstream(_) => StatusCode::BAD_GATEWAY,
+			ConstraintsError::Storage(_) | ConstraintsError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+
+	/// Rebuild the typed error from the status and body of an error response
+	/// Statuses outside of the taxonomy are reported as internal errors
+	pub fn from_status(status: StatusCode, message: String) -> Self {
+		match status {
+			StatusCode::BAD_REQUEST => ConstraintsError::InvalidRequest(message),
+			StatusCode::UNAUTHORIZED => ConstraintsError::Unauthenticated(message),
+			StatusCode::FORBIDDEN => ConstraintsError::Forbidden(message),
+			StatusCode::NOT_FOUND => ConstraintsError::NotFound(message),
+			StatusCode::CONFLICT => ConstraintsError::Conflict(message),
+			StatusCode::UNPROCESSABLE_ENTITY => ConstraintsError::InvalidProof(message),
+			StatusCode::SERVICE_UNAVAILABLE => ConstraintsError::Unavailable(message),
+			StatusCode::BAD_GATEWAY => ConstraintsError::Upstream(message),
+			status => ConstraintsError::Internal(format!("status {status}: {message}")),
+		}
+	}
+}
+
+#[cfg(feature = "full")]
+impl IntoResponse for ConstraintsError {
+	fn into_response(self) -> Response {
+		(self.status(), self.to_string()).into_response()
+	}
+}
+
+#[cfg(feature = "full")]
+impl From<reqwest::Error> for ConstraintsError {
+	fn from(error: reqwest::Error) -> Self {
+		ConstraintsError::Upstream(error.to_string())
+	}
+}
+
+#[cfg(feature = "full")]
+impl From<ReceiverAuthError> for ConstraintsError {
+	fn from(error: ReceiverAuthError) -> Self {
+		match error {
+			ReceiverAuthError::Malformed(message) => ConstraintsError::InvalidRequest(message),
+			ReceiverAuthError::Unauthenticated(message) => ConstraintsError::Unauthenticated(message),
+			ReceiverAuthError::Forbidden(message) => ConstraintsError::Forbidden(message),
+			ReceiverAuthError::Internal(message) => ConstraintsError::Internal(message),
+		}
+	}
+}
+
+#[cfg(all(test, feature = "full"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_status_round_trip() {
+		let errors = vec![
+			ConstraintsError::InvalidRequest("slot in the past".to_string()),
+			ConstraintsError::Unauthenticated("missing signature".to_string()),
+			ConstraintsError::Forbidden("not a receiver".to_string()),
+			ConstraintsError::NotFound("slot 10".to_string()),
+			ConstraintsError::Conflict("delegation exists".to_string()),
+			ConstraintsError::InvalidProof("root mismatch".to_string()),
+			ConstraintsError::Unavailable("draining".to_string()),
+			ConstraintsError::Upstream("connection refused".to_string()),
+		];
+
+		for error in errors {
+			let status = error.status();
+			let rebuilt = ConstraintsError::from_status(status, "detail".to_string());
+			assert_eq!(rebuilt.status(), status);
+			assert_eq!(std::mem::discriminant(&rebuilt), std::mem::discriminant(&error));
+		}
+	}
+
+	#[test]
+	fn test_unknown_status_is_internal() {
+		let error = ConstraintsError::from_status(StatusCode::IM_A_TEAPOT, "detail".to_string());
+		assert!(matches!(&error, ConstraintsError::Internal(message) if message.contains("detail")));
+		assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+		let storage = ConstraintsError::from(StorageError::Healthcheck);
+		assert_eq!(storage.status(), StatusCode::INTERNAL_SERVER_ERROR);
+	}
+}
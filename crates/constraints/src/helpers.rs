@@ -1,7 +1,10 @@
 use alloy::consensus::TxEnvelope;
+use alloy::eips::eip4844::kzg_to_versioned_hash;
+use alloy::primitives::B256;
 use alloy::rlp::Decodable;
 use alloy::rpc::types::beacon::relay::SubmitBlockRequest as AlloySubmitBlockRequest;
-use eyre::{Result, eyre};
+
+use crate::error::{ConstraintsError, Result};
 
 pub fn extract_transactions(block: &AlloySubmitBlockRequest) -> Result<Vec<TxEnvelope>> {
 	// Extract transaction bytes from the appropriate variant
@@ -18,14 +21,27 @@ pub fn extract_transactions(block: &AlloySubmitBlockRequest) -> Result<Vec<TxEnv
 	let mut transactions = Vec::new();
 
 	for tx_bytes in tx_bytes_list {
-		let tx =
-			TxEnvelope::decode(&mut tx_bytes.as_ref()).map_err(|e| eyre!("Failed to decode transaction: {}", e))?;
+		let tx = TxEnvelope::decode(&mut tx_bytes.as_ref())
+			.map_err(|e| ConstraintsError::InvalidRequest(format!("Failed to decode transaction: {}", e)))?;
 		transactions.push(tx);
 	}
 
 	if transactions.is_empty() {
-		return Err(eyre!("No transactions in execution payload"));
+		return Err(ConstraintsError::InvalidRequest("No transactions in execution payload".to_string()));
 	}
 
 	Ok(transactions)
 }
+
+/// Versioned hashes of the blob commitments in the block's blobs bundle, in bundle order
+pub fn extract_blob_versioned_hashes(block: &AlloySubmitBlockRequest) -> Vec<B256> {
+	let commitments = match &block {
+		AlloySubmitBlockRequest::Electra(request) => &request.blobs_bundle.commitments,
+		AlloySubmitBlockRequest::Fulu(request) => &request.blobs_bundle.commitments,
+		AlloySubmitBlockRequest::Deneb(request) => &request.blobs_bundle.commitments,
+		// Blobs were introduced after Capella
+		AlloySubmitBlockRequest::Capella(_) => return Vec::new(),
+	};
+
+	commitments.iter().map(|commitment| kzg_to_versioned_hash(commitment.as_slice())).collect()
+}
@@ -1,7 +1,17 @@
+#[cfg(feature = "full")]
 pub mod api;
+#[cfg(feature = "full")]
 pub mod client;
+pub mod conflicts;
+pub mod error;
 pub mod helpers;
+pub mod metadata;
+#[cfg(feature = "full")]
 pub mod metrics;
+#[cfg(feature = "full")]
+pub mod receiver_auth;
+pub mod registry;
 pub mod routes;
+#[cfg(feature = "full")]
 pub mod server;
 pub mod types;
@@ -0,0 +1,165 @@
+//! Versioned schema of `Delegation::metadata`, the terms a proposer delegates a slot under.
+//!
+//! The metadata is a version byte followed by the ABI encoding of the terms, so a slasher contract can decode
+//! them as well. Empty metadata carries no terms: no fee split and no limits on the delegate's constraints.
+
+use alloy::primitives::Bytes;
+use alloy::sol_types::SolValue;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ConstraintsError, Result};
+use crate::types::Constraint;
+
+/// Version byte of the metadata encoding written by `DelegationMetadata::encode`
+pub const DELEGATION_METADATA_VERSION: u8 = 1;
+
+/// Highest fee split, all of the gateway's commitment fees
+pub const MAX_FEE_SPLIT_BPS: u16 = 10_000;
+
+alloy::sol! {
+	struct SolDelegationMetadata {
+		uint16 feeSplitBps;
+		uint32 maxConstraints;
+		uint64[] allowedConstraintTypes;
+		uint64 validUntil;
+	}
+}
+
+/// Terms of a delegation, negotiated between the proposer and the gateway and signed with the delegation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DelegationMetadata {
+	/// Share of the gateway's commitment fees owed to the proposer, in basis points
+	pub fee_split_bps: u16,
+	/// Most constraints the delegate may post for the slot, unlimited if not set. Encoded as 0 when not set, so
+	/// `Some(0)` decodes as unlimited and is refused by the proposer config
+	pub max_constraints: Option<u32>,
+	/// Constraint types the delegate may post, any type if empty
+	pub allowed_constraint_types: Vec<u64>,
+	/// Unix timestamp in seconds after which the delegate may no longer post constraints, no deadline if not set.
+	/// Encoded as 0 when not set like `max_constraints`
+	pub valid_until: Option<u64>,
+}
+
+impl DelegationMetadata {
+	/// Encodes the terms, default terms encode to empty metadata
+	pub fn encode(&self) -> Bytes {
+		if *self == Self::default() {
+			return Bytes::new();
+		}
+		let encoded = SolDelegationMetadata {
+			feeSplitBps: self.fee_split_bps,
+			maxConstraints: self.max_constraints.unwrap_or(0),
+			allowedConstraintTypes: self.allowed_constraint_types.clone(),
+			validUntil: self.valid_until.unwrap_or(0),
+		}
+		.abi_encode();
+		let mut metadata = Vec::with_capacity(1 + encoded.len());
+		metadata.push(DELEGATION_METADATA_VERSION);
+		metadata.extend(encoded);
+		Bytes::from(metadata)
+	}
+
+	/// Decodes the terms of a delegation, empty metadata decodes to the default terms
+	pub fn decode(metadata: &Bytes) -> Result<Self> {
+		let Some((&version, encoded)) = metadata.split_first() else {
+			return Ok(Self::default());
+		};
+		if version != DELEGATION_METADATA_VERSION {
+			return Err(ConstraintsError::InvalidRequest(format!("unsupported delegation metadata version {version}")));
+		}
+		let decoded = SolDelegationMetadata::abi_decode(encoded)
+			.map_err(|e| ConstraintsError::InvalidRequest(format!("invalid delegation metadata: {e}")))?;
+		let terms = Self {
+			fee_split_bps: decoded.feeSplitBps,
+			max_constraints: (decoded.maxConstraints != 0).then_some(decoded.maxConstraints),
+			allowed_constraint_types: decoded.allowedConstraintTypes,
+			valid_until: (decoded.validUntil != 0).then_some(decoded.validUntil),
+		};
+		if terms.fee_split_bps > MAX_FEE_SPLIT_BPS {
+			return Err(ConstraintsError::InvalidRequest(format!(
+				"delegation fee split {} is above {} basis points",
+				terms.fee_split_bps, MAX_FEE_SPLIT_BPS
+			)));
+		}
+		Ok(terms)
+	}
+
+	/// Whether the delegate may post constraints of `constraint_type`
+	pub fn allows(&self, constraint_type: u64) -> bool {
+		self.allowed_constraint_types.is_empty() || self.allowed_constraint_types.contains(&constraint_type)
+	}
+
+	/// Whether the terms lapsed at unix timestamp `now` in seconds
+	pub fn is_expired(&self, now: u64) -> bool {
+		self.valid_until.is_some_and(|valid_until| now > valid_until)
+	}
+
+	/// Keeps the items whose constraint the terms allow, in order and up to `max_constraints`
+	/// Returns the kept items and the number of dropped ones
+	pub fn limit<T>(&self, items: Vec<T>, constraint: impl Fn(&T) -> &Constraint) -> (Vec<T>, usize) {
+		let total = items.len();
+		let max_constraints = self.max_constraints.map_or(usize::MAX, |max| max as usize);
+		let kept = items
+			.into_iter()
+			.filter(|item| self.allows(constraint(item).constraint_type))
+			.take(max_constraints)
+			.collect::<Vec<_>>();
+		let dropped = total - kept.len();
+		(kept, dropped)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn constraint(constraint_type: u64) -> Constraint {
+		Constraint { constraint_type, payload: Bytes::new() }
+	}
+
+	#[test]
+	fn test_metadata_round_trip() {
+		assert!(DelegationMetadata::default().encode().is_empty());
+		assert_eq!(DelegationMetadata::decode(&Bytes::new()).unwrap(), DelegationMetadata::default());
+
+		let terms = DelegationMetadata {
+			fee_split_bps: 2_500,
+			max_constraints: Some(64),
+			allowed_constraint_types: vec![1, 3],
+			valid_until: Some(1_700_000_000),
+		};
+		let encoded = terms.encode();
+		assert_eq!(encoded[0], DELEGATION_METADATA_VERSION);
+		assert_eq!(DelegationMetadata::decode(&encoded).unwrap(), terms);
+	}
+
+	#[test]
+	fn test_metadata_rejects_unknown_versions_and_invalid_terms() {
+		let mut encoded = DelegationMetadata { fee_split_bps: 1, ..Default::default() }.encode().to_vec();
+		encoded[0] = 2;
+		assert!(DelegationMetadata::decode(&Bytes::from(encoded)).is_err());
+		assert!(DelegationMetadata::decode(&Bytes::from(vec![DELEGATION_METADATA_VERSION, 1, 2])).is_err());
+
+		let above = DelegationMetadata { fee_split_bps: MAX_FEE_SPLIT_BPS + 1, ..Default::default() };
+		assert!(matches!(DelegationMetadata::decode(&above.encode()), Err(ConstraintsError::InvalidRequest(_))));
+	}
+
+	#[test]
+	fn test_limit_keeps_allowed_constraints_in_order() {
+		let constraints = vec![constraint(1), constraint(2), constraint(1), constraint(3), constraint(1)];
+		let open = DelegationMetadata::default();
+		assert_eq!(open.limit(constraints.clone(), |c| c).1, 0);
+
+		let terms =
+			DelegationMetadata { max_constraints: Some(3), allowed_constraint_types: vec![1, 3], ..Default::default() };
+		let (kept, dropped) = terms.limit(constraints, |c| c);
+		assert_eq!(kept.iter().map(|c| c.constraint_type).collect::<Vec<_>>(), vec![1, 1, 3]);
+		assert_eq!(dropped, 2);
+
+		assert!(!terms.is_expired(100));
+		let expiring = DelegationMetadata { valid_until: Some(100), ..Default::default() };
+		assert!(!expiring.is_expired(100));
+		assert!(expiring.is_expired(101));
+	}
+}
@@ -0,0 +1,347 @@
+//! Receiver authorization shared by the read routes of the Constraints API
+//!
+//! Constraints restricted to a receivers list may only be read by those receivers (and their delegate) before the
+//! slot passes. Every read route, native or proxied, authorizes through the same `ReceiverAuth` extractor and
+//! `ReceiverAuthorizer` instead of checking the receiver headers itself.
+use std::sync::Arc;
+
+use alloy::rpc::types::beacon::BlsPublicKey;
+use async_trait::async_trait;
+use axum::{
+	extract::{FromRequestParts, Request, State},
+	http::{StatusCode, request::Parts},
+	middleware::Next,
+	response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::error::Result;
+use crate::types::AuthorizationContext;
+
+/// Who may read a route before the slot it serves has passed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiverAuthPolicy {
+	/// Anyone
+	#[default]
+	Open,
+	/// Only authenticated receivers on the slot's allowlist, anyone once the slot has no allowlist
+	Receivers,
+}
+
+/// Receiver authorization of the read routes besides GET /constraints/{slot}, which always enforces it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReceiverAuthConfig {
+	/// Policy of GET /delegations/{slot}
+	pub delegations: ReceiverAuthPolicy,
+	/// Path prefixes of proxied routes only receivers may read, matched by whole segments so `/stream` protects
+	/// `/stream/constraints/1` but not `/streams/1`. The slot is the first numeric path segment
+	/// Paths are normalized before matching, as the downstream relay may resolve them: percent-encoding is decoded
+	/// and empty, `.` and `..` segments are resolved, so `//stream/%63onstraints/2` is protected too
+	pub proxy_paths: Vec<String>,
+}
+
+impl ReceiverAuthConfig {
+	/// Whether a proxied `path` requires an authenticated receiver
+	pub fn protects_proxy_path(&self, path: &str) -> bool {
+		let segments = path_segments(path);
+		self.proxy_paths.iter().any(|prefix| segments.starts_with(&path_segments(prefix)))
+	}
+}
+
+/// Segments of a request path as a server resolving it would see them: percent-decoded, until nothing is left to
+/// decode, split on `/` and `\`, which some servers treat alike, without empty or `.` segments and with `..`
+/// removing the segment before it
+fn path_segments(path: &str) -> Vec<String> {
+	let mut decoded = path.to_string();
+	loop {
+		let next = percent_decode(&decoded);
+		if next == decoded {
+			break;
+		}
+		decoded = next;
+	}
+
+	let mut segments = Vec::new();
+	for segment in decoded.split(['/', '\\']) {
+		match segment {
+			"" | "." => {}
+			".." => {
+				segments.pop();
+			}
+			segment => segments.push(segment.to_string()),
+		}
+	}
+	segments
+}
+
+fn percent_decode(value: &str) -> String {
+	let bytes = value.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut index = 0;
+	while index < bytes.len() {
+		let hex = bytes.get(index + 1..index + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+		match (bytes[index], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+			(b'%', Some(byte)) => {
+				decoded.push(byte);
+				index += 3;
+			}
+			(byte, _) => {
+				decoded.push(byte);
+				index += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Access granted to a read request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiverAccess {
+	/// The slot has no allowlist, or the route is open
+	Open,
+	/// An authenticated receiver on the slot's allowlist
+	Receiver(BlsPublicKey),
+}
+
+/// Why a read request was refused
+#[derive(Debug)]
+pub enum ReceiverAuthError {
+	/// Receiver headers that do not parse
+	Malformed(String),
+	/// Missing receiver headers or a signature that does not verify
+	Unauthenticated(String),
+	/// An authenticated receiver not on the slot's allowlist
+	Forbidden(String),
+	/// The allowlist could not be determined
+	Internal(String),
+}
+
+impl ReceiverAuthError {
+	pub fn status(&self) -> StatusCode {
+		match self {
+			ReceiverAuthError::Malformed(_) => StatusCode::BAD_REQUEST,
+			ReceiverAuthError::Unauthenticated(_) => StatusCode::UNAUTHORIZED,
+			ReceiverAuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+			ReceiverAuthError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+}
+
+impl std::fmt::Display for ReceiverAuthError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ReceiverAuthError::Malformed(message) => write!(f, "invalid receiver headers: {message}"),
+			ReceiverAuthError::Unauthenticated(message) => write!(f, "receiver not authenticated: {message}"),
+			ReceiverAuthError::Forbidden(message) => write!(f, "receiver not authorized: {message}"),
+			ReceiverAuthError::Internal(message) => write!(f, "receiver authorization failed: {message}"),
+		}
+	}
+}
+
+impl IntoResponse for ReceiverAuthError {
+	fn into_response(self) -> Response {
+		(self.status(), self.to_string()).into_response()
+	}
+}
+
+/// Allowlists and receiver verification of the server behind the read routes
+#[async_trait]
+pub trait ReceiverAuthorizer: Send + Sync + 'static {
+	/// Receivers allowed to read `slot`, `None` if anyone may, e.g. once the slot has passed
+	async fn allowed_receivers(&self, slot: u64) -> Result<Option<Vec<BlsPublicKey>>>;
+
+	/// Verify the receiver's signature over `slot`, returning its public key
+	async fn verify_receiver(&self, slot: u64, auth: &AuthorizationContext) -> Result<BlsPublicKey>;
+
+	/// Policies of the configurable read routes
+	fn receiver_auth_config(&self) -> ReceiverAuthConfig {
+		ReceiverAuthConfig::default()
+	}
+}
+
+/// Receiver headers of a request, rejected with 400 when they do not parse
+pub struct ReceiverAuth(pub AuthorizationContext);
+
+impl<S: Send + Sync> FromRequestParts<S> for ReceiverAuth {
+	type Rejection = ReceiverAuthError;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+		AuthorizationContext::from_headers(&parts.headers)
+			.map(ReceiverAuth)
+			.map_err(|e| ReceiverAuthError::Malformed(e.to_string()))
+	}
+}
+
+impl ReceiverAuth {
+	/// Access to `slot` under `policy`: open if the route or the slot is, else the verified receiver on its allowlist
+	pub async fn authorize<A>(
+		&self,
+		authorizer: &A,
+		slot: u64,
+		policy: ReceiverAuthPolicy,
+	) -> Result<ReceiverAccess, ReceiverAuthError>
+	where
+		A: ReceiverAuthorizer + ?Sized,
+	{
+		if policy == ReceiverAuthPolicy::Open {
+			return Ok(ReceiverAccess::Open);
+		}
+		let Some(allowed) =
+			authorizer.allowed_receivers(slot).await.map_err(|e| ReceiverAuthError::Internal(e.to_string()))?
+		else {
+			debug!("No receivers allowlist for slot {}, bypassing authentication", slot);
+			return Ok(ReceiverAccess::Open);
+		};
+
+		let receiver = authorizer
+			.verify_receiver(slot, &self.0)
+			.await
+			.map_err(|e| ReceiverAuthError::Unauthenticated(e.to_string()))?;
+		if !allowed.contains(&receiver) {
+			return Err(ReceiverAuthError::Forbidden(format!(
+				"{} is not part of the receivers list for slot {}",
+				receiver, slot
+			)));
+		}
+		Ok(ReceiverAccess::Receiver(receiver))
+	}
+}
+
+/// Middleware enforcing the receiver allowlist on the proxied paths of `ReceiverAuthConfig::proxy_paths`
+pub async fn proxy_receiver_auth<A>(State(authorizer): State<Arc<A>>, request: Request, next: Next) -> Response
+where
+	A: ReceiverAuthorizer,
+{
+	let path = request.uri().path();
+	if !authorizer.receiver_auth_config().protects_proxy_path(path) {
+		return next.run(request).await;
+	}
+	let Some(slot) = path_segments(path).iter().find_map(|segment| segment.parse::<u64>().ok()) else {
+		return ReceiverAuthError::Malformed(format!("no slot in path {}", path)).into_response();
+	};
+
+	let auth = match AuthorizationContext::from_headers(request.headers()) {
+		Ok(auth) => ReceiverAuth(auth),
+		Err(e) => return ReceiverAuthError::Malformed(e.to_string()).into_response(),
+	};
+	match auth.authorize(authorizer.as_ref(), slot, ReceiverAuthPolicy::Receivers).await {
+		Ok(_) => next.run(request).await,
+		Err(e) => e.into_response(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::error::ConstraintsError;
+	use axum::{Router, body::Body, middleware};
+	use tower::ServiceExt;
+
+	/// Slot 1 is open, slot 2 is restricted to key 1, the receiver is the public key header if set
+	struct TestAuthorizer {
+		config: ReceiverAuthConfig,
+	}
+
+	#[async_trait]
+	impl ReceiverAuthorizer for TestAuthorizer {
+		async fn allowed_receivers(&self, slot: u64) -> Result<Option<Vec<BlsPublicKey>>> {
+			Ok((slot == 2).then(|| vec![BlsPublicKey::repeat_byte(1)]))
+		}
+
+		async fn verify_receiver(&self, _slot: u64, auth: &AuthorizationContext) -> Result<BlsPublicKey> {
+			auth.public_key
+				.ok_or_else(|| ConstraintsError::Unauthenticated("Missing public key from header".to_string()))
+		}
+
+		fn receiver_auth_config(&self) -> ReceiverAuthConfig {
+			self.config.clone()
+		}
+	}
+
+	fn auth(public_key: Option<BlsPublicKey>) -> ReceiverAuth {
+		ReceiverAuth(AuthorizationContext {
+			signature: None,
+			public_key,
+			nonce: None,
+			signing_id: None,
+			timestamp_ms: None,
+		})
+	}
+
+	#[tokio::test]
+	async fn test_authorize_by_policy_and_allowlist() {
+		let authorizer = TestAuthorizer { config: ReceiverAuthConfig::default() };
+		let receiver = BlsPublicKey::repeat_byte(1);
+
+		let open = auth(None).authorize(&authorizer, 2, ReceiverAuthPolicy::Open).await.unwrap();
+		assert_eq!(open, ReceiverAccess::Open);
+		let unrestricted = auth(None).authorize(&authorizer, 1, ReceiverAuthPolicy::Receivers).await.unwrap();
+		assert_eq!(unrestricted, ReceiverAccess::Open);
+
+		let allowed = auth(Some(receiver)).authorize(&authorizer, 2, ReceiverAuthPolicy::Receivers).await.unwrap();
+		assert_eq!(allowed, ReceiverAccess::Receiver(receiver));
+		let missing = auth(None).authorize(&authorizer, 2, ReceiverAuthPolicy::Receivers).await.unwrap_err();
+		assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+		let other = auth(Some(BlsPublicKey::repeat_byte(9)));
+		let forbidden = other.authorize(&authorizer, 2, ReceiverAuthPolicy::Receivers).await.unwrap_err();
+		assert_eq!(forbidden.status(), StatusCode::FORBIDDEN);
+	}
+
+	#[tokio::test]
+	async fn test_proxy_paths_are_authorized() {
+		let authorizer = Arc::new(TestAuthorizer {
+			config: ReceiverAuthConfig { proxy_paths: vec!["/stream".to_string()], ..Default::default() },
+		});
+		let router = Router::new()
+			.fallback(|| async { "proxied" })
+			.layer(middleware::from_fn_with_state(authorizer, proxy_receiver_auth::<TestAuthorizer>));
+		let status = |uri: &str, public_key: Option<BlsPublicKey>| {
+			let router = router.clone();
+			let mut request = axum::http::Request::get(uri);
+			if let Some(public_key) = public_key {
+				request = request.header(crate::types::RECEIVER_PUBLIC_KEY_HEADER, public_key.to_string());
+			}
+			async move { router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status() }
+		};
+
+		assert_eq!(status("/eth/v1/builder/header/2", None).await, StatusCode::OK);
+		assert_eq!(status("/stream/constraints/1", None).await, StatusCode::OK);
+		assert_eq!(status("/stream/constraints/2", None).await, StatusCode::UNAUTHORIZED);
+		assert_eq!(status("/stream/constraints/2", Some(BlsPublicKey::repeat_byte(1))).await, StatusCode::OK);
+		assert_eq!(status("/stream/constraints", None).await, StatusCode::BAD_REQUEST);
+		assert_eq!(status("/streams/constraints/2", None).await, StatusCode::OK);
+
+		// The slot is found in the normalized path too
+		assert_eq!(status("//stream//constraints/2", None).await, StatusCode::UNAUTHORIZED);
+		assert_eq!(status("/stream/constraints/%32", None).await, StatusCode::UNAUTHORIZED);
+		assert_eq!(status("/stream/constraints/%32", Some(BlsPublicKey::repeat_byte(1))).await, StatusCode::OK);
+	}
+
+	#[test]
+	fn test_proxy_paths_match_whole_segments() {
+		let config = ReceiverAuthConfig {
+			proxy_paths: vec!["/constraints".to_string(), "/relay/v1/".to_string()],
+			..Default::default()
+		};
+		assert!(config.protects_proxy_path("/constraints"));
+		assert!(config.protects_proxy_path("/constraints/1"));
+		assert!(config.protects_proxy_path("/relay/v1/bids/1"));
+		assert!(config.protects_proxy_path("/relay/v1"));
+		assert!(!config.protects_proxy_path("/constraintsX/1"));
+		assert!(!config.protects_proxy_path("/relay/v10/bids/1"));
+		assert!(!config.protects_proxy_path("/api/constraints/1"));
+
+		// Paths are normalized before they are matched
+		assert!(config.protects_proxy_path("//constraints/2"));
+		assert!(config.protects_proxy_path("/relay//v1/bids/2"));
+		assert!(config.protects_proxy_path("/relay/./v1/bids/2"));
+		assert!(config.protects_proxy_path("/api/../constraints/2"));
+		assert!(config.protects_proxy_path("/%63onstraints/2"));
+		assert!(config.protects_proxy_path("/relay%2Fv1/bids/2"));
+		assert!(config.protects_proxy_path("/%2563onstraints/2"));
+		assert!(!config.protects_proxy_path("/%63onstraintsX/2"));
+	}
+}
@@ -0,0 +1,141 @@
+//! Extension point for constraint types.
+//!
+//! Each constraint type is served by a `ConstraintHandler` that validates its payload, checks that a
+//! proof is for the constraint and verifies the proof against the block. The relay dispatches through
+//! a `ConstraintRegistry` keyed by `constraint_type`, so supporting a new type means registering a
+//! handler for it. Handlers also describe what their constraints demand of a block, so conflicting
+//! constraints of different types can be detected.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::conflicts::ConstraintEffect;
+use crate::error::{ConstraintsError, Result};
+use crate::types::Constraint;
+use alloy::primitives::{B256, Bytes};
+
+/// The block a proof is verified against
+pub trait BlockProofs: Sync {
+	/// Verifies the trie proof of an index of the block's transactions trie, returns the value at the index if any
+	/// Proofs of a multiproof carry no nodes of their own and are read from the shared nodes
+	fn lookup(&self, index: usize, proof: &[Vec<u8>]) -> Result<Option<Vec<u8>>>;
+
+	/// Hashes of the block's transactions, in block order
+	fn tx_hashes(&self) -> &[B256];
+
+	/// Versioned hashes of the blobs in the block's blobs bundle
+	fn blob_versioned_hashes(&self) -> &[B256];
+}
+
+/// Validation and proof verification of one constraint type
+pub trait ConstraintHandler: Send + Sync {
+	/// Checks that a constraint payload is well formed
+	fn validate_payload(&self, payload: &Bytes) -> Result<()>;
+
+	/// Checks that a proof is for the constraint, e.g. that it proves the constrained transaction
+	/// Runs before the proof is verified against the block
+	fn check_proof(&self, constraint: &Constraint, proof: &Bytes) -> Result<()>;
+
+	/// Verifies a proof against the block it was submitted with
+	fn verify_proof(&self, constraint: &Constraint, proof: &Bytes, block: &dyn BlockProofs) -> Result<()>;
+
+	/// What a constraint payload demands of the block, none by default so the type is never part of a conflict
+	fn effects(&self, _payload: &Bytes) -> Result<Vec<ConstraintEffect>> {
+		Ok(vec![])
+	}
+}
+
+/// Constraint handlers keyed by constraint type
+#[derive(Clone, Default)]
+pub struct ConstraintRegistry {
+	handlers: HashMap<u64, Arc<dyn ConstraintHandler>>,
+}
+
+impl ConstraintRegistry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Register the handler of a constraint type, replacing any previous one
+	pub fn register(mut self, constraint_type: u64, handler: impl ConstraintHandler + 'static) -> Self {
+		self.handlers.insert(constraint_type, Arc::new(handler));
+		self
+	}
+
+	/// Handler of a constraint type, errors if the type is not supported
+	pub fn handler(&self, constraint_type: u64) -> Result<&dyn ConstraintHandler> {
+		self.handlers
+			.get(&constraint_type)
+			.map(|handler| handler.as_ref())
+			.ok_or_else(|| ConstraintsError::InvalidRequest(format!("Unsupported constraint type {constraint_type}")))
+	}
+
+	/// Supported constraint types, in ascending order
+	pub fn constraint_types(&self) -> Vec<u64> {
+		let mut constraint_types = self.handlers.keys().copied().collect::<Vec<_>>();
+		constraint_types.sort_unstable();
+		constraint_types
+	}
+
+	/// Checks the payload of a constraint with the handler of its type
+	pub fn validate(&self, constraint: &Constraint) -> Result<()> {
+		self.handler(constraint.constraint_type)?.validate_payload(&constraint.payload)
+	}
+
+	/// Checks that a proof is for the constraint with the handler of its type
+	pub fn check_proof(&self, constraint: &Constraint, proof: &Bytes) -> Result<()> {
+		self.handler(constraint.constraint_type)?.check_proof(constraint, proof)
+	}
+
+	/// Verifies a proof against the block with the handler of the constraint's type
+	pub fn verify_proof(&self, constraint: &Constraint, proof: &Bytes, block: &dyn BlockProofs) -> Result<()> {
+		self.handler(constraint.constraint_type)?.verify_proof(constraint, proof, block)
+	}
+
+	/// What a constraint demands of the block, described by the handler of its type
+	pub fn effects(&self, constraint: &Constraint) -> Result<Vec<ConstraintEffect>> {
+		self.handler(constraint.constraint_type)?.effects(&constraint.payload)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct NonEmpty;
+
+	impl ConstraintHandler for NonEmpty {
+		fn validate_payload(&self, payload: &Bytes) -> Result<()> {
+			if payload.is_empty() {
+				return Err(ConstraintsError::InvalidRequest("Empty payload".to_string()));
+			}
+			Ok(())
+		}
+
+		fn check_proof(&self, constraint: &Constraint, proof: &Bytes) -> Result<()> {
+			if constraint.payload != *proof {
+				return Err(ConstraintsError::InvalidProof("Proof is for another constraint".to_string()));
+			}
+			Ok(())
+		}
+
+		fn verify_proof(&self, _constraint: &Constraint, _proof: &Bytes, _block: &dyn BlockProofs) -> Result<()> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test_registry_dispatches_by_constraint_type() {
+		let registry = ConstraintRegistry::new().register(7, NonEmpty).register(3, NonEmpty);
+		assert_eq!(registry.constraint_types(), vec![3, 7]);
+
+		let constraint = Constraint { constraint_type: 7, payload: Bytes::from_static(&[1]) };
+		assert!(registry.validate(&constraint).is_ok());
+		assert!(registry.check_proof(&constraint, &Bytes::from_static(&[1])).is_ok());
+		assert!(registry.check_proof(&constraint, &Bytes::from_static(&[2])).is_err());
+		assert!(registry.validate(&Constraint { constraint_type: 7, payload: Bytes::new() }).is_err());
+
+		let unsupported = Constraint { constraint_type: 8, payload: Bytes::from_static(&[1]) };
+		assert!(matches!(registry.validate(&unsupported), Err(ConstraintsError::InvalidRequest(_))));
+	}
+}
@@ -7,6 +7,15 @@ pub const DELEGATION: &str = "/delegation";
 /// Get delegations for a specific slot
 pub const DELEGATIONS_SLOT: &str = "/delegations/{slot}";
 
+/// Publish a gateway's delegation offer endpoint
+pub const DELEGATION_OFFERS: &str = "/delegation_offers";
+
+/// Get the delegation offers for a specific slot
+pub const DELEGATION_OFFERS_SLOT: &str = "/delegation_offers/{slot}";
+
+/// Get the relay receipt for an accepted message
+pub const RECEIPT: &str = "/receipts/{message_hash}";
+
 /// Store constraints endpoint
 pub const CONSTRAINTS: &str = "/constraints";
 
@@ -19,5 +28,14 @@ pub const CAPABILITIES: &str = "/constraints/v0/builder/capabilities";
 /// Submit block with proofs endpoint
 pub const BLOCKS_WITH_PROOFS: &str = "/constraints/v0/relay/blocks_with_proofs";
 
+/// Get the blocks with proofs accepted for a specific slot
+pub const BLOCKS_WITH_PROOFS_SLOT: &str = "/constraints/v0/relay/blocks_with_proofs/{slot}";
+
+/// Validate block with proofs without submitting it endpoint
+pub const BLOCKS_WITH_PROOFS_VALIDATE: &str = "/constraints/v0/relay/blocks_with_proofs/validate";
+
 /// Downstream builder API submit block endpoint for proxying (optional)
 pub const LEGACY_SUBMIT_BLOCK: &str = "/relay/v1/builder/blocks";
+
+/// Downstream builder API status endpoint used for health checks
+pub const LEGACY_STATUS: &str = "/eth/v1/builder/status";
@@ -1,5 +1,6 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use alloy::primitives::B256;
 use axum::{
 	Json, Router,
 	body::Body,
@@ -10,14 +11,15 @@ use axum::{
 };
 use axum_reverse_proxy::ReverseProxy;
 use reqwest::Client;
-use tower::ServiceBuilder;
+use tower::{ServiceBuilder, ServiceExt, service_fn};
 use tower_http::trace::TraceLayer;
 use tracing::{Level, Span, error, info};
 
 use crate::api::ConstraintsApi;
 use crate::metrics::server_http_metrics;
+use crate::receiver_auth::{ReceiverAuth, ReceiverAuthPolicy, proxy_receiver_auth};
 use crate::routes;
-use crate::types::{AuthorizationContext, SignedConstraints, SignedDelegation, SubmitBlockRequestWithProofs};
+use crate::types::{SignedConstraints, SignedDelegation, SignedDelegationOffer, SubmitBlockRequestWithProofs};
 
 /// Build an Axum router for the Constraints REST API,
 /// using any implementation of `ConstraintsApi`.
@@ -34,38 +36,73 @@ where
 		.route(routes::CONSTRAINTS_SLOT, get(get_constraints::<A>))
 		.route(routes::DELEGATION, post(post_delegation::<A>))
 		.route(routes::DELEGATIONS_SLOT, get(get_delegations::<A>))
+		.route(routes::DELEGATION_OFFERS, post(post_delegation_offer::<A>))
+		.route(routes::DELEGATION_OFFERS_SLOT, get(get_delegation_offers::<A>))
+		.route(routes::RECEIPT, get(get_receipt::<A>))
 		.route(routes::BLOCKS_WITH_PROOFS, post(post_blocks_with_proofs::<A>))
+		.route(routes::BLOCKS_WITH_PROOFS_SLOT, get(get_blocks_with_proofs::<A>))
+		.route(routes::BLOCKS_WITH_PROOFS_VALIDATE, post(validate_blocks_with_proofs::<A>))
 		.with_state(state)
 }
 
 pub trait ProxyState: Send + Sync + 'static {
-	fn server_url(&self) -> &str;
+	/// Every downstream server URL the proxy may forward to
+	fn server_urls(&self) -> Vec<String>;
+	/// Downstream server URL the next proxied request should be forwarded to
+	fn server_url(&self) -> String;
 	fn http_client(&self) -> &Client;
 }
 
 #[derive(Clone)]
 struct DownstreamUrl(String);
 
+/// Base URL of the downstream server selected for a proxied request
+#[derive(Clone)]
+struct SelectedDownstream(String);
+
 /// Build an Axum router for the Constraints REST API with a proxy fallback,
 /// using any implementation of `ConstraintsApi` and `ProxyState`.
+///
+/// The downstream server is selected per request via `ProxyState::server_url`,
+/// so implementations can fail over between downstream servers at runtime.
+/// Proxied paths listed in `ReceiverAuthConfig::proxy_paths` are only
+/// forwarded for receivers authorized by the `ReceiverAuthorizer`.
 pub fn build_constraints_router_with_proxy<A>(api: A) -> Router
 where
 	A: ConstraintsApi + ProxyState,
 {
 	let state = Arc::new(api);
-	let downstream_url = state.server_url().to_string();
 
-	// This forwards every path and query to the downstream server URL.
-	let proxy = ReverseProxy::new("/", downstream_url.as_str());
+	// One reverse proxy per downstream server, each forwarding every path and query
+	let proxies: Arc<HashMap<String, ReverseProxy>> = Arc::new(
+		state.server_urls().into_iter().map(|url| (url.clone(), ReverseProxy::new("/", url.as_str()))).collect(),
+	);
+
+	let proxy_state = Arc::clone(&state);
+	let proxy = service_fn(move |req: Request<Body>| {
+		let proxies = Arc::clone(&proxies);
+		async move {
+			let selected = req.extensions().get::<SelectedDownstream>().map(|s| s.0.clone()).unwrap_or_default();
+			match proxies.get(&selected) {
+				Some(proxy) => proxy.clone().oneshot(req).await,
+				None => {
+					error!("No reverse proxy configured for downstream {}", selected);
+					Ok((StatusCode::BAD_GATEWAY, "no downstream server available").into_response())
+				}
+			}
+		}
+	});
 
 	// Add logging to proxy requests
 	let proxy = ServiceBuilder::new()
 		.map_request(move |mut req: Request<Body>| {
+			let downstream_url = proxy_state.server_url();
 			let path = req.uri().path();
 			let query = req.uri().query().map(|q| format!("?{q}")).unwrap_or_default();
 			let downstream_full_url = format!("{}{}{}", downstream_url.as_str(), path, query);
 
 			req.extensions_mut().insert(DownstreamUrl(downstream_full_url));
+			req.extensions_mut().insert(SelectedDownstream(downstream_url));
 			req
 		})
 		.layer(
@@ -99,8 +136,14 @@ where
 		.route(routes::CONSTRAINTS_SLOT, get(get_constraints::<A>))
 		.route(routes::DELEGATION, post(post_delegation::<A>))
 		.route(routes::DELEGATIONS_SLOT, get(get_delegations::<A>))
+		.route(routes::DELEGATION_OFFERS, post(post_delegation_offer::<A>))
+		.route(routes::DELEGATION_OFFERS_SLOT, get(get_delegation_offers::<A>))
+		.route(routes::RECEIPT, get(get_receipt::<A>))
 		.route(routes::BLOCKS_WITH_PROOFS, post(post_blocks_with_proofs::<A>))
+		.route(routes::BLOCKS_WITH_PROOFS_SLOT, get(get_blocks_with_proofs::<A>))
+		.route(routes::BLOCKS_WITH_PROOFS_VALIDATE, post(validate_blocks_with_proofs::<A>))
 		.fallback_service(proxy)
+		.layer(axum::middleware::from_fn_with_state(Arc::clone(&state), proxy_receiver_auth::<A>))
 		.with_state(state)
 }
 
@@ -122,9 +165,9 @@ where
 			metrics.finish_status(ENDPOINT, METHOD, 200, start);
 			StatusCode::OK
 		}
-		Err(_) => {
-			metrics.finish_status(ENDPOINT, METHOD, 500, start);
-			StatusCode::INTERNAL_SERVER_ERROR
+		Err(e) => {
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			e.status()
 		}
 	}
 }
@@ -146,8 +189,8 @@ where
 			(StatusCode::OK, Json(capabilities)).into_response()
 		}
 		Err(e) => {
-			metrics.finish_status(ENDPOINT, METHOD, 500, start);
-			(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to fetch capabilities: {e}")).into_response()
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to fetch capabilities: {e}")).into_response()
 		}
 	}
 }
@@ -164,19 +207,19 @@ where
 	let start = metrics.start(ENDPOINT, METHOD);
 
 	match api.post_constraints(body).await {
-		Ok(()) => {
+		Ok(receipt) => {
 			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
-			StatusCode::OK.into_response()
+			(StatusCode::OK, Json(receipt)).into_response()
 		}
 		Err(e) => {
-			metrics.finish_status(ENDPOINT, METHOD, StatusCode::INTERNAL_SERVER_ERROR.as_u16(), start);
-			(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to store constraints: {e}")).into_response()
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to store constraints: {e}")).into_response()
 		}
 	}
 }
 
 // GET /constraints/{slot}
-async fn get_constraints<A>(State(api): State<Arc<A>>, Path(slot): Path<u64>, headers: HeaderMap) -> impl IntoResponse
+async fn get_constraints<A>(State(api): State<Arc<A>>, Path(slot): Path<u64>, auth: ReceiverAuth) -> impl IntoResponse
 where
 	A: ConstraintsApi,
 {
@@ -186,21 +229,22 @@ where
 	let metrics = server_http_metrics();
 	let start = metrics.start(ENDPOINT, METHOD);
 
-	match AuthorizationContext::from_headers(&headers) {
-		Ok(auth) => match api.get_constraints(slot, auth).await {
-			Ok(constraints) => {
-				metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
-				(StatusCode::OK, Json(constraints)).into_response()
-			}
-			Err(e) => {
-				metrics.finish_status(ENDPOINT, METHOD, StatusCode::INTERNAL_SERVER_ERROR.as_u16(), start);
-				(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to get constraints for slot {slot}: {e}"))
-					.into_response()
-			}
-		},
+	let access = match auth.authorize(api.as_ref(), slot, ReceiverAuthPolicy::Receivers).await {
+		Ok(access) => access,
+		Err(e) => {
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			return e.into_response();
+		}
+	};
+
+	match api.get_constraints(slot, access).await {
+		Ok(constraints) => {
+			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
+			(StatusCode::OK, Json(constraints)).into_response()
+		}
 		Err(e) => {
-			return (StatusCode::BAD_REQUEST, format!("failed to get constraints for slot {slot}: {e}"))
-				.into_response();
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to get constraints for slot {slot}: {e}")).into_response()
 		}
 	}
 }
@@ -217,19 +261,19 @@ where
 	let start = metrics.start(ENDPOINT, METHOD);
 
 	match api.post_delegation(body).await {
-		Ok(()) => {
+		Ok(receipt) => {
 			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
-			StatusCode::OK.into_response()
+			(StatusCode::OK, Json(receipt)).into_response()
 		}
 		Err(e) => {
-			metrics.finish_status(ENDPOINT, METHOD, StatusCode::INTERNAL_SERVER_ERROR.as_u16(), start);
-			(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to store delegation: {e}")).into_response()
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to store delegation: {e}")).into_response()
 		}
 	}
 }
 
 // GET /delegations/{slot}
-async fn get_delegations<A>(State(api): State<Arc<A>>, Path(slot): Path<u64>) -> impl IntoResponse
+async fn get_delegations<A>(State(api): State<Arc<A>>, Path(slot): Path<u64>, auth: ReceiverAuth) -> impl IntoResponse
 where
 	A: ConstraintsApi,
 {
@@ -239,15 +283,95 @@ where
 	let metrics = server_http_metrics();
 	let start = metrics.start(ENDPOINT, METHOD);
 
+	if let Err(e) = auth.authorize(api.as_ref(), slot, api.receiver_auth_config().delegations).await {
+		metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+		return e.into_response();
+	}
+
 	match api.get_delegations(slot).await {
 		Ok(delegations) => {
 			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
 			(StatusCode::OK, Json(delegations)).into_response()
 		}
 		Err(e) => {
-			metrics.finish_status(ENDPOINT, METHOD, StatusCode::INTERNAL_SERVER_ERROR.as_u16(), start);
-			(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to get delegations for slot {slot}: {e}"))
-				.into_response()
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to get delegations for slot {slot}: {e}")).into_response()
+		}
+	}
+}
+
+// POST /delegation_offers
+async fn post_delegation_offer<A>(
+	State(api): State<Arc<A>>,
+	Json(body): Json<SignedDelegationOffer>,
+) -> impl IntoResponse
+where
+	A: ConstraintsApi,
+{
+	const ENDPOINT: &str = routes::DELEGATION_OFFERS;
+	const METHOD: &str = "POST";
+
+	let metrics = server_http_metrics();
+	let start = metrics.start(ENDPOINT, METHOD);
+
+	match api.post_delegation_offer(body).await {
+		Ok(()) => {
+			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
+			StatusCode::OK.into_response()
+		}
+		Err(e) => {
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to store delegation offer: {e}")).into_response()
+		}
+	}
+}
+
+// GET /delegation_offers/{slot}
+async fn get_delegation_offers<A>(State(api): State<Arc<A>>, Path(slot): Path<u64>) -> impl IntoResponse
+where
+	A: ConstraintsApi,
+{
+	const ENDPOINT: &str = routes::DELEGATION_OFFERS_SLOT;
+	const METHOD: &str = "GET";
+
+	let metrics = server_http_metrics();
+	let start = metrics.start(ENDPOINT, METHOD);
+
+	match api.get_delegation_offers(slot).await {
+		Ok(offers) => {
+			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
+			(StatusCode::OK, Json(offers)).into_response()
+		}
+		Err(e) => {
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to get delegation offers for slot {slot}: {e}")).into_response()
+		}
+	}
+}
+
+// GET /receipts/{message_hash}
+async fn get_receipt<A>(State(api): State<Arc<A>>, Path(message_hash): Path<B256>) -> impl IntoResponse
+where
+	A: ConstraintsApi,
+{
+	const ENDPOINT: &str = routes::RECEIPT;
+	const METHOD: &str = "GET";
+
+	let metrics = server_http_metrics();
+	let start = metrics.start(ENDPOINT, METHOD);
+
+	match api.get_receipt(message_hash).await {
+		Ok(Some(receipt)) => {
+			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
+			(StatusCode::OK, Json(receipt)).into_response()
+		}
+		Ok(None) => {
+			metrics.finish_status(ENDPOINT, METHOD, StatusCode::NOT_FOUND.as_u16(), start);
+			(StatusCode::NOT_FOUND, format!("no receipt found for message {message_hash}")).into_response()
+		}
+		Err(e) => {
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to get receipt for message {message_hash}: {e}")).into_response()
 		}
 	}
 }
@@ -275,8 +399,58 @@ where
 		}
 		Err(e) => {
 			error!("Failed to submit blocks with proofs: {e}");
-			metrics.finish_status(ENDPOINT, METHOD, StatusCode::INTERNAL_SERVER_ERROR.as_u16(), start);
-			(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to submit blocks with proofs: {e}")).into_response()
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to submit blocks with proofs: {e}")).into_response()
+		}
+	}
+}
+
+// GET /blocks_with_proofs/{slot}
+async fn get_blocks_with_proofs<A>(State(api): State<Arc<A>>, Path(slot): Path<u64>) -> impl IntoResponse
+where
+	A: ConstraintsApi,
+{
+	const ENDPOINT: &str = routes::BLOCKS_WITH_PROOFS_SLOT;
+	const METHOD: &str = "GET";
+
+	let metrics = server_http_metrics();
+	let start = metrics.start(ENDPOINT, METHOD);
+
+	match api.get_blocks_with_proofs(slot).await {
+		Ok(blocks) => {
+			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
+			(StatusCode::OK, Json(blocks)).into_response()
+		}
+		Err(e) => {
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to get blocks with proofs for slot {slot}: {e}")).into_response()
+		}
+	}
+}
+
+// POST /blocks_with_proofs/validate
+async fn validate_blocks_with_proofs<A>(
+	State(api): State<Arc<A>>,
+	Json(body): Json<SubmitBlockRequestWithProofs>,
+) -> impl IntoResponse
+where
+	A: ConstraintsApi,
+{
+	const ENDPOINT: &str = routes::BLOCKS_WITH_PROOFS_VALIDATE;
+	const METHOD: &str = "POST";
+
+	let metrics = server_http_metrics();
+	let start = metrics.start(ENDPOINT, METHOD);
+
+	match api.validate_blocks_with_proofs(body).await {
+		Ok(report) => {
+			metrics.finish_status(ENDPOINT, METHOD, StatusCode::OK.as_u16(), start);
+			(StatusCode::OK, Json(report)).into_response()
+		}
+		Err(e) => {
+			error!("Failed to validate blocks with proofs: {e}");
+			metrics.finish_status(ENDPOINT, METHOD, e.status().as_u16(), start);
+			(e.status(), format!("failed to validate blocks with proofs: {e}")).into_response()
 		}
 	}
 }
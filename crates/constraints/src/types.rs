@@ -1,14 +1,16 @@
 use alloy::consensus::TxEnvelope;
-use alloy::primitives::{Address, B256, Bytes};
-use alloy::rpc::types::beacon::relay::SubmitBlockRequest as AlloySubmitBlockRequest;
+use alloy::primitives::{Address, B256, Bytes, Signature, keccak256};
+use alloy::rpc::types::beacon::relay::{BidTrace, SubmitBlockRequest as AlloySubmitBlockRequest};
 use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
+#[cfg(feature = "full")]
 use axum::http::HeaderMap;
+#[cfg(feature = "full")]
 use common::utils::decode_pubkey;
-use eyre::{Result, eyre};
 use serde::{Deserialize, Serialize};
 use ssz_derive::{Decode, Encode};
 
-use crate::helpers::extract_transactions;
+use crate::error::{ConstraintsError, Result};
+use crate::helpers::{extract_blob_versioned_hashes, extract_transactions};
 
 /// A constraint with its type and payload
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
@@ -35,6 +37,51 @@ pub struct SignedDelegation {
 	pub signature: BlsSignature,
 }
 
+/// A gateway's offer to be the delegate of a slot, published to the relay for proposers to pick from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DelegationOffer {
+	/// Gateway key the delegation would name as delegate, the offer is signed by it
+	pub delegate: BlsPublicKey,
+	/// Committer address the delegation would name
+	pub committer: Address,
+	pub slot: u64,
+	/// Fee the gateway bids for the slot, in gwei
+	pub bid_gwei: u64,
+	/// Constraint types the gateway commits to
+	pub constraint_types: Vec<u64>,
+}
+
+/// A delegation offer with the delegate's BLS signature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedDelegationOffer {
+	pub message: DelegationOffer,
+	pub nonce: u64,
+	pub signing_id: B256,
+	pub signature: BlsSignature,
+}
+
+/// A builder's request to start or stop receiving the constraints of a gateway
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceiverUpdate {
+	/// Builder key the constraints are sent to, the update is signed by it
+	pub receiver: BlsPublicKey,
+	/// Gateway key the update is addressed to, so it cannot be replayed to another gateway
+	pub gateway: BlsPublicKey,
+	/// Whether the receiver is added or removed
+	pub add: bool,
+	/// Last slot the update is accepted in
+	pub expiry_slot: u64,
+}
+
+/// A receiver update with the receiver's BLS signature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedReceiverUpdate {
+	pub message: ReceiverUpdate,
+	pub nonce: u64,
+	pub signing_id: B256,
+	pub signature: BlsSignature,
+}
+
 /// A constraints message containing multiple constraints
 #[derive(Debug, Clone, Serialize, Deserialize, Default, Encode, Decode)]
 pub struct ConstraintsMessage {
@@ -65,6 +112,10 @@ pub struct ConstraintCapabilities {
 pub struct ConstraintProofs {
 	pub constraint_types: Vec<u64>,
 	pub payloads: Vec<Bytes>,
+	/// Trie nodes shared by every payload of a multiproof, whose payloads carry no nodes of their own
+	/// Empty for single proofs
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub multiproof_nodes: Vec<Bytes>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
@@ -79,6 +130,16 @@ impl SubmitBlockRequestWithProofs {
 		self.message.bid_trace().slot
 	}
 
+	/// Block hash claimed by the bid trace
+	pub fn block_hash(&self) -> B256 {
+		self.message.bid_trace().block_hash
+	}
+
+	/// Parent block hash claimed by the bid trace
+	pub fn parent_hash(&self) -> B256 {
+		self.message.bid_trace().parent_hash
+	}
+
 	pub fn into_block_request(self) -> AlloySubmitBlockRequest {
 		self.message
 	}
@@ -86,74 +147,205 @@ impl SubmitBlockRequestWithProofs {
 	pub fn transactions(&self) -> Result<Vec<TxEnvelope>> {
 		extract_transactions(&self.message)
 	}
+
+	/// Versioned hashes of the blobs carried by the block
+	pub fn blob_versioned_hashes(&self) -> Vec<B256> {
+		extract_blob_versioned_hashes(&self.message)
+	}
 }
 
+/// Headers a constraints receiver authenticates with on GET /constraints/{slot}
+/// The signature is the receiver's BLS signature over `AuthorizationContext::message_hash(slot, timestamp_ms)` under
+/// the signing id and nonce, the relay only accepts it once and close to its timestamp
+pub const RECEIVER_SIGNATURE_HEADER: &str = "X-Receiver-Signature";
+pub const RECEIVER_PUBLIC_KEY_HEADER: &str = "X-Receiver-PublicKey";
+pub const RECEIVER_SIGNING_ID_HEADER: &str = "X-Receiver-SigningId";
+pub const RECEIVER_NONCE_HEADER: &str = "X-Receiver-Nonce";
+pub const RECEIVER_TIMESTAMP_HEADER: &str = "X-Receiver-Timestamp";
+
 pub struct AuthorizationContext {
 	pub signature: Option<BlsSignature>,
 	pub public_key: Option<BlsPublicKey>,
 	pub nonce: Option<u64>,
 	pub signing_id: Option<B256>,
+	/// Unix time in milliseconds at which the receiver signed
+	pub timestamp_ms: Option<u64>,
 }
 
-/// Extract and parse BLS signature, public key, nonce, and signing_id from headers
+/// Extract and parse BLS signature, public key, nonce, signing_id and timestamp from headers
 impl AuthorizationContext {
+	/// Hash a receiver signs to read the constraints of `slot` at `timestamp_ms`:
+	/// `keccak256(slot_be_bytes ++ timestamp_ms_be_bytes)`
+	pub fn message_hash(slot: u64, timestamp_ms: u64) -> B256 {
+		let mut preimage = [0u8; 16];
+		preimage[..8].copy_from_slice(&slot.to_be_bytes());
+		preimage[8..].copy_from_slice(&timestamp_ms.to_be_bytes());
+		keccak256(preimage)
+	}
+
+	#[cfg(feature = "full")]
 	pub fn from_headers(headers: &HeaderMap) -> Result<AuthorizationContext> {
 		// Extract headers
-		let signature = match headers.get("X-Receiver-Signature") {
+		let signature = match headers.get(RECEIVER_SIGNATURE_HEADER) {
 			Some(signature_header) => {
-				let signature_str =
-					signature_header.to_str().map_err(|_| eyre!("Invalid X-Receiver-Signature header"))?;
-				let bls_signature = BlsSignature::new(
-					signature_str
-						.strip_prefix("0x")
-						.unwrap_or(signature_str)
-						.as_bytes()
-						.try_into()
-						.map_err(|e| eyre!("Invalid BLS signature: {:?}", e))?,
-				);
+				let signature_str = signature_header
+					.to_str()
+					.map_err(|_| ConstraintsError::InvalidRequest("Invalid X-Receiver-Signature header".to_string()))?;
+				let bls_signature = signature_str
+					.parse::<BlsSignature>()
+					.map_err(|e| ConstraintsError::InvalidRequest(format!("Invalid BLS signature: {:?}", e)))?;
 				Some(bls_signature)
 			}
 			None => None,
 		};
 
-		let public_key = match headers.get("X-Receiver-PublicKey") {
+		let public_key = match headers.get(RECEIVER_PUBLIC_KEY_HEADER) {
 			Some(public_key_header) => {
-				let public_key_str =
-					public_key_header.to_str().map_err(|_| eyre!("Invalid X-Receiver-PublicKey header"))?;
-				let public_key = decode_pubkey(public_key_str)?;
+				let public_key_str = public_key_header
+					.to_str()
+					.map_err(|_| ConstraintsError::InvalidRequest("Invalid X-Receiver-PublicKey header".to_string()))?;
+				let public_key =
+					decode_pubkey(public_key_str).map_err(|e| ConstraintsError::InvalidRequest(e.to_string()))?;
 				Some(public_key)
 			}
 			None => None,
 		};
 
-		let signing_id = match headers.get("X-Receiver-SigningId") {
+		let signing_id = match headers.get(RECEIVER_SIGNING_ID_HEADER) {
 			Some(signing_id_header) => {
-				let signing_id_str =
-					signing_id_header.to_str().map_err(|_| eyre!("Invalid X-Receiver-SigningId header"))?;
-				let signing_id =
-					B256::from_slice(signing_id_str.strip_prefix("0x").unwrap_or(signing_id_str).as_bytes());
+				let signing_id_str = signing_id_header
+					.to_str()
+					.map_err(|_| ConstraintsError::InvalidRequest("Invalid X-Receiver-SigningId header".to_string()))?;
+				let signing_id = signing_id_str
+					.parse::<B256>()
+					.map_err(|e| ConstraintsError::InvalidRequest(format!("Invalid signing id: {:?}", e)))?;
 				Some(signing_id)
 			}
 			None => None,
 		};
 
-		let nonce = match headers.get("X-Receiver-Nonce") {
+		let nonce = match headers.get(RECEIVER_NONCE_HEADER) {
 			Some(nonce_header) => {
-				let nonce_str = nonce_header.to_str().map_err(|_| eyre!("Invalid X-Receiver-Nonce header"))?;
-				Some(nonce_str.parse::<u64>().map_err(|e| eyre!("Invalid nonce format: {}", e))?)
+				let nonce_str = nonce_header
+					.to_str()
+					.map_err(|_| ConstraintsError::InvalidRequest("Invalid X-Receiver-Nonce header".to_string()))?;
+				Some(
+					nonce_str
+						.parse::<u64>()
+						.map_err(|e| ConstraintsError::InvalidRequest(format!("Invalid nonce format: {}", e)))?,
+				)
 			}
 			None => None,
 		};
 
-		Ok(AuthorizationContext { signature, public_key, nonce, signing_id })
+		let timestamp_ms = match headers.get(RECEIVER_TIMESTAMP_HEADER) {
+			Some(timestamp_header) => {
+				let timestamp_str = timestamp_header
+					.to_str()
+					.map_err(|_| ConstraintsError::InvalidRequest("Invalid X-Receiver-Timestamp header".to_string()))?;
+				Some(
+					timestamp_str
+						.parse::<u64>()
+						.map_err(|e| ConstraintsError::InvalidRequest(format!("Invalid timestamp format: {}", e)))?,
+				)
+			}
+			None => None,
+		};
+
+		Ok(AuthorizationContext { signature, public_key, nonce, signing_id, timestamp_ms })
 	}
+
+	/// Header name and value of every field that is set, the inverse of `from_headers`
+	pub fn to_headers(&self) -> Vec<(&'static str, String)> {
+		let mut headers = Vec::with_capacity(5);
+		if let Some(signature) = &self.signature {
+			headers.push((RECEIVER_SIGNATURE_HEADER, signature.to_string()));
+		}
+		if let Some(public_key) = &self.public_key {
+			headers.push((RECEIVER_PUBLIC_KEY_HEADER, public_key.to_string()));
+		}
+		if let Some(signing_id) = &self.signing_id {
+			headers.push((RECEIVER_SIGNING_ID_HEADER, signing_id.to_string()));
+		}
+		if let Some(nonce) = self.nonce {
+			headers.push((RECEIVER_NONCE_HEADER, nonce.to_string()));
+		}
+		if let Some(timestamp_ms) = self.timestamp_ms {
+			headers.push((RECEIVER_TIMESTAMP_HEADER, timestamp_ms.to_string()));
+		}
+		headers
+	}
+}
+/// Relay acknowledgment that a SignedConstraints or SignedDelegation message was accepted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Receipt {
+	/// Signing root of the accepted message
+	pub message_hash: B256,
+	/// Unix timestamp in milliseconds at which the relay accepted the message
+	pub timestamp_ms: u64,
+}
+
+/// A receipt signed by the relay's ECDSA key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedReceipt {
+	pub message: Receipt,
+	pub signer: Address,
+	pub signature: Signature,
 }
+
+/// Outcome of validating the proof for a single constraint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConstraintProofResult {
+	/// Position of the constraint in the merged constraints for the slot
+	pub index: usize,
+	pub constraint_type: u64,
+	/// Whether the block satisfies the constraint with a valid proof
+	pub satisfied: bool,
+	/// Why the proof failed, if it did
+	pub error: Option<String>,
+}
+
+/// Report returned by POST /blocks_with_proofs/validate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofValidationReport {
+	pub slot: u64,
+	/// Whether the submission would be accepted by POST /blocks_with_proofs
+	pub valid: bool,
+	/// Failure that is not tied to a single constraint, e.g. a proof count mismatch
+	pub error: Option<String>,
+	pub constraints: Vec<ConstraintProofResult>,
+}
+
+/// Record of a block accepted by POST /blocks_with_proofs, kept as evidence of which builder satisfied which
+/// constraints of the slot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptedBlockWithProofs {
+	/// Bid trace of the block, naming its builder
+	pub bid_trace: BidTrace,
+	/// Outcome of the proof of each merged constraint of the slot
+	pub constraints: Vec<ConstraintProofResult>,
+	/// Relay time the block was accepted at, in milliseconds
+	pub timestamp_ms: u64,
+}
+
+/// Response wrapper for GET /blocks_with_proofs/{slot}
+#[derive(Serialize, Deserialize)]
+pub struct BlocksWithProofsResponse {
+	pub blocks: Vec<AcceptedBlockWithProofs>,
+}
+
 /// Response wrapper for GET /delegations
 #[derive(Serialize, Deserialize)]
 pub struct DelegationsResponse {
 	pub delegations: Vec<SignedDelegation>,
 }
 
+/// Response wrapper for GET /delegation_offers/{slot}
+#[derive(Serialize, Deserialize)]
+pub struct DelegationOffersResponse {
+	pub offers: Vec<SignedDelegationOffer>,
+}
+
 /// Response wrapper for GET /constraints
 #[derive(Serialize, Deserialize)]
 pub struct ConstraintsResponse {
@@ -163,6 +355,7 @@ pub struct ConstraintsResponse {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use alloy::hex;
 	use alloy::primitives::Bytes;
 
 	#[test]
@@ -187,5 +380,94 @@ mod tests {
 		assert_eq!(constraint.payload, deserialized.payload);
 	}
 
+	#[cfg(feature = "full")]
+	#[test]
+	fn test_authorization_headers_roundtrip() {
+		let auth = AuthorizationContext {
+			signature: Some(BlsSignature::repeat_byte(0x11)),
+			public_key: Some(BlsPublicKey::repeat_byte(0x22)),
+			nonce: Some(7),
+			signing_id: Some(B256::repeat_byte(0x33)),
+			timestamp_ms: Some(1_700_000_000_000),
+		};
+
+		let mut headers = HeaderMap::new();
+		for (name, value) in auth.to_headers() {
+			headers.insert(name, value.parse().unwrap());
+		}
+		let parsed = AuthorizationContext::from_headers(&headers).unwrap();
+
+		assert_eq!(parsed.signature, auth.signature);
+		assert_eq!(parsed.public_key, auth.public_key);
+		assert_eq!(parsed.nonce, Some(7));
+		assert_eq!(parsed.signing_id, auth.signing_id);
+		assert_eq!(parsed.timestamp_ms, Some(1_700_000_000_000));
+		assert!(AuthorizationContext::from_headers(&HeaderMap::new()).unwrap().public_key.is_none());
+	}
+
+	/// Header values are hex strings decoded into the key and signature bytes, with or without a 0x prefix
+	#[cfg(feature = "full")]
+	#[test]
+	fn test_authorization_headers_are_hex_decoded() {
+		let mut headers = HeaderMap::new();
+		headers.insert(RECEIVER_SIGNATURE_HEADER, hex::encode([0xab; 96]).parse().unwrap());
+		headers.insert(RECEIVER_PUBLIC_KEY_HEADER, format!("0x{}", hex::encode([0xcd; 48])).parse().unwrap());
+		let parsed = AuthorizationContext::from_headers(&headers).unwrap();
+		assert_eq!(parsed.signature, Some(BlsSignature::repeat_byte(0xab)));
+		assert_eq!(parsed.public_key, Some(BlsPublicKey::repeat_byte(0xcd)));
+
+		headers.insert(RECEIVER_PUBLIC_KEY_HEADER, hex::encode([0xcd; 47]).parse().unwrap());
+		assert!(AuthorizationContext::from_headers(&headers).is_err());
+	}
+
+	/// The signed hash binds the slot and the timestamp
+	#[test]
+	fn test_authorization_message_hash() {
+		let hash = AuthorizationContext::message_hash(42, 1_000);
+		assert_eq!(hash, keccak256([42u64.to_be_bytes(), 1_000u64.to_be_bytes()].concat()));
+		assert_ne!(hash, AuthorizationContext::message_hash(42, 1_001));
+		assert_ne!(hash, AuthorizationContext::message_hash(43, 1_000));
+	}
+
+	/// The JSON and SSZ wire encodings of signed messages carry the same content
+	#[test]
+	fn test_signed_messages_json_and_ssz_roundtrip() {
+		use ssz::{Decode, Encode};
+
+		let constraints = SignedConstraints {
+			message: ConstraintsMessage {
+				proposer: BlsPublicKey::repeat_byte(1),
+				delegate: BlsPublicKey::repeat_byte(2),
+				slot: 42,
+				constraints: vec![Constraint { constraint_type: 1, payload: Bytes::from(vec![1, 2, 3]) }],
+				receivers: vec![BlsPublicKey::repeat_byte(3)],
+			},
+			nonce: 5,
+			signing_id: B256::repeat_byte(4),
+			signature: BlsSignature::repeat_byte(5),
+		};
+		let from_json: SignedConstraints = serde_json::from_str(&serde_json::to_string(&constraints).unwrap()).unwrap();
+		let from_ssz = SignedConstraints::from_ssz_bytes(&constraints.as_ssz_bytes()).unwrap();
+		assert_eq!(from_json.as_ssz_bytes(), constraints.as_ssz_bytes());
+		assert_eq!(serde_json::to_value(&from_ssz).unwrap(), serde_json::to_value(&constraints).unwrap());
+
+		let delegation = SignedDelegation {
+			message: Delegation {
+				proposer: BlsPublicKey::repeat_byte(1),
+				delegate: BlsPublicKey::repeat_byte(2),
+				committer: Address::repeat_byte(6),
+				slot: 42,
+				metadata: Bytes::from(vec![7]),
+			},
+			nonce: 8,
+			signing_id: B256::repeat_byte(4),
+			signature: BlsSignature::repeat_byte(5),
+		};
+		let from_json: SignedDelegation = serde_json::from_str(&serde_json::to_string(&delegation).unwrap()).unwrap();
+		let from_ssz = SignedDelegation::from_ssz_bytes(&delegation.as_ssz_bytes()).unwrap();
+		assert_eq!(from_json.as_ssz_bytes(), delegation.as_ssz_bytes());
+		assert_eq!(serde_json::to_value(&from_ssz).unwrap(), serde_json::to_value(&delegation).unwrap());
+	}
+
 	// todo more unit tests
 }
@@ -0,0 +1,35 @@
+use alloy::primitives::B256;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+use fabric_inclusion::proofs::TransactionTrieBuilder;
+use fabric_inclusion::types::InclusionPayload;
+
+/// Block sizes to benchmark, a full mainnet block holds a few hundred transactions
+const BLOCK_SIZES: [usize; 3] = [50, 200, 500];
+
+fn proofs(c: &mut Criterion) {
+	let mut group = c.benchmark_group("proofs");
+	for size in BLOCK_SIZES {
+		let payloads = (0..size).map(|_| InclusionPayload::random()).collect::<Vec<_>>();
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let tx_hashes = payloads.iter().map(|p| p.tx_hash().unwrap()).collect::<Vec<B256>>();
+
+		group.bench_with_input(BenchmarkId::new("build", size), &transactions, |b, transactions| {
+			b.iter(|| TransactionTrieBuilder::build(transactions).unwrap())
+		});
+
+		let builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		group.bench_with_input(BenchmarkId::new("prove_batch", size), &tx_hashes, |b, tx_hashes| {
+			b.iter(|| builder.prove_batch(tx_hashes).unwrap())
+		});
+
+		let proofs = builder.prove_batch(&tx_hashes).unwrap();
+		group.bench_with_input(BenchmarkId::new("verify_batch", size), &proofs, |b, proofs| {
+			b.iter(|| builder.verify_batch(proofs).unwrap())
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, proofs);
+criterion_main!(benches);
@@ -4,11 +4,61 @@ pub const INCLUSION_COMMITMENT_TYPE: u64 = 1;
 /// The constraint type for inclusion constraints
 pub const INCLUSION_CONSTRAINT_TYPE: u64 = 1;
 
+/// The commitment type for exclusion commitments, a transaction that must not be in the slot's block
+pub const EXCLUSION_COMMITMENT_TYPE: u64 = 2;
+
+/// The constraint type for exclusion constraints
+pub const EXCLUSION_CONSTRAINT_TYPE: u64 = 2;
+
+/// The commitment type for ordering commitments, a transaction that must be near the top of the slot's block
+pub const ORDERING_COMMITMENT_TYPE: u64 = 3;
+
+/// The constraint type for ordering constraints
+pub const ORDERING_CONSTRAINT_TYPE: u64 = 3;
+
+/// The commitment type for bundle commitments, transactions that must be included contiguously and in order
+pub const BUNDLE_COMMITMENT_TYPE: u64 = 4;
+
+/// The constraint type binding the transactions of a bundle to contiguous, ordered positions
+/// Each transaction of the bundle also gets its own inclusion constraint
+pub const BUNDLE_CONSTRAINT_TYPE: u64 = 4;
+
+/// Maximum number of transactions in a bundle commitment
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 16;
+
+/// The commitment type for blob commitments, an EIP-4844 transaction whose blobs must be in the slot's block
+pub const BLOB_COMMITMENT_TYPE: u64 = 5;
+
+/// The constraint type binding a blob transaction to the blobs bundle of the block
+/// The transaction also gets its own inclusion constraint
+pub const BLOB_CONSTRAINT_TYPE: u64 = 5;
+
 /// Maximum number of constraints per slot
 pub const MAX_CONSTRAINTS_PER_SLOT: usize = 256;
 
+/// Maximum number of delegation offers a relay stores per slot, one per delegate
+pub const MAX_DELEGATION_OFFERS_PER_SLOT: usize = 32;
+
 /// Number of slots to query for delegated slots
 pub const LOOKAHEAD_WINDOW_SIZE: u64 = 64;
 
 /// Number of milliseconds before the next slot to trigger posting SignedConstraints
 pub const CONSTRAINT_TRIGGER_OFFSET_MS: i64 = 14_000;
+
+/// Number of milliseconds after the end of a slot before its commitments are checked against the canonical block
+pub const INCLUSION_MONITOR_DELAY_MS: i64 = 4_000;
+
+/// Number of commitment events buffered for slow WebSocket subscribers
+pub const COMMITMENT_EVENTS_CHANNEL_SIZE: usize = 1024;
+
+/// Maximum number of slots re-verified by one signature audit, eight epochs of slots
+pub const MAX_AUDIT_SLOTS: u64 = 256;
+
+/// Number of milliseconds the execution client is given to build a fallback block before it is fetched
+pub const FALLBACK_BUILD_TIME_MS: u64 = 500;
+
+/// Number of milliseconds to wait before resubscribing to the execution client's pending transactions
+pub const MEMPOOL_RECONNECT_DELAY_MS: u64 = 5_000;
+
+/// Number of milliseconds between checks of the gateway config file for changes
+pub const CONFIG_WATCH_INTERVAL_MS: u64 = 5_000;
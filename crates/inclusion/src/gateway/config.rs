@@ -1,4 +1,21 @@
+use std::path::PathBuf;
+
+use alloy::primitives::Address;
+use commitments::auth::AuthConfig;
+use common::http::HttpClientConfig;
+use common::leader::LeaderElectionConfig;
+use common::logging::LoggingConfig;
+use config::{ConfigErrors, Validate};
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
+use signing::kms::KmsSignerConfig;
+use signing::local::LocalSignerConfig;
+use urc::indexer::UrcConfig;
+
+use crate::constants::CONSTRAINT_TRIGGER_OFFSET_MS;
+
+/// Prefix of the environment variables overriding gateway config values, e.g. `FABRIC_GATEWAY__RPC_PORT`
+pub const GATEWAY_ENV_PREFIX: &str = "FABRIC_GATEWAY";
 
 /// Gateway configuration for inclusion preconfs
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,12 +44,20 @@ pub struct GatewayConfig {
 	/// API key for the Relay server (constraints API)
 	pub relay_api_key: Option<String>,
 
+	/// Timeout and connection pool of the relay client, constraints are posted in the last second of a slot
+	#[serde(default)]
+	pub relay_http: HttpClientConfig,
+
 	/// Host of the Execution client
 	pub execution_client_host: String,
 
 	/// Port of the Execution client
 	pub execution_client_port: u16,
 
+	/// Beacon node the slot clock is synced against and head events are read from, the host clock is trusted if not set
+	#[serde(default)]
+	pub beacon_api_url: Option<Url>,
+
 	/// Constraints receivers
 	pub constraints_receivers: Vec<String>,
 
@@ -47,4 +72,280 @@ pub struct GatewayConfig {
 
 	/// Gateway public key for signing constraints
 	pub gateway_public_key: String,
+
+	/// Further BLS keys of this gateway that proposers may delegate to, e.g. per-relay or per-product proxy keys
+	/// Constraints of a slot are signed with the key it was delegated to
+	#[serde(default)]
+	pub additional_public_keys: Vec<String>,
+
+	/// Minimum tip per gas in wei a transaction must pay for the gateway to commit to it
+	#[serde(default)]
+	pub min_tip_per_gas_wei: u64,
+
+	/// Simulate committed transactions with eth_call before signing, rejecting those that revert
+	#[serde(default)]
+	pub simulate_commitments: bool,
+
+	/// Fraction of the block gas limit the gateway may commit to per slot
+	#[serde(default = "default_slot_gas_budget_fraction")]
+	pub slot_gas_budget_fraction: f64,
+
+	/// Reject commitment requests that arrive less than this many milliseconds before their slot
+	#[serde(default = "default_commitment_cutoff_ms")]
+	pub commitment_cutoff_ms: i64,
+
+	/// API keys, rate limits and per-slot commitment quotas for the Commitments RPC server
+	#[serde(default)]
+	pub auth: Option<AuthConfig>,
+
+	/// URC registry the gateway public key must be registered in and opted into the slasher, checked at startup
+	#[serde(default)]
+	pub urc: Option<UrcConfig>,
+
+	/// Committer address the URC opt-in must name, any committer is accepted if not set
+	#[serde(default)]
+	pub committer_address: Option<Address>,
+
+	/// Start even if the URC registration check fails, same as `--allow-unregistered`
+	#[serde(default)]
+	pub allow_unregistered: bool,
+
+	/// Sign constraints and commitments with local keys instead of the Commit-Boost signer service
+	#[serde(default)]
+	pub local_signer: Option<LocalSignerConfig>,
+
+	/// Sign commitments with a committer key held in AWS KMS, whatever signs constraints
+	#[serde(default)]
+	pub committer_kms: Option<KmsSignerConfig>,
+
+	/// Port of the admin API, served on `rpc_host`. The admin API is disabled if not set
+	#[serde(default)]
+	pub admin_port: Option<u16>,
+
+	/// Bearer token required to call the admin API
+	#[serde(default)]
+	pub admin_api_key: Option<String>,
+
+	/// Admit requests to a slot close to its gas budget by fee instead of first come, first served
+	#[serde(default)]
+	pub intake: Option<IntakeConfig>,
+
+	/// Retries and incremental updates of the constraints posted to the relay
+	#[serde(default)]
+	pub constraint_posting: ConstraintPostingConfig,
+
+	/// Run as one of several instances sharing the delegations, only the elected leader commits and posts constraints
+	#[serde(default)]
+	pub leader_election: Option<LeaderElectionConfig>,
+
+	/// Build and submit a block satisfying the constraints of delegated slots, in case no builder does
+	#[serde(default)]
+	pub fallback_builder: Option<FallbackBuilderConfig>,
+
+	/// Commit to matching transactions of the execution client's mempool, for senders not using the Commitments RPC
+	#[serde(default)]
+	pub mempool_ingestion: Option<MempoolIngestionConfig>,
+
+	/// Publish a delegation offer to the relay for every undelegated slot of the lookahead window
+	#[serde(default)]
+	pub delegation_offer: Option<DelegationOfferConfig>,
+
+	/// Log format, module levels and log file
+	#[serde(default)]
+	pub logging: LoggingConfig,
+}
+
+impl Validate for GatewayConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.host("rpc_host", &self.rpc_host);
+		errors.port("rpc_port", self.rpc_port);
+		errors.host("metrics_host", &self.metrics_host);
+		errors.port("metrics_port", self.metrics_port);
+		errors.distinct_ports(&[
+			("rpc_port", Some(self.rpc_port)),
+			("metrics_port", Some(self.metrics_port)),
+			("admin_port", self.admin_port),
+		]);
+		errors.not_empty("db_path", &self.db_path);
+		errors.host("relay_host", &self.relay_host);
+		errors.port("relay_port", self.relay_port);
+		errors.positive("relay_http.timeout_ms", self.relay_http.timeout_ms);
+		errors.host("execution_client_host", &self.execution_client_host);
+		errors.port("execution_client_port", self.execution_client_port);
+		for (index, receiver) in self.constraints_receivers.iter().enumerate() {
+			errors.bls_public_key(&format!("constraints_receivers[{}]", index), receiver);
+		}
+		errors.bytes32("module_signing_id", &self.module_signing_id);
+		errors.positive("delegation_check_interval_seconds", self.delegation_check_interval_seconds);
+		errors.bls_public_key("gateway_public_key", &self.gateway_public_key);
+		for (index, public_key) in self.additional_public_keys.iter().enumerate() {
+			errors.bls_public_key(&format!("additional_public_keys[{}]", index), public_key);
+		}
+		errors.ensure(
+			self.slot_gas_budget_fraction > 0.0 && self.slot_gas_budget_fraction <= 1.0,
+			"slot_gas_budget_fraction",
+			format!("{} must be in (0, 1]", self.slot_gas_budget_fraction),
+		);
+		errors.ensure(self.commitment_cutoff_ms >= 0, "commitment_cutoff_ms", "must not be negative");
+		if let Some(urc) = &self.urc {
+			errors.url("urc.rpc_url", &urc.rpc_url);
+		}
+		errors.ensure(
+			self.admin_port.is_none() || self.admin_api_key.as_ref().is_some_and(|key| !key.is_empty()),
+			"admin_api_key",
+			"must be set when admin_port is set",
+		);
+		if let Some(intake) = &self.intake {
+			errors.ensure(
+				intake.contention_fraction > 0.0 && intake.contention_fraction <= 1.0,
+				"intake.contention_fraction",
+				format!("{} must be in (0, 1]", intake.contention_fraction),
+			);
+		}
+		let posting = &self.constraint_posting;
+		errors.ensure(
+			posting.retry_backoff_ms <= posting.max_retry_backoff_ms,
+			"constraint_posting.retry_backoff_ms",
+			"must not exceed constraint_posting.max_retry_backoff_ms",
+		);
+		errors.ensure(posting.hard_cutoff_ms >= 0, "constraint_posting.hard_cutoff_ms", "must not be negative");
+		if let Some(offer) = &self.delegation_offer {
+			errors.ensure(
+				offer.committer_address != Address::ZERO,
+				"delegation_offer.committer_address",
+				"must be set",
+			);
+		}
+		if let Some(fallback_builder) = &self.fallback_builder {
+			errors.not_empty("fallback_builder.builder_key_env", &fallback_builder.builder_key_env);
+		}
+		if let Err(e) = self.logging.check() {
+			errors.add("logging", e);
+		}
+	}
+}
+
+/// How constraints are posted to the relay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstraintPostingConfig {
+	/// Delay before the first retry of a failed post (in milliseconds), doubled after each failed retry
+	#[serde(default = "default_retry_backoff_ms")]
+	pub retry_backoff_ms: u64,
+
+	/// Longest delay between two retries (in milliseconds)
+	#[serde(default = "default_max_retry_backoff_ms")]
+	pub max_retry_backoff_ms: u64,
+
+	/// No post is attempted less than this many milliseconds before the slot, a slot still unposted is dropped
+	#[serde(default = "default_posting_hard_cutoff_ms")]
+	pub hard_cutoff_ms: i64,
+
+	/// Post the slot's constraints again when commitments are added after the first post, up to the hard cutoff
+	/// The relay replaces the previous message of the gateway, so commitments are accepted until the hard cutoff
+	#[serde(default)]
+	pub incremental_updates: bool,
+}
+
+impl Default for ConstraintPostingConfig {
+	fn default() -> Self {
+		Self {
+			retry_backoff_ms: default_retry_backoff_ms(),
+			max_retry_backoff_ms: default_max_retry_backoff_ms(),
+			hard_cutoff_ms: default_posting_hard_cutoff_ms(),
+			incremental_updates: false,
+		}
+	}
+}
+
+/// Fee-prioritized admission of commitment requests to contended slots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntakeConfig {
+	/// Fraction of the slot gas budget past which requests to the slot compete on fee for admission
+	#[serde(default = "default_intake_contention_fraction")]
+	pub contention_fraction: f64,
+
+	/// How long requests to a contended slot are collected before the highest paying ones are admitted (in milliseconds)
+	#[serde(default = "default_intake_batch_window_ms")]
+	pub batch_window_ms: u64,
+}
+
+/// Delegation offers proposers may pick this gateway by, signed with `gateway_public_key`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelegationOfferConfig {
+	/// Fee bid per delegated slot, in gwei
+	pub bid_gwei: u64,
+
+	/// Committer address the offered delegations would name
+	pub committer_address: Address,
+
+	/// Constraint types offered, every type the gateway supports if empty
+	#[serde(default)]
+	pub constraint_types: Vec<u64>,
+}
+
+/// Automatic inclusion commitments for pending transactions of the execution client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MempoolIngestionConfig {
+	/// WebSocket endpoint of the execution client the pending transactions are subscribed from
+	pub execution_client_ws_url: Url,
+
+	/// Slasher named in the commitments created for pending transactions
+	pub slasher: Address,
+
+	/// Only transactions to one of these addresses are committed to, any recipient if empty
+	#[serde(default)]
+	pub to_allowlist: Vec<Address>,
+
+	/// Minimum priority fee per gas in wei (gas price for legacy transactions) a pending transaction must offer
+	#[serde(default)]
+	pub min_priority_fee_per_gas_wei: u64,
+}
+
+/// Fallback building of constrained blocks through the execution client's engine API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FallbackBuilderConfig {
+	/// Authenticated engine API of the execution client
+	pub engine_api_url: Url,
+
+	/// File holding the hex-encoded JWT secret of the engine API
+	pub jwt_secret_path: PathBuf,
+
+	/// Environment variable holding the hex-encoded BLS secret key the block bids are signed with
+	pub builder_key_env: String,
+
+	/// How long before the start of a delegated slot the block is built and submitted (in milliseconds)
+	#[serde(default = "default_fallback_trigger_offset_ms")]
+	pub trigger_offset_ms: i64,
+}
+
+fn default_commitment_cutoff_ms() -> i64 {
+	CONSTRAINT_TRIGGER_OFFSET_MS
+}
+
+fn default_retry_backoff_ms() -> u64 {
+	250
+}
+
+fn default_max_retry_backoff_ms() -> u64 {
+	2_000
+}
+
+fn default_posting_hard_cutoff_ms() -> i64 {
+	1_000
+}
+
+fn default_fallback_trigger_offset_ms() -> i64 {
+	1_000
+}
+
+fn default_slot_gas_budget_fraction() -> f64 {
+	0.5
+}
+
+fn default_intake_contention_fraction() -> f64 {
+	0.8
+}
+
+fn default_intake_batch_window_ms() -> u64 {
+	200
 }
@@ -0,0 +1,97 @@
+//! Minimal engine API client, authenticated with the execution client's JWT secret
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy::hex;
+use alloy::rpc::types::engine::{
+	ExecutionPayloadEnvelopeV4, ForkchoiceState, ForkchoiceUpdated, PayloadAttributes, PayloadId,
+};
+use eyre::{Result, WrapErr, eyre};
+use jsonwebtoken::{EncodingKey, Header};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// JWT claims of the engine API, only the issued-at time is required
+#[derive(Serialize)]
+struct Claims {
+	iat: u64,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+	code: i64,
+	message: String,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+	result: Option<T>,
+	error: Option<RpcError>,
+}
+
+/// Client of the execution client's authenticated engine API
+#[derive(Clone)]
+pub struct EngineClient {
+	client: reqwest::Client,
+	url: Url,
+	jwt_secret: EncodingKey,
+}
+
+impl EngineClient {
+	/// Client of the engine API at `url`, with the hex-encoded JWT secret read from `jwt_secret_path`
+	pub fn new(url: Url, jwt_secret_path: &Path) -> Result<Self> {
+		let secret = std::fs::read_to_string(jwt_secret_path)
+			.wrap_err_with(|| format!("Failed to read JWT secret {}", jwt_secret_path.display()))?;
+		let secret = hex::decode(secret.trim()).wrap_err("JWT secret is not hex")?;
+		if secret.len() != 32 {
+			return Err(eyre!("JWT secret must be 32 bytes, got {}", secret.len()));
+		}
+
+		Ok(Self { client: reqwest::Client::new(), url, jwt_secret: EncodingKey::from_secret(&secret) })
+	}
+
+	/// engine_forkchoiceUpdatedV3, starts building a payload on `state.head_block_hash` with `attributes`
+	pub async fn forkchoice_updated_v3(
+		&self,
+		state: ForkchoiceState,
+		attributes: PayloadAttributes,
+	) -> Result<ForkchoiceUpdated> {
+		self.call("engine_forkchoiceUpdatedV3", json!([state, attributes])).await
+	}
+
+	/// engine_getPayloadV4, the payload built so far with its value, blobs and execution requests
+	pub async fn get_payload_v4(&self, payload_id: PayloadId) -> Result<ExecutionPayloadEnvelopeV4> {
+		self.call("engine_getPayloadV4", json!([payload_id])).await
+	}
+
+	async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+		let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+		let response = self
+			.client
+			.post(self.url.clone())
+			.bearer_auth(self.token()?)
+			.json(&body)
+			.send()
+			.await
+			.wrap_err_with(|| format!("{} request failed", method))?
+			.error_for_status()
+			.wrap_err_with(|| format!("{} request failed", method))?
+			.json::<RpcResponse<T>>()
+			.await
+			.wrap_err_with(|| format!("Invalid {} response", method))?;
+
+		match (response.result, response.error) {
+			(_, Some(error)) => Err(eyre!("{} failed with code {}: {}", method, error.code, error.message)),
+			(Some(result), None) => Ok(result),
+			(None, None) => Err(eyre!("{} returned neither a result nor an error", method)),
+		}
+	}
+
+	/// A fresh token, execution clients reject tokens issued more than a minute ago
+	fn token(&self) -> Result<String> {
+		let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+		jsonwebtoken::encode(&Header::default(), &Claims { iat }, &self.jwt_secret).wrap_err("Failed to sign JWT")
+	}
+}
@@ -0,0 +1,46 @@
+//! Readiness of the gateway
+use std::sync::atomic::Ordering;
+
+use async_trait::async_trait;
+use common::health::{Readiness, ReadinessProbe};
+use constraints::client::ConstraintsClient;
+use eyre::eyre;
+
+use crate::gateway::state::GatewayState;
+
+/// Delegation checks that may be missed before the gateway's view of the lookahead counts as stale
+const MAX_MISSED_DELEGATION_CHECKS: u64 = 3;
+
+#[async_trait]
+impl ReadinessProbe for GatewayState {
+	async fn readiness(&self) -> Readiness {
+		let mut readiness = Readiness::default();
+		readiness.check("db", async { self.db.healthcheck().map_err(Into::into) }).await;
+		readiness.check("signer", async { self.signer_client.clone().consensus_public_keys().await.map(|_| ()) }).await;
+		readiness
+			.check("relay", async {
+				match self.constraints_client().health_check().await? {
+					true => Ok(()),
+					false => Err(eyre!("the relay reports it is unhealthy")),
+				}
+			})
+			.await;
+		readiness.add("lookahead", self.check_delegations_fresh());
+		readiness
+	}
+}
+
+impl GatewayState {
+	/// Ok if the delegations of the lookahead window were checked within the last few check intervals
+	fn check_delegations_fresh(&self) -> eyre::Result<()> {
+		let checked_ms = self.delegations_checked_ms.load(Ordering::Relaxed);
+		if checked_ms == 0 {
+			return Err(eyre!("delegations have not been checked yet"));
+		}
+		let age_ms = self.slot_clock.now_ms().saturating_sub(checked_ms);
+		if age_ms > MAX_MISSED_DELEGATION_CHECKS * self.delegation_check_interval_seconds * 1000 {
+			return Err(eyre!("delegations last checked {}s ago", age_ms / 1000));
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,221 @@
+//! Fee-prioritized admission of commitment requests to slots close to their gas budget
+//!
+//! Once a slot's committed gas passes the contention threshold, requests to it are not signed first come, first
+//! served. They are collected for a short window instead, and the highest paying ones that fit in the remaining
+//! budget are admitted. The others are answered with an `Outbid` error.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use alloy::primitives::U256;
+use commitments::error::CommitmentsRpcError;
+use common::storage::DatabaseContext;
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+
+use crate::gateway::config::IntakeConfig;
+use crate::storage::InclusionDbExt;
+
+/// A request waiting for its slot's batch to close
+struct PendingRequest {
+	/// Gas the request adds to the slot, net of the commitment it replaces
+	gas: u64,
+	fee_per_gas: U256,
+	admitted: oneshot::Sender<Result<(), CommitmentsRpcError>>,
+}
+
+/// Collects requests to contended slots and admits the highest paying ones
+pub struct CommitmentIntake {
+	config: IntakeConfig,
+	db: DatabaseContext,
+	pending: Mutex<HashMap<u64, Vec<PendingRequest>>>,
+}
+
+impl CommitmentIntake {
+	pub fn new(config: IntakeConfig, db: DatabaseContext) -> Self {
+		Self { config, db, pending: Mutex::new(HashMap::new()) }
+	}
+
+	/// Whether committing `gas` more would take the slot past the contention threshold of its budget
+	pub fn is_contended(&self, committed_gas: u64, gas: u64, gas_budget: u64) -> bool {
+		committed_gas.saturating_add(gas) as f64 > gas_budget as f64 * self.config.contention_fraction
+	}
+
+	/// Wait for the slot's batch to close, Ok if the request is among the highest paying ones that fit the budget
+	/// `paid_wei` is what the request pays the committer, requests compete on it per unit of gas. `replaced_gas` is
+	/// the gas of the commitment the request replaces in the slot, which it takes over rather than adds.
+	pub async fn admit(
+		self: &Arc<Self>,
+		slot: u64,
+		gas: u64,
+		replaced_gas: u64,
+		paid_wei: U256,
+		gas_budget: u64,
+	) -> Result<(), CommitmentsRpcError> {
+		let (admitted, receiver) = oneshot::channel();
+		let fee_per_gas = paid_wei / U256::from(gas.max(1));
+		let opens_batch = {
+			let mut pending = self.pending.lock().expect("intake lock poisoned");
+			let batch = pending.entry(slot).or_default();
+			batch.push(PendingRequest { gas: gas.saturating_sub(replaced_gas), fee_per_gas, admitted });
+			batch.len() == 1
+		};
+
+		// The batch closes in its own task, so a dropped request does not leave the others waiting
+		if opens_batch {
+			let intake = Arc::clone(self);
+			tokio::spawn(async move {
+				tokio::time::sleep(Duration::from_millis(intake.config.batch_window_ms)).await;
+				intake.close_batch(slot, gas_budget);
+			});
+		}
+
+		receiver.await.unwrap_or_else(|_| Err(CommitmentsRpcError::Internal("Commitment intake closed".to_string())))
+	}
+
+	/// Admit the highest paying requests of the slot's batch within the gas left in the budget
+	fn close_batch(&self, slot: u64, gas_budget: u64) {
+		let batch = self.pending.lock().expect("intake lock poisoned").remove(&slot).unwrap_or_default();
+		let committed_gas = match self.db.get_slot_committed_gas(slot) {
+			Ok(committed_gas) => committed_gas,
+			Err(e) => {
+				warn!("Failed to read the committed gas of slot {}: {}", slot, e);
+				for request in batch {
+					let error = format!("Failed to read the committed gas of slot {}: {}", slot, e);
+					let _ = request.admitted.send(Err(CommitmentsRpcError::Internal(error)));
+				}
+				return;
+			}
+		};
+
+		let requests = batch.iter().map(|request| (request.fee_per_gas, request.gas)).collect::<Vec<_>>();
+		let admitted = select_admitted(&requests, gas_budget.saturating_sub(committed_gas));
+		let lowest_admitted = requests.iter().zip(&admitted).filter(|(_, admitted)| **admitted).map(|(r, _)| r.0).min();
+		debug!(
+			"Closed intake batch of slot {}: {} of {} request(s) admitted",
+			slot,
+			admitted.iter().filter(|admitted| **admitted).count(),
+			batch.len()
+		);
+
+		for (request, admitted) in batch.into_iter().zip(admitted) {
+			let result = match (admitted, lowest_admitted) {
+				(true, _) => Ok(()),
+				(false, Some(lowest)) => Err(CommitmentsRpcError::Outbid(format!(
+					"Slot {} admitted requests paying at least {} wei per gas, offered {}",
+					slot, lowest, request.fee_per_gas
+				))),
+				(false, None) => Err(CommitmentsRpcError::OverCapacity(format!("Slot {} gas budget exhausted", slot))),
+			};
+			let _ = request.admitted.send(result);
+		}
+	}
+}
+
+/// Which requests, given as (fee per gas, gas), are admitted: the highest paying first, as long as they fit
+/// in the available gas. Equal fees are admitted in arrival order.
+pub fn select_admitted(requests: &[(U256, u64)], available_gas: u64) -> Vec<bool> {
+	let mut order = (0..requests.len()).collect::<Vec<_>>();
+	order.sort_by(|a, b| requests[*b].0.cmp(&requests[*a].0));
+
+	let mut admitted = vec![false; requests.len()];
+	let mut remaining = available_gas;
+	for index in order {
+		let gas = requests[index].1;
+		if gas <= remaining {
+			remaining -= gas;
+			admitted[index] = true;
+		}
+	}
+	admitted
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::primitives::{B256, Bytes};
+	use common::storage::db::TypedDbExt;
+	use constraints::types::Constraint;
+
+	use crate::constants::INCLUSION_CONSTRAINT_TYPE;
+	use crate::storage::constraint_key;
+
+	const SLOT: u64 = 100;
+
+	fn new_intake() -> (Arc<CommitmentIntake>, DatabaseContext) {
+		let tmp_dir = tempfile::TempDir::new().unwrap();
+		let mut opts = rocksdb::Options::default();
+		opts.create_if_missing(true);
+		let db = DatabaseContext::new(Arc::new(rocksdb::DB::open(&opts, tmp_dir.path()).unwrap()));
+		let config = IntakeConfig { contention_fraction: 0.8, batch_window_ms: 20 };
+		(Arc::new(CommitmentIntake::new(config, db.clone())), db)
+	}
+
+	#[test]
+	fn test_is_contended() {
+		let (intake, _) = new_intake();
+		assert!(!intake.is_contended(50_000, 30_000, 100_000));
+		assert!(intake.is_contended(50_000, 30_001, 100_000));
+	}
+
+	#[tokio::test]
+	async fn test_admit_highest_paying_requests() {
+		let (intake, _) = new_intake();
+		let paid = |fee_per_gas: u64| U256::from(fee_per_gas * 21_000);
+
+		let (low, high, mid) = tokio::join!(
+			intake.admit(SLOT, 21_000, 0, paid(1), 50_000),
+			intake.admit(SLOT, 21_000, 0, paid(3), 50_000),
+			intake.admit(SLOT, 21_000, 0, paid(2), 50_000),
+		);
+		assert_eq!(high, Ok(()));
+		assert_eq!(mid, Ok(()));
+		assert!(matches!(low, Err(CommitmentsRpcError::Outbid(_))));
+
+		// A batch with no room left is over capacity rather than outbid
+		let result = intake.admit(SLOT, 21_000, 0, paid(5), 20_000).await;
+		assert!(matches!(result, Err(CommitmentsRpcError::OverCapacity(_))));
+	}
+
+	#[tokio::test]
+	async fn test_replacement_only_claims_its_extra_gas() {
+		let (intake, _) = new_intake();
+		let paid = |fee_per_gas: u64| U256::from(fee_per_gas * 21_000);
+
+		// The replacement takes over 21000 gas already committed, so both fit in 21000
+		let (replacement, other) = tokio::join!(
+			intake.admit(SLOT, 21_000, 21_000, paid(1), 21_000),
+			intake.admit(SLOT, 21_000, 0, paid(2), 21_000),
+		);
+		assert_eq!(replacement, Ok(()));
+		assert_eq!(other, Ok(()));
+	}
+
+	#[tokio::test]
+	async fn test_batch_storage_error_is_replied() {
+		let (intake, db) = new_intake();
+		let corrupt = Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: Bytes::from(vec![0x01]) };
+		db.put_json(&constraint_key(SLOT, &B256::repeat_byte(0x01)), &corrupt).unwrap();
+
+		let (first, second) = tokio::join!(
+			intake.admit(SLOT, 21_000, 0, U256::from(21_000), 50_000),
+			intake.admit(SLOT, 21_000, 0, U256::from(42_000), 50_000),
+		);
+		assert!(matches!(first, Err(CommitmentsRpcError::Internal(e)) if e.contains("committed gas")));
+		assert!(matches!(second, Err(CommitmentsRpcError::Internal(_))));
+	}
+
+	#[test]
+	fn test_select_admitted_by_fee() {
+		let fee = U256::from;
+		let requests = [(fee(1), 50_000), (fee(5), 60_000), (fee(3), 30_000), (fee(5), 21_000), (fee(2), 10_000)];
+
+		// Both fee 5 requests, then fee 3 does not fit but the smaller fee 2 one does
+		assert_eq!(select_admitted(&requests, 91_000), vec![false, true, false, true, true]);
+		assert_eq!(select_admitted(&requests, 0), vec![false; 5]);
+		assert_eq!(select_admitted(&requests, u64::MAX), vec![true; 5]);
+
+		// Ties go to the earlier request
+		assert_eq!(select_admitted(&[(fee(4), 21_000), (fee(4), 21_000)], 21_000), vec![true, false]);
+	}
+}
@@ -0,0 +1,205 @@
+use alloy::primitives::U256;
+use commitments::error::{
+	CONFLICT_CODE, INVALID_PAYLOAD_CODE, INVALID_TRANSACTION_CODES, NO_DELEGATION_CODE, NOT_FOUND_CODE, OUTBID_CODE,
+	OVER_CAPACITY_CODE, PAYMENT_REQUIRED_CODE, SLOT_ELAPSED_CODE, STANDBY_CODE,
+};
+use lazy_static::lazy_static;
+use prometheus::{
+	CounterVec, Gauge, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, Registry, exponential_buckets,
+	register_counter_vec_with_registry, register_gauge_with_registry, register_histogram_vec_with_registry,
+	register_histogram_with_registry, register_int_counter_vec_with_registry, register_int_counter_with_registry,
+	register_int_gauge_with_registry,
+};
+
+use crate::constants::{
+	BLOB_COMMITMENT_TYPE, BUNDLE_COMMITMENT_TYPE, EXCLUSION_COMMITMENT_TYPE, INCLUSION_COMMITMENT_TYPE,
+	ORDERING_COMMITMENT_TYPE,
+};
+
+pub const GATEWAY_REGISTRY_NAME: &str = "gateway";
+
+lazy_static! {
+	pub static ref GATEWAY_METRICS_REGISTRY: Registry =
+		Registry::new_custom(Some(GATEWAY_REGISTRY_NAME.to_string()), None).unwrap();
+
+	// Commitment metrics
+	pub static ref GATEWAY_COMMITMENTS_ACCEPTED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"commitments_accepted_total",
+		"Total commitment requests signed by commitment type",
+		&["commitment_type"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_COMMITMENTS_REJECTED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"commitments_rejected_total",
+		"Total commitment requests rejected by reason",
+		&["reason"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_FEES_WEI_TOTAL: CounterVec = register_counter_vec_with_registry!(
+		"fees_wei_total",
+		"Total commitment fees in wei: quoted and paid when signed, collected once the commitment is honored",
+		&["kind"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_SIGNER_LATENCY_SECONDS: HistogramVec = register_histogram_vec_with_registry!(
+		"signer_latency_seconds",
+		"Latency of the signer in seconds by operation",
+		&["operation"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_LOOKAHEAD_DELEGATIONS: IntGauge = register_int_gauge_with_registry!(
+		"lookahead_delegations",
+		"Slots of the lookahead window delegated to this gateway at the last delegation check",
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	// Settlement metrics
+	pub static ref GATEWAY_COMMITMENTS_SETTLED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"commitments_settled_total",
+		"Total commitments checked against the canonical block of their slot by result",
+		&["result"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_SLOT_CONSTRAINTS: Histogram = register_histogram_with_registry!(
+		"slot_constraints",
+		"Constraints posted for each settled slot",
+		exponential_buckets(1.0, 2.0, 10).unwrap(),
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_SLOT_COMMITTED_GAS: Histogram = register_histogram_with_registry!(
+		"slot_committed_gas",
+		"Gas committed in each settled slot",
+		exponential_buckets(21_000.0, 2.0, 12).unwrap(),
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_SLOT_INCLUSION_RATE: Gauge = register_gauge_with_registry!(
+		"slot_inclusion_rate",
+		"Fraction of the commitments of the most recently settled slot honored by its canonical block",
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	// Constraint posting metrics
+	pub static ref GATEWAY_CONSTRAINT_POSTS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"constraint_posts_total",
+		"Total attempts to post signed constraints to the relay by result",
+		&["result"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_CONSTRAINT_SLOTS_DROPPED_TOTAL: IntCounter = register_int_counter_with_registry!(
+		"constraint_slots_dropped_total",
+		"Total delegated slots whose constraints could not be posted before the hard cutoff",
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref GATEWAY_CONSTRAINT_VERIFICATIONS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"constraint_verifications_total",
+		"Total checks of the constraints the relay serves back after a post by result",
+		&["result"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	// Fallback builder metrics
+	pub static ref GATEWAY_FALLBACK_BLOCKS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"fallback_blocks_total",
+		"Total fallback blocks built for delegated slots by result",
+		&["result"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	// Mempool ingestion metrics
+	pub static ref GATEWAY_MEMPOOL_COMMITMENTS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"mempool_commitments_total",
+		"Total commitment attempts for pending transactions matching the mempool filters by result",
+		&["result"],
+		GATEWAY_METRICS_REGISTRY
+	)
+	.unwrap();
+}
+
+/// Label values for GATEWAY_FEES_WEI_TOTAL
+pub const FEE_QUOTED: &str = "quoted";
+pub const FEE_PAID: &str = "paid";
+pub const FEE_COLLECTED: &str = "collected";
+
+/// Label values for GATEWAY_SIGNER_LATENCY_SECONDS
+pub const SIGN_COMMITMENT: &str = "commitment";
+pub const SIGN_CONSTRAINTS: &str = "constraints";
+pub const SIGN_RECEIVER_AUTH: &str = "receiver_auth";
+pub const SIGN_DELEGATION_OFFER: &str = "delegation_offer";
+
+/// Label values for GATEWAY_COMMITMENTS_SETTLED_TOTAL
+pub const COMMITMENT_HONORED: &str = "honored";
+pub const COMMITMENT_MISSED: &str = "missed";
+
+/// Label values for GATEWAY_CONSTRAINT_POSTS_TOTAL
+pub const CONSTRAINT_POST_ACCEPTED: &str = "accepted";
+pub const CONSTRAINT_POST_FAILED: &str = "failed";
+
+/// Label values for GATEWAY_CONSTRAINT_VERIFICATIONS_TOTAL
+pub const CONSTRAINT_VERIFICATION_MATCHED: &str = "matched";
+pub const CONSTRAINT_VERIFICATION_MISMATCH: &str = "mismatch";
+pub const CONSTRAINT_VERIFICATION_FAILED: &str = "failed";
+
+/// Label values for GATEWAY_FALLBACK_BLOCKS_TOTAL
+pub const FALLBACK_BLOCK_SUBMITTED: &str = "submitted";
+pub const FALLBACK_BLOCK_UNSATISFIED: &str = "unsatisfied";
+pub const FALLBACK_BLOCK_FAILED: &str = "failed";
+
+/// Label values for GATEWAY_MEMPOOL_COMMITMENTS_TOTAL
+pub const MEMPOOL_COMMITMENT_ACCEPTED: &str = "accepted";
+pub const MEMPOOL_COMMITMENT_REJECTED: &str = "rejected";
+
+/// Add a fee to GATEWAY_FEES_WEI_TOTAL
+pub fn record_fee(kind: &str, wei: U256) {
+	GATEWAY_FEES_WEI_TOTAL.with_label_values(&[kind]).inc_by(wei.saturating_to::<u128>() as f64);
+}
+
+/// Label of a commitment type for GATEWAY_COMMITMENTS_ACCEPTED_TOTAL
+pub fn commitment_type_label(commitment_type: u64) -> &'static str {
+	match commitment_type {
+		EXCLUSION_COMMITMENT_TYPE => "exclusion",
+		ORDERING_COMMITMENT_TYPE => "ordering",
+		BUNDLE_COMMITMENT_TYPE => "bundle",
+		BLOB_COMMITMENT_TYPE => "blob",
+		INCLUSION_COMMITMENT_TYPE => "inclusion",
+		_ => "unknown",
+	}
+}
+
+/// Label of a JSON-RPC error code for GATEWAY_COMMITMENTS_REJECTED_TOTAL
+pub fn rejection_reason(code: i32) -> &'static str {
+	match code {
+		INVALID_PAYLOAD_CODE => "invalid_payload",
+		NO_DELEGATION_CODE => "no_delegation",
+		SLOT_ELAPSED_CODE => "slot_elapsed",
+		OVER_CAPACITY_CODE => "over_capacity",
+		PAYMENT_REQUIRED_CODE => "payment_required",
+		CONFLICT_CODE => "conflict",
+		NOT_FOUND_CODE => "not_found",
+		STANDBY_CODE => "standby",
+		OUTBID_CODE => "outbid",
+		code if INVALID_TRANSACTION_CODES.contains(&code) => "invalid_transaction",
+		_ => "internal",
+	}
+}
@@ -1,4 +1,9 @@
 pub mod config;
+pub mod engine;
+pub mod health;
+pub mod intake;
+pub mod metrics;
+pub mod pricing;
 pub mod services;
 pub mod state;
 pub mod utils;
@@ -0,0 +1,458 @@
+//! Fees the gateway charges for commitments.
+//!
+//! A quote is the committer's share only: the transaction's priority fee over its estimated gas, plus any value it
+//! sends to the committer. The base fee is burnt and not part of the quote, `FeePayload::max_base_fee_per_gas` tells
+//! the caller what max fee the transaction needs on top of it. Quotes used to price the whole transaction at the gas
+//! price, which counted the burnt base fee as paid to the gateway.
+//!
+//! The market conditions are read from the execution client once per slot, see `MarketCache`, so pricing a request
+//! only costs the `eth_estimateGas` of its transaction.
+
+use alloy::consensus::{Transaction, TxEnvelope};
+use alloy::eips::BlockNumberOrTag;
+use alloy::network::Ethereum;
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::providers::{DynProvider, Provider};
+use commitments::types::{CommitmentRequest, FeeInfo};
+use common::storage::DatabaseContext;
+use eyre::{Result, WrapErr, bail, eyre};
+use lookahead::slot_clock::SlotClock;
+use tokio::sync::Mutex;
+use tracing::debug;
+use urc::utils::get_commitment_request_signing_root;
+
+use crate::constants::{EXCLUSION_COMMITMENT_TYPE, INCLUSION_COMMITMENT_TYPE, ORDERING_COMMITMENT_TYPE};
+use crate::gateway::state::GatewayState;
+use crate::gateway::utils::tx_envelope_to_rpc_request;
+use crate::storage::InclusionDbExt;
+use crate::types::{ExclusionRequestPayload, FeePayload, InclusionPayload, OrderingRequestPayload};
+
+/// Blocks of fee history sampled for the market tip
+const FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Priority fee percentile sampled from each block of the fee history
+const FEE_HISTORY_REWARD_PERCENTILE: f64 = 50.0;
+
+/// Slots over which the base fee is projected to rise, beyond this the projection is not meaningful
+const MAX_BASE_FEE_PROJECTION_SLOTS: u64 = 8;
+
+/// How long a fee quote returned by fee() stays valid, in seconds
+pub const FEE_QUOTE_VALIDITY_SECS: u64 = 12;
+
+/// Fee market conditions reported by the execution client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketFees {
+	/// Base fee of the pending block
+	pub pending_base_fee_per_gas: u64,
+	/// Larger of the suggested priority fee and the median priority fee of recent blocks
+	pub market_tip_per_gas: u128,
+}
+
+/// Execution layer conditions commitments are validated, capped and priced with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketSnapshot {
+	/// Base fee of the latest block, the transaction's fee cap must cover it
+	pub base_fee_per_gas: u64,
+	/// Gas limit of the latest block
+	pub gas_limit: u64,
+	/// Fees of the pending block
+	pub fees: MarketFees,
+}
+
+impl MarketSnapshot {
+	/// Gas the gateway is willing to commit to per slot, a fraction of the latest block gas limit
+	pub fn gas_budget(&self, fraction: f64) -> u64 {
+		(self.gas_limit as f64 * fraction) as u64
+	}
+}
+
+/// The market snapshot of the current slot, shared by every request of the slot
+/// The chain only moves on once per slot, so the snapshot is fetched on the first request of a slot instead of on
+/// every request
+#[derive(Debug, Default)]
+pub struct MarketCache {
+	snapshot: Mutex<Option<(u64, MarketSnapshot)>>,
+}
+
+impl MarketCache {
+	/// The snapshot of `slot`, fetched from the execution client if the cached one is of an earlier slot
+	pub async fn get(&self, execution_client: &DynProvider<Ethereum>, slot: u64) -> Result<MarketSnapshot> {
+		let mut snapshot = self.snapshot.lock().await;
+		if let Some((cached_slot, cached)) = *snapshot
+			&& cached_slot == slot
+		{
+			return Ok(cached);
+		}
+		let fetched = fetch_market_snapshot(execution_client).await?;
+		*snapshot = Some((slot, fetched));
+		Ok(fetched)
+	}
+}
+
+/// Demand on the target slot of a commitment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotDemand {
+	/// Number of slots between the current slot and the target slot
+	pub slots_ahead: u64,
+	/// Gas already committed for the target slot
+	pub committed_gas: u64,
+	/// Gas the gateway is willing to commit for the target slot
+	pub gas_budget: u64,
+}
+
+/// Price the gateway charges to commit to a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeQuote {
+	/// Gas the transaction is expected to use
+	pub estimated_gas: u64,
+	/// Base fee of the pending block, used to compute the transaction's effective tip
+	pub base_fee_per_gas: u64,
+	/// Base fee the transaction should be able to pay by the target slot
+	pub max_base_fee_per_gas: u64,
+	/// Tip per gas the gateway charges, the minimum priority fee of the transaction
+	pub tip_per_gas: u128,
+	/// Total quoted fee in wei
+	pub quoted_wei: U256,
+}
+
+/// Fetch the pending base fee and market tip from the execution client
+pub async fn fetch_market_fees(execution_client: &DynProvider<Ethereum>) -> Result<MarketFees> {
+	let suggested_tip = execution_client
+		.get_max_priority_fee_per_gas()
+		.await
+		.wrap_err("Failed to get max priority fee from execution client node")?;
+
+	let fee_history = execution_client
+		.get_fee_history(FEE_HISTORY_BLOCKS, BlockNumberOrTag::Latest, &[FEE_HISTORY_REWARD_PERCENTILE])
+		.await
+		.wrap_err("Failed to get fee history from execution client node")?;
+
+	// The last base fee of the history is the base fee of the pending block
+	let pending_base_fee_per_gas: u64 = fee_history
+		.base_fee_per_gas
+		.last()
+		.copied()
+		.ok_or_else(|| eyre!("Execution client returned an empty fee history"))?
+		.try_into()
+		.wrap_err("Pending base fee does not fit in u64")?;
+
+	let rewards = fee_history.reward.unwrap_or_default().iter().filter_map(|block| block.first().copied()).collect();
+	let market_tip_per_gas = suggested_tip.max(median(rewards));
+
+	Ok(MarketFees { pending_base_fee_per_gas, market_tip_per_gas })
+}
+
+/// Fetch the latest block and the market fees from the execution client
+pub async fn fetch_market_snapshot(execution_client: &DynProvider<Ethereum>) -> Result<MarketSnapshot> {
+	let latest_block = execution_client
+		.get_block_by_number(BlockNumberOrTag::Latest)
+		.await
+		.wrap_err("Failed to get latest block from execution client node")?
+		.ok_or_else(|| eyre!("Execution client returned no latest block"))?;
+	let fees = fetch_market_fees(execution_client).await?;
+	Ok(MarketSnapshot {
+		base_fee_per_gas: latest_block.header.base_fee_per_gas.unwrap_or_default(),
+		gas_limit: latest_block.header.gas_limit,
+		fees,
+	})
+}
+
+/// Current demand on a slot, used to price and cap commitments
+pub fn slot_demand(db: &DatabaseContext, slot_clock: &SlotClock, slot: u64, gas_budget: u64) -> Result<SlotDemand> {
+	Ok(SlotDemand {
+		slots_ahead: slot.saturating_sub(slot_clock.current_slot()),
+		committed_gas: db.get_slot_committed_gas(slot)?,
+		gas_budget,
+	})
+}
+
+/// Price a commitment from the market fees and the demand on its target slot
+///
+/// The tip is the larger of the configured minimum and the market tip, scaled up to twice that as the slot's gas
+/// budget fills up. The base fee is projected to rise by the EIP-1559 maximum of 12.5% per slot until the target
+/// slot, so the caller knows what max fee the transaction needs.
+pub fn price_commitment(
+	estimated_gas: u64,
+	market: &MarketFees,
+	demand: &SlotDemand,
+	min_tip_per_gas_wei: u64,
+) -> FeeQuote {
+	let base_tip = market.market_tip_per_gas.max(min_tip_per_gas_wei as u128);
+	let scarcity_premium = match demand.gas_budget {
+		0 => base_tip,
+		budget => base_tip * demand.committed_gas.min(budget) as u128 / budget as u128,
+	};
+	let tip_per_gas = base_tip + scarcity_premium;
+
+	let mut max_base_fee_per_gas = market.pending_base_fee_per_gas;
+	for _ in 0..demand.slots_ahead.min(MAX_BASE_FEE_PROJECTION_SLOTS) {
+		max_base_fee_per_gas = max_base_fee_per_gas.saturating_add(max_base_fee_per_gas.div_ceil(8));
+	}
+
+	FeeQuote {
+		estimated_gas,
+		base_fee_per_gas: market.pending_base_fee_per_gas,
+		max_base_fee_per_gas,
+		tip_per_gas,
+		quoted_wei: U256::from(estimated_gas) * U256::from(tip_per_gas),
+	}
+}
+
+/// Quote the fee for committing to a transaction at the given market conditions, only estimating its gas
+pub async fn quote_fee(
+	tx_envelope: &TxEnvelope,
+	execution_client: &DynProvider<Ethereum>,
+	market: &MarketFees,
+	demand: &SlotDemand,
+	min_tip_per_gas_wei: u64,
+) -> Result<FeeQuote> {
+	let tx_request = tx_envelope_to_rpc_request(tx_envelope)?;
+	let estimated_gas =
+		execution_client.estimate_gas(tx_request).await.wrap_err("Failed to estimate gas for transaction")?;
+
+	let quote = price_commitment(estimated_gas, market, demand, min_tip_per_gas_wei);
+
+	debug!(
+		"Quoted fee: estimated_gas={}, tip_per_gas={} wei, base_fee={} wei, max_base_fee={} wei, quoted={} wei",
+		estimated_gas, quote.tip_per_gas, quote.base_fee_per_gas, quote.max_base_fee_per_gas, quote.quoted_wei
+	);
+
+	Ok(quote)
+}
+
+/// Calculates fee information for a commitment request
+///
+/// The fee is the committer's share of what the transaction pays, see the module documentation
+///
+/// This function:
+/// 1. Decodes the InclusionPayload from the request
+/// 2. Decodes the signed transaction from the payload
+/// 3. Measures the demand on the target slot: distance from the current slot and committed gas against the budget
+/// 4. Quotes the fee from the slot's market snapshot, the same quote commitment_request enforces
+/// 5. Sets the quote to expire after its validity period or at the slot cutoff, whichever comes first
+///
+/// # Parameters
+///
+/// * `request` - The commitment request containing the InclusionPayload
+/// * `state` - The gateway state, for the execution client, storage and pricing configuration
+///
+/// # Returns
+///
+/// `FeeInfo` containing the calculated fee and commitment type
+pub async fn calculate_fee_info(request: &CommitmentRequest, state: &GatewayState) -> Result<FeeInfo> {
+	debug!("Calculating fee for commitment type: {}", request.commitment_type);
+
+	// 1. Decode the InclusionPayload from the request, exclusions and orderings are paid by their payment transaction
+	let inclusion_payload = match request.commitment_type {
+		INCLUSION_COMMITMENT_TYPE => {
+			InclusionPayload::abi_decode(&request.payload).wrap_err("Failed to decode InclusionPayload from request")?
+		}
+		EXCLUSION_COMMITMENT_TYPE => ExclusionRequestPayload::abi_decode(&request.payload)
+			.wrap_err("Failed to decode ExclusionRequestPayload from request")?
+			.payment(),
+		ORDERING_COMMITMENT_TYPE => OrderingRequestPayload::abi_decode(&request.payload)
+			.wrap_err("Failed to decode OrderingRequestPayload from request")?
+			.payment(),
+		_ => return Err(eyre!("Fee quotes are only offered for inclusion, exclusion and ordering commitments")),
+	};
+
+	// 2. Decode the signed transaction
+	let tx_envelope = inclusion_payload.decode_transaction()?;
+
+	// 3. Measure the demand on the target slot
+	let market = state.market_cache.get(&state.execution_client, state.slot_clock.current_slot()).await?;
+	let gas_budget = market.gas_budget(state.settings().slot_gas_budget_fraction);
+	let demand = slot_demand(&state.db, &state.slot_clock, inclusion_payload.slot, gas_budget)?;
+
+	// 4. Quote the fee
+	let quote =
+		quote_fee(&tx_envelope, &state.execution_client, &market.fees, &demand, state.settings().min_tip_per_gas_wei)
+			.await?;
+
+	// Convert from wei to gwei by dividing by 1 billion (1e9)
+	let price_gwei = (quote.quoted_wei / U256::from(1_000_000_000)).to();
+
+	// 5. Expire the quote
+	let now_secs = state.slot_clock.now_ms() / 1000;
+	let time_until_cutoff_ms = state.slot_clock.slot_deadline(inclusion_payload.slot) - state.commitment_cutoff_ms;
+	let expires_at = quote_expiry(now_secs, time_until_cutoff_ms);
+
+	let request_hash = get_commitment_request_signing_root(&request);
+	let fee_payload = FeePayload {
+		request_hash,
+		price_gwei,
+		min_priority_fee_per_gas: quote.tip_per_gas,
+		max_base_fee_per_gas: quote.max_base_fee_per_gas,
+		expires_at,
+	};
+
+	debug!(
+		"Calculated fee: quoted={} wei, price_gwei={} gwei, min_priority_fee={} wei, expires_at={}",
+		quote.quoted_wei, price_gwei, quote.tip_per_gas, expires_at
+	);
+
+	Ok(FeeInfo {
+		fee_payload: Bytes::from(serde_json::to_vec(&fee_payload)?),
+		commitment_type: request.commitment_type,
+	})
+}
+
+/// Unix timestamp at which a quote expires: after the validity period, or at the slot cutoff if that comes first
+pub fn quote_expiry(now_secs: u64, time_until_cutoff_ms: i64) -> u64 {
+	let until_cutoff_secs = (time_until_cutoff_ms.max(0) / 1000) as u64;
+	now_secs + until_cutoff_secs.min(FEE_QUOTE_VALIDITY_SECS)
+}
+
+/// Median of the sampled priority fees, zero when there are none
+fn median(mut values: Vec<u128>) -> u128 {
+	if values.is_empty() {
+		return 0;
+	}
+	values.sort_unstable();
+	values[values.len() / 2]
+}
+
+/// Amount a transaction pays the committer against a quote
+///
+/// Counts the transaction's effective priority fee over the estimated gas, plus any value the transaction transfers
+/// directly to the committer address.
+pub fn paid_fee(tx_envelope: &TxEnvelope, quote: &FeeQuote, committer: Address) -> U256 {
+	let tip_per_gas = tx_envelope.effective_tip_per_gas(quote.base_fee_per_gas).unwrap_or_default();
+	let priority_fee = U256::from(quote.estimated_gas) * U256::from(tip_per_gas);
+
+	let direct_payment = if tx_envelope.to() == Some(committer) { tx_envelope.value() } else { U256::ZERO };
+
+	priority_fee + direct_payment
+}
+
+/// Reject a commitment that pays less than it was quoted, the sum of its transactions' quotes
+pub fn enforce_payment(quoted_wei: U256, paid_wei: U256) -> Result<()> {
+	if paid_wei < quoted_wei {
+		bail!("Underpriced commitment: pays {} wei, quoted {} wei", paid_wei, quoted_wei);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::consensus::{SignableTransaction, Signed, TxEip1559};
+	use alloy::primitives::{Bytes, TxKind};
+	use alloy::signers::{SignerSync, local::PrivateKeySigner};
+
+	fn signed_tx(to: Address, value: U256, max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> TxEnvelope {
+		let tx = TxEip1559 {
+			chain_id: 1,
+			nonce: 0,
+			gas_limit: 21000,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			to: TxKind::Call(to),
+			value,
+			input: Bytes::new(),
+			access_list: Default::default(),
+		};
+		let signature = PrivateKeySigner::random().sign_hash_sync(&tx.signature_hash()).unwrap();
+		TxEnvelope::Eip1559(Signed::new_unhashed(tx, signature))
+	}
+
+	fn quote(tip_per_gas: u128) -> FeeQuote {
+		FeeQuote {
+			estimated_gas: 21000,
+			base_fee_per_gas: 10_000_000_000,
+			max_base_fee_per_gas: 10_000_000_000,
+			tip_per_gas,
+			quoted_wei: U256::from(21000u64) * U256::from(tip_per_gas),
+		}
+	}
+
+	#[test]
+	fn test_paid_fee_counts_effective_tip() {
+		let committer = Address::repeat_byte(0xCC);
+		let quote = quote(1_000_000_000);
+
+		// Tip is capped by max_fee_per_gas - base_fee
+		let tx = signed_tx(Address::repeat_byte(0x01), U256::from(1u64), 11_000_000_000, 2_000_000_000);
+		assert_eq!(paid_fee(&tx, &quote, committer), U256::from(21000u64 * 1_000_000_000));
+		assert!(enforce_payment(quote.quoted_wei, paid_fee(&tx, &quote, committer)).is_ok());
+
+		// A transaction that cannot cover the base fee pays no tip
+		let tx = signed_tx(Address::repeat_byte(0x01), U256::ZERO, 5_000_000_000, 2_000_000_000);
+		assert_eq!(paid_fee(&tx, &quote, committer), U256::ZERO);
+	}
+
+	#[test]
+	fn test_paid_fee_counts_direct_payment_to_committer() {
+		let committer = Address::repeat_byte(0xCC);
+		let quote = quote(2_000_000_000);
+
+		// No tip, but the transaction pays the committer directly
+		let tx = signed_tx(committer, quote.quoted_wei, 10_000_000_000, 0);
+		assert_eq!(paid_fee(&tx, &quote, committer), quote.quoted_wei);
+		assert!(enforce_payment(quote.quoted_wei, paid_fee(&tx, &quote, committer)).is_ok());
+
+		// The same value sent elsewhere does not count
+		let tx = signed_tx(Address::repeat_byte(0x01), quote.quoted_wei, 10_000_000_000, 0);
+		let paid = paid_fee(&tx, &quote, committer);
+		assert_eq!(paid, U256::ZERO);
+		assert!(enforce_payment(quote.quoted_wei, paid).is_err());
+	}
+
+	#[test]
+	fn test_price_commitment_scales_with_demand() {
+		let market = MarketFees { pending_base_fee_per_gas: 8_000_000_000, market_tip_per_gas: 1_000_000_000 };
+		let idle = SlotDemand { slots_ahead: 0, committed_gas: 0, gas_budget: 15_000_000 };
+
+		let quote = price_commitment(21000, &market, &idle, 0);
+		assert_eq!(quote.tip_per_gas, 1_000_000_000);
+		assert_eq!(quote.quoted_wei, U256::from(21000u64 * 1_000_000_000));
+		assert_eq!(quote.max_base_fee_per_gas, 8_000_000_000);
+
+		// The configured minimum tip takes over from a cheaper market
+		assert_eq!(price_commitment(21000, &market, &idle, 2_000_000_000).tip_per_gas, 2_000_000_000);
+
+		// Half the budget committed costs 1.5x, a full budget 2x
+		let half = SlotDemand { committed_gas: 7_500_000, ..idle };
+		assert_eq!(price_commitment(21000, &market, &half, 0).tip_per_gas, 1_500_000_000);
+		let full = SlotDemand { committed_gas: 15_000_000, ..idle };
+		assert_eq!(price_commitment(21000, &market, &full, 0).tip_per_gas, 2_000_000_000);
+
+		// The base fee may rise 12.5% per slot until the target slot, up to the projection limit
+		let ahead = SlotDemand { slots_ahead: 2, ..idle };
+		assert_eq!(price_commitment(21000, &market, &ahead, 0).max_base_fee_per_gas, 10_125_000_000);
+		let far = SlotDemand { slots_ahead: 32, ..idle };
+		let capped = SlotDemand { slots_ahead: MAX_BASE_FEE_PROJECTION_SLOTS, ..idle };
+		assert_eq!(
+			price_commitment(21000, &market, &far, 0).max_base_fee_per_gas,
+			price_commitment(21000, &market, &capped, 0).max_base_fee_per_gas
+		);
+	}
+
+	#[tokio::test]
+	async fn test_market_cache_fetches_once_per_slot() {
+		// Nothing listens on the execution client, so only a cached snapshot can be served
+		let execution_client = alloy::providers::ProviderBuilder::new()
+			.network::<Ethereum>()
+			.connect_http("http://127.0.0.1:1".parse().unwrap())
+			.erased();
+		let snapshot = MarketSnapshot {
+			base_fee_per_gas: 8_000_000_000,
+			gas_limit: 30_000_000,
+			fees: MarketFees { pending_base_fee_per_gas: 8_000_000_000, market_tip_per_gas: 1_000_000_000 },
+		};
+		let cache = MarketCache { snapshot: Mutex::new(Some((10, snapshot))) };
+
+		assert_eq!(cache.get(&execution_client, 10).await.unwrap(), snapshot);
+		assert!(cache.get(&execution_client, 11).await.is_err());
+		assert_eq!(snapshot.gas_budget(0.5), 15_000_000);
+	}
+
+	#[test]
+	fn test_quote_expiry() {
+		assert_eq!(quote_expiry(1_000, 60_000), 1_000 + FEE_QUOTE_VALIDITY_SECS);
+		assert_eq!(quote_expiry(1_000, 5_500), 1_005);
+		assert_eq!(quote_expiry(1_000, -1_000), 1_000);
+		assert_eq!(median(vec![3, 1, 2]), 2);
+		assert_eq!(median(vec![]), 0);
+	}
+}
@@ -0,0 +1,479 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, atomic::Ordering};
+
+use alloy::primitives::{Address, B256};
+use alloy::rpc::types::beacon::BlsPublicKey;
+use axum::{
+	Json, Router,
+	extract::{Path, Request, State},
+	http::{HeaderMap, StatusCode},
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+	routing::{delete, get, post},
+};
+use commitments::types::CommitmentStatus;
+use common::utils::{constant_time_eq, decode_pubkey};
+use constraints::client::HttpConstraintsClient;
+use constraints::types::SignedReceiverUpdate;
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use signing::signer::verify_bls;
+use tracing::{info, warn};
+use urc::utils::get_receiver_update_signing_root;
+
+use crate::constants::LOOKAHEAD_WINDOW_SIZE;
+use crate::gateway::services::constraint_manager::ConstraintManager;
+use crate::gateway::state::GatewayState;
+use crate::storage::InclusionDbExt;
+use crate::types::{AdminOverrides, RelayEndpoint};
+use proposer::storage::DelegationsDbExt;
+
+/// Admin API routes
+pub const ADMIN_DELEGATIONS: &str = "/admin/delegations";
+pub const ADMIN_SLOT_COMMITMENTS: &str = "/admin/slots/{slot}/commitments";
+pub const ADMIN_SLOT_CONSTRAINTS: &str = "/admin/slots/{slot}/constraints";
+pub const ADMIN_PAUSE: &str = "/admin/pause";
+pub const ADMIN_RESUME: &str = "/admin/resume";
+pub const ADMIN_RELAY: &str = "/admin/relay";
+pub const ADMIN_RECEIVERS: &str = "/admin/receivers";
+pub const ADMIN_RECEIVER: &str = "/admin/receivers/{public_key}";
+pub const ADMIN_SLOT_RECEIVERS: &str = "/admin/slots/{slot}/receivers";
+
+/// Route for signed receiver updates, served with the admin API but authenticated by the builder's signature
+pub const RECEIVER_UPDATE: &str = "/receivers/update";
+
+/// Shared state for the admin API handlers
+#[derive(Clone)]
+struct AdminState {
+	state: Arc<GatewayState>,
+	constraint_manager: Arc<ConstraintManager>,
+	api_key: String,
+	/// Serializes the read-modify-write of the added receivers
+	receivers_lock: Arc<Mutex<()>>,
+	/// Serializes the read-modify-write of the pause and relay overrides
+	overrides_lock: Arc<Mutex<()>>,
+}
+
+/// An upcoming slot delegated to the gateway
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DelegatedSlot {
+	pub slot: u64,
+	pub proposer: BlsPublicKey,
+	pub committer: Address,
+	pub constraints_posted: bool,
+}
+
+/// A commitment held for a slot
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlotCommitment {
+	pub request_hash: B256,
+	pub commitment_type: u64,
+	pub status: Option<CommitmentStatus>,
+}
+
+/// Request body for PUT /admin/relay
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelayRotationRequest {
+	pub host: String,
+	pub port: u16,
+	#[serde(default)]
+	pub api_key: Option<String>,
+}
+
+/// Response for GET /admin/relay
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelayResponse {
+	pub base_url: String,
+	pub accepting_commitments: bool,
+}
+
+/// Response for GET /admin/receivers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiversResponse {
+	/// Receivers of the config file, changed by editing it
+	pub configured: Vec<BlsPublicKey>,
+	/// Receivers added through the admin API or by signed receiver updates
+	pub added: Vec<BlsPublicKey>,
+}
+
+/// Request body for POST /admin/receivers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddReceiverRequest {
+	pub public_key: BlsPublicKey,
+}
+
+/// Request body for PUT /admin/slots/{slot}/receivers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlotReceiversRequest {
+	pub receivers: Vec<BlsPublicKey>,
+}
+
+/// Response for GET /admin/slots/{slot}/receivers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlotReceiversResponse {
+	pub slot: u64,
+	pub receivers: Vec<BlsPublicKey>,
+	/// Whether the receivers are an override of the slot
+	pub overridden: bool,
+}
+
+/// Build the authenticated admin router for the gateway
+///
+/// Every request must carry `Authorization: Bearer <api_key>`, except signed receiver updates.
+pub fn build_admin_router(
+	state: Arc<GatewayState>,
+	constraint_manager: Arc<ConstraintManager>,
+	api_key: String,
+) -> Router {
+	let admin_state = AdminState {
+		state,
+		constraint_manager,
+		api_key,
+		receivers_lock: Arc::new(Mutex::new(())),
+		overrides_lock: Arc::new(Mutex::new(())),
+	};
+
+	Router::new()
+		.route(ADMIN_DELEGATIONS, get(list_delegations))
+		.route(ADMIN_SLOT_COMMITMENTS, get(list_slot_commitments))
+		.route(ADMIN_SLOT_CONSTRAINTS, post(force_post_constraints))
+		.route(ADMIN_PAUSE, post(pause))
+		.route(ADMIN_RESUME, post(resume))
+		.route(ADMIN_RELAY, get(get_relay).put(rotate_relay))
+		.route(ADMIN_RECEIVERS, get(list_receivers).post(add_receiver))
+		.route(ADMIN_RECEIVER, delete(remove_receiver))
+		.route(ADMIN_SLOT_RECEIVERS, get(get_slot_receivers).put(override_slot_receivers).delete(clear_slot_receivers))
+		.layer(middleware::from_fn_with_state(admin_state.clone(), require_api_key))
+		.route(RECEIVER_UPDATE, post(update_receiver))
+		.with_state(admin_state)
+}
+
+/// Reject requests without the admin bearer token
+async fn require_api_key(State(admin): State<AdminState>, headers: HeaderMap, req: Request, next: Next) -> Response {
+	let authorized = headers
+		.get("Authorization")
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "))
+		.is_some_and(|token| constant_time_eq(token.as_bytes(), admin.api_key.as_bytes()));
+
+	if !authorized {
+		warn!("Rejected unauthorized admin request to {}", req.uri().path());
+		return StatusCode::UNAUTHORIZED.into_response();
+	}
+
+	next.run(req).await
+}
+
+// GET /admin/delegations
+async fn list_delegations(State(admin): State<AdminState>) -> impl IntoResponse {
+	let current_slot = admin.state.slot_clock.current_slot();
+	let delegated = || -> Result<Vec<DelegatedSlot>> {
+		admin
+			.state
+			.db
+			.get_delegations_in_range(current_slot, current_slot + LOOKAHEAD_WINDOW_SIZE)?
+			.into_iter()
+			.map(|(slot, delegation)| {
+				Ok(DelegatedSlot {
+					slot,
+					proposer: delegation.message.proposer,
+					committer: delegation.message.committer,
+					constraints_posted: admin.state.db.signed_constraints_finalized(slot)?,
+				})
+			})
+			.collect()
+	};
+
+	match delegated() {
+		Ok(delegated) => (StatusCode::OK, Json(delegated)).into_response(),
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to list delegated slots: {e}")).into_response(),
+	}
+}
+
+// GET /admin/slots/{slot}/commitments
+async fn list_slot_commitments(State(admin): State<AdminState>, Path(slot): Path<u64>) -> impl IntoResponse {
+	let commitments = || -> Result<Vec<SlotCommitment>> {
+		let mut seen = HashSet::new();
+		let mut commitments = Vec::new();
+		for (_, request_hash, _) in admin.state.db.get_constraints_in_range(slot, slot)? {
+			// Bundle and blob transactions are stored under member hashes of the same commitment
+			let Some(stored) = admin.state.db.get_signed_commitment(&request_hash)? else {
+				continue;
+			};
+			let commitment = stored.commitment.commitment;
+			if !seen.insert(commitment.request_hash) {
+				continue;
+			}
+			commitments.push(SlotCommitment {
+				request_hash: commitment.request_hash,
+				commitment_type: commitment.commitment_type,
+				status: admin.state.db.get_commitment_status(&commitment.request_hash)?,
+			});
+		}
+		Ok(commitments)
+	};
+
+	match commitments() {
+		Ok(commitments) => (StatusCode::OK, Json(commitments)).into_response(),
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to list commitments of slot {slot}: {e}"))
+			.into_response(),
+	}
+}
+
+// POST /admin/slots/{slot}/constraints
+async fn force_post_constraints(State(admin): State<AdminState>, Path(slot): Path<u64>) -> impl IntoResponse {
+	match admin.constraint_manager.force_post(slot).await {
+		Ok(()) => {
+			info!("Constraints of slot {} posted via admin API", slot);
+			StatusCode::OK.into_response()
+		}
+		Err(e) => (StatusCode::BAD_REQUEST, format!("failed to post constraints of slot {slot}: {e}")).into_response(),
+	}
+}
+
+// POST /admin/pause
+async fn pause(State(admin): State<AdminState>) -> impl IntoResponse {
+	if let Err(e) = update_overrides(&admin, |overrides| overrides.paused = true) {
+		return (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to pause: {e}")).into_response();
+	}
+	admin.state.accepting_commitments.store(false, Ordering::SeqCst);
+	info!("Gateway paused via admin API, no longer accepting commitment requests");
+	StatusCode::OK.into_response()
+}
+
+// POST /admin/resume
+async fn resume(State(admin): State<AdminState>) -> impl IntoResponse {
+	if let Err(e) = update_overrides(&admin, |overrides| overrides.paused = false) {
+		return (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to resume: {e}")).into_response();
+	}
+	admin.state.accepting_commitments.store(true, Ordering::SeqCst);
+	info!("Gateway resumed via admin API, accepting commitment requests");
+	StatusCode::OK.into_response()
+}
+
+// GET /admin/relay
+async fn get_relay(State(admin): State<AdminState>) -> impl IntoResponse {
+	Json(RelayResponse {
+		base_url: admin.state.constraints_client().base_url.to_string(),
+		accepting_commitments: admin.state.is_accepting_commitments(),
+	})
+}
+
+// PUT /admin/relay
+async fn rotate_relay(State(admin): State<AdminState>, Json(body): Json<RelayRotationRequest>) -> impl IntoResponse {
+	if reqwest::Url::parse(&format!("http://{}:{}", body.host, body.port)).is_err() {
+		return (StatusCode::BAD_REQUEST, format!("invalid relay endpoint {}:{}", body.host, body.port))
+			.into_response();
+	}
+
+	// The client is built before the rotation is persisted, so a relay it cannot be built for is not restored on restart
+	let relay = RelayEndpoint { host: body.host, port: body.port, api_key: body.api_key };
+	let client = match HttpConstraintsClient::with_http_config(
+		relay.host.clone(),
+		relay.port,
+		relay.api_key.clone(),
+		&admin.state.relay_http,
+	) {
+		Ok(client) => client,
+		Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to rotate relay: {e}")).into_response(),
+	};
+	if let Err(e) = update_overrides(&admin, |overrides| overrides.relay = Some(relay.clone())) {
+		return (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to rotate relay: {e}")).into_response();
+	}
+
+	info!("Relay rotated to {} via admin API", client.base_url);
+	admin.state.rotate_relay(client);
+	StatusCode::OK.into_response()
+}
+
+// GET /admin/receivers
+async fn list_receivers(State(admin): State<AdminState>) -> impl IntoResponse {
+	match admin.state.db.get_constraints_receivers() {
+		Ok(added) => (
+			StatusCode::OK,
+			Json(ReceiversResponse { configured: admin.state.settings().constraints_receivers, added }),
+		)
+			.into_response(),
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to list receivers: {e}")).into_response(),
+	}
+}
+
+// POST /admin/receivers
+async fn add_receiver(State(admin): State<AdminState>, Json(body): Json<AddReceiverRequest>) -> impl IntoResponse {
+	match store_receiver(&admin, body.public_key) {
+		Ok(added) => {
+			if added {
+				info!("Constraints receiver {} added via admin API", body.public_key);
+				wake_constraint_manager(&admin.state);
+			}
+			StatusCode::OK.into_response()
+		}
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to add receiver: {e}")).into_response(),
+	}
+}
+
+// DELETE /admin/receivers/{public_key}
+async fn remove_receiver(State(admin): State<AdminState>, Path(public_key): Path<String>) -> impl IntoResponse {
+	let public_key = match decode_pubkey(&public_key) {
+		Ok(public_key) => public_key,
+		Err(e) => return (StatusCode::BAD_REQUEST, format!("invalid receiver {public_key}: {e}")).into_response(),
+	};
+	if admin.state.settings().constraints_receivers.contains(&public_key) {
+		return (
+			StatusCode::CONFLICT,
+			format!("{public_key} is in constraints_receivers, remove it from the config file"),
+		)
+			.into_response();
+	}
+
+	match delete_receiver(&admin, &public_key) {
+		Ok(true) => {
+			info!("Constraints receiver {} removed via admin API", public_key);
+			wake_constraint_manager(&admin.state);
+			StatusCode::OK.into_response()
+		}
+		Ok(false) => (StatusCode::NOT_FOUND, format!("{public_key} is not a receiver")).into_response(),
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to remove receiver: {e}")).into_response(),
+	}
+}
+
+// POST /receivers/update
+async fn update_receiver(
+	State(admin): State<AdminState>,
+	Json(update): Json<SignedReceiverUpdate>,
+) -> impl IntoResponse {
+	let receiver = update.message.receiver;
+	if !admin.state.gateway_public_keys.contains(&update.message.gateway) {
+		return (StatusCode::BAD_REQUEST, format!("update is addressed to gateway {}", update.message.gateway))
+			.into_response();
+	}
+	if update.message.expiry_slot < admin.state.slot_clock.current_slot() {
+		return (StatusCode::BAD_REQUEST, format!("update expired in slot {}", update.message.expiry_slot))
+			.into_response();
+	}
+	let signing_root = get_receiver_update_signing_root(&update.message);
+	if let Err(e) =
+		verify_bls(admin.state.chain, &receiver, &signing_root, &update.signature, &update.signing_id, update.nonce)
+	{
+		return (StatusCode::UNAUTHORIZED, format!("invalid signature of {receiver}: {e}")).into_response();
+	}
+
+	if update.message.add {
+		return match store_receiver(&admin, receiver) {
+			Ok(added) => {
+				if added {
+					info!("Constraints receiver {} added by signed update", receiver);
+					wake_constraint_manager(&admin.state);
+				}
+				StatusCode::OK.into_response()
+			}
+			Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to add receiver: {e}")).into_response(),
+		};
+	}
+
+	// Receivers of the config file stay until it is edited
+	if admin.state.settings().constraints_receivers.contains(&receiver) {
+		return (StatusCode::CONFLICT, format!("{receiver} is in constraints_receivers of the gateway config"))
+			.into_response();
+	}
+	match delete_receiver(&admin, &receiver) {
+		Ok(removed) => {
+			if removed {
+				info!("Constraints receiver {} removed by signed update", receiver);
+				wake_constraint_manager(&admin.state);
+			}
+			StatusCode::OK.into_response()
+		}
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to remove receiver: {e}")).into_response(),
+	}
+}
+
+/// Add a receiver to the stored ones, false if it was already stored
+fn store_receiver(admin: &AdminState, public_key: BlsPublicKey) -> Result<bool> {
+	let _guard = admin.receivers_lock.lock().expect("receivers lock poisoned");
+	let mut receivers = admin.state.db.get_constraints_receivers()?;
+	if receivers.contains(&public_key) {
+		return Ok(false);
+	}
+	receivers.push(public_key);
+	admin.state.db.store_constraints_receivers(&receivers)?;
+	Ok(true)
+}
+
+/// Remove a receiver from the stored ones, false if it was not stored
+fn delete_receiver(admin: &AdminState, public_key: &BlsPublicKey) -> Result<bool> {
+	let _guard = admin.receivers_lock.lock().expect("receivers lock poisoned");
+	let mut receivers = admin.state.db.get_constraints_receivers()?;
+	let count = receivers.len();
+	receivers.retain(|receiver| receiver != public_key);
+	if receivers.len() == count {
+		return Ok(false);
+	}
+	admin.state.db.store_constraints_receivers(&receivers)?;
+	Ok(true)
+}
+
+// GET /admin/slots/{slot}/receivers
+async fn get_slot_receivers(State(admin): State<AdminState>, Path(slot): Path<u64>) -> impl IntoResponse {
+	let receivers = admin
+		.state
+		.db
+		.get_slot_receivers(slot)
+		.and_then(|overridden| Ok((overridden.is_some(), admin.state.constraints_receivers(slot)?)));
+
+	match receivers {
+		Ok((overridden, receivers)) => {
+			(StatusCode::OK, Json(SlotReceiversResponse { slot, receivers, overridden })).into_response()
+		}
+		Err(e) => {
+			(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to get receivers of slot {slot}: {e}")).into_response()
+		}
+	}
+}
+
+// PUT /admin/slots/{slot}/receivers
+async fn override_slot_receivers(
+	State(admin): State<AdminState>,
+	Path(slot): Path<u64>,
+	Json(body): Json<SlotReceiversRequest>,
+) -> impl IntoResponse {
+	if slot <= admin.state.slot_clock.current_slot() {
+		return (StatusCode::BAD_REQUEST, format!("slot {slot} has started")).into_response();
+	}
+
+	match admin.state.db.store_slot_receivers(slot, &body.receivers) {
+		Ok(()) => {
+			info!("Receivers of slot {} overridden via admin API: {:?}", slot, body.receivers);
+			wake_constraint_manager(&admin.state);
+			StatusCode::OK.into_response()
+		}
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to override receivers of slot {slot}: {e}"))
+			.into_response(),
+	}
+}
+
+// DELETE /admin/slots/{slot}/receivers
+async fn clear_slot_receivers(State(admin): State<AdminState>, Path(slot): Path<u64>) -> impl IntoResponse {
+	match admin.state.db.delete_slot_receivers(slot) {
+		Ok(()) => {
+			info!("Receiver override of slot {} cleared via admin API", slot);
+			wake_constraint_manager(&admin.state);
+			StatusCode::OK.into_response()
+		}
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to clear receivers of slot {slot}: {e}"))
+			.into_response(),
+	}
+}
+
+/// Persist a change of the pause or relay, so a restarted gateway applies it again
+fn update_overrides(admin: &AdminState, update: impl FnOnce(&mut AdminOverrides)) -> Result<()> {
+	let _guard = admin.overrides_lock.lock().expect("overrides lock poisoned");
+	let mut overrides = admin.state.db.get_admin_overrides()?;
+	update(&mut overrides);
+	admin.state.db.store_admin_overrides(&overrides)
+}
+
+/// Have the constraint manager check the next slot now, signing its posted constraints again for new receivers
+fn wake_constraint_manager(state: &GatewayState) {
+	state.constraints_stored.send_replace(state.slot_clock.current_slot() + 1);
+}
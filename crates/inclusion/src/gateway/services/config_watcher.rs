@@ -0,0 +1,179 @@
+use config::Validate;
+use eyre::{Result, WrapErr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{SignalKind, signal};
+use tracing::{info, warn};
+
+use crate::constants::CONFIG_WATCH_INTERVAL_MS;
+use crate::gateway::config::{GATEWAY_ENV_PREFIX, GatewayConfig};
+use crate::gateway::state::{GatewaySettings, GatewayState};
+
+/// Config watcher that applies changes of the gateway's module config without a restart
+///
+/// The file is read again on SIGHUP or when its modification time changes. Only the constraints receivers,
+/// the minimum tip, the per-slot gas budget and the API keys and quotas of `auth` are applied, everything else
+/// (keys, database, endpoints, ports) keeps its startup value until the next restart.
+pub struct ConfigWatcher {
+	state: Arc<GatewayState>,
+	path: PathBuf,
+	module_id: String,
+	startup: GatewayConfig,
+}
+
+impl ConfigWatcher {
+	/// Watch the module `module_id` of the Commit-Boost config at `path`, started with `startup`
+	pub fn new(state: Arc<GatewayState>, path: PathBuf, module_id: String, startup: GatewayConfig) -> Self {
+		Self { state, path, module_id, startup }
+	}
+
+	pub async fn run(&self) -> Result<()> {
+		info!("Watching {} for gateway config changes, reload on SIGHUP or file change", self.path.display());
+
+		let mut hangup = signal(SignalKind::hangup()).wrap_err("Failed to listen for SIGHUP")?;
+		let mut interval = tokio::time::interval(Duration::from_millis(CONFIG_WATCH_INTERVAL_MS));
+		let mut modified = self.modified().ok();
+		loop {
+			let forced = tokio::select! {
+				_ = hangup.recv() => true,
+				_ = interval.tick() => false,
+			};
+
+			let latest = match self.modified() {
+				Ok(latest) => Some(latest),
+				Err(e) => {
+					warn!("{}", e);
+					continue;
+				}
+			};
+			if !forced && latest == modified {
+				continue;
+			}
+			modified = latest;
+
+			// An invalid file leaves the current settings in place
+			if let Err(e) = self.reload() {
+				warn!("Keeping the current gateway settings, failed to reload {}: {}", self.path.display(), e);
+			}
+		}
+	}
+
+	fn modified(&self) -> Result<SystemTime> {
+		std::fs::metadata(&self.path)
+			.and_then(|metadata| metadata.modified())
+			.wrap_err_with(|| format!("Failed to read the modification time of {}", self.path.display()))
+	}
+
+	/// Read the config file and apply its reloadable settings
+	fn reload(&self) -> Result<()> {
+		let config = load_module_config(&self.path, &self.module_id)?;
+		config.check(&self.path.display().to_string())?;
+		let settings = GatewaySettings::from_config(&config)?;
+
+		if restart_required(&self.startup, &config) {
+			warn!(
+				"{} changes settings other than constraints_receivers, min_tip_per_gas_wei, slot_gas_budget_fraction \
+				 and auth keys and quotas, they only apply after a restart",
+				self.path.display()
+			);
+		}
+		if let (Some(authenticator), Some(auth)) = (&self.state.authenticator, config.auth) {
+			authenticator.update(auth);
+		}
+
+		if settings == self.state.settings() {
+			return Ok(());
+		}
+		info!(
+			"Reloaded gateway settings: {} constraints receiver(s), min tip {} wei per gas, slot gas budget fraction {}",
+			settings.constraints_receivers.len(),
+			settings.min_tip_per_gas_wei,
+			settings.slot_gas_budget_fraction
+		);
+		self.state.update_settings(settings);
+		Ok(())
+	}
+}
+
+/// Gateway config of the module `module_id` in a Commit-Boost config file, with the same environment overrides
+/// as at startup
+pub fn load_module_config(path: &Path, module_id: &str) -> Result<GatewayConfig> {
+	let module = config::load::read_module(path, module_id)?;
+	config::load::parse(module, GATEWAY_ENV_PREFIX, &format!("module {} of {}", module_id, path.display()))
+}
+
+/// Whether `reloaded` differs from the startup config in a setting that is not reloaded
+/// Turning `auth` on or off needs a restart, only its keys and quotas are reloaded
+pub fn restart_required(startup: &GatewayConfig, reloaded: &GatewayConfig) -> bool {
+	let mut reloaded = reloaded.clone();
+	reloaded.constraints_receivers = startup.constraints_receivers.clone();
+	reloaded.min_tip_per_gas_wei = startup.min_tip_per_gas_wei;
+	reloaded.slot_gas_budget_fraction = startup.slot_gas_budget_fraction;
+	if reloaded.auth.is_some() && startup.auth.is_some() {
+		reloaded.auth = startup.auth.clone();
+	}
+	serde_json::to_value(&reloaded).ok() != serde_json::to_value(startup).ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const RECEIVER: &str =
+		"a0b0c0d0e0f0a1b1c1d1e1f1a2b2c2d2e2f2a3b3c3d3e3f3a4b4c4d4e4f4a5b5c5d5e5f5a6b6c6d6e6f6a7b7c7d7e7f7";
+
+	fn module_config(module_id: &str, db_path: &str, min_tip_per_gas_wei: u64, receivers: &[&str]) -> String {
+		let receivers = receivers.iter().map(|receiver| format!("\"{}\"", receiver)).collect::<Vec<_>>().join(", ");
+		format!(
+			r#"
+[[modules]]
+id = "{module_id}"
+type = "commit"
+rpc_host = "0.0.0.0"
+rpc_port = 8000
+metrics_host = "0.0.0.0"
+metrics_port = 8001
+db_path = "{db_path}"
+relay_host = "relay"
+relay_port = 9000
+execution_client_host = "execution"
+execution_client_port = 8545
+constraints_receivers = [{receivers}]
+module_signing_id = "00"
+log_level = "info"
+delegation_check_interval_seconds = 12
+gateway_public_key = "{RECEIVER}"
+min_tip_per_gas_wei = {min_tip_per_gas_wei}
+"#
+		)
+	}
+
+	#[test]
+	fn test_load_module_config_and_restart_required() -> Result<()> {
+		let dir = tempfile::TempDir::new()?;
+		let path = dir.path().join("config.toml");
+		let startup = module_config("gateway", "/data/db", 1, &[]);
+		std::fs::write(&path, format!("{}{}", module_config("other", "/other", 7, &[]), startup))?;
+
+		let startup = load_module_config(&path, "gateway")?;
+		assert_eq!(startup.db_path, "/data/db");
+		assert!(load_module_config(&path, "missing").is_err());
+
+		// Reloadable settings change without a restart
+		std::fs::write(&path, module_config("gateway", "/data/db", 5, &[RECEIVER]))?;
+		let reloaded = load_module_config(&path, "gateway")?;
+		let settings = GatewaySettings::from_config(&reloaded)?;
+		assert_eq!(settings.min_tip_per_gas_wei, 5);
+		assert_eq!(settings.constraints_receivers.len(), 1);
+		assert!(!restart_required(&startup, &reloaded));
+
+		std::fs::write(&path, module_config("gateway", "/moved/db", 5, &[RECEIVER]))?;
+		assert!(restart_required(&startup, &load_module_config(&path, "gateway")?));
+
+		// An undecodable receiver is rejected as a whole
+		std::fs::write(&path, module_config("gateway", "/data/db", 5, &["zz"]))?;
+		assert!(GatewaySettings::from_config(&load_module_config(&path, "gateway")?).is_err());
+		Ok(())
+	}
+}
@@ -1,145 +1,442 @@
-use constraints::types::{Constraint, ConstraintsMessage, SignedDelegation};
-use eyre::Result;
-use std::sync::Arc;
+use alloy::primitives::B256;
+use alloy::rpc::types::beacon::BlsPublicKey;
+use commitments::types::CommitmentStatus;
+use constraints::conflicts::find_conflicts;
+use constraints::metadata::DelegationMetadata;
+use constraints::types::{Constraint, ConstraintsMessage, SignedConstraints, SignedDelegation};
+use eyre::{Result, WrapErr, eyre};
+use lookahead::events::{BeaconEvent, EventSubscription, EventTopic};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
-use crate::constants::CONSTRAINT_TRIGGER_OFFSET_MS;
+use crate::constants::{CONSTRAINT_TRIGGER_OFFSET_MS, INCLUSION_CONSTRAINT_TYPE};
+use crate::gateway::metrics::{
+	CONSTRAINT_POST_ACCEPTED, CONSTRAINT_POST_FAILED, CONSTRAINT_VERIFICATION_FAILED, CONSTRAINT_VERIFICATION_MATCHED,
+	CONSTRAINT_VERIFICATION_MISMATCH, GATEWAY_CONSTRAINT_POSTS_TOTAL, GATEWAY_CONSTRAINT_SLOTS_DROPPED_TOTAL,
+	GATEWAY_CONSTRAINT_VERIFICATIONS_TOTAL,
+};
 use crate::gateway::state::GatewayState;
-use crate::gateway::utils::sign_constraints_message;
+use crate::gateway::utils::{
+	create_commitment_event, served_constraints_mismatches, sign_constraints_message, sign_receiver_auth,
+};
+use crate::handlers::default_registry;
 use crate::storage::InclusionDbExt;
+use crate::types::HandedOverCommitment;
 use constraints::client::ConstraintsClient;
-use lookahead::utils::{current_slot, time_until_slot_ms};
 use proposer::storage::DelegationsDbExt;
 
 /// Constraint manager that monitors delegated slots and triggers constraint processing
 pub struct ConstraintManager {
 	state: Arc<GatewayState>,
+	/// Request hashes and receivers of the constraints the relay accepted for each slot, to post again only when
+	/// commitments are added or the receivers change
+	posted: Mutex<HashMap<u64, (HashSet<B256>, Vec<BlsPublicKey>)>>,
 }
 
 impl ConstraintManager {
 	/// Create a new constraint manager
 	pub fn new(state: Arc<GatewayState>) -> Self {
-		Self { state }
+		Self { state, posted: Mutex::new(HashMap::new()) }
 	}
 
 	/// Run the constraints task continuously
+	/// The next slot is checked at every slot tick, head event and stored commitment, and when its trigger time
+	/// (or, with incremental updates, its commitment cutoff) is reached
 	pub async fn run(&self) -> Result<()> {
 		info!("Starting constraints task - monitoring delegated slots");
+		let mut ticks = self.state.slot_clock.ticks();
+		let mut stored = self.state.constraints_stored.subscribe();
+		let mut events = self
+			.state
+			.beacon_client
+			.clone()
+			.map(|beacon_client| EventSubscription::new(beacon_client, vec![EventTopic::Head]));
 
+		let posting = &self.state.constraint_posting;
+		let mut backoff_ms = posting.retry_backoff_ms;
 		loop {
-			if let Err(e) = self.check_and_process_constraints().await {
-				error!("Error in constraints check: {}", e);
-			}
+			let target_slot = self.state.slot_clock.current_slot() + 1;
+			let time_until_slot_ms = self.state.slot_clock.slot_deadline(target_slot);
+
+			// A failed check is retried with backoff instead of waiting for the next head event or slot tick
+			let retry_ms = match self.check_and_process_constraints(target_slot).await {
+				Ok(()) => {
+					backoff_ms = posting.retry_backoff_ms;
+					None
+				}
+				Err(e) => {
+					error!("Error in constraints check: {}", e);
+					let retry_ms = retry_wake_ms(time_until_slot_ms, posting.hard_cutoff_ms, backoff_ms);
+					backoff_ms = (backoff_ms * 2).min(posting.max_retry_backoff_ms);
+					retry_ms
+				}
+			};
 
-			// Sleep for a short interval before checking again
-			sleep(Duration::from_millis(100)).await;
+			let wake_ms = next_wake_ms(
+				time_until_slot_ms,
+				CONSTRAINT_TRIGGER_OFFSET_MS,
+				posting.incremental_updates.then_some(self.state.commitment_cutoff_ms),
+			)
+			.into_iter()
+			.chain(retry_ms)
+			.min();
+			tokio::select! {
+				_ = ticks.recv() => {}
+				_ = next_head(&mut events) => {}
+				Ok(()) = stored.changed() => {}
+				_ = sleep_for(wake_ms) => {}
+			}
 		}
 	}
 
-	/// Check for delegated slots and process constraints if needed
-	async fn check_and_process_constraints(&self) -> Result<()> {
-		let target_slot = current_slot(&self.state.chain) + 1;
-
-		// Check if target slot is delegated
-		match self.state.db.get_delegation(target_slot) {
-			Ok(Some(delegation)) => {
-				// Check if constraints have already been finalized for this slot to prevent reprocessing
-				match self.state.db.signed_constraints_finalized(target_slot) {
-					Ok(true) => {
-						// Sleep briefly before retrying
-						tokio::time::sleep(Duration::from_millis(250)).await;
-					}
-					Ok(false) => {
-						// Calculate time until trigger offset before target slot starts (in milliseconds)
-						let time_until_slot = time_until_slot_ms(self.state.chain.genesis_time_sec(), target_slot);
-						let trigger_time_ms = time_until_slot - CONSTRAINT_TRIGGER_OFFSET_MS;
-
-						if trigger_time_ms <= 0 {
-							// Time to process constraints for this slot
-							debug!("Triggering constraints processing for slot {}", target_slot);
-							if let Err(e) = self.post_constraints(target_slot, delegation).await {
-								warn!("Failed to process constraints for slot {}: {}", target_slot, e);
-							}
-						} else {
-							// Wait until it's time to trigger
-							debug!(
-								"Slot {} is delegated, waiting {}ms until trigger time",
-								target_slot, trigger_time_ms
-							);
-							tokio::time::sleep(Duration::from_millis(trigger_time_ms as u64)).await;
-
-							// Now process constraints
-							debug!("Triggering constraints processing for slot {}", target_slot);
-							if let Err(e) = self.post_constraints(target_slot, delegation).await {
-								warn!("Failed to process constraints for slot {}: {}", target_slot, e);
-							}
-						}
-					}
-					Err(e) => {
-						error!("Failed to check constraint posted status for slot {}: {}", target_slot, e);
-						// Continue with processing despite the error
-					}
-				}
+	/// Post the constraints of the target slot if it is delegated and its trigger time has passed
+	async fn check_and_process_constraints(&self, target_slot: u64) -> Result<()> {
+		// Only the leader posts, a standby has not signed the commitments
+		if !self.state.is_leader() {
+			return Ok(());
+		}
+		let Some(delegation) = self.state.db.get_delegation(target_slot)? else {
+			return Ok(());
+		};
+
+		// Constraints are posted once, unless updated incrementally or signed again for new receivers
+		if self.state.db.signed_constraints_finalized(target_slot)? {
+			if !self.state.constraint_posting.incremental_updates && !self.receivers_changed(target_slot)? {
+				return Ok(());
 			}
-			Ok(None) => {
-				// Target slot is not delegated, nothing to do
-				// Sleep briefly before retrying
-				tokio::time::sleep(Duration::from_millis(250)).await;
+			return self
+				.post_constraints(target_slot, delegation, false)
+				.await
+				.wrap_err_with(|| format!("Failed to update constraints for slot {}", target_slot));
+		}
+
+		let trigger_in_ms = self.state.slot_clock.slot_deadline(target_slot) - CONSTRAINT_TRIGGER_OFFSET_MS;
+		if trigger_in_ms > 0 {
+			debug!("Slot {} is delegated, {}ms until trigger time", target_slot, trigger_in_ms);
+			return Ok(());
+		}
+
+		debug!("Triggering constraints processing for slot {}", target_slot);
+		self.post_constraints(target_slot, delegation, false)
+			.await
+			.wrap_err_with(|| format!("Failed to process constraints for slot {}", target_slot))
+	}
+
+	/// Post the constraints of a delegated slot now, whatever its trigger time and even if they were posted
+	pub async fn force_post(&self, slot: u64) -> Result<()> {
+		if !self.state.is_leader() {
+			return Err(eyre!("Only the leader posts constraints"));
+		}
+		let delegation =
+			self.state.db.get_delegation(slot)?.ok_or_else(|| eyre!("No delegation found for slot {}", slot))?;
+		info!("Force posting constraints for slot {}", slot);
+		self.post_constraints(slot, delegation, true).await
+	}
+
+	/// Store the commitments previous leaders handed over for `slot` that this instance does not hold, and prune
+	/// those of past slots. Only the commitments and their constraints are stored, to be posted
+	async fn take_over_commitments(&self, slot: u64) -> Result<()> {
+		let Some(leader_election) = &self.state.leader_election else {
+			return Ok(());
+		};
+		for encoded in leader_election.handed_over(slot).await? {
+			let record: HandedOverCommitment =
+				serde_json::from_slice(&encoded).wrap_err("Invalid handed over commitment")?;
+			if self.state.db.get_signed_commitment(&record.request_hash)?.is_some() {
+				continue;
 			}
-			Err(e) => {
-				error!("Failed to check delegation status for slot {}: {}", target_slot, e);
-				// Sleep briefly before retrying
-				tokio::time::sleep(Duration::from_millis(250)).await;
+			let Some(((request_hash, constraint), members)) = record.constraints.split_first() else {
+				continue;
+			};
+			if members.is_empty() {
+				self.state.db.store_signed_commitment_and_constraint(
+					slot,
+					request_hash,
+					&record.commitment,
+					constraint,
+				)?;
+			} else {
+				self.state.db.store_grouped_commitment(slot, request_hash, &record.commitment, constraint, members)?;
 			}
+			info!("Took over commitment {:?} for slot {} from a previous leader", request_hash, slot);
 		}
+		leader_election.prune_handed_over(self.state.slot_clock.current_slot()).await
+	}
 
-		Ok(())
+	/// Whether the receivers of a posted slot changed since the relay accepted its constraints
+	fn receivers_changed(&self, slot: u64) -> Result<bool> {
+		let posted = self.posted.lock().expect("posted constraints lock poisoned").get(&slot).cloned();
+		let Some((_, posted_receivers)) = posted else {
+			return Ok(false);
+		};
+		Ok(posted_receivers != self.state.constraints_receivers(slot)?)
 	}
 
 	/// Process constraints for a specific slot
-	async fn post_constraints(&self, slot: u64, delegation: SignedDelegation) -> Result<()> {
-		// Get constraints for the specific slot
-		let constraints: Vec<Constraint> = self
-			.state
-			.db
-			.get_constraints_in_range(slot, slot)?
-			.into_iter()
-			.map(|(_, _, constraint)| constraint)
-			.collect();
+	/// Unless forced, a slot is only posted again when commitments were added or its receivers changed since the
+	/// last post
+	async fn post_constraints(&self, slot: u64, delegation: SignedDelegation, force: bool) -> Result<()> {
+		// Commitments signed by a previous leader are posted along with this instance's own
+		self.take_over_commitments(slot).await.wrap_err("Failed to take over the handed over commitments")?;
+
+		// Get constraints for the specific slot, keyed by the request hash of their commitment
+		let commitments = self.state.db.get_constraints_in_range(slot, slot)?;
+
+		// Only the constraints the proposer's terms allow are posted, in order and up to their maximum. Requests outside
+		// the terms are refused at intake, so this only guards against commitments admitted concurrently
+		let terms = DelegationMetadata::decode(&delegation.message.metadata)
+			.wrap_err_with(|| format!("Invalid terms in the delegation of slot {}", slot))?;
+		if terms.is_expired(self.state.slot_clock.now_ms() / 1000) {
+			return Err(eyre!(
+				"The delegation terms of slot {} lapsed at {}, not posting its constraints",
+				slot,
+				terms.valid_until.unwrap_or_default()
+			));
+		}
+		let (commitments, dropped) = terms.limit(commitments, |(_, _, constraint)| constraint);
+		if dropped > 0 {
+			warn!("{} constraint(s) of slot {} are outside the delegation terms and not posted", dropped, slot);
+		}
+		let constraints: Vec<Constraint> = commitments.iter().map(|(_, _, constraint)| constraint.clone()).collect();
 
 		if constraints.is_empty() {
 			debug!("Delegated, but no constraints to post for slot {}", slot);
 			return Ok(());
 		}
 
+		// A slot is posted again only when commitments were added or its receivers changed since the relay accepted
+		// its constraints
+		let receivers = self.state.constraints_receivers(slot)?;
+		let posted = self.posted.lock().expect("posted constraints lock poisoned").get(&slot).cloned();
+		let update = posted.is_some();
+		let (posted, posted_receivers) = posted.unwrap_or_default();
+		let added = commitments.iter().filter(|(_, request_hash, _)| !posted.contains(request_hash)).count();
+		let receivers_changed = update && posted_receivers != receivers;
+		if added == 0 && !receivers_changed && !force {
+			return Ok(());
+		}
+		if update && self.state.slot_clock.slot_deadline(slot) < self.state.constraint_posting.hard_cutoff_ms {
+			return Err(eyre!("Past the hard cutoff, {} new constraint(s) are not posted", added));
+		}
+
+		// Conflicting requests are refused at intake and the slot gas budget caps their gas, so this only guards
+		// against a set the relay would reject
+		find_conflicts(&default_registry(), &constraints, None)?
+			.into_result()
+			.wrap_err_with(|| format!("Not posting the constraints of slot {}", slot))?;
+
 		let constraints_message = ConstraintsMessage {
 			proposer: delegation.message.proposer.clone(),
 			delegate: delegation.message.delegate.clone(),
 			slot,
 			constraints,
-			receivers: self.state.constraints_receivers.clone(),
+			receivers: receivers.clone(),
 		};
 
-		// Sign the constraints message with the gateway public key
+		// Sign the constraints message with the gateway key the slot was delegated to
+		if !self.state.is_gateway_key(&delegation.message.delegate) {
+			return Err(eyre!(
+				"Slot {} is delegated to {}, not a key of this gateway",
+				slot,
+				delegation.message.delegate
+			));
+		}
 		let signed_constraints = sign_constraints_message(
 			&constraints_message,
 			&mut self.state.signer_client.clone(),
+			&self.state.nonce_manager,
 			delegation.message.delegate,
 			&self.state.module_signing_id,
 			self.state.chain,
 		)
 		.await?;
 
-		// Send to relay using the client
-		self.state.constraints_client.post_constraints(&signed_constraints).await?;
+		// Send to relay using the client, only a 2xx marks the slot as posted
+		if let Err(e) = self.post_with_retry(slot, &signed_constraints).await {
+			if !update {
+				error!("Dropping the constraints of slot {}: {}", slot, e);
+				GATEWAY_CONSTRAINT_SLOTS_DROPPED_TOTAL.inc();
+			}
+			return Err(e);
+		}
+
+		// A relay serving something else than what it accepted must be noticed while another relay can be used,
+		// checked in the background so the next post is not held up by the relay
+		tokio::spawn(verify_posted(Arc::clone(&self.state), slot, signed_constraints));
 
 		// Mark constraints as posted for this slot to prevent reprocessing
 		self.state.db.finalize_signed_constraints(slot)?;
+		{
+			let mut accepted = self.posted.lock().expect("posted constraints lock poisoned");
+			accepted.insert(slot, (commitments.iter().map(|(_, request_hash, _)| *request_hash).collect(), receivers));
+			accepted.retain(|posted_slot, _| *posted_slot >= slot);
+		}
+
+		info!("Successfully posted {} constraint(s) for slot {}", commitments.len(), slot);
 
-		info!("Successfully posted constraints for slot {}", slot);
+		// Record the transition, then notify subscribers of the newly committed transactions
+		// The inclusion monitor settles the commitments once the slot's block is known
+		for (_, request_hash, constraint) in commitments.iter().filter(|(_, hash, _)| !posted.contains(hash)) {
+			// Bundle transactions are stored under member hashes, subscribers know them by the bundle's request hash
+			let commitment_hash = match self.state.db.get_signed_commitment(request_hash) {
+				Ok(Some(stored)) => stored.commitment.commitment.request_hash,
+				_ => *request_hash,
+			};
+			if let Err(e) = self.state.db.store_commitment_status(&commitment_hash, CommitmentStatus::Constrained) {
+				warn!("Failed to store status of commitment {}: {}", commitment_hash, e);
+			}
+
+			// Only inclusion commitments are followed, exclusions have no transaction to watch for
+			if constraint.constraint_type != INCLUSION_CONSTRAINT_TYPE {
+				continue;
+			}
+			match create_commitment_event(commitment_hash, constraint, slot, CommitmentStatus::Constrained) {
+				Ok(event) => {
+					let _ = self.state.commitment_events.send(event);
+				}
+				Err(e) => warn!("Failed to create commitment event for {}: {}", request_hash, e),
+			}
+		}
 
 		Ok(())
 	}
+
+	/// Post signed constraints until the relay accepts them, backing off between attempts
+	/// Gives up once the next attempt would fall within the hard cutoff before the slot
+	async fn post_with_retry(&self, slot: u64, signed_constraints: &SignedConstraints) -> Result<()> {
+		let config = &self.state.constraint_posting;
+		let mut backoff_ms = config.retry_backoff_ms;
+		let mut attempt = 1;
+		loop {
+			match self.state.constraints_client().post_constraints(signed_constraints).await {
+				Ok(receipt) => {
+					GATEWAY_CONSTRAINT_POSTS_TOTAL.with_label_values(&[CONSTRAINT_POST_ACCEPTED]).inc();
+					// Keep the relay receipt as evidence of acceptance
+					if let Some(receipt) = receipt {
+						debug!("Relay receipt for slot {} constraints: {:?}", slot, receipt);
+						self.state.db.store_receipt(&receipt)?;
+					}
+					return Ok(());
+				}
+				Err(e) => {
+					GATEWAY_CONSTRAINT_POSTS_TOTAL.with_label_values(&[CONSTRAINT_POST_FAILED]).inc();
+					let time_left_ms = self.state.slot_clock.slot_deadline(slot) - config.hard_cutoff_ms;
+					if time_left_ms < backoff_ms as i64 {
+						return Err(eyre!("Giving up after {} attempt(s), last error: {}", attempt, e));
+					}
+					warn!(
+						"Attempt {} to post constraints for slot {} failed, retrying in {}ms: {}",
+						attempt, slot, backoff_ms, e
+					);
+				}
+			}
+			tokio::time::sleep(Duration::from_millis(backoff_ms)).await;
+			backoff_ms = (backoff_ms * 2).min(config.max_retry_backoff_ms);
+			attempt += 1;
+		}
+	}
+}
+
+/// Fetch the slot's constraints back from the relay as the delegate and compare them with the posted message
+async fn verify_posted(state: Arc<GatewayState>, slot: u64, signed_constraints: SignedConstraints) {
+	let served = async {
+		let auth = sign_receiver_auth(
+			slot,
+			state.slot_clock.now_ms(),
+			&mut state.signer_client.clone(),
+			&state.nonce_manager,
+			signed_constraints.message.delegate,
+			&state.module_signing_id,
+			state.chain,
+		)
+		.await?;
+		eyre::Ok(state.constraints_client().get_constraints_as_receiver(slot, &auth).await?)
+	};
+
+	match served.await {
+		Ok(served) => {
+			let mismatches = served_constraints_mismatches(&signed_constraints, &served);
+			if mismatches.is_empty() {
+				debug!("Relay serves the posted constraints of slot {} unchanged", slot);
+				GATEWAY_CONSTRAINT_VERIFICATIONS_TOTAL.with_label_values(&[CONSTRAINT_VERIFICATION_MATCHED]).inc();
+			} else {
+				error!(
+					"Relay {} does not serve the constraints posted for slot {}: {}",
+					state.constraints_client().base_url,
+					slot,
+					mismatches.join("; ")
+				);
+				GATEWAY_CONSTRAINT_VERIFICATIONS_TOTAL.with_label_values(&[CONSTRAINT_VERIFICATION_MISMATCH]).inc();
+			}
+		}
+		Err(e) => {
+			warn!("Failed to fetch the posted constraints of slot {} back from the relay: {}", slot, e);
+			GATEWAY_CONSTRAINT_VERIFICATIONS_TOTAL.with_label_values(&[CONSTRAINT_VERIFICATION_FAILED]).inc();
+		}
+	}
+}
+
+/// Milliseconds until the next time the target slot must be checked, given the time until it starts
+/// That is its trigger time, then, if its constraints are updated incrementally, the close of its commitments
+fn next_wake_ms(time_until_slot_ms: i64, trigger_offset_ms: i64, commitment_cutoff_ms: Option<i64>) -> Option<i64> {
+	[Some(trigger_offset_ms), commitment_cutoff_ms]
+		.into_iter()
+		.flatten()
+		.map(|offset_ms| time_until_slot_ms - offset_ms)
+		.filter(|wake_ms| *wake_ms > 0)
+		.min()
+}
+
+/// Milliseconds until a failed check of the target slot is retried, none once the retry would fall within the
+/// hard cutoff before the slot, where nothing is posted anymore
+fn retry_wake_ms(time_until_slot_ms: i64, hard_cutoff_ms: i64, backoff_ms: u64) -> Option<i64> {
+	let retry_ms = backoff_ms as i64;
+	(time_until_slot_ms - hard_cutoff_ms > retry_ms).then_some(retry_ms)
+}
+
+/// Sleep for the given milliseconds, forever if there is nothing to wait for
+async fn sleep_for(wake_ms: Option<i64>) {
+	match wake_ms {
+		Some(wake_ms) => tokio::time::sleep(Duration::from_millis(wake_ms as u64)).await,
+		None => std::future::pending().await,
+	}
+}
+
+/// Wait for the next head event, forever without a beacon node
+async fn next_head(events: &mut Option<EventSubscription>) {
+	let Some(events) = events else {
+		return std::future::pending().await;
+	};
+	while !matches!(events.next().await, BeaconEvent::Head(_)) {}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_next_wake_is_the_first_deadline_still_ahead() {
+		// Trigger time 14s before the slot, commitments closing 2s before it
+		assert_eq!(next_wake_ms(20_000, 14_000, None), Some(6_000));
+		assert_eq!(next_wake_ms(20_000, 14_000, Some(2_000)), Some(6_000));
+		assert_eq!(next_wake_ms(10_000, 14_000, Some(2_000)), Some(8_000));
+
+		// Past every deadline, the next slot tick takes over
+		assert_eq!(next_wake_ms(10_000, 14_000, None), None);
+		assert_eq!(next_wake_ms(1_000, 14_000, Some(2_000)), None);
+	}
+
+	#[test]
+	fn test_failed_check_is_retried_until_the_hard_cutoff() {
+		// 5s before the slot with a 1s hard cutoff, a retry in 250ms is still in time
+		assert_eq!(retry_wake_ms(5_000, 1_000, 250), Some(250));
+		assert_eq!(retry_wake_ms(1_200, 1_000, 250), None);
+		assert_eq!(retry_wake_ms(500, 1_000, 250), None);
+
+		// Without a beacon node nothing else wakes the loop before the next slot tick
+		let wake_ms = next_wake_ms(5_000, 14_000, None).into_iter().chain(retry_wake_ms(5_000, 1_000, 250)).min();
+		assert_eq!(wake_ms, Some(250));
+	}
 }
@@ -1,24 +1,31 @@
 use eyre::Result;
+use std::collections::BTreeSet;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use std::time::Duration;
+use tokio::sync::Mutex;
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
 use crate::constants::LOOKAHEAD_WINDOW_SIZE;
+use crate::gateway::metrics::GATEWAY_LOOKAHEAD_DELEGATIONS;
 use crate::gateway::state::GatewayState;
+use crate::gateway::utils::{delegation_terms, sign_delegation_offer};
 use constraints::client::ConstraintsClient;
-use lookahead::utils::{current_slot, slot_to_epoch};
+use constraints::types::DelegationOffer;
 use proposer::storage::DelegationsDbExt;
 
 /// Delegation manager that monitors delegated slots
 pub struct DelegationManager {
 	state: Arc<GatewayState>,
+	/// Slots a delegation offer was published for, offers are signed once per slot
+	offered_slots: Mutex<BTreeSet<u64>>,
 }
 
 impl DelegationManager {
 	/// Create a new delegation task
 	pub fn new(state: Arc<GatewayState>) -> Self {
-		Self { state }
+		Self { state, offered_slots: Mutex::new(BTreeSet::new()) }
 	}
 
 	/// Run the delegation task continuously
@@ -26,8 +33,9 @@ impl DelegationManager {
 		info!("Starting delegation task with {}s polling interval", self.state.delegation_check_interval_seconds);
 
 		loop {
-			if let Err(e) = self.update_delegations().await {
-				error!("Error in delegation check: {}", e);
+			match self.update_delegations().await {
+				Ok(()) => self.state.delegations_checked_ms.store(self.state.slot_clock.now_ms(), Ordering::Relaxed),
+				Err(e) => error!("Error in delegation check: {}", e),
 			}
 
 			sleep(Duration::from_secs(self.state.delegation_check_interval_seconds)).await;
@@ -36,7 +44,7 @@ impl DelegationManager {
 
 	/// Check delegations for upcoming slots
 	async fn update_delegations(&self) -> Result<()> {
-		let current_slot = current_slot(&self.state.chain);
+		let current_slot = self.state.slot_clock.current_slot();
 		let lookahead_end = current_slot + LOOKAHEAD_WINDOW_SIZE;
 
 		// Batch read known delegated slots
@@ -58,6 +66,11 @@ impl DelegationManager {
 
 			// Process but don't return on error to continue processing other slots
 			match self.get_delegations_from_relay(slot).await {
+				Ok(0) if slot > current_slot => {
+					if let Err(e) = self.publish_delegation_offer(slot).await {
+						warn!("Failed to publish the delegation offer for slot {}: {}", slot, e);
+					}
+				}
 				Ok(found) => {
 					count += found;
 				}
@@ -66,9 +79,48 @@ impl DelegationManager {
 				}
 			}
 		}
+		self.offered_slots.lock().await.retain(|slot| *slot >= current_slot);
+
+		GATEWAY_LOOKAHEAD_DELEGATIONS.set(count as i64);
+		info!(
+			"{} delegations in epochs {}-{}",
+			count,
+			self.state.slot_clock.slot_to_epoch(current_slot),
+			self.state.slot_clock.slot_to_epoch(current_slot) + 1
+		);
+
+		Ok(())
+	}
 
-		info!("{} delegations in epochs {}-{}", count, slot_to_epoch(current_slot), slot_to_epoch(current_slot) + 1);
+	/// Offer to be the delegate of an undelegated slot, if offers are configured and none was published for it yet
+	/// Only the leader publishes, so the instances of a highly available gateway do not sign an offer each
+	async fn publish_delegation_offer(&self, slot: u64) -> Result<()> {
+		let Some(config) = &self.state.delegation_offer else {
+			return Ok(());
+		};
+		if !self.state.is_leader() || self.offered_slots.lock().await.contains(&slot) {
+			return Ok(());
+		}
 
+		let offer = DelegationOffer {
+			delegate: self.state.gateway_public_key,
+			committer: config.committer_address,
+			slot,
+			bid_gwei: config.bid_gwei,
+			constraint_types: config.constraint_types.clone(),
+		};
+		let signed_offer = sign_delegation_offer(
+			&offer,
+			&mut self.state.signer_client.clone(),
+			&self.state.nonce_manager,
+			&self.state.module_signing_id,
+			self.state.chain,
+		)
+		.await?;
+		self.state.constraints_client().post_delegation_offer(&signed_offer).await?;
+
+		self.offered_slots.lock().await.insert(slot);
+		debug!("Published delegation offer for slot {}, bid={} gwei", slot, config.bid_gwei);
 		Ok(())
 	}
 
@@ -76,20 +128,23 @@ impl DelegationManager {
 	async fn get_delegations_from_relay(&self, slot: u64) -> Result<u64> {
 		debug!("Getting delegations for slot {}", slot);
 		let mut found = 0;
-		let delegations = self.state.constraints_client.get_delegations(slot).await?;
+		let delegations = self.state.constraints_client().get_delegations(slot).await?;
 
-		// It's assumed there is only one delegation for a given slot
-		match delegations.first() {
+		// A slot may be delegated to several gateways, only keep the one to a key of this gateway
+		let delegation = self
+			.state
+			.gateway_public_keys
+			.iter()
+			.find_map(|public_key| delegations.iter().find(|d| d.message.delegate == *public_key));
+		match delegation {
 			Some(delegation) => {
-				if delegation.message.delegate != self.state.gateway_public_key {
-					// Don't error out if the delegation is not for the gateway public key
-					return Ok(found);
-				}
+				// Terms the gateway cannot decode could not be honoured, so the slot is not taken on
+				delegation_terms(delegation)?;
 
 				// Store delegation in the database to prevent reprocessing
 				self.state.db.store_delegation(&delegation)?;
 				found += 1;
-				info!("Delegation found for slot {}", slot);
+				info!("Delegation found for slot {} to {}", slot, delegation.message.delegate);
 
 				Ok(found)
 			}
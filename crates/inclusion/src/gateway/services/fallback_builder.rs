@@ -0,0 +1,223 @@
+use alloy::eips::eip4895::Withdrawal;
+use alloy::primitives::B256;
+use alloy::providers::Provider;
+use alloy::rpc::types::beacon::relay::{BidTrace, ElectraSubmitBlockRequest, SubmitBlockRequest};
+use alloy::rpc::types::beacon::requests::ExecutionRequestsV4;
+use alloy::rpc::types::engine::{ForkchoiceState, PayloadAttributes};
+use constraints::client::ConstraintsClient;
+use constraints::helpers::{extract_blob_versioned_hashes, extract_transactions};
+use constraints::types::{Constraint, SignedDelegation, SubmitBlockRequestWithProofs};
+use eyre::{Result, WrapErr, eyre};
+use lookahead::events::{BeaconEvent, EventSubscription, EventTopic, PayloadAttributesData};
+use proposer::storage::DelegationsDbExt;
+use signing::builder::BuilderSigner;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+use crate::constants::{FALLBACK_BUILD_TIME_MS, INCLUSION_CONSTRAINT_TYPE};
+use crate::gateway::config::FallbackBuilderConfig;
+use crate::gateway::engine::EngineClient;
+use crate::gateway::metrics::{
+	FALLBACK_BLOCK_FAILED, FALLBACK_BLOCK_SUBMITTED, FALLBACK_BLOCK_UNSATISFIED, GATEWAY_FALLBACK_BLOCKS_TOTAL,
+};
+use crate::gateway::state::GatewayState;
+use crate::proofs::prove_constraints_report;
+use crate::storage::InclusionDbExt;
+use crate::types::InclusionPayload;
+
+/// Fallback builder that submits a block satisfying the constraints of each delegated slot
+///
+/// Shortly before a delegated slot, the constrained transactions are sent to the execution client, which then
+/// builds a payload on the head named by the beacon node's `payload_attributes` event. The payload is proven
+/// against the slot's constraints and submitted to the relay as a signed bid, so the commitments can still be
+/// honored when no external builder respects the constraints. The relay auction keeps any better bid.
+pub struct FallbackBuilder {
+	state: Arc<GatewayState>,
+	engine: EngineClient,
+	builder_signer: BuilderSigner,
+	trigger_offset_ms: i64,
+}
+
+impl FallbackBuilder {
+	pub fn new(state: Arc<GatewayState>, config: &FallbackBuilderConfig) -> Result<Self> {
+		let engine = EngineClient::new(config.engine_api_url.clone(), &config.jwt_secret_path)?;
+		let builder_signer = BuilderSigner::from_env(&config.builder_key_env, state.chain)?;
+		Ok(Self { state, engine, builder_signer, trigger_offset_ms: config.trigger_offset_ms })
+	}
+
+	/// Build for one slot after the other, reading the payload attributes of each from the beacon event stream
+	pub async fn run(&self) -> Result<()> {
+		let Some(beacon_client) = self.state.beacon_client.clone() else {
+			return Err(eyre!("The fallback builder needs a beacon node for the payload attributes of each slot"));
+		};
+		info!(
+			"Starting fallback builder with builder key {}, building {}ms before delegated slots",
+			self.builder_signer.public_key(),
+			self.trigger_offset_ms
+		);
+
+		let mut events = EventSubscription::new(beacon_client, vec![EventTopic::PayloadAttributes]);
+		let mut attributes: HashMap<u64, PayloadAttributesData> = HashMap::new();
+		let mut slot = self.state.slot_clock.current_slot() + 1;
+		loop {
+			let wait_ms = self.state.slot_clock.slot_deadline(slot) - self.trigger_offset_ms;
+
+			tokio::select! {
+				event = events.next() => {
+					if let BeaconEvent::PayloadAttributes(event) = event {
+						// Attributes are sent again when the head changes, the last ones are built on
+						attributes.insert(event.data.proposal_slot, event.data);
+					}
+				}
+				_ = tokio::time::sleep(Duration::from_millis(wait_ms.max(0) as u64)) => {
+					if let Err(e) = self.build_slot(slot, attributes.remove(&slot)).await {
+						error!("Fallback block for slot {} failed: {}", slot, e);
+						GATEWAY_FALLBACK_BLOCKS_TOTAL.with_label_values(&[FALLBACK_BLOCK_FAILED]).inc();
+					}
+					slot = (slot + 1).max(self.state.slot_clock.current_slot() + 1);
+					attributes.retain(|proposal_slot, _| *proposal_slot >= slot);
+				}
+			}
+		}
+	}
+
+	/// Build and submit the block of `slot`, a no-op unless this instance leads, the slot is delegated to this
+	/// gateway and its constraints were posted
+	async fn build_slot(&self, slot: u64, attributes: Option<PayloadAttributesData>) -> Result<()> {
+		if !self.state.is_leader() {
+			return Ok(());
+		}
+		let Some(delegation) = self.state.db.get_delegation(slot)? else {
+			return Ok(());
+		};
+		if !self.state.db.signed_constraints_finalized(slot)? {
+			return Ok(());
+		}
+		let constraints = self
+			.state
+			.db
+			.get_constraints_in_range(slot, slot)?
+			.into_iter()
+			.map(|(_, _, constraint)| constraint)
+			.collect::<Vec<_>>();
+		if constraints.is_empty() {
+			return Ok(());
+		}
+		let attributes =
+			attributes.ok_or_else(|| eyre!("No payload attributes received from the beacon node for slot {}", slot))?;
+
+		self.send_constrained_transactions(&constraints).await;
+		let request = self.build_block(slot, &delegation, &attributes).await?;
+
+		// The execution client may have left out or reordered constrained transactions
+		let transactions = extract_transactions(&request)?;
+		let report = prove_constraints_report(&transactions, &extract_blob_versioned_hashes(&request), &constraints)?;
+		if !report.is_complete() {
+			warn!(
+				"Fallback block for slot {} leaves {} constraint(s) unsatisfied, not submitting it: {:?}",
+				slot,
+				report.unsatisfied.len(),
+				report.unsatisfied
+			);
+			GATEWAY_FALLBACK_BLOCKS_TOTAL.with_label_values(&[FALLBACK_BLOCK_UNSATISFIED]).inc();
+			return Ok(());
+		}
+
+		let block_hash = request.bid_trace().block_hash;
+		let submission = SubmitBlockRequestWithProofs { message: request, proofs: report.into_proofs()? };
+		self.state.constraints_client().post_blocks_with_proofs(&submission).await?;
+
+		info!("Submitted fallback block {} for slot {} with {} transaction(s)", block_hash, slot, transactions.len());
+		GATEWAY_FALLBACK_BLOCKS_TOTAL.with_label_values(&[FALLBACK_BLOCK_SUBMITTED]).inc();
+		Ok(())
+	}
+
+	/// Make sure the execution client knows every constrained transaction before it builds
+	async fn send_constrained_transactions(&self, constraints: &[Constraint]) {
+		for constraint in constraints.iter().filter(|c| c.constraint_type == INCLUSION_CONSTRAINT_TYPE) {
+			let payload = match InclusionPayload::abi_decode(&constraint.payload) {
+				Ok(payload) => payload,
+				Err(e) => {
+					warn!("Skipping undecodable inclusion constraint: {}", e);
+					continue;
+				}
+			};
+			// Transactions the execution client already holds are rejected as known
+			if let Err(e) = self.state.execution_client.send_raw_transaction(&payload.signed_tx).await {
+				debug!("Execution client did not accept constrained transaction: {}", e);
+			}
+		}
+	}
+
+	/// Have the execution client build a payload for the slot and wrap it in a signed bid
+	async fn build_block(
+		&self,
+		slot: u64,
+		delegation: &SignedDelegation,
+		attributes: &PayloadAttributesData,
+	) -> Result<SubmitBlockRequest> {
+		let forkchoice_state = ForkchoiceState {
+			head_block_hash: attributes.parent_block_hash,
+			safe_block_hash: B256::ZERO,
+			finalized_block_hash: B256::ZERO,
+		};
+		let payload_attributes = PayloadAttributes {
+			timestamp: attributes.payload_attributes.timestamp,
+			prev_randao: attributes.payload_attributes.prev_randao,
+			suggested_fee_recipient: attributes.payload_attributes.suggested_fee_recipient,
+			withdrawals: Some(
+				attributes
+					.payload_attributes
+					.withdrawals
+					.iter()
+					.map(|withdrawal| Withdrawal {
+						index: withdrawal.index,
+						validator_index: withdrawal.validator_index,
+						address: withdrawal.address,
+						amount: withdrawal.amount,
+					})
+					.collect(),
+			),
+			parent_beacon_block_root: Some(
+				attributes.payload_attributes.parent_beacon_block_root.unwrap_or(attributes.parent_block_root),
+			),
+		};
+
+		let updated = self.engine.forkchoice_updated_v3(forkchoice_state, payload_attributes).await?;
+		let payload_id = updated.payload_id.ok_or_else(|| {
+			eyre!("Execution client did not start building for slot {}: {:?}", slot, updated.payload_status)
+		})?;
+
+		// Give the payload builder time to pick up the constrained transactions
+		tokio::time::sleep(Duration::from_millis(FALLBACK_BUILD_TIME_MS)).await;
+		let envelope = self.engine.get_payload_v4(payload_id).await?;
+
+		let execution_payload = envelope.envelope_inner.execution_payload;
+		let payload = &execution_payload.payload_inner.payload_inner;
+		let bid_trace = BidTrace {
+			slot,
+			parent_hash: payload.parent_hash,
+			block_hash: payload.block_hash,
+			builder_pubkey: self.builder_signer.public_key(),
+			proposer_pubkey: delegation.message.proposer,
+			proposer_fee_recipient: payload.fee_recipient,
+			gas_limit: payload.gas_limit,
+			gas_used: payload.gas_used,
+			value: envelope.envelope_inner.block_value,
+		};
+		let signature = self.builder_signer.sign_bid_trace(&bid_trace);
+		let execution_requests = ExecutionRequestsV4::try_from(&envelope.execution_requests)
+			.map_err(|e| eyre!("Invalid execution requests: {:?}", e))
+			.wrap_err("Failed to convert the payload's execution requests")?;
+
+		Ok(SubmitBlockRequest::Electra(ElectraSubmitBlockRequest {
+			message: bid_trace,
+			execution_payload,
+			blobs_bundle: envelope.envelope_inner.blobs_bundle,
+			execution_requests,
+			signature,
+		}))
+	}
+}
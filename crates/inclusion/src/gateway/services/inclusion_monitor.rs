@@ -0,0 +1,202 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::B256;
+use alloy::providers::Provider;
+use commitments::types::CommitmentStatus;
+use common::metrics::SlotFulfillment;
+use eyre::{Result, eyre};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+use crate::constants::{INCLUSION_CONSTRAINT_TYPE, INCLUSION_MONITOR_DELAY_MS};
+use crate::gateway::metrics::{
+	COMMITMENT_HONORED, COMMITMENT_MISSED, FEE_COLLECTED, GATEWAY_COMMITMENTS_SETTLED_TOTAL,
+	GATEWAY_SLOT_COMMITTED_GAS, GATEWAY_SLOT_CONSTRAINTS, GATEWAY_SLOT_INCLUSION_RATE, record_fee,
+};
+use crate::gateway::state::GatewayState;
+use crate::gateway::utils::{create_commitment_event, settle_constraint};
+use crate::handlers::default_registry;
+use crate::storage::InclusionDbExt;
+use proposer::storage::DelegationsDbExt;
+use signing::signer::verify_ecdsa_commitment;
+
+/// Inclusion monitor that checks the commitments of each past slot against its canonical block
+pub struct InclusionMonitor {
+	state: Arc<GatewayState>,
+}
+
+impl InclusionMonitor {
+	/// Create a new inclusion monitor
+	pub fn new(state: Arc<GatewayState>) -> Self {
+		Self { state }
+	}
+
+	/// Run the inclusion monitor continuously, settling one slot after the other
+	pub async fn run(&self) -> Result<()> {
+		info!("Starting inclusion monitor - settling commitments against canonical blocks");
+
+		// Slots that ended before startup are not settled
+		let mut slot = self.state.slot_clock.current_slot();
+		loop {
+			// Wait for the end of the slot so its block has reached the execution client
+			let wait_ms = self.state.slot_clock.slot_deadline(slot + 1) + INCLUSION_MONITOR_DELAY_MS;
+			if wait_ms > 0 {
+				sleep(Duration::from_millis(wait_ms as u64)).await;
+			}
+
+			match self.settle_slot(slot).await {
+				Ok(true) => slot += 1,
+				Ok(false) => {
+					// The execution client has not caught up with the slot yet
+					debug!("Block for slot {} not yet available, retrying", slot);
+					sleep(Duration::from_secs(1)).await;
+				}
+				Err(e) => {
+					error!("Failed to settle commitments for slot {}: {}", slot, e);
+					slot += 1;
+				}
+			}
+		}
+	}
+
+	/// Check the commitments of a slot against its canonical block, returns false if the block is not yet available
+	async fn settle_slot(&self, slot: u64) -> Result<bool> {
+		let commitments = self.state.db.get_constraints_in_range(slot, slot)?;
+		if commitments.is_empty() {
+			return Ok(true);
+		}
+
+		let Some(block_tx_hashes) = self.slot_block_tx_hashes(slot).await? else {
+			return Ok(false);
+		};
+		let committer = self.state.db.get_delegation(slot)?.map(|delegation| delegation.message.committer);
+
+		// Bundle and blob commitments span several constraints, each under its own key, and are honored only if
+		// all of them are
+		let registry = default_registry();
+		let mut statuses: HashMap<B256, CommitmentStatus> = HashMap::new();
+		let mut settled = Vec::with_capacity(commitments.len());
+		for (_, request_hash, constraint) in &commitments {
+			let status = match settle_constraint(&registry, constraint, &block_tx_hashes) {
+				Ok(status) => status,
+				Err(e) => {
+					warn!("Failed to settle constraint of commitment {}: {}", request_hash, e);
+					continue;
+				}
+			};
+			let commitment_hash = match self.state.db.get_signed_commitment(request_hash) {
+				Ok(Some(stored)) => {
+					// A settled commitment is only evidence against the gateway if its committer signed it
+					if let Some(committer) = committer
+						&& let Err(e) = verify_ecdsa_commitment(self.state.chain, &stored.commitment, &committer)
+					{
+						warn!("Commitment {} does not verify against committer {}: {}", request_hash, committer, e);
+					}
+					stored.commitment.commitment.request_hash
+				}
+				_ => *request_hash,
+			};
+			statuses
+				.entry(commitment_hash)
+				.and_modify(|current| {
+					if status == CommitmentStatus::Missed {
+						*current = status;
+					}
+				})
+				.or_insert(status);
+			settled.push((commitment_hash, constraint));
+		}
+
+		let mut honored = 0;
+		let mut fulfillment = SlotFulfillment::default();
+		for (commitment_hash, status) in &statuses {
+			// The constraint manager marks commitments constrained once the relay accepted them, read it before the
+			// settled status replaces it
+			let constrained =
+				self.state.db.get_commitment_status(commitment_hash)? == Some(CommitmentStatus::Constrained);
+			fulfillment.made += 1;
+			if constrained {
+				fulfillment.constrained += 1;
+			}
+
+			self.state.db.store_commitment_status(commitment_hash, *status)?;
+			if *status == CommitmentStatus::Missed {
+				warn!("Commitment {} was missed in slot {}", commitment_hash, slot);
+				GATEWAY_COMMITMENTS_SETTLED_TOTAL.with_label_values(&[COMMITMENT_MISSED]).inc();
+				fulfillment.miss(constrained, block_tx_hashes.is_empty());
+			} else {
+				honored += 1;
+				fulfillment.included += 1;
+				GATEWAY_COMMITMENTS_SETTLED_TOTAL.with_label_values(&[COMMITMENT_HONORED]).inc();
+				// The fee of an honored commitment is collected with its block
+				if let Some(payment) = self.state.db.get_payment(commitment_hash)? {
+					record_fee(FEE_COLLECTED, payment.paid_wei);
+				}
+			}
+		}
+		if !statuses.is_empty() {
+			GATEWAY_SLOT_INCLUSION_RATE.set(honored as f64 / statuses.len() as f64);
+		}
+		fulfillment.record();
+		if self.state.db.signed_constraints_finalized(slot)? {
+			GATEWAY_SLOT_CONSTRAINTS.observe(commitments.len() as f64);
+		}
+		GATEWAY_SLOT_COMMITTED_GAS.observe(self.state.db.get_slot_committed_gas(slot)? as f64);
+
+		// Subscribers follow the committed transactions, so only inclusion constraints carry an event
+		for (commitment_hash, constraint) in
+			settled.into_iter().filter(|(_, constraint)| constraint.constraint_type == INCLUSION_CONSTRAINT_TYPE)
+		{
+			match create_commitment_event(commitment_hash, constraint, slot, statuses[&commitment_hash]) {
+				Ok(event) => {
+					let _ = self.state.commitment_events.send(event);
+				}
+				Err(e) => warn!("Failed to create commitment event for {}: {}", commitment_hash, e),
+			}
+		}
+
+		info!(
+			"Settled {} commitments for slot {}: {} constrained, {} honored, failures {:?}",
+			fulfillment.made, slot, fulfillment.constrained, honored, fulfillment.failures
+		);
+		Ok(true)
+	}
+
+	/// Transaction hashes of the canonical block of a slot, empty if the slot has no block
+	/// Returns None while the execution client has not reached the slot
+	async fn slot_block_tx_hashes(&self, slot: u64) -> Result<Option<Vec<B256>>> {
+		let slot_timestamp = self.state.slot_clock.slot_timestamp(slot);
+		let latest = self
+			.state
+			.execution_client
+			.get_block_by_number(BlockNumberOrTag::Latest)
+			.await?
+			.ok_or_else(|| eyre!("No latest block"))?;
+		if latest.header.timestamp < slot_timestamp {
+			return Ok(None);
+		}
+
+		// Every slot holds at most one block, so the slot's block is at or after this number
+		let slot_duration_secs = (self.state.slot_clock.slot_duration_ms() / 1000).max(1);
+		let mut number =
+			latest.header.number.saturating_sub((latest.header.timestamp - slot_timestamp) / slot_duration_secs);
+		loop {
+			let block = self
+				.state
+				.execution_client
+				.get_block_by_number(BlockNumberOrTag::Number(number))
+				.await?
+				.ok_or_else(|| eyre!("Block {} not found", number))?;
+			if block.header.timestamp == slot_timestamp {
+				return Ok(Some(block.transactions.hashes().collect()));
+			}
+			if block.header.timestamp > slot_timestamp {
+				// The slot was missed
+				return Ok(Some(Vec::new()));
+			}
+			number += 1;
+		}
+	}
+}
@@ -0,0 +1,180 @@
+use alloy::consensus::{Transaction, TxEnvelope};
+use alloy::eips::eip2718::Decodable2718;
+use alloy::primitives::{B256, Bytes};
+use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use commitments::rpc::CommitmentsRpcServer;
+use commitments::types::CommitmentRequest;
+use eyre::{Result, WrapErr};
+use jsonrpsee::Extensions;
+use proposer::storage::DelegationsDbExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::constants::{INCLUSION_COMMITMENT_TYPE, LOOKAHEAD_WINDOW_SIZE, MEMPOOL_RECONNECT_DELAY_MS};
+use crate::gateway::config::MempoolIngestionConfig;
+use crate::gateway::metrics::{
+	GATEWAY_MEMPOOL_COMMITMENTS_TOTAL, MEMPOOL_COMMITMENT_ACCEPTED, MEMPOOL_COMMITMENT_REJECTED,
+};
+use crate::gateway::services::rpc::GatewayRpc;
+use crate::gateway::state::GatewayState;
+use crate::gateway::utils;
+use crate::storage::InclusionDbExt;
+use crate::types::InclusionPayload;
+
+/// Mempool ingestor that commits to pending transactions of the execution client matching the configured filters
+///
+/// Each matching transaction goes through the same validation, pricing and quotas as a Commitments RPC request,
+/// targeting the next delegated slot still open for commitments.
+pub struct MempoolIngestor {
+	state: Arc<GatewayState>,
+	rpc: GatewayRpc,
+	config: MempoolIngestionConfig,
+}
+
+impl MempoolIngestor {
+	pub fn new(state: Arc<GatewayState>, config: MempoolIngestionConfig) -> Self {
+		Self { rpc: GatewayRpc::new(Arc::clone(&state)), state, config }
+	}
+
+	/// Subscribe to pending transactions, resubscribing whenever the WebSocket connection drops
+	pub async fn run(&self) -> Result<()> {
+		info!(
+			"Starting mempool ingestion from {}, {} allowlisted recipient(s), min priority fee {} wei",
+			self.config.execution_client_ws_url,
+			self.config.to_allowlist.len(),
+			self.config.min_priority_fee_per_gas_wei
+		);
+
+		loop {
+			if let Err(e) = self.ingest().await {
+				warn!("Pending transaction subscription failed: {}", e);
+			}
+			sleep(Duration::from_millis(MEMPOOL_RECONNECT_DELAY_MS)).await;
+		}
+	}
+
+	async fn ingest(&self) -> Result<()> {
+		let provider = ProviderBuilder::new()
+			.connect_ws(WsConnect::new(self.config.execution_client_ws_url.as_str()))
+			.await
+			.wrap_err("Failed to connect to the execution client WebSocket")?;
+		let mut pending =
+			provider.subscribe_pending_transactions().await.wrap_err("Failed to subscribe to pending transactions")?;
+
+		loop {
+			let tx_hash = pending.recv().await.wrap_err("Pending transaction subscription closed")?;
+			if let Err(e) = self.process_transaction(tx_hash).await {
+				debug!("Skipping pending transaction {}: {}", tx_hash, e);
+			}
+		}
+	}
+
+	async fn process_transaction(&self, tx_hash: B256) -> Result<()> {
+		if !self.state.is_leader() || !self.state.is_accepting_commitments() {
+			return Ok(());
+		}
+		// The transaction may already be mined or dropped
+		let Some(raw_tx) = self.state.execution_client.get_raw_transaction_by_hash(tx_hash).await? else {
+			return Ok(());
+		};
+		let tx = TxEnvelope::decode_2718(&mut raw_tx.as_ref()).wrap_err("Failed to decode transaction")?;
+		if !matches_filters(&tx, &self.config) {
+			return Ok(());
+		}
+		let Some(slot) = self.next_open_slot()? else {
+			debug!("No delegated slot open for commitments, skipping pending transaction {}", tx_hash);
+			return Ok(());
+		};
+
+		let request = commitment_request(slot, raw_tx, &self.config)?;
+		match self.rpc.commitment_request(&Extensions::new(), request).await {
+			Ok(signed_commitment) => {
+				info!(
+					"Committed to pending transaction {} for slot {}, request hash {:?}",
+					tx_hash, slot, signed_commitment.commitment.request_hash
+				);
+				GATEWAY_MEMPOOL_COMMITMENTS_TOTAL.with_label_values(&[MEMPOOL_COMMITMENT_ACCEPTED]).inc();
+			}
+			Err(e) => {
+				// Duplicates, underpriced and full slots are expected, the transaction stays in the mempool
+				debug!("Pending transaction {} not committed for slot {}: {}", tx_hash, slot, e.message());
+				GATEWAY_MEMPOOL_COMMITMENTS_TOTAL.with_label_values(&[MEMPOOL_COMMITMENT_REJECTED]).inc();
+			}
+		}
+		Ok(())
+	}
+
+	/// Earliest slot of the lookahead window delegated to this gateway, before the cutoff and still open to commitments
+	fn next_open_slot(&self) -> Result<Option<u64>> {
+		let current_slot = self.state.slot_clock.current_slot();
+		for slot in current_slot + 1..=current_slot + LOOKAHEAD_WINDOW_SIZE {
+			if utils::validate_commitment_timing(slot, &self.state.slot_clock, self.state.commitment_cutoff_ms).is_err()
+				|| (!self.state.constraint_posting.incremental_updates
+					&& self.state.db.signed_constraints_finalized(slot)?)
+			{
+				continue;
+			}
+			if self.state.db.get_delegation(slot)?.is_some() {
+				return Ok(Some(slot));
+			}
+		}
+		Ok(None)
+	}
+}
+
+/// Whether a pending transaction pays at least the minimum priority fee and goes to an allowlisted recipient
+pub fn matches_filters(tx: &TxEnvelope, config: &MempoolIngestionConfig) -> bool {
+	if tx.priority_fee_or_price() < config.min_priority_fee_per_gas_wei as u128 {
+		return false;
+	}
+	config.to_allowlist.is_empty() || tx.to().is_some_and(|to| config.to_allowlist.contains(&to))
+}
+
+/// Inclusion commitment request for a pending transaction in `slot`
+fn commitment_request(slot: u64, signed_tx: Bytes, config: &MempoolIngestionConfig) -> Result<CommitmentRequest> {
+	let payload = InclusionPayload { slot, signed_tx }.abi_encode()?;
+	Ok(CommitmentRequest { commitment_type: INCLUSION_COMMITMENT_TYPE, payload, slasher: config.slasher })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::consensus::{SignableTransaction, Signed, TxEip1559, TxLegacy};
+	use alloy::primitives::{Address, TxKind};
+	use alloy::signers::{SignerSync, local::PrivateKeySigner};
+
+	fn config(to_allowlist: Vec<Address>) -> MempoolIngestionConfig {
+		MempoolIngestionConfig {
+			execution_client_ws_url: "ws://localhost:8546".parse().unwrap(),
+			slasher: Address::repeat_byte(9),
+			to_allowlist,
+			min_priority_fee_per_gas_wei: 2,
+		}
+	}
+
+	fn eip1559(to: TxKind, max_priority_fee_per_gas: u128) -> TxEnvelope {
+		let tx = TxEip1559 { chain_id: 1, to, max_priority_fee_per_gas, max_fee_per_gas: 100, ..Default::default() };
+		let signature = PrivateKeySigner::random().sign_hash_sync(&tx.signature_hash()).unwrap();
+		TxEnvelope::Eip1559(Signed::new_unhashed(tx, signature))
+	}
+
+	#[test]
+	fn test_filters_on_recipient_and_priority_fee() {
+		let allowed = Address::repeat_byte(1);
+		let allowlisted = config(vec![allowed]);
+
+		assert!(matches_filters(&eip1559(TxKind::Call(allowed), 2), &allowlisted));
+		assert!(!matches_filters(&eip1559(TxKind::Call(allowed), 1), &allowlisted));
+		assert!(!matches_filters(&eip1559(TxKind::Call(Address::repeat_byte(2)), 2), &allowlisted));
+		assert!(!matches_filters(&eip1559(TxKind::Create, 2), &allowlisted));
+
+		// Legacy transactions are filtered on their gas price, any recipient passes an empty allowlist
+		let legacy = TxLegacy { chain_id: Some(1), gas_price: 3, to: TxKind::Create, ..Default::default() };
+		let signature = PrivateKeySigner::random().sign_hash_sync(&legacy.signature_hash()).unwrap();
+		let legacy = TxEnvelope::Legacy(Signed::new_unhashed(legacy, signature));
+		assert!(matches_filters(&legacy, &config(vec![])));
+		assert!(!matches_filters(&legacy, &allowlisted));
+	}
+}
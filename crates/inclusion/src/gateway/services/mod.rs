@@ -1,3 +1,8 @@
+pub mod admin;
+pub mod config_watcher;
 pub mod constraint_manager;
 pub mod delegation_manager;
+pub mod fallback_builder;
+pub mod inclusion_monitor;
+pub mod mempool_ingestor;
 pub mod rpc;
@@ -1,20 +1,45 @@
-use alloy::primitives::B256;
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, B256, Signature, U256};
 use async_trait::async_trait;
+use axum::Router;
 use commitments::server::CommitmentsServerInfo;
-use jsonrpsee::core::RpcResult;
+use common::health::health_router;
+use common::metrics::FABRIC_METRICS_REGISTRY;
+use jsonrpsee::core::{RpcResult, SubscriptionResult};
+use jsonrpsee::{Extensions, PendingSubscriptionSink};
+use prometheus::Registry;
 use reqwest::Url;
 use std::sync::Arc;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-use commitments::rpc::CommitmentsRpcServer;
-use commitments::types::{CommitmentRequest, FeeInfo, Offering, SignedCommitment, SlotInfo, SlotInfoResponse};
-use lookahead::utils::current_slot;
+use commitments::auth::{Authenticator, Caller, QuotaReservation};
+use commitments::error::CommitmentsRpcError;
+use commitments::rpc::{CommitmentsPubSubServer, CommitmentsRpcServer};
+use commitments::server::pipe_commitment_events;
+use commitments::types::{
+	CommitmentEventFilter, CommitmentRequest, CommitmentStatus, FeeInfo, MAX_SENDER_QUERY_SLOTS, SignedCommitment,
+	SlotInfoResponse,
+};
+use constraints::types::{Constraint, SignedDelegation};
 use proposer::storage::DelegationsDbExt;
 
-use crate::constants::{INCLUSION_COMMITMENT_TYPE, LOOKAHEAD_WINDOW_SIZE};
+use crate::constants::{
+	BLOB_COMMITMENT_TYPE, BUNDLE_COMMITMENT_TYPE, EXCLUSION_COMMITMENT_TYPE, INCLUSION_COMMITMENT_TYPE,
+	LOOKAHEAD_WINDOW_SIZE, ORDERING_COMMITMENT_TYPE,
+};
+use crate::gateway::metrics::{
+	FEE_PAID, FEE_QUOTED, GATEWAY_COMMITMENTS_ACCEPTED_TOTAL, GATEWAY_COMMITMENTS_REJECTED_TOTAL,
+	GATEWAY_METRICS_REGISTRY, commitment_type_label, record_fee, rejection_reason,
+};
+use crate::gateway::pricing::{MarketSnapshot, SlotDemand};
 use crate::gateway::state::GatewayState;
-use crate::gateway::utils;
+use crate::gateway::utils::GasReservation;
+use crate::gateway::{pricing, utils};
 use crate::storage::InclusionDbExt;
+use crate::types::{
+	CommitmentTombstone, HandedOverCommitment, InclusionPayload, PaymentRecord, TombstoneReason,
+	create_blob_constraints, create_bundle_constraints, create_exclusion_constraints, create_ordering_constraints,
+};
 
 #[derive(Clone)]
 pub struct GatewayRpc {
@@ -25,88 +50,582 @@ impl GatewayRpc {
 	pub fn new(state: Arc<GatewayState>) -> Self {
 		Self { state }
 	}
-}
 
-impl CommitmentsServerInfo for GatewayRpc {
-	fn server_url(&self) -> Url {
-		self.state.rpc_url.clone()
+	/// Rejects slots outside the lookahead window, past the commitment cutoff or whose constraints were posted
+	/// With incremental updates, posted slots stay open until the cutoff and their constraints are posted again
+	fn check_slot_open(&self, slot: u64) -> Result<(), CommitmentsRpcError> {
+		let current_slot = self.state.slot_clock.current_slot();
+		utils::validate_commitment_window(slot, current_slot).map_err(|e| {
+			if slot <= current_slot {
+				CommitmentsRpcError::SlotElapsed(e.to_string())
+			} else {
+				CommitmentsRpcError::InvalidPayload(e.to_string())
+			}
+		})?;
+
+		utils::validate_commitment_timing(slot, &self.state.slot_clock, self.state.commitment_cutoff_ms)
+			.map_err(|e| CommitmentsRpcError::SlotElapsed(e.to_string()))?;
+		if self.state.constraint_posting.incremental_updates {
+			return Ok(());
+		}
+		let finalized = self
+			.state
+			.db
+			.signed_constraints_finalized(slot)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to check constraints status: {}", e)))?;
+		if finalized {
+			return Err(CommitmentsRpcError::SlotElapsed(format!("Constraints for slot {} were already posted", slot)));
+		}
+		Ok(())
 	}
-	fn metrics_url(&self) -> Url {
-		self.state.metrics_url.clone()
+
+	/// The delegation of the slot to the gateway, NoDelegation if the slot is not delegated to it
+	fn delegation(&self, slot: u64) -> Result<SignedDelegation, CommitmentsRpcError> {
+		self.state
+			.db
+			.get_delegation(slot)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to get delegation: {}", e)))?
+			.ok_or_else(|| CommitmentsRpcError::NoDelegation(format!("No delegation found for slot {}", slot)))
 	}
-}
 
-/// Implementation of the CommitmentsRpcServer for inclusion preconfs
-#[async_trait]
-impl CommitmentsRpcServer for GatewayRpc {
-	async fn commitment_request(&self, request: CommitmentRequest) -> RpcResult<SignedCommitment> {
-		// Parse the inclusion payload
-		let inclusion_payload = utils::validate_commitment_request(&request).map_err(|e| {
-			jsonrpsee::types::error::ErrorObject::owned(
-				-32602, // Invalid params
-				"Invalid commitment request",
-				Some(format!("{}", e)),
+	/// Count a commitment against the authenticated caller's quota for the slot, the reservation is released unless
+	/// committed once the commitment is stored. None if the server or the request is not authenticated
+	fn reserve_quota(&self, ext: &Extensions, slot: u64) -> Result<Option<QuotaReservation<'_>>, CommitmentsRpcError> {
+		self.state
+			.authenticator
+			.as_ref()
+			.zip(ext.get::<Caller>())
+			.map(|(authenticator, caller)| authenticator.reserve_commitment(caller, slot))
+			.transpose()
+			.map_err(|e| CommitmentsRpcError::OverCapacity(e.to_string()))
+	}
+
+	/// Refuse a request the terms of the slot's delegation would keep the gateway from posting, the constraints of a
+	/// signed and paid commitment must reach the relay
+	fn check_delegation_terms(&self, slot: u64, request: &CommitmentRequest) -> Result<(), CommitmentsRpcError> {
+		let signed_delegation = self.delegation(slot)?;
+		let terms = utils::delegation_terms(&signed_delegation)
+			.map_err(|e| CommitmentsRpcError::NoDelegation(format!("{:#}", e)))?;
+		utils::check_delegation_terms(&self.state.db, &terms, slot, self.state.slot_clock.slot_timestamp(slot), request)
+	}
+
+	/// Hand a signed commitment over to the next leader before storing and returning it, so one signed by a leader
+	/// that fails is still posted. `constraints` starts with the constraint stored under the request hash
+	async fn hand_over_commitment(
+		&self,
+		slot: u64,
+		signed_commitment: &SignedCommitment,
+		constraints: Vec<(B256, Constraint)>,
+	) -> Result<(), CommitmentsRpcError> {
+		let Some(leader_election) = &self.state.leader_election else {
+			return Ok(());
+		};
+		let record = HandedOverCommitment {
+			slot,
+			request_hash: signed_commitment.commitment.request_hash,
+			commitment: signed_commitment.clone(),
+			constraints,
+		};
+		let encoded = serde_json::to_vec(&record)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to encode the commitment: {}", e)))?;
+		leader_election
+			.hand_over(slot, &record.request_hash.to_string(), &encoded)
+			.await
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to hand over the commitment: {}", e)))
+	}
+
+	/// Withdraw commitments handed over to the next leader
+	/// They are already withdrawn locally, a failure only lets a new leader post them
+	async fn withdraw_handed_over(&self, slot: u64, request_hashes: &[B256]) {
+		let Some(leader_election) = &self.state.leader_election else {
+			return;
+		};
+		for request_hash in request_hashes {
+			if let Err(e) = leader_election.withdraw_handed_over(slot, &request_hash.to_string()).await {
+				warn!("Failed to withdraw the handed over commitment {}: {}", request_hash, e);
+			}
+		}
+	}
+
+	/// Hold a request to a contended slot until its intake batch closes, Ok right away if the slot has room
+	/// `replaced_gas` is the gas of the commitment the request replaces in the slot
+	/// The caller checks the gas budget again once admitted
+	async fn admit_by_fee(
+		&self,
+		slot: u64,
+		gas: u64,
+		replaced_gas: u64,
+		paid_wei: U256,
+		demand: &SlotDemand,
+	) -> Result<(), CommitmentsRpcError> {
+		let committed_gas = demand.committed_gas.saturating_sub(replaced_gas);
+		match &self.state.intake {
+			Some(intake) if intake.is_contended(committed_gas, gas, demand.gas_budget) => {
+				intake.admit(slot, gas, replaced_gas, paid_wei, demand.gas_budget).await
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Market conditions of the current slot, fetched from the execution client on the slot's first request
+	async fn market(&self) -> Result<MarketSnapshot, CommitmentsRpcError> {
+		self.state
+			.market_cache
+			.get(&self.state.execution_client, self.state.slot_clock.current_slot())
+			.await
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to get market conditions: {}", e)))
+	}
+
+	/// Demand on `slot` against the gas budget of the market snapshot
+	fn slot_demand(&self, slot: u64, market: &MarketSnapshot) -> Result<SlotDemand, CommitmentsRpcError> {
+		let gas_budget = market.gas_budget(self.state.settings().slot_gas_budget_fraction);
+		pricing::slot_demand(&self.state.db, &self.state.slot_clock, slot, gas_budget)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to get slot demand: {}", e)))
+	}
+
+	/// Reserve `gas` in the slot's budget until the returned reservation is dropped, after the commitment is stored
+	/// `released` is the gas of the commitment the request replaces in the slot
+	fn reserve_gas(
+		&self,
+		slot: u64,
+		gas: u64,
+		released: u64,
+		budget: u64,
+	) -> Result<GasReservation<'_>, CommitmentsRpcError> {
+		self.state
+			.gas_reservations
+			.reserve(&self.state.db, slot, gas, released, budget)
+			.map_err(|e| CommitmentsRpcError::OverCapacity(e.to_string()))
+	}
+
+	/// Price, admit, sign and store a commitment paid by the transactions it commits for inclusion: a separate
+	/// payment transaction, the blob transaction of a blob commitment or the transactions of a bundle
+	/// `group_constraint` is stored under the request hash and each member's inclusion constraint under a member
+	/// hash, so the members are accounted for like any other committed transaction. The members together must pay
+	/// the sum of their quotes
+	async fn sign_and_store_paid_commitment(
+		&self,
+		slot: u64,
+		request: &CommitmentRequest,
+		members: &[InclusionPayload],
+		group_constraint: Constraint,
+		member_constraints: Vec<Constraint>,
+	) -> Result<SignedCommitment, CommitmentsRpcError> {
+		// Later members may depend on earlier ones, so only the first one is simulated
+		let market = self.market().await?;
+		for (index, member) in members.iter().enumerate() {
+			utils::validate_transaction_state(
+				member,
+				&members[..index],
+				&self.state.db,
+				&self.state.execution_client,
+				self.state.chain.id().to::<u64>(),
+				market.base_fee_per_gas,
+				self.state.simulate_commitments && index == 0,
 			)
-		})?;
-		debug!("Validated inclusion payload for slot {}", inclusion_payload.slot);
+			.await
+			.map_err(CommitmentsRpcError::from)?;
+		}
 
-		// Validate that there is enough time before the constraints submission time to process the commitment
-		utils::validate_commitment_timing(&inclusion_payload, &self.state.chain).map_err(|e| {
-			jsonrpsee::types::error::ErrorObject::owned(
-				-32602, // Invalid params
-				"Not enough time to satisfy request",
-				Some(format!("{}", e)),
+		// Members follow the same duplicate rules as inclusion commitments but never replace a pending commitment
+		let conflict = |e: eyre::Report| CommitmentsRpcError::Conflict(e.to_string());
+		for member in members {
+			utils::check_duplicate_commitment(&self.state.db, member).map_err(conflict)?;
+			utils::check_excluded_transaction(&self.state.db, member).map_err(conflict)?;
+			if let Some((_, replaced_hash)) =
+				utils::find_replaced_commitment(&self.state.db, member).map_err(conflict)?
+			{
+				return Err(CommitmentsRpcError::Conflict(format!(
+					"Transaction shares sender and nonce with pending commitment {}",
+					replaced_hash
+				)));
+			}
+		}
+		utils::check_constraint_conflicts(&self.state.db, slot, request).map_err(conflict)?;
+		self.check_delegation_terms(slot, request)?;
+
+		let demand = self.slot_demand(slot, &market)?;
+		utils::check_members_gas_budget(&self.state.db, slot, members, demand.gas_budget)
+			.map_err(|e| CommitmentsRpcError::OverCapacity(e.to_string()))?;
+
+		let signed_delegation = self.delegation(slot)?;
+		let committer = signed_delegation.message.committer;
+
+		let mut quoted_wei = U256::ZERO;
+		let mut paid_wei = U256::ZERO;
+		let mut nonces = Vec::with_capacity(members.len());
+		for member in members {
+			let tx_envelope =
+				member.decode_transaction().map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+			let quote = pricing::quote_fee(
+				&tx_envelope,
+				&self.state.execution_client,
+				&market.fees,
+				&demand,
+				self.state.settings().min_tip_per_gas_wei,
 			)
-		})?;
+			.await
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to quote commitment fee: {}", e)))?;
+			quoted_wei += quote.quoted_wei;
+			paid_wei += pricing::paid_fee(&tx_envelope, &quote, committer);
+			nonces.push(tx_envelope.nonce());
+		}
+		pricing::enforce_payment(quoted_wei, paid_wei)
+			.map_err(|e| CommitmentsRpcError::PaymentRequired(e.to_string()))?;
+
+		let gas =
+			members.iter().try_fold(0u64, |gas, member| member.gas().map(|member_gas| gas.saturating_add(member_gas)));
+		let gas = gas.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		self.admit_by_fee(slot, gas, 0, paid_wei, &demand).await?;
+		let _reservation = self.reserve_gas(slot, gas, 0, demand.gas_budget)?;
+
+		let signed_commitment = utils::create_signed_commitment(
+			request,
+			&mut self.state.signer_client.clone(),
+			committer,
+			&self.state.module_signing_id,
+			self.state.chain,
+		)
+		.await
+		.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to create signed commitment: {}", e)))?;
+		let request_hash = signed_commitment.commitment.request_hash;
+		let member_constraints = member_constraints
+			.into_iter()
+			.enumerate()
+			.map(|(index, constraint)| (utils::member_commitment_hash(&request_hash, index), constraint))
+			.collect::<Vec<_>>();
+		let mut constraints = vec![(request_hash, group_constraint.clone())];
+		constraints.extend(member_constraints.iter().cloned());
+		self.hand_over_commitment(slot, &signed_commitment, constraints).await?;
+
+		let store = || -> eyre::Result<()> {
+			self.state.db.store_grouped_commitment(
+				slot,
+				&request_hash,
+				&signed_commitment,
+				&group_constraint,
+				&member_constraints,
+			)?;
+			let mut senders = Vec::new();
+			for ((member, nonce), (member_hash, _)) in members.iter().zip(&nonces).zip(&member_constraints) {
+				let sender = member.sender()?;
+				self.state.db.store_tx_hash_commitment(&member.tx_hash()?, member_hash)?;
+				self.state.db.store_sender_nonce_commitment(&sender, *nonce, member_hash)?;
+				if !senders.contains(&sender) {
+					self.state.db.store_sender_commitment(&sender, slot, &request_hash)?;
+					senders.push(sender);
+				}
+			}
+			self.state.db.store_payment(&PaymentRecord { request_hash, quoted_wei, paid_wei })?;
+			Ok(())
+		};
+		store().map_err(|e| CommitmentsRpcError::Internal(format!("Failed to store paid commitment: {}", e)))?;
+		record_fee(FEE_QUOTED, quoted_wei);
+		record_fee(FEE_PAID, paid_wei);
+		self.state.constraints_stored.send_replace(slot);
+
+		// Subscribers get one event per member, all under the request hash
+		for (_, constraint) in &member_constraints {
+			match utils::create_commitment_event(request_hash, constraint, slot, CommitmentStatus::Accepted) {
+				Ok(event) => {
+					let _ = self.state.commitment_events.send(event);
+				}
+				Err(e) => warn!("Failed to create commitment event: {}", e),
+			}
+		}
+		Ok(signed_commitment)
+	}
+
+	/// Commit to keeping a transaction out of the block for a slot
+	/// Only the sender of the excluded transaction may request it, and its payment transaction is priced and
+	/// committed for inclusion like an inclusion commitment
+	async fn exclusion_commitment_request(
+		&self,
+		ext: &Extensions,
+		request: CommitmentRequest,
+	) -> RpcResult<SignedCommitment> {
+		let request_payload = utils::validate_exclusion_request(&request)
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		let slot = request_payload.slot;
+		self.check_slot_open(slot)?;
+
+		// Anyone could otherwise keep someone else's transaction out of the block
+		let owner = utils::transaction_owner(&self.state.execution_client, request_payload.tx_hash)
+			.await
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		request_payload
+			.authorizer()
+			.and_then(|authorizer| utils::verify_owner_authorization(authorizer, owner))
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+
+		let quota = self.reserve_quota(ext, slot)?;
+
+		let exclusion_payload = request_payload.exclusion();
+		utils::check_exclusion_conflict(&self.state.db, &exclusion_payload)
+			.map_err(|e| CommitmentsRpcError::Conflict(e.to_string()))?;
+
+		let (exclusion_constraint, payment_constraint) = create_exclusion_constraints(&request_payload)
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		let signed_commitment = self
+			.sign_and_store_paid_commitment(
+				slot,
+				&request,
+				&[request_payload.payment()],
+				exclusion_constraint,
+				vec![payment_constraint],
+			)
+			.await?;
+		let request_hash = signed_commitment.commitment.request_hash;
+		self.state
+			.db
+			.store_exclusion_commitment(slot, &exclusion_payload.tx_hash, &request_hash)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to index exclusion commitment: {}", e)))?;
+
+		if let Some(quota) = quota {
+			quota.commit();
+		}
+
+		info!(
+			"Signed exclusion commitment, slot {}, tx hash {:?}, request hash {:?}",
+			slot, exclusion_payload.tx_hash, request_hash
+		);
+		Ok(signed_commitment)
+	}
+
+	/// Commit to placing an inclusion committed transaction no deeper than an index of the slot's block
+	/// Only the sender of the ordered transaction may request it, and its payment transaction is priced and
+	/// committed for inclusion like an inclusion commitment
+	async fn ordering_commitment_request(
+		&self,
+		ext: &Extensions,
+		request: CommitmentRequest,
+	) -> RpcResult<SignedCommitment> {
+		let request_payload = utils::validate_ordering_request(&request)
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		let slot = request_payload.slot;
+		self.check_slot_open(slot)?;
+
+		let quota = self.reserve_quota(ext, slot)?;
+
+		let ordering_payload = request_payload.ordering();
+		utils::check_ordering_conflict(&self.state.db, &ordering_payload)
+			.map_err(|e| CommitmentsRpcError::Conflict(e.to_string()))?;
+
+		// Anyone could otherwise push someone else's transaction out of the top of the block
+		let owner = utils::committed_transaction_owner(&self.state.db, &ordering_payload.tx_hash)
+			.map_err(|e| CommitmentsRpcError::Conflict(e.to_string()))?;
+		request_payload
+			.authorizer()
+			.and_then(|authorizer| utils::verify_owner_authorization(authorizer, owner))
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+
+		let (ordering_constraint, payment_constraint) = create_ordering_constraints(&request_payload)
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		let signed_commitment = self
+			.sign_and_store_paid_commitment(
+				slot,
+				&request,
+				&[request_payload.payment()],
+				ordering_constraint,
+				vec![payment_constraint],
+			)
+			.await?;
+
+		if let Some(quota) = quota {
+			quota.commit();
+		}
+
+		info!(
+			"Signed ordering commitment, slot {}, tx hash {:?}, max index {}, request hash {:?}",
+			slot, ordering_payload.tx_hash, ordering_payload.max_index, signed_commitment.commitment.request_hash
+		);
+		Ok(signed_commitment)
+	}
+
+	/// Commit to including a bundle of transactions contiguously and in order
+	/// The bundle is paid as a whole: its transactions together must pay the sum of their quotes
+	async fn bundle_commitment_request(
+		&self,
+		ext: &Extensions,
+		request: CommitmentRequest,
+	) -> RpcResult<SignedCommitment> {
+		let bundle_payload =
+			utils::validate_bundle_request(&request).map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		let slot = bundle_payload.slot;
+		self.check_slot_open(slot)?;
+
+		let quota = self.reserve_quota(ext, slot)?;
+
+		// Each transaction gets its own inclusion constraint under a member hash derived from the request hash,
+		// so the duplicate, exclusion and ordering checks treat it like a single commitment
+		let members = bundle_payload.inclusion_payloads();
+		let (bundle_constraint, member_constraints) = create_bundle_constraints(&bundle_payload)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to create constraints: {}", e)))?;
+		let signed_commitment = self
+			.sign_and_store_paid_commitment(slot, &request, &members, bundle_constraint, member_constraints)
+			.await?;
+
+		if let Some(quota) = quota {
+			quota.commit();
+		}
+
+		info!(
+			"Signed bundle commitment, slot {}, {} transactions, request hash {:?}",
+			slot,
+			members.len(),
+			signed_commitment.commitment.request_hash
+		);
+		Ok(signed_commitment)
+	}
+
+	/// Commit to including a blob transaction and making its blobs available in the slot's block
+	/// Priced like an inclusion commitment, the blob fee is burned and pays the committer nothing
+	async fn blob_commitment_request(
+		&self,
+		ext: &Extensions,
+		request: CommitmentRequest,
+	) -> RpcResult<SignedCommitment> {
+		let inclusion_payload =
+			utils::validate_blob_request(&request).map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		let slot = inclusion_payload.slot;
+		self.check_slot_open(slot)?;
+
+		let quota = self.reserve_quota(ext, slot)?;
+
+		// The blob transaction pays for the commitment, its inclusion constraint is stored under a member hash so it
+		// is accounted for like any other committed transaction, the blob constraint under the request hash
+		let (blob_constraint, inclusion_constraint) = create_blob_constraints(&inclusion_payload)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to create constraints: {}", e)))?;
+		let signed_commitment = self
+			.sign_and_store_paid_commitment(
+				slot,
+				&request,
+				std::slice::from_ref(&inclusion_payload),
+				blob_constraint,
+				vec![inclusion_constraint],
+			)
+			.await?;
+
+		if let Some(quota) = quota {
+			quota.commit();
+		}
+
+		let blobs = inclusion_payload
+			.decode_transaction()
+			.ok()
+			.and_then(|tx_envelope| tx_envelope.blob_versioned_hashes().map(|hashes| hashes.len()))
+			.unwrap_or_default();
+		info!(
+			"Signed blob commitment, slot {}, {} blobs, request hash {:?}",
+			slot, blobs, signed_commitment.commitment.request_hash
+		);
+		Ok(signed_commitment)
+	}
+
+	/// Commit to including a transaction in the block for a slot, the default commitment type
+	async fn inclusion_commitment_request(
+		&self,
+		ext: &Extensions,
+		request: CommitmentRequest,
+	) -> RpcResult<SignedCommitment> {
+		// Parse the inclusion payload
+		let inclusion_payload = utils::validate_commitment_request(&request)
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		debug!("Validated inclusion payload for slot {}", inclusion_payload.slot);
+
+		// The target slot must be in the lookahead window, before the cutoff and not yet constrained
+		self.check_slot_open(inclusion_payload.slot)?;
+
+		// Reject transactions the execution layer would not include, each failure class has its own error code
+		let market = self.market().await?;
+		utils::validate_transaction_state(
+			&inclusion_payload,
+			&[],
+			&self.state.db,
+			&self.state.execution_client,
+			self.state.chain.id().to::<u64>(),
+			market.base_fee_per_gas,
+			self.state.simulate_commitments,
+		)
+		.await
+		.map_err(CommitmentsRpcError::from)?;
+		debug!("Validated transaction against execution client for slot {}", inclusion_payload.slot);
+
+		// Authenticated callers are limited to a number of commitments per slot
+		let quota = self.reserve_quota(ext, inclusion_payload.slot)?;
+
+		// Never commit to the same transaction twice
+		utils::check_duplicate_commitment(&self.state.db, &inclusion_payload)
+			.map_err(|e| CommitmentsRpcError::Conflict(e.to_string()))?;
+		utils::check_excluded_transaction(&self.state.db, &inclusion_payload)
+			.map_err(|e| CommitmentsRpcError::Conflict(e.to_string()))?;
+
+		// Nor to one no block could satisfy together with the slot's other constraints
+		utils::check_constraint_conflicts(&self.state.db, inclusion_payload.slot, &request)
+			.map_err(|e| CommitmentsRpcError::Conflict(e.to_string()))?;
+
+		// And only to constraints the proposer's terms let the gateway post
+		self.check_delegation_terms(inclusion_payload.slot, &request)?;
+
+		// A request for the same sender and nonce as a pending commitment must outbid it to replace it,
+		// otherwise the two transactions would be mutually exclusive
+		let replaced = utils::find_replaced_commitment(&self.state.db, &inclusion_payload)
+			.map_err(|e| CommitmentsRpcError::Conflict(e.to_string()))?;
+
+		// Committing beyond the slot gas budget would make every commitment in the slot unfulfillable
+		let demand = self.slot_demand(inclusion_payload.slot, &market)?;
+		utils::check_slot_gas_budget(&self.state.db, &inclusion_payload, replaced, demand.gas_budget)
+			.map_err(|e| CommitmentsRpcError::OverCapacity(e.to_string()))?;
 
 		// Get the *singular* valid signed delegation for the slot
 		// Error if none exists for this gateway
-		let signed_delegation = self
-			.state
-			.db
-			.get_delegation(inclusion_payload.slot)
-			.map_err(|e| {
-				jsonrpsee::types::error::ErrorObject::owned(
-					-32602, // Invalid params
-					"No delegation for slot",
-					Some(format!("{}", e)),
-				)
-			})?
-			.ok_or(jsonrpsee::types::error::ErrorObject::owned(
-				-32602, // Invalid params
-				"No delegation for slot",
-				Some(format!("No delegation found for slot {}", inclusion_payload.slot)),
-			))?;
+		let signed_delegation = self.delegation(inclusion_payload.slot)?;
 		debug!("Found signed delegation for slot {}", inclusion_payload.slot);
 
+		// The transaction must pay the committer at least the quoted fee
+		let committer = signed_delegation.message.committer;
+		let tx_envelope =
+			inclusion_payload.decode_transaction().map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		let quote = pricing::quote_fee(
+			&tx_envelope,
+			&self.state.execution_client,
+			&market.fees,
+			&demand,
+			self.state.settings().min_tip_per_gas_wei,
+		)
+		.await
+		.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to quote commitment fee: {}", e)))?;
+		let paid_wei = pricing::paid_fee(&tx_envelope, &quote, committer);
+		pricing::enforce_payment(quote.quoted_wei, paid_wei)
+			.map_err(|e| CommitmentsRpcError::PaymentRequired(e.to_string()))?;
+		debug!("Commitment for slot {} pays {} wei, quoted {} wei", inclusion_payload.slot, paid_wei, quote.quoted_wei);
+
+		// Close to the gas budget, the slot's remaining gas goes to the highest paying requests
+		let replaced_gas = utils::replaced_gas(&self.state.db, inclusion_payload.slot, replaced)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to get replaced commitment gas: {}", e)))?;
+		self.admit_by_fee(inclusion_payload.slot, tx_envelope.gas_limit(), replaced_gas, paid_wei, &demand).await?;
+		let gas = inclusion_payload.gas().map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+		let _reservation = self.reserve_gas(inclusion_payload.slot, gas, replaced_gas, demand.gas_budget)?;
+
 		// Sign the commitment using ECDSA key for "committer" address
 		let signed_commitment = utils::create_signed_commitment(
 			&request,
 			&mut self.state.signer_client.clone(),
-			signed_delegation.message.committer,
+			committer,
 			&self.state.module_signing_id,
 			self.state.chain,
 		)
 		.await
-		.map_err(|e| {
-			jsonrpsee::types::error::ErrorObject::owned(
-				-32603, // Internal error
-				"Failed to create signed commitment",
-				Some(format!("{}", e)),
-			)
-		})?;
+		.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to create signed commitment: {}", e)))?;
 		debug!("Created signed commitment for slot {}", inclusion_payload.slot);
 
 		// Create the corresponding constraint
-		let constraint =
-			utils::create_constraint_from_commitment_request(&request, inclusion_payload.slot).map_err(|e| {
-				jsonrpsee::types::error::ErrorObject::owned(
-					-32603, // Internal error
-					"Failed to create constraint",
-					Some(format!("{}", e)),
-				)
-			})?;
+		let constraint = utils::create_constraint_from_commitment_request(&request, inclusion_payload.slot)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to create constraint: {}", e)))?;
 		debug!("Created constraint for slot {}", inclusion_payload.slot);
+		let request_hash = signed_commitment.commitment.request_hash;
+		self.hand_over_commitment(inclusion_payload.slot, &signed_commitment, vec![(request_hash, constraint.clone())])
+			.await?;
 
 		// Store the commitment and constraint atomically
 		self.state
@@ -117,14 +636,61 @@ impl CommitmentsRpcServer for GatewayRpc {
 				&signed_commitment,
 				&constraint,
 			)
-			.map_err(|e| {
-				jsonrpsee::types::error::ErrorObject::owned(
-					-32603, // Internal error
-					"Failed to store commitment and constraint",
-					Some(format!("{}", e)),
-				)
-			})?;
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to store commitment and constraint: {}", e)))?;
 		debug!("Stored commitment and constraint for slot {}", inclusion_payload.slot);
+		self.state.constraints_stored.send_replace(inclusion_payload.slot);
+
+		// Withdraw the replaced commitment and index the new one by sender, sender nonce and transaction hash
+		let record_replacement = || -> eyre::Result<Vec<B256>> {
+			let mut withdrawn = Vec::new();
+			if let Some((replaced_slot, replaced_hash)) = replaced {
+				let reason = TombstoneReason::Replaced { replaced_by: request_hash };
+				withdrawn =
+					utils::withdraw_dependent_orderings(&self.state.db, &replaced_hash, replaced_slot, reason.clone())?;
+				self.state.db.tombstone_commitment(&CommitmentTombstone {
+					request_hash: replaced_hash,
+					slot: replaced_slot,
+					reason,
+				})?;
+				withdrawn.push(replaced_hash);
+				info!("Commitment {:?} replaced by {:?}", replaced_hash, request_hash);
+			}
+			let sender = inclusion_payload.sender()?;
+			self.state.db.store_tx_hash_commitment(&inclusion_payload.tx_hash()?, &request_hash)?;
+			self.state.db.store_sender_commitment(&sender, inclusion_payload.slot, &request_hash)?;
+			self.state.db.store_sender_nonce_commitment(&sender, tx_envelope.nonce(), &request_hash)?;
+			Ok(withdrawn)
+		};
+		let withdrawn = record_replacement()
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to record commitment replacement: {}", e)))?;
+		if let Some((replaced_slot, _)) = replaced {
+			self.withdraw_handed_over(replaced_slot, &withdrawn).await;
+		}
+
+		// Keep the quoted and paid amounts for accounting
+		self.state
+			.db
+			.store_payment(&PaymentRecord { request_hash, quoted_wei: quote.quoted_wei, paid_wei })
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to record commitment payment: {}", e)))?;
+		record_fee(FEE_QUOTED, quote.quoted_wei);
+		record_fee(FEE_PAID, paid_wei);
+
+		// Notify subscribers, a send error only means nobody is subscribed
+		match utils::create_commitment_event(
+			signed_commitment.commitment.request_hash,
+			&constraint,
+			inclusion_payload.slot,
+			CommitmentStatus::Accepted,
+		) {
+			Ok(event) => {
+				let _ = self.state.commitment_events.send(event);
+			}
+			Err(e) => warn!("Failed to create commitment event: {}", e),
+		}
+
+		if let Some(quota) = quota {
+			quota.commit();
+		}
 
 		info!(
 			"Signed commitment, slot {}, request hash {:?}",
@@ -134,69 +700,269 @@ impl CommitmentsRpcServer for GatewayRpc {
 		// Return the signed commitment
 		Ok(signed_commitment)
 	}
+}
+
+impl CommitmentsServerInfo for GatewayRpc {
+	fn server_url(&self) -> Url {
+		self.state.rpc_url.clone()
+	}
+	fn metrics_url(&self) -> Url {
+		self.state.metrics_url.clone()
+	}
+	fn authenticator(&self) -> Option<Arc<Authenticator>> {
+		self.state.authenticator.clone()
+	}
+	fn metrics_registries(&self) -> Vec<Registry> {
+		vec![GATEWAY_METRICS_REGISTRY.clone(), FABRIC_METRICS_REGISTRY.clone()]
+	}
+	fn metrics_server_routes(&self) -> Router {
+		health_router(Arc::clone(&self.state))
+	}
+}
+
+/// Implementation of the CommitmentsRpcServer for inclusion, exclusion, ordering, bundle and blob preconfs
+#[async_trait]
+impl CommitmentsRpcServer for GatewayRpc {
+	async fn commitment_request(&self, ext: &Extensions, request: CommitmentRequest) -> RpcResult<SignedCommitment> {
+		let commitment_type = request.commitment_type;
+		let result: RpcResult<SignedCommitment> = async {
+			// Standby instances of a highly available gateway leave commitments to the leader, and a paused gateway
+			// accepts none
+			if !self.state.is_leader() {
+				return Err(CommitmentsRpcError::Standby("This gateway instance is not the leader".to_string()).into());
+			}
+			if !self.state.is_accepting_commitments() {
+				return Err(CommitmentsRpcError::Standby("Commitment requests are paused".to_string()).into());
+			}
+
+			match commitment_type {
+				EXCLUSION_COMMITMENT_TYPE => self.exclusion_commitment_request(ext, request).await,
+				ORDERING_COMMITMENT_TYPE => self.ordering_commitment_request(ext, request).await,
+				BUNDLE_COMMITMENT_TYPE => self.bundle_commitment_request(ext, request).await,
+				BLOB_COMMITMENT_TYPE => self.blob_commitment_request(ext, request).await,
+				_ => self.inclusion_commitment_request(ext, request).await,
+			}
+		}
+		.await;
+
+		match &result {
+			Ok(_) => {
+				GATEWAY_COMMITMENTS_ACCEPTED_TOTAL.with_label_values(&[commitment_type_label(commitment_type)]).inc()
+			}
+			Err(e) => GATEWAY_COMMITMENTS_REJECTED_TOTAL.with_label_values(&[rejection_reason(e.code())]).inc(),
+		}
+		result
+	}
 
 	/// Query a previously created SignedCommitment
 	async fn commitment_result(&self, request_hash: B256) -> RpcResult<SignedCommitment> {
+		// Withdrawn commitments are reported as such rather than as active commitments
+		if let Ok(Some(tombstone)) = self.state.db.get_tombstone(&request_hash) {
+			return Err(CommitmentsRpcError::NotFound(format!(
+				"Commitment {} was withdrawn: {:?}",
+				request_hash, tombstone.reason
+			))
+			.into());
+		}
+
 		match self.state.db.get_signed_commitment(&request_hash) {
 			Ok(Some(signed_commitment)) => Ok(signed_commitment.commitment),
 			Ok(None) => {
-				Err(jsonrpsee::types::error::ErrorObject::owned(
-					-32602, // Invalid params
-					"Commitment not found",
-					Some(format!("No commitment found for request hash: {}", request_hash)),
-				))
+				Err(CommitmentsRpcError::NotFound(format!("No commitment found for request hash: {}", request_hash))
+					.into())
 			}
 			Err(e) => {
-				Err(jsonrpsee::types::error::ErrorObject::owned(
-					-32603, // Internal error
-					"Failed to get commitment and constraint",
-					Some(format!("{}", e)),
-				))
+				Err(CommitmentsRpcError::Internal(format!("Failed to get commitment and constraint: {}", e)).into())
 			}
 		}
 	}
 
+	/// Cancel a pending commitment before its constraint is posted
+	async fn commitment_cancel(&self, request_hash: B256, signature: Signature) -> RpcResult<()> {
+		fn internal_error(e: impl std::fmt::Display) -> CommitmentsRpcError {
+			CommitmentsRpcError::Internal(format!("Failed to cancel commitment: {}", e))
+		}
+
+		let stored = self.state.db.get_signed_commitment(&request_hash).map_err(internal_error)?.ok_or_else(|| {
+			CommitmentsRpcError::NotFound(format!("No commitment found for request hash: {}", request_hash))
+		})?;
+
+		if let Some(tombstone) = self.state.db.get_tombstone(&request_hash).map_err(internal_error)? {
+			return Err(CommitmentsRpcError::NotFound(format!(
+				"Commitment {} was already withdrawn: {:?}",
+				request_hash, tombstone.reason
+			))
+			.into());
+		}
+
+		if stored.commitment.commitment.commitment_type != INCLUSION_COMMITMENT_TYPE {
+			return Err(
+				CommitmentsRpcError::InvalidPayload("Only inclusion commitments can be cancelled".to_string()).into()
+			);
+		}
+
+		// Only the sender of the committed transaction may cancel it
+		let inclusion_payload = InclusionPayload::abi_decode(&stored.constraint.payload).map_err(internal_error)?;
+		let sender = inclusion_payload.sender().map_err(internal_error)?;
+		utils::verify_cancel_signature(&request_hash, inclusion_payload.slot, &signature, &sender)
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(format!("Invalid cancellation signature: {}", e)))?;
+
+		// The constraint must not have been posted, or be about to be
+		let finalized = self.state.db.signed_constraints_finalized(inclusion_payload.slot).map_err(internal_error)?;
+		if finalized {
+			return Err(CommitmentsRpcError::SlotElapsed(format!(
+				"Constraints for slot {} were already posted",
+				inclusion_payload.slot
+			))
+			.into());
+		}
+		utils::validate_commitment_timing(
+			inclusion_payload.slot,
+			&self.state.slot_clock,
+			self.state.commitment_cutoff_ms,
+		)
+		.map_err(|e| CommitmentsRpcError::SlotElapsed(format!("Too late to cancel commitment: {}", e)))?;
+
+		let mut withdrawn = utils::withdraw_dependent_orderings(
+			&self.state.db,
+			&request_hash,
+			inclusion_payload.slot,
+			TombstoneReason::Cancelled,
+		)
+		.map_err(internal_error)?;
+		self.state
+			.db
+			.tombstone_commitment(&CommitmentTombstone {
+				request_hash,
+				slot: inclusion_payload.slot,
+				reason: TombstoneReason::Cancelled,
+			})
+			.map_err(internal_error)?;
+		withdrawn.push(request_hash);
+		self.withdraw_handed_over(inclusion_payload.slot, &withdrawn).await;
+
+		info!("Cancelled commitment, slot {}, request hash {:?}", inclusion_payload.slot, request_hash);
+		Ok(())
+	}
+
+	/// Query the active commitments held by a transaction sender, only the sender may query them
+	async fn commitments_by_sender(
+		&self,
+		sender: Address,
+		start_slot: u64,
+		end_slot: u64,
+		expiry: u64,
+		signature: Signature,
+	) -> RpcResult<Vec<SignedCommitment>> {
+		if end_slot.saturating_sub(start_slot) >= MAX_SENDER_QUERY_SLOTS {
+			return Err(CommitmentsRpcError::InvalidPayload(format!(
+				"Slot range {}..={} spans more than {} slots",
+				start_slot, end_slot, MAX_SENDER_QUERY_SLOTS
+			))
+			.into());
+		}
+		let now = self.state.slot_clock.now_ms() / 1000;
+		utils::verify_sender_query_signature(&sender, start_slot, end_slot, expiry, &signature, now)
+			.map_err(|e| CommitmentsRpcError::InvalidPayload(format!("Invalid query signature: {}", e)))?;
+
+		let commitments = || -> eyre::Result<Vec<SignedCommitment>> {
+			let mut commitments = Vec::new();
+			for (_, request_hash) in self.state.db.get_sender_commitments(&sender, start_slot, end_slot)? {
+				// Withdrawn commitments are no longer held by the sender
+				if self.state.db.get_tombstone(&request_hash)?.is_some() {
+					continue;
+				}
+				if let Some(stored) = self.state.db.get_signed_commitment(&request_hash)? {
+					commitments.push(stored.commitment);
+				}
+			}
+			Ok(commitments)
+		};
+
+		let commitments = commitments()
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to get commitments by sender: {}", e)))?;
+		Ok(commitments)
+	}
+
 	/// Query slots information.
 	async fn slots(&self) -> RpcResult<SlotInfoResponse> {
 		// Get current slot
-		let current_slot = current_slot(&self.state.chain);
+		let current_slot = self.state.slot_clock.current_slot();
 		debug!("Current slot: {}", current_slot);
 
 		// Query slots this gateway is delegated to
-		let delegated_slots =
-			self.state.db.get_delegations_in_range(current_slot, current_slot + LOOKAHEAD_WINDOW_SIZE).map_err(
-				|e| {
-					jsonrpsee::types::error::ErrorObject::owned(
-						-32603, // Internal error
-						"Failed to get delegated slots",
-						Some(format!("{}", e)),
-					)
-				},
-			)?;
+		let delegated_slots = self
+			.state
+			.db
+			.get_delegations_in_range(current_slot, current_slot + LOOKAHEAD_WINDOW_SIZE)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to get delegated slots: {}", e)))?;
 
-		// Build slot info for each delegated slot
-		let mut slots = Vec::new();
+		// Offer inclusion commitments on each delegated slot
+		let mut response = utils::build_slot_info_response(self.state.chain.id().to::<u64>(), &delegated_slots);
 
-		// Create offering with chain ID and commitment type
-		let offering =
-			Offering { chain_id: self.state.chain.id().to::<u64>(), commitment_types: vec![INCLUSION_COMMITMENT_TYPE] };
+		// Only offer what the terms of each delegation let the gateway post, slots whose terms do not decode or take
+		// no more commitments are left out
+		let mut open_slots = Vec::with_capacity(response.slots.len());
+		for (mut slot_info, (slot, signed_delegation)) in response.slots.into_iter().zip(&delegated_slots) {
+			let terms = match utils::delegation_terms(signed_delegation) {
+				Ok(terms) => terms,
+				Err(e) => {
+					warn!("Not offering slot {}: {:#}", slot, e);
+					continue;
+				}
+			};
+			let active = self
+				.state
+				.db
+				.get_constraints_in_range(*slot, *slot)
+				.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to get constraints: {}", e)))?
+				.len();
+			if utils::apply_delegation_terms(
+				&mut slot_info,
+				&terms,
+				self.state.slot_clock.slot_timestamp(*slot),
+				active,
+			) {
+				open_slots.push(slot_info);
+			}
+		}
+		response.slots = open_slots;
 
-		for (slot, _) in delegated_slots {
-			slots.push(SlotInfo { slot, offerings: vec![offering.clone()] });
+		// Report the remaining gas budget per slot when the block gas limit is available
+		match self.market().await {
+			Ok(market) => {
+				let budget = market.gas_budget(self.state.settings().slot_gas_budget_fraction);
+				for slot_info in response.slots.iter_mut() {
+					let committed =
+						self.state.db.get_slot_committed_gas(slot_info.slot).map_err(|e| {
+							CommitmentsRpcError::Internal(format!("Failed to get committed gas: {}", e))
+						})?;
+					slot_info.remaining_gas = Some(budget.saturating_sub(committed));
+				}
+			}
+			Err(e) => warn!("Failed to get slot gas budget: {}", e),
 		}
 
-		Ok(SlotInfoResponse { slots })
+		Ok(response)
 	}
 
 	/// Query current fee information.
 	async fn fee(&self, request: CommitmentRequest) -> RpcResult<FeeInfo> {
-		let fee_info = utils::calculate_fee_info(&request, &self.state.execution_client).await.map_err(|e| {
-			jsonrpsee::types::error::ErrorObject::owned(
-				-32603, // Internal error
-				"Failed to calculate fee info",
-				Some(format!("{}", e)),
-			)
-		})?;
+		let fee_info = pricing::calculate_fee_info(&request, &self.state)
+			.await
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to calculate fee info: {}", e)))?;
 		Ok(fee_info)
 	}
 }
+
+/// WebSocket subscriptions to commitment status transitions
+#[async_trait]
+impl CommitmentsPubSubServer for GatewayRpc {
+	async fn subscribe_commitments(
+		&self,
+		pending: PendingSubscriptionSink,
+		filter: CommitmentEventFilter,
+	) -> SubscriptionResult {
+		pipe_commitment_events(pending, filter, self.state.commitment_events.subscribe()).await
+	}
+}
@@ -1,17 +1,38 @@
 use alloy::{
-	hex,
 	network::Ethereum,
 	primitives::B256,
 	providers::{DynProvider, Provider, ProviderBuilder},
 	rpc::types::beacon::BlsPublicKey,
 };
-use commit_boost::prelude::{Chain, StartCommitModuleConfig, commit::client::SignerClient};
+use commit_boost::prelude::{Chain, StartCommitModuleConfig};
 
-use common::{storage::DatabaseContext, utils::decode_pubkey};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use commitments::auth::Authenticator;
+use commitments::types::CommitmentEvent;
+use common::{http::HttpClientConfig, leader::LeaderElection, storage::DatabaseContext, utils::decode_pubkey};
 use constraints::client::HttpConstraintsClient;
+use lookahead::{
+	beacon_client::{BeaconApiClient, ReqwestClient},
+	slot_clock::SlotClock,
+	types::BeaconApiConfig,
+};
 use reqwest::Url;
+use signing::nonce::NonceManager;
+use signing::signer::SigningClient;
+use tokio::sync::{broadcast, watch};
+use tracing::warn;
+
+use eyre::{Result, WrapErr};
 
-use crate::gateway::config::GatewayConfig;
+use crate::constants::{COMMITMENT_EVENTS_CHANNEL_SIZE, CONSTRAINT_TRIGGER_OFFSET_MS};
+use crate::gateway::config::{ConstraintPostingConfig, DelegationOfferConfig, GatewayConfig};
+use crate::gateway::intake::CommitmentIntake;
+use crate::gateway::pricing::MarketCache;
+use crate::gateway::utils::GasReservations;
+use crate::handlers::default_registry;
+use crate::storage::InclusionDbExt;
 
 /// Server state that provides access to shared resources for gateway operations
 #[derive(Clone)]
@@ -22,78 +43,294 @@ pub struct GatewayState {
 	pub metrics_url: Url,
 	/// Storage
 	pub db: DatabaseContext,
-	/// Signer client for calling the signer API
-	pub signer_client: SignerClient,
-	/// Constraints client for sending constraints to the relay
-	pub constraints_client: HttpConstraintsClient,
+	/// Signer client for calling the signer API, or the local signer
+	pub signer_client: SigningClient,
+	/// Allocates the nonces of signed constraints
+	pub nonce_manager: Arc<NonceManager>,
+	/// Constraints client for sending constraints to the relay, replaced when the relay endpoint is rotated
+	relay_client: Arc<RwLock<HttpConstraintsClient>>,
+	/// Timeout and connection pool of the relay client, kept when the relay endpoint is rotated
+	pub relay_http: HttpClientConfig,
 	/// Execution client for pricing
 	pub execution_client: DynProvider<Ethereum>,
 	/// Gateway public key for signing constraints
 	pub gateway_public_key: BlsPublicKey,
-	/// Constraints receivers whitelist
-	pub constraints_receivers: Vec<BlsPublicKey>,
+	/// Every key delegations are accepted to, `gateway_public_key` first
+	pub gateway_public_keys: Vec<BlsPublicKey>,
 	/// Module signing ID for inclusion preconfs
 	pub module_signing_id: B256,
 	/// Chain ID
 	pub chain: Chain,
+	/// Beacon client for the slot clock and head events, if a beacon node is configured
+	pub beacon_client: Option<BeaconApiClient<ReqwestClient>>,
+	/// Slot clock, synced against the beacon node if one is configured
+	pub slot_clock: Arc<SlotClock>,
 	/// How often to check for new delegations
 	pub delegation_check_interval_seconds: u64,
+	/// Time of the last completed delegation check, in ms, 0 before the first one
+	pub delegations_checked_ms: Arc<AtomicU64>,
+	/// Settings reloaded from the config file while the gateway runs
+	settings: Arc<RwLock<GatewaySettings>>,
+	/// Whether to simulate committed transactions with eth_call
+	pub simulate_commitments: bool,
+	/// Milliseconds before a slot after which commitment requests are rejected
+	pub commitment_cutoff_ms: i64,
+	/// Retries and incremental updates of posted constraints
+	pub constraint_posting: ConstraintPostingConfig,
+	/// Authenticator for the Commitments RPC server, if auth is configured
+	pub authenticator: Option<Arc<Authenticator>>,
+	/// Fee-prioritized admission to slots close to their gas budget, if configured
+	pub intake: Option<Arc<CommitmentIntake>>,
+	/// Gas of the commitments being signed, counted against their slot's budget until stored
+	pub gas_reservations: Arc<GasReservations>,
+	/// Market conditions of the current slot, shared by its requests
+	pub market_cache: Arc<MarketCache>,
+	/// Commitment status transitions pushed to WebSocket subscribers
+	pub commitment_events: broadcast::Sender<CommitmentEvent>,
+	/// Slot of the latest stored commitment or receiver change, wakes the constraint manager
+	pub constraints_stored: watch::Sender<u64>,
+	/// Leadership among the instances of a highly available gateway, if configured
+	pub leader_election: Option<Arc<LeaderElection>>,
+	/// Whether commitment requests are accepted, cleared while paused through the admin API
+	pub accepting_commitments: Arc<AtomicBool>,
+	/// Port of the admin API, if enabled
+	pub admin_port: Option<u16>,
+	/// Bearer token required to call the admin API
+	pub admin_api_key: Option<String>,
+	/// Delegation offers published for undelegated slots, with every offered constraint type listed, if configured
+	pub delegation_offer: Option<DelegationOfferConfig>,
 }
 
 impl GatewayState {
-	pub fn new(db: DatabaseContext, config: StartCommitModuleConfig<GatewayConfig>) -> Self {
+	pub fn new(db: DatabaseContext, config: StartCommitModuleConfig<GatewayConfig>) -> Result<Self> {
+		// Pause and relay rotation made through the admin API outlive a restart
+		let overrides = db.get_admin_overrides().wrap_err("Failed to read the admin overrides")?;
+		if overrides.paused {
+			warn!("Gateway paused through the admin API, not accepting commitment requests until resumed");
+		}
+
 		// Create constraints client
-		let constraints_client = HttpConstraintsClient::new(
-			config.extra.relay_host,
-			config.extra.relay_port,
-			config.extra.relay_api_key.clone(),
-		);
+		let relay_http = config.extra.relay_http.clone();
+		let constraints_client = match overrides.relay {
+			Some(relay) => {
+				warn!(
+					"Using relay {}:{} set through the admin API instead of the configured one",
+					relay.host, relay.port
+				);
+				HttpConstraintsClient::with_http_config(relay.host, relay.port, relay.api_key, &relay_http)
+			}
+			None => HttpConstraintsClient::with_http_config(
+				config.extra.relay_host,
+				config.extra.relay_port,
+				config.extra.relay_api_key.clone(),
+				&relay_http,
+			),
+		}
+		.wrap_err("Failed to create the relay client")?;
 
 		let rpc_url = format!("http://{}:{}", config.extra.rpc_host, config.extra.rpc_port)
 			.parse::<Url>()
-			.expect("Failed to parse RPC address");
+			.wrap_err_with(|| format!("Invalid RPC address {}:{}", config.extra.rpc_host, config.extra.rpc_port))?;
 
 		let metrics_url = format!("http://{}:{}", config.extra.metrics_host, config.extra.metrics_port)
 			.parse::<Url>()
-			.expect("Failed to parse metrics address");
+			.wrap_err_with(|| {
+				format!("Invalid metrics address {}:{}", config.extra.metrics_host, config.extra.metrics_port)
+			})?;
 
 		// Create execution client
 		let execution_client_url = Url::parse(
 			format!("http://{}:{}", config.extra.execution_client_host, config.extra.execution_client_port).as_str(),
 		)
-		.expect("Failed to parse execution client URL from config");
+		.wrap_err_with(|| {
+			format!(
+				"Invalid execution client address {}:{}",
+				config.extra.execution_client_host, config.extra.execution_client_port
+			)
+		})?;
 		let execution_client = ProviderBuilder::new().network::<Ethereum>().connect_http(execution_client_url).erased();
 
 		// Parse config fields into their respective types
-		let signer_client = config.signer_client.clone();
+		let signer_client =
+			SigningClient::new(config.signer_client.clone(), config.extra.local_signer.as_ref(), config.chain)
+				.wrap_err("Failed to load local signer keys")?;
+		let nonce_manager = Arc::new(NonceManager::new(db.clone()));
 
-		let gateway_public_key =
-			decode_pubkey(config.extra.gateway_public_key.as_str()).expect("Failed to decode gateway public key");
+		let gateway_public_key = decode_pubkey(config.extra.gateway_public_key.as_str())
+			.wrap_err_with(|| format!("Invalid gateway_public_key {}", config.extra.gateway_public_key))?;
+		let mut gateway_public_keys = vec![gateway_public_key.clone()];
+		for key in &config.extra.additional_public_keys {
+			let key =
+				decode_pubkey(key.as_str()).wrap_err_with(|| format!("Invalid additional_public_keys {}", key))?;
+			if !gateway_public_keys.contains(&key) {
+				gateway_public_keys.push(key);
+			}
+		}
 
-		let constraints_receivers = config
-			.extra
-			.constraints_receivers
-			.iter()
-			.map(|receiver| decode_pubkey(receiver.as_str()).expect("Failed to decode constraints receiver"))
-			.collect::<Vec<_>>();
+		let settings = GatewaySettings::from_config(&config.extra).wrap_err("Failed to load gateway settings")?;
 
 		let chain = config.chain;
-		let module_signing_id = B256::from_slice(
-			&hex::decode(config.extra.module_signing_id.as_str()).expect("Failed to decode module signing id"),
-		);
+		let beacon_client = config
+			.extra
+			.beacon_api_url
+			.clone()
+			.map(|beacon_api_url| {
+				BeaconApiClient::with_default_client(BeaconApiConfig {
+					primary_endpoint: beacon_api_url,
+					fallback_endpoints: vec![],
+					request_timeout_secs: 30,
+					genesis_time: chain.genesis_time_sec(),
+				})
+				.wrap_err("Failed to create beacon client")
+			})
+			.transpose()?;
+		let mut slot_clock = SlotClock::new(&chain);
+		if let Some(beacon_client) = &beacon_client {
+			slot_clock = slot_clock.with_beacon_client(beacon_client.clone());
+		}
+		let slot_clock = Arc::new(slot_clock);
+		let module_signing_id = config
+			.extra
+			.module_signing_id
+			.parse::<B256>()
+			.wrap_err_with(|| format!("Invalid module_signing_id {}", config.extra.module_signing_id))?;
 		let delegation_check_interval_seconds = config.extra.delegation_check_interval_seconds;
-		Self {
+		let simulate_commitments = config.extra.simulate_commitments;
+		// Never accept commitments after their constraints have been posted for the last time
+		let constraint_posting = config.extra.constraint_posting.clone();
+		let last_post_offset_ms = match constraint_posting.incremental_updates {
+			true => constraint_posting.hard_cutoff_ms,
+			false => CONSTRAINT_TRIGGER_OFFSET_MS,
+		};
+		let commitment_cutoff_ms = config.extra.commitment_cutoff_ms.max(last_post_offset_ms);
+		let authenticator = config.extra.auth.clone().map(|auth| Arc::new(Authenticator::new(auth)));
+		let intake = config.extra.intake.clone().map(|intake| Arc::new(CommitmentIntake::new(intake, db.clone())));
+		let (commitment_events, _) = broadcast::channel(COMMITMENT_EVENTS_CHANNEL_SIZE);
+		let (constraints_stored, _) = watch::channel(0);
+		let leader_election = config
+			.extra
+			.leader_election
+			.as_ref()
+			.map(|leader| LeaderElection::new(leader).map(Arc::new))
+			.transpose()
+			.wrap_err("Failed to set up the leader election")?;
+		let delegation_offer = config.extra.delegation_offer.clone().map(|mut offer| {
+			if offer.constraint_types.is_empty() {
+				offer.constraint_types = default_registry().constraint_types();
+			}
+			offer
+		});
+		Ok(Self {
 			db,
 			signer_client,
-			constraints_client,
+			nonce_manager,
+			relay_client: Arc::new(RwLock::new(constraints_client)),
+			relay_http,
 			execution_client,
 			gateway_public_key,
-			constraints_receivers,
+			gateway_public_keys,
 			chain,
+			beacon_client,
+			slot_clock,
 			module_signing_id,
 			delegation_check_interval_seconds,
+			delegations_checked_ms: Arc::new(AtomicU64::new(0)),
+			settings: Arc::new(RwLock::new(settings)),
+			simulate_commitments,
+			commitment_cutoff_ms,
+			constraint_posting,
+			authenticator,
+			intake,
+			gas_reservations: Arc::new(GasReservations::default()),
+			market_cache: Arc::new(MarketCache::default()),
 			rpc_url,
 			metrics_url,
+			commitment_events,
+			constraints_stored,
+			leader_election,
+			accepting_commitments: Arc::new(AtomicBool::new(!overrides.paused)),
+			admin_port: config.extra.admin_port,
+			admin_api_key: config.extra.admin_api_key.clone(),
+			delegation_offer,
+		})
+	}
+
+	/// Current reloadable settings
+	pub fn settings(&self) -> GatewaySettings {
+		self.settings.read().expect("gateway settings lock poisoned").clone()
+	}
+
+	/// Apply settings reloaded from the config file, from the next request or post on
+	pub fn update_settings(&self, settings: GatewaySettings) {
+		*self.settings.write().expect("gateway settings lock poisoned") = settings;
+	}
+
+	/// Receivers of the constraints of `slot`, its override if one is set, else the configured and added receivers
+	pub fn constraints_receivers(&self, slot: u64) -> Result<Vec<BlsPublicKey>> {
+		if let Some(receivers) = self.db.get_slot_receivers(slot)? {
+			return Ok(receivers);
 		}
+		let mut receivers = self.settings().constraints_receivers;
+		for receiver in self.db.get_constraints_receivers()? {
+			if !receivers.contains(&receiver) {
+				receivers.push(receiver);
+			}
+		}
+		Ok(receivers)
+	}
+
+	/// Client of the current relay endpoint
+	pub fn constraints_client(&self) -> HttpConstraintsClient {
+		self.relay_client.read().expect("relay client lock poisoned").clone()
+	}
+
+	/// Send constraints, delegation lookups and blocks to another relay from now on
+	pub fn rotate_relay(&self, constraints_client: HttpConstraintsClient) {
+		*self.relay_client.write().expect("relay client lock poisoned") = constraints_client;
+	}
+
+	/// Whether commitment requests are accepted, false while paused
+	pub fn is_accepting_commitments(&self) -> bool {
+		self.accepting_commitments.load(Ordering::SeqCst)
+	}
+
+	/// Whether delegations to `public_key` are accepted by this gateway
+	pub fn is_gateway_key(&self, public_key: &BlsPublicKey) -> bool {
+		self.gateway_public_keys.contains(public_key)
+	}
+
+	/// Whether this instance signs commitments and posts constraints, always true without leader election
+	pub fn is_leader(&self) -> bool {
+		self.leader_election.as_ref().is_none_or(|leader| leader.is_leader())
+	}
+}
+
+/// Gateway settings that can change without a restart
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewaySettings {
+	/// Constraints receivers whitelist
+	pub constraints_receivers: Vec<BlsPublicKey>,
+	/// Minimum tip per gas in wei charged for a commitment
+	pub min_tip_per_gas_wei: u64,
+	/// Fraction of the block gas limit committed per slot before rejecting requests
+	pub slot_gas_budget_fraction: f64,
+}
+
+impl GatewaySettings {
+	pub fn from_config(config: &GatewayConfig) -> Result<Self> {
+		let constraints_receivers = config
+			.constraints_receivers
+			.iter()
+			.map(|receiver| {
+				decode_pubkey(receiver.as_str())
+					.wrap_err_with(|| format!("Failed to decode constraints receiver {}", receiver))
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Self {
+			constraints_receivers,
+			min_tip_per_gas_wei: config.min_tip_per_gas_wei,
+			slot_gas_budget_fraction: config.slot_gas_budget_fraction,
+		})
 	}
 }
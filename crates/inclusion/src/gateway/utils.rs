@@ -1,23 +1,52 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
 use eyre::{Result, WrapErr};
 use tracing::debug;
 
-use alloy::consensus::{SignableTransaction, TxEnvelope};
-use alloy::network::{Ethereum, TransactionBuilder};
-use alloy::primitives::{Address, B256, Bytes, U256};
+use alloy::consensus::{SignableTransaction, Transaction, TxEnvelope};
+use alloy::network::{Ethereum, TransactionBuilder, TransactionResponse};
+use alloy::primitives::{Address, B256, Bytes, Signature, U256, keccak256};
 use alloy::providers::{DynProvider, Provider};
 use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
-use commit_boost::prelude::{Chain, commit::client::SignerClient};
+use commit_boost::prelude::Chain;
 
-use commitments::types::{Commitment, CommitmentRequest, FeeInfo, SignedCommitment};
-use constraints::types::{Constraint, ConstraintsMessage, SignedConstraints};
-use lookahead::utils::time_until_slot_ms;
-use signing::signer;
+use commitments::error::CommitmentsRpcError;
+use commitments::types::{
+	Commitment, CommitmentEvent, CommitmentRequest, CommitmentStatus, MAX_SENDER_QUERY_VALIDITY_SECS, Offering,
+	SignedCommitment, SlotInfo, SlotInfoResponse, cancel_signing_hash, sender_query_signing_hash,
+};
+use common::storage::DatabaseContext;
+use constraints::conflicts::{ConstraintEffect, find_conflicts};
+use constraints::metadata::DelegationMetadata;
+use constraints::registry::ConstraintRegistry;
+use constraints::types::{
+	AuthorizationContext, Constraint, ConstraintsMessage, DelegationOffer, SignedConstraints, SignedDelegation,
+	SignedDelegationOffer,
+};
+use lookahead::slot_clock::SlotClock;
+use signing::nonce::{NonceKind, NonceManager};
+use signing::signer::{self, SigningClient};
+use urc::indexer::{KeyRegistration, UrcConfig, UrcIndexer};
 use urc::utils::{
-	get_commitment_request_signing_root, get_commitment_signing_root, get_constraints_message_signing_root,
+	get_commitment_signing_root, get_constraints_message_signing_root, get_delegation_offer_signing_root,
 };
 
-use crate::constants::{CONSTRAINT_TRIGGER_OFFSET_MS, INCLUSION_COMMITMENT_TYPE, INCLUSION_CONSTRAINT_TYPE};
-use crate::types::{FeePayload, InclusionPayload};
+use crate::constants::{
+	BLOB_COMMITMENT_TYPE, BLOB_CONSTRAINT_TYPE, BUNDLE_COMMITMENT_TYPE, BUNDLE_CONSTRAINT_TYPE,
+	EXCLUSION_COMMITMENT_TYPE, EXCLUSION_CONSTRAINT_TYPE, INCLUSION_COMMITMENT_TYPE, INCLUSION_CONSTRAINT_TYPE,
+	LOOKAHEAD_WINDOW_SIZE, MAX_BUNDLE_TRANSACTIONS, ORDERING_COMMITMENT_TYPE, ORDERING_CONSTRAINT_TYPE,
+};
+use crate::gateway::metrics::{
+	GATEWAY_SIGNER_LATENCY_SECONDS, SIGN_COMMITMENT, SIGN_CONSTRAINTS, SIGN_DELEGATION_OFFER, SIGN_RECEIVER_AUTH,
+	commitment_type_label,
+};
+use crate::handlers::default_registry;
+use crate::storage::InclusionDbExt;
+use crate::types::{
+	BlobConstraintPayload, BundlePayload, CommitmentTombstone, ExclusionPayload, ExclusionRequestPayload,
+	InclusionPayload, OrderingPayload, OrderingRequestPayload, TombstoneReason, commitment_constraints,
+};
 
 /// Helper functions for RPC business logic
 /// This module contains utility functions that can be shared across multiple RPC handlers
@@ -70,16 +99,219 @@ pub fn validate_commitment_request(request: &CommitmentRequest) -> Result<Inclus
 	}
 }
 
-/// Validates that there is enough time before the constraints submission time to process the commitment
-pub fn validate_commitment_timing(inclusion_payload: &InclusionPayload, chain: &Chain) -> Result<()> {
-	let target_slot = inclusion_payload.slot;
-	let time_until_slot = time_until_slot_ms(chain.genesis_time_sec(), target_slot);
-	let time_until_submission = time_until_slot - CONSTRAINT_TRIGGER_OFFSET_MS;
+/// Validates that the target slot is in the future and within the gateway's lookahead window
+pub fn validate_commitment_window(target_slot: u64, current_slot: u64) -> Result<()> {
+	if target_slot <= current_slot {
+		return Err(eyre::eyre!("Slot {} is not after the current slot {}", target_slot, current_slot));
+	}
+	if target_slot > current_slot + LOOKAHEAD_WINDOW_SIZE {
+		return Err(eyre::eyre!(
+			"Slot {} is beyond the lookahead window ending at slot {}",
+			target_slot,
+			current_slot + LOOKAHEAD_WINDOW_SIZE
+		));
+	}
+	Ok(())
+}
+
+/// Validates an exclusion commitment request and returns the decoded ExclusionRequestPayload
+pub fn validate_exclusion_request(request: &CommitmentRequest) -> Result<ExclusionRequestPayload> {
+	if request.commitment_type != EXCLUSION_COMMITMENT_TYPE {
+		return Err(eyre::eyre!(
+			"Invalid commitment type: expected {}, got {}",
+			EXCLUSION_COMMITMENT_TYPE,
+			request.commitment_type
+		));
+	}
+
+	if request.slasher == Address::ZERO {
+		return Err(eyre::eyre!("Invalid slasher address"));
+	}
+
+	let exclusion_payload = ExclusionRequestPayload::abi_decode(&request.payload)
+		.map_err(|e| eyre::eyre!("Invalid payload format: {}", e))?;
+
+	if exclusion_payload.slot == 0 {
+		return Err(eyre::eyre!("Invalid slot: 0"));
+	}
+
+	if exclusion_payload.tx_hash == B256::ZERO {
+		return Err(eyre::eyre!("Excluded transaction hash cannot be zero"));
+	}
+
+	// The payment transaction is committed for inclusion, so it cannot be the excluded transaction
+	let payment = exclusion_payload.payment();
+	payment.verify_signature()?;
+	if payment.tx_hash()? == exclusion_payload.tx_hash {
+		return Err(eyre::eyre!("The payment transaction cannot be the excluded transaction"));
+	}
+
+	Ok(exclusion_payload)
+}
+
+/// Sender of the transaction with `tx_hash`, the owner who may request its exclusion
+/// Errors when the execution client does not know the transaction, as its owner cannot be established
+pub async fn transaction_owner(execution_client: &DynProvider<Ethereum>, tx_hash: B256) -> Result<Address> {
+	let transaction = execution_client
+		.get_transaction_by_hash(tx_hash)
+		.await
+		.wrap_err("Failed to get transaction from execution client")?
+		.ok_or_else(|| eyre::eyre!("Transaction {} is unknown to the execution client", tx_hash))?;
+	Ok(transaction.from())
+}
+
+/// Sender of the transaction with `tx_hash` committed for inclusion, the owner who may request its ordering
+pub fn committed_transaction_owner(db: &DatabaseContext, tx_hash: &B256) -> Result<Address> {
+	let inclusion = match db.get_tx_hash_commitment(tx_hash)? {
+		Some(inclusion_hash) if db.get_tombstone(&inclusion_hash)?.is_none() => {
+			db.get_signed_commitment(&inclusion_hash)?
+		}
+		_ => None,
+	};
+	let inclusion =
+		inclusion.ok_or_else(|| eyre::eyre!("Transaction {} has no active inclusion commitment", tx_hash))?;
+	InclusionPayload::abi_decode(&inclusion.constraint.payload)?.sender()
+}
+
+/// Verifies that a commitment request was authorized by the owner of the transaction it targets
+pub fn verify_owner_authorization(authorizer: Address, owner: Address) -> Result<()> {
+	if authorizer != owner {
+		return Err(eyre::eyre!("Request authorized by {}, expected the transaction owner {}", authorizer, owner));
+	}
+	Ok(())
+}
+
+/// Validates an ordering commitment request and returns the decoded OrderingRequestPayload
+pub fn validate_ordering_request(request: &CommitmentRequest) -> Result<OrderingRequestPayload> {
+	if request.commitment_type != ORDERING_COMMITMENT_TYPE {
+		return Err(eyre::eyre!(
+			"Invalid commitment type: expected {}, got {}",
+			ORDERING_COMMITMENT_TYPE,
+			request.commitment_type
+		));
+	}
+
+	if request.slasher == Address::ZERO {
+		return Err(eyre::eyre!("Invalid slasher address"));
+	}
+
+	let ordering_payload = OrderingRequestPayload::abi_decode(&request.payload)
+		.map_err(|e| eyre::eyre!("Invalid payload format: {}", e))?;
+
+	if ordering_payload.slot == 0 {
+		return Err(eyre::eyre!("Invalid slot: 0"));
+	}
+
+	if ordering_payload.tx_hash == B256::ZERO {
+		return Err(eyre::eyre!("Ordered transaction hash cannot be zero"));
+	}
+
+	// The ordered transaction is already committed, so the payment must be another transaction
+	let payment = ordering_payload.payment();
+	payment.verify_signature()?;
+	if payment.tx_hash()? == ordering_payload.tx_hash {
+		return Err(eyre::eyre!("The payment transaction cannot be the ordered transaction"));
+	}
+
+	Ok(ordering_payload)
+}
+
+/// Validates a bundle commitment request and returns the decoded BundlePayload
+pub fn validate_bundle_request(request: &CommitmentRequest) -> Result<BundlePayload> {
+	if request.commitment_type != BUNDLE_COMMITMENT_TYPE {
+		return Err(eyre::eyre!(
+			"Invalid commitment type: expected {}, got {}",
+			BUNDLE_COMMITMENT_TYPE,
+			request.commitment_type
+		));
+	}
+
+	if request.slasher == Address::ZERO {
+		return Err(eyre::eyre!("Invalid slasher address"));
+	}
+
+	let bundle_payload =
+		BundlePayload::abi_decode(&request.payload).map_err(|e| eyre::eyre!("Invalid payload format: {}", e))?;
+
+	if bundle_payload.slot == 0 {
+		return Err(eyre::eyre!("Invalid slot: 0"));
+	}
+
+	if bundle_payload.signed_txs.is_empty() || bundle_payload.signed_txs.len() > MAX_BUNDLE_TRANSACTIONS {
+		return Err(eyre::eyre!(
+			"Bundle must hold between 1 and {} transactions, got {}",
+			MAX_BUNDLE_TRANSACTIONS,
+			bundle_payload.signed_txs.len()
+		));
+	}
+
+	let mut tx_hashes = std::collections::HashSet::new();
+	for inclusion_payload in bundle_payload.inclusion_payloads() {
+		inclusion_payload.verify_signature()?;
+		let tx_hash = inclusion_payload.tx_hash()?;
+		if !tx_hashes.insert(tx_hash) {
+			return Err(eyre::eyre!("Transaction {} appears twice in the bundle", tx_hash));
+		}
+	}
+
+	Ok(bundle_payload)
+}
+
+/// Validates a blob commitment request and returns the decoded InclusionPayload
+/// The transaction must be an EIP-4844 transaction in its canonical form, the blobs travel separately
+pub fn validate_blob_request(request: &CommitmentRequest) -> Result<InclusionPayload> {
+	if request.commitment_type != BLOB_COMMITMENT_TYPE {
+		return Err(eyre::eyre!(
+			"Invalid commitment type: expected {}, got {}",
+			BLOB_COMMITMENT_TYPE,
+			request.commitment_type
+		));
+	}
+
+	if request.slasher == Address::ZERO {
+		return Err(eyre::eyre!("Invalid slasher address"));
+	}
+
+	let inclusion_payload =
+		InclusionPayload::abi_decode(&request.payload).map_err(|e| eyre::eyre!("Invalid payload format: {}", e))?;
+
+	if inclusion_payload.slot == 0 {
+		return Err(eyre::eyre!("Invalid slot: 0"));
+	}
+
+	inclusion_payload.verify_signature()?;
+	let tx_envelope = inclusion_payload.decode_transaction()?;
+	if !tx_envelope.is_eip4844() {
+		return Err(eyre::eyre!(
+			"Blob commitments require an EIP-4844 transaction, got type {}",
+			tx_envelope.tx_type()
+		));
+	}
+
+	// The constraint is checked against the transaction hash of the block, which excludes the sidecar
+	inclusion_payload.tx_hash().wrap_err("Blob transaction must be sent without its sidecar")?;
+
+	let blob_payload = BlobConstraintPayload::from_inclusion_payload(&inclusion_payload)?;
+	if let Some(hash) = blob_payload
+		.blob_versioned_hashes
+		.iter()
+		.find(|hash| hash[0] != alloy::eips::eip4844::VERSIONED_HASH_VERSION_KZG)
+	{
+		return Err(eyre::eyre!("Unsupported blob versioned hash version: {}", hash));
+	}
+
+	Ok(inclusion_payload)
+}
+
+/// Validates that the request arrives at least `cutoff_ms` before the target slot
+pub fn validate_commitment_timing(target_slot: u64, slot_clock: &SlotClock, cutoff_ms: i64) -> Result<()> {
+	let time_until_slot = slot_clock.slot_deadline(target_slot);
+	let time_until_submission = time_until_slot - cutoff_ms;
 
 	debug!(
 		"validate_commitment_timing: target_slot={}, genesis_time={}, time_until_slot={}ms, time_until_submission={}ms",
 		target_slot,
-		chain.genesis_time_sec(),
+		slot_clock.genesis_time(),
 		time_until_slot,
 		time_until_submission
 	);
@@ -89,12 +321,257 @@ pub fn validate_commitment_timing(inclusion_payload: &InclusionPayload, chain: &
 			"Not enough time before constraints submission time to process commitment (target_slot={}, time_until_slot={}ms, need at least {}ms)",
 			target_slot,
 			time_until_slot,
-			CONSTRAINT_TRIGGER_OFFSET_MS
+			cutoff_ms
 		));
 	}
 	Ok(())
 }
 
+/// Reason a transaction would not be includable, each class maps to its own JSON-RPC error code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionValidationError {
+	ChainIdMismatch { expected: u64, actual: Option<u64> },
+	IntrinsicGasTooLow { gas_limit: u64, intrinsic_gas: u64 },
+	FeeCapTooLow { max_fee_per_gas: u128, base_fee_per_gas: u64 },
+	TipAboveFeeCap { max_priority_fee_per_gas: u128, max_fee_per_gas: u128 },
+	NonceTooLow { nonce: u64, account_nonce: u64 },
+	NonceGap { nonce: u64, next_nonce: u64 },
+	InsufficientBalance { required: U256, balance: U256 },
+	ExecutionReverted(String),
+	ExecutionClient(String),
+	Storage(String),
+}
+
+impl TransactionValidationError {
+	/// JSON-RPC error code for the failure class
+	pub fn code(&self) -> i32 {
+		match self {
+			TransactionValidationError::ChainIdMismatch { .. } => -32010,
+			TransactionValidationError::IntrinsicGasTooLow { .. } => -32011,
+			TransactionValidationError::FeeCapTooLow { .. } => -32012,
+			TransactionValidationError::TipAboveFeeCap { .. } => -32013,
+			TransactionValidationError::NonceTooLow { .. } => -32014,
+			TransactionValidationError::InsufficientBalance { .. } => -32015,
+			TransactionValidationError::ExecutionReverted(_) => -32016,
+			TransactionValidationError::NonceGap { .. } => -32017,
+			TransactionValidationError::ExecutionClient(_) | TransactionValidationError::Storage(_) => -32603,
+		}
+	}
+
+	/// Short JSON-RPC error message for the failure class
+	pub fn message(&self) -> &'static str {
+		match self {
+			TransactionValidationError::ChainIdMismatch { .. } => "Invalid chain id",
+			TransactionValidationError::IntrinsicGasTooLow { .. } => "Intrinsic gas too low",
+			TransactionValidationError::FeeCapTooLow { .. } => "Fee cap below base fee",
+			TransactionValidationError::TipAboveFeeCap { .. } => "Tip above fee cap",
+			TransactionValidationError::NonceTooLow { .. } => "Nonce too low",
+			TransactionValidationError::InsufficientBalance { .. } => "Insufficient funds",
+			TransactionValidationError::ExecutionReverted(_) => "Execution reverted",
+			TransactionValidationError::NonceGap { .. } => "Nonce gap",
+			TransactionValidationError::ExecutionClient(_) | TransactionValidationError::Storage(_) => {
+				"Failed to validate transaction"
+			}
+		}
+	}
+}
+
+impl std::fmt::Display for TransactionValidationError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			TransactionValidationError::ChainIdMismatch { expected, actual } => {
+				write!(f, "transaction chain id {:?} does not match chain id {}", actual, expected)
+			}
+			TransactionValidationError::IntrinsicGasTooLow { gas_limit, intrinsic_gas } => {
+				write!(f, "gas limit {} is below intrinsic gas {}", gas_limit, intrinsic_gas)
+			}
+			TransactionValidationError::FeeCapTooLow { max_fee_per_gas, base_fee_per_gas } => {
+				write!(f, "max fee per gas {} is below base fee {}", max_fee_per_gas, base_fee_per_gas)
+			}
+			TransactionValidationError::TipAboveFeeCap { max_priority_fee_per_gas, max_fee_per_gas } => {
+				write!(
+					f,
+					"max priority fee per gas {} exceeds max fee per gas {}",
+					max_priority_fee_per_gas, max_fee_per_gas
+				)
+			}
+			TransactionValidationError::NonceTooLow { nonce, account_nonce } => {
+				write!(f, "nonce {} is below account nonce {}", nonce, account_nonce)
+			}
+			TransactionValidationError::NonceGap { nonce, next_nonce } => {
+				write!(f, "nonce {} leaves a gap after the next nonce {}", nonce, next_nonce)
+			}
+			TransactionValidationError::InsufficientBalance { required, balance } => {
+				write!(f, "balance {} wei is below required {} wei", balance, required)
+			}
+			TransactionValidationError::ExecutionReverted(reason) => write!(f, "transaction reverts: {}", reason),
+			TransactionValidationError::ExecutionClient(reason) => write!(f, "execution client error: {}", reason),
+			TransactionValidationError::Storage(reason) => write!(f, "storage error: {}", reason),
+		}
+	}
+}
+
+impl From<TransactionValidationError> for CommitmentsRpcError {
+	fn from(error: TransactionValidationError) -> Self {
+		match error {
+			TransactionValidationError::ExecutionClient(_) | TransactionValidationError::Storage(_) => {
+				CommitmentsRpcError::Internal(error.to_string())
+			}
+			_ => CommitmentsRpcError::InvalidTransaction {
+				code: error.code(),
+				message: error.message().to_string(),
+				reason: error.to_string(),
+			},
+		}
+	}
+}
+
+/// Gas charged before execution: base cost, calldata, contract creation, access list and authorizations
+pub fn intrinsic_gas(tx_envelope: &TxEnvelope) -> u64 {
+	let input = tx_envelope.input();
+	let zero_bytes = input.iter().filter(|byte| **byte == 0).count() as u64;
+	let non_zero_bytes = input.len() as u64 - zero_bytes;
+	let mut gas = 21_000 + zero_bytes * 4 + non_zero_bytes * 16;
+
+	if tx_envelope.is_create() {
+		// Creation cost plus EIP-3860 initcode word cost
+		gas += 32_000 + 2 * (input.len() as u64).div_ceil(32);
+	}
+
+	if let Some(access_list) = tx_envelope.access_list() {
+		for item in access_list.iter() {
+			gas += 2_400 + 1_900 * item.storage_keys.len() as u64;
+		}
+	}
+
+	if let Some(authorizations) = tx_envelope.authorization_list() {
+		gas += 25_000 * authorizations.len() as u64;
+	}
+
+	gas
+}
+
+/// Checks that only depend on the transaction, the chain and the current base fee
+pub fn validate_transaction_fields(
+	tx_envelope: &TxEnvelope,
+	chain_id: u64,
+	base_fee_per_gas: u64,
+) -> std::result::Result<(), TransactionValidationError> {
+	if tx_envelope.chain_id() != Some(chain_id) {
+		return Err(TransactionValidationError::ChainIdMismatch { expected: chain_id, actual: tx_envelope.chain_id() });
+	}
+
+	let intrinsic_gas = intrinsic_gas(tx_envelope);
+	if tx_envelope.gas_limit() < intrinsic_gas {
+		return Err(TransactionValidationError::IntrinsicGasTooLow {
+			gas_limit: tx_envelope.gas_limit(),
+			intrinsic_gas,
+		});
+	}
+
+	let max_fee_per_gas = tx_envelope.max_fee_per_gas();
+	if let Some(max_priority_fee_per_gas) = tx_envelope.max_priority_fee_per_gas()
+		&& max_priority_fee_per_gas > max_fee_per_gas
+	{
+		return Err(TransactionValidationError::TipAboveFeeCap { max_priority_fee_per_gas, max_fee_per_gas });
+	}
+
+	if max_fee_per_gas < base_fee_per_gas as u128 {
+		return Err(TransactionValidationError::FeeCapTooLow { max_fee_per_gas, base_fee_per_gas });
+	}
+
+	Ok(())
+}
+
+/// First nonce of `sender` from `account_nonce` on that is neither committed by the gateway nor taken by the
+/// transactions committed `ahead` of this one in the same request
+pub fn next_committable_nonce(
+	db: &DatabaseContext,
+	sender: &Address,
+	account_nonce: u64,
+	ahead: &[(Address, u64)],
+) -> Result<u64> {
+	let mut nonce = account_nonce;
+	while ahead.contains(&(*sender, nonce)) || db.get_sender_nonce_commitment(sender, nonce)?.is_some() {
+		nonce += 1;
+	}
+	Ok(nonce)
+}
+
+/// Checks against the sender's account nonce and balance
+/// The nonce may replace a committed transaction or follow the committed ones, `next_nonce` being the first
+/// nonce from the account nonce on that is not committed, but may not leave a gap the block could not fill
+pub fn validate_sender_account(
+	tx_envelope: &TxEnvelope,
+	account_nonce: u64,
+	next_nonce: u64,
+	balance: U256,
+) -> std::result::Result<(), TransactionValidationError> {
+	if tx_envelope.nonce() < account_nonce {
+		return Err(TransactionValidationError::NonceTooLow { nonce: tx_envelope.nonce(), account_nonce });
+	}
+	if tx_envelope.nonce() > next_nonce {
+		return Err(TransactionValidationError::NonceGap { nonce: tx_envelope.nonce(), next_nonce });
+	}
+
+	let mut required =
+		U256::from(tx_envelope.gas_limit()) * U256::from(tx_envelope.max_fee_per_gas()) + tx_envelope.value();
+	// Blob transactions also reserve the blob fee
+	if let Some(blob_gas) = tx_envelope.blob_gas_used() {
+		required += U256::from(blob_gas) * U256::from(tx_envelope.max_fee_per_blob_gas().unwrap_or_default());
+	}
+	if balance < required {
+		return Err(TransactionValidationError::InsufficientBalance { required, balance });
+	}
+
+	Ok(())
+}
+
+/// Validates that the committed transaction can be included given the current execution layer state
+///
+/// Checks chain id, intrinsic gas and fee caps against the latest block, the sender's pending nonce and balance,
+/// and optionally simulates the transaction with eth_call. Invalid transactions would otherwise become
+/// unfulfillable constraints. The nonce may follow the sender's committed transactions and those `ahead` of it in
+/// the same request, e.g. the earlier members of a bundle. The latest base fee comes from the slot's market snapshot.
+pub async fn validate_transaction_state(
+	inclusion_payload: &InclusionPayload,
+	ahead: &[InclusionPayload],
+	db: &DatabaseContext,
+	execution_client: &DynProvider<Ethereum>,
+	chain_id: u64,
+	base_fee_per_gas: u64,
+	simulate: bool,
+) -> std::result::Result<(), TransactionValidationError> {
+	let client_error = |e: &dyn std::fmt::Display| TransactionValidationError::ExecutionClient(e.to_string());
+
+	let tx_envelope = inclusion_payload.decode_transaction().map_err(|e| client_error(&e))?;
+	let sender = inclusion_payload.sender().map_err(|e| client_error(&e))?;
+
+	validate_transaction_fields(&tx_envelope, chain_id, base_fee_per_gas)?;
+
+	let account_nonce = execution_client.get_transaction_count(sender).pending().await.map_err(|e| client_error(&e))?;
+	let balance = execution_client.get_balance(sender).pending().await.map_err(|e| client_error(&e))?;
+	let ahead = ahead
+		.iter()
+		.map(|payload| Ok((payload.sender()?, payload.decode_transaction()?.nonce())))
+		.collect::<Result<Vec<_>>>()
+		.map_err(|e| client_error(&e))?;
+	let next_nonce = next_committable_nonce(db, &sender, account_nonce, &ahead)
+		.map_err(|e| TransactionValidationError::Storage(e.to_string()))?;
+	validate_sender_account(&tx_envelope, account_nonce, next_nonce, balance)?;
+
+	if simulate {
+		let tx_request = tx_envelope_to_rpc_request(&tx_envelope).map_err(|e| client_error(&e))?;
+		execution_client
+			.call(tx_request)
+			.await
+			.map_err(|e| TransactionValidationError::ExecutionReverted(e.to_string()))?;
+	}
+
+	debug!("Transaction from {} passed execution client validation", sender);
+	Ok(())
+}
+
 /// Converts a TxEnvelope to a TransactionRequest suitable for eth_estimateGas
 ///
 /// This helper function extracts transaction fields from a signed transaction
@@ -232,96 +709,609 @@ pub fn tx_envelope_to_rpc_request(tx_envelope: &TxEnvelope) -> Result<alloy::rpc
 	Ok(tx_request)
 }
 
-/// Calculates fee information for a commitment request using RPC calls
-///
-/// This function:
-/// 1. Decodes the InclusionPayload from the request
-/// 2. Decodes the signed transaction from the payload
-/// 3. Converts it to a TransactionRequest for gas estimation
-/// 4. Calls eth_estimateGas to get the gas required
-/// 5. Calls eth_gasPrice to get the current gas price
-/// 6. Calculates the total fee as gas_price * estimated_gas
-///
-/// # Parameters
-///
-/// * `request` - The commitment request containing the InclusionPayload
-/// * `execution_client` - The execution client RPC API for gas price and estimation calls
-///
-/// # Returns
-///
-/// `FeeInfo` containing the calculated fee and commitment type
-pub async fn calculate_fee_info(
+/// Validates a request hash format
+pub fn validate_request_hash(hash: &str) -> Result<B256> {
+	if hash.len() != 66 || !hash.starts_with("0x") {
+		return Err(eyre::eyre!("Invalid hash format. Expected 0x followed by 64 hex characters"));
+	}
+
+	hash.parse::<B256>().wrap_err("Failed to parse hash")
+}
+
+/// Builds the slots response from the delegated slots, offering inclusion and exclusion commitments on every slot
+pub fn build_slot_info_response<T>(chain_id: u64, delegated_slots: &[(u64, T)]) -> SlotInfoResponse {
+	let offering = Offering {
+		chain_id,
+		commitment_types: vec![
+			INCLUSION_COMMITMENT_TYPE,
+			EXCLUSION_COMMITMENT_TYPE,
+			ORDERING_COMMITMENT_TYPE,
+			BUNDLE_COMMITMENT_TYPE,
+			BLOB_COMMITMENT_TYPE,
+		],
+	};
+
+	let slots = delegated_slots
+		.iter()
+		.map(|(slot, _)| SlotInfo { slot: *slot, offerings: vec![offering.clone()], remaining_gas: None })
+		.collect();
+
+	SlotInfoResponse { slots }
+}
+
+/// Constraint types the constraints of a commitment type are made of, paid commitments also carry the inclusion
+/// constraint of their payment transaction
+pub fn commitment_constraint_types(commitment_type: u64) -> &'static [u64] {
+	match commitment_type {
+		EXCLUSION_COMMITMENT_TYPE => &[EXCLUSION_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE],
+		ORDERING_COMMITMENT_TYPE => &[ORDERING_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE],
+		BUNDLE_COMMITMENT_TYPE => &[BUNDLE_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE],
+		BLOB_COMMITMENT_TYPE => &[BLOB_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE],
+		_ => &[INCLUSION_CONSTRAINT_TYPE],
+	}
+}
+
+/// Terms of a delegation, an error if its metadata does not decode as the gateway could not honour them
+pub fn delegation_terms(delegation: &SignedDelegation) -> Result<DelegationMetadata> {
+	DelegationMetadata::decode(&delegation.message.metadata)
+		.wrap_err_with(|| format!("Invalid terms in the delegation of slot {}", delegation.message.slot))
+}
+
+/// Narrow a slot's offerings to the commitment types the terms of its delegation allow
+/// Returns false if the slot takes no commitments under the terms: they lapse before the slot, every constraint
+/// they allow is used or none of the offered types is allowed
+pub fn apply_delegation_terms(
+	slot_info: &mut SlotInfo,
+	terms: &DelegationMetadata,
+	slot_timestamp: u64,
+	active_constraints: usize,
+) -> bool {
+	if terms.is_expired(slot_timestamp) || terms.max_constraints.is_some_and(|max| active_constraints >= max as usize) {
+		return false;
+	}
+	for offering in slot_info.offerings.iter_mut() {
+		offering.commitment_types.retain(|commitment_type| {
+			commitment_constraint_types(*commitment_type).iter().all(|constraint_type| terms.allows(*constraint_type))
+		});
+	}
+	slot_info.offerings.retain(|offering| !offering.commitment_types.is_empty());
+	!slot_info.offerings.is_empty()
+}
+
+/// Rejects a request whose constraints the terms of the slot's delegation would keep the gateway from posting:
+/// terms that lapse before the slot, a constraint type they do not allow or more constraints than their maximum
+/// Checked before signing, the constraint manager only drops constraints outside the terms as a last resort
+pub fn check_delegation_terms(
+	db: &DatabaseContext,
+	terms: &DelegationMetadata,
+	slot: u64,
+	slot_timestamp: u64,
 	request: &CommitmentRequest,
-	execution_client: &DynProvider<Ethereum>,
-) -> Result<FeeInfo> {
-	debug!("Calculating fee for commitment type: {}", request.commitment_type);
+) -> Result<(), CommitmentsRpcError> {
+	if terms.is_expired(slot_timestamp) {
+		return Err(CommitmentsRpcError::NoDelegation(format!(
+			"The delegation terms of slot {} lapse at {}, before the slot starts",
+			slot,
+			terms.valid_until.unwrap_or_default()
+		)));
+	}
 
-	// 1. Decode the InclusionPayload from the request
-	let inclusion_payload =
-		InclusionPayload::abi_decode(&request.payload).wrap_err("Failed to decode InclusionPayload from request")?;
+	let constraints = commitment_constraints(request.commitment_type, &request.payload)
+		.map_err(|e| CommitmentsRpcError::InvalidPayload(e.to_string()))?;
+	if let Some(constraint) = constraints.iter().find(|constraint| !terms.allows(constraint.constraint_type)) {
+		return Err(CommitmentsRpcError::InvalidPayload(format!(
+			"The delegation terms of slot {} do not allow constraint type {}",
+			slot, constraint.constraint_type
+		)));
+	}
 
-	// 2. Decode the signed transaction
-	let tx_envelope = inclusion_payload.decode_transaction()?;
+	if let Some(max_constraints) = terms.max_constraints {
+		let active = db
+			.get_constraints_in_range(slot, slot)
+			.map_err(|e| CommitmentsRpcError::Internal(format!("Failed to get constraints: {}", e)))?
+			.len();
+		if active + constraints.len() > max_constraints as usize {
+			return Err(CommitmentsRpcError::OverCapacity(format!(
+				"Slot {} holds {} of the {} constraints its delegation terms allow, the request needs {}",
+				slot,
+				active,
+				max_constraints,
+				constraints.len()
+			)));
+		}
+	}
+	Ok(())
+}
+
+/// Rejects a commitment whose transaction would push the slot over its gas budget
+///
+/// Gas of a commitment being replaced in the same slot is released before checking.
+pub fn check_slot_gas_budget(
+	db: &DatabaseContext,
+	inclusion_payload: &InclusionPayload,
+	replaced: Option<(u64, B256)>,
+	budget: u64,
+) -> Result<()> {
+	let committed = db.get_slot_committed_gas(inclusion_payload.slot)?.saturating_sub(replaced_gas(
+		db,
+		inclusion_payload.slot,
+		replaced,
+	)?);
+
+	let gas = inclusion_payload.gas()?;
+	if committed.saturating_add(gas) > budget {
+		return Err(eyre::eyre!(
+			"Slot {} gas budget exhausted: {} committed, {} requested, budget {}",
+			inclusion_payload.slot,
+			committed,
+			gas,
+			budget
+		));
+	}
+	Ok(())
+}
+
+/// Gas of the commitments being signed, reserved in their slot until they are stored
+/// A commitment's gas is counted against the slot's budget from the check until it is stored, so concurrent
+/// requests can not commit more than the budget between the check and the store
+#[derive(Debug, Default)]
+pub struct GasReservations {
+	reserved: Mutex<HashMap<u64, u64>>,
+}
+
+/// Gas reserved in a slot, released when dropped once the commitment is stored or the request failed
+#[derive(Debug)]
+pub struct GasReservation<'a> {
+	reservations: &'a GasReservations,
+	slot: u64,
+	gas: u64,
+}
+
+impl GasReservations {
+	/// Reserve `gas` in `slot` if the stored commitments, less the `released` gas of a replaced one, and the other
+	/// reservations leave room for it in `budget`
+	pub fn reserve(
+		&self,
+		db: &DatabaseContext,
+		slot: u64,
+		gas: u64,
+		released: u64,
+		budget: u64,
+	) -> Result<GasReservation<'_>> {
+		let mut reserved = self.reserved.lock().expect("gas reservations lock poisoned");
+		let committed = db.get_slot_committed_gas(slot)?.saturating_sub(released);
+		let pending = reserved.get(&slot).copied().unwrap_or_default();
+		if committed.saturating_add(pending).saturating_add(gas) > budget {
+			return Err(eyre::eyre!(
+				"Slot {} gas budget exhausted: {} committed, {} being committed, {} requested, budget {}",
+				slot,
+				committed,
+				pending,
+				gas,
+				budget
+			));
+		}
+		*reserved.entry(slot).or_default() += gas;
+		Ok(GasReservation { reservations: self, slot, gas })
+	}
+
+	/// Gas reserved in `slot` by commitments not stored yet
+	pub fn pending(&self, slot: u64) -> u64 {
+		self.reserved.lock().expect("gas reservations lock poisoned").get(&slot).copied().unwrap_or_default()
+	}
+}
+
+impl Drop for GasReservation<'_> {
+	fn drop(&mut self) {
+		let mut reserved = self.reservations.reserved.lock().expect("gas reservations lock poisoned");
+		if let Some(pending) = reserved.get_mut(&self.slot) {
+			*pending = pending.saturating_sub(self.gas);
+			if *pending == 0 {
+				reserved.remove(&self.slot);
+			}
+		}
+	}
+}
+
+/// Gas a replacement releases in `slot`: that of the replaced commitment if it targets the same slot
+pub fn replaced_gas(db: &DatabaseContext, slot: u64, replaced: Option<(u64, B256)>) -> Result<u64> {
+	if let Some((replaced_slot, replaced_hash)) = replaced
+		&& replaced_slot == slot
+		&& let Some(previous) = db.get_signed_commitment(&replaced_hash)?
+	{
+		return InclusionPayload::abi_decode(&previous.constraint.payload)?.gas();
+	}
+	Ok(0)
+}
+
+/// Rejects the members of a grouped commitment, a bundle, blob or paid commitment, whose transactions together
+/// would push the slot over its gas budget
+pub fn check_members_gas_budget(
+	db: &DatabaseContext,
+	slot: u64,
+	members: &[InclusionPayload],
+	budget: u64,
+) -> Result<()> {
+	let committed = db.get_slot_committed_gas(slot)?;
+	let gas = members.iter().try_fold(0u64, |gas, member| Ok::<_, eyre::Report>(gas.saturating_add(member.gas()?)))?;
+	if committed.saturating_add(gas) > budget {
+		return Err(eyre::eyre!(
+			"Slot {} gas budget exhausted: {} committed, {} requested by {} transactions, budget {}",
+			slot,
+			committed,
+			gas,
+			members.len(),
+			budget
+		));
+	}
+	Ok(())
+}
+
+/// Key under which a transaction's record and inclusion constraint are stored when it is part of a bundle or
+/// blob commitment
+pub fn member_commitment_hash(request_hash: &B256, index: usize) -> B256 {
+	keccak256([request_hash.as_slice(), &(index as u64).to_be_bytes()].concat())
+}
+
+/// Creates a constraint from a commitment request
+/// This function creates a constraint with the same payload but using the matching constraint type
+pub fn create_constraint_from_commitment_request(request: &CommitmentRequest, slot: u64) -> Result<Constraint> {
+	debug!("Creating constraint from commitment request for slot {}", slot);
+
+	let constraint_type = match request.commitment_type {
+		INCLUSION_COMMITMENT_TYPE => INCLUSION_CONSTRAINT_TYPE,
+		commitment_type => return Err(eyre::eyre!("Unsupported commitment type {}", commitment_type)),
+	};
+
+	// Create the constraint with the same payload but constraint type
+	let constraint = Constraint { constraint_type, payload: request.payload.clone() };
+
+	debug!(
+		"Created constraint with type {} and payload length {} for slot {}",
+		constraint.constraint_type,
+		constraint.payload.len(),
+		slot
+	);
+	Ok(constraint)
+}
+
+/// Builds the event pushed to commitment subscribers, using the committed transaction sender
+pub fn create_commitment_event(
+	request_hash: B256,
+	constraint: &Constraint,
+	slot: u64,
+	status: CommitmentStatus,
+) -> Result<CommitmentEvent> {
+	let sender = InclusionPayload::abi_decode(&constraint.payload)?.sender()?;
+	Ok(CommitmentEvent { request_hash, sender, slot, status })
+}
+
+/// Settles a constraint against the transaction hashes of its slot's canonical block, in block order, with the
+/// effects its handler describes. A slot without a block honors exclusions and misses every other constraint
+pub fn settle_constraint(
+	registry: &ConstraintRegistry,
+	constraint: &Constraint,
+	block_tx_hashes: &[B256],
+) -> Result<CommitmentStatus> {
+	let effects = registry.effects(constraint)?;
+	if effects.is_empty() {
+		return Err(eyre::eyre!("Constraint type {} has no effects to settle", constraint.constraint_type));
+	}
+	if !effects.iter().all(|effect| effect.is_satisfied_by(block_tx_hashes)) {
+		return Ok(CommitmentStatus::Missed);
+	}
+	let excludes_only = effects.iter().all(|effect| matches!(effect, ConstraintEffect::Excludes { .. }));
+	Ok(if excludes_only { CommitmentStatus::Excluded } else { CommitmentStatus::Included })
+}
+
+/// Checks each gateway key is registered in the URC by an operator in good standing, opted into the slasher
+/// with the expected committer
+pub async fn check_urc_registration(
+	urc: &UrcConfig,
+	gateway_public_keys: &[BlsPublicKey],
+	committer_address: Option<Address>,
+) -> Result<Vec<KeyRegistration>> {
+	let indexer = UrcIndexer::new(urc.clone())?;
+	indexer.sync().await?;
+	let mut registrations = Vec::with_capacity(gateway_public_keys.len());
+	for gateway_public_key in gateway_public_keys {
+		let registration = indexer.check_operator(gateway_public_key).await?;
+		let committer = indexer.slasher_committer(registration.registration_root).await?;
+		if let Some(expected) = committer_address
+			&& committer != expected
+		{
+			return Err(eyre::eyre!(
+				"URC registration {} of {} opted into slasher {} with committer {}, expected {}",
+				registration.registration_root,
+				gateway_public_key,
+				urc.slasher,
+				committer,
+				expected
+			));
+		}
+		registrations.push(registration);
+	}
+	Ok(registrations)
+}
+
+/// Verifies that a cancellation was signed over `cancel_hash(request_hash, slot)` by the sender of the committed
+/// transaction, so neither a raw signature over the request hash nor one for another slot cancels it
+pub fn verify_cancel_signature(request_hash: &B256, slot: u64, signature: &Signature, sender: &Address) -> Result<()> {
+	let digest = cancel_signing_hash(request_hash, slot);
+	let recovered = signature.recover_address_from_prehash(&digest).wrap_err("Invalid cancellation signature")?;
+	if recovered != *sender {
+		return Err(eyre::eyre!("Cancellation signed by {}, expected the transaction sender {}", recovered, sender));
+	}
+	Ok(())
+}
+
+/// Verifies that a query for the commitments of a sender was signed by that sender over the queried range, and
+/// that the signature's expiry has not passed at `now` (unix seconds) nor is further than
+/// `MAX_SENDER_QUERY_VALIDITY_SECS` away, so a captured signature can not list the sender's commitments for long
+pub fn verify_sender_query_signature(
+	sender: &Address,
+	start_slot: u64,
+	end_slot: u64,
+	expiry: u64,
+	signature: &Signature,
+	now: u64,
+) -> Result<()> {
+	if expiry < now {
+		return Err(eyre::eyre!("Query signature expired at {}, now {}", expiry, now));
+	}
+	if expiry > now.saturating_add(MAX_SENDER_QUERY_VALIDITY_SECS) {
+		return Err(eyre::eyre!(
+			"Query signature expiry {} is more than {} seconds away",
+			expiry,
+			MAX_SENDER_QUERY_VALIDITY_SECS
+		));
+	}
+	let digest = sender_query_signing_hash(sender, start_slot, end_slot, expiry);
+	let recovered = signature.recover_address_from_prehash(&digest).wrap_err("Invalid query signature")?;
+	if recovered != *sender {
+		return Err(eyre::eyre!("Query signed by {}, expected the sender {}", recovered, sender));
+	}
+	Ok(())
+}
+
+/// Rejects a transaction that an active commitment already covers, in this or any other slot
+pub fn check_duplicate_commitment(db: &DatabaseContext, inclusion_payload: &InclusionPayload) -> Result<()> {
+	let tx_hash = inclusion_payload.tx_hash()?;
+
+	let Some(existing_hash) = db.get_tx_hash_commitment(&tx_hash)? else {
+		return Ok(());
+	};
+
+	if db.get_tombstone(&existing_hash)?.is_some() {
+		return Ok(());
+	}
+
+	if let Some(existing) = db.get_signed_commitment(&existing_hash)? {
+		let existing_slot = InclusionPayload::abi_decode(&existing.constraint.payload)?.slot;
+		return Err(eyre::eyre!(
+			"Transaction {} is already committed for slot {} by commitment {}",
+			tx_hash,
+			existing_slot,
+			existing_hash
+		));
+	}
+
+	Ok(())
+}
+
+/// Rejects an inclusion commitment for a transaction an active exclusion commitment keeps out of the slot
+pub fn check_excluded_transaction(db: &DatabaseContext, inclusion_payload: &InclusionPayload) -> Result<()> {
+	let tx_hash = inclusion_payload.tx_hash()?;
+	if let Some(exclusion_hash) = db.get_exclusion_commitment(inclusion_payload.slot, &tx_hash)?
+		&& db.get_tombstone(&exclusion_hash)?.is_none()
+	{
+		return Err(eyre::eyre!(
+			"Transaction {} is excluded from slot {} by commitment {}",
+			tx_hash,
+			inclusion_payload.slot,
+			exclusion_hash
+		));
+	}
+	Ok(())
+}
+
+/// Rejects a request whose constraints no block could satisfy together with the active constraints of the slot
+/// Only conflicts involving the request's own constraints reject it, gas is left to the slot gas budget
+pub fn check_constraint_conflicts(db: &DatabaseContext, slot: u64, request: &CommitmentRequest) -> Result<()> {
+	let mut constraints =
+		db.get_constraints_in_range(slot, slot)?.into_iter().map(|(_, _, constraint)| constraint).collect::<Vec<_>>();
+	let active = constraints.len();
+	constraints.extend(commitment_constraints(request.commitment_type, &request.payload)?);
+
+	let report = find_conflicts(&default_registry(), &constraints, None)?;
+	if let Some(conflict) = report.conflicts.iter().find(|conflict| conflict.constraints.iter().any(|i| *i >= active)) {
+		return Err(eyre::eyre!("Conflicts with the constraints of slot {}: {}", slot, conflict.reason));
+	}
+	Ok(())
+}
 
-	// 3. Convert to TransactionRequest for gas estimation
-	let tx_request = tx_envelope_to_rpc_request(&tx_envelope)?;
+/// Rejects an exclusion commitment that repeats an active exclusion or contradicts an active inclusion
+/// commitment for the same transaction and slot
+pub fn check_exclusion_conflict(db: &DatabaseContext, exclusion_payload: &ExclusionPayload) -> Result<()> {
+	let tx_hash = exclusion_payload.tx_hash;
+	if let Some(existing_hash) = db.get_exclusion_commitment(exclusion_payload.slot, &tx_hash)?
+		&& db.get_tombstone(&existing_hash)?.is_none()
+	{
+		return Err(eyre::eyre!(
+			"Transaction {} is already excluded from slot {} by commitment {}",
+			tx_hash,
+			exclusion_payload.slot,
+			existing_hash
+		));
+	}
 
-	// 4. Estimate gas required for the transaction using Alloy provider
-	let estimated_gas =
-		U256::from(execution_client.estimate_gas(tx_request).await.wrap_err("Failed to estimate gas for transaction")?);
+	if let Some(inclusion_hash) = db.get_tx_hash_commitment(&tx_hash)?
+		&& db.get_tombstone(&inclusion_hash)?.is_none()
+		&& let Some(inclusion) = db.get_signed_commitment(&inclusion_hash)?
+		&& InclusionPayload::abi_decode(&inclusion.constraint.payload)?.slot == exclusion_payload.slot
+	{
+		return Err(eyre::eyre!(
+			"Transaction {} is committed for inclusion in slot {} by commitment {}",
+			tx_hash,
+			exclusion_payload.slot,
+			inclusion_hash
+		));
+	}
+	Ok(())
+}
 
-	// 5. Get current gas price
-	let gas_price = U256::from(
-		execution_client.get_gas_price().await.wrap_err("Failed to get gas price from execution client node")?,
-	);
+/// Rejects an ordering commitment unless the transaction has an active inclusion commitment for the slot,
+/// the builder needs the signed transaction to place it. Also rejects a second ordering of the transaction
+/// and a bound that leaves no position once the slot's other ordering commitments are placed.
+pub fn check_ordering_conflict(db: &DatabaseContext, ordering_payload: &OrderingPayload) -> Result<()> {
+	let tx_hash = ordering_payload.tx_hash;
+	let included = match db.get_tx_hash_commitment(&tx_hash)? {
+		Some(inclusion_hash) if db.get_tombstone(&inclusion_hash)?.is_none() => {
+			match db.get_signed_commitment(&inclusion_hash)? {
+				Some(inclusion) => {
+					InclusionPayload::abi_decode(&inclusion.constraint.payload)?.slot == ordering_payload.slot
+				}
+				None => false,
+			}
+		}
+		_ => false,
+	};
+	if !included {
+		return Err(eyre::eyre!(
+			"Transaction {} has no active inclusion commitment for slot {}",
+			tx_hash,
+			ordering_payload.slot
+		));
+	}
 
-	// Convert from wei to gwei by dividing by 1 billion (1e9)
-	let price_gwei = ((gas_price * estimated_gas) / U256::from(1_000_000_000)).to();
+	let mut max_indices = vec![ordering_payload.max_index];
+	for (request_hash, existing) in slot_ordering_commitments(db, ordering_payload.slot)? {
+		if existing.tx_hash == tx_hash {
+			return Err(eyre::eyre!(
+				"Transaction {} is already ordered in slot {} by commitment {}",
+				tx_hash,
+				ordering_payload.slot,
+				request_hash
+			));
+		}
+		max_indices.push(existing.max_index);
+	}
 
-	let request_hash = get_commitment_request_signing_root(&request);
-	let fee_payload = FeePayload { request_hash, price_gwei };
+	// With the bounds sorted, the transaction at each position needs a bound of at least its position
+	max_indices.sort_unstable();
+	if let Some((position, max_index)) =
+		max_indices.iter().enumerate().find(|(position, max_index)| **max_index < *position as u64)
+	{
+		return Err(eyre::eyre!(
+			"No position left in slot {}: {} transactions would have to be at index {} or lower",
+			ordering_payload.slot,
+			position + 1,
+			max_index
+		));
+	}
+	Ok(())
+}
 
-	debug!(
-		"Calculated fee: estimated_gas={}, gas_price={} wei, price_gwei={} gwei",
-		estimated_gas, gas_price, price_gwei
-	);
+/// Active ordering commitments for a slot with their request hashes
+pub fn slot_ordering_commitments(db: &DatabaseContext, slot: u64) -> Result<Vec<(B256, OrderingPayload)>> {
+	db.get_constraints_in_range(slot, slot)?
+		.into_iter()
+		.filter(|(_, _, constraint)| constraint.constraint_type == ORDERING_CONSTRAINT_TYPE)
+		.map(|(_, request_hash, constraint)| Ok((request_hash, OrderingPayload::abi_decode(&constraint.payload)?)))
+		.collect()
+}
 
-	Ok(FeeInfo {
-		fee_payload: Bytes::from(serde_json::to_vec(&fee_payload)?),
-		commitment_type: request.commitment_type,
-	})
+/// Withdraws the ordering commitments that depend on a withdrawn inclusion commitment, with their payments
+/// Without the inclusion commitment the builder has no transaction to place. Returns the withdrawn request hashes
+pub fn withdraw_dependent_orderings(
+	db: &DatabaseContext,
+	inclusion_hash: &B256,
+	slot: u64,
+	reason: TombstoneReason,
+) -> Result<Vec<B256>> {
+	let mut withdrawn = Vec::new();
+	let Some(inclusion) = db.get_signed_commitment(inclusion_hash)? else {
+		return Ok(withdrawn);
+	};
+	let tx_hash = InclusionPayload::abi_decode(&inclusion.constraint.payload)?.tx_hash()?;
+	for (request_hash, ordering) in slot_ordering_commitments(db, slot)? {
+		if ordering.tx_hash == tx_hash {
+			db.tombstone_commitment(&CommitmentTombstone { request_hash, slot, reason: reason.clone() })?;
+			let payment_hash = member_commitment_hash(&request_hash, 0);
+			if db.get_signed_commitment(&payment_hash)?.is_some() {
+				db.tombstone_commitment(&CommitmentTombstone {
+					request_hash: payment_hash,
+					slot,
+					reason: reason.clone(),
+				})?;
+			}
+			debug!("Withdrew ordering commitment {:?} with inclusion commitment {:?}", request_hash, inclusion_hash);
+			withdrawn.push(request_hash);
+		}
+	}
+	Ok(withdrawn)
 }
 
-/// Validates a request hash format
-pub fn validate_request_hash(hash: &str) -> Result<B256> {
-	if hash.len() != 66 || !hash.starts_with("0x") {
-		return Err(eyre::eyre!("Invalid hash format. Expected 0x followed by 64 hex characters"));
+/// Finds the pending commitment a new request would replace, keyed by the sender and nonce of its transaction
+/// Returns the slot and request hash of the replaced commitment, or an error if it can no longer be replaced
+pub fn find_replaced_commitment(
+	db: &DatabaseContext,
+	inclusion_payload: &InclusionPayload,
+) -> Result<Option<(u64, B256)>> {
+	let tx = inclusion_payload.decode_transaction()?;
+	let sender = inclusion_payload.sender()?;
+
+	let Some(previous_hash) = db.get_sender_nonce_commitment(&sender, tx.nonce())? else {
+		return Ok(None);
+	};
+
+	// A cancelled commitment leaves nothing to replace
+	if db.get_tombstone(&previous_hash)?.is_some() {
+		return Ok(None);
 	}
 
-	hash.parse::<B256>().wrap_err("Failed to parse hash")
-}
+	let Some(previous) = db.get_signed_commitment(&previous_hash)? else {
+		return Ok(None);
+	};
+	let previous_type = previous.commitment.commitment.commitment_type;
+	// Transactions of grouped commitments, including the payment of an exclusion, are never replaced
+	if previous_type != INCLUSION_COMMITMENT_TYPE {
+		return Err(eyre::eyre!(
+			"Nonce conflict: sender {} nonce {} is committed by {} commitment {}, which cannot be replaced",
+			sender,
+			tx.nonce(),
+			commitment_type_label(previous_type),
+			previous.commitment.commitment.request_hash
+		));
+	}
+	let previous_payload = InclusionPayload::abi_decode(&previous.constraint.payload)?;
 
-/// Creates a constraint from a commitment request
-/// This function creates a constraint with the same payload but using the constraint type
-pub fn create_constraint_from_commitment_request(request: &CommitmentRequest, slot: u64) -> Result<Constraint> {
-	debug!("Creating constraint from commitment request for slot {}", slot);
+	if db.signed_constraints_finalized(previous_payload.slot)? {
+		return Err(eyre::eyre!(
+			"Nonce conflict: commitment {} for sender {} and nonce {} was already constrained",
+			previous_hash,
+			sender,
+			tx.nonce()
+		));
+	}
 
-	// Create the constraint with the same payload but constraint type
-	let constraint = Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: request.payload.clone() };
+	let previous_fee = previous_payload.decode_transaction()?.priority_fee_or_price();
+	if tx.priority_fee_or_price() <= previous_fee {
+		return Err(eyre::eyre!(
+			"Nonce conflict: replacement fee {} does not exceed {} of commitment {}",
+			tx.priority_fee_or_price(),
+			previous_fee,
+			previous_hash
+		));
+	}
 
-	debug!(
-		"Created constraint with type {} and payload length {} for slot {}",
-		constraint.constraint_type,
-		constraint.payload.len(),
-		slot
-	);
-	Ok(constraint)
+	Ok(Some((previous_payload.slot, previous_hash)))
 }
 
 /// Creates a properly signed commitment using ECDSA
 pub async fn create_signed_commitment(
 	request: &CommitmentRequest,
-	signer_client: &mut SignerClient,
+	signer_client: &mut SigningClient,
 	committer_address: Address,
 	module_signing_id: &B256,
 	chain: Chain,
@@ -338,9 +1328,11 @@ pub async fn create_signed_commitment(
 	let commitment_hash = get_commitment_signing_root(&commitment);
 
 	// Call the proxy_ecdsa signer
+	let timer = GATEWAY_SIGNER_LATENCY_SECONDS.with_label_values(&[SIGN_COMMITMENT]).start_timer();
 	let response =
 		signer::call_proxy_ecdsa_signer(signer_client, commitment_hash, committer_address, module_signing_id, chain)
 			.await?;
+	timer.observe_duration();
 
 	// 6. Construct the SignedCommitment
 	let signed_commitment = SignedCommitment {
@@ -353,20 +1345,6 @@ pub async fn create_signed_commitment(
 	Ok(signed_commitment)
 }
 
-/// Validates a signature against a commitment
-pub fn verify_commitment_signature(
-	commitment: &Commitment,
-	signature: &alloy::primitives::Signature,
-	expected_signer: &Address,
-) -> Result<bool> {
-	let commitment_hash = get_commitment_signing_root(commitment);
-	let recovered_address = signature
-		.recover_address_from_prehash(&commitment_hash)
-		.wrap_err("Failed to recover address from signature")?;
-
-	Ok(recovered_address == *expected_signer)
-}
-
 /// Creates a valid RLP-encoded EIP-1559 transaction with a mock signature
 /// This can be reused across tests to generate properly formatted signed transactions
 pub fn create_valid_signed_transaction() -> Bytes {
@@ -397,10 +1375,11 @@ pub fn create_valid_signed_transaction() -> Bytes {
 	Bytes::from(encoded_tx)
 }
 
-/// Creates a properly signed constraints message using BLS
+/// Creates a properly signed constraints message using BLS, under the signing key's next constraints nonce
 pub async fn sign_constraints_message(
 	message: &ConstraintsMessage,
-	signer_client: &mut SignerClient,
+	signer_client: &mut SigningClient,
+	nonce_manager: &NonceManager,
 	bls_public_key: BlsPublicKey,
 	module_signing_id: &B256,
 	chain: Chain,
@@ -409,10 +1388,14 @@ pub async fn sign_constraints_message(
 
 	// Hash the constraints message
 	let signing_root = get_constraints_message_signing_root(message)?;
+	let nonce = nonce_manager.next_nonce(bls_public_key.as_slice(), NonceKind::Constraints)?;
 
 	// Call the proxy_bls signer
+	let timer = GATEWAY_SIGNER_LATENCY_SECONDS.with_label_values(&[SIGN_CONSTRAINTS]).start_timer();
 	let response =
-		signer::call_proxy_bls_signer(signer_client, signing_root, bls_public_key, module_signing_id, chain).await?;
+		signer::call_proxy_bls_signer(signer_client, signing_root, bls_public_key, module_signing_id, chain, nonce)
+			.await?;
+	timer.observe_duration();
 	debug!("Received response from proxy_bls: {:?}", response);
 
 	let signed_constraints = SignedConstraints {
@@ -426,10 +1409,108 @@ pub async fn sign_constraints_message(
 	Ok(signed_constraints)
 }
 
+/// Receiver headers of a gateway key for GET /constraints/{slot}, a signature over the slot and `timestamp_ms` under a
+/// fresh nonce
+pub async fn sign_receiver_auth(
+	slot: u64,
+	timestamp_ms: u64,
+	signer_client: &mut SigningClient,
+	nonce_manager: &NonceManager,
+	bls_public_key: BlsPublicKey,
+	module_signing_id: &B256,
+	chain: Chain,
+) -> Result<AuthorizationContext> {
+	let message_hash = AuthorizationContext::message_hash(slot, timestamp_ms);
+	let nonce = nonce_manager.next_nonce(bls_public_key.as_slice(), NonceKind::ReceiverAuth)?;
+	let timer = GATEWAY_SIGNER_LATENCY_SECONDS.with_label_values(&[SIGN_RECEIVER_AUTH]).start_timer();
+	let response =
+		signer::call_proxy_bls_signer(signer_client, message_hash, bls_public_key, module_signing_id, chain, nonce)
+			.await
+			.wrap_err("Failed to sign the receiver authentication")?;
+	timer.observe_duration();
+
+	Ok(AuthorizationContext {
+		signature: Some(BlsSignature::new(response.signature.serialize())),
+		public_key: Some(bls_public_key),
+		nonce: Some(response.nonce),
+		signing_id: Some(response.module_signing_id),
+		timestamp_ms: Some(timestamp_ms),
+	})
+}
+
+/// Sign a delegation offer with the offer's delegate key, under its next delegation offer nonce
+pub async fn sign_delegation_offer(
+	offer: &DelegationOffer,
+	signer_client: &mut SigningClient,
+	nonce_manager: &NonceManager,
+	module_signing_id: &B256,
+	chain: Chain,
+) -> Result<SignedDelegationOffer> {
+	let signing_root = get_delegation_offer_signing_root(offer);
+	let nonce = nonce_manager.next_nonce(offer.delegate.as_slice(), NonceKind::DelegationOffer)?;
+	let timer = GATEWAY_SIGNER_LATENCY_SECONDS.with_label_values(&[SIGN_DELEGATION_OFFER]).start_timer();
+	let response =
+		signer::call_proxy_bls_signer(signer_client, signing_root, offer.delegate, module_signing_id, chain, nonce)
+			.await
+			.wrap_err("Failed to sign the delegation offer")?;
+	timer.observe_duration();
+
+	Ok(SignedDelegationOffer {
+		message: offer.clone(),
+		nonce: response.nonce,
+		signing_id: response.module_signing_id,
+		signature: BlsSignature::new(response.signature.serialize()),
+	})
+}
+
+/// Differences between the constraints posted to a relay and the message of the same delegate it serves back,
+/// empty if the relay serves exactly what was signed
+pub fn served_constraints_mismatches(posted: &SignedConstraints, served: &[SignedConstraints]) -> Vec<String> {
+	let delegate = &posted.message.delegate;
+	let mut served = served.iter().filter(|signed| signed.message.delegate == *delegate);
+	let Some(message) = served.next() else {
+		return vec![format!("no constraints of delegate {} served", delegate)];
+	};
+
+	let mut mismatches = Vec::new();
+	if served.next().is_some() {
+		mismatches.push(format!("several messages of delegate {} served", delegate));
+	}
+	if message.signature != posted.signature || message.nonce != posted.nonce || message.signing_id != posted.signing_id
+	{
+		mismatches.push("signature, nonce or signing id differ".to_string());
+	}
+	let dropped = posted
+		.message
+		.constraints
+		.iter()
+		.filter(|constraint| {
+			!message.message.constraints.iter().any(|served| {
+				served.constraint_type == constraint.constraint_type && served.payload == constraint.payload
+			})
+		})
+		.count();
+	if dropped > 0 {
+		mismatches.push(format!("{} of {} constraint(s) missing", dropped, posted.message.constraints.len()));
+	}
+	let content = |message: &ConstraintsMessage| {
+		let constraints =
+			message.constraints.iter().map(|c| (c.constraint_type, c.payload.clone())).collect::<Vec<_>>();
+		(message.proposer, message.slot, constraints, message.receivers.clone())
+	};
+	if content(&message.message) != content(&posted.message) {
+		mismatches.push("message content differs".to_string());
+	}
+	mismatches
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::types::{BundleConstraintPayload, create_blob_constraints, create_bundle_constraints};
 	use alloy::primitives::{Address, Bytes};
+	use alloy::signers::{SignerSync, local::PrivateKeySigner};
+	use commitments::types::{cancel_hash, sender_query_hash};
 
 	#[tokio::test]
 	async fn test_validate_commitment_request() -> Result<()> {
@@ -608,4 +1689,763 @@ mod tests {
 		println!("Signed transaction verification integration test passed");
 		Ok(())
 	}
+
+	#[test]
+	fn test_build_slot_info_response() {
+		let response = build_slot_info_response::<()>(1, &[]);
+		assert!(response.slots.is_empty());
+
+		let response = build_slot_info_response(17000, &[(10, ()), (12, ())]);
+		assert_eq!(response.slots.len(), 2);
+		assert_eq!(response.slots[0].slot, 10);
+		assert_eq!(response.slots[1].slot, 12);
+		for slot in &response.slots {
+			assert_eq!(slot.offerings.len(), 1);
+			assert_eq!(slot.offerings[0].chain_id, 17000);
+			assert_eq!(
+				slot.offerings[0].commitment_types,
+				vec![
+					INCLUSION_COMMITMENT_TYPE,
+					EXCLUSION_COMMITMENT_TYPE,
+					ORDERING_COMMITMENT_TYPE,
+					BUNDLE_COMMITMENT_TYPE,
+					BLOB_COMMITMENT_TYPE
+				]
+			);
+		}
+	}
+
+	#[test]
+	fn test_create_commitment_event_recovers_sender() -> Result<()> {
+		let payload = InclusionPayload::random();
+		let sender = payload.sender()?;
+		let constraint = Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payload.abi_encode()? };
+		let request_hash = B256::repeat_byte(0x11);
+
+		let event = create_commitment_event(request_hash, &constraint, payload.slot, CommitmentStatus::Accepted)?;
+		assert_eq!(event.request_hash, request_hash);
+		assert_eq!(event.sender, sender);
+		assert_eq!(event.slot, payload.slot);
+		assert_eq!(event.status, CommitmentStatus::Accepted);
+
+		Ok(())
+	}
+
+	fn signed_payload(signer: &PrivateKeySigner, nonce: u64, priority_fee: u128, slot: u64) -> InclusionPayload {
+		use alloy::consensus::{Signed, TxEip1559};
+		use alloy::eips::eip2718::Encodable2718;
+		use alloy::primitives::TxKind;
+
+		let tx = TxEip1559 {
+			chain_id: 1,
+			nonce,
+			gas_limit: 21000,
+			max_fee_per_gas: 20_000_000_000u128,
+			max_priority_fee_per_gas: priority_fee,
+			to: TxKind::Call(Address::from([0x01; 20])),
+			value: U256::from(1u64),
+			input: Bytes::new(),
+			access_list: Default::default(),
+		};
+		let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+		let mut encoded_tx = Vec::new();
+		TxEnvelope::Eip1559(Signed::new_unhashed(tx, signature)).encode_2718(&mut encoded_tx);
+		InclusionPayload { slot, signed_tx: Bytes::from(encoded_tx) }
+	}
+
+	fn store_commitment(db: &DatabaseContext, payload: &InclusionPayload, request_hash: B256) -> Result<()> {
+		let constraint = Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payload.abi_encode()? };
+		let commitment = SignedCommitment {
+			commitment: Commitment {
+				commitment_type: INCLUSION_COMMITMENT_TYPE,
+				payload: constraint.payload.clone(),
+				request_hash,
+				slasher: Address::repeat_byte(0x01),
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: Signature::new(U256::ZERO, U256::ZERO, false),
+		};
+		db.store_signed_commitment_and_constraint(payload.slot, &request_hash, &commitment, &constraint)?;
+		db.store_tx_hash_commitment(&payload.tx_hash()?, &request_hash)?;
+		db.store_sender_nonce_commitment(&payload.sender()?, 0, &request_hash)?;
+		Ok(())
+	}
+
+	fn new_temp_db() -> Result<DatabaseContext> {
+		let tmp_dir = tempfile::TempDir::new()?;
+		let mut opts = rocksdb::Options::default();
+		opts.create_if_missing(true);
+		let db = rocksdb::DB::open(&opts, tmp_dir.path())?;
+		Ok(DatabaseContext::new(std::sync::Arc::new(db)))
+	}
+
+	#[test]
+	fn test_validate_transaction_fields() -> Result<()> {
+		let signer = PrivateKeySigner::random();
+		let tx = signed_payload(&signer, 0, 2_000_000_000, 100).decode_transaction()?;
+		assert_eq!(intrinsic_gas(&tx), 21000);
+
+		assert!(validate_transaction_fields(&tx, 1, 10_000_000_000).is_ok());
+
+		let err = validate_transaction_fields(&tx, 17000, 10_000_000_000).unwrap_err();
+		assert_eq!(err, TransactionValidationError::ChainIdMismatch { expected: 17000, actual: Some(1) });
+		assert_eq!(err.code(), -32010);
+
+		let err = validate_transaction_fields(&tx, 1, 30_000_000_000).unwrap_err();
+		assert!(matches!(err, TransactionValidationError::FeeCapTooLow { .. }));
+
+		let tx = signed_payload(&signer, 0, 30_000_000_000, 100).decode_transaction()?;
+		let err = validate_transaction_fields(&tx, 1, 10_000_000_000).unwrap_err();
+		assert!(matches!(err, TransactionValidationError::TipAboveFeeCap { .. }));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_validate_sender_account() -> Result<()> {
+		let signer = PrivateKeySigner::random();
+		let tx = signed_payload(&signer, 5, 2_000_000_000, 100).decode_transaction()?;
+		// 21000 gas at 20 gwei plus 1 wei of value
+		let required = U256::from(21000u64 * 20_000_000_000) + U256::from(1u64);
+
+		assert!(validate_sender_account(&tx, 5, 5, required).is_ok());
+		// A future nonce may follow or replace committed transactions
+		assert!(validate_sender_account(&tx, 3, 5, required).is_ok());
+		assert!(validate_sender_account(&tx, 3, 6, required).is_ok());
+
+		let err = validate_sender_account(&tx, 6, 6, required).unwrap_err();
+		assert_eq!(err, TransactionValidationError::NonceTooLow { nonce: 5, account_nonce: 6 });
+
+		// Nonces the block could not fill are not accepted
+		let err = validate_sender_account(&tx, 3, 4, required).unwrap_err();
+		assert_eq!(err, TransactionValidationError::NonceGap { nonce: 5, next_nonce: 4 });
+		assert_eq!(err.code(), -32017);
+
+		let err = validate_sender_account(&tx, 5, 5, required - U256::from(1u64)).unwrap_err();
+		assert_eq!(err.code(), -32015);
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_next_committable_nonce() -> Result<()> {
+		let db = new_temp_db()?;
+		let sender = Address::repeat_byte(0x01);
+		let other = Address::repeat_byte(0x02);
+		assert_eq!(next_committable_nonce(&db, &sender, 3, &[])?, 3);
+
+		// Committed nonces and the transactions ahead in the request are skipped, those of other senders are not
+		db.store_sender_nonce_commitment(&sender, 3, &B256::repeat_byte(0x01))?;
+		db.store_sender_nonce_commitment(&other, 5, &B256::repeat_byte(0x02))?;
+		assert_eq!(next_committable_nonce(&db, &sender, 3, &[])?, 4);
+		assert_eq!(next_committable_nonce(&db, &sender, 3, &[(sender, 4), (other, 5)])?, 5);
+		assert_eq!(next_committable_nonce(&db, &other, 3, &[])?, 3);
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_slot_gas_budget() -> Result<()> {
+		let db = new_temp_db()?;
+		let signer = PrivateKeySigner::random();
+		let committed = signed_payload(&signer, 0, 2_000_000_000, 100);
+		let committed_hash = B256::repeat_byte(0x01);
+		store_commitment(&db, &committed, committed_hash)?;
+
+		let other = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		assert!(check_slot_gas_budget(&db, &other, None, 42000).is_ok());
+		assert!(check_slot_gas_budget(&db, &other, None, 41999).is_err());
+
+		// Other slots are unaffected
+		let next_slot = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 101);
+		assert!(check_slot_gas_budget(&db, &next_slot, None, 21000).is_ok());
+
+		// A replacement releases the gas of the commitment it replaces
+		let replacement = signed_payload(&signer, 0, 3_000_000_000, 100);
+		assert!(check_slot_gas_budget(&db, &replacement, Some((100, committed_hash)), 21000).is_ok());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_gas_reservations() -> Result<()> {
+		let db = new_temp_db()?;
+		let committed = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		store_commitment(&db, &committed, B256::repeat_byte(0x01))?;
+		let reservations = GasReservations::default();
+
+		// Requests being signed hold their gas, a concurrent one no longer fits in the budget
+		let first = reservations.reserve(&db, 100, 21000, 0, 63000)?;
+		let second = reservations.reserve(&db, 100, 21000, 0, 63000)?;
+		assert_eq!(reservations.pending(100), 42000);
+		assert!(reservations.reserve(&db, 100, 21000, 0, 63000).is_err());
+		assert!(reservations.reserve(&db, 101, 21000, 0, 21000).is_ok());
+
+		// A replacement only needs room for its extra gas
+		assert!(reservations.reserve(&db, 100, 21000, 21000, 63000).is_ok());
+
+		// Dropped once stored or failed, the gas is released
+		drop(first);
+		assert_eq!(reservations.pending(100), 21000);
+		assert!(reservations.reserve(&db, 100, 21000, 0, 63000).is_ok());
+		drop(second);
+		assert_eq!(reservations.pending(100), 0);
+		Ok(())
+	}
+
+	#[test]
+	fn test_validate_exclusion_request() -> Result<()> {
+		let slasher: Address = "0x1234567890123456789012345678901234567890".parse()?;
+		let owner = PrivateKeySigner::random();
+		let payment = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		let mut payload = ExclusionRequestPayload {
+			slot: 100,
+			tx_hash: B256::repeat_byte(0x01),
+			payment_tx: payment.signed_tx.clone(),
+			signature: Bytes::new(),
+		};
+		payload.signature = Bytes::from(owner.sign_hash_sync(&payload.authorization_hash())?.as_bytes().to_vec());
+		let request =
+			CommitmentRequest { commitment_type: EXCLUSION_COMMITMENT_TYPE, payload: payload.abi_encode()?, slasher };
+		assert_eq!(validate_exclusion_request(&request)?, payload);
+		assert!(verify_owner_authorization(payload.authorizer()?, owner.address()).is_ok());
+		assert!(verify_owner_authorization(payload.authorizer()?, payment.sender()?).is_err());
+
+		let zero_hash = ExclusionRequestPayload { tx_hash: B256::ZERO, ..payload.clone() };
+		let invalid = CommitmentRequest { payload: zero_hash.abi_encode()?, ..request.clone() };
+		assert!(validate_exclusion_request(&invalid).is_err());
+
+		// The payment must be a signed transaction other than the excluded one
+		let unsigned = ExclusionRequestPayload { payment_tx: Bytes::from(vec![0x01]), ..payload.clone() };
+		let invalid = CommitmentRequest { payload: unsigned.abi_encode()?, ..request.clone() };
+		assert!(validate_exclusion_request(&invalid).is_err());
+		let self_paid = ExclusionRequestPayload { tx_hash: payment.tx_hash()?, ..payload.clone() };
+		let invalid = CommitmentRequest { payload: self_paid.abi_encode()?, ..request.clone() };
+		assert!(validate_exclusion_request(&invalid).is_err());
+
+		let request =
+			CommitmentRequest { commitment_type: INCLUSION_COMMITMENT_TYPE, payload: payload.abi_encode()?, slasher };
+		assert!(validate_exclusion_request(&request).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_validate_bundle_request() -> Result<()> {
+		let slasher: Address = "0x1234567890123456789012345678901234567890".parse()?;
+		let signer = PrivateKeySigner::random();
+		let first = signed_payload(&signer, 0, 2_000_000_000, 100);
+		let second = signed_payload(&signer, 1, 2_000_000_000, 100);
+
+		let bundle = BundlePayload { slot: 100, signed_txs: vec![first.signed_tx.clone(), second.signed_tx.clone()] };
+		let request =
+			CommitmentRequest { commitment_type: BUNDLE_COMMITMENT_TYPE, payload: bundle.abi_encode()?, slasher };
+		assert_eq!(validate_bundle_request(&request)?, bundle);
+
+		let (bundle_constraint, members) = create_bundle_constraints(&bundle)?;
+		assert_eq!(
+			BundleConstraintPayload::abi_decode(&bundle_constraint.payload)?.tx_hashes,
+			vec![first.tx_hash()?, second.tx_hash()?]
+		);
+		assert_eq!(members.len(), 2);
+		assert_eq!(InclusionPayload::abi_decode(&members[1].payload)?.tx_hash()?, second.tx_hash()?);
+		assert_ne!(member_commitment_hash(&B256::ZERO, 0), member_commitment_hash(&B256::ZERO, 1));
+
+		// Empty bundles and repeated transactions are rejected
+		let empty = BundlePayload { slot: 100, signed_txs: vec![] };
+		assert!(
+			validate_bundle_request(&CommitmentRequest { payload: empty.abi_encode()?, ..request.clone() }).is_err()
+		);
+		let repeated = BundlePayload { slot: 100, signed_txs: vec![first.signed_tx.clone(), first.signed_tx.clone()] };
+		assert!(validate_bundle_request(&CommitmentRequest { payload: repeated.abi_encode()?, ..request }).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_validate_blob_request() -> Result<()> {
+		let slasher = Address::repeat_byte(0x01);
+		let versioned_hash = alloy::eips::eip4844::kzg_to_versioned_hash(&[0x11; 48]);
+		let payload = InclusionPayload::random_blob(vec![versioned_hash]);
+		let request =
+			CommitmentRequest { commitment_type: BLOB_COMMITMENT_TYPE, payload: payload.abi_encode()?, slasher };
+		assert_eq!(validate_blob_request(&request)?.tx_hash()?, payload.tx_hash()?);
+
+		let (blob_constraint, inclusion_constraint) = create_blob_constraints(&payload)?;
+		let blob_payload = BlobConstraintPayload::abi_decode(&blob_constraint.payload)?;
+		assert_eq!(blob_payload.tx_hash, payload.tx_hash()?);
+		assert_eq!(blob_payload.blob_versioned_hashes, vec![versioned_hash]);
+		assert_eq!(inclusion_constraint.constraint_type, INCLUSION_CONSTRAINT_TYPE);
+
+		// Transactions without blobs and unknown versioned hash versions are rejected
+		let plain = InclusionPayload::random();
+		assert!(validate_blob_request(&CommitmentRequest { payload: plain.abi_encode()?, ..request.clone() }).is_err());
+		let unversioned = InclusionPayload::random_blob(vec![B256::repeat_byte(0x02)]);
+		assert!(validate_blob_request(&CommitmentRequest { payload: unversioned.abi_encode()?, ..request }).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_bundle_member_cannot_be_replaced() -> Result<()> {
+		let db = new_temp_db()?;
+		let signer = PrivateKeySigner::random();
+		let member = signed_payload(&signer, 0, 1_000_000_000, 100);
+		let bundle = BundlePayload { slot: 100, signed_txs: vec![member.signed_tx.clone()] };
+
+		let request_hash = B256::repeat_byte(0x01);
+		let member_hash = member_commitment_hash(&request_hash, 0);
+		let (bundle_constraint, member_constraints) = create_bundle_constraints(&bundle)?;
+		let commitment = SignedCommitment {
+			commitment: Commitment {
+				commitment_type: BUNDLE_COMMITMENT_TYPE,
+				payload: bundle.abi_encode()?,
+				request_hash,
+				slasher: Address::repeat_byte(0x01),
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: Signature::new(U256::ZERO, U256::ZERO, false),
+		};
+		db.store_grouped_commitment(
+			100,
+			&request_hash,
+			&commitment,
+			&bundle_constraint,
+			&[(member_hash, member_constraints[0].clone())],
+		)?;
+		db.store_sender_nonce_commitment(&member.sender()?, 0, &member_hash)?;
+
+		let outbidding = signed_payload(&signer, 0, 5_000_000_000, 100);
+		assert!(find_replaced_commitment(&db, &outbidding).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_exclusion_conflicts() -> Result<()> {
+		let db = new_temp_db()?;
+		let payload = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		let exclusion = ExclusionPayload { slot: 100, tx_hash: payload.tx_hash()? };
+
+		assert!(check_exclusion_conflict(&db, &exclusion).is_ok());
+		db.store_exclusion_commitment(exclusion.slot, &exclusion.tx_hash, &B256::repeat_byte(0x02))?;
+
+		// The transaction can no longer be excluded again or committed for inclusion in that slot
+		assert!(check_exclusion_conflict(&db, &exclusion).is_err());
+		assert!(check_excluded_transaction(&db, &payload).is_err());
+		let other_slot = InclusionPayload { slot: 101, signed_tx: payload.signed_tx.clone() };
+		assert!(check_excluded_transaction(&db, &other_slot).is_ok());
+
+		// An exclusion cannot contradict an inclusion commitment for the same slot
+		let committed = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		store_commitment(&db, &committed, B256::repeat_byte(0x03))?;
+		let contradicting = ExclusionPayload { slot: 100, tx_hash: committed.tx_hash()? };
+		assert!(check_exclusion_conflict(&db, &contradicting).is_err());
+		assert!(check_exclusion_conflict(&db, &ExclusionPayload { slot: 101, ..contradicting }).is_ok());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_ordering_conflicts() -> Result<()> {
+		let db = new_temp_db()?;
+		let owner = PrivateKeySigner::random();
+		let tx_a = signed_payload(&owner, 0, 2_000_000_000, 100);
+		let tx_b = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		store_commitment(&db, &tx_a, B256::repeat_byte(0x01))?;
+		store_commitment(&db, &tx_b, B256::repeat_byte(0x02))?;
+
+		// Only committed transactions have an owner who may order them
+		assert_eq!(committed_transaction_owner(&db, &tx_a.tx_hash()?)?, owner.address());
+		assert!(committed_transaction_owner(&db, &B256::repeat_byte(0x09)).is_err());
+
+		let store_ordering = |ordering: &OrderingPayload, request_hash: B256| -> Result<()> {
+			let constraint = Constraint { constraint_type: ORDERING_CONSTRAINT_TYPE, payload: ordering.abi_encode()? };
+			let commitment = SignedCommitment {
+				commitment: Commitment {
+					commitment_type: ORDERING_COMMITMENT_TYPE,
+					payload: constraint.payload.clone(),
+					request_hash,
+					slasher: Address::repeat_byte(0x01),
+				},
+				nonce: 0,
+				signing_id: B256::ZERO,
+				signature: Signature::new(U256::ZERO, U256::ZERO, false),
+			};
+			db.store_signed_commitment_and_constraint(ordering.slot, &request_hash, &commitment, &constraint)?;
+			Ok(())
+		};
+
+		// The ordered transaction must be committed for inclusion in the same slot
+		let uncommitted = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		assert!(
+			check_ordering_conflict(&db, &OrderingPayload { slot: 100, tx_hash: uncommitted.tx_hash()?, max_index: 0 })
+				.is_err()
+		);
+		assert!(
+			check_ordering_conflict(&db, &OrderingPayload { slot: 101, tx_hash: tx_a.tx_hash()?, max_index: 0 })
+				.is_err()
+		);
+
+		let top_of_block = OrderingPayload { slot: 100, tx_hash: tx_a.tx_hash()?, max_index: 0 };
+		assert!(check_ordering_conflict(&db, &top_of_block).is_ok());
+		store_ordering(&top_of_block, B256::repeat_byte(0x03))?;
+		assert_eq!(slot_ordering_commitments(&db, 100)?, vec![(B256::repeat_byte(0x03), top_of_block.clone())]);
+
+		// The same transaction cannot be ordered twice, and index 0 is taken
+		assert!(check_ordering_conflict(&db, &OrderingPayload { max_index: 5, ..top_of_block }).is_err());
+		let second = OrderingPayload { slot: 100, tx_hash: tx_b.tx_hash()?, max_index: 0 };
+		assert!(check_ordering_conflict(&db, &second).is_err());
+		assert!(check_ordering_conflict(&db, &OrderingPayload { max_index: 1, ..second }).is_ok());
+
+		// Withdrawing the inclusion commitment withdraws its ordering
+		withdraw_dependent_orderings(&db, &B256::repeat_byte(0x01), 100, TombstoneReason::Cancelled)?;
+		assert!(slot_ordering_commitments(&db, 100)?.is_empty());
+		assert!(db.get_tombstone(&B256::repeat_byte(0x03))?.is_some());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_constraint_conflicts() -> Result<()> {
+		let db = new_temp_db()?;
+		let slasher = Address::repeat_byte(0x01);
+		let committed = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		store_commitment(&db, &committed, B256::repeat_byte(0x01))?;
+
+		// Excluding a transaction committed for inclusion in the same slot is refused, not the slot's other requests
+		let payment = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		let exclusion = ExclusionRequestPayload {
+			slot: 100,
+			tx_hash: committed.tx_hash()?,
+			payment_tx: payment.signed_tx.clone(),
+			signature: Bytes::new(),
+		};
+		let request =
+			CommitmentRequest { commitment_type: EXCLUSION_COMMITMENT_TYPE, payload: exclusion.abi_encode()?, slasher };
+		assert!(check_constraint_conflicts(&db, 100, &request).is_err());
+
+		let other_slot =
+			CommitmentRequest { payload: ExclusionRequestPayload { slot: 101, ..exclusion }.abi_encode()?, ..request };
+		assert!(check_constraint_conflicts(&db, 101, &other_slot).is_ok());
+		let inclusion =
+			CommitmentRequest { commitment_type: INCLUSION_COMMITMENT_TYPE, payload: payment.abi_encode()?, slasher };
+		assert!(check_constraint_conflicts(&db, 100, &inclusion).is_ok());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_delegation_terms() -> Result<()> {
+		let db = new_temp_db()?;
+		let slasher = Address::repeat_byte(0x01);
+		let committed = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		store_commitment(&db, &committed, B256::repeat_byte(0x01))?;
+
+		let payment = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		let inclusion =
+			CommitmentRequest { commitment_type: INCLUSION_COMMITMENT_TYPE, payload: payment.abi_encode()?, slasher };
+		let exclusion = ExclusionRequestPayload {
+			slot: 100,
+			tx_hash: B256::repeat_byte(0x02),
+			payment_tx: payment.signed_tx.clone(),
+			signature: Bytes::new(),
+		};
+		let exclusion =
+			CommitmentRequest { commitment_type: EXCLUSION_COMMITMENT_TYPE, payload: exclusion.abi_encode()?, slasher };
+		assert!(check_delegation_terms(&db, &DelegationMetadata::default(), 100, 1_000, &exclusion).is_ok());
+
+		// A paid commitment also needs the inclusion constraint of its payment to be allowed
+		let inclusions_only =
+			DelegationMetadata { allowed_constraint_types: vec![INCLUSION_CONSTRAINT_TYPE], ..Default::default() };
+		assert!(check_delegation_terms(&db, &inclusions_only, 100, 1_000, &inclusion).is_ok());
+		assert!(matches!(
+			check_delegation_terms(&db, &inclusions_only, 100, 1_000, &exclusion),
+			Err(CommitmentsRpcError::InvalidPayload(_))
+		));
+		let exclusions_only =
+			DelegationMetadata { allowed_constraint_types: vec![EXCLUSION_CONSTRAINT_TYPE], ..Default::default() };
+		assert!(check_delegation_terms(&db, &exclusions_only, 100, 1_000, &exclusion).is_err());
+
+		// The slot already holds one constraint and an exclusion brings two
+		let two = DelegationMetadata { max_constraints: Some(2), ..Default::default() };
+		assert!(check_delegation_terms(&db, &two, 100, 1_000, &inclusion).is_ok());
+		assert!(matches!(
+			check_delegation_terms(&db, &two, 100, 1_000, &exclusion),
+			Err(CommitmentsRpcError::OverCapacity(_))
+		));
+
+		let lapsing = DelegationMetadata { valid_until: Some(999), ..Default::default() };
+		assert!(matches!(
+			check_delegation_terms(&db, &lapsing, 100, 1_000, &inclusion),
+			Err(CommitmentsRpcError::NoDelegation(_))
+		));
+		Ok(())
+	}
+
+	#[test]
+	fn test_apply_delegation_terms() {
+		let slot_info = || build_slot_info_response(1, &[(10, ())]).slots.remove(0);
+
+		let mut open = slot_info();
+		assert!(apply_delegation_terms(&mut open, &DelegationMetadata::default(), 1_000, 5));
+		assert_eq!(open.offerings[0].commitment_types, slot_info().offerings[0].commitment_types);
+
+		// Every commitment type carries an inclusion constraint but exclusions also need theirs
+		let terms = DelegationMetadata {
+			max_constraints: Some(5),
+			allowed_constraint_types: vec![INCLUSION_CONSTRAINT_TYPE, EXCLUSION_CONSTRAINT_TYPE],
+			..Default::default()
+		};
+		let mut limited = slot_info();
+		assert!(apply_delegation_terms(&mut limited, &terms, 1_000, 4));
+		assert_eq!(limited.offerings[0].commitment_types, vec![INCLUSION_COMMITMENT_TYPE, EXCLUSION_COMMITMENT_TYPE]);
+
+		// Slots whose terms take no more constraints or lapse before the slot are not offered
+		assert!(!apply_delegation_terms(&mut slot_info(), &terms, 1_000, 5));
+		let lapsing = DelegationMetadata { valid_until: Some(999), ..Default::default() };
+		assert!(!apply_delegation_terms(&mut slot_info(), &lapsing, 1_000, 0));
+		let blobs_only =
+			DelegationMetadata { allowed_constraint_types: vec![BLOB_CONSTRAINT_TYPE], ..Default::default() };
+		assert!(!apply_delegation_terms(&mut slot_info(), &blobs_only, 1_000, 0));
+	}
+
+	#[test]
+	fn test_validate_ordering_request() -> Result<()> {
+		let slasher: Address = "0x1234567890123456789012345678901234567890".parse()?;
+		let payment = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		let payload = OrderingRequestPayload {
+			slot: 100,
+			tx_hash: B256::repeat_byte(0x01),
+			max_index: 0,
+			payment_tx: payment.signed_tx.clone(),
+			signature: Bytes::new(),
+		};
+		let request =
+			CommitmentRequest { commitment_type: ORDERING_COMMITMENT_TYPE, payload: payload.abi_encode()?, slasher };
+		assert_eq!(validate_ordering_request(&request)?, payload);
+
+		let zero_hash = OrderingRequestPayload { tx_hash: B256::ZERO, ..payload.clone() };
+		let invalid = CommitmentRequest { payload: zero_hash.abi_encode()?, ..request.clone() };
+		assert!(validate_ordering_request(&invalid).is_err());
+
+		// The ordered transaction cannot pay for its own ordering
+		let self_paid = OrderingRequestPayload { tx_hash: payment.tx_hash()?, ..payload.clone() };
+		let invalid = CommitmentRequest { payload: self_paid.abi_encode()?, ..request.clone() };
+		assert!(validate_ordering_request(&invalid).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_check_duplicate_commitment() -> Result<()> {
+		let db = new_temp_db()?;
+		let payload = signed_payload(&PrivateKeySigner::random(), 0, 2_000_000_000, 100);
+		let request_hash = B256::repeat_byte(0x01);
+
+		assert!(check_duplicate_commitment(&db, &payload).is_ok());
+		store_commitment(&db, &payload, request_hash)?;
+
+		// The same transaction cannot be committed again, for this or another slot
+		assert!(check_duplicate_commitment(&db, &payload).is_err());
+		let other_slot = InclusionPayload { slot: 101, signed_tx: payload.signed_tx.clone() };
+		assert!(check_duplicate_commitment(&db, &other_slot).is_err());
+
+		// Once withdrawn the transaction may be committed again
+		db.tombstone_commitment(&CommitmentTombstone {
+			request_hash,
+			slot: payload.slot,
+			reason: TombstoneReason::Cancelled,
+		})?;
+		assert!(check_duplicate_commitment(&db, &payload).is_ok());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_validate_commitment_window() {
+		assert!(validate_commitment_window(101, 100).is_ok());
+		assert!(validate_commitment_window(100 + LOOKAHEAD_WINDOW_SIZE, 100).is_ok());
+		assert!(validate_commitment_window(100, 100).is_err());
+		assert!(validate_commitment_window(99, 100).is_err());
+		assert!(validate_commitment_window(101 + LOOKAHEAD_WINDOW_SIZE, 100).is_err());
+	}
+
+	#[test]
+	fn test_validate_commitment_timing() {
+		let slot_clock = SlotClock::new(&Chain::Mainnet);
+		let current = slot_clock.current_slot();
+
+		assert!(validate_commitment_timing(current + 10, &slot_clock, 14_000).is_ok());
+		assert!(validate_commitment_timing(current, &slot_clock, 14_000).is_err());
+		// A longer cutoff rejects requests that would otherwise be in time
+		assert!(validate_commitment_timing(current + 2, &slot_clock, 60_000).is_err());
+	}
+
+	#[test]
+	fn test_verify_cancel_signature() -> Result<()> {
+		let signer = PrivateKeySigner::random();
+		let request_hash = B256::repeat_byte(0x42);
+		let signature = signer.sign_message_sync(cancel_hash(&request_hash, 100).as_slice())?;
+
+		assert!(verify_cancel_signature(&request_hash, 100, &signature, &signer.address()).is_ok());
+		assert!(verify_cancel_signature(&request_hash, 100, &signature, &Address::repeat_byte(0x01)).is_err());
+		assert!(verify_cancel_signature(&B256::repeat_byte(0x43), 100, &signature, &signer.address()).is_err());
+		// The signature only covers the signed slot
+		assert!(verify_cancel_signature(&request_hash, 101, &signature, &signer.address()).is_err());
+
+		// A raw signature over the request hash or the digest is refused
+		let raw = signer.sign_hash_sync(&request_hash)?;
+		assert!(verify_cancel_signature(&request_hash, 100, &raw, &signer.address()).is_err());
+		let raw = signer.sign_hash_sync(&cancel_hash(&request_hash, 100))?;
+		assert!(verify_cancel_signature(&request_hash, 100, &raw, &signer.address()).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_verify_sender_query_signature() -> Result<()> {
+		let signer = PrivateKeySigner::random();
+		let sender = signer.address();
+		let now = 1_700_000_000;
+		let expiry = now + 60;
+		let signature = signer.sign_message_sync(sender_query_hash(&sender, 10, 20, expiry).as_slice())?;
+
+		assert!(verify_sender_query_signature(&sender, 10, 20, expiry, &signature, now).is_ok());
+		// The signature only covers the signed range, sender and expiry
+		assert!(verify_sender_query_signature(&sender, 10, 21, expiry, &signature, now).is_err());
+		assert!(verify_sender_query_signature(&sender, 9, 20, expiry, &signature, now).is_err());
+		assert!(verify_sender_query_signature(&sender, 10, 20, expiry + 1, &signature, now).is_err());
+		assert!(verify_sender_query_signature(&Address::repeat_byte(0x01), 10, 20, expiry, &signature, now).is_err());
+
+		// It is refused once expired, and when it would stay valid for too long
+		assert!(verify_sender_query_signature(&sender, 10, 20, expiry, &signature, expiry + 1).is_err());
+		let far = now + MAX_SENDER_QUERY_VALIDITY_SECS + 1;
+		let signature = signer.sign_message_sync(sender_query_hash(&sender, 10, 20, far).as_slice())?;
+		assert!(verify_sender_query_signature(&sender, 10, 20, far, &signature, now).is_err());
+
+		// A signature over the raw digest, as a transaction or another prehash would be, is refused
+		let raw = signer.sign_hash_sync(&sender_query_hash(&sender, 10, 20, expiry))?;
+		assert!(verify_sender_query_signature(&sender, 10, 20, expiry, &raw, now).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_find_replaced_commitment() -> Result<()> {
+		let db = new_temp_db()?;
+		let signer = PrivateKeySigner::random();
+		let previous_hash = B256::repeat_byte(0x01);
+
+		// Nothing to replace yet
+		let original = signed_payload(&signer, 0, 2_000_000_000, 100);
+		assert_eq!(find_replaced_commitment(&db, &original)?, None);
+
+		store_commitment(&db, &original, previous_hash)?;
+
+		// Same fee is underpriced, a higher fee replaces the earlier commitment
+		assert!(find_replaced_commitment(&db, &signed_payload(&signer, 0, 2_000_000_000, 100)).is_err());
+		let replacement = signed_payload(&signer, 0, 3_000_000_000, 100);
+		assert_eq!(find_replaced_commitment(&db, &replacement)?, Some((100, previous_hash)));
+
+		// A different nonce does not replace anything
+		assert_eq!(find_replaced_commitment(&db, &signed_payload(&signer, 1, 3_000_000_000, 100))?, None);
+
+		// Once constraints are posted the commitment can no longer be replaced
+		db.finalize_signed_constraints(100)?;
+		assert!(find_replaced_commitment(&db, &replacement).is_err());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_settle_constraint() -> Result<()> {
+		let included = InclusionPayload { slot: 10, ..InclusionPayload::random() };
+		let missing = InclusionPayload { slot: 10, ..InclusionPayload::random() };
+		let (first, second) = (B256::random(), B256::random());
+		let block = vec![first, included.tx_hash()?, second];
+		let registry = default_registry();
+
+		let inclusion = |payload: &InclusionPayload| -> Result<Constraint> {
+			Ok(Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payload.abi_encode()? })
+		};
+		assert_eq!(settle_constraint(&registry, &inclusion(&included)?, &block)?, CommitmentStatus::Included);
+		assert_eq!(settle_constraint(&registry, &inclusion(&missing)?, &block)?, CommitmentStatus::Missed);
+		// A slot without a block misses inclusions
+		assert_eq!(settle_constraint(&registry, &inclusion(&included)?, &[])?, CommitmentStatus::Missed);
+
+		let exclusion = |tx_hash: B256| -> Result<Constraint> {
+			Ok(Constraint {
+				constraint_type: EXCLUSION_CONSTRAINT_TYPE,
+				payload: ExclusionPayload { slot: 10, tx_hash }.abi_encode()?,
+			})
+		};
+		assert_eq!(settle_constraint(&registry, &exclusion(missing.tx_hash()?)?, &block)?, CommitmentStatus::Excluded);
+		assert_eq!(settle_constraint(&registry, &exclusion(first)?, &block)?, CommitmentStatus::Missed);
+
+		let ordering = |max_index: u64| -> Result<Constraint> {
+			Ok(Constraint {
+				constraint_type: ORDERING_CONSTRAINT_TYPE,
+				payload: OrderingPayload { slot: 10, tx_hash: second, max_index }.abi_encode()?,
+			})
+		};
+		assert_eq!(settle_constraint(&registry, &ordering(2)?, &block)?, CommitmentStatus::Included);
+		assert_eq!(settle_constraint(&registry, &ordering(1)?, &block)?, CommitmentStatus::Missed);
+
+		let bundle = |tx_hashes: Vec<B256>| -> Result<Constraint> {
+			Ok(Constraint {
+				constraint_type: BUNDLE_CONSTRAINT_TYPE,
+				payload: BundleConstraintPayload { slot: 10, tx_hashes }.abi_encode()?,
+			})
+		};
+		assert_eq!(
+			settle_constraint(&registry, &bundle(vec![included.tx_hash()?, second])?, &block)?,
+			CommitmentStatus::Included
+		);
+		assert_eq!(settle_constraint(&registry, &bundle(vec![first, second])?, &block)?, CommitmentStatus::Missed);
+
+		assert!(
+			settle_constraint(&registry, &Constraint { constraint_type: 99, payload: Bytes::new() }, &block).is_err()
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_served_constraints_mismatches() {
+		let constraint = |payload: u8| Constraint { constraint_type: 1, payload: Bytes::from(vec![payload]) };
+		let posted = SignedConstraints {
+			message: ConstraintsMessage {
+				proposer: BlsPublicKey::repeat_byte(1),
+				delegate: BlsPublicKey::repeat_byte(2),
+				slot: 10,
+				constraints: vec![constraint(1), constraint(2)],
+				receivers: vec![BlsPublicKey::repeat_byte(3)],
+			},
+			nonce: 7,
+			signing_id: B256::repeat_byte(4),
+			signature: BlsSignature::repeat_byte(5),
+		};
+		let mut other_delegate = posted.clone();
+		other_delegate.message.delegate = BlsPublicKey::repeat_byte(6);
+
+		assert!(served_constraints_mismatches(&posted, &[other_delegate.clone(), posted.clone()]).is_empty());
+		assert_eq!(served_constraints_mismatches(&posted, &[other_delegate]).len(), 1);
+
+		// A relay dropping a constraint changes the content, the signature no longer covers it
+		let mut dropped = posted.clone();
+		dropped.message.constraints.pop();
+		assert_eq!(
+			served_constraints_mismatches(&posted, &[dropped]),
+			vec!["1 of 2 constraint(s) missing".to_string(), "message content differs".to_string()]
+		);
+
+		let mut resigned = posted.clone();
+		resigned.signature = BlsSignature::repeat_byte(8);
+		resigned.message.receivers.clear();
+		assert_eq!(
+			served_constraints_mismatches(&posted, &[resigned]),
+			vec!["signature, nonce or signing id differ".to_string(), "message content differs".to_string()]
+		);
+	}
 }
@@ -0,0 +1,252 @@
+//! `ConstraintHandler`s of the constraint types of this crate and the registry serving all of them.
+
+use alloy::consensus::Transaction;
+use alloy::primitives::{B256, Bytes};
+
+use constraints::conflicts::ConstraintEffect;
+use constraints::error::{ConstraintsError, Result};
+use constraints::registry::{BlockProofs, ConstraintHandler, ConstraintRegistry};
+use constraints::types::Constraint;
+
+use crate::constants::{
+	BLOB_CONSTRAINT_TYPE, BUNDLE_CONSTRAINT_TYPE, EXCLUSION_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE,
+	MAX_BUNDLE_TRANSACTIONS, ORDERING_CONSTRAINT_TYPE,
+};
+use crate::proofs::{BundleProof, ExclusionProof, InclusionProof, verify_exclusion, verify_inclusion};
+use crate::types::{
+	BlobConstraintPayload, BundleConstraintPayload, ExclusionPayload, InclusionPayload, OrderingPayload,
+};
+
+/// Registry of the inclusion, exclusion, ordering, bundle and blob constraint types
+pub fn default_registry() -> ConstraintRegistry {
+	ConstraintRegistry::new()
+		.register(INCLUSION_CONSTRAINT_TYPE, InclusionHandler)
+		.register(EXCLUSION_CONSTRAINT_TYPE, ExclusionHandler)
+		.register(ORDERING_CONSTRAINT_TYPE, OrderingHandler)
+		.register(BUNDLE_CONSTRAINT_TYPE, BundleHandler)
+		.register(BLOB_CONSTRAINT_TYPE, BlobHandler)
+}
+
+/// Constraint payloads that do not decode are invalid requests
+fn invalid_payload(error: eyre::Report) -> ConstraintsError {
+	ConstraintsError::InvalidRequest(format!("{error:#}"))
+}
+
+/// Errors if the proof is not for the expected transaction
+fn check_tx_hash(proven: B256, expected: B256) -> Result<()> {
+	if proven != expected {
+		return Err(ConstraintsError::InvalidProof("Transaction hash mismatch".to_string()));
+	}
+	Ok(())
+}
+
+/// Errors if a blob of the constraint is missing from the block's blobs bundle
+pub fn check_blob_availability(payload: &BlobConstraintPayload, blob_versioned_hashes: &[B256]) -> Result<()> {
+	if let Some(missing) = payload.blob_versioned_hashes.iter().find(|hash| !blob_versioned_hashes.contains(hash)) {
+		return Err(ConstraintsError::InvalidProof(format!(
+			"Blob {} of transaction {} is missing from the blobs bundle",
+			missing, payload.tx_hash
+		)));
+	}
+	Ok(())
+}
+
+/// A transaction is included in the block
+pub struct InclusionHandler;
+
+impl ConstraintHandler for InclusionHandler {
+	fn validate_payload(&self, payload: &Bytes) -> Result<()> {
+		InclusionPayload::abi_decode(payload).map_err(invalid_payload)?.tx_hash().map_err(invalid_payload)?;
+		Ok(())
+	}
+
+	fn check_proof(&self, constraint: &Constraint, proof: &Bytes) -> Result<()> {
+		let tx_hash = InclusionPayload::abi_decode(&constraint.payload)
+			.map_err(invalid_payload)?
+			.tx_hash()
+			.map_err(invalid_payload)?;
+		check_tx_hash(InclusionProof::from_bytes(proof)?.tx_hash, tx_hash)
+	}
+
+	fn verify_proof(&self, _constraint: &Constraint, proof: &Bytes, block: &dyn BlockProofs) -> Result<()> {
+		verify_inclusion(block, &InclusionProof::from_bytes(proof)?)?;
+		Ok(())
+	}
+
+	fn effects(&self, payload: &Bytes) -> Result<Vec<ConstraintEffect>> {
+		let payload = InclusionPayload::abi_decode(payload).map_err(invalid_payload)?;
+		let gas_limit = payload.decode_transaction().ok().map(|tx| tx.gas_limit());
+		Ok(vec![ConstraintEffect::Includes { tx_hash: payload.tx_hash().map_err(invalid_payload)?, gas_limit }])
+	}
+}
+
+/// A transaction is not included in the block
+pub struct ExclusionHandler;
+
+impl ConstraintHandler for ExclusionHandler {
+	fn validate_payload(&self, payload: &Bytes) -> Result<()> {
+		ExclusionPayload::abi_decode(payload).map_err(invalid_payload)?;
+		Ok(())
+	}
+
+	fn check_proof(&self, constraint: &Constraint, proof: &Bytes) -> Result<()> {
+		let payload = ExclusionPayload::abi_decode(&constraint.payload).map_err(invalid_payload)?;
+		if ExclusionProof::from_bytes(proof)?.tx_hash != payload.tx_hash {
+			return Err(ConstraintsError::InvalidProof("Excluded transaction hash mismatch".to_string()));
+		}
+		Ok(())
+	}
+
+	fn verify_proof(&self, _constraint: &Constraint, proof: &Bytes, block: &dyn BlockProofs) -> Result<()> {
+		verify_exclusion(block, &ExclusionProof::from_bytes(proof)?)?;
+		Ok(())
+	}
+
+	fn effects(&self, payload: &Bytes) -> Result<Vec<ConstraintEffect>> {
+		Ok(vec![ConstraintEffect::Excludes {
+			tx_hash: ExclusionPayload::abi_decode(payload).map_err(invalid_payload)?.tx_hash,
+		}])
+	}
+}
+
+/// A transaction is included at or before an index of the block
+pub struct OrderingHandler;
+
+impl ConstraintHandler for OrderingHandler {
+	fn validate_payload(&self, payload: &Bytes) -> Result<()> {
+		OrderingPayload::abi_decode(payload).map_err(invalid_payload)?;
+		Ok(())
+	}
+
+	fn check_proof(&self, constraint: &Constraint, proof: &Bytes) -> Result<()> {
+		let payload = OrderingPayload::abi_decode(&constraint.payload).map_err(invalid_payload)?;
+		let proof = InclusionProof::from_bytes(proof)?;
+		check_tx_hash(proof.tx_hash, payload.tx_hash)?;
+		if proof.tx_index as u64 > payload.max_index {
+			return Err(ConstraintsError::InvalidProof(format!(
+				"Transaction {} is at index {}, beyond the committed maximum index {}",
+				payload.tx_hash, proof.tx_index, payload.max_index
+			)));
+		}
+		Ok(())
+	}
+
+	fn verify_proof(&self, _constraint: &Constraint, proof: &Bytes, block: &dyn BlockProofs) -> Result<()> {
+		verify_inclusion(block, &InclusionProof::from_bytes(proof)?)?;
+		Ok(())
+	}
+
+	fn effects(&self, payload: &Bytes) -> Result<Vec<ConstraintEffect>> {
+		let payload = OrderingPayload::abi_decode(payload).map_err(invalid_payload)?;
+		Ok(vec![ConstraintEffect::AtOrBefore { tx_hash: payload.tx_hash, max_index: payload.max_index }])
+	}
+}
+
+/// Transactions are included contiguously and in order
+pub struct BundleHandler;
+
+impl ConstraintHandler for BundleHandler {
+	fn validate_payload(&self, payload: &Bytes) -> Result<()> {
+		let payload = BundleConstraintPayload::abi_decode(payload).map_err(invalid_payload)?;
+		if payload.tx_hashes.is_empty() || payload.tx_hashes.len() > MAX_BUNDLE_TRANSACTIONS {
+			return Err(ConstraintsError::InvalidRequest(format!(
+				"Bundle must hold between 1 and {} transactions, got {}",
+				MAX_BUNDLE_TRANSACTIONS,
+				payload.tx_hashes.len()
+			)));
+		}
+		Ok(())
+	}
+
+	fn check_proof(&self, constraint: &Constraint, proof: &Bytes) -> Result<()> {
+		let payload = BundleConstraintPayload::abi_decode(&constraint.payload).map_err(invalid_payload)?;
+		let proof = BundleProof::from_bytes(proof)?;
+		let proven = proof.proofs.iter().map(|p| p.tx_hash).collect::<Vec<_>>();
+		if proven != payload.tx_hashes {
+			return Err(ConstraintsError::InvalidProof("Bundle transaction hashes mismatch".to_string()));
+		}
+		// Each transaction must directly follow the previous one
+		if let Some(pair) = proof.proofs.windows(2).find(|pair| pair[1].tx_index != pair[0].tx_index + 1) {
+			return Err(ConstraintsError::InvalidProof(format!(
+				"Bundle is not contiguous: transaction {} at index {} follows index {}",
+				pair[1].tx_hash, pair[1].tx_index, pair[0].tx_index
+			)));
+		}
+		Ok(())
+	}
+
+	fn verify_proof(&self, _constraint: &Constraint, proof: &Bytes, block: &dyn BlockProofs) -> Result<()> {
+		BundleProof::from_bytes(proof)?
+			.proofs
+			.iter()
+			.try_for_each(|inclusion_proof| verify_inclusion(block, inclusion_proof))?;
+		Ok(())
+	}
+
+	fn effects(&self, payload: &Bytes) -> Result<Vec<ConstraintEffect>> {
+		Ok(vec![ConstraintEffect::Contiguous {
+			tx_hashes: BundleConstraintPayload::abi_decode(payload).map_err(invalid_payload)?.tx_hashes,
+		}])
+	}
+}
+
+/// A blob transaction is included and its blobs are in the block's blobs bundle
+pub struct BlobHandler;
+
+impl ConstraintHandler for BlobHandler {
+	fn validate_payload(&self, payload: &Bytes) -> Result<()> {
+		if BlobConstraintPayload::abi_decode(payload).map_err(invalid_payload)?.blob_versioned_hashes.is_empty() {
+			return Err(ConstraintsError::InvalidRequest("Blob constraint without blobs".to_string()));
+		}
+		Ok(())
+	}
+
+	fn check_proof(&self, constraint: &Constraint, proof: &Bytes) -> Result<()> {
+		let payload = BlobConstraintPayload::abi_decode(&constraint.payload).map_err(invalid_payload)?;
+		check_tx_hash(InclusionProof::from_bytes(proof)?.tx_hash, payload.tx_hash)
+	}
+
+	fn verify_proof(&self, constraint: &Constraint, proof: &Bytes, block: &dyn BlockProofs) -> Result<()> {
+		verify_inclusion(block, &InclusionProof::from_bytes(proof)?)?;
+		check_blob_availability(
+			&BlobConstraintPayload::abi_decode(&constraint.payload).map_err(invalid_payload)?,
+			block.blob_versioned_hashes(),
+		)
+	}
+
+	fn effects(&self, payload: &Bytes) -> Result<Vec<ConstraintEffect>> {
+		Ok(vec![ConstraintEffect::Includes {
+			tx_hash: BlobConstraintPayload::abi_decode(payload).map_err(invalid_payload)?.tx_hash,
+			gas_limit: None,
+		}])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use constraints::conflicts::find_conflicts;
+
+	#[test]
+	fn test_default_registry_detects_conflicts_across_types() {
+		let inclusion = InclusionPayload::random();
+		let tx_hash = inclusion.tx_hash().unwrap();
+		let gas_limit = inclusion.decode_transaction().unwrap().gas_limit();
+		let constraints = [
+			Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: inclusion.abi_encode().unwrap() },
+			Constraint {
+				constraint_type: EXCLUSION_CONSTRAINT_TYPE,
+				payload: ExclusionPayload { slot: inclusion.slot, tx_hash }.abi_encode().unwrap(),
+			},
+		];
+
+		let report = find_conflicts(&default_registry(), &constraints[..1], Some(gas_limit)).unwrap();
+		assert!(report.is_empty());
+		let report = find_conflicts(&default_registry(), &constraints[..1], Some(gas_limit - 1)).unwrap();
+		assert_eq!(report.conflicts.len(), 1);
+
+		let report = find_conflicts(&default_registry(), &constraints, None).unwrap();
+		assert_eq!(report.conflicts.len(), 1);
+		assert_eq!(report.conflicts[0].constraints, vec![1, 0]);
+	}
+}
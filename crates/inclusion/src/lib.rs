@@ -2,6 +2,8 @@ pub mod constants;
 #[cfg(feature = "full")]
 pub mod gateway;
 #[cfg(feature = "proofs")]
+pub mod handlers;
+#[cfg(feature = "proofs")]
 pub mod proofs;
 #[cfg(feature = "full")]
 pub mod relay;
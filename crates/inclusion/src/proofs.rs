@@ -1,18 +1,119 @@
 use alloy::consensus::TxEnvelope;
-use alloy::primitives::{B256, Bytes, U256};
+use alloy::consensus::proofs::{calculate_transaction_root, ordered_trie_root_with_encoder};
+use alloy::eips::eip2718::{Decodable2718, Encodable2718};
+use alloy::primitives::{B256, Bytes, U256, keccak256};
+use alloy::rlp::{Decodable, Encodable};
 use alloy::rpc::types::beacon::relay::SubmitBlockRequest as AlloySubmitBlockRequest;
-use eth_trie::{EthTrie, MemoryDB, Trie};
+use eth_trie::{DB, EthTrie, MemoryDB, Trie};
 use ethereum_types::H256;
-use eyre::{Context, Result, eyre};
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
 use std::sync::Arc;
 use tracing::info;
 
-use constraints::helpers::extract_transactions;
-use constraints::types::ConstraintProofs;
+use constraints::conflicts::ConstraintEffect;
+use constraints::error::ConstraintsError;
+use constraints::helpers::{extract_blob_versioned_hashes, extract_transactions};
+use constraints::registry::{BlockProofs, ConstraintRegistry};
+use constraints::types::{Constraint, ConstraintProofs, SignedConstraints};
 
-/// Merkle inclusion proof for an inclusion payload
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::constants::{
+	BLOB_CONSTRAINT_TYPE, BUNDLE_CONSTRAINT_TYPE, EXCLUSION_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE,
+	ORDERING_CONSTRAINT_TYPE,
+};
+use crate::handlers::default_registry;
+
+/// Error returned by proof generation and verification
+#[derive(Debug, thiserror::Error)]
+pub enum ProofError {
+	/// A proof, constraint payload or transaction could not be decoded
+	#[error("failed to decode {0}")]
+	Decode(String),
+	/// A transaction the proof is about is not in the block
+	#[error("transaction not found: {0}")]
+	TransactionNotFound(String),
+	/// The proof does not prove its claim against the transactions root
+	#[error("invalid proof: {0}")]
+	Invalid(String),
+	/// The block does not satisfy the constraint, e.g. an excluded transaction is included
+	#[error("constraint not satisfied: {0}")]
+	Unsatisfied(String),
+	/// No proof format is defined for the constraint type
+	#[error("unsupported constraint type {0}")]
+	UnsupportedConstraintType(u64),
+	/// The transaction trie could not be built or read
+	#[error("transaction trie error: {0}")]
+	Trie(String),
+	/// The constraint's handler rejected the constraint or its proof
+	#[error(transparent)]
+	Constraints(#[from] ConstraintsError),
+}
+
+/// Result of proof generation and verification
+pub type Result<T, E = ProofError> = std::result::Result<T, E>;
+
+impl ProofError {
+	fn decode(name: &str, error: impl Display) -> Self {
+		ProofError::Decode(format!("{name}: {error}"))
+	}
+
+	fn trie(context: String, error: impl Display) -> Self {
+		ProofError::Trie(format!("{context}: {error}"))
+	}
+}
+
+/// Proofs that fail are rejected as invalid, constraints of an unknown type as invalid requests
+impl From<ProofError> for ConstraintsError {
+	fn from(error: ProofError) -> Self {
+		match error {
+			ProofError::Constraints(error) => error,
+			ProofError::UnsupportedConstraintType(_) => ConstraintsError::InvalidRequest(error.to_string()),
+			ProofError::Trie(_) => ConstraintsError::Internal(error.to_string()),
+			ProofError::Decode(_)
+			| ProofError::TransactionNotFound(_)
+			| ProofError::Invalid(_)
+			| ProofError::Unsatisfied(_) => ConstraintsError::InvalidProof(error.to_string()),
+		}
+	}
+}
+
+/// First byte of SSZ encoded proofs, whose trie leaves are the EIP-2718 encoded transactions of the header's
+/// transactions root
+/// Legacy bincode proofs start with the length prefix of the transaction hash (0x20) or of the bundle
+/// proofs (at most 16), so the two encodings cannot be confused. Their leaves are the RLP encoded transactions,
+/// which differ from the EIP-2718 encoding for typed transactions, see `ProofVerifier::with_legacy_proofs`
+pub const PROOF_VERSION_SSZ: u8 = 0xf1;
+
+/// Whether a proof is in the legacy bincode encoding, proven over the RLP encoded transactions
+pub fn is_legacy_proof(payload: &Bytes) -> bool {
+	payload.first() != Some(&PROOF_VERSION_SSZ)
+}
+
+/// Encodes a proof as the SSZ version byte followed by its SSZ encoding
+fn encode_versioned<T: ssz::Encode>(proof: &T) -> Bytes {
+	let mut buf = Vec::with_capacity(1 + proof.ssz_bytes_len());
+	buf.push(PROOF_VERSION_SSZ);
+	proof.ssz_append(&mut buf);
+	Bytes::from(buf)
+}
+
+/// Decodes a proof by its version byte, payloads without one are decoded as legacy bincode
+fn decode_versioned<T: ssz::Decode + DeserializeOwned>(bytes: &Bytes, name: &str) -> Result<T> {
+	match bytes.split_first() {
+		Some((&PROOF_VERSION_SSZ, encoded)) => {
+			T::from_ssz_bytes(encoded).map_err(|e| ProofError::Decode(format!("SSZ {name}: {e:?}")))
+		}
+		_ => bincode::deserialize(bytes.as_ref()).map_err(|e| ProofError::decode(name, e)),
+	}
+}
+
+/// Merkle inclusion proof for an inclusion payload, also proves the index bound of an ordering payload
+/// and the transaction of a blob payload
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct InclusionProof {
 	/// Transaction hash
 	pub tx_hash: B256,
@@ -24,7 +125,7 @@ pub struct InclusionProof {
 
 impl InclusionProof {
 	/// Creates a new InclusionProof
-	pub fn new(trie_builder: &mut TransactionTrieBuilder, tx_hash: B256) -> Result<Self> {
+	pub fn new(trie_builder: &TransactionTrieBuilder, tx_hash: B256) -> Result<Self> {
 		// Find the transaction index
 		let tx_index = trie_builder.find_tx_index(&tx_hash)?;
 
@@ -34,138 +135,241 @@ impl InclusionProof {
 		Ok(InclusionProof { tx_hash, tx_index, proof })
 	}
 
-	/// Serializes the InclusionProof to Bytes
+	/// Serializes the InclusionProof to a version byte followed by its SSZ encoding
+	pub fn to_bytes(&self) -> Result<Bytes> {
+		Ok(encode_versioned(self))
+	}
+
+	/// Deserializes an SSZ encoded InclusionProof, or a legacy bincode one
+	pub fn from_bytes(bytes: &Bytes) -> Result<Self> {
+		decode_versioned(bytes, "InclusionProof")
+	}
+}
+
+/// Non-inclusion proof for an exclusion payload
+///
+/// The transactions trie is keyed by index, so absence of a hash cannot be shown by a single path.
+/// Instead the proof pins the number of transactions to the transactions root: the last index is
+/// present and the next one is not. The verifier then checks none of those transactions has the hash.
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct ExclusionProof {
+	/// Hash of the excluded transaction
+	pub tx_hash: B256,
+	/// Number of transactions in the block
+	pub tx_count: usize,
+	/// Merkle proof of the transaction at index `tx_count - 1`, empty for an empty block
+	pub last_tx_proof: Vec<Vec<u8>>,
+	/// Merkle proof that index `tx_count` is absent
+	pub end_proof: Vec<Vec<u8>>,
+}
+
+impl ExclusionProof {
+	/// Creates a new ExclusionProof, errors if the transaction is in the block
+	pub fn new(trie_builder: &TransactionTrieBuilder, tx_hash: B256) -> Result<Self> {
+		if let Ok(tx_index) = trie_builder.find_tx_index(&tx_hash) {
+			return Err(ProofError::Unsatisfied(format!(
+				"Excluded transaction {tx_hash} is included at index {tx_index}"
+			)));
+		}
+
+		let tx_count = trie_builder.transactions.len();
+		let last_tx_proof = match tx_count.checked_sub(1) {
+			Some(last_index) => trie_builder.get_proof(last_index)?,
+			None => Vec::new(),
+		};
+		let end_proof = trie_builder.get_index_proof(tx_count)?;
+
+		Ok(ExclusionProof { tx_hash, tx_count, last_tx_proof, end_proof })
+	}
+
+	/// Serializes the ExclusionProof to a version byte followed by its SSZ encoding
 	pub fn to_bytes(&self) -> Result<Bytes> {
-		let buf = bincode::serialize(self).wrap_err("failed to serialize InclusionProof")?;
-		Ok(Bytes::from(buf))
+		Ok(encode_versioned(self))
 	}
 
+	/// Deserializes an SSZ encoded ExclusionProof, or a legacy bincode one
 	pub fn from_bytes(bytes: &Bytes) -> Result<Self> {
-		let proof: InclusionProof =
-			bincode::deserialize(bytes.as_ref()).wrap_err("failed to deserialize InclusionProof")?;
-		Ok(proof)
+		decode_versioned(bytes, "ExclusionProof")
+	}
+}
+
+/// Inclusion proofs of every transaction of a bundle, in bundle order
+/// Adjacency of the proven indices is checked against the constraint payload by `BundleHandler::check_proof`
+#[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
+pub struct BundleProof {
+	pub proofs: Vec<InclusionProof>,
+}
+
+impl BundleProof {
+	/// Creates a new BundleProof, errors if any transaction is missing from the block
+	pub fn new(trie_builder: &TransactionTrieBuilder, tx_hashes: &[B256]) -> Result<Self> {
+		let proofs =
+			tx_hashes.iter().map(|tx_hash| InclusionProof::new(trie_builder, *tx_hash)).collect::<Result<Vec<_>>>()?;
+		Ok(BundleProof { proofs })
+	}
+
+	/// Serializes the BundleProof to a version byte followed by its SSZ encoding
+	pub fn to_bytes(&self) -> Result<Bytes> {
+		Ok(encode_versioned(self))
+	}
+
+	/// Deserializes an SSZ encoded BundleProof, or a legacy bincode one
+	pub fn from_bytes(bytes: &Bytes) -> Result<Self> {
+		decode_versioned(bytes, "BundleProof")
 	}
 }
 
 /// Builder for transaction Merkle Patricia Trie
+///
+/// The trie is finalized when built, proofs are generated and verified against its cached root in
+/// parallel across transactions.
 pub struct TransactionTrieBuilder {
 	trie: EthTrie<MemoryDB>,
+	root: B256,
 	transactions: Vec<B256>,
+	/// Position of each transaction hash in `transactions`
+	indices: HashMap<B256, usize>,
 }
 
 impl TransactionTrieBuilder {
-	/// Create a new trie builder
+	/// Create a builder for an empty trie
 	pub fn new() -> Self {
-		let memdb = Arc::new(MemoryDB::new(true));
-		let trie = EthTrie::new(memdb);
-		Self { trie, transactions: Vec::new() }
+		Self::build(&[]).expect("an empty trie always has a root")
 	}
 
 	/// Build the transaction trie from a list of signed transactions
 	pub fn build(transactions: &[TxEnvelope]) -> Result<Self> {
-		let mut builder = Self::new();
+		let memdb = Arc::new(MemoryDB::new(true));
+		let mut trie = EthTrie::new(memdb);
+		let mut hashes = Vec::with_capacity(transactions.len());
 
 		for (idx, tx) in transactions.iter().enumerate() {
 			// Key is RLP-encoded index
 			let key = alloy::rlp::encode(U256::from(idx));
 
-			// Value is RLP-encoded signed transaction
-			let tx_bytes = alloy::rlp::encode(tx);
+			// Value is the EIP-2718 encoded transaction, so the root matches the header's transactions root
+			let tx_bytes = tx.encoded_2718();
 
-			builder
-				.trie
-				.insert(key.as_slice(), &tx_bytes)
-				.wrap_err_with(|| format!("Failed to insert transaction at index {idx} into trie"))?;
-			builder.transactions.push(*tx.hash());
+			trie.insert(key.as_slice(), &tx_bytes)
+				.map_err(|e| ProofError::trie(format!("Failed to insert transaction at index {idx} into trie"), e))?;
+			hashes.push(*tx.hash());
 		}
 
-		Ok(builder)
+		// Computing the root commits the trie to the database, which every proof is then read from
+		let root = trie.root_hash().map_err(|e| ProofError::trie("Failed to compute trie root hash".to_string(), e))?;
+		let root = B256::from_slice(root.as_bytes());
+		let indices = hashes.iter().enumerate().map(|(idx, hash)| (*hash, idx)).collect();
+
+		Ok(Self { trie, root, transactions: hashes, indices })
 	}
 
 	/// Proves inclusion of a batch of transactions and returns an encoded ConstraintProofs
-	pub fn prove_batch(&mut self, tx_hashes: &[B256]) -> Result<ConstraintProofs> {
-		// Finalize the trie by computing root before generating proofs
-		// This ensures the trie structure is committed and proofs will be valid
-		let _ = self.root()?;
-
+	pub fn prove_batch(&self, tx_hashes: &[B256]) -> Result<ConstraintProofs> {
 		let payloads: Vec<Bytes> = tx_hashes
-			.iter()
+			.par_iter()
 			.map(|tx_hash| InclusionProof::new(self, *tx_hash)?.to_bytes())
 			.collect::<Result<Vec<_>>>()?;
 
-		let constraint_types = vec![crate::constants::INCLUSION_CONSTRAINT_TYPE; payloads.len()];
+		let constraint_types = vec![INCLUSION_CONSTRAINT_TYPE; payloads.len()];
 
-		Ok(ConstraintProofs { constraint_types, payloads })
+		Ok(ConstraintProofs { constraint_types, payloads, multiproof_nodes: Vec::new() })
 	}
 
-	/// Verifies a batch of inclusion proofs, errors if any proof is invalid
-	pub fn verify_batch(&mut self, proofs: &ConstraintProofs) -> Result<()> {
-		let transactions_root = self.root()?;
-		for (constraint_type, payload) in proofs.constraint_types.iter().zip(proofs.payloads.iter()) {
-			if *constraint_type != crate::constants::INCLUSION_CONSTRAINT_TYPE {
-				return Err(eyre!("Invalid constraint type {constraint_type}"));
-			}
+	/// Proves a list of constraints of any supported type, in the order of the constraints
+	pub fn prove_constraints(&self, constraints: &[Constraint]) -> Result<ConstraintProofs> {
+		let registry = default_registry();
+		let payloads = constraints
+			.par_iter()
+			.map(|constraint| self.prove_constraint(&registry, constraint))
+			.collect::<Result<Vec<_>>>()?;
 
-			let inclusion_proof: InclusionProof = InclusionProof::from_bytes(payload)?;
-			let tx_bytes = self.verify_proof(inclusion_proof.tx_index, &inclusion_proof.proof, &transactions_root)?;
-
-			// Decode the transaction and verify the hash matches the claimed tx_hash
-			let tx: TxEnvelope = alloy::rlp::Decodable::decode(&mut tx_bytes.as_slice())
-				.wrap_err("Failed to decode transaction from proof")?;
-			if *tx.hash() != inclusion_proof.tx_hash {
-				return Err(eyre!(
-					"Transaction hash mismatch: proof claims {} but transaction at index {} has hash {}",
-					inclusion_proof.tx_hash,
-					inclusion_proof.tx_index,
-					tx.hash()
-				));
+		Ok(ConstraintProofs {
+			constraint_types: constraints.iter().map(|constraint| constraint.constraint_type).collect(),
+			payloads,
+			multiproof_nodes: Vec::new(),
+		})
+	}
+
+	/// Proves a single constraint with the proof the effects described by its handler call for: an inclusion proof
+	/// of an included transaction, an exclusion proof of an excluded one or a bundle proof of contiguous ones
+	fn prove_constraint(&self, registry: &ConstraintRegistry, constraint: &Constraint) -> Result<Bytes> {
+		let unsupported = || ProofError::UnsupportedConstraintType(constraint.constraint_type);
+		let handler = registry.handler(constraint.constraint_type).map_err(|_| unsupported())?;
+		match handler.effects(&constraint.payload)?.as_slice() {
+			[ConstraintEffect::Includes { tx_hash, .. }] | [ConstraintEffect::AtOrBefore { tx_hash, .. }] => {
+				InclusionProof::new(self, *tx_hash)?.to_bytes()
 			}
+			[ConstraintEffect::Excludes { tx_hash }] => ExclusionProof::new(self, *tx_hash)?.to_bytes(),
+			[ConstraintEffect::Contiguous { tx_hashes }] => BundleProof::new(self, tx_hashes)?.to_bytes(),
+			_ => Err(unsupported()),
 		}
-		Ok(())
+	}
+
+	/// Verifies a batch of inclusion, exclusion, ordering, bundle and blob proofs, errors if any proof is invalid
+	/// Ordering, bundle and blob proofs are made of inclusion proofs, the index bound and adjacency are checked
+	/// against the constraint payload by their `ConstraintHandler`, blob availability against the block
+	pub fn verify_batch(&self, proofs: &ConstraintProofs) -> Result<()> {
+		verify_batch_against_root(&self.root, &self.transactions, proofs)
+	}
+
+	/// Verifier of the proofs against the trie root, loads the shared nodes if the proofs are a multiproof
+	pub fn verifier(&self, proofs: &ConstraintProofs) -> Result<ProofVerifier<'_>> {
+		ProofVerifier::new(self.root, &self.transactions, proofs)
+	}
+
+	/// Proves inclusion of a batch of transactions as a multiproof, the trie nodes are shared by all proofs
+	pub fn prove_batch_multiproof(&self, tx_hashes: &[B256]) -> Result<ConstraintProofs> {
+		into_multiproof(self.prove_batch(tx_hashes)?)
 	}
 
 	/// Get the root hash of the trie
-	pub fn root(&mut self) -> Result<B256> {
-		let root = self.trie.root_hash().wrap_err("Failed to compute trie root hash")?;
-		Ok(B256::from_slice(root.as_bytes()))
+	pub fn root(&self) -> Result<B256> {
+		Ok(self.root)
 	}
 
 	/// Generate a proof for a transaction at the given index
-	pub fn get_proof(&mut self, tx_index: usize) -> Result<Vec<Vec<u8>>> {
+	pub fn get_proof(&self, tx_index: usize) -> Result<Vec<Vec<u8>>> {
 		if tx_index >= self.transactions.len() {
-			return Err(eyre!("Transaction not found at index {tx_index}"));
+			return Err(ProofError::TransactionNotFound(format!("no transaction at index {tx_index}")));
 		}
 
+		self.get_index_proof(tx_index)
+	}
+
+	/// Generate a proof for an index, which may be absent from the trie
+	/// Each call walks its own view of the committed trie so proofs can be generated concurrently
+	fn get_index_proof(&self, tx_index: usize) -> Result<Vec<Vec<u8>>> {
 		let key = alloy::rlp::encode(U256::from(tx_index));
-		let proof = self
-			.trie
+		self.trie
+			.at_root(H256::from_slice(self.root.as_slice()))
 			.get_proof(key.as_slice())
-			.wrap_err_with(|| format!("Failed to generate proof for transaction at index {tx_index}"))?;
-		Ok(proof)
+			.map_err(|e| ProofError::trie(format!("Failed to generate proof for index {tx_index}"), e))
 	}
 
 	/// Find the index of a transaction by its hash
 	pub fn find_tx_index(&self, tx_hash: &B256) -> Result<usize> {
-		self.transactions
-			.iter()
-			.position(|hash| hash == tx_hash)
-			.ok_or_else(|| eyre!("Transaction hash {tx_hash} not found in block"))
+		self.indices
+			.get(tx_hash)
+			.copied()
+			.ok_or_else(|| ProofError::TransactionNotFound(format!("{tx_hash} is not in the block")))
 	}
 
 	/// Verify a proof for a transaction at the given index
 	pub fn verify_proof(&self, tx_index: usize, proof: &[Vec<u8>], root: &B256) -> Result<Vec<u8>> {
-		let key = alloy::rlp::encode(U256::from(tx_index));
-		let root_hash = H256::from_slice(root.as_slice());
+		verify_proof_against_root(tx_index, proof, root)
+	}
 
-		self.trie
-			.verify_proof(root_hash, key.as_slice(), proof.to_vec())
-			.wrap_err_with(|| format!("Failed to verify proof for transaction at index {tx_index}"))?
-			.ok_or_else(|| eyre!("Invalid proof for transaction at index {tx_index}"))
+	/// Hashes of the transactions in the trie, in block order
+	pub fn tx_hashes(&self) -> &[B256] {
+		&self.transactions
 	}
 
 	/// Get the transaction hash at the given index
 	pub fn get_tx_hash(&self, tx_index: usize) -> Result<B256> {
-		self.transactions.get(tx_index).copied().ok_or_else(|| eyre!("Transaction not found at index {tx_index}"))
+		self.transactions
+			.get(tx_index)
+			.copied()
+			.ok_or_else(|| ProofError::TransactionNotFound(format!("no transaction at index {tx_index}")))
 	}
 }
 
@@ -180,11 +384,143 @@ pub fn prove_constraints(block: &AlloySubmitBlockRequest, tx_hashes: &[B256]) ->
 		return Ok(ConstraintProofs::default());
 	}
 	let transactions = extract_transactions(block)?;
-	let mut builder = TransactionTrieBuilder::build(&transactions)?;
+	let builder = TransactionTrieBuilder::build(&transactions)?;
 	let proofs = builder.prove_batch(tx_hashes)?;
 	Ok(proofs)
 }
 
+/// Proves constraints of any supported type against a block, in the order of the constraints
+pub fn prove_typed_constraints(
+	block: &AlloySubmitBlockRequest,
+	constraints: &[Constraint],
+) -> Result<ConstraintProofs> {
+	if constraints.is_empty() {
+		return Ok(ConstraintProofs::default());
+	}
+	let transactions = extract_transactions(block)?;
+	let builder = TransactionTrieBuilder::build(&transactions)?;
+	builder.prove_constraints(constraints)
+}
+
+/// A constraint the block does not satisfy, as reported by `prove_signed_constraints`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsatisfiedConstraint {
+	/// Position of the constraint in the flattened constraints of the slot
+	pub index: usize,
+	pub constraint_type: u64,
+	/// Why no valid proof could be produced, e.g. a missing transaction
+	pub reason: String,
+}
+
+/// Outcome of proving every constraint of a slot against a block
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProverReport {
+	/// Proofs of the satisfied constraints, in the order the relay expects them
+	pub proofs: ConstraintProofs,
+	/// Constraints without a proof, the block would be rejected by the relay if any
+	pub unsatisfied: Vec<UnsatisfiedConstraint>,
+}
+
+impl ProverReport {
+	/// Whether every constraint has a proof
+	pub fn is_complete(&self) -> bool {
+		self.unsatisfied.is_empty()
+	}
+
+	/// The proofs to submit with the block, errors listing the unsatisfied constraints if any
+	pub fn into_proofs(self) -> Result<ConstraintProofs> {
+		if let Some(first) = self.unsatisfied.first() {
+			return Err(ProofError::Unsatisfied(format!(
+				"{} constraints unsatisfied, first at index {}: {}",
+				self.unsatisfied.len(),
+				first.index,
+				first.reason
+			)));
+		}
+		Ok(self.proofs)
+	}
+}
+
+/// Flattens the signed constraints of a slot in the order the relay expects proofs:
+/// by delegate public key, then in message order
+pub fn flatten_signed_constraints(signed_constraints: &[SignedConstraints]) -> Vec<Constraint> {
+	let mut sorted = signed_constraints.iter().collect::<Vec<_>>();
+	sorted.sort_by(|a, b| a.message.delegate.cmp(&b.message.delegate));
+	sorted.into_iter().flat_map(|signed| signed.message.constraints.iter().cloned()).collect()
+}
+
+/// Proves the constraints of a slot against the block a builder is about to submit.
+///
+/// `signed_constraints` are the messages served by the relay for the slot. The returned report holds
+/// the proofs in the order the relay checks them, and lists the constraints the block does not
+/// satisfy instead of failing on the first one.
+///
+/// Example:
+/// ```ignore
+/// let report = prove_signed_constraints(&block, &signed_constraints)?;
+/// let submission = SubmitBlockRequestWithProofs { message: block, proofs: report.into_proofs()? };
+/// ```
+pub fn prove_signed_constraints(
+	block: &AlloySubmitBlockRequest,
+	signed_constraints: &[SignedConstraints],
+) -> Result<ProverReport> {
+	let slot = block.bid_trace().slot;
+	if let Some(other) = signed_constraints.iter().find(|signed| signed.message.slot != slot) {
+		return Err(ProofError::Constraints(ConstraintsError::InvalidRequest(format!(
+			"Constraints for slot {} cannot be proven against a block for slot {}",
+			other.message.slot, slot
+		))));
+	}
+
+	let constraints = flatten_signed_constraints(signed_constraints);
+	if constraints.is_empty() {
+		return Ok(ProverReport::default());
+	}
+	let transactions = extract_transactions(block)?;
+	prove_constraints_report(&transactions, &extract_blob_versioned_hashes(block), &constraints)
+}
+
+/// Proves each constraint against the block transactions and blob versioned hashes, collecting failures
+pub fn prove_constraints_report(
+	transactions: &[TxEnvelope],
+	blob_versioned_hashes: &[B256],
+	constraints: &[Constraint],
+) -> Result<ProverReport> {
+	let builder = TransactionTrieBuilder::build(transactions)?;
+	let registry = default_registry();
+	// Each proof is checked by its constraint's handler like the relay does, so an unsatisfied constraint is
+	// reported instead of submitted
+	let verifier = ProofVerifier::new(builder.root, &builder.transactions, &ConstraintProofs::default())?
+		.with_blob_versioned_hashes(blob_versioned_hashes);
+	let outcomes = constraints
+		.par_iter()
+		.map(|constraint| {
+			let proof = builder.prove_constraint(&registry, constraint)?;
+			registry.check_proof(constraint, &proof)?;
+			registry.verify_proof(constraint, &proof, &verifier)?;
+			Ok(proof)
+		})
+		.collect::<Vec<Result<Bytes>>>();
+
+	let mut report = ProverReport::default();
+	for (index, (constraint, outcome)) in constraints.iter().zip(outcomes).enumerate() {
+		match outcome {
+			Ok(proof) => {
+				report.proofs.constraint_types.push(constraint.constraint_type);
+				report.proofs.payloads.push(proof);
+			}
+			Err(e) => report.unsatisfied.push(UnsatisfiedConstraint {
+				index,
+				constraint_type: constraint.constraint_type,
+				reason: e.to_string(),
+			}),
+		}
+	}
+	Ok(report)
+}
+
+/// Verifies proofs against the transactions root of a block, which is computed without storing the trie nodes
+/// Legacy bincode proofs are verified against the root of the RLP encoded transactions
 pub fn verify_constraints(block: &AlloySubmitBlockRequest, proofs: &ConstraintProofs) -> Result<()> {
 	let transactions = extract_transactions(block)?;
 
@@ -195,15 +531,299 @@ pub fn verify_constraints(block: &AlloySubmitBlockRequest, proofs: &ConstraintPr
 		proofs.payloads.len()
 	);
 
-	let mut builder = TransactionTrieBuilder::build(&transactions)?;
-	builder.verify_batch(proofs)?;
+	let transactions_root = TransactionsRoot::new(&transactions);
+	transactions_root.verifier(proofs)?.with_legacy_proofs(proofs, &transactions).verify_batch(proofs)
+}
+
+/// Transactions root and transaction hashes of a block, all its proofs are verified against
+/// The root is computed over the EIP-2718 encoded transactions like the execution header's, without keeping
+/// the trie nodes a prover needs
+#[derive(Debug, Clone)]
+pub struct TransactionsRoot {
+	root: B256,
+	tx_hashes: Vec<B256>,
+}
+
+impl TransactionsRoot {
+	pub fn new(transactions: &[TxEnvelope]) -> Self {
+		Self {
+			root: calculate_transaction_root(transactions),
+			tx_hashes: transactions.iter().map(|tx| *tx.hash()).collect(),
+		}
+	}
+
+	pub fn root(&self) -> B256 {
+		self.root
+	}
+
+	/// Hashes of the transactions, in block order
+	pub fn tx_hashes(&self) -> &[B256] {
+		&self.tx_hashes
+	}
+
+	/// Verifier of the proofs against the root, loads the shared nodes if the proofs are a multiproof
+	pub fn verifier(&self, proofs: &ConstraintProofs) -> Result<ProofVerifier<'_>> {
+		ProofVerifier::new(self.root, &self.tx_hashes, proofs)
+	}
+}
+
+/// Verifies a batch of proofs directly against a transactions root, e.g. the one of a trusted block header
+/// The transaction hashes of the block are only used by exclusion proofs, which must show the hash is absent
+/// Legacy bincode proofs are not over the leaves of the root and fail, see `ProofVerifier::with_legacy_proofs`
+pub fn verify_batch_against_root(
+	transactions_root: &B256,
+	tx_hashes: &[B256],
+	proofs: &ConstraintProofs,
+) -> Result<()> {
+	ProofVerifier::new(*transactions_root, tx_hashes, proofs)?.verify_batch(proofs)
+}
+
+/// Verifies single proofs and multiproofs against a transactions root
+/// The shared nodes of a multiproof are loaded once, single proofs are checked against their own nodes
+pub struct ProofVerifier<'a> {
+	root: B256,
+	tx_hashes: &'a [B256],
+	blob_versioned_hashes: &'a [B256],
+	/// Trie over the shared nodes of a multiproof
+	shared: Option<EthTrie<MemoryDB>>,
+	/// Verifier of legacy bincode proofs, against the root of the RLP encoded transactions
+	legacy: Option<Box<ProofVerifier<'a>>>,
+}
+
+impl<'a> ProofVerifier<'a> {
+	pub fn new(root: B256, tx_hashes: &'a [B256], proofs: &ConstraintProofs) -> Result<Self> {
+		let mut verifier = Self { root, tx_hashes, blob_versioned_hashes: &[], shared: None, legacy: None };
+		if proofs.multiproof_nodes.is_empty() {
+			return Ok(verifier);
+		}
+
+		let db = Arc::new(MemoryDB::new(true));
+		for node in &proofs.multiproof_nodes {
+			db.insert(keccak256(node).as_slice(), node.to_vec())
+				.map_err(|e| ProofError::Invalid(format!("Failed to load multiproof node: {e}")))?;
+		}
+		let shared = EthTrie::from(db, H256::from_slice(root.as_slice()))
+			.map_err(|e| ProofError::Invalid(format!("Multiproof does not contain the transactions root node: {e}")))?;
+		verifier.shared = Some(shared);
+		Ok(verifier)
+	}
+
+	/// Versioned hashes of the blobs in the block, needed by blob constraints verified through a registry
+	pub fn with_blob_versioned_hashes(mut self, blob_versioned_hashes: &'a [B256]) -> Self {
+		self.blob_versioned_hashes = blob_versioned_hashes;
+		if let Some(legacy) = self.legacy.take() {
+			self.legacy = Some(Box::new(legacy.with_blob_versioned_hashes(blob_versioned_hashes)));
+		}
+		self
+	}
+
+	/// Accepts the legacy bincode proofs of the batch while provers upgrade. Their leaves are the RLP encoded
+	/// transactions, so they are verified against the root over those, computed only if the batch has one
+	/// Legacy proofs predate multiproofs and carry their own nodes
+	pub fn with_legacy_proofs(mut self, proofs: &ConstraintProofs, transactions: &[TxEnvelope]) -> Self {
+		if proofs.payloads.iter().any(is_legacy_proof) {
+			let root = ordered_trie_root_with_encoder(transactions, |tx, buf| tx.encode(buf));
+			self.legacy = Some(Box::new(Self {
+				root,
+				tx_hashes: self.tx_hashes,
+				blob_versioned_hashes: self.blob_versioned_hashes,
+				shared: None,
+				legacy: None,
+			}));
+		}
+		self
+	}
+
+	/// The verifier of a proof, the legacy one for legacy bincode proofs
+	pub fn for_proof(&self, payload: &Bytes) -> Result<&Self> {
+		if !is_legacy_proof(payload) {
+			return Ok(self);
+		}
+		self.legacy.as_deref().ok_or_else(|| {
+			ProofError::Invalid(
+				"Legacy bincode proof over RLP encoded transactions, prove against the transactions root".to_string(),
+			)
+		})
+	}
+
+	/// Verifies every proof of the batch, errors if any proof is invalid
+	pub fn verify_batch(&self, proofs: &ConstraintProofs) -> Result<()> {
+		if proofs.constraint_types.len() != proofs.payloads.len() {
+			return Err(ProofError::Invalid("Constraint types and payloads length mismatch".to_string()));
+		}
+		proofs
+			.constraint_types
+			.par_iter()
+			.zip(proofs.payloads.par_iter())
+			.try_for_each(|(constraint_type, payload)| self.verify(*constraint_type, payload))
+	}
+
+	/// Verifies a proof against the trie alone, constraint specific checks are left to the constraint's handler
+	pub fn verify(&self, constraint_type: u64, payload: &Bytes) -> Result<()> {
+		let verifier = self.for_proof(payload)?;
+		match constraint_type {
+			INCLUSION_CONSTRAINT_TYPE | ORDERING_CONSTRAINT_TYPE | BLOB_CONSTRAINT_TYPE => {
+				verify_inclusion(verifier, &InclusionProof::from_bytes(payload)?)
+			}
+			EXCLUSION_CONSTRAINT_TYPE => verify_exclusion(verifier, &ExclusionProof::from_bytes(payload)?),
+			BUNDLE_CONSTRAINT_TYPE => BundleProof::from_bytes(payload)?
+				.proofs
+				.iter()
+				.try_for_each(|inclusion_proof| verify_inclusion(verifier, inclusion_proof)),
+			constraint_type => Err(ProofError::UnsupportedConstraintType(constraint_type)),
+		}
+	}
+}
+
+impl BlockProofs for ProofVerifier<'_> {
+	/// Reads the multiproof nodes if any, otherwise the proof's own nodes
+	fn lookup(&self, index: usize, proof: &[Vec<u8>]) -> constraints::error::Result<Option<Vec<u8>>> {
+		let value = match &self.shared {
+			Some(trie) => trie
+				.get(alloy::rlp::encode(U256::from(index)).as_slice())
+				.map_err(|e| ProofError::Invalid(format!("Multiproof is missing nodes for index {index}: {e}")))?,
+			None => verify_key_against_root(index, proof, &self.root)?,
+		};
+		Ok(value)
+	}
+
+	fn tx_hashes(&self) -> &[B256] {
+		self.tx_hashes
+	}
+
+	fn blob_versioned_hashes(&self) -> &[B256] {
+		self.blob_versioned_hashes
+	}
+}
+
+/// Verifies that the proven transaction is at the claimed index of the block
+pub fn verify_inclusion(block: &dyn BlockProofs, inclusion_proof: &InclusionProof) -> Result<()> {
+	let tx_bytes = block
+		.lookup(inclusion_proof.tx_index, &inclusion_proof.proof)
+		.map_err(|e| {
+			ProofError::Invalid(format!(
+				"Failed to verify proof for transaction at index {}: {e}",
+				inclusion_proof.tx_index
+			))
+		})?
+		.ok_or_else(|| ProofError::Invalid(format!("no transaction at index {}", inclusion_proof.tx_index)))?;
+
+	// Decode the transaction and verify the hash matches the claimed tx_hash, leaves of legacy proofs are RLP encoded
+	let tx = TxEnvelope::decode_2718(&mut tx_bytes.as_slice())
+		.or_else(|_| <TxEnvelope as Decodable>::decode(&mut tx_bytes.as_slice()))
+		.map_err(|e| ProofError::decode("transaction from proof", e))?;
+	if *tx.hash() != inclusion_proof.tx_hash {
+		return Err(ProofError::Invalid(format!(
+			"Transaction hash mismatch: proof claims {} but transaction at index {} has hash {}",
+			inclusion_proof.tx_hash,
+			inclusion_proof.tx_index,
+			tx.hash()
+		)));
+	}
 	Ok(())
 }
+
+/// Verifies that the excluded transaction is absent from the block
+pub fn verify_exclusion(block: &dyn BlockProofs, proof: &ExclusionProof) -> Result<()> {
+	let tx_hashes = block.tx_hashes();
+	if proof.tx_count != tx_hashes.len() {
+		return Err(ProofError::Invalid(format!(
+			"Transaction count mismatch: proof claims {} transactions but block has {}",
+			proof.tx_count,
+			tx_hashes.len()
+		)));
+	}
+
+	if let Some(last_index) = proof.tx_count.checked_sub(1) {
+		block
+			.lookup(last_index, &proof.last_tx_proof)
+			.map_err(|e| {
+				ProofError::Invalid(format!("Failed to verify proof for transaction at index {last_index}: {e}"))
+			})?
+			.ok_or_else(|| ProofError::Invalid(format!("no transaction at index {last_index}")))?;
+	}
+
+	let end = block.lookup(proof.tx_count, &proof.end_proof).map_err(|e| {
+		ProofError::Invalid(format!("Failed to verify absence of transaction at index {}: {e}", proof.tx_count))
+	})?;
+	if end.is_some() {
+		return Err(ProofError::Invalid(format!(
+			"Block has a transaction at index {} beyond the proven count",
+			proof.tx_count
+		)));
+	}
+
+	if let Some(tx_index) = tx_hashes.iter().position(|tx_hash| *tx_hash == proof.tx_hash) {
+		return Err(ProofError::Unsatisfied(format!(
+			"Excluded transaction {} is included at index {}",
+			proof.tx_hash, tx_index
+		)));
+	}
+	Ok(())
+}
+
+/// Moves the trie nodes of every proof into a single de-duplicated list shared by all of them
+/// The payloads keep their format with empty node lists, so the constraint types and order are unchanged
+pub fn into_multiproof(proofs: ConstraintProofs) -> Result<ConstraintProofs> {
+	let mut seen = HashSet::new();
+	let mut nodes = proofs.multiproof_nodes;
+	seen.extend(nodes.iter().cloned());
+	let mut share = |proof: &mut Vec<Vec<u8>>| {
+		for node in std::mem::take(proof) {
+			let node = Bytes::from(node);
+			if seen.insert(node.clone()) {
+				nodes.push(node);
+			}
+		}
+	};
+
+	let payloads = proofs
+		.constraint_types
+		.iter()
+		.zip(proofs.payloads)
+		.map(|(constraint_type, payload)| match *constraint_type {
+			INCLUSION_CONSTRAINT_TYPE | ORDERING_CONSTRAINT_TYPE | BLOB_CONSTRAINT_TYPE => {
+				let mut proof = InclusionProof::from_bytes(&payload)?;
+				share(&mut proof.proof);
+				proof.to_bytes()
+			}
+			EXCLUSION_CONSTRAINT_TYPE => {
+				let mut proof = ExclusionProof::from_bytes(&payload)?;
+				share(&mut proof.last_tx_proof);
+				share(&mut proof.end_proof);
+				proof.to_bytes()
+			}
+			BUNDLE_CONSTRAINT_TYPE => {
+				let mut proof = BundleProof::from_bytes(&payload)?;
+				proof.proofs.iter_mut().for_each(|inclusion_proof| share(&mut inclusion_proof.proof));
+				proof.to_bytes()
+			}
+			constraint_type => Err(ProofError::UnsupportedConstraintType(constraint_type)),
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(ConstraintProofs { constraint_types: proofs.constraint_types, payloads, multiproof_nodes: nodes })
+}
+
+/// Verifies a proof for a transaction at the given index against a transactions root, returns the transaction
+pub fn verify_proof_against_root(tx_index: usize, proof: &[Vec<u8>], root: &B256) -> Result<Vec<u8>> {
+	verify_key_against_root(tx_index, proof, root)?
+		.ok_or_else(|| ProofError::Invalid(format!("no transaction at index {tx_index}")))
+}
+
+/// Verifies a proof for an index against a root, returns the value at the index if any
+/// The proof nodes are checked on their own, the trie the verifier is called on is not read
+fn verify_key_against_root(index: usize, proof: &[Vec<u8>], root: &B256) -> Result<Option<Vec<u8>>> {
+	let key = alloy::rlp::encode(U256::from(index));
+	EthTrie::new(Arc::new(MemoryDB::new(true)))
+		.verify_proof(H256::from_slice(root.as_slice()), key.as_slice(), proof.to_vec())
+		.map_err(|e| ProofError::Invalid(format!("trie proof for index {index} does not verify: {e}")))
+}
 #[cfg(test)]
 mod tests {
 
 	use super::*;
-	use crate::types::InclusionPayload;
+	use crate::types::{BundleConstraintPayload, ExclusionPayload, InclusionPayload, OrderingPayload};
 
 	#[test]
 	fn test_inclusion_proof_serialization() {
@@ -215,6 +835,46 @@ mod tests {
 		assert_eq!(proof.proof.len(), proof2.proof.len());
 	}
 
+	#[test]
+	fn test_proof_errors_map_to_constraints_errors() {
+		let corrupted = InclusionProof::from_bytes(&Bytes::from(vec![PROOF_VERSION_SSZ, 0x01])).unwrap_err();
+		assert!(matches!(corrupted, ProofError::Decode(_)));
+		assert!(matches!(ConstraintsError::from(corrupted), ConstraintsError::InvalidProof(_)));
+
+		let builder = TransactionTrieBuilder::new();
+		let unsupported = Constraint { constraint_type: 99, payload: Bytes::new() };
+		let error = builder.prove_constraints(&[unsupported]).unwrap_err();
+		assert!(matches!(error, ProofError::UnsupportedConstraintType(99)));
+		assert!(matches!(ConstraintsError::from(error), ConstraintsError::InvalidRequest(_)));
+	}
+
+	#[test]
+	fn test_proof_encoding_versions() {
+		let proof = InclusionProof { tx_hash: B256::random(), tx_index: 7, proof: vec![vec![0x01, 0x02], vec![0x03]] };
+		let bytes = proof.to_bytes().unwrap();
+		assert_eq!(bytes[0], PROOF_VERSION_SSZ);
+		let decoded = InclusionProof::from_bytes(&bytes).unwrap();
+		assert_eq!(
+			(decoded.tx_hash, decoded.tx_index, decoded.proof),
+			(proof.tx_hash, proof.tx_index, proof.proof.clone())
+		);
+
+		// Proofs encoded by older provers still decode
+		let legacy = Bytes::from(bincode::serialize(&proof).unwrap());
+		assert_ne!(legacy[0], PROOF_VERSION_SSZ);
+		assert_eq!(InclusionProof::from_bytes(&legacy).unwrap().tx_hash, proof.tx_hash);
+
+		let bundle = BundleProof { proofs: vec![proof.clone()] };
+		let legacy = Bytes::from(bincode::serialize(&bundle).unwrap());
+		assert_ne!(legacy[0], PROOF_VERSION_SSZ);
+		assert_eq!(BundleProof::from_bytes(&legacy).unwrap().proofs.len(), 1);
+		assert_eq!(BundleProof::from_bytes(&bundle.to_bytes().unwrap()).unwrap().proofs[0].tx_index, 7);
+
+		let mut corrupted = bytes.to_vec();
+		corrupted.truncate(10);
+		assert!(InclusionProof::from_bytes(&Bytes::from(corrupted)).is_err());
+	}
+
 	#[test]
 	fn test_build_trie_and_generate_proof() {
 		// Create some test transactions
@@ -225,7 +885,7 @@ mod tests {
 		let transactions = vec![tx1, tx2];
 
 		// // Build trie
-		let mut builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		let builder = TransactionTrieBuilder::build(&transactions).unwrap();
 
 		// Get root
 		let root = builder.root().unwrap();
@@ -272,15 +932,349 @@ mod tests {
 		let tx_hashes = vec![payload1.tx_hash().unwrap(), payload2.tx_hash().unwrap(), payload3.tx_hash().unwrap()];
 
 		// Build trie and prove
-		let mut prover_builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		let prover_builder = TransactionTrieBuilder::build(&transactions).unwrap();
 		let proofs = prover_builder.prove_batch(&tx_hashes).unwrap();
 
 		assert_eq!(proofs.constraint_types.len(), 3);
 		assert_eq!(proofs.payloads.len(), 3);
 
 		// Build a separate trie and verify (simulates verifier rebuilding from block)
-		let mut verifier_builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		let verifier_builder = TransactionTrieBuilder::build(&transactions).unwrap();
 		let result = verifier_builder.verify_batch(&proofs);
 		assert!(result.is_ok(), "verify_batch failed: {:?}", result.err());
 	}
+
+	#[test]
+	fn test_multiproof_shares_nodes() {
+		let payloads = (0..20).map(|_| InclusionPayload::random()).collect::<Vec<_>>();
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let tx_hashes = transactions.iter().map(|tx| *tx.hash()).collect::<Vec<_>>();
+		let builder = TransactionTrieBuilder::build(&transactions).unwrap();
+
+		let single = builder.prove_batch(&tx_hashes).unwrap();
+		let multiproof = builder.prove_batch_multiproof(&tx_hashes).unwrap();
+		assert_eq!(multiproof.constraint_types, single.constraint_types);
+		let size = |proofs: &ConstraintProofs| {
+			proofs.payloads.iter().chain(&proofs.multiproof_nodes).map(|bytes| bytes.len()).sum::<usize>()
+		};
+		assert!(size(&multiproof) < size(&single));
+		assert!(builder.verify_batch(&multiproof).is_ok());
+
+		// Exclusion proofs aggregate too, and a multiproof missing a node is rejected
+		let constraints = vec![
+			Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payloads[3].abi_encode().unwrap() },
+			Constraint {
+				constraint_type: EXCLUSION_CONSTRAINT_TYPE,
+				payload: ExclusionPayload { slot: 12345, tx_hash: B256::random() }.abi_encode().unwrap(),
+			},
+		];
+		let mut multiproof = into_multiproof(builder.prove_constraints(&constraints).unwrap()).unwrap();
+		assert!(builder.verify_batch(&multiproof).is_ok());
+		multiproof.multiproof_nodes.pop();
+		assert!(builder.verify_batch(&multiproof).is_err());
+	}
+
+	#[test]
+	fn test_verify_against_transactions_root() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let tx_hashes = transactions.iter().map(|tx| *tx.hash()).collect::<Vec<_>>();
+
+		// The trie commits to the same root as the execution header
+		let builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		let transactions_root = calculate_transaction_root(&transactions);
+		assert_eq!(builder.root().unwrap(), transactions_root);
+
+		let constraints = vec![
+			Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payloads[2].abi_encode().unwrap() },
+			Constraint {
+				constraint_type: EXCLUSION_CONSTRAINT_TYPE,
+				payload: ExclusionPayload { slot: 12345, tx_hash: B256::random() }.abi_encode().unwrap(),
+			},
+		];
+		let proofs = builder.prove_constraints(&constraints).unwrap();
+		assert!(verify_batch_against_root(&transactions_root, &tx_hashes, &proofs).is_ok());
+		assert!(verify_batch_against_root(&B256::random(), &tx_hashes, &proofs).is_err());
+	}
+
+	#[test]
+	fn test_verify_legacy_proofs_over_rlp_encoded_transactions() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+
+		// Legacy provers put the RLP encoded transactions in the trie and encoded proofs with bincode
+		let mut trie = EthTrie::new(Arc::new(MemoryDB::new(true)));
+		for (index, tx) in transactions.iter().enumerate() {
+			trie.insert(alloy::rlp::encode(U256::from(index)).as_slice(), &alloy::rlp::encode(tx)).unwrap();
+		}
+		let legacy_root = B256::from_slice(trie.root_hash().unwrap().as_bytes());
+		let proof = InclusionProof {
+			tx_hash: *transactions[1].hash(),
+			tx_index: 1,
+			proof: trie.get_proof(alloy::rlp::encode(U256::from(1)).as_slice()).unwrap(),
+		};
+		let legacy = ConstraintProofs {
+			constraint_types: vec![INCLUSION_CONSTRAINT_TYPE],
+			payloads: vec![Bytes::from(bincode::serialize(&proof).unwrap())],
+			..Default::default()
+		};
+		assert!(is_legacy_proof(&legacy.payloads[0]));
+
+		// Typed transactions are not encoded the same way, the legacy root is not the header's
+		let transactions_root = TransactionsRoot::new(&transactions);
+		assert_ne!(transactions_root.root(), legacy_root);
+		assert!(transactions_root.verifier(&legacy).unwrap().verify_batch(&legacy).is_err());
+		let verifier = transactions_root.verifier(&legacy).unwrap().with_legacy_proofs(&legacy, &transactions);
+		assert!(verifier.verify_batch(&legacy).is_ok());
+
+		// Versioned proofs of the same batch are still verified against the header's root
+		let versioned = ConstraintProofs {
+			constraint_types: vec![INCLUSION_CONSTRAINT_TYPE],
+			payloads: vec![proof.to_bytes().unwrap()],
+			..Default::default()
+		};
+		assert!(verifier.verify_batch(&versioned).is_err());
+		let proofs =
+			TransactionTrieBuilder::build(&transactions).unwrap().prove_batch(&[*transactions[1].hash()]).unwrap();
+		assert!(verifier.verify_batch(&proofs).is_ok());
+	}
+
+	#[test]
+	fn test_prove_and_verify_exclusion() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let excluded = InclusionPayload::random().tx_hash().unwrap();
+
+		let constraints = vec![
+			Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payloads[1].abi_encode().unwrap() },
+			Constraint {
+				constraint_type: EXCLUSION_CONSTRAINT_TYPE,
+				payload: ExclusionPayload { slot: 12345, tx_hash: excluded }.abi_encode().unwrap(),
+			},
+		];
+
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		assert_eq!(proofs.constraint_types, vec![INCLUSION_CONSTRAINT_TYPE, EXCLUSION_CONSTRAINT_TYPE]);
+
+		let verifier_builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		let result = verifier_builder.verify_batch(&proofs);
+		assert!(result.is_ok(), "verify_batch failed: {:?}", result.err());
+
+		// An exclusion proof does not hold for a block that contains the transaction
+		let included = payloads[0].tx_hash().unwrap();
+		let builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		assert!(ExclusionProof::new(&builder, included).is_err());
+
+		let forged = ExclusionProof { tx_hash: included, ..ExclusionProof::new(&builder, excluded).unwrap() };
+		let proofs = ConstraintProofs {
+			constraint_types: vec![EXCLUSION_CONSTRAINT_TYPE],
+			payloads: vec![forged.to_bytes().unwrap()],
+			..Default::default()
+		};
+		assert!(builder.verify_batch(&proofs).is_err());
+	}
+
+	#[test]
+	fn test_prove_and_verify_ordering() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+
+		let ordering = OrderingPayload { slot: 12345, tx_hash: payloads[2].tx_hash().unwrap(), max_index: 2 };
+		let constraints =
+			vec![Constraint { constraint_type: ORDERING_CONSTRAINT_TYPE, payload: ordering.abi_encode().unwrap() }];
+
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		assert_eq!(proofs.constraint_types, vec![ORDERING_CONSTRAINT_TYPE]);
+		assert_eq!(InclusionProof::from_bytes(&proofs.payloads[0]).unwrap().tx_index, 2);
+
+		let verifier_builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		let result = verifier_builder.verify_batch(&proofs);
+		assert!(result.is_ok(), "verify_batch failed: {:?}", result.err());
+	}
+
+	#[test]
+	fn test_prove_and_verify_bundle() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+
+		let bundle = BundleConstraintPayload {
+			slot: 12345,
+			tx_hashes: vec![payloads[1].tx_hash().unwrap(), payloads[2].tx_hash().unwrap()],
+		};
+		let constraints =
+			vec![Constraint { constraint_type: BUNDLE_CONSTRAINT_TYPE, payload: bundle.abi_encode().unwrap() }];
+
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		let bundle_proof = BundleProof::from_bytes(&proofs.payloads[0]).unwrap();
+		assert_eq!(bundle_proof.proofs.iter().map(|p| p.tx_index).collect::<Vec<_>>(), vec![1, 2]);
+
+		let verifier_builder = TransactionTrieBuilder::build(&transactions).unwrap();
+		let result = verifier_builder.verify_batch(&proofs);
+		assert!(result.is_ok(), "verify_batch failed: {:?}", result.err());
+	}
+
+	#[test]
+	fn test_prove_constraints_report_lists_unsatisfied() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads[..2].iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let inclusion = |payload: &InclusionPayload| Constraint {
+			constraint_type: INCLUSION_CONSTRAINT_TYPE,
+			payload: payload.abi_encode().unwrap(),
+		};
+		let ordering = OrderingPayload { slot: 12345, tx_hash: payloads[1].tx_hash().unwrap(), max_index: 0 };
+		let constraints = vec![
+			inclusion(&payloads[0]),
+			inclusion(&payloads[2]),
+			Constraint { constraint_type: ORDERING_CONSTRAINT_TYPE, payload: ordering.abi_encode().unwrap() },
+		];
+
+		let report = prove_constraints_report(&transactions, &[], &constraints).unwrap();
+		assert!(!report.is_complete());
+		assert_eq!(report.proofs.constraint_types, vec![INCLUSION_CONSTRAINT_TYPE]);
+		// The third transaction is missing and the second one sits below its ordering bound
+		assert_eq!(report.unsatisfied.iter().map(|u| u.index).collect::<Vec<_>>(), vec![1, 2]);
+		assert!(report.unsatisfied[1].reason.contains("beyond the maximum index"));
+		assert!(report.into_proofs().is_err());
+
+		let report = prove_constraints_report(&transactions, &[], &constraints[..1]).unwrap();
+		let proofs = report.into_proofs().unwrap();
+		assert!(TransactionTrieBuilder::build(&transactions).unwrap().verify_batch(&proofs).is_ok());
+	}
+
+	#[test]
+	fn test_exclusion_proof_rejects_wrong_transaction_count() {
+		let transactions = vec![InclusionPayload::random().decode_transaction().unwrap()];
+		let builder = TransactionTrieBuilder::build(&transactions).unwrap();
+
+		let mut proof = ExclusionProof::new(&builder, B256::random()).unwrap();
+		proof.tx_count = 0;
+		let proofs = ConstraintProofs {
+			constraint_types: vec![EXCLUSION_CONSTRAINT_TYPE],
+			payloads: vec![proof.to_bytes().unwrap()],
+			..Default::default()
+		};
+		assert!(builder.verify_batch(&proofs).is_err());
+	}
+
+	/// Proofs come from builders, malformed or adversarial ones must be rejected without panicking
+	mod properties {
+		use super::*;
+		use proptest::prelude::*;
+		use std::sync::LazyLock;
+
+		const TX_COUNT: usize = 8;
+
+		/// Signing transactions is slow, the blocks are shared by every case
+		static BLOCK: LazyLock<TransactionTrieBuilder> = LazyLock::new(random_block);
+		static OTHER_BLOCK: LazyLock<TransactionTrieBuilder> = LazyLock::new(random_block);
+
+		fn random_block() -> TransactionTrieBuilder {
+			let transactions =
+				(0..TX_COUNT).map(|_| InclusionPayload::random().decode_transaction().unwrap()).collect::<Vec<_>>();
+			TransactionTrieBuilder::build(&transactions).unwrap()
+		}
+
+		fn proofs_of(constraint_type: u64, payload: Bytes) -> ConstraintProofs {
+			ConstraintProofs { constraint_types: vec![constraint_type], payloads: vec![payload], ..Default::default() }
+		}
+
+		fn inclusion_proof(index: usize) -> InclusionProof {
+			InclusionProof::new(&BLOCK, BLOCK.tx_hashes()[index]).unwrap()
+		}
+
+		fn constraint_type() -> impl Strategy<Value = u64> {
+			prop_oneof![
+				Just(INCLUSION_CONSTRAINT_TYPE),
+				Just(EXCLUSION_CONSTRAINT_TYPE),
+				Just(ORDERING_CONSTRAINT_TYPE),
+				Just(BUNDLE_CONSTRAINT_TYPE),
+				Just(BLOB_CONSTRAINT_TYPE),
+				any::<u64>(),
+			]
+		}
+
+		proptest! {
+			#[test]
+			fn test_decoding_arbitrary_bytes_never_panics(
+				mut bytes in prop::collection::vec(any::<u8>(), 0..512),
+				versioned in any::<bool>(),
+			) {
+				if versioned {
+					bytes.insert(0, PROOF_VERSION_SSZ);
+				}
+				let bytes = Bytes::from(bytes);
+				let _ = InclusionProof::from_bytes(&bytes);
+				let _ = ExclusionProof::from_bytes(&bytes);
+				let _ = BundleProof::from_bytes(&bytes);
+			}
+
+			#[test]
+			fn test_truncated_proofs_do_not_verify(index in 0..TX_COUNT, cut in any::<prop::sample::Index>()) {
+				let encoded = inclusion_proof(index).to_bytes().unwrap();
+				let truncated = encoded.slice(..cut.index(encoded.len()));
+				prop_assert!(BLOCK.verify_batch(&proofs_of(INCLUSION_CONSTRAINT_TYPE, truncated)).is_err());
+			}
+
+			#[test]
+			fn test_corrupted_nodes_do_not_verify(
+				index in 0..TX_COUNT,
+				node in any::<prop::sample::Index>(),
+				byte in any::<prop::sample::Index>(),
+				flip in 1..=u8::MAX,
+			) {
+				let mut proof = inclusion_proof(index);
+				let node = node.get_mut(&mut proof.proof);
+				let byte = byte.index(node.len());
+				node[byte] ^= flip;
+				let proofs = proofs_of(INCLUSION_CONSTRAINT_TYPE, proof.to_bytes().unwrap());
+				prop_assert!(BLOCK.verify_batch(&proofs).is_err());
+			}
+
+			#[test]
+			fn test_proofs_do_not_verify_at_another_index(index in 0..TX_COUNT, claimed in 0..TX_COUNT + 2) {
+				prop_assume!(index != claimed);
+				let mut proof = inclusion_proof(index);
+				proof.tx_index = claimed;
+				let proofs = proofs_of(INCLUSION_CONSTRAINT_TYPE, proof.to_bytes().unwrap());
+				prop_assert!(BLOCK.verify_batch(&proofs).is_err());
+			}
+
+			#[test]
+			fn test_proofs_of_another_block_do_not_verify(index in 0..TX_COUNT, multiproof in any::<bool>()) {
+				let mut proofs = OTHER_BLOCK.prove_batch(&OTHER_BLOCK.tx_hashes()[index..=index]).unwrap();
+				if multiproof {
+					proofs = into_multiproof(proofs).unwrap();
+				}
+				prop_assert!(BLOCK.verify_batch(&proofs).is_err());
+			}
+
+			#[test]
+			fn test_forged_exclusion_proofs_do_not_verify(index in 0..TX_COUNT, tx_count in 0..TX_COUNT * 2) {
+				let mut included = ExclusionProof::new(&BLOCK, B256::random()).unwrap();
+				included.tx_hash = BLOCK.tx_hashes()[index];
+				let proofs = proofs_of(EXCLUSION_CONSTRAINT_TYPE, included.to_bytes().unwrap());
+				prop_assert!(BLOCK.verify_batch(&proofs).is_err());
+
+				prop_assume!(tx_count != TX_COUNT);
+				let mut miscounted = ExclusionProof::new(&BLOCK, B256::random()).unwrap();
+				miscounted.tx_count = tx_count;
+				let proofs = proofs_of(EXCLUSION_CONSTRAINT_TYPE, miscounted.to_bytes().unwrap());
+				prop_assert!(BLOCK.verify_batch(&proofs).is_err());
+			}
+
+			#[test]
+			fn test_arbitrary_batches_do_not_verify(
+				constraint_types in prop::collection::vec(constraint_type(), 1..8),
+				payloads in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..256), 0..8),
+				multiproof_nodes in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..128), 0..4),
+			) {
+				let proofs = ConstraintProofs {
+					constraint_types,
+					payloads: payloads.into_iter().map(Bytes::from).collect(),
+					multiproof_nodes: multiproof_nodes.into_iter().map(Bytes::from).collect(),
+				};
+				prop_assert!(BLOCK.verify_batch(&proofs).is_err());
+			}
+		}
+	}
 }
@@ -1,5 +1,14 @@
 use commit_boost::prelude::Chain;
+use common::http::HttpClientConfig;
+use common::logging::LoggingConfig;
+use config::{ConfigErrors, Validate};
+use constraints::receiver_auth::ReceiverAuthConfig;
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
+use urc::indexer::UrcConfig;
+
+/// Prefix of the environment variables overriding relay config values, e.g. `FABRIC_RELAY__PORT`
+pub const RELAY_ENV_PREFIX: &str = "FABRIC_RELAY";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RelayConfig {
@@ -27,9 +36,146 @@ pub struct RelayConfig {
 	/// How often to update the lookahead window
 	pub lookahead_update_interval: u64,
 
+	/// Epochs after the current one whose proposer duties are prefetched every slot
+	#[serde(default = "default_lookahead_prefetch_epochs")]
+	pub lookahead_prefetch_epochs: u64,
+
 	/// Host of the downstream relay for proxying unhandled requests
 	pub downstream_relay_host: String,
 
 	/// Port of the downstream relay for proxying unhandled requests
 	pub downstream_relay_port: u16,
+
+	/// Additional downstream relay URLs (e.g. "http://host:port") to fail over to
+	#[serde(default)]
+	pub downstream_relay_fallback_urls: Vec<String>,
+
+	/// Hex encoded ECDSA private key used to sign receipts for accepted messages
+	/// Required, so receipts keep verifying against the same address across restarts
+	pub receipt_signing_key: String,
+
+	/// Port of the Prometheus metrics server, served on the same host. Metrics are not served if not set
+	#[serde(default)]
+	pub metrics_port: Option<u16>,
+
+	/// Port of the admin API, served on the same host. The admin API is disabled if not set
+	#[serde(default)]
+	pub admin_port: Option<u16>,
+
+	/// Bearer token required to call the admin API
+	#[serde(default)]
+	pub admin_api_key: Option<String>,
+
+	/// How often to health check the downstream relays, in seconds
+	#[serde(default = "default_downstream_health_check_interval")]
+	pub downstream_health_check_interval: u64,
+
+	/// Timeout and connection pool of the downstream relay clients, blocks are forwarded on the slot's critical path
+	#[serde(default)]
+	pub downstream_relay_http: HttpClientConfig,
+
+	/// Blocks are accepted for the current and the next slot, and for the previous slot until this many milliseconds
+	/// into the current one
+	#[serde(default = "default_block_slot_grace_ms")]
+	pub block_slot_grace_ms: u64,
+
+	/// Execution client whose head at the start of a block's slot must be the block's parent, the parent is not checked
+	/// if not set
+	#[serde(default)]
+	pub execution_client_url: Option<Url>,
+
+	/// Number of blocks whose transaction trie is kept for resubmissions and dry runs, 0 disables the cache
+	#[serde(default = "default_trie_cache_size")]
+	pub trie_cache_size: usize,
+
+	/// How far the timestamp of a receiver's signature on GET /constraints/{slot} may be from the relay's clock, in
+	/// milliseconds. Each signature is accepted once within this window
+	#[serde(default = "default_receiver_auth_window_ms")]
+	pub receiver_auth_window_ms: u64,
+
+	/// Which read routes besides GET /constraints/{slot} only authenticated receivers may read before the slot
+	#[serde(default)]
+	pub receiver_auth: ReceiverAuthConfig,
+
+	/// Gateway delegate public keys constraints and delegations are accepted for, any delegate if not set
+	#[serde(default)]
+	pub trusted_delegates: Option<Vec<String>>,
+
+	/// URC registry delegations are checked against, any validly signed delegation is accepted if not set
+	#[serde(default)]
+	pub urc: Option<UrcConfig>,
+
+	/// Log format, module levels and log file
+	#[serde(default)]
+	pub logging: LoggingConfig,
+}
+
+impl Validate for RelayConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.host("host", &self.host);
+		errors.port("port", self.port);
+		errors.distinct_ports(&[
+			("port", Some(self.port)),
+			("metrics_port", self.metrics_port),
+			("admin_port", self.admin_port),
+		]);
+		errors.not_empty("db_path", &self.db_path);
+		errors.ensure(!self.constraint_capabilities.is_empty(), "constraint_capabilities", "must not be empty");
+		errors.host("beacon_api_host", &self.beacon_api_host);
+		errors.port("beacon_api_port", self.beacon_api_port);
+		errors.positive("lookahead_update_interval", self.lookahead_update_interval);
+		errors.host("downstream_relay_host", &self.downstream_relay_host);
+		errors.port("downstream_relay_port", self.downstream_relay_port);
+		for (index, url) in self.downstream_relay_fallback_urls.iter().enumerate() {
+			errors.url(&format!("downstream_relay_fallback_urls[{}]", index), url);
+		}
+		errors.bytes32("receipt_signing_key", &self.receipt_signing_key);
+		errors.ensure(
+			self.admin_port.is_none() || self.admin_api_key.as_ref().is_some_and(|key| !key.is_empty()),
+			"admin_api_key",
+			"must be set when admin_port is set",
+		);
+		errors.positive("downstream_health_check_interval", self.downstream_health_check_interval);
+		errors.positive("receiver_auth_window_ms", self.receiver_auth_window_ms);
+		for (index, path) in self.receiver_auth.proxy_paths.iter().enumerate() {
+			errors.ensure(
+				path.starts_with('/'),
+				&format!("receiver_auth.proxy_paths[{}]", index),
+				"must start with '/'",
+			);
+		}
+		errors.positive("downstream_relay_http.timeout_ms", self.downstream_relay_http.timeout_ms);
+		if let Some(trusted_delegates) = &self.trusted_delegates {
+			errors.ensure(!trusted_delegates.is_empty(), "trusted_delegates", "must not be empty when set");
+			for (index, delegate) in trusted_delegates.iter().enumerate() {
+				errors.bls_public_key(&format!("trusted_delegates[{}]", index), delegate);
+			}
+		}
+		if let Some(urc) = &self.urc {
+			errors.url("urc.rpc_url", &urc.rpc_url);
+		}
+		if let Err(e) = self.logging.check() {
+			errors.add("logging", e);
+		}
+	}
+}
+
+fn default_downstream_health_check_interval() -> u64 {
+	6
+}
+
+fn default_trie_cache_size() -> usize {
+	64
+}
+
+fn default_block_slot_grace_ms() -> u64 {
+	1_000
+}
+
+fn default_receiver_auth_window_ms() -> u64 {
+	12_000
+}
+
+fn default_lookahead_prefetch_epochs() -> u64 {
+	1
 }
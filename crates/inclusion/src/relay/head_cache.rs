@@ -0,0 +1,227 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::network::Ethereum;
+use alloy::primitives::B256;
+use alloy::providers::{DynProvider, Provider};
+use constraints::error::{ConstraintsError, Result};
+
+/// Heads kept, enough for a block of the previous slot to find its parent once the current slot has a block
+const MAX_HEADS: usize = 4;
+/// Least time between two head requests made for submissions naming a parent that is not cached
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+/// Age after which the cached head is refreshed before being served, the head otherwise only changes on submissions
+const MAX_HEAD_AGE: Duration = Duration::from_secs(12);
+
+/// Head block of the execution client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadBlock {
+	pub hash: B256,
+	pub parent_hash: B256,
+	pub number: u64,
+	/// Unix timestamp in seconds
+	pub timestamp: u64,
+	pub gas_limit: u64,
+}
+
+#[derive(Default)]
+struct RecentHeads {
+	/// Consecutive heads, oldest first
+	heads: VecDeque<HeadBlock>,
+	refreshed_at: Option<Instant>,
+}
+
+impl RecentHeads {
+	/// Record a head, starting over if it does not extend the cached chain, after a reorg or a missed head
+	fn observe(&mut self, head: HeadBlock, now: Instant) {
+		self.refreshed_at = Some(now);
+		match self.heads.back() {
+			Some(last) if last.hash == head.hash => return,
+			Some(last) if last.hash == head.parent_hash => {}
+			_ => self.heads.clear(),
+		}
+		self.heads.push_back(head);
+		while self.heads.len() > MAX_HEADS {
+			self.heads.pop_front();
+		}
+	}
+
+	/// Parent of a block of the slot starting at `slot_start_ms`: the newest head from before the slot
+	/// None if every cached head is from a later slot
+	fn parent_of(&self, slot_start_ms: u64) -> Option<B256> {
+		self.heads.iter().rev().find(|head| head.timestamp * 1000 < slot_start_ms).map(|head| head.hash).or_else(|| {
+			// The parent of a block of the slot itself is from before the slot
+			let oldest = self.heads.front()?;
+			(oldest.timestamp * 1000 == slot_start_ms).then_some(oldest.parent_hash)
+		})
+	}
+
+	fn is_older_than(&self, age: Duration) -> bool {
+		self.refreshed_at.is_none_or(|refreshed_at| refreshed_at.elapsed() >= age)
+	}
+}
+
+/// Recent heads of the execution client the relay follows
+///
+/// Submitted blocks must build on the head their slot started on. A block of the previous slot, accepted during the
+/// grace window, builds on the head from before that slot even once the previous slot's block is the head.
+/// The execution client is only asked for the head when a submission names a parent that is not cached, so the
+/// submissions of a slot cost one request rather than one each.
+pub struct HeadCache {
+	execution_client: DynProvider<Ethereum>,
+	recent: Mutex<RecentHeads>,
+}
+
+impl HeadCache {
+	pub fn new(execution_client: DynProvider<Ethereum>) -> Self {
+		Self { execution_client, recent: Mutex::new(RecentHeads::default()) }
+	}
+
+	/// Latest head, fetched if none is cached or it is older than a slot
+	pub async fn head(&self) -> Result<HeadBlock> {
+		{
+			let recent = self.recent.lock().expect("head cache lock poisoned");
+			if let Some(head) = recent.heads.back()
+				&& !recent.is_older_than(MAX_HEAD_AGE)
+			{
+				return Ok(*head);
+			}
+		}
+		self.refresh().await
+	}
+
+	/// Check that `parent_hash` is the head a block of the slot starting at `slot_start_ms` builds on
+	/// A parent that is not cached refreshes the head, at most once per REFRESH_INTERVAL
+	pub async fn check_parent(&self, slot_start_ms: u64, parent_hash: B256) -> Result<()> {
+		let (mut expected, refresh) = {
+			let recent = self.recent.lock().expect("head cache lock poisoned");
+			(recent.parent_of(slot_start_ms), recent.is_older_than(REFRESH_INTERVAL))
+		};
+		if expected != Some(parent_hash) && refresh {
+			self.refresh().await?;
+			expected = self.recent.lock().expect("head cache lock poisoned").parent_of(slot_start_ms);
+		}
+
+		match expected {
+			Some(expected) if expected == parent_hash => Ok(()),
+			Some(expected) => Err(ConstraintsError::InvalidRequest(format!(
+				"Block parent {} is not the head {} the slot builds on",
+				parent_hash, expected
+			))),
+			None => Err(ConstraintsError::InvalidRequest(format!(
+				"Block parent {} is not a known head, the slot has already been built on",
+				parent_hash
+			))),
+		}
+	}
+
+	/// Record a head of the execution client
+	pub fn observe(&self, head: HeadBlock) {
+		self.recent.lock().expect("head cache lock poisoned").observe(head, Instant::now());
+	}
+
+	async fn refresh(&self) -> Result<HeadBlock> {
+		let block = self
+			.execution_client
+			.get_block_by_number(BlockNumberOrTag::Latest)
+			.await
+			.map_err(|e| ConstraintsError::Upstream(format!("Failed to fetch the head block: {e}")))?
+			.ok_or_else(|| ConstraintsError::Upstream("Execution client returned no head block".to_string()))?;
+		let head = HeadBlock {
+			hash: block.header.hash,
+			parent_hash: block.header.parent_hash,
+			number: block.header.number,
+			timestamp: block.header.timestamp,
+			gas_limit: block.header.gas_limit,
+		};
+		self.observe(head);
+		Ok(head)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Blocks of consecutive 12 second slots from slot 1, each on the previous one
+	fn chain(slots: std::ops::Range<u64>) -> Vec<HeadBlock> {
+		slots
+			.map(|slot| HeadBlock {
+				hash: B256::with_last_byte(slot as u8),
+				parent_hash: B256::with_last_byte(slot as u8 - 1),
+				number: slot,
+				timestamp: slot * 12,
+				gas_limit: 30_000_000,
+			})
+			.collect()
+	}
+
+	fn slot_start_ms(slot: u64) -> u64 {
+		slot * 12_000
+	}
+
+	#[test]
+	fn test_parent_of_each_slot() {
+		let mut recent = RecentHeads::default();
+		assert_eq!(recent.parent_of(slot_start_ms(5)), None);
+
+		// Only the block of slot 4 is known, its parent is what a late block of slot 4 builds on
+		let blocks = chain(1..6);
+		recent.observe(blocks[3], Instant::now());
+		assert_eq!(recent.parent_of(slot_start_ms(4)), Some(blocks[2].hash));
+		assert_eq!(recent.parent_of(slot_start_ms(5)), Some(blocks[3].hash));
+		assert_eq!(recent.parent_of(slot_start_ms(6)), Some(blocks[3].hash));
+
+		// Once slot 5 has a block, blocks of slot 5 still build on slot 4 and blocks of slot 6 on slot 5
+		recent.observe(blocks[4], Instant::now());
+		assert_eq!(recent.parent_of(slot_start_ms(4)), Some(blocks[2].hash));
+		assert_eq!(recent.parent_of(slot_start_ms(5)), Some(blocks[3].hash));
+		assert_eq!(recent.parent_of(slot_start_ms(6)), Some(blocks[4].hash));
+
+		// An empty slot 6 leaves slot 7 building on slot 5
+		assert_eq!(recent.parent_of(slot_start_ms(7)), Some(blocks[4].hash));
+	}
+
+	#[test]
+	fn test_observe_keeps_consecutive_heads() {
+		let mut recent = RecentHeads::default();
+		let blocks = chain(1..8);
+		for block in &blocks {
+			recent.observe(*block, Instant::now());
+			recent.observe(*block, Instant::now());
+		}
+		assert_eq!(recent.heads.iter().copied().collect::<Vec<_>>(), blocks[3..]);
+
+		// A head that does not extend the cached chain replaces it
+		let reorged = HeadBlock { hash: B256::repeat_byte(0xff), parent_hash: blocks[5].hash, ..blocks[6] };
+		recent.observe(reorged, Instant::now());
+		assert_eq!(recent.heads.iter().copied().collect::<Vec<_>>(), [reorged]);
+		assert_eq!(recent.parent_of(slot_start_ms(7)), Some(blocks[5].hash));
+		assert_eq!(recent.parent_of(slot_start_ms(6)), None);
+	}
+
+	#[tokio::test]
+	async fn test_cached_heads_need_no_request() {
+		// Nothing listens on the execution client's port, every request would fail
+		let url = "http://127.0.0.1:1".parse().unwrap();
+		let cache = HeadCache::new(alloy::providers::ProviderBuilder::new().connect_http(url).erased());
+		let blocks = chain(1..6);
+		cache.observe(blocks[3]);
+		cache.observe(blocks[4]);
+
+		assert_eq!(cache.head().await.unwrap(), blocks[4]);
+		cache.check_parent(slot_start_ms(6), blocks[4].hash).await.unwrap();
+		cache.check_parent(slot_start_ms(5), blocks[3].hash).await.unwrap();
+
+		// A wrong parent right after a refresh is rejected without asking again
+		let error = cache.check_parent(slot_start_ms(6), blocks[3].hash).await.unwrap_err();
+		assert!(matches!(error, ConstraintsError::InvalidRequest(_)), "{}", error);
+
+		// Later on, the head is refreshed before rejecting, and the unreachable execution client fails the check
+		cache.recent.lock().unwrap().refreshed_at = Some(Instant::now() - REFRESH_INTERVAL);
+		let error = cache.check_parent(slot_start_ms(6), blocks[3].hash).await.unwrap_err();
+		assert!(matches!(error, ConstraintsError::Upstream(_)), "{}", error);
+	}
+}
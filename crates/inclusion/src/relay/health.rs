@@ -0,0 +1,25 @@
+//! Readiness of the relay
+use async_trait::async_trait;
+use common::health::{Readiness, ReadinessProbe};
+use eyre::eyre;
+
+use crate::relay::state::RelayState;
+
+/// Slots without a successful proposer duties fetch before the lookahead counts as stale. The duties are prefetched
+/// every slot, so this only trips when the beacon node is unreachable
+const MAX_LOOKAHEAD_AGE_SLOTS: u64 = 2;
+
+#[async_trait]
+impl ReadinessProbe for RelayState {
+	async fn readiness(&self) -> Readiness {
+		let mut readiness = Readiness::default();
+		readiness.add("db", self.db.healthcheck().map_err(Into::into));
+		readiness.add("lookahead", self.lookahead_cache.check_fresh(MAX_LOOKAHEAD_AGE_SLOTS));
+		let downstream = match self.downstream_relays.health().iter().any(|(_, health)| health.healthy) {
+			true => Ok(()),
+			false => Err(eyre!("no downstream relay is healthy")),
+		};
+		readiness.add("downstream_relays", downstream);
+		readiness
+	}
+}
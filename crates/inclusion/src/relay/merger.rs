@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use alloy::primitives::B256;
+use alloy::rpc::types::beacon::BlsPublicKey;
+use constraints::conflicts::{ConstraintEffect, find_conflicts};
+use constraints::error::{ConstraintsError, Result};
+use constraints::registry::ConstraintRegistry;
+use constraints::types::{Constraint, SignedConstraints};
+
+use crate::constants::MAX_CONSTRAINTS_PER_SLOT;
+use crate::proofs::flatten_signed_constraints;
+
+/// Merges the signed constraints posted by different delegated gateways for the same slot.
+///
+/// Each delegate contributes at most one SignedConstraints message per slot; a re-post from the
+/// same delegate replaces its previous message. The merged set is the union over all delegates,
+/// ordered by delegate public key so builders see a stable constraint ordering.
+#[derive(Debug, Clone, Default)]
+pub struct ConstraintsMerger {
+	constraints: Vec<SignedConstraints>,
+}
+
+impl ConstraintsMerger {
+	/// Create a merger from the signed constraints already accepted for a slot
+	pub fn new(existing: Vec<SignedConstraints>) -> Self {
+		let mut constraints = existing;
+		constraints.sort_by(|a, b| a.message.delegate.cmp(&b.message.delegate));
+		Self { constraints }
+	}
+
+	/// Merge an incoming SignedConstraints message into the set
+	/// Rejects the message if it duplicates or conflicts with constraints posted by another delegate, as described
+	/// by the registry's handlers, or if the merged constraints need more gas than `block_gas_limit`
+	pub fn insert(
+		&mut self,
+		registry: &ConstraintRegistry,
+		incoming: SignedConstraints,
+		block_gas_limit: Option<u64>,
+	) -> Result<()> {
+		let slot = incoming.message.slot;
+		let delegate = incoming.message.delegate;
+
+		// Constraints posted by other delegates are kept as-is
+		let others = self.constraints.iter().filter(|c| c.message.delegate != delegate).collect::<Vec<_>>();
+
+		if let Some(other) = others.iter().find(|c| c.message.slot != slot) {
+			return Err(ConstraintsError::Conflict(format!(
+				"Cannot merge constraints for slot {} with constraints for slot {}",
+				slot, other.message.slot
+			)));
+		}
+
+		if let Some(other) = others.iter().find(|c| c.message.proposer != incoming.message.proposer) {
+			return Err(ConstraintsError::Conflict(format!(
+				"Proposer mismatch for slot {}: delegate {} signed for a different proposer than delegate {}",
+				slot, delegate, other.message.delegate
+			)));
+		}
+
+		let total =
+			others.iter().map(|c| c.message.constraints.len()).sum::<usize>() + incoming.message.constraints.len();
+		if total > MAX_CONSTRAINTS_PER_SLOT {
+			return Err(ConstraintsError::Conflict(format!(
+				"Too many constraints for slot {}: {} exceeds maximum of {}",
+				slot, total, MAX_CONSTRAINTS_PER_SLOT
+			)));
+		}
+
+		// Detect conflicting payloads across all delegates, including duplicates within the incoming message
+		let mut seen: HashMap<ConstraintKey, BlsPublicKey> = HashMap::new();
+		for other in &others {
+			for constraint in &other.message.constraints {
+				seen.insert(ConstraintKey::from_constraint(registry, constraint)?, other.message.delegate);
+			}
+		}
+
+		for constraint in &incoming.message.constraints {
+			let key = ConstraintKey::from_constraint(registry, constraint)?;
+			if let Some(owner) = seen.get(&key) {
+				return Err(ConstraintsError::Conflict(format!(
+					"Conflicting constraint for slot {}: {} already constrained by {}",
+					slot, key, owner
+				)));
+			}
+			seen.insert(key, delegate);
+		}
+
+		// The merged constraints of every delegate must still be satisfiable by a single block
+		let merged = others
+			.iter()
+			.flat_map(|c| c.message.constraints.iter())
+			.chain(&incoming.message.constraints)
+			.cloned()
+			.collect::<Vec<_>>();
+		find_conflicts(registry, &merged, block_gas_limit)?.into_result()?;
+
+		// Replace any previous message from this delegate and keep the set ordered by delegate
+		self.constraints.retain(|c| c.message.delegate != delegate);
+		let position = self.constraints.partition_point(|c| c.message.delegate < delegate);
+		self.constraints.insert(position, incoming);
+
+		Ok(())
+	}
+
+	/// The merged signed constraints, one entry per delegate
+	pub fn merged(&self) -> &[SignedConstraints] {
+		&self.constraints
+	}
+
+	/// Consume the merger and return the merged signed constraints
+	pub fn into_merged(self) -> Vec<SignedConstraints> {
+		self.constraints
+	}
+
+	/// All constraints across delegates, in the order builders must prove them
+	pub fn flattened_constraints(&self) -> Vec<Constraint> {
+		flatten_signed_constraints(&self.constraints)
+	}
+}
+
+/// Identity used to detect duplicate constraints
+/// A constraint whose handler describes a single transaction is identified by its type and that transaction, other
+/// constraints by their type and payload. Constraints of different types on the same transaction are left to
+/// `find_conflicts`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ConstraintKey {
+	Transaction(u64, B256),
+	Payload(u64, Vec<u8>),
+}
+
+impl ConstraintKey {
+	fn from_constraint(registry: &ConstraintRegistry, constraint: &Constraint) -> Result<Self> {
+		Ok(match registry.effects(constraint)?.as_slice() {
+			[ConstraintEffect::Includes { tx_hash, .. }]
+			| [ConstraintEffect::Excludes { tx_hash }]
+			| [ConstraintEffect::AtOrBefore { tx_hash, .. }] => ConstraintKey::Transaction(constraint.constraint_type, *tx_hash),
+			_ => ConstraintKey::Payload(constraint.constraint_type, constraint.payload.to_vec()),
+		})
+	}
+}
+
+impl std::fmt::Display for ConstraintKey {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ConstraintKey::Transaction(constraint_type, tx_hash) => {
+				write!(f, "transaction {} of constraint type {}", tx_hash, constraint_type)
+			}
+			ConstraintKey::Payload(constraint_type, _) => write!(f, "payload of constraint type {}", constraint_type),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::constants::{EXCLUSION_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE, ORDERING_CONSTRAINT_TYPE};
+	use crate::handlers::default_registry;
+	use crate::types::{ExclusionPayload, InclusionPayload, OrderingPayload};
+	use alloy::primitives::Bytes;
+	use constraints::registry::{BlockProofs, ConstraintHandler};
+	use constraints::types::ConstraintsMessage;
+
+	/// Accepts any payload and describes no effects
+	struct OpaqueHandler;
+
+	impl ConstraintHandler for OpaqueHandler {
+		fn validate_payload(&self, _payload: &Bytes) -> Result<()> {
+			Ok(())
+		}
+
+		fn check_proof(&self, _constraint: &Constraint, _proof: &Bytes) -> Result<()> {
+			Ok(())
+		}
+
+		fn verify_proof(&self, _constraint: &Constraint, _proof: &Bytes, _block: &dyn BlockProofs) -> Result<()> {
+			Ok(())
+		}
+	}
+
+	fn insert(merger: &mut ConstraintsMerger, incoming: SignedConstraints) -> Result<()> {
+		merger.insert(&default_registry().register(99, OpaqueHandler), incoming, None)
+	}
+
+	fn inclusion_constraint(payload: &InclusionPayload) -> Constraint {
+		Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payload.abi_encode().unwrap() }
+	}
+
+	fn signed_constraints(delegate: u8, slot: u64, constraints: Vec<Constraint>) -> SignedConstraints {
+		SignedConstraints {
+			message: ConstraintsMessage {
+				proposer: BlsPublicKey::from([0xAAu8; 48]),
+				delegate: BlsPublicKey::from([delegate; 48]),
+				slot,
+				constraints,
+				receivers: vec![],
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: Default::default(),
+		}
+	}
+
+	#[test]
+	fn test_merges_constraints_from_different_delegates() {
+		let tx_a = InclusionPayload::random();
+		let tx_b = InclusionPayload::random();
+
+		let mut merger = ConstraintsMerger::default();
+		insert(&mut merger, signed_constraints(0x02, 10, vec![inclusion_constraint(&tx_b)])).unwrap();
+		insert(&mut merger, signed_constraints(0x01, 10, vec![inclusion_constraint(&tx_a)])).unwrap();
+
+		let merged = merger.merged();
+		assert_eq!(merged.len(), 2);
+		assert_eq!(merged[0].message.delegate, BlsPublicKey::from([0x01u8; 48]));
+		assert_eq!(merged[1].message.delegate, BlsPublicKey::from([0x02u8; 48]));
+
+		let flattened = merger.flattened_constraints();
+		assert_eq!(flattened.len(), 2);
+		assert_eq!(flattened[0].payload, tx_a.abi_encode().unwrap());
+		assert_eq!(flattened[1].payload, tx_b.abi_encode().unwrap());
+	}
+
+	#[test]
+	fn test_rejects_duplicate_transaction_across_delegates() {
+		let tx = InclusionPayload::random();
+
+		let mut merger = ConstraintsMerger::default();
+		insert(&mut merger, signed_constraints(0x01, 10, vec![inclusion_constraint(&tx)])).unwrap();
+
+		let result = insert(&mut merger, signed_constraints(0x02, 10, vec![inclusion_constraint(&tx)]));
+		assert!(result.is_err());
+		assert!(result.unwrap_err().to_string().contains("Conflicting constraint"));
+		assert_eq!(merger.merged().len(), 1);
+	}
+
+	#[test]
+	fn test_rejects_duplicate_transaction_within_message() {
+		let tx = InclusionPayload::random();
+
+		let mut merger = ConstraintsMerger::default();
+		let result = insert(
+			&mut merger,
+			signed_constraints(0x01, 10, vec![inclusion_constraint(&tx), inclusion_constraint(&tx)]),
+		);
+		assert!(result.is_err());
+		assert!(merger.merged().is_empty());
+	}
+
+	#[test]
+	fn test_repost_from_same_delegate_replaces_previous() {
+		let tx = InclusionPayload::random();
+
+		let mut merger = ConstraintsMerger::default();
+		insert(&mut merger, signed_constraints(0x01, 10, vec![inclusion_constraint(&tx)])).unwrap();
+		// Same transaction from the same delegate is not a conflict
+		insert(&mut merger, signed_constraints(0x01, 10, vec![inclusion_constraint(&tx)])).unwrap();
+
+		assert_eq!(merger.merged().len(), 1);
+		assert_eq!(merger.flattened_constraints().len(), 1);
+	}
+
+	#[test]
+	fn test_rejects_inclusion_and_exclusion_of_same_transaction() {
+		let tx = InclusionPayload::random();
+		let exclusion = Constraint {
+			constraint_type: EXCLUSION_CONSTRAINT_TYPE,
+			payload: ExclusionPayload { slot: 10, tx_hash: tx.tx_hash().unwrap() }.abi_encode().unwrap(),
+		};
+
+		let mut merger = ConstraintsMerger::default();
+		insert(&mut merger, signed_constraints(0x01, 10, vec![inclusion_constraint(&tx)])).unwrap();
+		assert!(insert(&mut merger, signed_constraints(0x02, 10, vec![exclusion])).is_err());
+	}
+
+	#[test]
+	fn test_ordering_constraints_must_be_satisfiable() {
+		let ordering = |tx: &InclusionPayload, max_index| Constraint {
+			constraint_type: ORDERING_CONSTRAINT_TYPE,
+			payload: OrderingPayload { slot: 10, tx_hash: tx.tx_hash().unwrap(), max_index }.abi_encode().unwrap(),
+		};
+		let tx_a = InclusionPayload::random();
+		let tx_b = InclusionPayload::random();
+		let tx_c = InclusionPayload::random();
+
+		let mut merger = ConstraintsMerger::default();
+		// Ordering a transaction another gateway committed to include is not a conflict
+		insert(&mut merger, signed_constraints(0x01, 10, vec![inclusion_constraint(&tx_a), ordering(&tx_b, 0)]))
+			.unwrap();
+		insert(&mut merger, signed_constraints(0x02, 10, vec![inclusion_constraint(&tx_b), ordering(&tx_a, 1)]))
+			.unwrap();
+
+		// Both top positions are taken
+		assert!(insert(&mut merger, signed_constraints(0x03, 10, vec![ordering(&tx_c, 1)])).is_err());
+		insert(&mut merger, signed_constraints(0x03, 10, vec![ordering(&tx_c, 2)])).unwrap();
+
+		// An ordered transaction can neither be ordered twice nor excluded
+		assert!(insert(&mut merger, signed_constraints(0x04, 10, vec![ordering(&tx_a, 5)])).is_err());
+		let exclusion = Constraint {
+			constraint_type: EXCLUSION_CONSTRAINT_TYPE,
+			payload: ExclusionPayload { slot: 10, tx_hash: tx_c.tx_hash().unwrap() }.abi_encode().unwrap(),
+		};
+		assert!(insert(&mut merger, signed_constraints(0x04, 10, vec![exclusion])).is_err());
+	}
+
+	#[test]
+	fn test_rejects_identical_payloads_without_effects() {
+		let constraint = Constraint { constraint_type: 99, payload: Bytes::from(vec![0x01, 0x02]) };
+
+		let mut merger = ConstraintsMerger::default();
+		insert(&mut merger, signed_constraints(0x01, 10, vec![constraint.clone()])).unwrap();
+		assert!(insert(&mut merger, signed_constraints(0x02, 10, vec![constraint])).is_err());
+	}
+
+	#[test]
+	fn test_rejects_proposer_mismatch() {
+		let mut merger = ConstraintsMerger::new(vec![signed_constraints(0x01, 10, vec![])]);
+
+		let mut incoming = signed_constraints(0x02, 10, vec![]);
+		incoming.message.proposer = BlsPublicKey::from([0xBBu8; 48]);
+
+		assert!(insert(&mut merger, incoming).is_err());
+	}
+}
@@ -0,0 +1,128 @@
+use axum::response::{IntoResponse, Response};
+use common::metrics::FABRIC_METRICS_REGISTRY;
+use constraints::metrics::CONSTRAINTS_SERVER_METRICS_REGISTRY;
+use lazy_static::lazy_static;
+use prometheus::{
+	Encoder, Histogram, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Registry, TextEncoder,
+	register_histogram_with_registry, register_int_counter_vec_with_registry, register_int_counter_with_registry,
+	register_int_gauge_vec_with_registry, register_int_gauge_with_registry,
+};
+
+pub const RELAY_REGISTRY_NAME: &str = "relay";
+
+lazy_static! {
+	pub static ref RELAY_METRICS_REGISTRY: Registry =
+		Registry::new_custom(Some(RELAY_REGISTRY_NAME.to_string()), None).unwrap();
+
+	// Constraints metrics
+	pub static ref RELAY_CONSTRAINTS_MESSAGES_ACCEPTED_TOTAL: IntCounter = register_int_counter_with_registry!(
+		"constraints_messages_accepted_total",
+		"Total signed constraints messages accepted",
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref RELAY_CONSTRAINTS_PER_SLOT: IntGauge = register_int_gauge_with_registry!(
+		"constraints_per_slot",
+		"Number of merged constraints for the slot of the most recently accepted constraints message",
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	// Delegation metrics
+	pub static ref RELAY_DELEGATIONS_ACCEPTED_TOTAL: IntCounter = register_int_counter_with_registry!(
+		"delegations_accepted_total",
+		"Total signed delegations accepted",
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref RELAY_DELEGATION_OFFERS_ACCEPTED_TOTAL: IntCounter = register_int_counter_with_registry!(
+		"delegation_offers_accepted_total",
+		"Total signed delegation offers accepted",
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref RELAY_DELEGATIONS_PER_EPOCH: IntGaugeVec = register_int_gauge_vec_with_registry!(
+		"delegations_per_epoch",
+		"Number of delegations stored for the current and next epoch",
+		&["epoch"],
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref RELAY_INELIGIBLE_PROPOSERS_TOTAL: IntCounter = register_int_counter_with_registry!(
+		"ineligible_proposers_total",
+		"Total lookahead slots whose scheduled proposer is slashed or exited",
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref RELAY_LOOKAHEAD_REORGS_TOTAL: IntCounter = register_int_counter_with_registry!(
+		"lookahead_reorgs_total",
+		"Total epochs whose proposer duties dependent root changed after being fetched",
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	// Block submission metrics
+	pub static ref RELAY_PROOF_VERIFICATION_LATENCY_SECONDS: Histogram = register_histogram_with_registry!(
+		"proof_verification_latency_seconds",
+		"Time spent verifying constraint proofs of a block submission in seconds",
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref RELAY_BLOCKS_WITH_PROOFS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"blocks_with_proofs_total",
+		"Total blocks with proofs submissions by result",
+		&["result"],
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref RELAY_TRANSACTION_TRIE_CACHE_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+		"transaction_trie_cache_total",
+		"Total transaction trie lookups of block submissions by result",
+		&["result"],
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+
+	pub static ref RELAY_DOWNSTREAM_SUBMIT_LATENCY_SECONDS: Histogram = register_histogram_with_registry!(
+		"downstream_submit_latency_seconds",
+		"Time spent forwarding a block to the downstream relays in seconds, including failover",
+		RELAY_METRICS_REGISTRY
+	)
+	.unwrap();
+}
+
+/// Label values for RELAY_BLOCKS_WITH_PROOFS_TOTAL
+pub const BLOCK_ACCEPTED: &str = "accepted";
+pub const BLOCK_REJECTED: &str = "rejected";
+
+/// Label values for RELAY_TRANSACTION_TRIE_CACHE_TOTAL
+pub const CACHE_HIT: &str = "hit";
+pub const CACHE_MISS: &str = "miss";
+
+/// Label values for RELAY_DELEGATIONS_PER_EPOCH
+pub const CURRENT_EPOCH: &str = "current";
+pub const NEXT_EPOCH: &str = "next";
+
+/// Serves the relay business and fabric metrics together with the constraints server HTTP metrics
+pub async fn relay_metrics_handler() -> Response {
+	let mut metric_families = CONSTRAINTS_SERVER_METRICS_REGISTRY.gather();
+	metric_families.extend(RELAY_METRICS_REGISTRY.gather());
+	metric_families.extend(FABRIC_METRICS_REGISTRY.gather());
+	let mut buffer = Vec::new();
+	let encoder = TextEncoder::new();
+	if encoder.encode(&metric_families, &mut buffer).is_err() {
+		return axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+	}
+	Response::builder()
+		.status(axum::http::StatusCode::OK)
+		.header(axum::http::header::CONTENT_TYPE, encoder.format_type())
+		.body(axum::body::Body::from(buffer))
+		.unwrap()
+}
@@ -1,4 +1,10 @@
 pub mod config;
+pub mod head_cache;
+pub mod health;
+pub mod merger;
+pub mod metrics;
+pub mod replay;
 pub mod services;
 pub mod state;
+pub mod trie_cache;
 pub mod utils;
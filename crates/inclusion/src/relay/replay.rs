@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use alloy::rpc::types::beacon::BlsSignature;
+use eyre::{Result, eyre};
+
+/// Receiver authentications of GET /constraints/{slot} accepted within the replay window
+///
+/// A receiver signs the slot together with the time it signed at. The relay only accepts a signature whose
+/// timestamp is within `window_ms` of its own clock, and only once, so a captured signature can not be reused to
+/// read a receiver's constraints later or a second time.
+pub struct ReplayGuard {
+	window_ms: u64,
+	/// Timestamp of every accepted signature still within the window
+	seen: Mutex<HashMap<BlsSignature, u64>>,
+}
+
+impl ReplayGuard {
+	pub fn new(window_ms: u64) -> Self {
+		Self { window_ms, seen: Mutex::new(HashMap::new()) }
+	}
+
+	pub fn window_ms(&self) -> u64 {
+		self.window_ms
+	}
+
+	/// Reject a timestamp further than the window from `now_ms`, checked before the signature is verified
+	pub fn check_timestamp(&self, timestamp_ms: u64, now_ms: u64) -> Result<()> {
+		if timestamp_ms.abs_diff(now_ms) > self.window_ms {
+			return Err(eyre!(
+				"Receiver authentication timestamp {} is more than {}ms away from the relay time {}",
+				timestamp_ms,
+				self.window_ms,
+				now_ms
+			));
+		}
+		Ok(())
+	}
+
+	/// Accept a verified signature signed at `timestamp_ms` once, forgetting those that left the window
+	pub fn accept(&self, signature: &BlsSignature, timestamp_ms: u64, now_ms: u64) -> Result<()> {
+		self.check_timestamp(timestamp_ms, now_ms)?;
+
+		let mut seen = self.seen.lock().expect("replay guard lock poisoned");
+		seen.retain(|_, seen_ms| seen_ms.saturating_add(self.window_ms) >= now_ms);
+		if seen.insert(*signature, timestamp_ms).is_some() {
+			return Err(eyre!("Receiver authentication was already used"));
+		}
+		Ok(())
+	}
+
+	/// Number of remembered signatures
+	pub fn len(&self) -> usize {
+		self.seen.lock().expect("replay guard lock poisoned").len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_replay_guard_window() {
+		let guard = ReplayGuard::new(1_000);
+		assert!(guard.check_timestamp(10_000, 10_500).is_ok());
+		assert!(guard.check_timestamp(10_500, 10_000).is_ok());
+		assert!(guard.check_timestamp(10_000, 11_001).is_err());
+		assert!(guard.check_timestamp(12_000, 10_000).is_err());
+	}
+
+	#[test]
+	fn test_replay_guard_accepts_a_signature_once() {
+		let guard = ReplayGuard::new(1_000);
+		let signature = BlsSignature::repeat_byte(1);
+
+		guard.accept(&signature, 10_000, 10_000).unwrap();
+		assert!(guard.accept(&signature, 10_000, 10_200).is_err());
+		guard.accept(&BlsSignature::repeat_byte(2), 10_000, 10_200).unwrap();
+		assert_eq!(guard.len(), 2);
+
+		// Signatures that left the window are forgotten, and rejected by their timestamp instead
+		guard.accept(&BlsSignature::repeat_byte(3), 11_500, 11_500).unwrap();
+		assert_eq!(guard.len(), 1);
+		assert!(guard.accept(&signature, 10_000, 11_500).is_err());
+	}
+}
@@ -0,0 +1,418 @@
+use std::sync::{Arc, Mutex, atomic::Ordering};
+
+use alloy::rpc::types::beacon::BlsPublicKey;
+use axum::{
+	Json, Router,
+	extract::{Path, Query, Request, State},
+	http::{HeaderMap, StatusCode},
+	middleware::{self, Next},
+	response::{IntoResponse, Response},
+	routing::{get, post},
+};
+use common::utils::constant_time_eq;
+use constraints::types::{SignedConstraints, SignedDelegation};
+use eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::constants::MAX_AUDIT_SLOTS;
+use crate::relay::utils::{verify_constraints_signature, verify_delegation_signature, verify_signatures_batch};
+use crate::relay::{services::lookahead_manager::LookaheadManager, state::RelayState};
+use crate::storage::{InclusionDbExt, LookaheadDbExt};
+use proposer::storage::DelegationsDbExt;
+
+/// Admin API routes
+pub const ADMIN_LOOKAHEAD_REFRESH: &str = "/admin/lookahead/refresh";
+pub const ADMIN_PRUNE: &str = "/admin/prune";
+pub const ADMIN_SLOT: &str = "/admin/slots/{slot}";
+pub const ADMIN_DOWNSTREAM: &str = "/admin/downstream";
+pub const ADMIN_DRAIN: &str = "/admin/drain";
+pub const ADMIN_RESUME: &str = "/admin/resume";
+pub const ADMIN_AUDIT: &str = "/admin/audit";
+
+/// Shared state for the admin API handlers
+#[derive(Clone)]
+struct AdminState {
+	state: Arc<RelayState>,
+	lookahead_manager: Arc<LookaheadManager>,
+	api_key: String,
+	/// Serializes the read-modify-write of the drain override
+	overrides_lock: Arc<Mutex<()>>,
+}
+
+/// Query parameters for POST /admin/prune
+#[derive(Debug, Deserialize)]
+pub struct PruneQuery {
+	/// Delete all slot-keyed data strictly before this slot
+	pub before_slot: u64,
+}
+
+/// Response for POST /admin/prune
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PruneResponse {
+	pub deleted: usize,
+}
+
+/// Everything the relay knows about a slot
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlotStateResponse {
+	pub slot: u64,
+	pub proposer: Option<BlsPublicKey>,
+	pub delegations: Vec<SignedDelegation>,
+	pub constraints: Vec<SignedConstraints>,
+	pub constraints_finalized: bool,
+}
+
+/// Request body for PUT /admin/downstream
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DownstreamPinRequest {
+	/// Configured downstream relay to pin, or null to go back to latency based ordering
+	pub base_url: Option<String>,
+}
+
+/// Health of a single downstream relay
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DownstreamRelayStatus {
+	pub base_url: String,
+	pub healthy: bool,
+	pub latency_ms: Option<u64>,
+}
+
+/// Response for GET /admin/downstream
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DownstreamStatusResponse {
+	pub pinned: Option<String>,
+	pub relays: Vec<DownstreamRelayStatus>,
+}
+
+/// Query parameters for GET /admin/audit
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+	pub start_slot: u64,
+	/// Inclusive
+	pub end_slot: u64,
+}
+
+/// Response for GET /admin/audit
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignatureAuditResponse {
+	/// Number of stored delegations checked
+	pub delegations: usize,
+	/// Number of stored constraints messages checked
+	pub constraints: usize,
+	/// Messages whose signature does not verify
+	pub invalid: Vec<String>,
+}
+
+/// Build the authenticated admin router for the relay
+///
+/// Every request must carry `Authorization: Bearer <api_key>`.
+pub fn build_admin_router(state: Arc<RelayState>, api_key: String) -> Router {
+	let lookahead_manager = Arc::new(LookaheadManager::new(Arc::clone(&state)));
+	let admin_state = AdminState { state, lookahead_manager, api_key, overrides_lock: Arc::new(Mutex::new(())) };
+
+	Router::new()
+		.route(ADMIN_LOOKAHEAD_REFRESH, post(refresh_lookahead))
+		.route(ADMIN_PRUNE, post(prune))
+		.route(ADMIN_SLOT, get(get_slot_state))
+		.route(ADMIN_DOWNSTREAM, get(get_downstream).put(pin_downstream))
+		.route(ADMIN_DRAIN, post(drain))
+		.route(ADMIN_RESUME, post(resume))
+		.route(ADMIN_AUDIT, get(audit_signatures))
+		.layer(middleware::from_fn_with_state(admin_state.clone(), require_api_key))
+		.with_state(admin_state)
+}
+
+/// Reject requests without the admin bearer token
+async fn require_api_key(State(admin): State<AdminState>, headers: HeaderMap, req: Request, next: Next) -> Response {
+	let authorized = headers
+		.get("Authorization")
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.strip_prefix("Bearer "))
+		.is_some_and(|token| constant_time_eq(token.as_bytes(), admin.api_key.as_bytes()));
+
+	if !authorized {
+		warn!("Rejected unauthorized admin request to {}", req.uri().path());
+		return StatusCode::UNAUTHORIZED.into_response();
+	}
+
+	next.run(req).await
+}
+
+// POST /admin/lookahead/refresh
+async fn refresh_lookahead(State(admin): State<AdminState>) -> impl IntoResponse {
+	admin.state.lookahead_cache.invalidate();
+	match admin.lookahead_manager.process_lookahead().await {
+		Ok(()) => {
+			info!("Lookahead refreshed via admin API");
+			StatusCode::OK.into_response()
+		}
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to refresh lookahead: {e}")).into_response(),
+	}
+}
+
+// POST /admin/prune?before_slot=N
+async fn prune(State(admin): State<AdminState>, Query(query): Query<PruneQuery>) -> impl IntoResponse {
+	let prune = || -> Result<usize> {
+		let deleted = admin.state.db.prune_before_slot(query.before_slot)?
+			+ admin.state.db.prune_delegations_before_slot(query.before_slot)?;
+		Ok(deleted)
+	};
+
+	match prune() {
+		Ok(deleted) => {
+			info!("Pruned {} entries before slot {} via admin API", deleted, query.before_slot);
+			(StatusCode::OK, Json(PruneResponse { deleted })).into_response()
+		}
+		Err(e) => {
+			(StatusCode::INTERNAL_SERVER_ERROR, format!("failed to prune before slot {}: {e}", query.before_slot))
+				.into_response()
+		}
+	}
+}
+
+// GET /admin/slots/{slot}
+async fn get_slot_state(State(admin): State<AdminState>, Path(slot): Path<u64>) -> impl IntoResponse {
+	let slot_state = || -> Result<SlotStateResponse> {
+		Ok(SlotStateResponse {
+			slot,
+			proposer: admin.state.db.get_proposer_bls_key(slot)?,
+			delegations: admin.state.db.get_slot_delegations(slot)?,
+			constraints: admin.state.db.get_slot_signed_constraints(slot)?,
+			constraints_finalized: admin.state.db.signed_constraints_finalized(slot)?,
+		})
+	};
+
+	match slot_state() {
+		Ok(slot_state) => (StatusCode::OK, Json(slot_state)).into_response(),
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to inspect slot {slot}: {e}")).into_response(),
+	}
+}
+
+// GET /admin/downstream
+async fn get_downstream(State(admin): State<AdminState>) -> impl IntoResponse {
+	let downstream_relays = &admin.state.downstream_relays;
+	let relays = downstream_relays
+		.health()
+		.into_iter()
+		.map(|(base_url, health)| DownstreamRelayStatus {
+			base_url,
+			healthy: health.healthy,
+			latency_ms: health.latency.map(|latency| latency.as_millis() as u64),
+		})
+		.collect();
+
+	Json(DownstreamStatusResponse { pinned: downstream_relays.pinned(), relays })
+}
+
+// PUT /admin/downstream
+async fn pin_downstream(State(admin): State<AdminState>, Json(body): Json<DownstreamPinRequest>) -> impl IntoResponse {
+	match admin.state.downstream_relays.pin(body.base_url.as_deref()) {
+		Ok(()) => {
+			info!("Downstream relay pinned to {:?} via admin API", body.base_url);
+			StatusCode::OK.into_response()
+		}
+		Err(e) => (StatusCode::BAD_REQUEST, format!("failed to pin downstream relay: {e}")).into_response(),
+	}
+}
+
+// POST /admin/drain
+async fn drain(State(admin): State<AdminState>) -> impl IntoResponse {
+	if let Err(e) = store_drained(&admin, true) {
+		return (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to drain: {e}")).into_response();
+	}
+	admin.state.accepting_constraints.store(false, Ordering::SeqCst);
+	info!("Relay drained via admin API, no longer accepting new constraints");
+	StatusCode::OK.into_response()
+}
+
+// POST /admin/resume
+async fn resume(State(admin): State<AdminState>) -> impl IntoResponse {
+	if let Err(e) = store_drained(&admin, false) {
+		return (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to resume: {e}")).into_response();
+	}
+	admin.state.accepting_constraints.store(true, Ordering::SeqCst);
+	info!("Relay resumed via admin API, accepting new constraints");
+	StatusCode::OK.into_response()
+}
+
+/// Persist a drain or resume, so a restarted relay applies it again
+fn store_drained(admin: &AdminState, drained: bool) -> Result<()> {
+	let _guard = admin.overrides_lock.lock().expect("overrides lock poisoned");
+	let mut overrides = admin.state.db.get_admin_overrides()?;
+	overrides.paused = drained;
+	admin.state.db.store_admin_overrides(&overrides)
+}
+
+// GET /admin/audit?start_slot=N&end_slot=M
+async fn audit_signatures(State(admin): State<AdminState>, Query(query): Query<AuditQuery>) -> impl IntoResponse {
+	if query.end_slot < query.start_slot || query.end_slot - query.start_slot >= MAX_AUDIT_SLOTS {
+		return (
+			StatusCode::BAD_REQUEST,
+			format!("slot range must be ordered and span at most {MAX_AUDIT_SLOTS} slots"),
+		)
+			.into_response();
+	}
+
+	// Reading and verifying a range of slots is synchronous work, keep it off the async workers
+	let state = admin.state.clone();
+	let audit = tokio::task::spawn_blocking(move || audit_slot_range(&state, query.start_slot, query.end_slot));
+
+	match audit.await.map_err(|e| eyre!("audit task failed: {e}")).and_then(|result| result) {
+		Ok(response) => {
+			if !response.invalid.is_empty() {
+				warn!(
+					"Signature audit of slots {}..={} found {} invalid messages",
+					query.start_slot,
+					query.end_slot,
+					response.invalid.len()
+				);
+			}
+			(StatusCode::OK, Json(response)).into_response()
+		}
+		Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("failed to audit signatures: {e}")).into_response(),
+	}
+}
+
+/// Re-verify the signatures of the delegations and constraints stored for slots in [start_slot, end_slot]
+fn audit_slot_range(state: &RelayState, start_slot: u64, end_slot: u64) -> Result<SignatureAuditResponse> {
+	let mut delegations = Vec::new();
+	let mut constraints = Vec::new();
+	for slot in start_slot..=end_slot {
+		delegations.extend(state.db.get_slot_delegations(slot)?);
+		constraints.extend(state.db.get_slot_signed_constraints(slot)?);
+	}
+
+	// One batch verification for the whole range, each message is only checked on its own if it fails
+	let mut invalid = Vec::new();
+	if verify_signatures_batch(&delegations, &constraints, &state.chain).is_err() {
+		for delegation in &delegations {
+			if let Err(e) = verify_delegation_signature(delegation, &state.chain) {
+				invalid.push(format!(
+					"delegation of slot {} from {}: {e}",
+					delegation.message.slot, delegation.message.proposer
+				));
+			}
+		}
+		for signed_constraints in &constraints {
+			if let Err(e) = verify_constraints_signature(signed_constraints, &state.chain) {
+				invalid.push(format!(
+					"constraints of slot {} from {}: {e}",
+					signed_constraints.message.slot, signed_constraints.message.delegate
+				));
+			}
+		}
+	}
+
+	Ok(SignatureAuditResponse { delegations: delegations.len(), constraints: constraints.len(), invalid })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::relay::config::RelayConfig;
+	use common::storage::DatabaseContext;
+
+	const API_KEY: &str = "admin-secret";
+
+	fn new_state(db: DatabaseContext) -> Arc<RelayState> {
+		let config = toml::from_str::<RelayConfig>(
+			r#"
+			chain = "Holesky"
+			host = "127.0.0.1"
+			port = 9998
+			db_path = "unused"
+			constraint_capabilities = [1]
+			beacon_api_host = "127.0.0.1"
+			beacon_api_port = 5052
+			lookahead_update_interval = 1
+			downstream_relay_host = "127.0.0.1"
+			downstream_relay_port = 18550
+			receipt_signing_key = "0x0101010101010101010101010101010101010101010101010101010101010101"
+			"#,
+		)
+		.unwrap();
+		Arc::new(RelayState::new(db, config).unwrap())
+	}
+
+	fn open_db(dir: &tempfile::TempDir) -> DatabaseContext {
+		let mut opts = rocksdb::Options::default();
+		opts.create_if_missing(true);
+		DatabaseContext::new(Arc::new(rocksdb::DB::open(&opts, dir.path()).unwrap()))
+	}
+
+	async fn serve_admin(state: Arc<RelayState>) -> String {
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		let router = build_admin_router(state, API_KEY.to_string());
+		tokio::spawn(async move { axum::serve(listener, router).await });
+		format!("http://{addr}")
+	}
+
+	#[tokio::test]
+	async fn test_admin_requires_the_api_key() {
+		let dir = tempfile::TempDir::new().unwrap();
+		let url = serve_admin(new_state(open_db(&dir))).await;
+		let client = reqwest::Client::new();
+		let slot_url = format!("{url}/admin/slots/1");
+
+		let response = client.get(&slot_url).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+		for token in ["admin-secreT", "admin", "admin-secret2"] {
+			let response = client.get(&slot_url).bearer_auth(token).send().await.unwrap();
+			assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "token {token}");
+		}
+		let response = client.get(&slot_url).header("Authorization", API_KEY).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+		let response = client.get(&slot_url).bearer_auth(API_KEY).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		let slot_state: SlotStateResponse = response.json().await.unwrap();
+		assert_eq!(slot_state.slot, 1);
+		assert!(slot_state.delegations.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_admin_drain_outlives_a_restart() {
+		let dir = tempfile::TempDir::new().unwrap();
+		let state = new_state(open_db(&dir));
+		let url = serve_admin(Arc::clone(&state)).await;
+		let client = reqwest::Client::new();
+
+		let response = client.post(format!("{url}{ADMIN_DRAIN}")).bearer_auth(API_KEY).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		assert!(!state.accepting_constraints.load(Ordering::SeqCst));
+
+		// A relay started on the same database is still drained
+		let restarted = new_state(state.db.clone());
+		assert!(!restarted.accepting_constraints.load(Ordering::SeqCst));
+
+		let response = client.post(format!("{url}{ADMIN_RESUME}")).bearer_auth(API_KEY).send().await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		assert!(state.accepting_constraints.load(Ordering::SeqCst));
+		let restarted = new_state(state.db.clone());
+		assert!(restarted.accepting_constraints.load(Ordering::SeqCst));
+	}
+
+	#[tokio::test]
+	async fn test_admin_audit_bounds_the_slot_range() {
+		let dir = tempfile::TempDir::new().unwrap();
+		let url = serve_admin(new_state(open_db(&dir))).await;
+		let client = reqwest::Client::new();
+		let audit = |start_slot: u64, end_slot: u64| {
+			client
+				.get(format!("{url}{ADMIN_AUDIT}?start_slot={start_slot}&end_slot={end_slot}"))
+				.bearer_auth(API_KEY)
+				.send()
+		};
+
+		let response = audit(10, 10 + MAX_AUDIT_SLOTS - 1).await.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		let report: SignatureAuditResponse = response.json().await.unwrap();
+		assert_eq!((report.delegations, report.constraints), (0, 0));
+		assert!(report.invalid.is_empty());
+
+		assert_eq!(audit(10, 10 + MAX_AUDIT_SLOTS).await.unwrap().status(), StatusCode::BAD_REQUEST);
+		assert_eq!(audit(10, 9).await.unwrap().status(), StatusCode::BAD_REQUEST);
+	}
+}
@@ -0,0 +1,40 @@
+use eyre::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{error, info};
+
+use crate::relay::state::RelayState;
+
+/// Downstream health manager that periodically probes the downstream relays
+/// so block submissions and proxied requests go to the fastest healthy relay
+pub struct DownstreamHealthManager {
+	state: Arc<RelayState>,
+}
+
+impl DownstreamHealthManager {
+	/// Create a new downstream health manager
+	pub fn new(state: Arc<RelayState>) -> Self {
+		Self { state }
+	}
+
+	/// Run the health check task continuously
+	pub async fn run(&self) -> Result<()> {
+		info!(
+			"Starting downstream health manager for {} relay(s) with {}s check interval",
+			self.state.downstream_relays.base_urls().len(),
+			self.state.downstream_health_check_interval
+		);
+
+		loop {
+			self.state.downstream_relays.check_health().await;
+
+			let healthy = self.state.downstream_relays.health().iter().filter(|(_, health)| health.healthy).count();
+			if healthy == 0 {
+				error!("All downstream relays are unhealthy");
+			}
+
+			sleep(Duration::from_secs(self.state.downstream_health_check_interval)).await;
+		}
+	}
+}
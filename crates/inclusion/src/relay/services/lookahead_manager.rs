@@ -1,14 +1,26 @@
+use alloy::primitives::B256;
 use alloy::rpc::types::beacon::BlsPublicKey;
-use eyre::Result;
+use eyre::{Result, WrapErr};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 
-use crate::storage::LookaheadDbExt;
-use lookahead::utils::{current_slot, epoch_to_first_slot, epoch_to_last_slot, slot_to_epoch};
+use crate::storage::{InclusionDbExt, LookaheadDbExt};
+use crate::types::{EpochLookahead, SlotProposer};
+use common::storage::DatabaseContext;
+use lookahead::events::{BeaconEvent, EventSubscription, EventTopic};
+use lookahead::types::{ValidatorDuty, ValidatorId, ValidatorStatus};
 
-use crate::relay::state::RelayState;
+use crate::relay::{
+	metrics::{
+		CURRENT_EPOCH, NEXT_EPOCH, RELAY_DELEGATIONS_PER_EPOCH, RELAY_INELIGIBLE_PROPOSERS_TOTAL,
+		RELAY_LOOKAHEAD_REORGS_TOTAL,
+	},
+	state::RelayState,
+};
+use proposer::storage::DelegationsDbExt;
 
 /// Delegation manager that monitors lookahead duties and signs delegations
 pub struct LookaheadManager {
@@ -22,22 +34,44 @@ impl LookaheadManager {
 	}
 
 	/// Run the proposer lookahead task continuously
+	/// The lookahead is refreshed at every epoch transition and reorg reported by the beacon node, and at
+	/// least every update interval
 	pub async fn run(&self) -> Result<()> {
 		info!("Starting lookahead manager with {}s update interval", self.state.lookahead_update_interval);
+		let mut events =
+			EventSubscription::new(self.state.beacon_client.clone(), vec![EventTopic::Head, EventTopic::ChainReorg]);
 
 		loop {
 			if let Err(e) = self.process_lookahead().await {
 				error!("Error updating proposer lookahead: {}", e);
 			}
 
-			sleep(Duration::from_secs(self.state.lookahead_update_interval)).await;
+			let update_interval = sleep(Duration::from_secs(self.state.lookahead_update_interval));
+			tokio::pin!(update_interval);
+			loop {
+				tokio::select! {
+					_ = &mut update_interval => break,
+					event = events.next() => match event {
+						BeaconEvent::Head(head) if head.epoch_transition => {
+							debug!("Epoch transition at slot {}, refreshing lookahead", head.slot);
+							break;
+						}
+						BeaconEvent::ChainReorg(reorg) => {
+							info!("Reorg of depth {} at slot {}, refreshing lookahead", reorg.depth, reorg.slot);
+							self.state.lookahead_cache.invalidate();
+							break;
+						}
+						_ => {}
+					},
+				}
+			}
 		}
 	}
 
 	/// Update the proposer lookahead for upcoming slots
-	async fn process_lookahead(&self) -> Result<()> {
+	pub async fn process_lookahead(&self) -> Result<()> {
 		// Calculate current epoch
-		let current_epoch = slot_to_epoch(current_slot(&self.state.chain));
+		let current_epoch = self.state.slot_clock.slot_to_epoch(self.state.slot_clock.current_slot());
 
 		// Populate each epoch in the range
 		for epoch in current_epoch..=current_epoch + 1 {
@@ -46,37 +80,343 @@ impl LookaheadManager {
 
 		info!("Lookahead updated for epochs {} to {}", current_epoch, current_epoch + 1);
 
+		// Report how many delegations were received for the epochs in the lookahead window
+		RELAY_DELEGATIONS_PER_EPOCH.with_label_values(&[CURRENT_EPOCH]).set(self.count_delegations(current_epoch)?);
+		RELAY_DELEGATIONS_PER_EPOCH.with_label_values(&[NEXT_EPOCH]).set(self.count_delegations(current_epoch + 1)?);
+
 		Ok(())
 	}
 
+	/// Count the delegations stored for every slot of an epoch
+	fn count_delegations(&self, epoch: u64) -> Result<i64> {
+		let mut count = 0;
+		for slot in self.state.slot_clock.epoch_to_first_slot(epoch)..=self.state.slot_clock.epoch_to_last_slot(epoch) {
+			count += self.state.db.get_slot_delegations(slot)?.len() as i64;
+		}
+		Ok(count)
+	}
+
 	/// Populate the proposer lookahead for a specific epoch
 	/// This is a public method that can be called from tests or for manual population
 	/// If proposer_key is provided, all slots in the epoch will use that key (useful for testing)
-	/// Otherwise, fetch proposer duties from the beacon node
+	/// Otherwise, fetch proposer duties from the beacon node. The stored set is only replaced when the duties'
+	/// dependent root differs from the one it was stored at, the proposer statuses are refreshed either way
 	pub async fn populate_lookahead(&self, epoch: u64, proposer_key: Option<BlsPublicKey>) -> Result<()> {
 		// Calculate the slot range for this epoch
-		let start_slot = epoch_to_first_slot(epoch);
-		let end_slot = epoch_to_last_slot(epoch);
+		let start_slot = self.state.slot_clock.epoch_to_first_slot(epoch);
+		let end_slot = self.state.slot_clock.epoch_to_last_slot(epoch);
+		let previous = self.state.db.get_lookahead_for_epoch(epoch)?;
 
-		match proposer_key {
+		let (dependent_root, mut proposers) = match proposer_key {
 			Some(key) => {
 				// If a test proposer key is provided, use it for all slots in the epoch
-				for slot in start_slot..=end_slot {
-					self.state.db.store_proposer_bls_key(slot, &key)?;
-				}
+				(B256::ZERO, (start_slot..=end_slot).map(|slot| SlotProposer { slot, pubkey: key }).collect::<Vec<_>>())
 			}
 			None => {
-				// Otherwise, fetch proposer duties from the beacon node
-				let duties = self.state.beacon_client.get_proposer_duties(epoch).await?;
+				// Otherwise, take the proposer duties from the cache prefetching them from the beacon node
+				let duties = self.state.lookahead_cache.get_proposer_duties(epoch).await?;
+
+				// A new dependent root means the duties were recomputed, e.g. after a reorg
+				let unchanged = match &previous {
+					Some(previous) if previous.dependent_root == duties.dependent_root => true,
+					Some(previous) => {
+						warn!(
+							"Dependent root for epoch {} changed from {} to {}, re-validating delegations",
+							epoch, previous.dependent_root, duties.dependent_root
+						);
+						RELAY_LOOKAHEAD_REORGS_TOTAL.inc();
+						false
+					}
+					None => false,
+				};
+
+				// Without the statuses the lookahead is left as it is, keeping the slots flagged so far
+				let statuses = self.proposer_statuses(&duties.data).await?;
 
+				let mut proposers = Vec::with_capacity(duties.data.len());
 				for duty in duties.data {
 					let slot = duty.parse_slot()?;
 					let pubkey = duty.parse_pubkey()?;
-					self.state.db.store_proposer_bls_key(slot, &pubkey)?;
+
+					// Flag slots whose proposer cannot produce a valid block, their delegations are refused
+					match statuses.get(&pubkey).filter(|status| status.is_slashed() || status.is_exited()) {
+						Some(status) => {
+							warn!("Proposer {} of slot {} is {:?}, flagging the slot", pubkey, slot, status);
+							RELAY_INELIGIBLE_PROPOSERS_TOTAL.inc();
+							self.state.db.store_proposer_status(slot, status)?;
+						}
+						None => self.state.db.delete_proposer_status(slot)?,
+					}
+
+					proposers.push(SlotProposer { slot, pubkey });
+				}
+
+				// A proposer can be slashed or exit while the duties stay the same, only its status is refreshed
+				if unchanged {
+					debug!("Lookahead for epoch {} unchanged at dependent root {}", epoch, duties.dependent_root);
+					return Ok(());
 				}
+				(duties.dependent_root, proposers)
+			}
+		};
+
+		// Drop delegations signed by a proposer that is no longer scheduled for the slot
+		for proposer in &proposers {
+			let previous_proposer = previous.as_ref().and_then(|previous| previous.proposer(proposer.slot));
+			if previous_proposer.is_some_and(|previous| *previous != proposer.pubkey) {
+				let invalidated = invalidate_delegations(&self.state.db, proposer.slot, &proposer.pubkey)?;
+				warn!("Proposer changed for slot {}, invalidated {} delegation(s)", proposer.slot, invalidated);
 			}
 		}
 
+		proposers.sort_by_key(|proposer| proposer.slot);
+		let fetched_at = self.state.slot_clock.now_ms() / 1000;
+		self.state.db.store_epoch_lookahead(&EpochLookahead { epoch, dependent_root, fetched_at, proposers })?;
+		Ok(())
+	}
+
+	/// Status of the proposers of the duties, fetched in one request
+	async fn proposer_statuses(&self, duties: &[ValidatorDuty]) -> Result<HashMap<BlsPublicKey, ValidatorStatus>> {
+		let ids = duties
+			.iter()
+			.filter_map(|duty| duty.parse_validator_index().ok())
+			.map(ValidatorId::Index)
+			.collect::<Vec<_>>();
+
+		let validators = self
+			.state
+			.beacon_client
+			.get_validators(&ids)
+			.await
+			.wrap_err("Failed to fetch proposer validator statuses")?;
+		Ok(validators
+			.data
+			.into_iter()
+			.filter_map(|validator| {
+				let status = if validator.is_slashed() && !validator.status.is_slashed() {
+					ValidatorStatus::ActiveSlashed
+				} else {
+					validator.status
+				};
+				validator.parse_pubkey().ok().map(|pubkey| (pubkey, status))
+			})
+			.collect())
+	}
+}
+
+/// Remove the delegations for a slot that were not signed by its scheduled proposer,
+/// together with any constraints their delegates posted. Returns the number of delegations removed.
+pub fn invalidate_delegations(db: &DatabaseContext, slot: u64, proposer: &BlsPublicKey) -> Result<usize> {
+	let mut invalidated = 0;
+	for delegation in db.get_slot_delegations(slot)? {
+		if delegation.message.proposer == *proposer {
+			continue;
+		}
+
+		db.delete_delegate_delegation(slot, &delegation.message.delegate)?;
+		db.delete_delegate_signed_constraints(slot, &delegation.message.delegate)?;
+		invalidated += 1;
+	}
+	Ok(invalidated)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::relay::config::RelayConfig;
+	use alloy::primitives::{Address, Bytes};
+	use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::get};
+	use constraints::types::{ConstraintsMessage, Delegation, SignedConstraints, SignedDelegation};
+	use rocksdb::Options;
+	use std::sync::Mutex;
+	use tempfile::TempDir;
+
+	fn new_temp_db() -> Result<DatabaseContext> {
+		let tmp_dir = TempDir::new()?;
+		let mut opts = Options::default();
+		opts.create_if_missing(true);
+		let db = rocksdb::DB::open(&opts, tmp_dir.path())?;
+		Ok(DatabaseContext::new(Arc::new(db)))
+	}
+
+	fn signed_delegation(proposer: BlsPublicKey, delegate: BlsPublicKey, slot: u64) -> SignedDelegation {
+		SignedDelegation {
+			message: Delegation { proposer, delegate, committer: Address::ZERO, slot, metadata: Bytes::new() },
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: Default::default(),
+		}
+	}
+
+	fn signed_constraints(proposer: BlsPublicKey, delegate: BlsPublicKey, slot: u64) -> SignedConstraints {
+		SignedConstraints {
+			message: ConstraintsMessage { proposer, delegate, slot, constraints: vec![], receivers: vec![] },
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: Default::default(),
+		}
+	}
+
+	#[test]
+	fn test_invalidate_delegations_drops_other_proposers() -> Result<()> {
+		let db = new_temp_db()?;
+		let old_proposer = BlsPublicKey::from([0xAAu8; 48]);
+		let new_proposer = BlsPublicKey::from([0xBBu8; 48]);
+		let stale_delegate = BlsPublicKey::from([0x01u8; 48]);
+		let valid_delegate = BlsPublicKey::from([0x02u8; 48]);
+
+		db.store_delegate_delegation(&signed_delegation(old_proposer, stale_delegate, 10))?;
+		db.store_delegate_signed_constraints(&signed_constraints(old_proposer, stale_delegate, 10))?;
+		db.store_delegate_delegation(&signed_delegation(new_proposer, valid_delegate, 10))?;
+
+		assert_eq!(invalidate_delegations(&db, 10, &new_proposer)?, 1);
+
+		let delegations = db.get_slot_delegations(10)?;
+		assert_eq!(delegations.len(), 1);
+		assert_eq!(delegations[0].message.delegate, valid_delegate);
+		assert!(db.get_slot_signed_constraints(10)?.is_empty());
+
+		Ok(())
+	}
+
+	/// Beacon node serving the duties of one proposer for every slot, and its status unless `validators_down`
+	struct MockBeacon {
+		proposer: BlsPublicKey,
+		dependent_root: B256,
+		status: ValidatorStatus,
+		validators_down: bool,
+	}
+
+	async fn serve_mock_beacon(beacon: Arc<Mutex<MockBeacon>>) -> u16 {
+		async fn duties(
+			State(beacon): State<Arc<Mutex<MockBeacon>>>,
+			axum::extract::Path(epoch): axum::extract::Path<u64>,
+		) -> impl IntoResponse {
+			let beacon = beacon.lock().unwrap();
+			let data = (epoch * 32..(epoch + 1) * 32)
+				.map(|slot| {
+					serde_json::json!({
+						"validator_index": "7",
+						"pubkey": beacon.proposer.to_string(),
+						"slot": slot.to_string(),
+					})
+				})
+				.collect::<Vec<_>>();
+			Json(serde_json::json!({
+				"dependent_root": beacon.dependent_root,
+				"execution_optimistic": false,
+				"data": data,
+			}))
+		}
+
+		async fn validators(State(beacon): State<Arc<Mutex<MockBeacon>>>) -> impl IntoResponse {
+			let beacon = beacon.lock().unwrap();
+			if beacon.validators_down {
+				return StatusCode::SERVICE_UNAVAILABLE.into_response();
+			}
+			Json(serde_json::json!({
+				"data": [{
+					"index": "7",
+					"status": beacon.status,
+					"validator": { "pubkey": beacon.proposer.to_string(), "slashed": beacon.status.is_slashed() },
+				}],
+			}))
+			.into_response()
+		}
+
+		let app = Router::new()
+			.route("/eth/v1/validator/duties/proposer/{epoch}", get(duties))
+			.route("/eth/v1/beacon/states/head/validators", get(validators))
+			.with_state(beacon);
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let port = listener.local_addr().unwrap().port();
+		tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+		port
+	}
+
+	fn new_lookahead_manager(beacon_port: u16) -> LookaheadManager {
+		let mut config = toml::from_str::<RelayConfig>(
+			r#"
+			chain = "Holesky"
+			host = "127.0.0.1"
+			port = 9998
+			db_path = "unused"
+			constraint_capabilities = [1]
+			beacon_api_host = "127.0.0.1"
+			beacon_api_port = 5052
+			lookahead_update_interval = 1
+			downstream_relay_host = "127.0.0.1"
+			downstream_relay_port = 18550
+			receipt_signing_key = "0x0101010101010101010101010101010101010101010101010101010101010101"
+			"#,
+		)
+		.unwrap();
+		config.beacon_api_port = beacon_port;
+		LookaheadManager::new(Arc::new(RelayState::new(new_temp_db().unwrap(), config).unwrap()))
+	}
+
+	#[tokio::test]
+	async fn test_populate_lookahead_keeps_statuses_without_beacon() -> Result<()> {
+		let proposer = BlsPublicKey::from([0xAAu8; 48]);
+		let beacon = Arc::new(Mutex::new(MockBeacon {
+			proposer,
+			dependent_root: B256::repeat_byte(1),
+			status: ValidatorStatus::ActiveSlashed,
+			validators_down: false,
+		}));
+		let manager = new_lookahead_manager(serve_mock_beacon(beacon.clone()).await);
+		let db = &manager.state.db;
+		let epoch = manager.state.slot_clock.slot_to_epoch(manager.state.slot_clock.current_slot());
+		let slot = manager.state.slot_clock.epoch_to_first_slot(epoch);
+
+		// A slashed proposer's slots are flagged
+		manager.populate_lookahead(epoch, None).await?;
+		assert_eq!(db.get_proposer_status(slot)?, Some(ValidatorStatus::ActiveSlashed));
+
+		// The duties changed but the statuses can not be fetched, the last known ones and the lookahead are kept
+		{
+			let mut beacon = beacon.lock().unwrap();
+			beacon.dependent_root = B256::repeat_byte(2);
+			beacon.status = ValidatorStatus::ActiveOngoing;
+			beacon.validators_down = true;
+		}
+		manager.state.lookahead_cache.invalidate();
+		assert!(manager.populate_lookahead(epoch, None).await.is_err());
+		assert_eq!(db.get_proposer_status(slot)?, Some(ValidatorStatus::ActiveSlashed));
+		assert_eq!(db.get_lookahead_for_epoch(epoch)?.unwrap().dependent_root, B256::repeat_byte(1));
+
+		// Once they can, the lookahead is replaced and the proposer is no longer flagged
+		beacon.lock().unwrap().validators_down = false;
+		manager.populate_lookahead(epoch, None).await?;
+		assert_eq!(db.get_proposer_status(slot)?, None);
+		assert_eq!(db.get_lookahead_for_epoch(epoch)?.unwrap().dependent_root, B256::repeat_byte(2));
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_populate_lookahead_refreshes_statuses_of_unchanged_duties() -> Result<()> {
+		let proposer = BlsPublicKey::from([0xAAu8; 48]);
+		let beacon = Arc::new(Mutex::new(MockBeacon {
+			proposer,
+			dependent_root: B256::repeat_byte(1),
+			status: ValidatorStatus::ActiveOngoing,
+			validators_down: false,
+		}));
+		let manager = new_lookahead_manager(serve_mock_beacon(beacon.clone()).await);
+		let db = &manager.state.db;
+		let epoch = manager.state.slot_clock.slot_to_epoch(manager.state.slot_clock.current_slot());
+		let slot = manager.state.slot_clock.epoch_to_first_slot(epoch);
+
+		manager.populate_lookahead(epoch, None).await?;
+		assert_eq!(db.get_proposer_status(slot)?, None);
+
+		// The proposer is slashed without the duties changing
+		beacon.lock().unwrap().status = ValidatorStatus::ActiveSlashed;
+		manager.populate_lookahead(epoch, None).await?;
+		assert_eq!(db.get_proposer_status(slot)?, Some(ValidatorStatus::ActiveSlashed));
+		assert_eq!(db.get_lookahead_for_epoch(epoch)?.unwrap().dependent_root, B256::repeat_byte(1));
+
 		Ok(())
 	}
 }
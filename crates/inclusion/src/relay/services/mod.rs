@@ -1,3 +1,6 @@
+pub mod admin;
+pub mod downstream_health;
 pub mod lookahead_manager;
 pub mod proxy;
 pub mod server;
+pub mod urc_sync;
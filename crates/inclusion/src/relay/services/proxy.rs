@@ -1,10 +1,17 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
 use alloy::rpc::types::beacon::relay::SubmitBlockRequest as AlloySubmitBlockRequest;
 use axum::http::HeaderMap;
+use common::http::HttpClientConfig;
 use eyre::{Result, eyre};
-use reqwest::Client;
+use reqwest::{Client, Response};
+
+use constraints::routes::{LEGACY_STATUS, LEGACY_SUBMIT_BLOCK};
+use tracing::{debug, info, warn};
 
-use constraints::routes::LEGACY_SUBMIT_BLOCK;
-use tracing::info;
+/// Timeout for a single downstream relay status probe
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
 
 #[derive(Clone)]
 pub struct LegacyRelayClient {
@@ -14,12 +21,32 @@ pub struct LegacyRelayClient {
 
 impl LegacyRelayClient {
 	pub fn new(base_url: String) -> Result<Self> {
-		let client = Client::builder().timeout(std::time::Duration::from_secs(30)).build()?;
+		Self::with_http_config(base_url, &HttpClientConfig::default())
+	}
+
+	/// Client with the given timeout and connection pool settings
+	pub fn with_http_config(base_url: String, http: &HttpClientConfig) -> Result<Self> {
+		let client = http.client()?;
 		let base_url = base_url.trim_end_matches('/').to_string();
 		Ok(Self { client, base_url })
 	}
 
 	pub async fn submit_block(&self, block: AlloySubmitBlockRequest, headers: HeaderMap) -> Result<()> {
+		let response = self.send_block(&block, &headers).await?;
+
+		if response.status().is_success() {
+			// Drain the body to encourage clean connection reuse under load, even if you ignore it.
+			let _ = response.bytes().await;
+			Ok(())
+		} else {
+			let status = response.status();
+			let body = response.text().await.unwrap_or_else(|_| "Failed to read response body".to_string());
+			Err(eyre!("Failed to submit block to downstream relay: status={}, body={}", status, body))
+		}
+	}
+
+	/// Send a block to the downstream relay and return the raw response
+	async fn send_block(&self, block: &AlloySubmitBlockRequest, headers: &HeaderMap) -> Result<Response> {
 		let url = format!("{}/{}", self.base_url.trim_end_matches('/'), LEGACY_SUBMIT_BLOCK.trim_start_matches('/'));
 
 		info!("Submitting block to downstream relay: {}", url);
@@ -51,16 +78,268 @@ impl LegacyRelayClient {
 		// Do NOT forward Content-Length; reqwest computes it for the outbound body.
 		//
 		// Send block request to downstream relay
-		let response = req.json(&block).send().await?;
+		Ok(req.json(block).send().await?)
+	}
+
+	/// Probe the downstream relay status endpoint, returning the round trip latency
+	pub async fn check_status(&self) -> Result<Duration> {
+		let url = format!("{}/{}", self.base_url.trim_end_matches('/'), LEGACY_STATUS.trim_start_matches('/'));
+
+		let start = Instant::now();
+		let response = self.client.get(&url).timeout(HEALTH_CHECK_TIMEOUT).send().await?;
+		let latency = start.elapsed();
+
+		if !response.status().is_success() {
+			return Err(eyre!("Downstream relay {} status check failed: status={}", self.base_url, response.status()));
+		}
+
+		Ok(latency)
+	}
+}
+
+/// Health of a downstream relay as last observed by the health checker
+#[derive(Debug, Clone, Copy)]
+pub struct DownstreamRelayHealth {
+	pub healthy: bool,
+	pub latency: Option<Duration>,
+}
+
+impl Default for DownstreamRelayHealth {
+	// Relays are assumed healthy until the first health check says otherwise
+	fn default() -> Self {
+		Self { healthy: true, latency: None }
+	}
+}
+
+/// Set of downstream relays ordered by health and latency
+///
+/// Healthy relays are preferred in order of increasing latency. Unhealthy relays are kept
+/// at the back of the list and are still tried as a last resort.
+#[derive(Clone)]
+pub struct DownstreamRelays {
+	relays: Vec<LegacyRelayClient>,
+	health: Arc<RwLock<Vec<DownstreamRelayHealth>>>,
+	/// Relay pinned by an operator, always tried first regardless of health
+	pinned: Arc<RwLock<Option<usize>>>,
+}
+
+impl DownstreamRelays {
+	pub fn new(base_urls: Vec<String>) -> Result<Self> {
+		Self::with_http_config(base_urls, &HttpClientConfig::default())
+	}
+
+	/// Clients of the downstream relays sharing the given timeout and connection pool settings
+	pub fn with_http_config(base_urls: Vec<String>, http: &HttpClientConfig) -> Result<Self> {
+		if base_urls.is_empty() {
+			return Err(eyre!("At least one downstream relay must be configured"));
+		}
+
+		let relays = base_urls
+			.into_iter()
+			.map(|base_url| LegacyRelayClient::with_http_config(base_url, http))
+			.collect::<Result<Vec<_>>>()?;
+		let health = Arc::new(RwLock::new(vec![DownstreamRelayHealth::default(); relays.len()]));
+		Ok(Self { relays, health, pinned: Arc::new(RwLock::new(None)) })
+	}
+
+	/// Downstream relays in order of preference
+	pub fn ordered(&self) -> Vec<&LegacyRelayClient> {
+		let health = self.health.read().expect("downstream relay health lock poisoned");
+
+		let pinned = *self.pinned.read().expect("downstream relay pin lock poisoned");
+
+		let mut indices = (0..self.relays.len()).collect::<Vec<_>>();
+		// Pinned first, then healthy before unhealthy, then lowest latency, unknown latency last, then configuration order
+		indices
+			.sort_by_key(|&i| (pinned != Some(i), !health[i].healthy, health[i].latency.unwrap_or(Duration::MAX), i));
+
+		indices.into_iter().map(|i| &self.relays[i]).collect()
+	}
+
+	/// The preferred downstream relay
+	pub fn primary(&self) -> &LegacyRelayClient {
+		self.ordered()[0]
+	}
+
+	/// Base URLs of every configured downstream relay
+	pub fn base_urls(&self) -> Vec<String> {
+		self.relays.iter().map(|relay| relay.base_url.clone()).collect()
+	}
+
+	/// Current health of every configured downstream relay, in configuration order
+	pub fn health(&self) -> Vec<(String, DownstreamRelayHealth)> {
+		let health = self.health.read().expect("downstream relay health lock poisoned");
+		self.relays.iter().zip(health.iter()).map(|(relay, health)| (relay.base_url.clone(), *health)).collect()
+	}
+
+	/// Pin a configured downstream relay so it is always tried first, or unpin with None
+	pub fn pin(&self, base_url: Option<&str>) -> Result<()> {
+		let index = match base_url {
+			Some(base_url) => {
+				let base_url = base_url.trim_end_matches('/');
+				let index = self
+					.relays
+					.iter()
+					.position(|relay| relay.base_url == base_url)
+					.ok_or_else(|| eyre!("Downstream relay {} is not configured", base_url))?;
+				Some(index)
+			}
+			None => None,
+		};
+
+		*self.pinned.write().expect("downstream relay pin lock poisoned") = index;
+		Ok(())
+	}
+
+	/// Base URL of the pinned downstream relay, if any
+	pub fn pinned(&self) -> Option<String> {
+		let pinned = *self.pinned.read().expect("downstream relay pin lock poisoned");
+		pinned.map(|i| self.relays[i].base_url.clone())
+	}
+
+	/// Submit a block, failing over to the next relay on transport errors or 5xx responses
+	pub async fn submit_block(&self, block: AlloySubmitBlockRequest, headers: HeaderMap) -> Result<()> {
+		let mut last_error = None;
+
+		for relay in self.ordered() {
+			let response = match relay.send_block(&block, &headers).await {
+				Ok(response) => response,
+				Err(e) => {
+					warn!("Downstream relay {} unreachable, failing over: {}", relay.base_url, e);
+					self.mark_unhealthy(&relay.base_url);
+					last_error = Some(e);
+					continue;
+				}
+			};
 
-		if response.status().is_success() {
-			// Drain the body to encourage clean connection reuse under load, even if you ignore it.
-			let _ = response.bytes().await;
-			Ok(())
-		} else {
 			let status = response.status();
+			if status.is_success() {
+				// Drain the body to encourage clean connection reuse under load, even if you ignore it.
+				let _ = response.bytes().await;
+				return Ok(());
+			}
+
 			let body = response.text().await.unwrap_or_else(|_| "Failed to read response body".to_string());
-			Err(eyre!("Failed to submit block to downstream relay: status={}, body={}", status, body))
+			let error = eyre!(
+				"Failed to submit block to downstream relay {}: status={}, body={}",
+				relay.base_url,
+				status,
+				body
+			);
+
+			// Client errors are about the block itself, another relay would reject it too
+			if !status.is_server_error() {
+				return Err(error);
+			}
+
+			warn!("{}, failing over", error);
+			self.mark_unhealthy(&relay.base_url);
+			last_error = Some(error);
 		}
+
+		Err(last_error.unwrap_or_else(|| eyre!("No downstream relays configured")))
+	}
+
+	/// Probe every downstream relay and update their health
+	pub async fn check_health(&self) {
+		let mut results = Vec::with_capacity(self.relays.len());
+		for relay in &self.relays {
+			results.push(relay.check_status().await);
+		}
+
+		let mut health = self.health.write().expect("downstream relay health lock poisoned");
+		for ((relay, result), health) in self.relays.iter().zip(results).zip(health.iter_mut()) {
+			match result {
+				Ok(latency) => {
+					debug!("Downstream relay {} healthy, latency={}ms", relay.base_url, latency.as_millis());
+					*health = DownstreamRelayHealth { healthy: true, latency: Some(latency) };
+				}
+				Err(e) => {
+					warn!("Downstream relay {} unhealthy: {}", relay.base_url, e);
+					*health = DownstreamRelayHealth { healthy: false, latency: None };
+				}
+			}
+		}
+	}
+
+	fn mark_unhealthy(&self, base_url: &str) {
+		let mut health = self.health.write().expect("downstream relay health lock poisoned");
+		if let Some(index) = self.relays.iter().position(|relay| relay.base_url == base_url) {
+			health[index] = DownstreamRelayHealth { healthy: false, latency: None };
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn relays() -> DownstreamRelays {
+		DownstreamRelays::new(vec![
+			"http://relay-a:1000".to_string(),
+			"http://relay-b:2000/".to_string(),
+			"http://relay-c:3000".to_string(),
+		])
+		.unwrap()
+	}
+
+	fn set_health(relays: &DownstreamRelays, index: usize, healthy: bool, latency_ms: Option<u64>) {
+		relays.health.write().unwrap()[index] =
+			DownstreamRelayHealth { healthy, latency: latency_ms.map(Duration::from_millis) };
+	}
+
+	fn ordered_urls(relays: &DownstreamRelays) -> Vec<String> {
+		relays.ordered().into_iter().map(|relay| relay.base_url.clone()).collect()
+	}
+
+	#[test]
+	fn test_requires_at_least_one_relay() {
+		assert!(DownstreamRelays::new(vec![]).is_err());
+	}
+
+	#[test]
+	fn test_defaults_to_configuration_order() {
+		let relays = relays();
+		assert_eq!(ordered_urls(&relays), vec!["http://relay-a:1000", "http://relay-b:2000", "http://relay-c:3000"]);
+		assert_eq!(relays.primary().base_url, "http://relay-a:1000");
+	}
+
+	#[test]
+	fn test_orders_healthy_relays_by_latency() {
+		let relays = relays();
+		set_health(&relays, 0, true, Some(80));
+		set_health(&relays, 1, true, Some(20));
+		set_health(&relays, 2, true, Some(50));
+
+		assert_eq!(ordered_urls(&relays), vec!["http://relay-b:2000", "http://relay-c:3000", "http://relay-a:1000"]);
+	}
+
+	#[test]
+	fn test_unhealthy_relays_are_tried_last() {
+		let relays = relays();
+		set_health(&relays, 0, false, None);
+		set_health(&relays, 1, true, Some(90));
+		set_health(&relays, 2, true, Some(10));
+
+		assert_eq!(ordered_urls(&relays), vec!["http://relay-c:3000", "http://relay-b:2000", "http://relay-a:1000"]);
+
+		relays.mark_unhealthy("http://relay-c:3000");
+		assert_eq!(relays.primary().base_url, "http://relay-b:2000");
+	}
+
+	#[test]
+	fn test_pinned_relay_is_tried_first() {
+		let relays = relays();
+		set_health(&relays, 0, true, Some(10));
+		set_health(&relays, 2, false, None);
+
+		relays.pin(Some("http://relay-c:3000/")).unwrap();
+		assert_eq!(relays.pinned(), Some("http://relay-c:3000".to_string()));
+		assert_eq!(ordered_urls(&relays), vec!["http://relay-c:3000", "http://relay-a:1000", "http://relay-b:2000"]);
+
+		assert!(relays.pin(Some("http://unknown:4000")).is_err());
+
+		relays.pin(None).unwrap();
+		assert_eq!(relays.primary().base_url, "http://relay-a:1000");
 	}
 }
@@ -1,27 +1,40 @@
-use std::sync::Arc;
+use std::sync::{Arc, atomic::Ordering};
+use std::time::Instant;
 
-use alloy::primitives::keccak256;
+use alloy::{primitives::B256, rpc::types::beacon::BlsPublicKey};
 use async_trait::async_trait;
 use axum::http::HeaderMap;
+use common::metrics::{FABRIC_CONSTRAINTS_TOTAL, STAGE_POSTED, STAGE_PROVEN};
 use constraints::{
 	api::ConstraintsApi,
+	error::{ConstraintsError, Result},
+	receiver_auth::{ReceiverAccess, ReceiverAuthConfig, ReceiverAuthorizer},
 	server::ProxyState,
 	types::{
-		AuthorizationContext, ConstraintCapabilities, ConstraintsResponse, DelegationsResponse, SignedConstraints,
-		SignedDelegation, SubmitBlockRequestWithProofs,
+		AcceptedBlockWithProofs, AuthorizationContext, BlocksWithProofsResponse, Constraint, ConstraintCapabilities,
+		ConstraintsResponse, DelegationOffersResponse, DelegationsResponse, ProofValidationReport, SignedConstraints,
+		SignedDelegation, SignedDelegationOffer, SignedReceipt, SubmitBlockRequestWithProofs,
 	},
 };
-use eyre::{Result, eyre};
-use lookahead::utils::current_slot;
 use reqwest::Client;
 use signing::signer::verify_bls;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+use urc::utils::{get_constraints_message_signing_root, get_delegation_signing_root};
 
+use crate::constants::MAX_DELEGATION_OFFERS_PER_SLOT;
 use crate::relay::{
+	merger::ConstraintsMerger,
+	metrics::{
+		BLOCK_ACCEPTED, BLOCK_REJECTED, RELAY_BLOCKS_WITH_PROOFS_TOTAL, RELAY_CONSTRAINTS_MESSAGES_ACCEPTED_TOTAL,
+		RELAY_CONSTRAINTS_PER_SLOT, RELAY_DELEGATION_OFFERS_ACCEPTED_TOTAL, RELAY_DELEGATIONS_ACCEPTED_TOTAL,
+		RELAY_DOWNSTREAM_SUBMIT_LATENCY_SECONDS, RELAY_PROOF_VERIFICATION_LATENCY_SECONDS,
+	},
 	state::RelayState,
 	utils::{
-		handle_proof_validation, validate_constraints_message, validate_delegation_message, validate_is_gateway,
-		validate_is_proposer, verify_constraints_signature, verify_delegation_signature,
+		build_proof_validation_report, handle_proof_validation, sign_receipt, validate_block_slot,
+		validate_constraints_message, validate_delegation_message, validate_delegation_offer, validate_is_gateway,
+		validate_is_offering_gateway, validate_is_proposer, validate_is_trusted_delegate, verify_constraints_signature,
+		verify_delegation_offer_signature, verify_delegation_signature,
 	},
 };
 use crate::storage::InclusionDbExt;
@@ -38,6 +51,83 @@ impl RelayServer {
 	}
 }
 
+impl RelayServer {
+	/// Sign and store a receipt for an accepted message
+	fn issue_receipt(&self, message_hash: B256) -> Result<SignedReceipt> {
+		let timestamp_ms = self.state.slot_clock.now_ms();
+		let receipt = sign_receipt(message_hash, timestamp_ms, &self.state.receipt_signer)
+			.map_err(|e| ConstraintsError::Internal(format!("Failed to sign receipt: {e}")))?;
+		self.state.db.store_receipt(&receipt)?;
+		debug!("Issued receipt for message {}", message_hash);
+		Ok(receipt)
+	}
+
+	/// Check that a block is for a live slot and builds on the current head, returning the merged constraints of
+	/// every delegate for its slot. Shared by the submission and its dry-run, so both reject a block alike
+	async fn block_constraints(&self, block_request: &SubmitBlockRequestWithProofs) -> Result<Vec<Constraint>> {
+		let slot = block_request.slot();
+
+		// Reject stale and far-future blocks before looking up their constraints
+		validate_block_slot(slot, &self.state.slot_clock, self.state.block_slot_grace_ms)?;
+
+		// The block must build on the head its slot started on, if the relay follows an execution client
+		if let Some(head_cache) = &self.state.head_cache {
+			head_cache.check_parent(self.state.slot_clock.slot_start_ms(slot), block_request.parent_hash()).await?;
+		}
+
+		debug!("fetching signed constraints from database");
+		// Fetch the merged constraints of every delegate for the slot
+		let signed_constraints = self.state.db.get_slot_signed_constraints(slot)?;
+		if signed_constraints.is_empty() {
+			return Err(ConstraintsError::NotFound(format!("No signed constraints found for slot {}", slot)));
+		}
+		Ok(ConstraintsMerger::new(signed_constraints).flattened_constraints())
+	}
+
+	/// Validate the proofs of a block submission and forward the block to the downstream relays
+	async fn submit_block_with_proofs(
+		&self,
+		block_request: SubmitBlockRequestWithProofs,
+		headers: HeaderMap,
+	) -> Result<()> {
+		info!("post_blocks_with_proofs(), slot={}", block_request.slot());
+		let slot = block_request.slot();
+		let constraints = self.block_constraints(&block_request).await?;
+
+		debug!("validating proofs");
+		// Validate the proofs
+		let start = Instant::now();
+		let validation = handle_proof_validation(
+			&block_request,
+			&constraints,
+			&self.state.trie_cache,
+			&self.state.constraint_registry,
+		);
+		RELAY_PROOF_VERIFICATION_LATENCY_SECONDS.observe(start.elapsed().as_secs_f64());
+		let results = validation?;
+
+		// Make the legacy submit block request to the downstream relays, failing over if needed
+		let bid_trace = block_request.message.bid_trace().clone();
+		let block = block_request.into_block_request();
+		let start = Instant::now();
+		let submission = self.state.downstream_relays.submit_block(block, headers).await;
+		RELAY_DOWNSTREAM_SUBMIT_LATENCY_SECONDS.observe(start.elapsed().as_secs_f64());
+		submission.map_err(|e| ConstraintsError::Upstream(format!("{e:#}")))?;
+
+		// Keep a record of the accepted block with the outcome of each proof. The block is already forwarded, so a
+		// failure to store the record must not fail the submission
+		let proven = results.iter().filter(|result| result.satisfied).count();
+		FABRIC_CONSTRAINTS_TOTAL.with_label_values(&[STAGE_PROVEN]).inc_by(proven as u64);
+		let accepted =
+			AcceptedBlockWithProofs { bid_trace, constraints: results, timestamp_ms: self.state.slot_clock.now_ms() };
+		if let Err(e) = self.state.db.store_accepted_block(&accepted) {
+			warn!("Failed to store the record of the accepted block for slot {}: {}", slot, e);
+		}
+
+		Ok(())
+	}
+}
+
 impl AsRef<RelayState> for RelayServer {
 	fn as_ref(&self) -> &RelayState {
 		&self.state
@@ -45,143 +135,300 @@ impl AsRef<RelayState> for RelayServer {
 }
 
 impl ProxyState for RelayServer {
-	fn server_url(&self) -> &str {
-		&self.state.downstream_relay_client.base_url
+	fn server_urls(&self) -> Vec<String> {
+		self.state.server_urls()
+	}
+
+	fn server_url(&self) -> String {
+		self.state.server_url()
 	}
 
 	fn http_client(&self) -> &Client {
-		&self.state.downstream_relay_client.client
+		self.state.http_client()
+	}
+}
+
+#[async_trait]
+impl ReceiverAuthorizer for RelayServer {
+	/// Receivers and delegates of the merged constraints of a slot
+	/// None once the slot has passed, or if no delegate restricted its constraints to a receivers list
+	async fn allowed_receivers(&self, slot: u64) -> Result<Option<Vec<BlsPublicKey>>> {
+		// If we're at slot_target + 1 or beyond, bypass authentication
+		if self.state.slot_clock.current_slot() > slot {
+			return Ok(None);
+		}
+
+		let merged = ConstraintsMerger::new(self.state.db.get_slot_signed_constraints(slot)?).into_merged();
+		if merged.iter().all(|c| c.message.receivers.is_empty()) {
+			return Ok(None);
+		}
+
+		// A delegate may read its own constraints, so a gateway can check what the relay serves
+		let mut allowed = Vec::new();
+		for c in &merged {
+			for public_key in c.message.receivers.iter().chain(std::iter::once(&c.message.delegate)) {
+				if !allowed.contains(public_key) {
+					allowed.push(*public_key);
+				}
+			}
+		}
+		Ok(Some(allowed))
+	}
+
+	/// Verify the receiver's signature over the slot and timestamp, accepting each signature once
+	async fn verify_receiver(&self, slot: u64, auth: &AuthorizationContext) -> Result<BlsPublicKey> {
+		// All headers must be present
+		let missing = |header: &str| ConstraintsError::Unauthenticated(format!("Missing {header} from header"));
+		let public_key = auth.public_key.ok_or_else(|| missing("public key"))?;
+		let signature = auth.signature.ok_or_else(|| missing("signature"))?;
+		let signing_id = auth.signing_id.ok_or_else(|| missing("signing id"))?;
+		let nonce = auth.nonce.ok_or_else(|| missing("nonce"))?;
+		let timestamp_ms = auth.timestamp_ms.ok_or_else(|| missing("timestamp"))?;
+		let unauthenticated = |e: eyre::Report| ConstraintsError::Unauthenticated(e.to_string());
+
+		// Reject stale or future signatures before verifying them
+		let now_ms = self.state.slot_clock.now_ms();
+		self.state.receiver_auth_guard.check_timestamp(timestamp_ms, now_ms).map_err(unauthenticated)?;
+
+		debug!("verifying slot signature");
+		// Verify caller's signature over the slot and timestamp using standardized commit-boost verification
+		let message_hash = AuthorizationContext::message_hash(slot, timestamp_ms);
+		verify_bls(self.state.chain, &public_key, &message_hash, &signature, &signing_id, nonce)
+			.map_err(unauthenticated)?;
+
+		// A signature is only accepted once, so a captured one can not be replayed within the window
+		self.state.receiver_auth_guard.accept(&signature, timestamp_ms, now_ms).map_err(unauthenticated)?;
+
+		Ok(public_key)
+	}
+
+	fn receiver_auth_config(&self) -> ReceiverAuthConfig {
+		self.state.receiver_auth.clone()
 	}
 }
 
 #[async_trait]
 impl ConstraintsApi for RelayServer {
 	/// POST /constraints
-	async fn post_constraints(&self, signed_constraints: SignedConstraints) -> Result<()> {
+	async fn post_constraints(&self, signed_constraints: SignedConstraints) -> Result<SignedReceipt> {
+		// Reject new constraints while the relay is drained
+		if !self.state.accepting_constraints.load(Ordering::SeqCst) {
+			return Err(ConstraintsError::Unavailable(
+				"Relay is draining and not accepting new constraints".to_string(),
+			));
+		}
+
 		debug!("validate_constraints_message()");
 		// Validate constraints message structure
-		validate_constraints_message(&signed_constraints.message, &self.state.chain)?;
+		validate_constraints_message(
+			&signed_constraints.message,
+			&self.state.slot_clock,
+			&self.state.constraint_registry,
+		)?;
+
+		debug!("validate_is_trusted_delegate()");
+		// Permissioned relays only accept constraints of their trusted gateways
+		validate_is_trusted_delegate(&signed_constraints.message.delegate, self.state.trusted_delegates.as_deref())?;
 
 		debug!("verify_constraints_signature()");
 		// Verify BLS signature using the delegate public key from the message
-		verify_constraints_signature(&signed_constraints, &self.state.chain)?;
+		verify_constraints_signature(&signed_constraints, &self.state.chain)
+			.map_err(|e| ConstraintsError::Unauthenticated(format!("Invalid constraints signature: {e}")))?;
 
 		debug!("validate_is_gateway()");
 		// Verify a delegation exists and is for the correct gateway
 		validate_is_gateway(&signed_constraints.message.delegate, signed_constraints.message.slot, &self.state.db)?;
 
-		debug!("store_signed_constraints()");
+		debug!("merging constraints");
+		// Merge with constraints already posted by other delegated gateways for this slot. The merged constraints of
+		// every delegate must still be satisfiable by a single block, without a head block the gas check is skipped
+		// like on the gateway
+		let slot = signed_constraints.message.slot;
+		let block_gas_limit = match &self.state.head_cache {
+			Some(head_cache) => match head_cache.head().await {
+				Ok(head) => Some(head.gas_limit),
+				Err(e) => {
+					warn!("Checking the constraints of slot {} without a block gas limit: {}", slot, e);
+					None
+				}
+			},
+			None => None,
+		};
+		let mut merger = ConstraintsMerger::new(self.state.db.get_slot_signed_constraints(slot)?);
+		merger.insert(&self.state.constraint_registry, signed_constraints.clone(), block_gas_limit)?;
+
+		debug!("store_delegate_signed_constraints()");
 		// Store signed constraints in database
-		self.state.db.store_signed_constraints(&signed_constraints)?;
+		self.state.db.store_delegate_signed_constraints(&signed_constraints)?;
 
 		info!(
-			"Received signed constraints for slot {} from {}",
-			signed_constraints.message.slot, signed_constraints.message.delegate
+			"Received signed constraints for slot {} from {}, {} delegate(s) merged",
+			slot,
+			signed_constraints.message.delegate,
+			merger.merged().len()
 		);
 
-		Ok(())
+		RELAY_CONSTRAINTS_MESSAGES_ACCEPTED_TOTAL.inc();
+		FABRIC_CONSTRAINTS_TOTAL
+			.with_label_values(&[STAGE_POSTED])
+			.inc_by(signed_constraints.message.constraints.len() as u64);
+		RELAY_CONSTRAINTS_PER_SLOT.set(merger.flattened_constraints().len() as i64);
+
+		// Acknowledge acceptance with a signed receipt
+		let message_hash = get_constraints_message_signing_root(&signed_constraints.message)
+			.map_err(|e| ConstraintsError::Internal(e.to_string()))?;
+		self.issue_receipt(message_hash)
 	}
 
 	/// GET /constraints
-	/// Returns all signed constraints for a slot
-	/// If the slot has passed, returns all signed constraints for the slot without authentication
-	/// If the slot has not passed, verifies the authentication headers against the receivers list
-	async fn get_constraints(&self, slot: u64, auth: AuthorizationContext) -> Result<ConstraintsResponse> {
-		// Get current slot to check if target slot has passed
-		let current_slot = current_slot(&self.state.chain);
-
-		// If we're at slot_target + 1 or beyond, bypass authentication
-		if current_slot > slot {
-			// Simply fetch and return all constraints for this slot
-			let signed_constraints = self.state.db.get_signed_constraints(slot)?;
-			match signed_constraints {
-				Some(signed_constraints) => {
-					return Ok(ConstraintsResponse { constraints: vec![signed_constraints] });
-				}
-				None => {
-					return Ok(ConstraintsResponse { constraints: vec![] });
-				}
-			}
-		}
-
-		// Get signed constraints from database
-		let signed_constraints = match self.state.db.get_signed_constraints(slot)? {
-			Some(signed_constraints) => signed_constraints,
-			None => return Ok(ConstraintsResponse { constraints: vec![] }),
+	/// Returns the merged signed constraints of every delegate for a slot
+	/// Once the slot has passed, or if no message has a receivers list, every message is returned
+	/// Otherwise messages with a receivers list are only returned to those receivers and to their delegate,
+	/// so a gateway can check what the relay serves of its constraints
+	async fn get_constraints(&self, slot: u64, access: ReceiverAccess) -> Result<ConstraintsResponse> {
+		// Get merged signed constraints from database
+		let merged = ConstraintsMerger::new(self.state.db.get_slot_signed_constraints(slot)?).into_merged();
+
+		let public_key = match access {
+			ReceiverAccess::Open => return Ok(ConstraintsResponse { constraints: merged }),
+			ReceiverAccess::Receiver(public_key) => public_key,
 		};
 
-		// Bypass authentication if the receivers list is empty
-		if signed_constraints.message.receivers.is_empty() {
-			debug!("get_constraints(): No receivers list found for slot {}, bypassing authentication", slot);
-			return Ok(ConstraintsResponse { constraints: vec![signed_constraints] });
-		}
-
-		// Slot has not passed yet and receivers list is not empty -> enforce authentication
-		// All headers must be present
-		let public_key = auth.public_key.ok_or(eyre!("Missing public key from header"))?;
-		let signature = auth.signature.ok_or(eyre!("Missing signature from header"))?;
-		let signing_id = auth.signing_id.ok_or(eyre!("Missing signing id from header"))?;
-		let nonce = auth.nonce.ok_or(eyre!("Missing nonce from header"))?;
-
-		// Compute slot hash for signature verification
-		let slot_hash = keccak256(&slot.to_be_bytes());
-
-		debug!("verifying slot signature");
-		// Verify caller's signature against the slot hash using standardized commit-boost verification
-		verify_bls(self.state.chain, &public_key, &slot_hash, &signature, &signing_id, nonce)?;
-
-		debug!("verifying receiver list");
-		// Verify the caller is part of the receivers list
-		if !signed_constraints.message.receivers.contains(&public_key) {
-			return Err(eyre!("Caller is not part of the receivers list for slot {}", slot));
-		}
-
-		info!("returning signed constraints for slot {}", slot);
-		Ok(ConstraintsResponse { constraints: vec![signed_constraints] })
+		// Only return messages that are unrestricted, list the caller as a receiver or were signed by the caller
+		let constraints = merged
+			.into_iter()
+			.filter(|c| {
+				c.message.receivers.is_empty()
+					|| c.message.receivers.contains(&public_key)
+					|| c.message.delegate == public_key
+			})
+			.collect::<Vec<_>>();
+
+		info!("returning {} signed constraints for slot {}", constraints.len(), slot);
+		Ok(ConstraintsResponse { constraints })
 	}
 
 	/// POST /delegation
-	async fn post_delegation(&self, signed_delegation: SignedDelegation) -> Result<()> {
+	async fn post_delegation(&self, signed_delegation: SignedDelegation) -> Result<SignedReceipt> {
 		debug!("validate_delegation_message()");
 		// Validate delegation message is for a future slot
-		validate_delegation_message(&signed_delegation.message, &self.state.chain)?;
+		validate_delegation_message(&signed_delegation.message, &self.state.slot_clock)?;
+
+		debug!("validate_is_trusted_delegate()");
+		// Permissioned relays only accept delegations to their trusted gateways
+		validate_is_trusted_delegate(&signed_delegation.message.delegate, self.state.trusted_delegates.as_deref())?;
 
 		debug!("verify_delegation_signature()");
 		// Verify delegation was signed by proposer
-		verify_delegation_signature(&signed_delegation, &self.state.chain)?;
+		verify_delegation_signature(&signed_delegation, &self.state.chain)
+			.map_err(|e| ConstraintsError::Unauthenticated(format!("Invalid delegation signature: {e}")))?;
 
 		debug!("validate_is_proposer()");
 		// Validate proposer is scheduled for this slot
 		validate_is_proposer(&signed_delegation.message.proposer, signed_delegation.message.slot, &self.state.db)?;
 
+		// Validate the proposer is registered in the URC and opted into the slasher, if configured
+		if let Some(urc_indexer) = &self.state.urc_indexer {
+			debug!("check_operator()");
+			urc_indexer
+				.check_operator(&signed_delegation.message.proposer)
+				.await
+				.map_err(|e| ConstraintsError::Forbidden(e.to_string()))?;
+		}
+
 		debug!("checking for existing delegation");
-		// Check for existing delegation to prevent equivocation
-		if self.state.db.is_delegated(signed_delegation.message.slot)? {
-			return Err(eyre!("Delegation already exists for slot {}", signed_delegation.message.slot));
+		// A slot may be delegated to several gateways, but only once per delegate
+		if self
+			.state
+			.db
+			.get_delegate_delegation(signed_delegation.message.slot, &signed_delegation.message.delegate)?
+			.is_some()
+		{
+			return Err(ConstraintsError::Conflict(format!(
+				"Delegation already exists for slot {} and delegate {}",
+				signed_delegation.message.slot, signed_delegation.message.delegate
+			)));
 		}
 
 		debug!("storing delegation in database");
 		// Store delegation in database
-		self.state.db.store_delegation(&signed_delegation)?;
+		self.state.db.store_delegate_delegation(&signed_delegation)?;
 
 		info!(
 			"Delegation posted for slot {}, key={:?}",
 			signed_delegation.message.slot, signed_delegation.message.proposer
 		);
 
-		Ok(())
+		RELAY_DELEGATIONS_ACCEPTED_TOTAL.inc();
+
+		// Acknowledge acceptance with a signed receipt
+		let message_hash = get_delegation_signing_root(&signed_delegation.message)
+			.map_err(|e| ConstraintsError::Internal(e.to_string()))?;
+		self.issue_receipt(message_hash)
+	}
+
+	/// GET /receipts/{message_hash}
+	async fn get_receipt(&self, message_hash: B256) -> Result<Option<SignedReceipt>> {
+		Ok(self.state.db.get_receipt(&message_hash)?)
 	}
 
 	/// GET /delegations/{slot}
 	async fn get_delegations(&self, slot: u64) -> Result<DelegationsResponse> {
-		match self.state.db.get_delegation(slot)? {
-			Some(delegation) => {
-				return Ok(DelegationsResponse { delegations: vec![delegation] });
-			}
-			None => {
-				return Ok(DelegationsResponse { delegations: vec![] });
-			}
+		let delegations = self.state.db.get_slot_delegations(slot)?;
+		Ok(DelegationsResponse { delegations })
+	}
+
+	/// POST /delegation_offers
+	async fn post_delegation_offer(&self, signed_offer: SignedDelegationOffer) -> Result<()> {
+		debug!("validate_delegation_offer()");
+		// Validate the offer is for an upcoming slot and only names supported constraint types
+		validate_delegation_offer(&signed_offer.message, &self.state.slot_clock, &self.state.constraint_registry)?;
+
+		debug!("verify_delegation_offer_signature()");
+		// Verify the offer was signed by the delegate
+		verify_delegation_offer_signature(&signed_offer, &self.state.chain)
+			.map_err(|e| ConstraintsError::Unauthenticated(format!("Invalid delegation offer signature: {e}")))?;
+
+		debug!("validate_is_offering_gateway()");
+		// Only trusted or bonded gateways may offer, anyone else could outbid them with a fresh key
+		validate_is_offering_gateway(
+			&signed_offer.message.delegate,
+			self.state.trusted_delegates.as_deref(),
+			self.state.urc_indexer.as_deref(),
+		)
+		.await?;
+
+		// Each delegate holds one offer per slot and the number of delegates offering is capped
+		let slot = signed_offer.message.slot;
+		if self.state.db.get_delegation_offer(slot, &signed_offer.message.delegate)?.is_none()
+			&& self.state.db.get_slot_delegation_offers(slot)?.len() >= MAX_DELEGATION_OFFERS_PER_SLOT
+		{
+			return Err(ConstraintsError::Conflict(format!(
+				"Slot {} already holds {} delegation offers",
+				slot, MAX_DELEGATION_OFFERS_PER_SLOT
+			)));
+		}
+
+		// A later offer of the same delegate replaces the previous one, e.g. to raise the bid
+		if !self.state.db.store_delegation_offer(&signed_offer)? {
+			return Err(ConstraintsError::Conflict(format!(
+				"Delegation offer nonce {} does not exceed the stored offer of the delegate for slot {}",
+				signed_offer.nonce, slot
+			)));
 		}
+
+		info!(
+			"Delegation offer posted for slot {} by {}, bid={} gwei",
+			signed_offer.message.slot, signed_offer.message.delegate, signed_offer.message.bid_gwei
+		);
+		RELAY_DELEGATION_OFFERS_ACCEPTED_TOTAL.inc();
+		Ok(())
+	}
+
+	/// GET /delegation_offers/{slot}
+	async fn get_delegation_offers(&self, slot: u64) -> Result<DelegationOffersResponse> {
+		let offers = self.state.db.get_slot_delegation_offers(slot)?;
+		Ok(DelegationOffersResponse { offers })
 	}
 
 	/// POST /blocks_with_proofs
@@ -190,27 +437,51 @@ impl ConstraintsApi for RelayServer {
 		block_request: SubmitBlockRequestWithProofs,
 		headers: HeaderMap,
 	) -> Result<()> {
-		info!("post_blocks_with_proofs(), slot={}", block_request.slot());
-		// Get the slot
-		let slot = block_request.slot();
+		let result = self.submit_block_with_proofs(block_request, headers).await;
 
-		debug!("fetching signed constraints from database");
-		// Fetch constraints from database for the slot
-		let signed_constraints = self
-			.state
-			.db
-			.get_signed_constraints(slot)?
-			.ok_or(eyre!("No signed constraints found for slot {}", slot))?;
+		let label = if result.is_ok() { BLOCK_ACCEPTED } else { BLOCK_REJECTED };
+		RELAY_BLOCKS_WITH_PROOFS_TOTAL.with_label_values(&[label]).inc();
 
-		debug!("validating proofs");
-		// Validate the proofs
-		handle_proof_validation(&block_request, signed_constraints)?;
+		result
+	}
 
-		// Make the legacy submit block request to the downnstream relay
-		let block = block_request.into_block_request();
-		self.state.downstream_relay_client.submit_block(block, headers).await?;
+	/// GET /blocks_with_proofs/{slot}
+	/// Only served once the slot has passed, so the bids of a live auction are not disclosed
+	async fn get_blocks_with_proofs(&self, slot: u64) -> Result<BlocksWithProofsResponse> {
+		if slot >= self.state.slot_clock.current_slot() {
+			return Err(ConstraintsError::Forbidden(format!(
+				"Accepted blocks of slot {} are served once it has passed",
+				slot
+			)));
+		}
+		let blocks = self.state.db.get_slot_accepted_blocks(slot)?;
+		Ok(BlocksWithProofsResponse { blocks })
+	}
 
-		Ok(())
+	/// POST /blocks_with_proofs/validate
+	/// Validates the proofs against the merged constraints for the slot without forwarding the block
+	async fn validate_blocks_with_proofs(
+		&self,
+		block_request: SubmitBlockRequestWithProofs,
+	) -> Result<ProofValidationReport> {
+		let slot = block_request.slot();
+		let constraints = self.block_constraints(&block_request).await?;
+
+		let report = build_proof_validation_report(
+			&block_request,
+			&constraints,
+			&self.state.trie_cache,
+			&self.state.constraint_registry,
+		);
+		info!(
+			"Dry-run validated block for slot {}, valid={}, {}/{} constraints satisfied",
+			slot,
+			report.valid,
+			report.constraints.iter().filter(|result| result.satisfied).count(),
+			report.constraints.len()
+		);
+
+		Ok(report)
 	}
 
 	/// GET /capabilities
@@ -218,8 +489,224 @@ impl ConstraintsApi for RelayServer {
 		Ok(self.state.constraint_capabilities.clone())
 	}
 
-	/// GET /health
+	/// GET /health, liveness only: the readiness of the relay's dependencies is served on /readyz
 	async fn health_check(&self) -> Result<()> {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::constants::INCLUSION_CONSTRAINT_TYPE;
+	use crate::relay::config::RelayConfig;
+	use crate::relay::head_cache::HeadBlock;
+	use alloy::primitives::{Address, U256};
+	use alloy::rpc::types::beacon::BlsSignature;
+	use alloy::rpc::types::beacon::relay::BidTrace;
+	use alloy::rpc::types::beacon::relay::SubmitBlockRequest;
+	use cb_common::types::BlsSecretKey;
+	use commit_boost::prelude::Chain;
+	use common::health::ReadinessProbe;
+	use common::storage::DatabaseContext;
+	use constraints::types::{ConstraintProofResult, ConstraintProofs, DelegationOffer};
+	use signing::builder::{BuilderSigner, build_block};
+	use signing::local::LocalSigner;
+	use urc::utils::get_delegation_offer_signing_root;
+
+	fn new_relay(trusted_delegates: &[BlsPublicKey]) -> RelayServer {
+		relay_with_config(|config| {
+			if !trusted_delegates.is_empty() {
+				config.trusted_delegates =
+					Some(trusted_delegates.iter().map(|delegate| delegate.to_string()).collect());
+			}
+		})
+	}
+
+	fn relay_with_config(configure: impl FnOnce(&mut RelayConfig)) -> RelayServer {
+		let tmp_dir = tempfile::TempDir::new().unwrap();
+		let mut opts = rocksdb::Options::default();
+		opts.create_if_missing(true);
+		let db = DatabaseContext::new(Arc::new(rocksdb::DB::open(&opts, tmp_dir.path()).unwrap()));
+
+		let mut config = toml::from_str::<RelayConfig>(
+			r#"
+			chain = "Holesky"
+			host = "127.0.0.1"
+			port = 9998
+			db_path = "unused"
+			constraint_capabilities = [1]
+			beacon_api_host = "127.0.0.1"
+			beacon_api_port = 5052
+			lookahead_update_interval = 1
+			downstream_relay_host = "127.0.0.1"
+			downstream_relay_port = 18550
+			receipt_signing_key = "0x0101010101010101010101010101010101010101010101010101010101010101"
+			"#,
+		)
+		.unwrap();
+		configure(&mut config);
+		RelayServer::new(Arc::new(RelayState::new(db, config).unwrap()))
+	}
+
+	fn sign_offer(
+		signer: &LocalSigner,
+		delegate: BlsPublicKey,
+		slot: u64,
+		bid_gwei: u64,
+		nonce: u64,
+	) -> SignedDelegationOffer {
+		let offer = DelegationOffer {
+			delegate,
+			committer: Address::repeat_byte(1),
+			slot,
+			bid_gwei,
+			constraint_types: vec![INCLUSION_CONSTRAINT_TYPE],
+		};
+		let signature =
+			signer.sign_bls(&delegate, get_delegation_offer_signing_root(&offer), &B256::ZERO, nonce).unwrap();
+		SignedDelegationOffer {
+			message: offer,
+			nonce,
+			signing_id: B256::ZERO,
+			signature: BlsSignature::new(signature.serialize()),
+		}
+	}
+
+	#[tokio::test]
+	async fn test_post_delegation_offer() {
+		let mut signer = LocalSigner::new(Chain::Holesky);
+		let delegate = signer.add_bls_key(BlsSecretKey::random());
+		let untrusted = signer.add_bls_key(BlsSecretKey::random());
+		let relay = new_relay(&[delegate]);
+		let slot = relay.state.slot_clock.current_slot() + 2;
+
+		relay.post_delegation_offer(sign_offer(&signer, delegate, slot, 1_000, 1)).await.unwrap();
+		assert_eq!(relay.get_delegation_offers(slot).await.unwrap().offers.len(), 1);
+
+		// A replayed or older offer does not replace the latest one, a later one does
+		let replayed = relay.post_delegation_offer(sign_offer(&signer, delegate, slot, 900, 1)).await;
+		assert!(matches!(replayed, Err(ConstraintsError::Conflict(_))));
+		relay.post_delegation_offer(sign_offer(&signer, delegate, slot, 1_100, 2)).await.unwrap();
+		assert_eq!(relay.get_delegation_offers(slot).await.unwrap().offers[0].message.bid_gwei, 1_100);
+
+		// The signature must cover the offer
+		let mut raised = sign_offer(&signer, delegate, slot, 1_100, 3);
+		raised.message.bid_gwei = 2_000;
+		assert!(matches!(relay.post_delegation_offer(raised).await, Err(ConstraintsError::Unauthenticated(_))));
+
+		// Only trusted gateways may offer
+		let offer = sign_offer(&signer, untrusted, slot, 5_000, 1);
+		assert!(matches!(relay.post_delegation_offer(offer).await, Err(ConstraintsError::Forbidden(_))));
+		let permissionless = new_relay(&[]);
+		let offer = sign_offer(&signer, delegate, slot, 1_000, 1);
+		assert!(matches!(permissionless.post_delegation_offer(offer).await, Err(ConstraintsError::Forbidden(_))));
+	}
+
+	#[tokio::test]
+	async fn test_post_delegation_offer_caps_offers_per_slot() {
+		let mut signer = LocalSigner::new(Chain::Holesky);
+		let delegate = signer.add_bls_key(BlsSecretKey::random());
+		let relay = new_relay(&[delegate]);
+		let slot = relay.state.slot_clock.current_slot() + 2;
+
+		for byte in 0..MAX_DELEGATION_OFFERS_PER_SLOT as u8 {
+			let mut offer = sign_offer(&signer, delegate, slot, 1_000, 1);
+			offer.message.delegate = BlsPublicKey::repeat_byte(byte);
+			relay.state.db.store_delegation_offer(&offer).unwrap();
+		}
+		let offer = sign_offer(&signer, delegate, slot, 1_000, 1);
+		assert!(matches!(relay.post_delegation_offer(offer).await, Err(ConstraintsError::Conflict(_))));
+	}
+
+	#[tokio::test]
+	async fn test_health_is_liveness_only() {
+		// Without a beacon node the relay has no lookahead and is not ready, but it is alive
+		let relay = new_relay(&[]);
+		assert!(relay.health_check().await.is_ok());
+		let readiness = relay.state.readiness().await;
+		assert!(!readiness.ready);
+		let failed =
+			readiness.checks.iter().filter(|check| !check.ok).map(|check| check.name.as_str()).collect::<Vec<_>>();
+		assert_eq!(failed, vec!["lookahead"]);
+	}
+
+	#[tokio::test]
+	async fn test_get_blocks_with_proofs_only_for_past_slots() {
+		let relay = new_relay(&[]);
+		let current_slot = relay.state.slot_clock.current_slot();
+		for slot in [current_slot - 1, current_slot] {
+			relay
+				.state
+				.db
+				.store_accepted_block(&AcceptedBlockWithProofs {
+					bid_trace: BidTrace {
+						slot,
+						parent_hash: B256::ZERO,
+						block_hash: B256::repeat_byte(1),
+						builder_pubkey: BlsPublicKey::repeat_byte(1),
+						proposer_pubkey: BlsPublicKey::repeat_byte(2),
+						proposer_fee_recipient: Address::ZERO,
+						gas_limit: 30_000_000,
+						gas_used: 21_000,
+						value: U256::from(1u64),
+					},
+					constraints: vec![ConstraintProofResult {
+						index: 0,
+						constraint_type: INCLUSION_CONSTRAINT_TYPE,
+						satisfied: true,
+						error: None,
+					}],
+					timestamp_ms: relay.state.slot_clock.now_ms(),
+				})
+				.unwrap();
+		}
+
+		// The bids of the current and next slots are not disclosed
+		assert_eq!(relay.get_blocks_with_proofs(current_slot - 1).await.unwrap().blocks.len(), 1);
+		for slot in [current_slot, current_slot + 1] {
+			assert!(matches!(relay.get_blocks_with_proofs(slot).await, Err(ConstraintsError::Forbidden(_))));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_blocks_build_on_the_head_their_slot_started_on() {
+		// Nothing listens on the execution client's port, parents are checked against the observed heads alone
+		let relay = relay_with_config(|config| {
+			config.execution_client_url = Some("http://127.0.0.1:1".parse().unwrap());
+			config.block_slot_grace_ms = 12_000;
+		});
+		let slot_clock = &relay.state.slot_clock;
+		let current_slot = slot_clock.current_slot();
+		let head = |slot: u64, hash: u8, parent: u8| HeadBlock {
+			hash: B256::repeat_byte(hash),
+			parent_hash: B256::repeat_byte(parent),
+			number: slot,
+			timestamp: slot_clock.slot_start_ms(slot) / 1000,
+			gas_limit: 30_000_000,
+		};
+		let head_cache = relay.state.head_cache.as_ref().unwrap();
+		head_cache.observe(head(current_slot - 2, 1, 0));
+		head_cache.observe(head(current_slot - 1, 2, 1));
+
+		let builder = BuilderSigner::new(BlsSecretKey::random(), relay.state.chain);
+		let block = |slot: u64, parent: u8| {
+			let mut message = build_block(slot, BlsPublicKey::repeat_byte(2), &[], &builder);
+			if let SubmitBlockRequest::Electra(request) = &mut message {
+				request.message.parent_hash = B256::repeat_byte(parent);
+			}
+			SubmitBlockRequestWithProofs { message, proofs: ConstraintProofs::default() }
+		};
+
+		// A block past the parent check is only refused for having no constraints to prove
+		for (slot, parent) in [(current_slot, 2), (current_slot + 1, 2), (current_slot - 1, 1)] {
+			let result = relay.block_constraints(&block(slot, parent)).await;
+			assert!(matches!(result, Err(ConstraintsError::NotFound(_))), "slot {}: {:?}", slot, result.err());
+		}
+		// The previous slot's block is the head, but a late block of that slot builds on the head before it
+		for (slot, parent) in [(current_slot, 1), (current_slot - 1, 2)] {
+			let result = relay.block_constraints(&block(slot, parent)).await;
+			assert!(matches!(result, Err(ConstraintsError::InvalidRequest(_))), "slot {}: {:?}", slot, result.err());
+		}
+	}
+}
@@ -0,0 +1,40 @@
+use eyre::{Result, eyre};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::relay::state::RelayState;
+
+/// URC sync manager that periodically indexes new URC registrations
+/// so delegations can be checked against the proposer's on-chain registration
+pub struct UrcSyncManager {
+	state: Arc<RelayState>,
+}
+
+impl UrcSyncManager {
+	/// Create a new URC sync manager
+	pub fn new(state: Arc<RelayState>) -> Self {
+		Self { state }
+	}
+
+	/// Run the sync task continuously
+	pub async fn run(&self) -> Result<()> {
+		let indexer = self.state.urc_indexer.as_ref().ok_or_else(|| eyre!("URC checks are not configured"))?;
+		info!(
+			"Starting URC sync manager for registry {} with {}s sync interval",
+			indexer.config().registry_address,
+			indexer.config().sync_interval_secs
+		);
+
+		loop {
+			match indexer.sync().await {
+				Ok(0) => {}
+				Ok(indexed) => debug!("Indexed {} URC registered keys", indexed),
+				Err(e) => warn!("Failed to sync URC registrations: {}", e),
+			}
+
+			sleep(Duration::from_secs(indexer.config().sync_interval_secs)).await;
+		}
+	}
+}
@@ -1,14 +1,34 @@
+use std::sync::{Arc, atomic::AtomicBool};
+
+use alloy::{
+	network::Ethereum,
+	providers::{Provider, ProviderBuilder},
+	rpc::types::beacon::BlsPublicKey,
+	signers::local::PrivateKeySigner,
+};
 use commit_boost::prelude::Chain;
+use eyre::{Result, WrapErr};
 use reqwest::{Client, Url};
+use tracing::warn;
+use urc::indexer::UrcIndexer;
 
-use common::storage::DatabaseContext;
-use constraints::{server::ProxyState, types::ConstraintCapabilities};
+use common::{storage::DatabaseContext, utils::decode_pubkey};
+use constraints::{
+	receiver_auth::ReceiverAuthConfig, registry::ConstraintRegistry, server::ProxyState, types::ConstraintCapabilities,
+};
 use lookahead::{
 	beacon_client::{BeaconApiClient, ReqwestClient},
+	cache::LookaheadCache,
+	slot_clock::SlotClock,
 	types::BeaconApiConfig,
 };
 
-use crate::relay::{config::RelayConfig, services::proxy::LegacyRelayClient};
+use crate::handlers::default_registry;
+use crate::relay::{
+	config::RelayConfig, head_cache::HeadCache, replay::ReplayGuard, services::proxy::DownstreamRelays,
+	trie_cache::TransactionTrieCache,
+};
+use crate::storage::InclusionDbExt;
 
 /// Server state that provides access to shared resources for gateway operations
 #[derive(Clone)]
@@ -21,28 +41,64 @@ pub struct RelayState {
 	pub db: DatabaseContext,
 	/// Beacon client for fetching proposer duties
 	pub beacon_client: BeaconApiClient<ReqwestClient>,
-	/// Client to call downstream relay
-	pub downstream_relay_client: LegacyRelayClient,
+	/// Slot clock synced against the beacon node
+	pub slot_clock: Arc<SlotClock>,
+	/// Proposer duties prefetched from the beacon node
+	pub lookahead_cache: Arc<LookaheadCache>,
+	/// Downstream relays, ordered by health and latency
+	pub downstream_relays: DownstreamRelays,
+	/// How often to health check the downstream relays
+	pub downstream_health_check_interval: u64,
 	/// Chain ID
 	pub chain: Chain,
 	/// How often to update the lookahead window
 	pub lookahead_update_interval: u64,
 	/// Supported constraint types
 	pub constraint_capabilities: ConstraintCapabilities,
+	/// Key used to sign receipts for accepted messages
+	pub receipt_signer: PrivateKeySigner,
+	/// Whether new constraints are accepted, cleared when the relay is drained
+	pub accepting_constraints: Arc<AtomicBool>,
+	/// Port of the metrics server, if enabled
+	pub metrics_port: Option<u16>,
+	/// Port of the admin API, if enabled
+	pub admin_port: Option<u16>,
+	/// Bearer token required to call the admin API
+	pub admin_api_key: Option<String>,
+	/// How long into a slot blocks of the previous slot are still accepted
+	pub block_slot_grace_ms: u64,
+	/// Heads of the execution client submitted blocks must build on, if one is configured
+	pub head_cache: Option<HeadCache>,
+	/// Transaction tries of recently submitted blocks
+	pub trie_cache: Arc<TransactionTrieCache>,
+	/// Receiver authentications of GET /constraints/{slot} accepted within the replay window
+	pub receiver_auth_guard: Arc<ReplayGuard>,
+	/// Receiver authorization of the delegations and proxied read routes
+	pub receiver_auth: ReceiverAuthConfig,
+	/// Handlers of the constraint types the relay validates
+	pub constraint_registry: ConstraintRegistry,
+	/// Gateway delegates constraints and delegations are accepted for, any delegate if not set
+	pub trusted_delegates: Option<Vec<BlsPublicKey>>,
+	/// Index of the URC registrations delegations are checked against, if configured
+	pub urc_indexer: Option<Arc<UrcIndexer>>,
 }
 
 impl ProxyState for RelayState {
-	fn server_url(&self) -> &str {
-		&self.downstream_relay_client.base_url
+	fn server_urls(&self) -> Vec<String> {
+		self.downstream_relays.base_urls()
+	}
+
+	fn server_url(&self) -> String {
+		self.downstream_relays.primary().base_url.clone()
 	}
 
 	fn http_client(&self) -> &Client {
-		&self.downstream_relay_client.client
+		&self.downstream_relays.primary().client
 	}
 }
 
 impl RelayState {
-	pub fn new(db: DatabaseContext, config: RelayConfig) -> Self {
+	pub fn new(db: DatabaseContext, config: RelayConfig) -> Result<Self> {
 		let chain = config.chain;
 		let host = config.host;
 		let port = config.port;
@@ -52,29 +108,107 @@ impl RelayState {
 			primary_endpoint: Url::parse(
 				format!("http://{}:{}", config.beacon_api_host, config.beacon_api_port).as_str(),
 			)
-			.unwrap(),
+			.wrap_err_with(|| {
+				format!("Invalid beacon API address {}:{}", config.beacon_api_host, config.beacon_api_port)
+			})?,
 			fallback_endpoints: vec![],
 			request_timeout_secs: 30,
 			genesis_time: chain.genesis_time_sec(),
 		})
-		.expect("Failed to create beacon client");
+		.wrap_err("Failed to create beacon client")?;
+		let slot_clock = Arc::new(SlotClock::new(&chain).with_beacon_client(beacon_client.clone()));
+		let lookahead_cache = Arc::new(LookaheadCache::new(
+			beacon_client.clone(),
+			Arc::clone(&slot_clock),
+			config.lookahead_prefetch_epochs,
+		));
+
+		// Create downstream relay clients, the configured host first followed by the fallbacks
+		let mut downstream_relay_urls =
+			vec![format!("http://{}:{}", config.downstream_relay_host, config.downstream_relay_port)];
+		downstream_relay_urls.extend(config.downstream_relay_fallback_urls);
+		let downstream_relays =
+			DownstreamRelays::with_http_config(downstream_relay_urls, &config.downstream_relay_http)
+				.wrap_err("Failed to create downstream relay clients")?;
+		let downstream_health_check_interval = config.downstream_health_check_interval;
 
-		// Create downstream relay client
-		let downstream_relay_client =
-			LegacyRelayClient::new(format!("http://{}:{}", config.downstream_relay_host, config.downstream_relay_port))
-				.expect("Failed to create downstream relay client");
+		// Create receipt signer
+		let receipt_signer =
+			config.receipt_signing_key.parse::<PrivateKeySigner>().wrap_err("Invalid receipt_signing_key")?;
 
+		// A drain through the admin API outlives a restart
+		let drained = db.get_admin_overrides().wrap_err("Failed to read the admin overrides")?.paused;
+		if drained {
+			warn!("Relay drained through the admin API, not accepting new constraints until resumed");
+		}
+
+		let metrics_port = config.metrics_port;
+		let admin_port = config.admin_port;
+		let admin_api_key = config.admin_api_key;
 		let lookahead_update_interval = config.lookahead_update_interval;
 		let constraint_capabilities = ConstraintCapabilities { constraint_types: config.constraint_capabilities };
-		Self {
+		let trie_cache = Arc::new(TransactionTrieCache::new(config.trie_cache_size));
+		let block_slot_grace_ms = config.block_slot_grace_ms;
+		let head_cache = config
+			.execution_client_url
+			.map(|url| HeadCache::new(ProviderBuilder::new().network::<Ethereum>().connect_http(url).erased()));
+		let receiver_auth_guard = Arc::new(ReplayGuard::new(config.receiver_auth_window_ms));
+		let receiver_auth = config.receiver_auth;
+		let trusted_delegates = config
+			.trusted_delegates
+			.map(|delegates| {
+				delegates
+					.iter()
+					.map(|delegate| {
+						decode_pubkey(delegate.as_str())
+							.wrap_err_with(|| format!("Failed to decode trusted delegate {}", delegate))
+					})
+					.collect::<Result<Vec<_>>>()
+			})
+			.transpose()?;
+
+		// Constraints of an advertised type without a handler are rejected
+		let constraint_registry = default_registry();
+		let supported = constraint_registry.constraint_types();
+		for constraint_type in &constraint_capabilities.constraint_types {
+			if !supported.contains(constraint_type) {
+				warn!(
+					"Constraint type {} is advertised but has no handler, its constraints will be rejected",
+					constraint_type
+				);
+			}
+		}
+		let urc_indexer = config
+			.urc
+			.map(|urc| UrcIndexer::new(urc).map(Arc::new))
+			.transpose()
+			.wrap_err("Failed to create URC indexer")?;
+
+		Ok(Self {
 			db,
 			host,
 			port,
 			beacon_client,
+			slot_clock,
+			lookahead_cache,
 			chain,
 			lookahead_update_interval,
-			downstream_relay_client,
+			downstream_relays,
+			downstream_health_check_interval,
 			constraint_capabilities,
-		}
+			receipt_signer,
+			accepting_constraints: Arc::new(AtomicBool::new(!drained)),
+			metrics_port,
+			admin_port,
+			admin_api_key,
+			block_slot_grace_ms,
+			head_cache,
+			trie_cache,
+			receiver_auth_guard,
+			receiver_auth,
+			constraint_registry,
+			trusted_delegates,
+			urc_indexer,
+		})
 	}
 }
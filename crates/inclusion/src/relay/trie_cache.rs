@@ -0,0 +1,115 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use alloy::consensus::TxEnvelope;
+use alloy::primitives::B256;
+
+use crate::proofs::TransactionsRoot;
+use crate::relay::metrics::{CACHE_HIT, CACHE_MISS, RELAY_TRANSACTION_TRIE_CACHE_TOTAL};
+
+#[derive(Default)]
+struct CachedTries {
+	roots: HashMap<(u64, B256), Arc<TransactionsRoot>>,
+	/// Keys in insertion order, the oldest is evicted first
+	order: VecDeque<(u64, B256)>,
+}
+
+/// Transactions roots of recently submitted blocks, keyed by slot and block hash
+///
+/// Builders resubmit the same block and dry-run it on `/blocks_with_proofs/validate`, so the root is
+/// computed once per block instead of once per request.
+/// Proofs are verified against the root alone, the relay never builds the trie of a block.
+pub struct TransactionTrieCache {
+	capacity: usize,
+	cached: Mutex<CachedTries>,
+}
+
+impl TransactionTrieCache {
+	/// A cache holding the roots of up to `capacity` blocks, caching is disabled when zero
+	pub fn new(capacity: usize) -> Self {
+		Self { capacity, cached: Mutex::new(CachedTries::default()) }
+	}
+
+	/// Get the transactions root of a block, computing and caching it on a miss
+	/// The block hash is claimed by the submitter, so a cached root is only reused when it is over the
+	/// same transactions
+	pub fn get_or_build(&self, slot: u64, block_hash: B256, transactions: &[TxEnvelope]) -> Arc<TransactionsRoot> {
+		let key = (slot, block_hash);
+		let cached = self.cached.lock().expect("trie cache lock poisoned").roots.get(&key).cloned();
+		if let Some(root) = cached
+			&& root.tx_hashes().iter().eq(transactions.iter().map(|tx| tx.hash()))
+		{
+			RELAY_TRANSACTION_TRIE_CACHE_TOTAL.with_label_values(&[CACHE_HIT]).inc();
+			return root;
+		}
+		RELAY_TRANSACTION_TRIE_CACHE_TOTAL.with_label_values(&[CACHE_MISS]).inc();
+
+		// Computed outside the lock so other submissions are not held up
+		let root = Arc::new(TransactionsRoot::new(transactions));
+		if self.capacity == 0 {
+			return root;
+		}
+
+		let mut cached = self.cached.lock().expect("trie cache lock poisoned");
+		if cached.roots.insert(key, root.clone()).is_none() {
+			cached.order.push_back(key);
+		}
+		while cached.order.len() > self.capacity {
+			if let Some(evicted) = cached.order.pop_front() {
+				cached.roots.remove(&evicted);
+			}
+		}
+		root
+	}
+
+	/// Number of cached roots
+	pub fn len(&self) -> usize {
+		self.cached.lock().expect("trie cache lock poisoned").roots.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::types::InclusionPayload;
+
+	fn transactions(count: usize) -> Vec<TxEnvelope> {
+		(0..count).map(|_| InclusionPayload::random().decode_transaction().unwrap()).collect()
+	}
+
+	#[test]
+	fn test_trie_cache_reuses_and_evicts() {
+		let cache = TransactionTrieCache::new(2);
+		let block = transactions(3);
+		let block_hash = B256::random();
+
+		let first = cache.get_or_build(10, block_hash, &block);
+		let second = cache.get_or_build(10, block_hash, &block);
+		assert!(Arc::ptr_eq(&first, &second));
+
+		// A different block claiming the same hash gets its own root
+		let spoofed = transactions(3);
+		let rebuilt = cache.get_or_build(10, block_hash, &spoofed);
+		assert!(!Arc::ptr_eq(&first, &rebuilt));
+		assert_eq!(rebuilt.tx_hashes(), spoofed.iter().map(|tx| *tx.hash()).collect::<Vec<_>>().as_slice());
+		assert_eq!(cache.len(), 1);
+
+		cache.get_or_build(11, B256::random(), &transactions(1));
+		cache.get_or_build(12, B256::random(), &transactions(1));
+		assert_eq!(cache.len(), 2);
+		let evicted = cache.get_or_build(10, block_hash, &spoofed);
+		assert!(!Arc::ptr_eq(&rebuilt, &evicted));
+	}
+
+	#[test]
+	fn test_trie_cache_disabled() {
+		let cache = TransactionTrieCache::new(0);
+		let block = transactions(1);
+		cache.get_or_build(10, B256::random(), &block);
+		assert!(cache.is_empty());
+	}
+}
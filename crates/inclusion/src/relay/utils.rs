@@ -1,23 +1,34 @@
-use alloy::primitives::Address;
+use alloy::consensus::TxEnvelope;
+use alloy::primitives::{Address, B256, Bytes};
 use alloy::rpc::types::beacon::BlsPublicKey;
+use alloy::signers::{SignerSync, local::PrivateKeySigner};
 use common::storage::DatabaseContext;
 use eyre::{Result, eyre};
 use tracing::info;
 
 use commit_boost::prelude::Chain;
+use constraints::error::ConstraintsError;
+use constraints::registry::ConstraintRegistry;
 use constraints::types::{
-	Constraint, ConstraintProofs, ConstraintsMessage, Delegation, SignedConstraints, SignedDelegation,
+	Constraint, ConstraintProofResult, ConstraintProofs, ConstraintsMessage, Delegation, DelegationOffer,
+	ProofValidationReport, Receipt, SignedConstraints, SignedDelegation, SignedDelegationOffer, SignedReceipt,
 	SubmitBlockRequestWithProofs,
 };
-use lookahead::utils::current_slot;
+use lookahead::slot_clock::SlotClock;
 use proposer::storage::DelegationsDbExt;
-use signing::signer::verify_bls;
-use urc::utils::{get_constraints_message_signing_root, get_delegation_signing_root};
+use rayon::prelude::*;
+use signing::signer::{BlsMessage, verify_bls, verify_bls_batch};
+use urc::indexer::UrcIndexer;
+use urc::utils::{
+	get_constraints_message_signing_root, get_delegation_offer_signing_root, get_delegation_signing_root,
+	get_receipt_signing_root,
+};
 
-use crate::constants::{INCLUSION_CONSTRAINT_TYPE, MAX_CONSTRAINTS_PER_SLOT};
-use crate::proofs::{InclusionProof, verify_constraints};
+use crate::constants::{LOOKAHEAD_WINDOW_SIZE, MAX_CONSTRAINTS_PER_SLOT};
+use crate::handlers::default_registry;
+use crate::proofs::{ProofVerifier, TransactionsRoot};
+use crate::relay::trie_cache::TransactionTrieCache;
 use crate::storage::LookaheadDbExt;
-use crate::types::InclusionPayload;
 
 /// Verify BLS signature on a SignedConstraints message using the delegate public key from the message
 pub fn verify_constraints_signature(signed_constraints: &SignedConstraints, chain: &Chain) -> Result<()> {
@@ -51,127 +62,469 @@ pub fn verify_delegation_signature(signed_delegation: &SignedDelegation, chain:
 	)
 }
 
+/// Verify BLS signature on a SignedDelegationOffer message using the delegate public key from the message
+pub fn verify_delegation_offer_signature(signed_offer: &SignedDelegationOffer, chain: &Chain) -> Result<()> {
+	let signing_root = get_delegation_offer_signing_root(&signed_offer.message);
+
+	verify_bls(
+		chain.clone(),
+		&signed_offer.message.delegate,
+		&signing_root,
+		&signed_offer.signature,
+		&signed_offer.signing_id,
+		signed_offer.nonce,
+	)
+}
+
+/// BLS message of a SignedConstraints, signed by the delegate
+pub fn constraints_bls_message(signed_constraints: &SignedConstraints) -> Result<BlsMessage> {
+	Ok(BlsMessage {
+		public_key: signed_constraints.message.delegate,
+		message_hash: get_constraints_message_signing_root(&signed_constraints.message)?,
+		signature: signed_constraints.signature,
+		module_signing_id: signed_constraints.signing_id,
+		nonce: signed_constraints.nonce,
+	})
+}
+
+/// BLS message of a SignedDelegation, signed by the proposer
+pub fn delegation_bls_message(signed_delegation: &SignedDelegation) -> Result<BlsMessage> {
+	Ok(BlsMessage {
+		public_key: signed_delegation.message.proposer,
+		message_hash: get_delegation_signing_root(&signed_delegation.message)?,
+		signature: signed_delegation.signature,
+		module_signing_id: signed_delegation.signing_id,
+		nonce: signed_delegation.nonce,
+	})
+}
+
+/// Verify the signatures of many delegations and constraints messages with one batch verification
+/// Errors if any signature is invalid, use `verify_delegation_signature` and `verify_constraints_signature`
+/// to find the invalid ones
+pub fn verify_signatures_batch(
+	delegations: &[SignedDelegation],
+	constraints: &[SignedConstraints],
+	chain: &Chain,
+) -> Result<()> {
+	let messages = delegations
+		.iter()
+		.map(delegation_bls_message)
+		.chain(constraints.iter().map(constraints_bls_message))
+		.collect::<Result<Vec<_>>>()?;
+	verify_bls_batch(*chain, &messages)
+}
+
+/// Sign a receipt acknowledging that the relay accepted the message with the given signing root
+pub fn sign_receipt(message_hash: B256, timestamp_ms: u64, signer: &PrivateKeySigner) -> Result<SignedReceipt> {
+	let receipt = Receipt { message_hash, timestamp_ms };
+	let signature = signer.sign_hash_sync(&get_receipt_signing_root(&receipt))?;
+	Ok(SignedReceipt { message: receipt, signer: signer.address(), signature })
+}
+
+/// Verify that a receipt was signed by the expected relay address
+pub fn verify_receipt_signature(receipt: &SignedReceipt, expected_signer: &Address) -> Result<()> {
+	let signing_root = get_receipt_signing_root(&receipt.message);
+	let recovered = receipt.signature.recover_address_from_prehash(&signing_root)?;
+
+	if recovered != receipt.signer || recovered != *expected_signer {
+		return Err(eyre!("Receipt signed by {}, expected {}", recovered, expected_signer));
+	}
+
+	Ok(())
+}
+
 /// Validate delegation message structure
-pub fn validate_delegation_message(delegation: &Delegation, chain: &Chain) -> Result<()> {
+pub fn validate_delegation_message(delegation: &Delegation, slot_clock: &SlotClock) -> Result<(), ConstraintsError> {
 	// Check that committer address is not zero
 	if delegation.committer == Address::ZERO {
-		return Err(eyre!("Invalid committer address"));
+		return Err(ConstraintsError::InvalidRequest("Invalid committer address".to_string()));
 	}
 
 	// Check that the delegation slot has not already elapsed
-	if delegation.slot <= current_slot(chain) {
-		return Err(eyre!("Delegation slot has already elapsed"));
+	if delegation.slot <= slot_clock.current_slot() {
+		return Err(ConstraintsError::InvalidRequest("Delegation slot has already elapsed".to_string()));
+	}
+
+	Ok(())
+}
+
+/// Validate a delegation offer
+/// Checks that the committer is set, that the slot is upcoming but within the lookahead window, and that every
+/// offered constraint type is supported by the relay
+pub fn validate_delegation_offer(
+	offer: &DelegationOffer,
+	slot_clock: &SlotClock,
+	registry: &ConstraintRegistry,
+) -> Result<(), ConstraintsError> {
+	if offer.committer == Address::ZERO {
+		return Err(ConstraintsError::InvalidRequest("Invalid committer address".to_string()));
+	}
+
+	let current_slot = slot_clock.current_slot();
+	if offer.slot <= current_slot {
+		return Err(ConstraintsError::InvalidRequest("Offer slot has already elapsed".to_string()));
+	}
+	if offer.slot > current_slot + LOOKAHEAD_WINDOW_SIZE {
+		return Err(ConstraintsError::InvalidRequest(format!(
+			"Offer slot {} is more than {} slots ahead",
+			offer.slot, LOOKAHEAD_WINDOW_SIZE
+		)));
+	}
+
+	let supported = registry.constraint_types();
+	if let Some(unsupported) =
+		offer.constraint_types.iter().find(|constraint_type| !supported.contains(constraint_type))
+	{
+		return Err(ConstraintsError::InvalidRequest(format!("Unsupported constraint type {}", unsupported)));
 	}
 
 	Ok(())
 }
 
 /// Validate a constraints message
-/// Checks that the constraints slot has not already elapsed
-pub fn validate_constraints_message(message: &ConstraintsMessage, chain: &Chain) -> Result<()> {
+/// Checks that the constraints slot has not already elapsed and that every constraint is of a supported
+/// type with a well formed payload
+pub fn validate_constraints_message(
+	message: &ConstraintsMessage,
+	slot_clock: &SlotClock,
+	registry: &ConstraintRegistry,
+) -> Result<(), ConstraintsError> {
 	// Check that the constraints slot has not already elapsed
-	if message.slot <= current_slot(chain) {
-		return Err(eyre::eyre!("Constraints slot has already elapsed"));
+	if message.slot <= slot_clock.current_slot() {
+		return Err(ConstraintsError::InvalidRequest("Constraints slot has already elapsed".to_string()));
+	}
+
+	for (index, constraint) in message.constraints.iter().enumerate() {
+		registry
+			.validate(constraint)
+			.map_err(|e| ConstraintsError::InvalidRequest(format!("Invalid constraint at index {}: {}", index, e)))?;
 	}
 
 	Ok(())
 }
 
+/// Validate that a block is for the current or the next slot, or for the previous slot within `grace_ms` of the
+/// current slot's start, so stale and far-future submissions are rejected
+pub fn validate_block_slot(slot: u64, slot_clock: &SlotClock, grace_ms: u64) -> Result<(), ConstraintsError> {
+	let current_slot = slot_clock.current_slot();
+	if slot == current_slot || slot == current_slot + 1 {
+		return Ok(());
+	}
+	if slot + 1 == current_slot && slot_clock.now_ms() < slot_clock.slot_start_ms(current_slot) + grace_ms {
+		return Ok(());
+	}
+
+	Err(ConstraintsError::InvalidRequest(format!(
+		"Block slot {} is outside the submission window of current slot {}",
+		slot, current_slot
+	)))
+}
+
 /// Validate that the given public key is the scheduled proposer for the given slot
 /// Reads from the proposer lookahead stored in the database
-pub fn validate_is_proposer(pubkey: &BlsPublicKey, slot: u64, db: &DatabaseContext) -> Result<()> {
+pub fn validate_is_proposer(pubkey: &BlsPublicKey, slot: u64, db: &DatabaseContext) -> Result<(), ConstraintsError> {
 	// Look up the expected proposer from the lookahead database
 	match db.get_proposer_bls_key(slot)? {
 		Some(expected_proposer) => {
 			// Compare the provided pubkey with the expected proposer
 			if pubkey == &expected_proposer {
+				// A slashed or exited proposer cannot produce a valid block for the slot
+				if let Some(status) = db.get_proposer_status(slot)? {
+					return Err(ConstraintsError::Forbidden(format!(
+						"Proposer of slot {} is {:?}, rejecting validation",
+						slot, status
+					)));
+				}
 				info!("Proposer validation successful for slot {}", slot);
 				Ok(())
 			} else {
-				Err(eyre!(
+				Err(ConstraintsError::Forbidden(format!(
 					"Proposer validation failed for slot {}: provided pubkey does not match expected proposer",
 					slot
-				))
+				)))
 			}
 		}
-		None => Err(eyre!("No proposer lookahead found for slot {}, rejecting validation", slot)),
+		None => Err(ConstraintsError::Forbidden(format!(
+			"No proposer lookahead found for slot {}, rejecting validation",
+			slot
+		))),
 	}
 }
 
 /// Validate that the supplied gateway public key is delegated to for the given slot
-pub fn validate_is_gateway(gateway: &BlsPublicKey, slot: u64, db: &DatabaseContext) -> Result<()> {
-	// Get the delegation for the given slot
-	let delegation = db.get_delegation(slot)?.ok_or(eyre!("No delegation found for slot {}", slot))?;
+/// A slot may be delegated to several gateways, so the delegation is looked up by delegate
+pub fn validate_is_gateway(gateway: &BlsPublicKey, slot: u64, db: &DatabaseContext) -> Result<(), ConstraintsError> {
+	if db.get_delegate_delegation(slot, gateway)?.is_none() {
+		return Err(ConstraintsError::Forbidden(format!(
+			"No delegation found for slot {} and the supplied gateway public key",
+			slot
+		)));
+	}
+
+	Ok(())
+}
+
+/// Validate that the delegate is one of the relay's trusted gateways, if the relay only trusts some
+pub fn validate_is_trusted_delegate(
+	delegate: &BlsPublicKey,
+	trusted_delegates: Option<&[BlsPublicKey]>,
+) -> Result<(), ConstraintsError> {
+	if let Some(trusted_delegates) = trusted_delegates
+		&& !trusted_delegates.contains(delegate)
+	{
+		return Err(ConstraintsError::Forbidden(format!(
+			"Delegate {} is not a trusted gateway of this relay",
+			delegate
+		)));
+	}
+
+	Ok(())
+}
 
-	// Check that the delegation is for the expected gateway
-	if delegation.message.delegate != *gateway {
-		return Err(eyre!("Delegation for slot {} is not for the supplied gateway public key", slot));
+/// Validate that a delegation offer comes from a gateway the relay can vouch for, as offers are made for slots
+/// nobody is delegated to yet
+/// Permissioned relays take the offers of their trusted gateways, and with URC checks configured the delegate key
+/// must be registered and bonded. A relay with neither accepts no offers, so unbonded keys cannot outbid gateways.
+pub async fn validate_is_offering_gateway(
+	delegate: &BlsPublicKey,
+	trusted_delegates: Option<&[BlsPublicKey]>,
+	urc_indexer: Option<&UrcIndexer>,
+) -> Result<(), ConstraintsError> {
+	validate_is_trusted_delegate(delegate, trusted_delegates)?;
+	match urc_indexer {
+		Some(urc_indexer) => {
+			urc_indexer.check_operator(delegate).await.map_err(|e| ConstraintsError::Forbidden(e.to_string()))?;
+		}
+		None if trusted_delegates.is_none() => {
+			return Err(ConstraintsError::Forbidden(
+				"Delegation offers are only accepted from trusted or URC registered gateways".to_string(),
+			));
+		}
+		None => {}
 	}
 
 	Ok(())
 }
 
+/// Validate block proofs against the merged constraints of every delegate for the slot, returning the outcome of
+/// each constraint's proof. The block's transactions root is taken from the cache when the block was seen before
+/// The checks are those of `build_proof_validation_report`, failing on the first error it would report
 pub fn handle_proof_validation(
 	block_request: &SubmitBlockRequestWithProofs,
-	signed_constraints: SignedConstraints,
-) -> Result<()> {
-	if block_request.proofs.constraint_types.len() != block_request.proofs.payloads.len() {
-		return Err(eyre!("Constraint types and payloads length mismatch"));
+	constraints: &[Constraint],
+	trie_cache: &TransactionTrieCache,
+	registry: &ConstraintRegistry,
+) -> Result<Vec<ConstraintProofResult>, ConstraintsError> {
+	let transactions = block_request.transactions()?;
+	let transactions_root = trie_cache.get_or_build(block_request.slot(), block_request.block_hash(), &transactions);
+	let checks = check_proofs(
+		&transactions_root,
+		&transactions,
+		&block_request.blob_versioned_hashes(),
+		&block_request.proofs,
+		constraints,
+		registry,
+	);
+	if let Some(error) = checks.error {
+		return Err(error);
 	}
 
-	if block_request.proofs.constraint_types.len() > MAX_CONSTRAINTS_PER_SLOT {
-		return Err(eyre!(
-			"Too many proofs: {} exceeds maximum of {}",
-			block_request.proofs.constraint_types.len(),
-			MAX_CONSTRAINTS_PER_SLOT
-		));
+	let results = constraints
+		.iter()
+		.zip(checks.outcomes)
+		.enumerate()
+		.map(|(index, (constraint, outcome))| {
+			outcome.map(|()| ConstraintProofResult {
+				index,
+				constraint_type: constraint.constraint_type,
+				satisfied: true,
+				error: None,
+			})
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	info!("Proofs verified successfully");
+	Ok(results)
+}
+
+/// Dry-run the proof validation of `handle_proof_validation`, checking every constraint instead of
+/// stopping at the first failure. The report is valid exactly when `handle_proof_validation` succeeds
+pub fn build_proof_validation_report(
+	block_request: &SubmitBlockRequestWithProofs,
+	constraints: &[Constraint],
+	trie_cache: &TransactionTrieCache,
+	registry: &ConstraintRegistry,
+) -> ProofValidationReport {
+	match block_request.transactions() {
+		Ok(transactions) => validate_proofs_against_root(
+			block_request.slot(),
+			&trie_cache.get_or_build(block_request.slot(), block_request.block_hash(), &transactions),
+			&transactions,
+			&block_request.blob_versioned_hashes(),
+			&block_request.proofs,
+			constraints,
+			registry,
+		),
+		Err(e) => ProofValidationReport {
+			slot: block_request.slot(),
+			valid: false,
+			error: Some(format!("Failed to extract transactions from block: {e}")),
+			constraints: vec![],
+		},
 	}
+}
 
-	// We first verify the proof corresponds to the constraints
-	verify_proof_completeness(&block_request.proofs, &signed_constraints.message.constraints)?;
-	info!("Proofs correspond to constraints");
+/// Check each constraint against its proof, the block transactions and the versioned hashes of the block's blobs
+/// Constraints are checked by the handlers of the default registry
+pub fn validate_proofs_against_transactions(
+	slot: u64,
+	transactions: &[TxEnvelope],
+	blob_versioned_hashes: &[B256],
+	proofs: &ConstraintProofs,
+	constraints: &[Constraint],
+) -> ProofValidationReport {
+	validate_proofs_against_root(
+		slot,
+		&TransactionsRoot::new(transactions),
+		transactions,
+		blob_versioned_hashes,
+		proofs,
+		constraints,
+		&default_registry(),
+	)
+}
 
-	// We then verify the validity of the proofs
-	// For now we assume all constraints are inclusion constraints
-	verify_constraints(&block_request.message, &block_request.proofs)?;
+/// Check each constraint against its proof, the transactions root of the block and the versioned hashes of its blobs
+/// The transactions are only read again for legacy proofs, see `ProofVerifier::with_legacy_proofs`
+pub fn validate_proofs_against_root(
+	slot: u64,
+	transactions_root: &TransactionsRoot,
+	transactions: &[TxEnvelope],
+	blob_versioned_hashes: &[B256],
+	proofs: &ConstraintProofs,
+	constraints: &[Constraint],
+	registry: &ConstraintRegistry,
+) -> ProofValidationReport {
+	let checks = check_proofs(transactions_root, transactions, blob_versioned_hashes, proofs, constraints, registry);
+	let results = constraints
+		.iter()
+		.zip(checks.outcomes)
+		.enumerate()
+		.map(|(index, (constraint, outcome))| ConstraintProofResult {
+			index,
+			constraint_type: constraint.constraint_type,
+			satisfied: outcome.is_ok(),
+			error: outcome.err().map(|e| e.to_string()),
+		})
+		.collect::<Vec<_>>();
+
+	let error = checks.error.map(|e| e.to_string());
+	let valid = error.is_none() && results.iter().all(|result| result.satisfied);
+	ProofValidationReport { slot, valid, error, constraints: results }
+}
 
-	info!("Proofs verified successfully");
+/// Outcome of checking the proofs of a block: a failure of the proofs as a whole, and the outcome of each constraint
+struct ProofChecks {
+	error: Option<ConstraintsError>,
+	outcomes: Vec<Result<(), ConstraintsError>>,
+}
 
-	Ok(())
+/// Check every constraint against its proof, shared by the submission and the dry-run so they always agree
+fn check_proofs(
+	transactions_root: &TransactionsRoot,
+	transactions: &[TxEnvelope],
+	blob_versioned_hashes: &[B256],
+	proofs: &ConstraintProofs,
+	constraints: &[Constraint],
+	registry: &ConstraintRegistry,
+) -> ProofChecks {
+	// Structural failures are reported once rather than per constraint
+	let mut error = if proofs.constraint_types.len() != proofs.payloads.len() {
+		Some(ConstraintsError::InvalidProof("Constraint types and payloads length mismatch".to_string()))
+	} else if proofs.constraint_types.len() > MAX_CONSTRAINTS_PER_SLOT {
+		Some(ConstraintsError::InvalidProof(format!(
+			"Too many proofs: {} exceeds maximum of {}",
+			proofs.constraint_types.len(),
+			MAX_CONSTRAINTS_PER_SLOT
+		)))
+	} else if proofs.constraint_types.len() != constraints.len() {
+		Some(ConstraintsError::InvalidProof(format!(
+			"Constraint types length mismatch, received {} constraints, expected {}",
+			proofs.constraint_types.len(),
+			constraints.len()
+		)))
+	} else {
+		None
+	};
+
+	// The transactions root, and the shared nodes of a multiproof, serve every proof
+	let verifier = match transactions_root.verifier(proofs) {
+		Ok(verifier) => {
+			Some(verifier.with_legacy_proofs(proofs, transactions).with_blob_versioned_hashes(blob_versioned_hashes))
+		}
+		Err(e) => {
+			error.get_or_insert(ConstraintsError::InvalidProof(format!("Invalid multiproof: {e}")));
+			None
+		}
+	};
+
+	let outcomes = constraints
+		.par_iter()
+		.enumerate()
+		.map(|(index, constraint)| match proofs.constraint_types.get(index).zip(proofs.payloads.get(index)) {
+			Some((constraint_type, proof)) => {
+				check_constraint_proof(registry, constraint, *constraint_type, proof, verifier.as_ref())
+			}
+			None => Err(ConstraintsError::InvalidProof("Missing proof".to_string())),
+		})
+		.collect::<Vec<_>>();
+
+	ProofChecks { error, outcomes }
+}
+
+/// Checks that a single proof is for the constraint and holds against the block
+fn check_constraint_proof(
+	registry: &ConstraintRegistry,
+	constraint: &Constraint,
+	constraint_type: u64,
+	proof: &Bytes,
+	verifier: Option<&ProofVerifier<'_>>,
+) -> Result<(), ConstraintsError> {
+	if constraint_type != constraint.constraint_type {
+		return Err(ConstraintsError::InvalidProof("Constraint types mismatch".to_string()));
+	}
+	registry.check_proof(constraint, proof)?;
+	let verifier = verifier.ok_or_else(|| ConstraintsError::InvalidProof("Invalid multiproof".to_string()))?;
+	registry.verify_proof(constraint, proof, verifier.for_proof(proof)?)
 }
 
 /// Verifies that the proofs cover all the constraints
 /// Assumes that the constraints are sorted by constraint type
-pub fn verify_proof_completeness(proofs: &ConstraintProofs, constraints: &[Constraint]) -> Result<()> {
+pub fn verify_proof_completeness(
+	proofs: &ConstraintProofs,
+	constraints: &[Constraint],
+	registry: &ConstraintRegistry,
+) -> Result<(), ConstraintsError> {
+	if proofs.constraint_types.len() != proofs.payloads.len() {
+		return Err(ConstraintsError::InvalidProof("Constraint types and payloads length mismatch".to_string()));
+	}
+
 	if proofs.constraint_types.len() != constraints.len() {
-		return Err(eyre!(
+		return Err(ConstraintsError::InvalidProof(format!(
 			"Constraint types length mismatch, received {} constraints, expected {}",
 			proofs.constraint_types.len(),
 			constraints.len()
-		));
+		)));
 	}
 
 	let matching_constraint_types =
 		proofs.constraint_types.iter().zip(constraints.iter()).all(|(n, t)| *n == t.constraint_type);
 
 	if !matching_constraint_types {
-		return Err(eyre!("Constraint types mismatch"));
+		return Err(ConstraintsError::InvalidProof("Constraint types mismatch".to_string()));
 	}
 
 	for (proof, constraint) in proofs.payloads.iter().zip(constraints.iter()) {
-		match constraint.constraint_type {
-			INCLUSION_CONSTRAINT_TYPE => {
-				let proof = InclusionProof::from_bytes(proof)?;
-				let payload = InclusionPayload::abi_decode(&constraint.payload)?;
-				let tx_hash = payload.tx_hash()?;
-				if proof.tx_hash != tx_hash {
-					return Err(eyre!("Transaction hash mismatch"));
-				}
-			}
-			_ => {
-				return Err(eyre!("Unsupported constraint type {:?}", constraint.constraint_type));
-			}
-		}
+		registry.check_proof(constraint, proof)?;
 	}
 	Ok(())
 }
@@ -179,9 +532,197 @@ pub fn verify_proof_completeness(proofs: &ConstraintProofs, constraints: &[Const
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::constants::{
+		BLOB_CONSTRAINT_TYPE, BUNDLE_CONSTRAINT_TYPE, EXCLUSION_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE,
+		ORDERING_CONSTRAINT_TYPE,
+	};
+	use crate::proofs::TransactionTrieBuilder;
+	use crate::types::{
+		BlobConstraintPayload, BundleConstraintPayload, ExclusionPayload, InclusionPayload, OrderingPayload,
+	};
 	use alloy::primitives::Bytes;
 	use alloy::primitives::hex;
 	use alloy::rpc::types::beacon::BlsPublicKey;
+	use lookahead::clock::ManualClock;
+	use proptest::prelude::*;
+	use std::sync::Arc;
+
+	fn inclusion_constraint(payload: &InclusionPayload) -> Constraint {
+		Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payload.abi_encode().unwrap() }
+	}
+
+	#[test]
+	fn test_proof_validation_report_all_satisfied() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let constraints = payloads.iter().map(inclusion_constraint).collect::<Vec<_>>();
+
+		let tx_hashes = transactions.iter().map(|tx| *tx.hash()).collect::<Vec<_>>();
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_batch(&tx_hashes).unwrap();
+
+		let report = validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints);
+		assert!(report.valid);
+		assert!(report.error.is_none());
+		assert_eq!(report.constraints.len(), 2);
+		assert!(report.constraints.iter().all(|result| result.satisfied));
+	}
+
+	#[test]
+	fn test_proof_validation_report_flags_missing_proof() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let constraints = payloads.iter().map(inclusion_constraint).collect::<Vec<_>>();
+
+		// Only prove the first constraint
+		let proofs =
+			TransactionTrieBuilder::build(&transactions).unwrap().prove_batch(&[*transactions[0].hash()]).unwrap();
+
+		let report = validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints);
+		assert!(!report.valid);
+		assert!(report.error.is_some());
+		assert!(report.constraints[0].satisfied);
+		assert!(!report.constraints[1].satisfied);
+		assert_eq!(report.constraints[1].error.as_deref(), Some("invalid proof: Missing proof"));
+	}
+
+	#[test]
+	fn test_proof_validation_report_flags_mismatched_proof() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let constraints = payloads.iter().map(inclusion_constraint).collect::<Vec<_>>();
+
+		// Prove the constraints in the wrong order
+		let tx_hashes = [*transactions[1].hash(), *transactions[0].hash()];
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_batch(&tx_hashes).unwrap();
+
+		let report = validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints);
+		assert!(!report.valid);
+		assert!(report.error.is_none());
+		assert!(report.constraints.iter().all(|result| !result.satisfied));
+	}
+
+	#[test]
+	fn test_proof_validation_report_exclusion() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random()];
+		let excluded = InclusionPayload::random();
+		let constraints = vec![Constraint {
+			constraint_type: EXCLUSION_CONSTRAINT_TYPE,
+			payload: ExclusionPayload { slot: 10, tx_hash: excluded.tx_hash().unwrap() }.abi_encode().unwrap(),
+		}];
+
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		let report = validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints);
+		assert!(report.valid);
+
+		// The same proof does not hold once the block includes the excluded transaction
+		let mut censored = transactions.clone();
+		censored.push(excluded.decode_transaction().unwrap());
+		let report = validate_proofs_against_transactions(10, &censored, &[], &proofs, &constraints);
+		assert!(!report.valid);
+		assert!(!report.constraints[0].satisfied);
+	}
+
+	#[test]
+	fn test_proof_validation_report_ordering() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let ordering_constraint = |tx_hash, max_index| Constraint {
+			constraint_type: ORDERING_CONSTRAINT_TYPE,
+			payload: OrderingPayload { slot: 10, tx_hash, max_index }.abi_encode().unwrap(),
+		};
+
+		let constraints = vec![ordering_constraint(payloads[0].tx_hash().unwrap(), 0)];
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		let report = validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints);
+		assert!(report.valid);
+
+		// A valid inclusion proof does not satisfy the constraint when the transaction is too deep in the block
+		let constraints = vec![ordering_constraint(payloads[2].tx_hash().unwrap(), 1)];
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		let report = validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints);
+		assert!(!report.valid);
+		assert!(report.constraints[0].error.as_ref().unwrap().contains("beyond the committed maximum index"));
+	}
+
+	#[test]
+	fn test_proof_validation_report_blob() {
+		let available = alloy::eips::eip4844::kzg_to_versioned_hash(&[0x11; 48]);
+		let missing = alloy::eips::eip4844::kzg_to_versioned_hash(&[0x22; 48]);
+		let blob_tx = InclusionPayload::random_blob(vec![available]);
+		let transactions = [InclusionPayload::random(), blob_tx.clone()]
+			.iter()
+			.map(|p| p.decode_transaction().unwrap())
+			.collect::<Vec<_>>();
+		let constraints = vec![Constraint {
+			constraint_type: BLOB_CONSTRAINT_TYPE,
+			payload: BlobConstraintPayload::from_inclusion_payload(&blob_tx).unwrap().abi_encode().unwrap(),
+		}];
+
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		assert!(validate_proofs_against_transactions(10, &transactions, &[available], &proofs, &constraints).valid);
+
+		// The transaction is in the block but its blob is not in the blobs bundle
+		let report = validate_proofs_against_transactions(10, &transactions, &[missing], &proofs, &constraints);
+		assert!(!report.valid);
+		assert!(report.constraints[0].error.as_ref().unwrap().contains("missing from the blobs bundle"));
+	}
+
+	#[test]
+	fn test_proof_validation_report_bundle() {
+		let payloads = [InclusionPayload::random(), InclusionPayload::random(), InclusionPayload::random()];
+		let transactions = payloads.iter().map(|p| p.decode_transaction().unwrap()).collect::<Vec<_>>();
+		let bundle_constraint = |tx_hashes| Constraint {
+			constraint_type: BUNDLE_CONSTRAINT_TYPE,
+			payload: BundleConstraintPayload { slot: 10, tx_hashes }.abi_encode().unwrap(),
+		};
+
+		let constraints = vec![bundle_constraint(vec![payloads[0].tx_hash().unwrap(), payloads[1].tx_hash().unwrap()])];
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		let report = validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints);
+		assert!(report.valid);
+
+		// Valid inclusion proofs do not satisfy the bundle when another transaction sits in between
+		let constraints = vec![bundle_constraint(vec![payloads[0].tx_hash().unwrap(), payloads[2].tx_hash().unwrap()])];
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		let report = validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints);
+		assert!(!report.valid);
+		assert!(report.constraints[0].error.as_ref().unwrap().contains("not contiguous"));
+
+		// Nor when the order is reversed
+		let constraints = vec![bundle_constraint(vec![payloads[1].tx_hash().unwrap(), payloads[0].tx_hash().unwrap()])];
+		let proofs = TransactionTrieBuilder::build(&transactions).unwrap().prove_constraints(&constraints).unwrap();
+		assert!(!validate_proofs_against_transactions(10, &transactions, &[], &proofs, &constraints).valid);
+	}
+
+	#[test]
+	fn test_sign_and_verify_receipt() {
+		let signer = PrivateKeySigner::random();
+		let message_hash = B256::repeat_byte(0x42);
+
+		let receipt = sign_receipt(message_hash, 1_700_000_000_000, &signer).unwrap();
+		assert_eq!(receipt.message.message_hash, message_hash);
+		assert_eq!(receipt.signer, signer.address());
+		assert!(verify_receipt_signature(&receipt, &signer.address()).is_ok());
+
+		// Wrong expected signer
+		let other = PrivateKeySigner::random();
+		assert!(verify_receipt_signature(&receipt, &other.address()).is_err());
+
+		// Tampered timestamp
+		let mut tampered = receipt.clone();
+		tampered.message.timestamp_ms += 1;
+		assert!(verify_receipt_signature(&tampered, &signer.address()).is_err());
+	}
+
+	#[test]
+	fn test_validate_is_trusted_delegate() {
+		let trusted = [BlsPublicKey::repeat_byte(1), BlsPublicKey::repeat_byte(2)];
+
+		assert!(validate_is_trusted_delegate(&BlsPublicKey::repeat_byte(9), None).is_ok());
+		assert!(validate_is_trusted_delegate(&BlsPublicKey::repeat_byte(2), Some(&trusted)).is_ok());
+		assert!(validate_is_trusted_delegate(&BlsPublicKey::repeat_byte(9), Some(&trusted)).is_err());
+	}
 
 	#[test]
 	fn test_validate_delegation_message_zero_committer() {
@@ -190,7 +731,7 @@ mod tests {
 			"af6e96c0eccd8d4ae868be9299af737855a1b08d57bccb565ea7e69311a30baeebe08d493c3fea97077e8337e95ac5a6",
 		)
 		.unwrap();
-		let chain = Chain::Mainnet;
+		let slot_clock = SlotClock::new(&Chain::Mainnet);
 
 		let delegation = Delegation {
 			proposer: BlsPublicKey::new(valid_bls_key.clone().try_into().unwrap()),
@@ -200,7 +741,7 @@ mod tests {
 			metadata: Bytes::from(vec![0x01, 0x02]),
 		};
 
-		assert!(validate_delegation_message(&delegation, &chain).is_err());
+		assert!(validate_delegation_message(&delegation, &slot_clock).is_err());
 	}
 
 	#[test]
@@ -211,10 +752,10 @@ mod tests {
 		)
 		.unwrap();
 
-		let chain = Chain::Mainnet;
+		let slot_clock = SlotClock::new(&Chain::Mainnet);
 
 		// Get current slot and try to delegate a slot that has already elapsed
-		let current_slot = current_slot(&chain);
+		let current_slot = slot_clock.current_slot();
 
 		let delegation = Delegation {
 			proposer: BlsPublicKey::new(valid_bls_key.clone().try_into().unwrap()),
@@ -224,7 +765,7 @@ mod tests {
 			metadata: Bytes::from(vec![0x01, 0x02]),
 		};
 
-		let result = validate_delegation_message(&delegation, &chain);
+		let result = validate_delegation_message(&delegation, &slot_clock);
 		assert!(result.is_err());
 		assert!(result.unwrap_err().to_string().contains("already elapsed"));
 	}
@@ -237,10 +778,10 @@ mod tests {
 		)
 		.unwrap();
 
-		let chain = Chain::Mainnet;
+		let slot_clock = SlotClock::new(&Chain::Mainnet);
 
 		// Get current slot and try to delegate to a future slot
-		let current_slot = current_slot(&chain);
+		let current_slot = slot_clock.current_slot();
 
 		let delegation = Delegation {
 			proposer: BlsPublicKey::new(valid_bls_key.clone().try_into().unwrap()),
@@ -250,10 +791,104 @@ mod tests {
 			metadata: Bytes::from(vec![0x01, 0x02]),
 		};
 
-		let result = validate_delegation_message(&delegation, &chain);
+		let result = validate_delegation_message(&delegation, &slot_clock);
 		assert!(result.is_ok());
 	}
 
+	#[test]
+	fn test_validate_delegation_message_at_slot_boundary() {
+		let valid_bls_key = hex::decode(
+			"af6e96c0eccd8d4ae868be9299af737855a1b08d57bccb565ea7e69311a30baeebe08d493c3fea97077e8337e95ac5a6",
+		)
+		.unwrap();
+		let clock = Arc::new(ManualClock::default());
+		let slot_clock = SlotClock::new(&Chain::Mainnet).with_clock(clock.clone());
+
+		let delegation = Delegation {
+			proposer: BlsPublicKey::new(valid_bls_key.clone().try_into().unwrap()),
+			delegate: BlsPublicKey::new(valid_bls_key.try_into().unwrap()),
+			committer: "0x1234567890123456789012345678901234567890".parse().unwrap(),
+			slot: 1000,
+			metadata: Bytes::new(),
+		};
+
+		// Accepted until the last millisecond before the slot starts
+		clock.set_ms(slot_clock.slot_start_ms(1000) - 1);
+		assert!(validate_delegation_message(&delegation, &slot_clock).is_ok());
+
+		// Rejected from its first millisecond on
+		clock.advance_ms(1);
+		let result = validate_delegation_message(&delegation, &slot_clock);
+		assert!(result.unwrap_err().to_string().contains("already elapsed"));
+	}
+
+	#[test]
+	fn test_validate_block_slot_window() {
+		let clock = Arc::new(ManualClock::default());
+		let slot_clock = SlotClock::new(&Chain::Mainnet).with_clock(clock.clone());
+		clock.set_ms(slot_clock.slot_start_ms(1000) + 500);
+
+		assert!(validate_block_slot(1000, &slot_clock, 1_000).is_ok());
+		assert!(validate_block_slot(1001, &slot_clock, 1_000).is_ok());
+		assert!(validate_block_slot(1002, &slot_clock, 1_000).is_err());
+		assert!(validate_block_slot(998, &slot_clock, 1_000).is_err());
+
+		// The previous slot is only accepted within the grace window
+		assert!(validate_block_slot(999, &slot_clock, 1_000).is_ok());
+		assert!(validate_block_slot(999, &slot_clock, 500).is_err());
+	}
+
+	#[tokio::test]
+	async fn test_validate_is_offering_gateway() {
+		let trusted = BlsPublicKey::repeat_byte(1);
+		let other = BlsPublicKey::repeat_byte(2);
+
+		assert!(validate_is_offering_gateway(&trusted, Some(&[trusted]), None).await.is_ok());
+		assert!(matches!(
+			validate_is_offering_gateway(&other, Some(&[trusted]), None).await,
+			Err(ConstraintsError::Forbidden(_))
+		));
+
+		// Without trusted gateways or URC checks nobody can vouch for the delegate
+		assert!(matches!(
+			validate_is_offering_gateway(&trusted, None, None).await,
+			Err(ConstraintsError::Forbidden(_))
+		));
+	}
+
+	#[test]
+	fn test_validate_delegation_offer() {
+		let clock = Arc::new(ManualClock::default());
+		let slot_clock = SlotClock::new(&Chain::Mainnet).with_clock(clock.clone());
+		clock.set_ms(slot_clock.slot_start_ms(1000) + 500);
+		let registry = default_registry();
+		let offer = |slot: u64, constraint_types: Vec<u64>| DelegationOffer {
+			delegate: BlsPublicKey::repeat_byte(1),
+			committer: Address::repeat_byte(1),
+			slot,
+			bid_gwei: 1_000,
+			constraint_types,
+		};
+
+		assert!(
+			validate_delegation_offer(&offer(1001, vec![INCLUSION_CONSTRAINT_TYPE]), &slot_clock, &registry).is_ok()
+		);
+		assert!(
+			validate_delegation_offer(&offer(1000 + LOOKAHEAD_WINDOW_SIZE, vec![]), &slot_clock, &registry).is_ok()
+		);
+
+		// Elapsed, too far ahead, or naming a constraint type the relay does not support
+		assert!(validate_delegation_offer(&offer(1000, vec![]), &slot_clock, &registry).is_err());
+		assert!(
+			validate_delegation_offer(&offer(1001 + LOOKAHEAD_WINDOW_SIZE, vec![]), &slot_clock, &registry).is_err()
+		);
+		let unsupported = validate_delegation_offer(&offer(1001, vec![999]), &slot_clock, &registry).unwrap_err();
+		assert!(unsupported.to_string().contains("Unsupported constraint type 999"));
+
+		let no_committer = DelegationOffer { committer: Address::ZERO, ..offer(1001, vec![]) };
+		assert!(validate_delegation_offer(&no_committer, &slot_clock, &registry).is_err());
+	}
+
 	#[test]
 	fn test_validate_constraints_message_slot_elapsed() {
 		// Use a valid BLS public key
@@ -262,10 +897,10 @@ mod tests {
 		)
 		.unwrap();
 
-		let chain = Chain::Mainnet;
+		let slot_clock = SlotClock::new(&Chain::Mainnet);
 
 		// Get current slot and try to create constraints for a slot that has already elapsed
-		let current_slot = current_slot(&chain);
+		let current_slot = slot_clock.current_slot();
 
 		let constraints_message = ConstraintsMessage {
 			proposer: BlsPublicKey::new(valid_bls_key.clone().try_into().unwrap()),
@@ -275,7 +910,7 @@ mod tests {
 			receivers: vec![],
 		};
 
-		let result = validate_constraints_message(&constraints_message, &chain);
+		let result = validate_constraints_message(&constraints_message, &slot_clock, &default_registry());
 		assert!(result.is_err());
 		assert!(result.unwrap_err().to_string().contains("already elapsed"));
 	}
@@ -288,10 +923,10 @@ mod tests {
 		)
 		.unwrap();
 
-		let chain = Chain::Mainnet;
+		let slot_clock = SlotClock::new(&Chain::Mainnet);
 
 		// Get current slot and try to create constraints for the current slot
-		let current_slot = current_slot(&chain);
+		let current_slot = slot_clock.current_slot();
 
 		let constraints_message = ConstraintsMessage {
 			proposer: BlsPublicKey::new(valid_bls_key.clone().try_into().unwrap()),
@@ -301,7 +936,7 @@ mod tests {
 			receivers: vec![],
 		};
 
-		let result = validate_constraints_message(&constraints_message, &chain);
+		let result = validate_constraints_message(&constraints_message, &slot_clock, &default_registry());
 		assert!(result.is_err());
 		assert!(result.unwrap_err().to_string().contains("already elapsed"));
 	}
@@ -314,10 +949,10 @@ mod tests {
 		)
 		.unwrap();
 
-		let chain = Chain::Mainnet;
+		let slot_clock = SlotClock::new(&Chain::Mainnet);
 
 		// Get current slot and try to create constraints for a future slot
-		let current_slot = current_slot(&chain);
+		let current_slot = slot_clock.current_slot();
 
 		let constraints_message = ConstraintsMessage {
 			proposer: BlsPublicKey::new(valid_bls_key.clone().try_into().unwrap()),
@@ -327,7 +962,32 @@ mod tests {
 			receivers: vec![],
 		};
 
-		let result = validate_constraints_message(&constraints_message, &chain);
+		let result = validate_constraints_message(&constraints_message, &slot_clock, &default_registry());
 		assert!(result.is_ok());
 	}
+
+	proptest! {
+		/// Builder supplied proofs that do not line up with the constraints are rejected without panicking
+		#[test]
+		fn test_proof_completeness_rejects_misaligned_proofs(
+			constraint_types in prop::collection::vec(0..8u64, 0..6),
+			proof_types in prop::collection::vec(0..8u64, 0..6),
+			payloads in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..128), 0..6),
+		) {
+			let constraints = constraint_types
+				.iter()
+				.map(|constraint_type| Constraint { constraint_type: *constraint_type, payload: Bytes::new() })
+				.collect::<Vec<_>>();
+			let proofs = ConstraintProofs {
+				constraint_types: proof_types.clone(),
+				payloads: payloads.iter().cloned().map(Bytes::from).collect(),
+				..Default::default()
+			};
+
+			let result = verify_proof_completeness(&proofs, &constraints, &default_registry());
+			if proof_types != constraint_types || payloads.len() != proof_types.len() {
+				prop_assert!(result.is_err());
+			}
+		}
+	}
 }
@@ -1,16 +1,23 @@
-use alloy::primitives::B256;
+use std::collections::HashSet;
+
+use alloy::primitives::{Address, B256};
 use alloy::rpc::types::beacon::BlsPublicKey;
-use commitments::types::SignedCommitment;
-use constraints::types::{Constraint, SignedConstraints};
-use eyre::Result;
+use commitments::types::{CommitmentStatus, SignedCommitment};
+use constraints::types::{
+	AcceptedBlockWithProofs, Constraint, SignedConstraints, SignedDelegationOffer, SignedReceipt,
+};
+use lookahead::types::ValidatorStatus;
 use rocksdb::{Direction, IteratorMode};
 
 use common::storage::{
-	DatabaseContext,
-	db::{DbOp, TypedDbExt, scan_slot_range_kind, slot_prefix},
+	DatabaseContext, Result, StorageError,
+	db::{DbOp, TypedDbExt, delete_slot_range_kind, scan_slot_range_kind, slot_prefix},
 };
 
-use crate::types::SignedCommitmentAndConstraint;
+use crate::constants::INCLUSION_CONSTRAINT_TYPE;
+use crate::types::{
+	AdminOverrides, CommitmentTombstone, EpochLookahead, InclusionPayload, PaymentRecord, SignedCommitmentAndConstraint,
+};
 
 /// 1-byte table tags so everything shares the same RocksDB instance.
 const KIND_SIGNED_CONSTRAINT: u8 = b'B';
@@ -18,6 +25,23 @@ const KIND_CONSTRAINT: u8 = b'C';
 const KIND_SIGNED_COMMITMENT: u8 = b'D';
 const KIND_LOOKAHEAD: u8 = b'E';
 const KIND_SIGNED_CONSTRAINTS_POSTED: u8 = b'F';
+const KIND_DELEGATE_SIGNED_CONSTRAINTS: u8 = b'H';
+const KIND_RECEIPT: u8 = b'I';
+const KIND_TOMBSTONE: u8 = b'K';
+const KIND_SENDER_NONCE: u8 = b'L';
+const KIND_PAYMENT: u8 = b'M';
+const KIND_TX_HASH: u8 = b'N';
+const KIND_SENDER_COMMITMENT: u8 = b'O';
+const KIND_EXCLUSION: u8 = b'P';
+const KIND_COMMITMENT_STATUS: u8 = b'Q';
+const KIND_PROPOSER_STATUS: u8 = b'S';
+const KIND_CONSTRAINTS_RECEIVERS: u8 = b'W';
+const KIND_SLOT_RECEIVERS: u8 = b'X';
+const KIND_ACCEPTED_BLOCK: u8 = b'Y';
+const KIND_DELEGATION_OFFER: u8 = b'Z';
+// 'J' held the bare dependent root of an epoch and is retired, so older databases are never read as lookaheads
+const KIND_EPOCH_LOOKAHEAD: u8 = b'a';
+const KIND_ADMIN_OVERRIDES: u8 = b'b';
 
 /// Key for a single SignedConstraints.
 /// Layout: [ 'B' ][ slot_be ]
@@ -64,6 +88,229 @@ pub fn signed_constraints_finalized_key(slot: u64) -> [u8; 1 + 8] {
 	key[1..].copy_from_slice(&slot.to_be_bytes());
 	key
 }
+
+/// Key for the SignedConstraints posted by a specific delegate.
+/// Layout: [ 'H' ][ slot_be ][ delegate (48 bytes) ]
+pub fn delegate_signed_constraints_key(slot: u64, delegate: &BlsPublicKey) -> [u8; 1 + 8 + 48] {
+	let mut key = [0u8; 1 + 8 + 48];
+	key[0] = KIND_DELEGATE_SIGNED_CONSTRAINTS;
+	key[1..9].copy_from_slice(&slot.to_be_bytes());
+	key[9..].copy_from_slice(delegate.as_slice());
+	key
+}
+
+/// Key for a relay receipt.
+/// Layout: [ 'I' ][ message_hash (32 bytes) ]
+pub fn receipt_key(message_hash: &B256) -> [u8; 1 + 32] {
+	let mut key = [0u8; 1 + 32];
+	key[0] = KIND_RECEIPT;
+	key[1..].copy_from_slice(message_hash.as_slice());
+	key
+}
+
+/// Key for the proposer set and duties dependent root of an epoch.
+/// Layout: [ 'a' ][ epoch_be ]
+pub fn epoch_lookahead_key(epoch: u64) -> [u8; 1 + 8] {
+	let mut key = [0u8; 1 + 8];
+	key[0] = KIND_EPOCH_LOOKAHEAD;
+	key[1..].copy_from_slice(&epoch.to_be_bytes());
+	key
+}
+
+/// Key for the tombstone of a withdrawn commitment.
+/// Layout: [ 'K' ][ request_hash (32 bytes) ]
+pub fn tombstone_key(request_hash: &B256) -> [u8; 1 + 32] {
+	let mut key = [0u8; 1 + 32];
+	key[0] = KIND_TOMBSTONE;
+	key[1..].copy_from_slice(request_hash.as_slice());
+	key
+}
+
+/// Key for the latest commitment request hash of a sender and transaction nonce.
+/// Layout: [ 'L' ][ sender (20 bytes) ][ nonce_be ]
+pub fn sender_nonce_key(sender: &Address, nonce: u64) -> [u8; 1 + 20 + 8] {
+	let mut key = [0u8; 1 + 20 + 8];
+	key[0] = KIND_SENDER_NONCE;
+	key[1..21].copy_from_slice(sender.as_slice());
+	key[21..].copy_from_slice(&nonce.to_be_bytes());
+	key
+}
+
+/// Key for the commitment request hash of a committed transaction.
+/// Layout: [ 'N' ][ tx_hash (32 bytes) ]
+pub fn tx_hash_key(tx_hash: &B256) -> [u8; 1 + 32] {
+	let mut key = [0u8; 1 + 32];
+	key[0] = KIND_TX_HASH;
+	key[1..].copy_from_slice(tx_hash.as_slice());
+	key
+}
+
+/// Key for a commitment in the index of commitments by sender address.
+/// Layout: [ 'O' ][ sender (20 bytes) ][ slot_be ][ request_hash (32 bytes) ]
+pub fn sender_commitment_key(sender: &Address, slot: u64, request_hash: &B256) -> [u8; 1 + 20 + 8 + 32] {
+	let mut key = [0u8; 1 + 20 + 8 + 32];
+	key[0] = KIND_SENDER_COMMITMENT;
+	key[1..21].copy_from_slice(sender.as_slice());
+	key[21..29].copy_from_slice(&slot.to_be_bytes());
+	key[29..].copy_from_slice(request_hash.as_slice());
+	key
+}
+
+/// Key for the exclusion commitment of a transaction in a slot.
+/// Layout: [ 'P' ][ slot_be ][ tx_hash (32 bytes) ]
+pub fn exclusion_key(slot: u64, tx_hash: &B256) -> [u8; 1 + 8 + 32] {
+	let mut key = [0u8; 1 + 8 + 32];
+	key[0] = KIND_EXCLUSION;
+	key[1..9].copy_from_slice(&slot.to_be_bytes());
+	key[9..].copy_from_slice(tx_hash.as_slice());
+	key
+}
+
+/// Key for the quoted and paid fee of a commitment.
+/// Layout: [ 'M' ][ request_hash (32 bytes) ]
+pub fn payment_key(request_hash: &B256) -> [u8; 1 + 32] {
+	let mut key = [0u8; 1 + 32];
+	key[0] = KIND_PAYMENT;
+	key[1..].copy_from_slice(request_hash.as_slice());
+	key
+}
+
+/// Key for the status of a slot's proposer.
+/// Layout: [ 'S' ][ slot_be ]
+pub fn proposer_status_key(slot: u64) -> [u8; 1 + 8] {
+	let mut key = [0u8; 1 + 8];
+	key[0] = KIND_PROPOSER_STATUS;
+	key[1..].copy_from_slice(&slot.to_be_bytes());
+	key
+}
+
+/// Key for the latest status of a commitment.
+/// Layout: [ 'Q' ][ request_hash (32 bytes) ]
+pub fn commitment_status_key(request_hash: &B256) -> [u8; 1 + 32] {
+	let mut key = [0u8; 1 + 32];
+	key[0] = KIND_COMMITMENT_STATUS;
+	key[1..].copy_from_slice(request_hash.as_slice());
+	key
+}
+
+/// Key for the record of a block accepted by the relay.
+/// Layout: [ 'Y' ][ slot_be ][ block_hash (32 bytes) ]
+pub fn accepted_block_key(slot: u64, block_hash: &B256) -> [u8; 1 + 8 + 32] {
+	let mut key = [0u8; 1 + 8 + 32];
+	key[0] = KIND_ACCEPTED_BLOCK;
+	key[1..9].copy_from_slice(&slot.to_be_bytes());
+	key[9..].copy_from_slice(block_hash.as_slice());
+	key
+}
+
+/// Key for the delegation offer of a gateway.
+/// Layout: [ 'Z' ][ slot_be ][ delegate (48 bytes) ]
+pub fn delegation_offer_key(slot: u64, delegate: &BlsPublicKey) -> [u8; 1 + 8 + 48] {
+	let mut key = [0u8; 1 + 8 + 48];
+	key[0] = KIND_DELEGATION_OFFER;
+	key[1..9].copy_from_slice(&slot.to_be_bytes());
+	key[9..].copy_from_slice(delegate.as_slice());
+	key
+}
+
+/// Key for the constraints receivers added at runtime.
+/// Layout: [ 'W' ]
+pub fn constraints_receivers_key() -> [u8; 1] {
+	[KIND_CONSTRAINTS_RECEIVERS]
+}
+
+/// Key for the changes made through the admin API.
+/// Layout: [ 'b' ]
+pub fn admin_overrides_key() -> [u8; 1] {
+	[KIND_ADMIN_OVERRIDES]
+}
+
+/// Key for the constraints receivers overriding the others in a specific slot.
+/// Layout: [ 'X' ][ slot_be ]
+pub fn slot_receivers_key(slot: u64) -> [u8; 1 + 8] {
+	let mut key = [0u8; 1 + 8];
+	key[0] = KIND_SLOT_RECEIVERS;
+	key[1..].copy_from_slice(&slot.to_be_bytes());
+	key
+}
+
+/// Delete the sender index entries of commitments for slots before `slot`.
+///
+/// The index is keyed by sender first, so the whole 'O' table is scanned rather than a slot range.
+fn delete_sender_commitments_before(db: &DatabaseContext, slot: u64) -> Result<usize> {
+	let start_key = [KIND_SENDER_COMMITMENT];
+	let inner: &rocksdb::DB = &*db.inner();
+
+	let iter = inner.iterator(IteratorMode::From(&start_key, Direction::Forward));
+	let mut ops = Vec::new();
+
+	for item in iter {
+		let (key, _) = item?;
+
+		if key[0] != KIND_SENDER_COMMITMENT {
+			break;
+		}
+		if key.len() != 1 + 20 + 8 + 32 {
+			continue;
+		}
+
+		let mut slot_bytes = [0u8; 8];
+		slot_bytes.copy_from_slice(&key[21..29]);
+		if u64::from_be_bytes(slot_bytes) < slot {
+			ops.push(DbOp::Delete { key: key.to_vec() });
+		}
+	}
+
+	let deleted = ops.len();
+	db.batch_write_raw(ops)?;
+	Ok(deleted)
+}
+
+/// Delete the transaction hash and sender nonce index entries of the commitments whose constraints are stored in
+/// slots before `slot`. Both indexes are keyed without a slot, so the pruned request hashes are collected from the
+/// constraint keys first and the indexes are scanned for entries pointing at them
+fn delete_commitment_indexes_before(db: &DatabaseContext, slot: u64) -> Result<usize> {
+	let inner: &rocksdb::DB = &*db.inner();
+
+	let mut pruned = HashSet::new();
+	for item in inner.iterator(IteratorMode::From(&[KIND_CONSTRAINT], Direction::Forward)) {
+		let (key, _) = item?;
+		if key[0] != KIND_CONSTRAINT {
+			break;
+		}
+		if key.len() != 1 + 8 + 32 {
+			continue;
+		}
+		let mut slot_bytes = [0u8; 8];
+		slot_bytes.copy_from_slice(&key[1..9]);
+		if u64::from_be_bytes(slot_bytes) >= slot {
+			break;
+		}
+		pruned.insert(B256::from_slice(&key[9..]));
+	}
+	if pruned.is_empty() {
+		return Ok(0);
+	}
+
+	let mut ops = Vec::new();
+	for kind in [KIND_TX_HASH, KIND_SENDER_NONCE] {
+		for item in inner.iterator(IteratorMode::From(&[kind], Direction::Forward)) {
+			let (key, value) = item?;
+			if key[0] != kind {
+				break;
+			}
+			let request_hash: B256 = serde_json::from_slice(&value)?;
+			if pruned.contains(&request_hash) {
+				ops.push(DbOp::Delete { key: key.to_vec() });
+			}
+		}
+	}
+
+	let deleted = ops.len();
+	db.batch_write_raw(ops)?;
+	Ok(deleted)
+}
+
 pub trait InclusionDbExt {
 	fn store_signed_constraints(&self, constraint: &SignedConstraints) -> Result<()>;
 
@@ -79,12 +326,87 @@ pub trait InclusionDbExt {
 		constraint: &Constraint,
 	) -> Result<()>;
 
+	/// Store a commitment backed by several constraints: its own constraint under the request hash, and each
+	/// transaction's inclusion constraint under its member hash. Every member record holds the signed commitment.
+	fn store_grouped_commitment(
+		&self,
+		slot: u64,
+		request_hash: &B256,
+		commitment: &SignedCommitment,
+		constraint: &Constraint,
+		members: &[(B256, Constraint)],
+	) -> Result<()>;
+
 	fn get_signed_commitment(&self, request_hash: &B256) -> Result<Option<SignedCommitmentAndConstraint>>;
 
 	fn get_constraints_in_range(&self, start_slot: u64, end_slot: u64) -> Result<Vec<(u64, B256, Constraint)>>;
 
+	/// Cumulative gas limit of the inclusion constraints still active for a slot
+	fn get_slot_committed_gas(&self, slot: u64) -> Result<u64>;
+
 	fn finalize_signed_constraints(&self, slot: u64) -> Result<()>;
 	fn signed_constraints_finalized(&self, slot: u64) -> Result<bool>;
+
+	/// Signed constraints keyed by delegate, used by the relay to merge constraints from several gateways
+	fn store_delegate_signed_constraints(&self, constraints: &SignedConstraints) -> Result<()>;
+	fn get_slot_signed_constraints(&self, slot: u64) -> Result<Vec<SignedConstraints>>;
+	fn delete_delegate_signed_constraints(&self, slot: u64, delegate: &BlsPublicKey) -> Result<()>;
+
+	fn store_receipt(&self, receipt: &SignedReceipt) -> Result<()>;
+	fn get_receipt(&self, message_hash: &B256) -> Result<Option<SignedReceipt>>;
+
+	/// Records of the blocks accepted by the relay, keyed by slot and block hash
+	fn store_accepted_block(&self, block: &AcceptedBlockWithProofs) -> Result<()>;
+	fn get_slot_accepted_blocks(&self, slot: u64) -> Result<Vec<AcceptedBlockWithProofs>>;
+
+	/// Delegation offers published by gateways, one per delegate and slot
+	/// An offer only replaces the stored one of its delegate with a higher nonce, so older offers cannot be replayed.
+	/// Returns whether it was stored.
+	fn store_delegation_offer(&self, offer: &SignedDelegationOffer) -> Result<bool>;
+	fn get_delegation_offer(&self, slot: u64, delegate: &BlsPublicKey) -> Result<Option<SignedDelegationOffer>>;
+	fn get_slot_delegation_offers(&self, slot: u64) -> Result<Vec<SignedDelegationOffer>>;
+
+	/// Withdraw a commitment: store its tombstone and drop its constraint so it is never posted
+	fn tombstone_commitment(&self, tombstone: &CommitmentTombstone) -> Result<()>;
+	fn get_tombstone(&self, request_hash: &B256) -> Result<Option<CommitmentTombstone>>;
+
+	/// Index of the latest commitment per (sender, nonce), used for replace-by-fee
+	fn store_sender_nonce_commitment(&self, sender: &Address, nonce: u64, request_hash: &B256) -> Result<()>;
+	fn get_sender_nonce_commitment(&self, sender: &Address, nonce: u64) -> Result<Option<B256>>;
+
+	/// Index of the commitment per committed transaction hash, used to reject duplicates
+	fn store_tx_hash_commitment(&self, tx_hash: &B256, request_hash: &B256) -> Result<()>;
+	fn get_tx_hash_commitment(&self, tx_hash: &B256) -> Result<Option<B256>>;
+
+	/// Index of commitments by the sender address of their transaction, ordered by slot
+	fn store_sender_commitment(&self, sender: &Address, slot: u64, request_hash: &B256) -> Result<()>;
+	fn get_sender_commitments(&self, sender: &Address, start_slot: u64, end_slot: u64) -> Result<Vec<(u64, B256)>>;
+
+	/// Exclusion commitments by slot and excluded transaction hash
+	fn store_exclusion_commitment(&self, slot: u64, tx_hash: &B256, request_hash: &B256) -> Result<()>;
+	fn get_exclusion_commitment(&self, slot: u64, tx_hash: &B256) -> Result<Option<B256>>;
+
+	/// Quoted versus paid fee per commitment
+	fn store_payment(&self, payment: &PaymentRecord) -> Result<()>;
+	fn get_payment(&self, request_hash: &B256) -> Result<Option<PaymentRecord>>;
+
+	/// Latest status of a commitment, unset until its constraints are posted
+	fn store_commitment_status(&self, request_hash: &B256, status: CommitmentStatus) -> Result<()>;
+	fn get_commitment_status(&self, request_hash: &B256) -> Result<Option<CommitmentStatus>>;
+
+	// Constraints receivers managed at runtime
+	fn store_constraints_receivers(&self, receivers: &[BlsPublicKey]) -> Result<()>;
+	fn get_constraints_receivers(&self) -> Result<Vec<BlsPublicKey>>;
+	fn store_slot_receivers(&self, slot: u64, receivers: &[BlsPublicKey]) -> Result<()>;
+	fn get_slot_receivers(&self, slot: u64) -> Result<Option<Vec<BlsPublicKey>>>;
+	fn delete_slot_receivers(&self, slot: u64) -> Result<()>;
+
+	/// Pause, drain and relay rotation of the admin API, kept across restarts
+	fn store_admin_overrides(&self, overrides: &AdminOverrides) -> Result<()>;
+	fn get_admin_overrides(&self) -> Result<AdminOverrides>;
+
+	/// Delete all slot-keyed constraints and lookahead data before the given slot
+	fn prune_before_slot(&self, slot: u64) -> Result<usize>;
 }
 
 impl InclusionDbExt for DatabaseContext {
@@ -114,6 +436,224 @@ impl InclusionDbExt for DatabaseContext {
 		Ok(flag.unwrap_or(false))
 	}
 
+	fn store_delegate_signed_constraints(&self, constraints: &SignedConstraints) -> Result<()> {
+		let key = delegate_signed_constraints_key(constraints.message.slot, &constraints.message.delegate);
+		self.put_json(&key, constraints)
+	}
+
+	fn get_slot_signed_constraints(&self, slot: u64) -> Result<Vec<SignedConstraints>> {
+		// Keys sort by delegate within a slot, so the merged order is deterministic
+		let constraints =
+			scan_slot_range_kind::<SignedConstraints>(self, KIND_DELEGATE_SIGNED_CONSTRAINTS, slot, slot)?;
+		Ok(constraints.into_iter().map(|(_, constraints)| constraints).collect())
+	}
+
+	fn delete_delegate_signed_constraints(&self, slot: u64, delegate: &BlsPublicKey) -> Result<()> {
+		let key = delegate_signed_constraints_key(slot, delegate);
+		self.delete_raw(&key)
+	}
+
+	fn store_receipt(&self, receipt: &SignedReceipt) -> Result<()> {
+		let key = receipt_key(&receipt.message.message_hash);
+		self.put_json(&key, receipt)
+	}
+
+	fn store_accepted_block(&self, block: &AcceptedBlockWithProofs) -> Result<()> {
+		let key = accepted_block_key(block.bid_trace.slot, &block.bid_trace.block_hash);
+		self.put_json(&key, block)
+	}
+
+	fn get_slot_accepted_blocks(&self, slot: u64) -> Result<Vec<AcceptedBlockWithProofs>> {
+		let blocks = scan_slot_range_kind::<AcceptedBlockWithProofs>(self, KIND_ACCEPTED_BLOCK, slot, slot)?;
+		Ok(blocks.into_iter().map(|(_, block)| block).collect())
+	}
+
+	fn store_delegation_offer(&self, offer: &SignedDelegationOffer) -> Result<bool> {
+		if let Some(stored) = self.get_delegation_offer(offer.message.slot, &offer.message.delegate)?
+			&& stored.nonce >= offer.nonce
+		{
+			return Ok(false);
+		}
+		let key = delegation_offer_key(offer.message.slot, &offer.message.delegate);
+		self.put_json(&key, offer)?;
+		Ok(true)
+	}
+
+	fn get_delegation_offer(&self, slot: u64, delegate: &BlsPublicKey) -> Result<Option<SignedDelegationOffer>> {
+		let key = delegation_offer_key(slot, delegate);
+		self.get_json(&key)
+	}
+
+	fn get_slot_delegation_offers(&self, slot: u64) -> Result<Vec<SignedDelegationOffer>> {
+		let offers = scan_slot_range_kind::<SignedDelegationOffer>(self, KIND_DELEGATION_OFFER, slot, slot)?;
+		Ok(offers.into_iter().map(|(_, offer)| offer).collect())
+	}
+
+	fn get_receipt(&self, message_hash: &B256) -> Result<Option<SignedReceipt>> {
+		let key = receipt_key(message_hash);
+		self.get_json(&key)
+	}
+
+	fn tombstone_commitment(&self, tombstone: &CommitmentTombstone) -> Result<()> {
+		let tombstone_key = tombstone_key(&tombstone.request_hash);
+		let constraint_key = constraint_key(tombstone.slot, &tombstone.request_hash);
+
+		self.batch_write_raw(vec![
+			DbOp::Put { key: tombstone_key.to_vec(), value: serde_json::to_vec(tombstone)? },
+			DbOp::Delete { key: constraint_key.to_vec() },
+		])
+	}
+
+	fn get_tombstone(&self, request_hash: &B256) -> Result<Option<CommitmentTombstone>> {
+		let key = tombstone_key(request_hash);
+		self.get_json(&key)
+	}
+
+	fn store_sender_nonce_commitment(&self, sender: &Address, nonce: u64, request_hash: &B256) -> Result<()> {
+		let key = sender_nonce_key(sender, nonce);
+		self.put_json(&key, request_hash)
+	}
+
+	fn get_sender_nonce_commitment(&self, sender: &Address, nonce: u64) -> Result<Option<B256>> {
+		let key = sender_nonce_key(sender, nonce);
+		self.get_json(&key)
+	}
+
+	fn store_tx_hash_commitment(&self, tx_hash: &B256, request_hash: &B256) -> Result<()> {
+		let key = tx_hash_key(tx_hash);
+		self.put_json(&key, request_hash)
+	}
+
+	fn get_tx_hash_commitment(&self, tx_hash: &B256) -> Result<Option<B256>> {
+		let key = tx_hash_key(tx_hash);
+		self.get_json(&key)
+	}
+
+	fn store_sender_commitment(&self, sender: &Address, slot: u64, request_hash: &B256) -> Result<()> {
+		let key = sender_commitment_key(sender, slot, request_hash);
+		self.put_json(&key, request_hash)
+	}
+
+	fn get_sender_commitments(&self, sender: &Address, start_slot: u64, end_slot: u64) -> Result<Vec<(u64, B256)>> {
+		if start_slot > end_slot {
+			return Ok(Vec::new());
+		}
+
+		let start_key = sender_commitment_key(sender, start_slot, &B256::ZERO);
+		let inner: &rocksdb::DB = &*self.inner();
+
+		let iter = inner.iterator(IteratorMode::From(&start_key, Direction::Forward));
+		let mut out = Vec::new();
+
+		for item in iter {
+			let (key, _) = item?;
+
+			// Stop once past this sender's entries
+			if key.len() != start_key.len() || key[..21] != start_key[..21] {
+				break;
+			}
+
+			let mut slot_bytes = [0u8; 8];
+			slot_bytes.copy_from_slice(&key[21..29]);
+			let slot = u64::from_be_bytes(slot_bytes);
+			if slot > end_slot {
+				break;
+			}
+
+			out.push((slot, B256::from_slice(&key[29..])));
+		}
+
+		Ok(out)
+	}
+
+	fn store_payment(&self, payment: &PaymentRecord) -> Result<()> {
+		let key = payment_key(&payment.request_hash);
+		self.put_json(&key, payment)
+	}
+
+	fn get_payment(&self, request_hash: &B256) -> Result<Option<PaymentRecord>> {
+		let key = payment_key(request_hash);
+		self.get_json(&key)
+	}
+
+	fn store_commitment_status(&self, request_hash: &B256, status: CommitmentStatus) -> Result<()> {
+		let key = commitment_status_key(request_hash);
+		self.put_json(&key, &status)
+	}
+
+	fn get_commitment_status(&self, request_hash: &B256) -> Result<Option<CommitmentStatus>> {
+		let key = commitment_status_key(request_hash);
+		self.get_json(&key)
+	}
+
+	fn store_constraints_receivers(&self, receivers: &[BlsPublicKey]) -> Result<()> {
+		self.put_json(&constraints_receivers_key(), &receivers)
+	}
+
+	fn get_constraints_receivers(&self) -> Result<Vec<BlsPublicKey>> {
+		let receivers: Option<Vec<BlsPublicKey>> = self.get_json(&constraints_receivers_key())?;
+		Ok(receivers.unwrap_or_default())
+	}
+
+	fn store_admin_overrides(&self, overrides: &AdminOverrides) -> Result<()> {
+		self.put_json(&admin_overrides_key(), overrides)
+	}
+
+	fn get_admin_overrides(&self) -> Result<AdminOverrides> {
+		let overrides: Option<AdminOverrides> = self.get_json(&admin_overrides_key())?;
+		Ok(overrides.unwrap_or_default())
+	}
+
+	fn store_slot_receivers(&self, slot: u64, receivers: &[BlsPublicKey]) -> Result<()> {
+		let key = slot_receivers_key(slot);
+		self.put_json(&key, &receivers)
+	}
+
+	fn get_slot_receivers(&self, slot: u64) -> Result<Option<Vec<BlsPublicKey>>> {
+		let key = slot_receivers_key(slot);
+		self.get_json(&key)
+	}
+
+	fn delete_slot_receivers(&self, slot: u64) -> Result<()> {
+		let key = slot_receivers_key(slot);
+		self.delete_raw(&key)
+	}
+
+	fn store_exclusion_commitment(&self, slot: u64, tx_hash: &B256, request_hash: &B256) -> Result<()> {
+		let key = exclusion_key(slot, tx_hash);
+		self.put_json(&key, request_hash)
+	}
+
+	fn get_exclusion_commitment(&self, slot: u64, tx_hash: &B256) -> Result<Option<B256>> {
+		let key = exclusion_key(slot, tx_hash);
+		self.get_json(&key)
+	}
+
+	fn prune_before_slot(&self, slot: u64) -> Result<usize> {
+		if slot == 0 {
+			return Ok(0);
+		}
+
+		// The indexes are resolved through the constraint keys, so they are pruned before them
+		let mut deleted = delete_commitment_indexes_before(self, slot)?;
+		for kind in [
+			KIND_SIGNED_CONSTRAINT,
+			KIND_CONSTRAINT,
+			KIND_LOOKAHEAD,
+			KIND_PROPOSER_STATUS,
+			KIND_SIGNED_CONSTRAINTS_POSTED,
+			KIND_DELEGATE_SIGNED_CONSTRAINTS,
+			KIND_EXCLUSION,
+			KIND_SLOT_RECEIVERS,
+			KIND_ACCEPTED_BLOCK,
+			KIND_DELEGATION_OFFER,
+		] {
+			deleted += delete_slot_range_kind(self, kind, 0, slot - 1)?;
+		}
+		deleted += delete_sender_commitments_before(self, slot)?;
+		Ok(deleted)
+	}
+
 	fn store_signed_commitment_and_constraint(
 		&self,
 		slot: u64,
@@ -123,18 +663,52 @@ impl InclusionDbExt for DatabaseContext {
 	) -> Result<()> {
 		let signed_commitment_key = signed_commitment_key(request_hash);
 		let constraint_key = constraint_key(slot, request_hash);
+		let record = SignedCommitmentAndConstraint { commitment: commitment.clone(), constraint: constraint.clone() };
 
 		self.batch_write_raw(vec![
-			DbOp::Put { key: signed_commitment_key.to_vec(), value: serde_json::to_vec(commitment)? },
+			DbOp::Put { key: signed_commitment_key.to_vec(), value: serde_json::to_vec(&record)? },
 			DbOp::Put { key: constraint_key.to_vec(), value: serde_json::to_vec(constraint)? },
 		])
 	}
 
+	fn store_grouped_commitment(
+		&self,
+		slot: u64,
+		request_hash: &B256,
+		commitment: &SignedCommitment,
+		constraint: &Constraint,
+		members: &[(B256, Constraint)],
+	) -> Result<()> {
+		let mut ops = Vec::with_capacity(2 * (members.len() + 1));
+		for (hash, constraint) in std::iter::once((request_hash, constraint)).chain(members.iter().map(|(h, c)| (h, c)))
+		{
+			let record =
+				SignedCommitmentAndConstraint { commitment: commitment.clone(), constraint: constraint.clone() };
+			ops.push(DbOp::Put { key: signed_commitment_key(hash).to_vec(), value: serde_json::to_vec(&record)? });
+			ops.push(DbOp::Put { key: constraint_key(slot, hash).to_vec(), value: serde_json::to_vec(constraint)? });
+		}
+		self.batch_write_raw(ops)
+	}
+
 	fn get_signed_commitment(&self, request_hash: &B256) -> Result<Option<SignedCommitmentAndConstraint>> {
 		let key = signed_commitment_key(request_hash);
 		self.get_json(&key)
 	}
 
+	fn get_slot_committed_gas(&self, slot: u64) -> Result<u64> {
+		let mut gas = 0u64;
+		for (_, _, constraint) in self.get_constraints_in_range(slot, slot)? {
+			if constraint.constraint_type != INCLUSION_CONSTRAINT_TYPE {
+				continue;
+			}
+			let gas_limit = InclusionPayload::abi_decode(&constraint.payload)
+				.and_then(|payload| payload.gas())
+				.map_err(|e| StorageError::Corrupt(format!("inclusion constraint of slot {}: {}", slot, e)))?;
+			gas = gas.saturating_add(gas_limit);
+		}
+		Ok(gas)
+	}
+
 	fn get_constraints_in_range(&self, start_slot: u64, end_slot: u64) -> Result<Vec<(u64, B256, Constraint)>> {
 		if start_slot > end_slot {
 			return Ok(Vec::new());
@@ -185,6 +759,17 @@ impl InclusionDbExt for DatabaseContext {
 pub trait LookaheadDbExt {
 	fn store_proposer_bls_key(&self, slot: u64, key: &BlsPublicKey) -> Result<()>;
 	fn get_proposer_bls_key(&self, slot: u64) -> Result<Option<BlsPublicKey>>;
+
+	/// Store the proposer set of an epoch along with the per-slot proposer keys, replacing the previous set
+	fn store_epoch_lookahead(&self, lookahead: &EpochLookahead) -> Result<()>;
+	fn get_lookahead_for_epoch(&self, epoch: u64) -> Result<Option<EpochLookahead>>;
+	/// Dependent root of the proposer duties last fetched for an epoch, used to detect reorgs
+	fn get_dependent_root(&self, epoch: u64) -> Result<Option<B256>>;
+
+	/// Status of a slot's proposer, only stored when it is slashed or exited
+	fn store_proposer_status(&self, slot: u64, status: &ValidatorStatus) -> Result<()>;
+	fn get_proposer_status(&self, slot: u64) -> Result<Option<ValidatorStatus>>;
+	fn delete_proposer_status(&self, slot: u64) -> Result<()>;
 }
 
 impl LookaheadDbExt for DatabaseContext {
@@ -197,13 +782,57 @@ impl LookaheadDbExt for DatabaseContext {
 		let key = lookahead_key(slot);
 		self.get_json(&key)
 	}
+
+	fn store_epoch_lookahead(&self, lookahead: &EpochLookahead) -> Result<()> {
+		let mut ops = Vec::with_capacity(lookahead.proposers.len() + 1);
+		for proposer in &lookahead.proposers {
+			ops.push(DbOp::Put {
+				key: lookahead_key(proposer.slot).to_vec(),
+				value: serde_json::to_vec(&proposer.pubkey)?,
+			});
+		}
+		ops.push(DbOp::Put {
+			key: epoch_lookahead_key(lookahead.epoch).to_vec(),
+			value: serde_json::to_vec(lookahead)?,
+		});
+		self.batch_write_raw(ops)
+	}
+
+	fn get_lookahead_for_epoch(&self, epoch: u64) -> Result<Option<EpochLookahead>> {
+		let key = epoch_lookahead_key(epoch);
+		self.get_json(&key)
+	}
+
+	fn get_dependent_root(&self, epoch: u64) -> Result<Option<B256>> {
+		Ok(self.get_lookahead_for_epoch(epoch)?.map(|lookahead| lookahead.dependent_root))
+	}
+
+	fn store_proposer_status(&self, slot: u64, status: &ValidatorStatus) -> Result<()> {
+		let key = proposer_status_key(slot);
+		self.put_json(&key, status)
+	}
+
+	fn get_proposer_status(&self, slot: u64) -> Result<Option<ValidatorStatus>> {
+		let key = proposer_status_key(slot);
+		self.get_json(&key)
+	}
+
+	fn delete_proposer_status(&self, slot: u64) -> Result<()> {
+		let key = proposer_status_key(slot);
+		self.delete_raw(&key)
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use alloy::primitives::Bytes;
+	use crate::types::{SlotProposer, TombstoneReason};
+	use alloy::primitives::{Bytes, Signature, U256};
+	use alloy::rpc::types::beacon::BlsSignature;
+	use alloy::rpc::types::beacon::relay::BidTrace;
+	use commitments::types::Commitment;
 	use common::storage::db::DbOp;
+	use constraints::types::{ConstraintProofResult, ConstraintsMessage, DelegationOffer};
 	use eyre::Result;
 	use rocksdb::Options;
 	use serde::{Deserialize, Serialize};
@@ -274,6 +903,16 @@ mod tests {
 		assert_eq!(parsed_hash_bytes, hash_bytes);
 	}
 
+	#[test]
+	fn receipt_key_layout_is_correct() {
+		let message_hash = B256::from([0x44u8; 32]);
+		let key = receipt_key(&message_hash);
+
+		assert_eq!(key.len(), 1 + 32);
+		assert_eq!(key[0], KIND_RECEIPT);
+		assert_eq!(&key[1..], message_hash.as_slice());
+	}
+
 	#[test]
 	fn signed_constraints_finalized_key_layout_is_correct() {
 		let slot = 999u64;
@@ -288,6 +927,47 @@ mod tests {
 		assert_eq!(parsed, slot);
 	}
 
+	#[test]
+	fn delegate_signed_constraints_key_layout_is_correct() {
+		let slot = 77u64;
+		let delegate = BlsPublicKey::from([0x33u8; 48]);
+		let key = delegate_signed_constraints_key(slot, &delegate);
+
+		assert_eq!(key.len(), 1 + 8 + 48);
+		assert_eq!(key[0], KIND_DELEGATE_SIGNED_CONSTRAINTS);
+
+		let mut slot_bytes = [0u8; 8];
+		slot_bytes.copy_from_slice(&key[1..9]);
+		assert_eq!(u64::from_be_bytes(slot_bytes), slot);
+		assert_eq!(&key[9..], delegate.as_slice());
+	}
+
+	#[test]
+	fn slot_signed_constraints_returns_one_entry_per_delegate() -> Result<()> {
+		let db = new_temp_db()?;
+
+		let make = |delegate: u8, slot: u64| SignedConstraints {
+			message: ConstraintsMessage { delegate: BlsPublicKey::from([delegate; 48]), slot, ..Default::default() },
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: Default::default(),
+		};
+
+		db.store_delegate_signed_constraints(&make(0x02, 10))?;
+		db.store_delegate_signed_constraints(&make(0x01, 10))?;
+		db.store_delegate_signed_constraints(&make(0x01, 11))?;
+		// Re-posting from the same delegate replaces the previous entry
+		db.store_delegate_signed_constraints(&make(0x02, 10))?;
+
+		let stored = db.get_slot_signed_constraints(10)?;
+		assert_eq!(stored.len(), 2);
+		assert_eq!(stored[0].message.delegate, BlsPublicKey::from([0x01u8; 48]));
+		assert_eq!(stored[1].message.delegate, BlsPublicKey::from([0x02u8; 48]));
+		assert!(db.get_slot_signed_constraints(12)?.is_empty());
+
+		Ok(())
+	}
+
 	#[test]
 	fn constraints_range_scan_works_with_mixed_data() -> Result<()> {
 		let db = new_temp_db()?;
@@ -390,4 +1070,417 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn epoch_lookahead_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+
+		assert_eq!(db.get_lookahead_for_epoch(5)?, None);
+		assert_eq!(db.get_dependent_root(5)?, None);
+
+		let root = B256::from([0x05u8; 32]);
+		let proposer = BlsPublicKey::from([0x0Au8; 48]);
+		let lookahead = EpochLookahead {
+			epoch: 5,
+			dependent_root: root,
+			fetched_at: 1_700_000_000,
+			proposers: (160..192).map(|slot| SlotProposer { slot, pubkey: proposer }).collect(),
+		};
+		db.store_epoch_lookahead(&lookahead)?;
+
+		assert_eq!(db.get_lookahead_for_epoch(5)?, Some(lookahead));
+		assert_eq!(db.get_dependent_root(5)?, Some(root));
+		assert_eq!(db.get_dependent_root(6)?, None);
+		// The per-slot proposer keys are written with the set
+		assert_eq!(db.get_proposer_bls_key(160)?, Some(proposer));
+		assert_eq!(db.get_proposer_bls_key(191)?, Some(proposer));
+		assert_eq!(db.get_proposer_bls_key(192)?, None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn tombstone_commitment_removes_constraint() -> Result<()> {
+		let db = new_temp_db()?;
+
+		let c = Constraint { constraint_type: 1, payload: Bytes::from([0x01u8; 32]) };
+		let h = B256::from([0x01u8; 32]);
+		db.put_json(&constraint_key(10, &h), &c)?;
+		assert_eq!(db.get_constraints_in_range(10, 10)?.len(), 1);
+
+		let tombstone = CommitmentTombstone { request_hash: h, slot: 10, reason: TombstoneReason::Cancelled };
+		db.tombstone_commitment(&tombstone)?;
+
+		assert!(db.get_constraints_in_range(10, 10)?.is_empty());
+		assert_eq!(db.get_tombstone(&h)?, Some(tombstone));
+
+		Ok(())
+	}
+
+	#[test]
+	fn signed_commitment_record_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+		let h = B256::from([0x01u8; 32]);
+		let commitment = SignedCommitment {
+			commitment: Commitment {
+				commitment_type: 1,
+				payload: Bytes::from([0x02u8; 8]),
+				request_hash: h,
+				slasher: Address::repeat_byte(0x03),
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: Signature::new(U256::ZERO, U256::ZERO, false),
+		};
+		let c = Constraint { constraint_type: 1, payload: Bytes::from([0x01u8; 32]) };
+		db.store_signed_commitment_and_constraint(10, &h, &commitment, &c)?;
+
+		let stored = db.get_signed_commitment(&h)?.expect("record stored");
+		assert_eq!(stored.commitment.commitment.request_hash, h);
+		assert_eq!(stored.constraint.payload, c.payload);
+
+		// A bundle stores its own record plus one per member, all sharing the bundle commitment
+		let member = B256::from([0x04u8; 32]);
+		let bundle_constraint = Constraint { constraint_type: 4, payload: Bytes::from([0x05u8; 32]) };
+		db.store_grouped_commitment(11, &h, &commitment, &bundle_constraint, &[(member, c.clone())])?;
+		assert_eq!(db.get_constraints_in_range(11, 11)?.len(), 2);
+		let stored = db.get_signed_commitment(&member)?.expect("member record stored");
+		assert_eq!(stored.commitment.commitment.request_hash, h);
+		assert_eq!(stored.constraint.constraint_type, 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn slot_committed_gas_sums_active_constraints() -> Result<()> {
+		let db = new_temp_db()?;
+		assert_eq!(db.get_slot_committed_gas(10)?, 0);
+
+		let c = Constraint {
+			constraint_type: INCLUSION_CONSTRAINT_TYPE,
+			payload: InclusionPayload::random().abi_encode()?,
+		};
+		let h1 = B256::from([0x01u8; 32]);
+		let h2 = B256::from([0x02u8; 32]);
+		db.put_json(&constraint_key(10, &h1), &c)?;
+		db.put_json(&constraint_key(10, &h2), &c)?;
+		db.put_json(&constraint_key(11, &h1), &c)?;
+		assert_eq!(db.get_slot_committed_gas(10)?, 42000);
+
+		// Withdrawn commitments no longer count towards the slot
+		db.tombstone_commitment(&CommitmentTombstone {
+			request_hash: h2,
+			slot: 10,
+			reason: TombstoneReason::Cancelled,
+		})?;
+		assert_eq!(db.get_slot_committed_gas(10)?, 21000);
+
+		Ok(())
+	}
+
+	#[test]
+	fn sender_nonce_commitment_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+		let sender = Address::repeat_byte(0x22);
+
+		assert_eq!(db.get_sender_nonce_commitment(&sender, 7)?, None);
+
+		let h = B256::from([0x07u8; 32]);
+		db.store_sender_nonce_commitment(&sender, 7, &h)?;
+		assert_eq!(db.get_sender_nonce_commitment(&sender, 7)?, Some(h));
+		assert_eq!(db.get_sender_nonce_commitment(&sender, 8)?, None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn tx_hash_commitment_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+		let tx_hash = B256::from([0x09u8; 32]);
+
+		assert_eq!(db.get_tx_hash_commitment(&tx_hash)?, None);
+
+		let h = B256::from([0x0Au8; 32]);
+		db.store_tx_hash_commitment(&tx_hash, &h)?;
+		assert_eq!(db.get_tx_hash_commitment(&tx_hash)?, Some(h));
+
+		Ok(())
+	}
+
+	#[test]
+	fn exclusion_commitment_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+		let tx_hash = B256::from([0x0Bu8; 32]);
+		let h = B256::from([0x0Cu8; 32]);
+
+		db.store_exclusion_commitment(10, &tx_hash, &h)?;
+		assert_eq!(db.get_exclusion_commitment(10, &tx_hash)?, Some(h));
+		assert_eq!(db.get_exclusion_commitment(11, &tx_hash)?, None);
+
+		db.prune_before_slot(11)?;
+		assert_eq!(db.get_exclusion_commitment(10, &tx_hash)?, None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn sender_commitments_range_scan() -> Result<()> {
+		let db = new_temp_db()?;
+		let sender = Address::repeat_byte(0x33);
+		let other = Address::repeat_byte(0x34);
+
+		let h1 = B256::from([0x01u8; 32]);
+		let h2 = B256::from([0x02u8; 32]);
+		let h3 = B256::from([0x03u8; 32]);
+		db.store_sender_commitment(&sender, 10, &h1)?;
+		db.store_sender_commitment(&sender, 12, &h2)?;
+		db.store_sender_commitment(&sender, 20, &h3)?;
+		db.store_sender_commitment(&other, 11, &h1)?;
+
+		assert_eq!(db.get_sender_commitments(&sender, 0, u64::MAX)?, vec![(10, h1), (12, h2), (20, h3)]);
+		assert_eq!(db.get_sender_commitments(&sender, 11, 12)?, vec![(12, h2)]);
+		assert_eq!(db.get_sender_commitments(&other, 0, 100)?, vec![(11, h1)]);
+		assert!(db.get_sender_commitments(&sender, 13, 19)?.is_empty());
+		assert!(db.get_sender_commitments(&sender, 12, 10)?.is_empty());
+
+		Ok(())
+	}
+
+	#[test]
+	fn sender_commitments_pruned_before_slot() -> Result<()> {
+		let db = new_temp_db()?;
+		let sender = Address::repeat_byte(0x33);
+		let other = Address::repeat_byte(0x34);
+
+		let h1 = B256::from([0x01u8; 32]);
+		let h2 = B256::from([0x02u8; 32]);
+		db.store_sender_commitment(&sender, 10, &h1)?;
+		db.store_sender_commitment(&sender, 12, &h2)?;
+		db.store_sender_commitment(&other, 11, &h1)?;
+		db.store_sender_commitment(&other, 12, &h2)?;
+
+		db.prune_before_slot(12)?;
+		assert_eq!(db.get_sender_commitments(&sender, 0, u64::MAX)?, vec![(12, h2)]);
+		assert_eq!(db.get_sender_commitments(&other, 0, u64::MAX)?, vec![(12, h2)]);
+
+		Ok(())
+	}
+
+	#[test]
+	fn commitment_indexes_pruned_before_slot() -> Result<()> {
+		let db = new_temp_db()?;
+		let c = Constraint {
+			constraint_type: INCLUSION_CONSTRAINT_TYPE,
+			payload: InclusionPayload::random().abi_encode()?,
+		};
+		let sender = Address::repeat_byte(0x33);
+		let (old, kept, replaced) = (B256::repeat_byte(0x01), B256::repeat_byte(0x02), B256::repeat_byte(0x03));
+		db.put_json(&constraint_key(10, &old), &c)?;
+		db.put_json(&constraint_key(12, &kept), &c)?;
+		db.put_json(&constraint_key(11, &replaced), &c)?;
+		db.store_tx_hash_commitment(&B256::repeat_byte(0x11), &old)?;
+		db.store_tx_hash_commitment(&B256::repeat_byte(0x12), &kept)?;
+		db.store_sender_nonce_commitment(&sender, 1, &old)?;
+		db.store_sender_nonce_commitment(&sender, 2, &kept)?;
+
+		// The nonce was committed again in a later slot, the index follows the latest commitment
+		db.store_sender_nonce_commitment(&sender, 3, &replaced)?;
+		db.store_sender_nonce_commitment(&sender, 3, &kept)?;
+
+		db.prune_before_slot(12)?;
+		assert_eq!(db.get_tx_hash_commitment(&B256::repeat_byte(0x11))?, None);
+		assert_eq!(db.get_tx_hash_commitment(&B256::repeat_byte(0x12))?, Some(kept));
+		assert_eq!(db.get_sender_nonce_commitment(&sender, 1)?, None);
+		assert_eq!(db.get_sender_nonce_commitment(&sender, 2)?, Some(kept));
+		assert_eq!(db.get_sender_nonce_commitment(&sender, 3)?, Some(kept));
+		Ok(())
+	}
+
+	#[test]
+	fn payment_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+		let h = B256::from([0x08u8; 32]);
+
+		assert_eq!(db.get_payment(&h)?, None);
+
+		let payment = PaymentRecord {
+			request_hash: h,
+			quoted_wei: alloy::primitives::U256::from(21_000_000_000_000u64),
+			paid_wei: alloy::primitives::U256::from(42_000_000_000_000u64),
+		};
+		db.store_payment(&payment)?;
+		assert_eq!(db.get_payment(&h)?, Some(payment));
+
+		Ok(())
+	}
+
+	#[test]
+	fn commitment_status_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+		let h = B256::from([0x09u8; 32]);
+
+		assert_eq!(db.get_commitment_status(&h)?, None);
+
+		db.store_commitment_status(&h, CommitmentStatus::Constrained)?;
+		assert_eq!(db.get_commitment_status(&h)?, Some(CommitmentStatus::Constrained));
+
+		db.store_commitment_status(&h, CommitmentStatus::Missed)?;
+		assert_eq!(db.get_commitment_status(&h)?, Some(CommitmentStatus::Missed));
+
+		Ok(())
+	}
+
+	#[test]
+	fn constraints_receivers_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+		let receivers = vec![BlsPublicKey::repeat_byte(1), BlsPublicKey::repeat_byte(2)];
+
+		assert!(db.get_constraints_receivers()?.is_empty());
+		db.store_constraints_receivers(&receivers)?;
+		assert_eq!(db.get_constraints_receivers()?, receivers);
+
+		assert_eq!(db.get_slot_receivers(7)?, None);
+		db.store_slot_receivers(7, &receivers[..1])?;
+		assert_eq!(db.get_slot_receivers(7)?, Some(receivers[..1].to_vec()));
+
+		// Overrides are slot-keyed and pruned with the slot
+		db.prune_before_slot(8)?;
+		assert_eq!(db.get_slot_receivers(7)?, None);
+		db.store_slot_receivers(9, &[])?;
+		db.delete_slot_receivers(9)?;
+		assert_eq!(db.get_slot_receivers(9)?, None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn accepted_blocks_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+		let block = |slot: u64, byte: u8| AcceptedBlockWithProofs {
+			bid_trace: BidTrace {
+				slot,
+				parent_hash: B256::ZERO,
+				block_hash: B256::repeat_byte(byte),
+				builder_pubkey: BlsPublicKey::repeat_byte(1),
+				proposer_pubkey: BlsPublicKey::repeat_byte(2),
+				proposer_fee_recipient: Address::ZERO,
+				gas_limit: 30_000_000,
+				gas_used: 21_000,
+				value: U256::from(1u64),
+			},
+			constraints: vec![ConstraintProofResult { index: 0, constraint_type: 1, satisfied: true, error: None }],
+			timestamp_ms: 1_700_000_000_000,
+		};
+
+		db.store_accepted_block(&block(7, 1))?;
+		db.store_accepted_block(&block(7, 2))?;
+		db.store_accepted_block(&block(8, 3))?;
+
+		let blocks = db.get_slot_accepted_blocks(7)?;
+		assert_eq!(blocks.len(), 2);
+		assert_eq!(blocks[1].bid_trace.block_hash, B256::repeat_byte(2));
+		assert!(blocks[0].constraints[0].satisfied);
+
+		db.prune_before_slot(8)?;
+		assert!(db.get_slot_accepted_blocks(7)?.is_empty());
+		assert_eq!(db.get_slot_accepted_blocks(8)?.len(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn delegation_offers_keep_the_latest_per_delegate() -> Result<()> {
+		let db = new_temp_db()?;
+		let offer = |slot: u64, byte: u8, bid_gwei: u64, nonce: u64| SignedDelegationOffer {
+			message: DelegationOffer {
+				delegate: BlsPublicKey::repeat_byte(byte),
+				committer: Address::repeat_byte(byte),
+				slot,
+				bid_gwei,
+				constraint_types: vec![1],
+			},
+			nonce,
+			signing_id: B256::ZERO,
+			signature: BlsSignature::ZERO,
+		};
+
+		assert!(db.store_delegation_offer(&offer(7, 1, 10, 1))?);
+		assert!(db.store_delegation_offer(&offer(7, 2, 20, 1))?);
+		assert!(db.store_delegation_offer(&offer(7, 1, 30, 2))?);
+		assert!(db.store_delegation_offer(&offer(8, 1, 40, 3))?);
+
+		// An older offer of the same delegate is not stored again
+		assert!(!db.store_delegation_offer(&offer(7, 1, 10, 1))?);
+		assert!(!db.store_delegation_offer(&offer(7, 1, 50, 2))?);
+
+		let offers = db.get_slot_delegation_offers(7)?;
+		assert_eq!(offers.len(), 2);
+		assert_eq!(offers[0], offer(7, 1, 30, 2));
+		assert_eq!(offers[1].message.bid_gwei, 20);
+		assert_eq!(db.get_delegation_offer(7, &BlsPublicKey::repeat_byte(2))?, Some(offer(7, 2, 20, 1)));
+
+		db.prune_before_slot(8)?;
+		assert!(db.get_slot_delegation_offers(7)?.is_empty());
+		assert_eq!(db.get_slot_delegation_offers(8)?.len(), 1);
+
+		Ok(())
+	}
+
+	#[test]
+	fn proposer_status_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+
+		assert_eq!(db.get_proposer_status(5)?, None);
+
+		db.store_proposer_status(5, &ValidatorStatus::ActiveSlashed)?;
+		assert_eq!(db.get_proposer_status(5)?, Some(ValidatorStatus::ActiveSlashed));
+
+		db.delete_proposer_status(5)?;
+		assert_eq!(db.get_proposer_status(5)?, None);
+
+		Ok(())
+	}
+
+	#[test]
+	fn kind_tags_do_not_collide_with_other_crates() {
+		// Delegations and signing nonces share the RocksDB instance under tags of the proposer and signing crates
+		let delegate = BlsPublicKey::repeat_byte(1);
+		let foreign = [
+			proposer::storage::signed_delegation_key(0)[0],
+			proposer::storage::delegate_delegation_key(0, &delegate)[0],
+			proposer::storage::relay_acks_key(0)[0],
+			proposer::storage::delegated_key(0)[0],
+			proposer::storage::constraint_attestations_key(0)[0],
+			signing::nonce::signing_nonce_key(&[], signing::nonce::NonceKind::Commitment)[0],
+		];
+		let own = [
+			KIND_SIGNED_CONSTRAINT,
+			KIND_CONSTRAINT,
+			KIND_SIGNED_COMMITMENT,
+			KIND_LOOKAHEAD,
+			KIND_SIGNED_CONSTRAINTS_POSTED,
+			KIND_DELEGATE_SIGNED_CONSTRAINTS,
+			KIND_RECEIPT,
+			KIND_EPOCH_LOOKAHEAD,
+			KIND_TOMBSTONE,
+			KIND_SENDER_NONCE,
+			KIND_PAYMENT,
+			KIND_TX_HASH,
+			KIND_SENDER_COMMITMENT,
+			KIND_EXCLUSION,
+			KIND_COMMITMENT_STATUS,
+			KIND_PROPOSER_STATUS,
+			KIND_CONSTRAINTS_RECEIVERS,
+			KIND_SLOT_RECEIVERS,
+			KIND_ACCEPTED_BLOCK,
+			KIND_DELEGATION_OFFER,
+			KIND_ADMIN_OVERRIDES,
+		];
+
+		for (index, tag) in own.iter().enumerate() {
+			assert_ne!(*tag, b'J', "tag J is retired");
+			assert!(!foreign.contains(tag), "tag {} is used by another crate", *tag as char);
+			assert!(!own[index + 1..].contains(tag), "tag {} is used twice", *tag as char);
+		}
+	}
 }
@@ -2,8 +2,9 @@ use alloy::consensus::{
 	Signed, Transaction, TxEip1559, TxEip2930, TxEip4844, TxEip7702, TxEnvelope, TxLegacy,
 	transaction::SignerRecoverable,
 };
-use alloy::primitives::{B256, Bytes};
+use alloy::primitives::{Address, B256, Bytes, Signature, U256, keccak256};
 use alloy::rlp::Decodable;
+use alloy::rpc::types::beacon::BlsPublicKey;
 use alloy::sol_types::SolValue;
 use eyre::{Result, WrapErr, bail};
 use serde::{Deserialize, Serialize};
@@ -11,11 +12,29 @@ use serde::{Deserialize, Serialize};
 use commitments::types::SignedCommitment;
 use constraints::types::Constraint;
 
+use crate::constants::{
+	BLOB_COMMITMENT_TYPE, BLOB_CONSTRAINT_TYPE, BUNDLE_COMMITMENT_TYPE, BUNDLE_CONSTRAINT_TYPE,
+	EXCLUSION_COMMITMENT_TYPE, EXCLUSION_CONSTRAINT_TYPE, INCLUSION_COMMITMENT_TYPE, INCLUSION_CONSTRAINT_TYPE,
+	ORDERING_COMMITMENT_TYPE, ORDERING_CONSTRAINT_TYPE,
+};
+
 /// Fee payload for an inclusion preconf request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FeePayload {
 	pub request_hash: B256,
+	/// Fee the committer is paid for the commitment, in gwei: the quoted priority fee over the estimated gas, not
+	/// the transaction's total gas cost
 	pub price_gwei: u64,
+	/// Minimum priority fee per gas the transaction must pay, in wei
+	/// Older gateways do not send the quote terms, so they default to zero
+	#[serde(default)]
+	pub min_priority_fee_per_gas: u128,
+	/// Base fee per gas the transaction should cover by the target slot, in wei
+	#[serde(default)]
+	pub max_base_fee_per_gas: u64,
+	/// Unix timestamp in seconds after which the quote is no longer honoured, zero if unknown
+	#[serde(default)]
+	pub expires_at: u64,
 }
 
 /// A signed commitment and its paired constraint for a specific slot
@@ -25,6 +44,86 @@ pub struct SignedCommitmentAndConstraint {
 	pub constraint: Constraint,
 }
 
+/// Fee quoted for a commitment and what its transaction actually pays the committer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentRecord {
+	pub request_hash: B256,
+	pub quoted_wei: U256,
+	pub paid_wei: U256,
+}
+
+/// Why a commitment was withdrawn before its constraint was posted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TombstoneReason {
+	/// Cancelled by the original requester
+	Cancelled,
+	/// Superseded by a higher fee commitment for the same sender and nonce
+	Replaced { replaced_by: B256 },
+}
+
+/// Record left in place of a withdrawn commitment so it is never constrained
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentTombstone {
+	pub request_hash: B256,
+	pub slot: u64,
+	pub reason: TombstoneReason,
+}
+
+/// A signed commitment with the constraints it is stored under, handed over to the next leader of a highly
+/// available gateway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandedOverCommitment {
+	pub slot: u64,
+	pub request_hash: B256,
+	pub commitment: SignedCommitment,
+	/// Constraint stored under the request hash, followed by the member constraints of a grouped commitment
+	pub constraints: Vec<(B256, Constraint)>,
+}
+
+/// Relay endpoint set through the gateway's admin API
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayEndpoint {
+	pub host: String,
+	pub port: u16,
+	#[serde(default)]
+	pub api_key: Option<String>,
+}
+
+/// Changes made through the admin API, applied again when the gateway or relay restarts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminOverrides {
+	/// Whether the gateway refuses commitment requests, or the relay is drained of new constraints
+	pub paused: bool,
+	/// Relay replacing the one configured for the gateway
+	pub relay: Option<RelayEndpoint>,
+}
+
+/// Proposer of a slot in the lookahead
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotProposer {
+	pub slot: u64,
+	pub pubkey: BlsPublicKey,
+}
+
+/// Proposer set of an epoch and the dependent root of the duties it was fetched at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochLookahead {
+	pub epoch: u64,
+	/// The duties only change when the block at this root is reorged out
+	pub dependent_root: B256,
+	/// Unix timestamp in seconds of the fetch
+	pub fetched_at: u64,
+	/// Proposers ordered by slot
+	pub proposers: Vec<SlotProposer>,
+}
+
+impl EpochLookahead {
+	/// Proposer of `slot`, `None` if the slot is not in the epoch
+	pub fn proposer(&self, slot: u64) -> Option<&BlsPublicKey> {
+		self.proposers.iter().find(|proposer| proposer.slot == slot).map(|proposer| &proposer.pubkey)
+	}
+}
+
 /// Payload for commitments/constraints
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InclusionPayload {
@@ -75,6 +174,12 @@ impl InclusionPayload {
 		Ok(())
 	}
 
+	/// Recovers the sender of the signed transaction
+	pub fn sender(&self) -> Result<Address> {
+		let tx_envelope = self.decode_transaction()?;
+		tx_envelope.recover_signer().wrap_err("Failed to recover signer - invalid signature")
+	}
+
 	/// Returns the transaction hash by trying each supported transaction type
 	/// This hash includes the signature of the transaction so is not the same
 	/// as the hash of the unsigned transaction / the hash for signing commitments
@@ -155,6 +260,524 @@ impl InclusionPayload {
 		tx_envelope.encode_2718(&mut encoded_tx);
 		InclusionPayload { slot: 12345, signed_tx: Bytes::from(encoded_tx) }
 	}
+
+	/// Useful for testing, a blob transaction without its sidecar
+	pub fn random_blob(blob_versioned_hashes: Vec<B256>) -> Self {
+		use alloy::consensus::{SignableTransaction, Signed, TxEip4844, TxEip4844Variant, TxEnvelope};
+		use alloy::eips::eip2718::Encodable2718;
+		use alloy::primitives::{Address, Bytes, U256};
+		use alloy::signers::{SignerSync, local::PrivateKeySigner};
+
+		let signer = PrivateKeySigner::random();
+		let tx = TxEip4844 {
+			chain_id: 1,
+			nonce: 0,
+			gas_limit: 21000,
+			max_fee_per_gas: 20_000_000_000u128,
+			max_priority_fee_per_gas: 2_000_000_000u128,
+			to: Address::from([0x01; 20]),
+			value: U256::ZERO,
+			access_list: Default::default(),
+			blob_versioned_hashes,
+			max_fee_per_blob_gas: 1_000_000_000u128,
+			input: Bytes::new(),
+		};
+
+		let signature = signer.sign_hash_sync(&tx.signature_hash()).expect("Failed to sign message");
+		let tx_envelope = TxEnvelope::Eip4844(Signed::new_unhashed(TxEip4844Variant::TxEip4844(tx), signature));
+		let mut encoded_tx = Vec::new();
+		tx_envelope.encode_2718(&mut encoded_tx);
+		InclusionPayload { slot: 12345, signed_tx: Bytes::from(encoded_tx) }
+	}
+}
+
+/// Payload for exclusion commitments/constraints
+/// The transaction with `tx_hash` must not be included in the block for `slot`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExclusionPayload {
+	pub slot: u64,
+	pub tx_hash: B256,
+}
+
+impl ExclusionPayload {
+	/// ABI-encodes the ExclusionPayload struct for signing commitments
+	pub fn abi_encode(&self) -> Result<Bytes> {
+		alloy::sol! {
+			struct SolExclusionPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+			}
+		}
+
+		Ok(Bytes::from(SolExclusionPayload::abi_encode(&SolExclusionPayload {
+			slot: self.slot,
+			tx_hash: self.tx_hash,
+		})))
+	}
+
+	/// ABI-decodes an ExclusionPayload from bytes
+	pub fn abi_decode(data: &Bytes) -> Result<Self> {
+		alloy::sol! {
+			struct SolExclusionPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+			}
+		}
+
+		let decoded = SolExclusionPayload::abi_decode(data).wrap_err("Failed to decode ExclusionPayload")?;
+
+		Ok(ExclusionPayload { slot: decoded.slot, tx_hash: decoded.tx_hash })
+	}
+}
+
+/// Payload for exclusion commitment requests
+/// The owner of the excluded transaction signs `authorization_hash`, and `payment_tx` pays the committer for the
+/// exclusion like an inclusion commitment and is committed for inclusion in the same slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExclusionRequestPayload {
+	pub slot: u64,
+	pub tx_hash: B256,
+	pub payment_tx: Bytes,
+	pub signature: Bytes,
+}
+
+impl ExclusionRequestPayload {
+	/// ABI-encodes the ExclusionRequestPayload struct for signing commitments
+	pub fn abi_encode(&self) -> Result<Bytes> {
+		alloy::sol! {
+			struct SolExclusionRequestPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+				bytes payment_tx;
+				bytes signature;
+			}
+		}
+
+		Ok(Bytes::from(SolExclusionRequestPayload::abi_encode(&SolExclusionRequestPayload {
+			slot: self.slot,
+			tx_hash: self.tx_hash,
+			payment_tx: self.payment_tx.clone(),
+			signature: self.signature.clone(),
+		})))
+	}
+
+	/// ABI-decodes an ExclusionRequestPayload from bytes
+	pub fn abi_decode(data: &Bytes) -> Result<Self> {
+		alloy::sol! {
+			struct SolExclusionRequestPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+				bytes payment_tx;
+				bytes signature;
+			}
+		}
+
+		let decoded =
+			SolExclusionRequestPayload::abi_decode(data).wrap_err("Failed to decode ExclusionRequestPayload")?;
+
+		Ok(ExclusionRequestPayload {
+			slot: decoded.slot,
+			tx_hash: decoded.tx_hash,
+			payment_tx: decoded.payment_tx,
+			signature: decoded.signature,
+		})
+	}
+
+	/// Hash the owner of the excluded transaction signs:
+	/// `keccak256(abi.encode(EXCLUSION_COMMITMENT_TYPE, slot, tx_hash, keccak256(payment_tx)))`
+	pub fn authorization_hash(&self) -> B256 {
+		keccak256(
+			(U256::from(EXCLUSION_COMMITMENT_TYPE), self.slot, self.tx_hash, keccak256(&self.payment_tx))
+				.abi_encode_params(),
+		)
+	}
+
+	/// Address that signed the authorization, to be checked against the sender of the excluded transaction
+	pub fn authorizer(&self) -> Result<Address> {
+		recover_authorizer(&self.signature, &self.authorization_hash())
+	}
+
+	/// Payload of the exclusion constraint
+	pub fn exclusion(&self) -> ExclusionPayload {
+		ExclusionPayload { slot: self.slot, tx_hash: self.tx_hash }
+	}
+
+	/// Payload of the inclusion constraint of the payment transaction
+	pub fn payment(&self) -> InclusionPayload {
+		InclusionPayload { slot: self.slot, signed_tx: self.payment_tx.clone() }
+	}
+}
+
+/// Recovers the signer of an authorization from its 65 byte ECDSA signature
+fn recover_authorizer(signature: &Bytes, authorization_hash: &B256) -> Result<Address> {
+	let signature = Signature::try_from(signature.as_ref()).wrap_err("Invalid authorization signature")?;
+	signature.recover_address_from_prehash(authorization_hash).wrap_err("Failed to recover authorization signer")
+}
+
+/// Payload for ordering commitments/constraints
+/// The transaction with `tx_hash` must be included in the block for `slot` at an index of at most `max_index`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderingPayload {
+	pub slot: u64,
+	pub tx_hash: B256,
+	pub max_index: u64,
+}
+
+impl OrderingPayload {
+	/// ABI-encodes the OrderingPayload struct for signing commitments
+	pub fn abi_encode(&self) -> Result<Bytes> {
+		alloy::sol! {
+			struct SolOrderingPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+				uint64 max_index;
+			}
+		}
+
+		Ok(Bytes::from(SolOrderingPayload::abi_encode(&SolOrderingPayload {
+			slot: self.slot,
+			tx_hash: self.tx_hash,
+			max_index: self.max_index,
+		})))
+	}
+
+	/// ABI-decodes an OrderingPayload from bytes
+	pub fn abi_decode(data: &Bytes) -> Result<Self> {
+		alloy::sol! {
+			struct SolOrderingPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+				uint64 max_index;
+			}
+		}
+
+		let decoded = SolOrderingPayload::abi_decode(data).wrap_err("Failed to decode OrderingPayload")?;
+
+		Ok(OrderingPayload { slot: decoded.slot, tx_hash: decoded.tx_hash, max_index: decoded.max_index })
+	}
+}
+
+/// Payload for ordering commitment requests
+/// The sender of the inclusion committed transaction signs `authorization_hash`, and `payment_tx` pays the committer
+/// for the ordering like an inclusion commitment and is committed for inclusion in the same slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderingRequestPayload {
+	pub slot: u64,
+	pub tx_hash: B256,
+	pub max_index: u64,
+	pub payment_tx: Bytes,
+	pub signature: Bytes,
+}
+
+impl OrderingRequestPayload {
+	/// ABI-encodes the OrderingRequestPayload struct for signing commitments
+	pub fn abi_encode(&self) -> Result<Bytes> {
+		alloy::sol! {
+			struct SolOrderingRequestPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+				uint64 max_index;
+				bytes payment_tx;
+				bytes signature;
+			}
+		}
+
+		Ok(Bytes::from(SolOrderingRequestPayload::abi_encode(&SolOrderingRequestPayload {
+			slot: self.slot,
+			tx_hash: self.tx_hash,
+			max_index: self.max_index,
+			payment_tx: self.payment_tx.clone(),
+			signature: self.signature.clone(),
+		})))
+	}
+
+	/// ABI-decodes an OrderingRequestPayload from bytes
+	pub fn abi_decode(data: &Bytes) -> Result<Self> {
+		alloy::sol! {
+			struct SolOrderingRequestPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+				uint64 max_index;
+				bytes payment_tx;
+				bytes signature;
+			}
+		}
+
+		let decoded =
+			SolOrderingRequestPayload::abi_decode(data).wrap_err("Failed to decode OrderingRequestPayload")?;
+
+		Ok(OrderingRequestPayload {
+			slot: decoded.slot,
+			tx_hash: decoded.tx_hash,
+			max_index: decoded.max_index,
+			payment_tx: decoded.payment_tx,
+			signature: decoded.signature,
+		})
+	}
+
+	/// Hash the sender of the ordered transaction signs:
+	/// `keccak256(abi.encode(ORDERING_COMMITMENT_TYPE, slot, tx_hash, max_index, keccak256(payment_tx)))`
+	pub fn authorization_hash(&self) -> B256 {
+		keccak256(
+			(
+				U256::from(ORDERING_COMMITMENT_TYPE),
+				self.slot,
+				self.tx_hash,
+				self.max_index,
+				keccak256(&self.payment_tx),
+			)
+				.abi_encode_params(),
+		)
+	}
+
+	/// Address that signed the authorization, to be checked against the sender of the ordered transaction
+	pub fn authorizer(&self) -> Result<Address> {
+		recover_authorizer(&self.signature, &self.authorization_hash())
+	}
+
+	/// Payload of the ordering constraint
+	pub fn ordering(&self) -> OrderingPayload {
+		OrderingPayload { slot: self.slot, tx_hash: self.tx_hash, max_index: self.max_index }
+	}
+
+	/// Payload of the inclusion constraint of the payment transaction
+	pub fn payment(&self) -> InclusionPayload {
+		InclusionPayload { slot: self.slot, signed_tx: self.payment_tx.clone() }
+	}
+}
+
+/// Payload for bundle commitments
+/// The signed transactions must all be included in the block for `slot`, contiguously and in this order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundlePayload {
+	pub slot: u64,
+	pub signed_txs: Vec<Bytes>,
+}
+
+impl BundlePayload {
+	/// ABI-encodes the BundlePayload struct for signing commitments
+	pub fn abi_encode(&self) -> Result<Bytes> {
+		alloy::sol! {
+			struct SolBundlePayload {
+				uint64 slot;
+				bytes[] signed_txs;
+			}
+		}
+
+		Ok(Bytes::from(SolBundlePayload::abi_encode(&SolBundlePayload {
+			slot: self.slot,
+			signed_txs: self.signed_txs.clone(),
+		})))
+	}
+
+	/// ABI-decodes a BundlePayload from bytes
+	pub fn abi_decode(data: &Bytes) -> Result<Self> {
+		alloy::sol! {
+			struct SolBundlePayload {
+				uint64 slot;
+				bytes[] signed_txs;
+			}
+		}
+
+		let decoded = SolBundlePayload::abi_decode(data).wrap_err("Failed to decode BundlePayload")?;
+
+		Ok(BundlePayload { slot: decoded.slot, signed_txs: decoded.signed_txs })
+	}
+
+	/// Splits the bundle into one inclusion payload per transaction, in bundle order
+	pub fn inclusion_payloads(&self) -> Vec<InclusionPayload> {
+		self.signed_txs
+			.iter()
+			.map(|signed_tx| InclusionPayload { slot: self.slot, signed_tx: signed_tx.clone() })
+			.collect()
+	}
+
+	/// Hashes of the bundle transactions, in bundle order
+	pub fn tx_hashes(&self) -> Result<Vec<B256>> {
+		self.inclusion_payloads().iter().map(InclusionPayload::tx_hash).collect()
+	}
+}
+
+/// Payload for bundle constraints
+/// The transactions with `tx_hashes` must be at consecutive indices of the block for `slot`, in this order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleConstraintPayload {
+	pub slot: u64,
+	pub tx_hashes: Vec<B256>,
+}
+
+impl BundleConstraintPayload {
+	/// ABI-encodes the BundleConstraintPayload struct
+	pub fn abi_encode(&self) -> Result<Bytes> {
+		alloy::sol! {
+			struct SolBundleConstraintPayload {
+				uint64 slot;
+				bytes32[] tx_hashes;
+			}
+		}
+
+		Ok(Bytes::from(SolBundleConstraintPayload::abi_encode(&SolBundleConstraintPayload {
+			slot: self.slot,
+			tx_hashes: self.tx_hashes.clone(),
+		})))
+	}
+
+	/// ABI-decodes a BundleConstraintPayload from bytes
+	pub fn abi_decode(data: &Bytes) -> Result<Self> {
+		alloy::sol! {
+			struct SolBundleConstraintPayload {
+				uint64 slot;
+				bytes32[] tx_hashes;
+			}
+		}
+
+		let decoded =
+			SolBundleConstraintPayload::abi_decode(data).wrap_err("Failed to decode BundleConstraintPayload")?;
+
+		Ok(BundleConstraintPayload { slot: decoded.slot, tx_hashes: decoded.tx_hashes })
+	}
+}
+
+/// Payload for blob constraints
+/// The transaction with `tx_hash` must be in the block for `slot`, and the block's blobs bundle must carry
+/// a commitment for each of `blob_versioned_hashes`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobConstraintPayload {
+	pub slot: u64,
+	pub tx_hash: B256,
+	pub blob_versioned_hashes: Vec<B256>,
+}
+
+impl BlobConstraintPayload {
+	/// Builds the constraint payload of a blob transaction, errors if the transaction carries no blobs
+	pub fn from_inclusion_payload(inclusion_payload: &InclusionPayload) -> Result<Self> {
+		let tx_envelope = inclusion_payload.decode_transaction()?;
+		let blob_versioned_hashes = match tx_envelope.blob_versioned_hashes() {
+			Some(hashes) if !hashes.is_empty() => hashes.to_vec(),
+			_ => bail!("Transaction {} carries no blobs", tx_envelope.tx_hash()),
+		};
+		Ok(BlobConstraintPayload {
+			slot: inclusion_payload.slot,
+			tx_hash: inclusion_payload.tx_hash()?,
+			blob_versioned_hashes,
+		})
+	}
+
+	/// ABI-encodes the BlobConstraintPayload struct
+	pub fn abi_encode(&self) -> Result<Bytes> {
+		alloy::sol! {
+			struct SolBlobConstraintPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+				bytes32[] blob_versioned_hashes;
+			}
+		}
+
+		Ok(Bytes::from(SolBlobConstraintPayload::abi_encode(&SolBlobConstraintPayload {
+			slot: self.slot,
+			tx_hash: self.tx_hash,
+			blob_versioned_hashes: self.blob_versioned_hashes.clone(),
+		})))
+	}
+
+	/// ABI-decodes a BlobConstraintPayload from bytes
+	pub fn abi_decode(data: &Bytes) -> Result<Self> {
+		alloy::sol! {
+			struct SolBlobConstraintPayload {
+				uint64 slot;
+				bytes32 tx_hash;
+				bytes32[] blob_versioned_hashes;
+			}
+		}
+
+		let decoded = SolBlobConstraintPayload::abi_decode(data).wrap_err("Failed to decode BlobConstraintPayload")?;
+
+		Ok(BlobConstraintPayload {
+			slot: decoded.slot,
+			tx_hash: decoded.tx_hash,
+			blob_versioned_hashes: decoded.blob_versioned_hashes,
+		})
+	}
+}
+
+/// Creates the constraints of a bundle: the bundle constraint binding the transactions to contiguous
+/// positions, and one inclusion constraint per transaction in bundle order
+pub fn create_bundle_constraints(bundle_payload: &BundlePayload) -> Result<(Constraint, Vec<Constraint>)> {
+	let bundle_constraint = Constraint {
+		constraint_type: BUNDLE_CONSTRAINT_TYPE,
+		payload: BundleConstraintPayload { slot: bundle_payload.slot, tx_hashes: bundle_payload.tx_hashes()? }
+			.abi_encode()?,
+	};
+	let member_constraints = bundle_payload
+		.inclusion_payloads()
+		.iter()
+		.map(|inclusion_payload| {
+			Ok(Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: inclusion_payload.abi_encode()? })
+		})
+		.collect::<Result<Vec<_>>>()?;
+	Ok((bundle_constraint, member_constraints))
+}
+
+/// Creates the constraints of a blob commitment: the blob constraint binding the transaction to the
+/// blobs bundle, and the transaction's inclusion constraint
+pub fn create_blob_constraints(inclusion_payload: &InclusionPayload) -> Result<(Constraint, Constraint)> {
+	let blob_constraint = Constraint {
+		constraint_type: BLOB_CONSTRAINT_TYPE,
+		payload: BlobConstraintPayload::from_inclusion_payload(inclusion_payload)?.abi_encode()?,
+	};
+	let inclusion_constraint =
+		Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: inclusion_payload.abi_encode()? };
+	Ok((blob_constraint, inclusion_constraint))
+}
+
+/// Exclusion constraint of an exclusion commitment request and the inclusion constraint of its payment transaction
+pub fn create_exclusion_constraints(request_payload: &ExclusionRequestPayload) -> Result<(Constraint, Constraint)> {
+	let exclusion_constraint =
+		Constraint { constraint_type: EXCLUSION_CONSTRAINT_TYPE, payload: request_payload.exclusion().abi_encode()? };
+	let payment_constraint =
+		Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: request_payload.payment().abi_encode()? };
+	Ok((exclusion_constraint, payment_constraint))
+}
+
+/// Ordering constraint of an ordering commitment request and the inclusion constraint of its payment transaction
+pub fn create_ordering_constraints(request_payload: &OrderingRequestPayload) -> Result<(Constraint, Constraint)> {
+	let ordering_constraint =
+		Constraint { constraint_type: ORDERING_CONSTRAINT_TYPE, payload: request_payload.ordering().abi_encode()? };
+	let payment_constraint =
+		Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: request_payload.payment().abi_encode()? };
+	Ok((ordering_constraint, payment_constraint))
+}
+
+/// Constraints a gateway posts for a commitment of `commitment_type` with `payload`, in the order it posts them
+/// Inclusion commitments carry their constraint payload as is, exclusion, ordering, bundle and blob commitments
+/// expand into the constraints of `create_exclusion_constraints`, `create_ordering_constraints`,
+/// `create_bundle_constraints` and `create_blob_constraints`
+pub fn commitment_constraints(commitment_type: u64, payload: &Bytes) -> Result<Vec<Constraint>> {
+	let constraint_type = match commitment_type {
+		INCLUSION_COMMITMENT_TYPE => INCLUSION_CONSTRAINT_TYPE,
+		EXCLUSION_COMMITMENT_TYPE => {
+			let (exclusion_constraint, payment_constraint) =
+				create_exclusion_constraints(&ExclusionRequestPayload::abi_decode(payload)?)?;
+			return Ok(vec![exclusion_constraint, payment_constraint]);
+		}
+		ORDERING_COMMITMENT_TYPE => {
+			let (ordering_constraint, payment_constraint) =
+				create_ordering_constraints(&OrderingRequestPayload::abi_decode(payload)?)?;
+			return Ok(vec![ordering_constraint, payment_constraint]);
+		}
+		BUNDLE_COMMITMENT_TYPE => {
+			let (bundle_constraint, mut constraints) = create_bundle_constraints(&BundlePayload::abi_decode(payload)?)?;
+			constraints.insert(0, bundle_constraint);
+			return Ok(constraints);
+		}
+		BLOB_COMMITMENT_TYPE => {
+			let (blob_constraint, inclusion_constraint) =
+				create_blob_constraints(&InclusionPayload::abi_decode(payload)?)?;
+			return Ok(vec![blob_constraint, inclusion_constraint]);
+		}
+		commitment_type => bail!("Unsupported commitment type {}", commitment_type),
+	};
+	Ok(vec![Constraint { constraint_type, payload: payload.clone() }])
 }
 
 #[cfg(test)]
@@ -199,6 +822,138 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_fee_payload_without_quote_terms() -> Result<()> {
+		let request_hash = B256::repeat_byte(0x01);
+		let json = format!(r#"{{"request_hash":"{request_hash}","price_gwei":42}}"#);
+
+		let payload: FeePayload = serde_json::from_str(&json)?;
+		assert_eq!(payload.request_hash, request_hash);
+		assert_eq!(payload.price_gwei, 42);
+		assert_eq!(payload.min_priority_fee_per_gas, 0);
+		assert_eq!(payload.max_base_fee_per_gas, 0);
+		assert_eq!(payload.expires_at, 0);
+		Ok(())
+	}
+
+	#[test]
+	fn test_abi_roundtrip_exclusion_payload() -> Result<()> {
+		let payload = ExclusionPayload { slot: 67890, tx_hash: B256::from([0x0Fu8; 32]) };
+
+		let decoded = ExclusionPayload::abi_decode(&payload.abi_encode()?)?;
+		assert_eq!(decoded, payload);
+		Ok(())
+	}
+
+	#[test]
+	fn test_exclusion_request_payload() -> Result<()> {
+		use alloy::signers::{SignerSync, local::PrivateKeySigner};
+
+		let owner = PrivateKeySigner::random();
+		let mut payload = ExclusionRequestPayload {
+			slot: 67890,
+			tx_hash: B256::from([0x0Fu8; 32]),
+			payment_tx: InclusionPayload::random().signed_tx,
+			signature: Bytes::new(),
+		};
+		let signature = owner.sign_hash_sync(&payload.authorization_hash())?;
+		payload.signature = Bytes::from(signature.as_bytes().to_vec());
+
+		let decoded = ExclusionRequestPayload::abi_decode(&payload.abi_encode()?)?;
+		assert_eq!(decoded, payload);
+		assert_eq!(decoded.authorizer()?, owner.address());
+
+		// The authorization binds the payment transaction
+		let other_payment =
+			ExclusionRequestPayload { payment_tx: InclusionPayload::random().signed_tx, ..payload.clone() };
+		assert_ne!(other_payment.authorizer()?, owner.address());
+		assert!(
+			ExclusionRequestPayload { signature: Bytes::from_static(&[1, 2]), ..payload.clone() }.authorizer().is_err()
+		);
+
+		// The exclusion constraint carries the excluded hash, the payment is committed for inclusion in the slot
+		let constraints = commitment_constraints(EXCLUSION_COMMITMENT_TYPE, &payload.abi_encode()?)?;
+		assert_eq!(constraints.len(), 2);
+		assert_eq!(constraints[0].constraint_type, EXCLUSION_CONSTRAINT_TYPE);
+		assert_eq!(ExclusionPayload::abi_decode(&constraints[0].payload)?, payload.exclusion());
+		assert_eq!(constraints[1].constraint_type, INCLUSION_CONSTRAINT_TYPE);
+		assert_eq!(InclusionPayload::abi_decode(&constraints[1].payload)?, payload.payment());
+		Ok(())
+	}
+
+	#[test]
+	fn test_abi_roundtrip_ordering_payload() -> Result<()> {
+		let payload = OrderingPayload { slot: 67890, tx_hash: B256::from([0x0Fu8; 32]), max_index: 2 };
+
+		let decoded = OrderingPayload::abi_decode(&payload.abi_encode()?)?;
+		assert_eq!(decoded, payload);
+		Ok(())
+	}
+
+	#[test]
+	fn test_ordering_request_payload() -> Result<()> {
+		use alloy::signers::{SignerSync, local::PrivateKeySigner};
+
+		let owner = PrivateKeySigner::random();
+		let mut payload = OrderingRequestPayload {
+			slot: 67890,
+			tx_hash: B256::from([0x0Fu8; 32]),
+			max_index: 2,
+			payment_tx: InclusionPayload::random().signed_tx,
+			signature: Bytes::new(),
+		};
+		let signature = owner.sign_hash_sync(&payload.authorization_hash())?;
+		payload.signature = Bytes::from(signature.as_bytes().to_vec());
+
+		let decoded = OrderingRequestPayload::abi_decode(&payload.abi_encode()?)?;
+		assert_eq!(decoded, payload);
+		assert_eq!(decoded.authorizer()?, owner.address());
+
+		// The authorization binds the index and the payment transaction
+		assert_ne!(OrderingRequestPayload { max_index: 0, ..payload.clone() }.authorizer()?, owner.address());
+		let other_payment =
+			OrderingRequestPayload { payment_tx: InclusionPayload::random().signed_tx, ..payload.clone() };
+		assert_ne!(other_payment.authorizer()?, owner.address());
+
+		let constraints = commitment_constraints(ORDERING_COMMITMENT_TYPE, &payload.abi_encode()?)?;
+		assert_eq!(constraints.len(), 2);
+		assert_eq!(constraints[0].constraint_type, ORDERING_CONSTRAINT_TYPE);
+		assert_eq!(OrderingPayload::abi_decode(&constraints[0].payload)?, payload.ordering());
+		assert_eq!(constraints[1].constraint_type, INCLUSION_CONSTRAINT_TYPE);
+		assert_eq!(InclusionPayload::abi_decode(&constraints[1].payload)?, payload.payment());
+		Ok(())
+	}
+
+	#[test]
+	fn test_abi_roundtrip_bundle_payloads() -> Result<()> {
+		let first = InclusionPayload::random();
+		let second = InclusionPayload::random();
+		let bundle = BundlePayload { slot: 12345, signed_txs: vec![first.signed_tx.clone(), second.signed_tx.clone()] };
+
+		let decoded = BundlePayload::abi_decode(&bundle.abi_encode()?)?;
+		assert_eq!(decoded, bundle);
+		assert_eq!(decoded.tx_hashes()?, vec![first.tx_hash()?, second.tx_hash()?]);
+
+		let constraint = BundleConstraintPayload { slot: 12345, tx_hashes: bundle.tx_hashes()? };
+		assert_eq!(BundleConstraintPayload::abi_decode(&constraint.abi_encode()?)?, constraint);
+		Ok(())
+	}
+
+	#[test]
+	fn test_blob_constraint_payload() -> Result<()> {
+		let blob_versioned_hashes = vec![B256::repeat_byte(0x01), B256::repeat_byte(0x02)];
+		let payload = InclusionPayload::random_blob(blob_versioned_hashes.clone());
+
+		let constraint = BlobConstraintPayload::from_inclusion_payload(&payload)?;
+		assert_eq!(constraint.tx_hash, payload.tx_hash()?);
+		assert_eq!(constraint.blob_versioned_hashes, blob_versioned_hashes);
+		assert_eq!(BlobConstraintPayload::abi_decode(&constraint.abi_encode()?)?, constraint);
+
+		// Transactions without blobs have nothing to constrain
+		assert!(BlobConstraintPayload::from_inclusion_payload(&InclusionPayload::random()).is_err());
+		Ok(())
+	}
+
 	#[test]
 	fn test_tx_helpers() -> Result<()> {
 		let payload = InclusionPayload::random();
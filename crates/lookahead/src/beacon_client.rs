@@ -2,14 +2,22 @@
 #![allow(async_fn_in_trait)]
 
 use eyre::{Context, Result};
-use reqwest::Client;
+use reqwest::{Client, Url};
 use serde::Deserialize;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 
-use crate::constants::PROPOSER_DUTIES_ROUTE;
-use crate::types::{BeaconApiConfig, ProposerDutiesResponse};
+use crate::constants::{
+	ENDPOINT_PROBE_INTERVAL_SECS, EVENTS_ROUTE, GENESIS_ROUTE, HEAD_HEADER_ROUTE, NODE_SYNCING_ROUTE,
+	PROPOSER_DUTIES_ROUTE, SPEC_ROUTE, VALIDATOR_STATUS_ROUTE,
+};
+use crate::endpoints::{EndpointPool, EndpointStats};
+use crate::events::{BeaconEventStream, EventTopic};
+use crate::types::{
+	BeaconApiConfig, BlockHeaderResponse, GenesisResponse, ProposerDutiesResponse, SpecResponse, ValidatorId,
+	ValidatorResponse, ValidatorsResponse,
+};
 
 /// HTTP response containing status code and body
 #[derive(Debug, Clone)]
@@ -61,9 +69,14 @@ impl HttpClient for ReqwestClient {
 }
 
 /// Beacon API client for retrieving chain state and proposer information
+///
+/// Requests go to the configured endpoints in order of their score, see `EndpointPool`.
 pub struct BeaconApiClient<H: HttpClient> {
 	http_client: Arc<H>,
 	config: BeaconApiConfig,
+	endpoints: EndpointPool,
+	/// Race the two best endpoints on every request
+	hedged: bool,
 }
 
 // Manual Debug implementation since H might not implement Debug
@@ -76,7 +89,12 @@ impl<H: HttpClient> std::fmt::Debug for BeaconApiClient<H> {
 // Manual Clone implementation since H might not implement Clone
 impl<H: HttpClient> Clone for BeaconApiClient<H> {
 	fn clone(&self) -> Self {
-		Self { http_client: Arc::clone(&self.http_client), config: self.config.clone() }
+		Self {
+			http_client: Arc::clone(&self.http_client),
+			config: self.config.clone(),
+			endpoints: self.endpoints.clone(),
+			hedged: self.hedged,
+		}
 	}
 }
 
@@ -100,13 +118,26 @@ impl<H: HttpClient> BeaconApiClient<H> {
 			eyre::bail!("Request timeout must be greater than zero");
 		}
 
-		Ok(Self { http_client: Arc::new(http_client), config })
+		let endpoints = EndpointPool::new(config.primary_endpoint.clone(), config.fallback_endpoints.clone());
+		Ok(Self { http_client: Arc::new(http_client), config, endpoints, hedged: false })
+	}
+
+	/// A client sharing the endpoint scores of this one that races the two best endpoints on every request
+	///
+	/// Meant for requests close to a deadline, where waiting on a slow endpoint costs more than a duplicate request.
+	pub fn hedged(&self) -> Self {
+		Self { hedged: true, ..self.clone() }
+	}
+
+	/// Latency and error rate of every endpoint, in configuration order
+	pub fn endpoint_stats(&self) -> Vec<(Url, EndpointStats)> {
+		self.endpoints.stats()
 	}
 
 	/// Fetches proposer duties for the given epoch from the configured beacon endpoints.
 	///
-	/// Tries the primary endpoint first and falls back to configured fallback endpoints; returns
-	/// the first successful response or an error if all endpoints fail.
+	/// Tries the endpoints in order of score; returns the first successful response or an error if
+	/// all endpoints fail.
 	///
 	/// # Returns
 	///
@@ -117,49 +148,152 @@ impl<H: HttpClient> BeaconApiClient<H> {
 	///
 	pub async fn get_proposer_duties(&self, epoch: u64) -> Result<ProposerDutiesResponse> {
 		let endpoint = format!("{}/{}", PROPOSER_DUTIES_ROUTE, epoch);
+		self.get_with_fallback(&endpoint).await
+	}
 
-		// Try primary endpoint first, then fallbacks
-		let mut _last_error = None;
-
-		// Try primary endpoint
-		match self.make_request(&self.config.primary_endpoint.to_string(), &endpoint).await {
-			Ok(response) => return Ok(response),
-			Err(e) => {
-				warn!(
-					endpoint = %self.config.primary_endpoint,
-					epoch = epoch,
-					error = %e,
-					"Primary beacon endpoint failed, trying fallbacks"
-				);
-				_last_error = Some(e);
+	/// Fetches the chain genesis
+	pub async fn get_genesis(&self) -> Result<GenesisResponse> {
+		self.get_with_fallback(GENESIS_ROUTE).await
+	}
+
+	/// Fetches the chain's config spec, e.g. `SECONDS_PER_SLOT` and `SLOTS_PER_EPOCH`
+	pub async fn get_spec(&self) -> Result<SpecResponse> {
+		self.get_with_fallback(SPEC_ROUTE).await
+	}
+
+	/// Fetches the status of a validator at the head state
+	pub async fn get_validator(&self, id: &ValidatorId) -> Result<ValidatorResponse> {
+		self.get_with_fallback(&format!("{}/{}", VALIDATOR_STATUS_ROUTE, id)).await
+	}
+
+	/// Fetches the status of several validators at the head state in one request
+	/// Unknown validators are left out of the response
+	pub async fn get_validators(&self, ids: &[ValidatorId]) -> Result<ValidatorsResponse> {
+		let ids = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",");
+		self.get_with_fallback(&format!("{}?id={}", VALIDATOR_STATUS_ROUTE, ids)).await
+	}
+
+	/// Fetches the header of the head block
+	pub async fn get_head_header(&self) -> Result<BlockHeaderResponse> {
+		self.get_with_fallback(HEAD_HEADER_ROUTE).await
+	}
+
+	/// GET `endpoint` from the beacon endpoints in order of score, failing over to the next one on errors
+	/// A hedged client races the two best endpoints before failing over to the rest
+	async fn get_with_fallback<T>(&self, endpoint: &str) -> Result<T>
+	where
+		T: for<'de> Deserialize<'de>,
+	{
+		let ordered = self.endpoints.ordered();
+		let mut remaining = ordered.as_slice();
+		let mut last_error = None;
+
+		if self.hedged
+			&& let [first, second, rest @ ..] = ordered.as_slice()
+		{
+			match self.race(first, second, endpoint).await {
+				Ok(response) => return Ok(response),
+				Err(e) => last_error = Some(e),
 			}
+			remaining = rest;
 		}
 
-		// Try fallback endpoints
-		for fallback_endpoint in &self.config.fallback_endpoints {
-			match self.make_request(fallback_endpoint.to_string().as_str(), &endpoint).await {
-				Ok(response) => {
-					debug!(
-						endpoint = %fallback_endpoint,
-						epoch = epoch,
-						"Successfully retrieved proposer duties from fallback endpoint"
-					);
-					return Ok(response);
-				}
+		for base_url in remaining {
+			match self.scored_request(base_url, endpoint).await {
+				Ok(response) => return Ok(response),
 				Err(e) => {
 					warn!(
-						endpoint = %fallback_endpoint,
-						epoch = epoch,
+						endpoint = %base_url,
+						route = endpoint,
 						error = %e,
-						"Fallback beacon endpoint failed"
+						"Beacon endpoint failed, trying the next one"
 					);
-					_last_error = Some(e);
+					last_error = Some(e);
 				}
 			}
 		}
 
 		// All endpoints failed
-		Err(_last_error.unwrap_or_else(|| eyre::eyre!("No beacon endpoints configured")))
+		Err(last_error.unwrap_or_else(|| eyre::eyre!("No beacon endpoints configured")))
+	}
+
+	/// Send the request to two endpoints at once and return the first successful response
+	async fn race<T>(&self, first: &Url, second: &Url, endpoint: &str) -> Result<T>
+	where
+		T: for<'de> Deserialize<'de>,
+	{
+		let first_request = self.scored_request(first, endpoint);
+		let second_request = self.scored_request(second, endpoint);
+		tokio::pin!(first_request, second_request);
+
+		let mut first_error = None;
+		let mut second_error = None;
+		loop {
+			tokio::select! {
+				result = &mut first_request, if first_error.is_none() => match result {
+					Ok(response) => return Ok(response),
+					Err(e) => {
+						warn!(endpoint = %first, route = endpoint, error = %e, "Hedged beacon request failed");
+						first_error = Some(e);
+					}
+				},
+				result = &mut second_request, if second_error.is_none() => match result {
+					Ok(response) => return Ok(response),
+					Err(e) => {
+						warn!(endpoint = %second, route = endpoint, error = %e, "Hedged beacon request failed");
+						second_error = Some(e);
+					}
+				},
+				else => break,
+			}
+		}
+
+		Err(second_error.or(first_error).unwrap_or_else(|| eyre::eyre!("Hedged beacon request failed")))
+	}
+
+	/// `make_request`, recording the latency or failure in the endpoint's score
+	async fn scored_request<T>(&self, base_url: &Url, endpoint: &str) -> Result<T>
+	where
+		T: for<'de> Deserialize<'de>,
+	{
+		let start = Instant::now();
+		let result = self.make_request(base_url.as_str(), endpoint).await;
+		match &result {
+			Ok(_) => self.endpoints.record_success(base_url, start.elapsed()),
+			Err(_) => self.endpoints.record_failure(base_url),
+		}
+		result
+	}
+
+	/// Request every endpoint once to refresh its score
+	pub async fn probe_endpoints(&self) {
+		for base_url in self.endpoints.endpoints() {
+			if let Err(e) = self.scored_request::<serde_json::Value>(base_url, NODE_SYNCING_ROUTE).await {
+				debug!(endpoint = %base_url, error = %e, "Beacon endpoint probe failed");
+			}
+		}
+	}
+
+	/// Probe the endpoints every `ENDPOINT_PROBE_INTERVAL_SECS`, so an endpoint that was demoted after a bad
+	/// patch can win back traffic. Returns at once with a single endpoint, which has nothing to fail over to.
+	pub async fn run_probes(&self) {
+		if self.endpoints.len() < 2 {
+			return;
+		}
+
+		let mut interval = tokio::time::interval(Duration::from_secs(ENDPOINT_PROBE_INTERVAL_SECS));
+		loop {
+			interval.tick().await;
+			self.probe_endpoints().await;
+			for (base_url, stats) in self.endpoints.stats() {
+				debug!(
+					endpoint = %base_url,
+					latency_ms = ?stats.latency_ms,
+					error_rate = stats.error_rate,
+					"Beacon endpoint score"
+				);
+			}
+		}
 	}
 
 	/// Perform an HTTP GET to the given endpoint on `base_url`, validate the response, and deserialize the JSON body into `T`.
@@ -225,4 +359,40 @@ impl BeaconApiClient<ReqwestClient> {
 		let http_client = ReqwestClient::new(config.request_timeout_secs)?;
 		Self::new(config, http_client)
 	}
+
+	/// Subscribes to the event stream of the best scoring beacon endpoint that accepts the subscription.
+	///
+	/// The stream stays open until the beacon node closes it, so it is read without the request timeout.
+	///
+	/// # Errors
+	///
+	/// Returns an error if no endpoint accepts the subscription.
+	pub async fn subscribe_events(&self, topics: &[EventTopic]) -> Result<BeaconEventStream> {
+		let topics = topics.iter().map(|topic| topic.as_str()).collect::<Vec<_>>().join(",");
+		let client = Client::builder()
+			.connect_timeout(Duration::from_secs(self.config.request_timeout_secs))
+			.build()
+			.context("Failed to create HTTP client")?;
+
+		let mut last_error = None;
+		for base_url in self.endpoints.ordered() {
+			let url = format!("{}/{}?topics={}", base_url.as_str().trim_end_matches('/'), EVENTS_ROUTE, topics);
+			match client.get(&url).header("Accept", "text/event-stream").send().await {
+				Ok(response) if response.status().is_success() => {
+					debug!(url = %url, "Subscribed to beacon events");
+					return Ok(BeaconEventStream::new(response));
+				}
+				Ok(response) => {
+					warn!(url = %url, status = %response.status(), "Beacon event subscription rejected");
+					last_error =
+						Some(eyre::eyre!("Beacon event subscription failed with status {}", response.status()));
+				}
+				Err(e) => {
+					warn!(url = %url, error = %e, "Beacon event subscription failed");
+					last_error = Some(e.into());
+				}
+			}
+		}
+		Err(last_error.unwrap_or_else(|| eyre::eyre!("No beacon endpoints configured")))
+	}
 }
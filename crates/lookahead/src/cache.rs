@@ -0,0 +1,109 @@
+//! Epoch-level cache of proposer duties
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use eyre::{Result, eyre};
+use tracing::{debug, warn};
+
+use crate::beacon_client::{BeaconApiClient, ReqwestClient};
+use crate::constants::HEDGE_WINDOW_MS;
+use crate::slot_clock::SlotClock;
+use crate::types::ProposerDutiesResponse;
+
+/// Proposer duties of the current epoch and the epochs after it
+///
+/// `run()` refetches the duties of the current epoch and the next `prefetch_epochs` at the start of every slot,
+/// so callers polling the lookahead read the duties from memory instead of each asking the beacon node. Epochs
+/// missing from the cache are fetched on first use.
+pub struct LookaheadCache {
+	beacon_client: BeaconApiClient<ReqwestClient>,
+	slot_clock: Arc<SlotClock>,
+	/// Epochs after the current one that are prefetched
+	prefetch_epochs: u64,
+	duties: RwLock<BTreeMap<u64, ProposerDutiesResponse>>,
+	/// Time of the last successful fetch from the beacon node, in ms, 0 before the first one
+	refreshed_ms: AtomicU64,
+}
+
+impl LookaheadCache {
+	pub fn new(
+		beacon_client: BeaconApiClient<ReqwestClient>,
+		slot_clock: Arc<SlotClock>,
+		prefetch_epochs: u64,
+	) -> Self {
+		Self {
+			beacon_client,
+			slot_clock,
+			prefetch_epochs,
+			duties: RwLock::new(BTreeMap::new()),
+			refreshed_ms: AtomicU64::new(0),
+		}
+	}
+
+	/// Proposer duties of `epoch`, fetched from the beacon node if they are not cached
+	pub async fn get_proposer_duties(&self, epoch: u64) -> Result<ProposerDutiesResponse> {
+		let cached = self.duties.read().expect("proposer duties cache lock poisoned").get(&epoch).cloned();
+		match cached {
+			Some(duties) => Ok(duties),
+			None => self.refresh(epoch).await,
+		}
+	}
+
+	/// Fetch the proposer duties of `epoch` from the beacon node, replacing the cached ones
+	pub async fn refresh(&self, epoch: u64) -> Result<ProposerDutiesResponse> {
+		// Close to the next slot a slow endpoint would leave callers without duties, so race two endpoints
+		let beacon_client = if self.slot_clock.time_until_next_slot_ms() < HEDGE_WINDOW_MS {
+			self.beacon_client.hedged()
+		} else {
+			self.beacon_client.clone()
+		};
+		let duties = beacon_client.get_proposer_duties(epoch).await?;
+		self.duties.write().expect("proposer duties cache lock poisoned").insert(epoch, duties.clone());
+		self.refreshed_ms.store(self.slot_clock.now_ms(), Ordering::Relaxed);
+		Ok(duties)
+	}
+
+	/// Ok if the duties of the current epoch are cached and were fetched within the last `max_age_slots` slots
+	pub fn check_fresh(&self, max_age_slots: u64) -> Result<()> {
+		let current_epoch = self.slot_clock.slot_to_epoch(self.slot_clock.current_slot());
+		if !self.duties.read().expect("proposer duties cache lock poisoned").contains_key(&current_epoch) {
+			return Err(eyre!("no proposer duties for the current epoch {}", current_epoch));
+		}
+		let age_ms = self.slot_clock.now_ms().saturating_sub(self.refreshed_ms.load(Ordering::Relaxed));
+		if age_ms > max_age_slots * self.slot_clock.slot_duration_ms() {
+			return Err(eyre!("proposer duties last fetched {}s ago", age_ms / 1000));
+		}
+		Ok(())
+	}
+
+	/// Drop every cached epoch, e.g. after a reorg changed the duties
+	pub fn invalidate(&self) {
+		self.duties.write().expect("proposer duties cache lock poisoned").clear();
+	}
+
+	/// Refetch the current and prefetched epochs, dropping the epochs before the current one
+	pub async fn prefetch(&self) {
+		let current_epoch = self.slot_clock.slot_to_epoch(self.slot_clock.current_slot());
+		self.duties.write().expect("proposer duties cache lock poisoned").retain(|epoch, _| *epoch >= current_epoch);
+
+		for epoch in current_epoch..=current_epoch + self.prefetch_epochs {
+			match self.refresh(epoch).await {
+				Ok(_) => debug!("Prefetched proposer duties for epoch {}", epoch),
+				Err(e) => warn!("Failed to prefetch proposer duties for epoch {}: {}", epoch, e),
+			}
+		}
+	}
+
+	/// Prefetch at startup and at the start of every slot, driven by the slot clock ticks
+	pub async fn run(&self) {
+		let mut ticks = self.slot_clock.ticks();
+		loop {
+			self.prefetch().await;
+			// A lagged receiver only missed ticks, the next one still marks a new slot
+			if let Err(tokio::sync::broadcast::error::RecvError::Closed) = ticks.recv().await {
+				return;
+			}
+		}
+	}
+}
@@ -0,0 +1,62 @@
+//! Sources of the current time for the slot clock
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source of the current Unix time
+pub trait Clock: Send + Sync {
+	/// Unix time in milliseconds
+	fn now_ms(&self) -> u64;
+}
+
+/// The host clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now_ms(&self) -> u64 {
+		SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+	}
+}
+
+/// A clock that only moves when told to, for tests and simulations at exact slot boundaries
+#[derive(Debug, Default)]
+pub struct ManualClock {
+	now_ms: AtomicU64,
+}
+
+impl ManualClock {
+	pub fn new(now_ms: u64) -> Self {
+		Self { now_ms: AtomicU64::new(now_ms) }
+	}
+
+	pub fn set_ms(&self, now_ms: u64) {
+		self.now_ms.store(now_ms, Ordering::Relaxed);
+	}
+
+	pub fn advance_ms(&self, ms: u64) {
+		self.now_ms.fetch_add(ms, Ordering::Relaxed);
+	}
+}
+
+impl Clock for ManualClock {
+	fn now_ms(&self) -> u64 {
+		self.now_ms.load(Ordering::Relaxed)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_manual_clock_moves_only_when_told() {
+		let clock = ManualClock::new(1_000);
+		assert_eq!(clock.now_ms(), 1_000);
+
+		clock.advance_ms(500);
+		assert_eq!(clock.now_ms(), 1_500);
+
+		clock.set_ms(42);
+		assert_eq!(clock.now_ms(), 42);
+	}
+}
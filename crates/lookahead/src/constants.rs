@@ -2,11 +2,42 @@ pub const PROPOSER_DUTIES_ROUTE: &str = "eth/v1/validator/duties/proposer";
 
 pub const VALIDATOR_STATUS_ROUTE: &str = "eth/v1/beacon/states/head/validators";
 
-/// Ethereum slot duration in seconds
+pub const GENESIS_ROUTE: &str = "eth/v1/beacon/genesis";
+
+pub const SPEC_ROUTE: &str = "eth/v1/config/spec";
+
+pub const EVENTS_ROUTE: &str = "eth/v1/events";
+
+pub const HEAD_HEADER_ROUTE: &str = "eth/v1/beacon/headers/head";
+
+pub const NODE_SYNCING_ROUTE: &str = "eth/v1/node/syncing";
+
+/// Mainnet slot duration in seconds, the `SlotClock` reads the chain's from the beacon node
 pub const SLOT_DURATION_SECONDS: u64 = 12;
 
-/// Ethereum slot duration in milliseconds
+/// Mainnet slot duration in milliseconds
 pub const SLOT_DURATION_MS: u64 = SLOT_DURATION_SECONDS * 1000;
 
-/// Slots per epoch
+/// Mainnet slots per epoch
 pub const SLOTS_PER_EPOCH: u64 = 32;
+
+/// Clock corrections larger than this are logged, they point at a badly skewed host clock or a faulty beacon node
+pub const MAX_CLOCK_SKEW_MS: i64 = 2_000;
+
+/// Seconds to wait before resubscribing to the beacon event stream after it failed
+pub const EVENTS_RESUBSCRIBE_DELAY_SECS: u64 = 5;
+
+/// Number of slot ticks buffered for slow subscribers
+pub const SLOT_TICKS_CHANNEL_SIZE: usize = 16;
+
+/// Weight of the latest request in the latency and error rate averages of a beacon endpoint
+pub const ENDPOINT_SCORE_DECAY: f64 = 0.2;
+
+/// Score penalty in milliseconds of an endpoint whose requests all fail
+pub const ENDPOINT_ERROR_PENALTY_MS: f64 = 5_000.0;
+
+/// Seconds between probes of every beacon endpoint, so demoted endpoints can win back traffic
+pub const ENDPOINT_PROBE_INTERVAL_SECS: u64 = 30;
+
+/// Requests needed within this many milliseconds are hedged across the two best beacon endpoints
+pub const HEDGE_WINDOW_MS: i64 = 4_000;
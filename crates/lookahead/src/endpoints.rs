@@ -0,0 +1,126 @@
+//! Scored pool of beacon node endpoints
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use reqwest::Url;
+
+use crate::constants::{ENDPOINT_ERROR_PENALTY_MS, ENDPOINT_SCORE_DECAY};
+
+/// Latency and error rate of an endpoint, as exponentially weighted moving averages
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EndpointStats {
+	/// `None` until the first successful request
+	pub latency_ms: Option<f64>,
+	/// Share of recent requests that failed, between 0 and 1
+	pub error_rate: f64,
+}
+
+impl EndpointStats {
+	/// Expected cost of a request in milliseconds, lower is better
+	/// Endpoints that were never reached score as fast, so they are tried before known slow ones
+	pub fn score(&self) -> f64 {
+		self.latency_ms.unwrap_or(0.0) + self.error_rate * ENDPOINT_ERROR_PENALTY_MS
+	}
+}
+
+/// Beacon endpoints ordered by score
+///
+/// Every request and probe updates the stats of its endpoint. Endpoints are tried in order of increasing score,
+/// ties keep the configuration order so the primary endpoint wins until it is measured to be worse.
+#[derive(Debug, Clone)]
+pub struct EndpointPool {
+	endpoints: Vec<Url>,
+	stats: Arc<RwLock<Vec<EndpointStats>>>,
+}
+
+impl EndpointPool {
+	/// Pool of the primary endpoint followed by the fallbacks
+	pub fn new(primary: Url, fallbacks: Vec<Url>) -> Self {
+		let endpoints = std::iter::once(primary).chain(fallbacks).collect::<Vec<_>>();
+		let stats = Arc::new(RwLock::new(vec![EndpointStats::default(); endpoints.len()]));
+		Self { endpoints, stats }
+	}
+
+	pub fn len(&self) -> usize {
+		self.endpoints.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.endpoints.is_empty()
+	}
+
+	/// Every endpoint, in configuration order
+	pub fn endpoints(&self) -> &[Url] {
+		&self.endpoints
+	}
+
+	/// Endpoints in order of preference
+	pub fn ordered(&self) -> Vec<Url> {
+		let stats = self.stats.read().expect("beacon endpoint stats lock poisoned");
+
+		let mut indices = (0..self.endpoints.len()).collect::<Vec<_>>();
+		indices.sort_by(|&a, &b| stats[a].score().total_cmp(&stats[b].score()).then(a.cmp(&b)));
+
+		indices.into_iter().map(|i| self.endpoints[i].clone()).collect()
+	}
+
+	/// Current stats of every endpoint, in configuration order
+	pub fn stats(&self) -> Vec<(Url, EndpointStats)> {
+		let stats = self.stats.read().expect("beacon endpoint stats lock poisoned");
+		self.endpoints.iter().cloned().zip(stats.iter().copied()).collect()
+	}
+
+	pub fn record_success(&self, endpoint: &Url, latency: Duration) {
+		self.update(endpoint, |stats| {
+			let latency_ms = latency.as_secs_f64() * 1000.0;
+			stats.latency_ms = Some(match stats.latency_ms {
+				Some(previous) => previous + ENDPOINT_SCORE_DECAY * (latency_ms - previous),
+				None => latency_ms,
+			});
+			stats.error_rate -= ENDPOINT_SCORE_DECAY * stats.error_rate;
+		});
+	}
+
+	pub fn record_failure(&self, endpoint: &Url) {
+		self.update(endpoint, |stats| stats.error_rate += ENDPOINT_SCORE_DECAY * (1.0 - stats.error_rate));
+	}
+
+	fn update(&self, endpoint: &Url, f: impl FnOnce(&mut EndpointStats)) {
+		let mut stats = self.stats.write().expect("beacon endpoint stats lock poisoned");
+		if let Some(index) = self.endpoints.iter().position(|configured| configured == endpoint) {
+			f(&mut stats[index]);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_pool_prefers_fast_and_reliable_endpoints() {
+		let primary = Url::parse("http://primary:5052").unwrap();
+		let fallback = Url::parse("http://fallback:5052").unwrap();
+		let pool = EndpointPool::new(primary.clone(), vec![fallback.clone()]);
+
+		// Configuration order until measured
+		assert_eq!(pool.ordered(), vec![primary.clone(), fallback.clone()]);
+
+		// A slow primary loses to a fast fallback
+		pool.record_success(&primary, Duration::from_millis(2_000));
+		pool.record_success(&fallback, Duration::from_millis(50));
+		assert_eq!(pool.ordered(), vec![fallback.clone(), primary.clone()]);
+
+		// Failures outweigh latency
+		for _ in 0..3 {
+			pool.record_failure(&fallback);
+		}
+		assert_eq!(pool.ordered(), vec![primary.clone(), fallback.clone()]);
+
+		// The fallback recovers once it answers again
+		for _ in 0..20 {
+			pool.record_success(&fallback, Duration::from_millis(50));
+		}
+		assert_eq!(pool.ordered(), vec![fallback, primary]);
+	}
+}
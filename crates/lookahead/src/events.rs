@@ -0,0 +1,280 @@
+//! Beacon node event stream (`/eth/v1/events`, server-sent events)
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256};
+use eyre::{Context, Result, eyre};
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::warn;
+
+use crate::beacon_client::{BeaconApiClient, ReqwestClient};
+use crate::constants::EVENTS_RESUBSCRIBE_DELAY_SECS;
+
+/// Topics of the beacon node event stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTopic {
+	Head,
+	PayloadAttributes,
+	ChainReorg,
+}
+
+impl EventTopic {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			EventTopic::Head => "head",
+			EventTopic::PayloadAttributes => "payload_attributes",
+			EventTopic::ChainReorg => "chain_reorg",
+		}
+	}
+}
+
+/// A new head block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeadEvent {
+	#[serde(deserialize_with = "quoted_u64")]
+	pub slot: u64,
+	pub block: B256,
+	/// Whether the block is the first of an epoch
+	pub epoch_transition: bool,
+	pub previous_duty_dependent_root: B256,
+	pub current_duty_dependent_root: B256,
+}
+
+/// The head moved to a block that does not descend from the previous head
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainReorgEvent {
+	#[serde(deserialize_with = "quoted_u64")]
+	pub slot: u64,
+	#[serde(deserialize_with = "quoted_u64")]
+	pub depth: u64,
+	pub old_head_block: B256,
+	pub new_head_block: B256,
+	#[serde(deserialize_with = "quoted_u64")]
+	pub epoch: u64,
+}
+
+/// Attributes of the payload the next proposer will build on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayloadAttributesEvent {
+	pub version: String,
+	pub data: PayloadAttributesData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayloadAttributesData {
+	#[serde(deserialize_with = "quoted_u64")]
+	pub proposer_index: u64,
+	#[serde(deserialize_with = "quoted_u64")]
+	pub proposal_slot: u64,
+	#[serde(deserialize_with = "quoted_u64")]
+	pub parent_block_number: u64,
+	pub parent_block_root: B256,
+	pub parent_block_hash: B256,
+	pub payload_attributes: EventPayloadAttributes,
+}
+
+/// Attributes the execution client builds the payload of the proposal slot with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventPayloadAttributes {
+	#[serde(deserialize_with = "quoted_u64")]
+	pub timestamp: u64,
+	pub prev_randao: B256,
+	pub suggested_fee_recipient: Address,
+	#[serde(default)]
+	pub withdrawals: Vec<EventWithdrawal>,
+	#[serde(default)]
+	pub parent_beacon_block_root: Option<B256>,
+}
+
+/// A withdrawal of the payload, with the beacon API's quoted integers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventWithdrawal {
+	#[serde(deserialize_with = "quoted_u64")]
+	pub index: u64,
+	#[serde(deserialize_with = "quoted_u64")]
+	pub validator_index: u64,
+	pub address: Address,
+	/// Amount in gwei
+	#[serde(deserialize_with = "quoted_u64")]
+	pub amount: u64,
+}
+
+/// An event of one of the subscribed topics
+#[derive(Debug, Clone)]
+pub enum BeaconEvent {
+	Head(HeadEvent),
+	PayloadAttributes(PayloadAttributesEvent),
+	ChainReorg(ChainReorgEvent),
+}
+
+impl BeaconEvent {
+	/// Parse the data of an event, `None` for topics that are not handled
+	pub fn parse(event: &str, data: &str) -> Result<Option<Self>> {
+		let parsed = match event {
+			"head" => BeaconEvent::Head(serde_json::from_str(data)?),
+			"payload_attributes" => BeaconEvent::PayloadAttributes(serde_json::from_str(data)?),
+			"chain_reorg" => BeaconEvent::ChainReorg(serde_json::from_str(data)?),
+			_ => return Ok(None),
+		};
+		Ok(Some(parsed))
+	}
+}
+
+/// Events of an open `/eth/v1/events` subscription
+pub struct BeaconEventStream {
+	response: reqwest::Response,
+	buffer: String,
+}
+
+impl BeaconEventStream {
+	pub(crate) fn new(response: reqwest::Response) -> Self {
+		Self { response, buffer: String::new() }
+	}
+
+	/// The next event, `None` once the beacon node closed the stream
+	pub async fn next(&mut self) -> Result<Option<BeaconEvent>> {
+		loop {
+			while let Some((event, data)) = next_message(&mut self.buffer) {
+				match BeaconEvent::parse(&event, &data) {
+					Ok(Some(parsed)) => return Ok(Some(parsed)),
+					Ok(None) => continue,
+					Err(e) => return Err(e).wrap_err_with(|| format!("Failed to parse {} event", event)),
+				}
+			}
+
+			match self.response.chunk().await.wrap_err("Beacon event stream failed")? {
+				Some(chunk) => self.buffer.push_str(
+					std::str::from_utf8(&chunk).map_err(|e| eyre!("Beacon event stream is not UTF-8: {}", e))?,
+				),
+				None => return Ok(None),
+			}
+		}
+	}
+}
+
+/// Subscription to the beacon event stream that resubscribes whenever the stream fails or is closed
+pub struct EventSubscription {
+	beacon_client: BeaconApiClient<ReqwestClient>,
+	topics: Vec<EventTopic>,
+	stream: Option<BeaconEventStream>,
+}
+
+impl EventSubscription {
+	/// Subscribes lazily, on the first call to `next()`
+	pub fn new(beacon_client: BeaconApiClient<ReqwestClient>, topics: Vec<EventTopic>) -> Self {
+		Self { beacon_client, topics, stream: None }
+	}
+
+	/// The next event, waits while the beacon node is unreachable
+	pub async fn next(&mut self) -> BeaconEvent {
+		loop {
+			if self.stream.is_none() {
+				match self.beacon_client.subscribe_events(&self.topics).await {
+					Ok(stream) => self.stream = Some(stream),
+					Err(e) => {
+						warn!(
+							"Failed to subscribe to beacon events, retrying in {}s: {}",
+							EVENTS_RESUBSCRIBE_DELAY_SECS, e
+						);
+						tokio::time::sleep(Duration::from_secs(EVENTS_RESUBSCRIBE_DELAY_SECS)).await;
+						continue;
+					}
+				}
+			}
+			let Some(stream) = self.stream.as_mut() else {
+				continue;
+			};
+
+			match stream.next().await {
+				Ok(Some(event)) => return event,
+				Ok(None) => {
+					warn!("Beacon event stream closed, resubscribing");
+					self.stream = None;
+				}
+				Err(e) => {
+					warn!("Beacon event stream failed, resubscribing: {}", e);
+					self.stream = None;
+					tokio::time::sleep(Duration::from_secs(EVENTS_RESUBSCRIBE_DELAY_SECS)).await;
+				}
+			}
+		}
+	}
+}
+
+/// Take the next complete message off the buffer, returns its event name and data
+/// Comments and messages without data, such as keep-alives, are skipped
+fn next_message(buffer: &mut String) -> Option<(String, String)> {
+	loop {
+		let normalized = buffer.replace("\r\n", "\n");
+		let end = normalized.find("\n\n")?;
+		let message = normalized[..end].to_string();
+		*buffer = normalized[end + 2..].to_string();
+
+		let mut event = "message".to_string();
+		let mut data = Vec::new();
+		for line in message.lines() {
+			if let Some(value) = line.strip_prefix("event:") {
+				event = value.trim().to_string();
+			} else if let Some(value) = line.strip_prefix("data:") {
+				data.push(value.strip_prefix(' ').unwrap_or(value));
+			}
+		}
+		if !data.is_empty() {
+			return Some((event, data.join("\n")));
+		}
+	}
+}
+
+fn quoted_u64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u64, D::Error> {
+	String::deserialize(deserializer)?.parse::<u64>().map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_event_stream_messages() {
+		let root = B256::repeat_byte(1);
+		let head = format!(
+			r#"{{"slot":"10","block":"{root}","state":"{root}","epoch_transition":true,"previous_duty_dependent_root":"{root}","current_duty_dependent_root":"{root}","execution_optimistic":false}}"#
+		);
+		let reorg = format!(
+			r#"{{"slot":"11","depth":"2","old_head_block":"{root}","new_head_block":"{root}","old_head_state":"{root}","new_head_state":"{root}","epoch":"0","execution_optimistic":false}}"#
+		);
+		let mut buffer = format!(
+			": keep-alive\n\nevent: head\ndata: {head}\n\nevent: chain_reorg\r\ndata: {reorg}\r\n\r\nevent: head\n"
+		);
+
+		let (event, data) = next_message(&mut buffer).unwrap();
+		match BeaconEvent::parse(&event, &data).unwrap() {
+			Some(BeaconEvent::Head(head)) => {
+				assert_eq!(head.slot, 10);
+				assert!(head.epoch_transition);
+			}
+			other => panic!("Expected a head event, got {:?}", other),
+		}
+
+		let (event, data) = next_message(&mut buffer).unwrap();
+		match BeaconEvent::parse(&event, &data).unwrap() {
+			Some(BeaconEvent::ChainReorg(reorg)) => assert_eq!(reorg.depth, 2),
+			other => panic!("Expected a chain_reorg event, got {:?}", other),
+		}
+
+		let attributes = format!(
+			r#"{{"version":"electra","data":{{"proposer_index":"3","proposal_slot":"12","parent_block_number":"9","parent_block_root":"{root}","parent_block_hash":"{root}","payload_attributes":{{"timestamp":"1700000000","prev_randao":"{root}","suggested_fee_recipient":"0x0000000000000000000000000000000000000001","withdrawals":[{{"index":"1","validator_index":"2","address":"0x0000000000000000000000000000000000000002","amount":"32"}}],"parent_beacon_block_root":"{root}"}}}}}}"#
+		);
+		match BeaconEvent::parse("payload_attributes", &attributes).unwrap() {
+			Some(BeaconEvent::PayloadAttributes(event)) => {
+				assert_eq!(event.data.proposal_slot, 12);
+				assert_eq!(event.data.payload_attributes.timestamp, 1_700_000_000);
+				assert_eq!(event.data.payload_attributes.withdrawals[0].amount, 32);
+			}
+			other => panic!("Expected a payload_attributes event, got {:?}", other),
+		}
+
+		// The last message is incomplete and stays buffered
+		assert!(next_message(&mut buffer).is_none());
+		assert_eq!(buffer, "event: head\n");
+		assert!(BeaconEvent::parse("block", "{}").unwrap().is_none());
+	}
+}
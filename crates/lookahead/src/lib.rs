@@ -1,4 +1,9 @@
 pub mod beacon_client;
+pub mod cache;
+pub mod clock;
 pub mod constants;
+pub mod endpoints;
+pub mod events;
+pub mod slot_clock;
 pub mod types;
 pub mod utils;
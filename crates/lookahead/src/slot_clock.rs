@@ -0,0 +1,279 @@
+//! Beacon slot clock kept in sync with a beacon node
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use commit_boost::prelude::Chain;
+use eyre::{Result, eyre};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use crate::beacon_client::{BeaconApiClient, ReqwestClient};
+use crate::clock::{Clock, SystemClock};
+use crate::constants::{MAX_CLOCK_SKEW_MS, SLOT_DURATION_MS, SLOT_TICKS_CHANNEL_SIZE, SLOTS_PER_EPOCH};
+
+/// Slot clock of the beacon chain
+///
+/// Starts from the chain's genesis time, mainnet slot timing and the host clock. With a beacon client, `sync()`
+/// discovers the genesis time, slot duration and epoch length of the chain and corrects the host clock against
+/// the head block. `run()` resyncs every epoch and ticks at the start of every slot.
+///
+/// The host clock can be replaced by any `Clock`, e.g. a `ManualClock` to test at exact slot boundaries.
+pub struct SlotClock {
+	/// Genesis time in milliseconds
+	genesis_time_ms: AtomicU64,
+	slot_duration_ms: AtomicU64,
+	slots_per_epoch: AtomicU64,
+	/// Milliseconds the host clock is behind the beacon chain
+	skew_ms: AtomicI64,
+	/// Host clock
+	clock: RwLock<Arc<dyn Clock>>,
+	beacon_client: Option<BeaconApiClient<ReqwestClient>>,
+	ticks: broadcast::Sender<u64>,
+}
+
+impl SlotClock {
+	/// A clock based on the chain's genesis time and the host clock
+	pub fn new(chain: &Chain) -> Self {
+		Self::from_genesis_time(chain.genesis_time_sec())
+	}
+
+	/// A clock based on a genesis time in seconds and the host clock
+	pub fn from_genesis_time(genesis_time: u64) -> Self {
+		let (ticks, _) = broadcast::channel(SLOT_TICKS_CHANNEL_SIZE);
+		Self {
+			genesis_time_ms: AtomicU64::new(genesis_time * 1000),
+			slot_duration_ms: AtomicU64::new(SLOT_DURATION_MS),
+			slots_per_epoch: AtomicU64::new(SLOTS_PER_EPOCH),
+			skew_ms: AtomicI64::new(0),
+			clock: RwLock::new(Arc::new(SystemClock)),
+			beacon_client: None,
+			ticks,
+		}
+	}
+
+	/// Sync the clock against a beacon node
+	pub fn with_beacon_client(mut self, beacon_client: BeaconApiClient<ReqwestClient>) -> Self {
+		self.beacon_client = Some(beacon_client);
+		self
+	}
+
+	/// Read the time from `clock` instead of the host clock
+	pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+		self.set_clock(clock);
+		self
+	}
+
+	/// Replace the clock of a running slot clock, shared by every service holding it
+	pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+		*self.clock.write().expect("slot clock lock poisoned") = clock;
+	}
+
+	/// Genesis time in seconds
+	pub fn genesis_time(&self) -> u64 {
+		self.genesis_time_ms.load(Ordering::Relaxed) / 1000
+	}
+
+	/// Slot duration in milliseconds
+	pub fn slot_duration_ms(&self) -> u64 {
+		self.slot_duration_ms.load(Ordering::Relaxed)
+	}
+
+	pub fn slots_per_epoch(&self) -> u64 {
+		self.slots_per_epoch.load(Ordering::Relaxed)
+	}
+
+	pub fn slot_to_epoch(&self, slot: u64) -> u64 {
+		slot / self.slots_per_epoch()
+	}
+
+	pub fn epoch_to_first_slot(&self, epoch: u64) -> u64 {
+		epoch * self.slots_per_epoch()
+	}
+
+	pub fn epoch_to_last_slot(&self, epoch: u64) -> u64 {
+		(epoch + 1) * self.slots_per_epoch() - 1
+	}
+
+	/// Unix timestamp in seconds of the start of `slot`
+	pub fn slot_timestamp(&self, slot: u64) -> u64 {
+		self.slot_start_ms(slot) / 1000
+	}
+
+	/// Unix time in milliseconds of the start of `slot`
+	pub fn slot_start_ms(&self, slot: u64) -> u64 {
+		self.genesis_time_ms.load(Ordering::Relaxed) + slot * self.slot_duration_ms()
+	}
+
+	/// Unix time in milliseconds, corrected for the skew of the host clock
+	pub fn now_ms(&self) -> u64 {
+		self.host_time_ms().saturating_add_signed(self.skew_ms.load(Ordering::Relaxed))
+	}
+
+	/// The current slot, `0` before genesis
+	pub fn current_slot(&self) -> u64 {
+		self.now_ms().saturating_sub(self.genesis_time_ms.load(Ordering::Relaxed)) / self.slot_duration_ms()
+	}
+
+	/// Milliseconds until the start of `slot`, negative once it has started
+	pub fn slot_deadline(&self, slot: u64) -> i64 {
+		self.slot_start_ms(slot) as i64 - self.now_ms() as i64
+	}
+
+	/// Milliseconds until the start of the next slot
+	pub fn time_until_next_slot_ms(&self) -> i64 {
+		self.slot_deadline(self.current_slot() + 1)
+	}
+
+	/// Slot numbers sent at the start of each slot while `run()` is running
+	pub fn ticks(&self) -> broadcast::Receiver<u64> {
+		self.ticks.subscribe()
+	}
+
+	/// Fetch the genesis time, spec and head slot from the beacon node, a no-op without a beacon client
+	pub async fn sync(&self) -> Result<()> {
+		let Some(beacon_client) = &self.beacon_client else {
+			return Ok(());
+		};
+		let spec = beacon_client.get_spec().await?;
+		self.set_spec(spec.parse_u64("SECONDS_PER_SLOT")? * 1000, spec.parse_u64("SLOTS_PER_EPOCH")?)?;
+		let genesis_time = beacon_client.get_genesis().await?.data.parse_genesis_time()?;
+		let head_slot = beacon_client.get_head_header().await?.parse_slot()?;
+		self.observe(genesis_time, head_slot, self.host_time_ms());
+		Ok(())
+	}
+
+	/// Set the slot timing of the chain, logging when it differs from the current one
+	fn set_spec(&self, slot_duration_ms: u64, slots_per_epoch: u64) -> Result<()> {
+		if slot_duration_ms == 0 || slots_per_epoch == 0 {
+			return Err(eyre!("Invalid spec: {}ms slots, {} slots per epoch", slot_duration_ms, slots_per_epoch));
+		}
+		let previous_duration_ms = self.slot_duration_ms.swap(slot_duration_ms, Ordering::Relaxed);
+		let previous_slots_per_epoch = self.slots_per_epoch.swap(slots_per_epoch, Ordering::Relaxed);
+		if previous_duration_ms != slot_duration_ms || previous_slots_per_epoch != slots_per_epoch {
+			info!("Chain spec from the beacon node: {}ms slots, {} slots per epoch", slot_duration_ms, slots_per_epoch);
+		}
+		Ok(())
+	}
+
+	/// Update the clock from the beacon node's genesis time and head slot, seen at host time `host_now_ms`
+	///
+	/// The head block was produced in its slot, so a host clock before the start of that slot is behind by
+	/// the difference. A head behind the host clock may just be missed slots, so the clock never moves back
+	/// past the host clock.
+	fn observe(&self, genesis_time: u64, head_slot: u64, host_now_ms: u64) {
+		let genesis_time_ms = genesis_time * 1000;
+		let previous = self.genesis_time_ms.swap(genesis_time_ms, Ordering::Relaxed);
+		if previous != genesis_time_ms {
+			warn!("Beacon node genesis time {} differs from the configured {}", genesis_time, previous / 1000);
+		}
+
+		let head_start_ms = genesis_time_ms + head_slot * self.slot_duration_ms();
+		let skew_ms = (head_start_ms as i64 - host_now_ms as i64).max(0);
+		if skew_ms > MAX_CLOCK_SKEW_MS {
+			warn!("Host clock is {}ms behind the beacon node head at slot {}", skew_ms, head_slot);
+		} else {
+			debug!("Slot clock synced at head slot {}, skew {}ms", head_slot, skew_ms);
+		}
+		self.skew_ms.store(skew_ms, Ordering::Relaxed);
+	}
+
+	/// Tick at the start of every slot, resyncing with the beacon node every epoch
+	/// `sync()` is expected to have run at startup
+	pub async fn run(&self) {
+		if self.beacon_client.is_some() {
+			info!("Syncing slot clock with the beacon node every epoch");
+		}
+
+		loop {
+			let next_slot = self.current_slot() + 1;
+			let sleep_ms = self.slot_deadline(next_slot).max(0) as u64;
+			tokio::time::sleep(Duration::from_millis(sleep_ms)).await;
+
+			// Sending only fails when there are no subscribers
+			let _ = self.ticks.send(next_slot);
+
+			if next_slot % self.slots_per_epoch() == 0
+				&& let Err(e) = self.sync().await
+			{
+				warn!("Failed to sync slot clock with the beacon node: {}", e);
+			}
+		}
+	}
+
+	/// Time of the host clock, before the skew correction
+	fn host_time_ms(&self) -> u64 {
+		self.clock.read().expect("slot clock lock poisoned").now_ms()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::clock::ManualClock;
+
+	#[test]
+	fn test_slot_clock_corrects_host_clock_behind_head() {
+		let now_ms = SystemClock.now_ms();
+		// Genesis ten and a half slots ago
+		let genesis_time = (now_ms - 10 * SLOT_DURATION_MS - SLOT_DURATION_MS / 2) / 1000;
+		let clock = SlotClock::from_genesis_time(genesis_time);
+		assert_eq!(clock.current_slot(), 10);
+		assert!(clock.slot_deadline(10) < 0);
+		assert!(clock.slot_deadline(11) > 0);
+
+		// A head block two slots ahead of the host clock moves the clock forward
+		clock.observe(genesis_time, 12, now_ms);
+		assert_eq!(clock.current_slot(), 12);
+		assert!(clock.slot_deadline(12) <= 0);
+
+		// A head behind the host clock is missed slots, the clock falls back to the host clock
+		clock.observe(genesis_time, 8, now_ms);
+		assert_eq!(clock.current_slot(), 10);
+
+		// The genesis time of the beacon node wins over the configured one
+		clock.observe(genesis_time - 2 * SLOT_DURATION_MS / 1000, 8, now_ms);
+		assert_eq!(clock.current_slot(), 12);
+	}
+
+	#[test]
+	fn test_slot_clock_uses_chain_spec() {
+		let genesis_time = SystemClock.now_ms() / 1000 - 60;
+		let clock = SlotClock::from_genesis_time(genesis_time);
+		assert_eq!(clock.current_slot(), 5);
+		assert_eq!(clock.slot_to_epoch(40), 1);
+
+		// Gnosis: 5 second slots, 16 slots per epoch
+		clock.set_spec(5_000, 16).unwrap();
+		assert_eq!(clock.current_slot(), 12);
+		assert_eq!(clock.slot_to_epoch(40), 2);
+		assert_eq!(clock.epoch_to_first_slot(2), 32);
+		assert_eq!(clock.epoch_to_last_slot(2), 47);
+		assert_eq!(clock.slot_timestamp(3), genesis_time + 15);
+		assert!(clock.set_spec(0, 16).is_err());
+	}
+
+	#[test]
+	fn test_slot_clock_with_manual_clock_at_slot_boundaries() {
+		let genesis_time = 1_700_000_000;
+		let manual = Arc::new(ManualClock::new(genesis_time * 1000));
+		let clock = SlotClock::from_genesis_time(genesis_time).with_clock(manual.clone());
+		assert_eq!(clock.current_slot(), 0);
+
+		// The last millisecond of slot 9, then the first of slot 10
+		manual.set_ms(clock.slot_start_ms(10) - 1);
+		assert_eq!(clock.current_slot(), 9);
+		assert_eq!(clock.time_until_next_slot_ms(), 1);
+		manual.advance_ms(1);
+		assert_eq!(clock.current_slot(), 10);
+		assert_eq!(clock.slot_deadline(10), 0);
+
+		// A synced skew applies on top of the injected clock
+		clock.observe(genesis_time, 12, manual.now_ms());
+		assert_eq!(clock.current_slot(), 12);
+
+		// Replacing the clock moves every holder of the slot clock
+		clock.set_clock(Arc::new(ManualClock::new(clock.slot_start_ms(100))));
+		assert_eq!(clock.current_slot(), 102);
+	}
+}
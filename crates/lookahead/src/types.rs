@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use alloy::{primitives::B256, rpc::types::beacon::BlsPublicKey};
 use common::utils::decode_pubkey;
 use eyre::Result;
@@ -51,6 +53,163 @@ pub struct ProposerDutiesResponse {
 	pub data: Vec<ValidatorDuty>,
 }
 
+/// A validator, by index or public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidatorId {
+	Index(u64),
+	Pubkey(BlsPublicKey),
+}
+
+impl std::fmt::Display for ValidatorId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ValidatorId::Index(index) => write!(f, "{}", index),
+			ValidatorId::Pubkey(pubkey) => write!(f, "{}", pubkey),
+		}
+	}
+}
+
+/// Status of a validator as reported by the Beacon API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+	PendingInitialized,
+	PendingQueued,
+	ActiveOngoing,
+	ActiveExiting,
+	ActiveSlashed,
+	ExitedUnslashed,
+	ExitedSlashed,
+	WithdrawalPossible,
+	WithdrawalDone,
+}
+
+impl ValidatorStatus {
+	pub fn is_slashed(&self) -> bool {
+		matches!(self, ValidatorStatus::ActiveSlashed | ValidatorStatus::ExitedSlashed)
+	}
+
+	pub fn is_exited(&self) -> bool {
+		matches!(
+			self,
+			ValidatorStatus::ExitedUnslashed
+				| ValidatorStatus::ExitedSlashed
+				| ValidatorStatus::WithdrawalPossible
+				| ValidatorStatus::WithdrawalDone
+		)
+	}
+}
+
+/// Validator information from Beacon API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorInfo {
+	/// Validator index in beacon state
+	pub index: String,
+	pub status: ValidatorStatus,
+	pub validator: ValidatorData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorData {
+	/// BLS public key of the validator
+	pub pubkey: String,
+	pub slashed: bool,
+}
+
+impl ValidatorInfo {
+	pub fn parse_index(&self) -> Result<u64> {
+		self.index.parse::<u64>().map_err(|e| eyre::eyre!("Failed to parse validator index: {:?}", e))
+	}
+
+	pub fn parse_pubkey(&self) -> Result<BlsPublicKey> {
+		decode_pubkey(self.validator.pubkey.as_str())
+	}
+
+	/// Slashed validators may still be scheduled, but their blocks are invalid
+	pub fn is_slashed(&self) -> bool {
+		self.validator.slashed || self.status.is_slashed()
+	}
+}
+
+/// Response from Beacon API for a validator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorResponse {
+	pub data: ValidatorInfo,
+}
+
+/// Response from Beacon API for a set of validators
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorsResponse {
+	pub data: Vec<ValidatorInfo>,
+}
+
+/// Response from Beacon API for the chain genesis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenesisResponse {
+	pub data: GenesisData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenesisData {
+	/// Unix timestamp in seconds, quoted
+	pub genesis_time: String,
+	pub genesis_validators_root: B256,
+}
+
+impl GenesisData {
+	pub fn parse_genesis_time(&self) -> Result<u64> {
+		self.genesis_time.parse::<u64>().map_err(|e| eyre::eyre!("Failed to parse genesis time: {:?}", e))
+	}
+}
+
+/// Response from Beacon API for the chain's config spec
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecResponse {
+	/// Spec values keyed by name, numbers are quoted
+	pub data: HashMap<String, serde_json::Value>,
+}
+
+impl SpecResponse {
+	/// Parse a numeric spec value
+	pub fn parse_u64(&self, name: &str) -> Result<u64> {
+		self.data
+			.get(name)
+			.and_then(|value| value.as_str())
+			.ok_or_else(|| eyre::eyre!("Spec value {} not found", name))?
+			.parse::<u64>()
+			.map_err(|e| eyre::eyre!("Failed to parse spec value {}: {:?}", name, e))
+	}
+}
+
+/// Response from Beacon API for a block header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockHeaderResponse {
+	pub data: BlockHeaderData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockHeaderData {
+	pub root: B256,
+	pub header: SignedBeaconBlockHeader,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedBeaconBlockHeader {
+	pub message: BeaconBlockHeader,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeaconBlockHeader {
+	/// Slot number, quoted
+	pub slot: String,
+}
+
+impl BlockHeaderResponse {
+	pub fn parse_slot(&self) -> Result<u64> {
+		self.data.header.message.slot.parse::<u64>().map_err(|e| eyre::eyre!("Failed to parse slot: {:?}", e))
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -0,0 +1,322 @@
+//! Checks of the constraints gateways post for delegated slots
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::consensus::{Transaction, TxEnvelope};
+use alloy::eips::eip2718::Decodable2718;
+use alloy::primitives::Bytes;
+use alloy::rpc::types::beacon::BlsSignature;
+use alloy::sol_types::SolValue;
+use commit_boost::prelude::Chain;
+use common::metrics::{
+	AUDIT_CLEAN, AUDIT_VIOLATION, FABRIC_CONSTRAINT_AUDITS_TOTAL, FABRIC_CONSTRAINTS_TOTAL, STAGE_AUDITED,
+};
+use constraints::types::{AuthorizationContext, SignedConstraints, SignedDelegation};
+use eyre::{Result, WrapErr};
+use serde::Deserialize;
+use signing::nonce::NonceKind;
+use signing::signer::{call_bls_signer, verify_bls};
+use tracing::{debug, error, info, warn};
+use urc::utils::get_constraints_message_signing_root;
+
+use crate::state::ProposerState;
+use crate::storage::DelegationsDbExt;
+use crate::types::ConstraintAttestation;
+
+/// Constraint type of inclusion constraints, whose payload carries the transaction to include
+/// Bundle and blob transactions also get an inclusion constraint each, so their gas is counted once
+const INCLUSION_CONSTRAINT_TYPE: u64 = 1;
+
+alloy::sol! {
+	struct SolInclusionPayload {
+		uint64 slot;
+		bytes signed_tx;
+	}
+}
+
+/// Limits the constraints of a delegated slot must stay within
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConstraintAuditConfig {
+	/// How long before the start of a delegated slot its constraints are fetched (in milliseconds)
+	#[serde(default = "default_lead_time_ms")]
+	pub lead_time_ms: u64,
+
+	/// Maximum number of constraints across every message of the slot
+	#[serde(default = "default_max_constraints")]
+	pub max_constraints: usize,
+
+	/// Maximum gas limit summed over the transactions of the inclusion constraints, unlimited if not set
+	#[serde(default)]
+	pub max_gas: Option<u64>,
+}
+
+impl Default for ConstraintAuditConfig {
+	fn default() -> Self {
+		Self { lead_time_ms: default_lead_time_ms(), max_constraints: default_max_constraints(), max_gas: None }
+	}
+}
+
+/// Fetches the constraints of every delegated slot from each relay shortly before the slot and records an
+/// attestation of what was served and whether it is within policy
+pub struct ConstraintAuditor {
+	state: Arc<ProposerState>,
+}
+
+impl ConstraintAuditor {
+	pub fn new(state: Arc<ProposerState>) -> Self {
+		Self { state }
+	}
+
+	/// Audit the next slot `lead_time_ms` before it starts, driven by the slot clock ticks
+	pub async fn run(&self) -> Result<()> {
+		let lead_time_ms = self.state.constraint_audit.lead_time_ms as i64;
+		info!("Starting constraint auditor, auditing delegated slots {}ms before they start", lead_time_ms);
+
+		let mut ticks = self.state.slot_clock.ticks();
+		loop {
+			let next_slot = self.state.slot_clock.current_slot() + 1;
+			let wait_ms = self.state.slot_clock.slot_deadline(next_slot) - lead_time_ms;
+			if wait_ms > 0 {
+				tokio::time::sleep(Duration::from_millis(wait_ms as u64)).await;
+			}
+
+			if let Err(e) = self.audit_slot(next_slot).await {
+				error!("Error auditing the constraints of slot {}: {}", next_slot, e);
+			}
+
+			// A lagged receiver only missed ticks, the next one still marks a new slot
+			if let Err(tokio::sync::broadcast::error::RecvError::Closed) = ticks.recv().await {
+				return Ok(());
+			}
+		}
+	}
+
+	/// Fetch and check the constraints of `slot` from every relay, a no-op unless the slot is delegated
+	pub async fn audit_slot(&self, slot: u64) -> Result<()> {
+		if !self.state.db.is_delegated(slot)? {
+			return Ok(());
+		}
+		let Some(delegation) = self.state.db.get_delegation(slot)? else {
+			return Ok(());
+		};
+		debug!("Auditing the constraints of slot {}", slot);
+
+		let auth = self.receiver_auth(&delegation).await?;
+		let mut attestations = Vec::with_capacity(self.state.constraints_clients.len());
+		for client in &self.state.constraints_clients {
+			let relay = client.base_url.to_string();
+			let constraints = match client.get_constraints_as_receiver(slot, &auth).await {
+				Ok(constraints) => constraints,
+				Err(e) => {
+					warn!("Failed to fetch the constraints of slot {} from relay {}: {}", slot, relay, e);
+					continue;
+				}
+			};
+
+			let attestation = attest_constraints(
+				&delegation,
+				relay,
+				constraints,
+				self.state.slot_clock.now_ms(),
+				&self.state.constraint_audit,
+				self.state.chain,
+			);
+			FABRIC_CONSTRAINTS_TOTAL.with_label_values(&[STAGE_AUDITED]).inc_by(attestation.constraint_count as u64);
+			if attestation.is_clean() {
+				FABRIC_CONSTRAINT_AUDITS_TOTAL.with_label_values(&[AUDIT_CLEAN]).inc();
+				info!(
+					"Relay {} serves {} constraint(s) using {} gas for slot {}, within policy",
+					attestation.relay, attestation.constraint_count, attestation.gas, slot
+				);
+			} else {
+				FABRIC_CONSTRAINT_AUDITS_TOTAL.with_label_values(&[AUDIT_VIOLATION]).inc();
+				error!(
+					"Relay {} serves constraints for slot {} outside of policy: {}",
+					attestation.relay,
+					slot,
+					attestation.violations.join("; ")
+				);
+			}
+			attestations.push(attestation);
+		}
+
+		self.state.db.store_constraint_attestations(slot, &attestations)?;
+		Ok(())
+	}
+
+	/// Receiver headers of the slot's proposer, a signature over the slot and the current time under a fresh nonce
+	/// The proposer key is expected in the receivers list of constraints restricted to one
+	async fn receiver_auth(&self, delegation: &SignedDelegation) -> Result<AuthorizationContext> {
+		let proposer = delegation.message.proposer;
+		let timestamp_ms = self.state.slot_clock.now_ms();
+		let message_hash = AuthorizationContext::message_hash(delegation.message.slot, timestamp_ms);
+		let nonce = self.state.nonce_manager.next_nonce(proposer.as_slice(), NonceKind::ReceiverAuth)?;
+
+		let response = call_bls_signer(
+			&mut self.state.signer_client.clone(),
+			message_hash,
+			proposer,
+			&self.state.module_signing_id,
+			self.state.chain,
+			nonce,
+		)
+		.await
+		.wrap_err("Failed to sign the receiver authentication")?;
+
+		Ok(AuthorizationContext {
+			signature: Some(BlsSignature::new(response.signature.serialize())),
+			public_key: Some(proposer),
+			nonce: Some(response.nonce),
+			signing_id: Some(response.module_signing_id),
+			timestamp_ms: Some(timestamp_ms),
+		})
+	}
+}
+
+/// Check the constraints a relay serves for a delegated slot against the delegation and the audit limits
+pub fn attest_constraints(
+	delegation: &SignedDelegation,
+	relay: String,
+	constraints: Vec<SignedConstraints>,
+	fetched_at_ms: u64,
+	config: &ConstraintAuditConfig,
+	chain: Chain,
+) -> ConstraintAttestation {
+	let slot = delegation.message.slot;
+	let mut violations = Vec::new();
+	let mut constraint_count = 0;
+	let mut gas = 0u64;
+
+	for (index, signed) in constraints.iter().enumerate() {
+		let message = &signed.message;
+		if message.slot != slot || message.proposer != delegation.message.proposer {
+			violations.push(format!("message {} is for slot {} of proposer {}", index, message.slot, message.proposer));
+		}
+		if message.delegate != delegation.message.delegate {
+			violations.push(format!("message {} is signed by {}, not the delegated gateway", index, message.delegate));
+		} else if let Err(e) = verify_gateway_signature(signed, chain) {
+			violations.push(format!("message {} has an invalid gateway signature: {}", index, e));
+		}
+
+		constraint_count += message.constraints.len();
+		for constraint in message.constraints.iter().filter(|c| c.constraint_type == INCLUSION_CONSTRAINT_TYPE) {
+			match inclusion_gas(&constraint.payload) {
+				Ok(tx_gas) => gas = gas.saturating_add(tx_gas),
+				Err(e) => violations.push(format!("message {} has an undecodable inclusion constraint: {}", index, e)),
+			}
+		}
+	}
+
+	if constraint_count > config.max_constraints {
+		violations.push(format!("{} constraints exceed the limit of {}", constraint_count, config.max_constraints));
+	}
+	if let Some(max_gas) = config.max_gas
+		&& gas > max_gas
+	{
+		violations.push(format!("{} gas exceeds the limit of {}", gas, max_gas));
+	}
+
+	ConstraintAttestation {
+		slot,
+		relay,
+		delegate: delegation.message.delegate,
+		fetched_at_ms,
+		constraints,
+		constraint_count,
+		gas,
+		violations,
+	}
+}
+
+fn verify_gateway_signature(signed: &SignedConstraints, chain: Chain) -> Result<()> {
+	let signing_root = get_constraints_message_signing_root(&signed.message)?;
+	verify_bls(chain, &signed.message.delegate, &signing_root, &signed.signature, &signed.signing_id, signed.nonce)
+}
+
+/// Gas limit of the transaction carried by an inclusion constraint payload
+fn inclusion_gas(payload: &Bytes) -> Result<u64> {
+	let decoded = SolInclusionPayload::abi_decode(payload).wrap_err("Failed to decode inclusion payload")?;
+	let tx = TxEnvelope::decode_2718(&mut decoded.signed_tx.as_ref()).wrap_err("Failed to decode transaction")?;
+	Ok(tx.gas_limit())
+}
+
+fn default_lead_time_ms() -> u64 {
+	2_000
+}
+
+fn default_max_constraints() -> usize {
+	256
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::consensus::{SignableTransaction, Signed, TxEip1559};
+	use alloy::eips::eip2718::Encodable2718;
+	use alloy::primitives::{Address, B256, TxKind};
+	use alloy::rpc::types::beacon::BlsPublicKey;
+	use alloy::signers::{SignerSync, local::PrivateKeySigner};
+	use constraints::types::{Constraint, ConstraintsMessage, Delegation};
+
+	fn inclusion_constraint(gas_limit: u64) -> Constraint {
+		let tx = TxEip1559 { chain_id: 1, gas_limit, to: TxKind::Call(Address::repeat_byte(1)), ..Default::default() };
+		let signature = PrivateKeySigner::random().sign_hash_sync(&tx.signature_hash()).unwrap();
+		let envelope = TxEnvelope::Eip1559(Signed::new_unhashed(tx, signature));
+		let payload = SolInclusionPayload { slot: 10, signed_tx: envelope.encoded_2718().into() };
+		Constraint { constraint_type: INCLUSION_CONSTRAINT_TYPE, payload: payload.abi_encode().into() }
+	}
+
+	fn signed_constraints(delegate: BlsPublicKey, constraints: Vec<Constraint>) -> SignedConstraints {
+		SignedConstraints {
+			message: ConstraintsMessage {
+				proposer: BlsPublicKey::repeat_byte(1),
+				delegate,
+				slot: 10,
+				constraints,
+				receivers: vec![],
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: BlsSignature::ZERO,
+		}
+	}
+
+	#[test]
+	fn test_attestation_flags_constraints_outside_policy() {
+		let delegation = SignedDelegation {
+			message: Delegation {
+				proposer: BlsPublicKey::repeat_byte(1),
+				delegate: BlsPublicKey::repeat_byte(2),
+				committer: Address::repeat_byte(3),
+				slot: 10,
+				metadata: Bytes::new(),
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: BlsSignature::ZERO,
+		};
+		let config = ConstraintAuditConfig { lead_time_ms: 0, max_constraints: 2, max_gas: Some(50_000) };
+
+		let constraints = vec![
+			signed_constraints(
+				BlsPublicKey::repeat_byte(2),
+				vec![inclusion_constraint(21_000), inclusion_constraint(40_000)],
+			),
+			signed_constraints(BlsPublicKey::repeat_byte(9), vec![inclusion_constraint(21_000)]),
+		];
+		let attestation =
+			attest_constraints(&delegation, "http://relay:9000/".to_string(), constraints, 1, &config, Chain::Mainnet);
+
+		assert_eq!(attestation.constraint_count, 3);
+		assert_eq!(attestation.gas, 82_000);
+		assert!(!attestation.is_clean());
+		let violations = attestation.violations.join("\n");
+		assert!(violations.contains("message 0 has an invalid gateway signature"), "{}", violations);
+		assert!(violations.contains("message 1 is signed by"), "{}", violations);
+		assert!(violations.contains("3 constraints exceed the limit of 2"), "{}", violations);
+		assert!(violations.contains("82000 gas exceeds the limit of 50000"), "{}", violations);
+
+		let empty = attest_constraints(&delegation, String::new(), vec![], 1, &config, Chain::Mainnet);
+		assert!(empty.is_clean());
+	}
+}
@@ -1,4 +1,16 @@
+use common::http::HttpClientConfig;
+use common::logging::LoggingConfig;
+use config::{ConfigErrors, Validate};
+use constraints::metadata::MAX_FEE_SPLIT_BPS;
 use serde::Deserialize;
+use signing::local::LocalSignerConfig;
+
+use crate::auditor::ConstraintAuditConfig;
+use crate::policy::{DelegationOffersConfig, DelegationPolicyConfig, GatewayConfig};
+use crate::validators::ValidatorSelectionConfig;
+
+/// Prefix of the environment variables overriding proposer config values, e.g. `FABRIC_PROPOSER__RELAY_PORT`
+pub const PROPOSER_ENV_PREFIX: &str = "FABRIC_PROPOSER";
 
 /// Configuration for the proposer service
 #[derive(Debug, Clone, Deserialize)]
@@ -6,11 +18,29 @@ pub struct ProposerConfig {
 	/// Path to the RocksDB database for storing delegations (for equivocation prevention)
 	pub db_path: String,
 
-	/// Gateway delegate BLS public key
-	pub gateway_public_key: String,
+	/// Gateway delegate BLS public key, delegated to along with `gateways`
+	#[serde(default)]
+	pub gateway_public_key: Option<String>,
+
+	/// Gateway committer EOA address of `gateway_public_key`
+	#[serde(default)]
+	pub gateway_address: Option<String>,
+
+	/// Gateways the delegation policy picks the delegate of each slot from
+	#[serde(default)]
+	pub gateways: Vec<GatewayConfig>,
+
+	/// How the delegate of each slot is picked among the gateways
+	#[serde(default)]
+	pub delegation_policy: DelegationPolicyConfig,
 
-	/// Gateway committer EOA address
-	pub gateway_address: String,
+	/// Delegation offers of the gateways, ranked per slot before the delegation policy picks a gateway
+	#[serde(default)]
+	pub delegation_offers: DelegationOffersConfig,
+
+	/// Validators of the signer that delegate, every one if not set
+	#[serde(default)]
+	pub validators: ValidatorSelectionConfig,
 
 	/// Address of the Relay server (constraints API)
 	pub relay_host: String,
@@ -21,6 +51,18 @@ pub struct ProposerConfig {
 	/// API key for the Relay server (constraints API)
 	pub relay_api_key: Option<String>,
 
+	/// Additional relays every delegation is also posted to
+	#[serde(default)]
+	pub additional_relays: Vec<RelayEndpointConfig>,
+
+	/// Number of relays that must accept a delegation before its slot counts as delegated
+	#[serde(default = "default_delegation_quorum")]
+	pub delegation_quorum: usize,
+
+	/// Timeout and connection pool of the relay clients
+	#[serde(default)]
+	pub relay_http: HttpClientConfig,
+
 	/// Host of the Beacon API for fetching proposer duties
 	pub beacon_api_host: String,
 
@@ -30,6 +72,124 @@ pub struct ProposerConfig {
 	/// How often to poll for proposer duties (in seconds)
 	pub lookahead_check_interval_seconds: u64,
 
+	/// Epochs after the current one whose proposer duties are prefetched every slot
+	#[serde(default = "default_lookahead_prefetch_epochs")]
+	pub lookahead_prefetch_epochs: u64,
+
+	/// How often to check that the relays still hold the delegations of upcoming slots (in seconds)
+	#[serde(default = "default_reconcile_interval_seconds")]
+	pub reconcile_interval_seconds: u64,
+
+	/// Limits the constraints of delegated slots are audited against
+	#[serde(default)]
+	pub constraint_audit: ConstraintAuditConfig,
+
 	/// Module signing ID for this proposer instance
 	pub module_signing_id: String,
+
+	/// Sign delegations with local keys instead of the Commit-Boost signer service
+	#[serde(default)]
+	pub local_signer: Option<LocalSignerConfig>,
+
+	/// Host the `/healthz` and `/readyz` server listens on
+	#[serde(default = "default_health_host")]
+	pub health_host: String,
+
+	/// Port of the `/healthz` and `/readyz` server, not started if not set
+	#[serde(default)]
+	pub health_port: Option<u16>,
+
+	/// Log format, module levels and log file
+	#[serde(default)]
+	pub logging: LoggingConfig,
+}
+
+/// A relay delegations are posted to
+#[derive(Debug, Clone, Deserialize)]
+pub struct RelayEndpointConfig {
+	/// Address of the Relay server (constraints API)
+	pub host: String,
+
+	/// Port of the Relay server (constraints API)
+	pub port: u16,
+
+	/// API key for the Relay server (constraints API)
+	#[serde(default)]
+	pub api_key: Option<String>,
+}
+
+impl Validate for ProposerConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.not_empty("db_path", &self.db_path);
+		match (&self.gateway_public_key, &self.gateway_address) {
+			(Some(public_key), Some(address)) => {
+				errors.bls_public_key("gateway_public_key", public_key);
+				errors.address("gateway_address", address);
+			}
+			(None, None) => {}
+			_ => errors.add("gateway_address", "gateway_public_key and gateway_address must be set together"),
+		}
+		for (index, gateway) in self.gateways.iter().enumerate() {
+			errors.not_empty(&format!("gateways[{}].name", index), &gateway.name);
+			errors.bls_public_key(&format!("gateways[{}].public_key", index), &gateway.public_key);
+			errors.address(&format!("gateways[{}].address", index), &gateway.address);
+			errors.ensure(
+				gateway.terms.fee_split_bps <= MAX_FEE_SPLIT_BPS,
+				&format!("gateways[{}].terms.fee_split_bps", index),
+				format!("{} must be at most {} basis points", gateway.terms.fee_split_bps, MAX_FEE_SPLIT_BPS),
+			);
+			// Zero encodes an unset limit, so it would grant unlimited constraints or no deadline
+			errors.ensure(
+				gateway.terms.max_constraints != Some(0),
+				&format!("gateways[{}].terms.max_constraints", index),
+				"must be positive, leave it unset for no limit",
+			);
+			errors.ensure(
+				gateway.terms.valid_until != Some(0),
+				&format!("gateways[{}].terms.valid_until", index),
+				"must be positive, leave it unset for no deadline",
+			);
+		}
+		errors.host("relay_host", &self.relay_host);
+		errors.port("relay_port", self.relay_port);
+		errors.positive("relay_http.timeout_ms", self.relay_http.timeout_ms);
+		for (index, relay) in self.additional_relays.iter().enumerate() {
+			errors.host(&format!("additional_relays[{}].host", index), &relay.host);
+			errors.port(&format!("additional_relays[{}].port", index), relay.port);
+		}
+		let relays = self.additional_relays.len() + 1;
+		errors.ensure(
+			(1..=relays).contains(&self.delegation_quorum),
+			"delegation_quorum",
+			format!("{} must be between 1 and the number of relays ({})", self.delegation_quorum, relays),
+		);
+		errors.host("beacon_api_host", &self.beacon_api_host);
+		errors.port("beacon_api_port", self.beacon_api_port);
+		errors.positive("lookahead_check_interval_seconds", self.lookahead_check_interval_seconds);
+		errors.positive("reconcile_interval_seconds", self.reconcile_interval_seconds);
+		errors.bytes32("module_signing_id", &self.module_signing_id);
+		if let Some(port) = self.health_port {
+			errors.host("health_host", &self.health_host);
+			errors.port("health_port", port);
+		}
+		if let Err(e) = self.logging.check() {
+			errors.add("logging", e);
+		}
+	}
+}
+
+fn default_health_host() -> String {
+	"0.0.0.0".to_string()
+}
+
+fn default_delegation_quorum() -> usize {
+	1
+}
+
+fn default_lookahead_prefetch_epochs() -> u64 {
+	1
+}
+
+fn default_reconcile_interval_seconds() -> u64 {
+	60
 }
@@ -1,12 +1,17 @@
+use crate::policy::Gateway;
 use crate::state::ProposerState;
 use crate::storage::DelegationsDbExt;
-use crate::utils::create_signed_delegation;
+use crate::types::{RelayAck, RelayAckStatus};
+use crate::utils::{create_signed_delegation, verify_delegation_offer};
+use crate::validators::ValidatorSelection;
 use alloy::rpc::types::beacon::BlsPublicKey;
-use constraints::client::ConstraintsClient;
+use constraints::client::{ConstraintsClient, HttpConstraintsClient};
+use constraints::error::ConstraintsError;
+use constraints::types::{SignedDelegation, SignedReceipt};
 use eyre::{Context, Result};
-use lookahead::utils::{current_slot, slot_to_epoch};
 use std::sync::Arc;
 use tracing::{debug, info, warn};
+use urc::utils::get_delegation_signing_root;
 
 /// Delegation manager that monitors lookahead duties and signs delegations
 pub struct DelegationManager {
@@ -21,10 +26,18 @@ impl DelegationManager {
 
 	/// Get all consensus BLS public keys from the signer client
 	pub async fn get_consensus_keys(&self) -> Result<Vec<BlsPublicKey>> {
-		let response =
-			self.state.signer_client.clone().get_pubkeys().await.context("Failed to get public keys from signer")?;
+		self.state.signer_client.clone().consensus_public_keys().await
+	}
 
-		Ok(response.keys.iter().map(|map| BlsPublicKey::new(map.consensus.serialize())).collect())
+	/// Consensus keys of the signer that are enabled by the validator selection
+	pub async fn get_delegating_keys(&self, selection: &ValidatorSelection) -> Result<Vec<BlsPublicKey>> {
+		let keys = self.get_consensus_keys().await?;
+		let total = keys.len();
+		let enabled = keys.into_iter().filter(|key| selection.is_enabled(key)).collect::<Vec<_>>();
+		if enabled.len() < total {
+			debug!("{} of {} consensus key(s) are disabled by the validator selection", total - enabled.len(), total);
+		}
+		Ok(enabled)
 	}
 
 	/// Process proposer lookahead to find upcoming duties and sign delegations
@@ -33,22 +46,26 @@ impl DelegationManager {
 	/// If the configured proposer is assigned to a slot, it creates, signs, and posts a delegation
 	/// to the relay.
 	pub async fn process_lookahead(&self) -> Result<()> {
-		let our_pubkeys = self.get_consensus_keys().await?;
+		if let Err(e) = self.state.validator_selector.reload_if_changed() {
+			warn!("Failed to reload the validator selection, keeping the previous one: {}", e);
+		}
+		let selection = self.state.validator_selector.current();
+		let our_pubkeys = self.get_delegating_keys(&selection).await?;
 
 		if our_pubkeys.is_empty() {
-			warn!("No consensus keys found in signer");
+			warn!("No consensus keys found in signer, or none enabled by the validator selection");
 			return Ok(());
 		}
 
 		debug!("Processing lookahead for {} consensus key(s)", our_pubkeys.len());
 
 		// Calculate current epoch
-		let current_epoch = slot_to_epoch(current_slot(&self.state.chain));
+		let current_epoch = self.state.slot_clock.slot_to_epoch(self.state.slot_clock.current_slot());
 
 		// Check duties for both current and next epoch
 		let mut count = 0;
 		for epoch in [current_epoch, current_epoch + 1] {
-			count += self.process_epoch_duties(epoch, &our_pubkeys).await?;
+			count += self.process_epoch_duties(epoch, &our_pubkeys, &selection).await?;
 		}
 
 		info!("{} keys have delegated in current epoch", count);
@@ -57,10 +74,15 @@ impl DelegationManager {
 	}
 
 	/// Process duties for a specific epoch
-	async fn process_epoch_duties(&self, epoch: u64, our_pubkeys: &[BlsPublicKey]) -> Result<usize> {
+	async fn process_epoch_duties(
+		&self,
+		epoch: u64,
+		our_pubkeys: &[BlsPublicKey],
+		selection: &ValidatorSelection,
+	) -> Result<usize> {
 		// Get proposer duties for this epoch
 		let duties =
-			self.state.beacon_client.get_proposer_duties(epoch).await.context("Failed to get proposer duties")?;
+			self.state.lookahead_cache.get_proposer_duties(epoch).await.context("Failed to get proposer duties")?;
 
 		let mut count = 0;
 
@@ -74,46 +96,175 @@ impl DelegationManager {
 			// Only process duties that:
 			// 1. Match one of our proposer keys
 			// 2. Are in the future (slot > current_slot)
-			if our_pubkeys.contains(&duty_pubkey) && duty_slot > current_slot(&self.state.chain) {
+			if our_pubkeys.contains(&duty_pubkey) && duty_slot > self.state.slot_clock.current_slot() {
 				debug!("Found proposer duty for slot {}", duty_slot);
-				let existing_delegation = self.state.db.get_delegation(duty_slot)?;
-
-				if existing_delegation.is_some() {
-					debug!(
-						"Delegation already exists for slot {}. Skipping to prevent equivocation. Existing delegation: proposer={:?}",
-						duty_slot,
-						existing_delegation.unwrap().message.proposer
-					);
+				if self.state.db.is_delegated(duty_slot)? {
+					debug!("Slot {} is already delegated", duty_slot);
 					count += 1;
 					continue;
 				}
 
-				// No existing delegation, proceed to create and sign
-				let signed_delegation = create_signed_delegation(
-					&mut self.state.signer_client.clone(),
-					&duty_pubkey,
-					&self.state.gateway_public_key,
-					duty_slot,
-					&self.state.gateway_address,
-					&self.state.module_signing_id,
-					&self.state.chain,
-				)
-				.await?;
-
-				// Store before sending to prevent equivocation
-				self.state.db.store_delegation(&signed_delegation)?;
+				let signed_delegation = match self.state.db.get_delegation(duty_slot)? {
+					// Signed before but not accepted by a quorum of relays yet, signing again would equivocate
+					Some(existing_delegation) => {
+						debug!(
+							"Delegation already exists for slot {}, posting it again. Existing delegation: proposer={:?}",
+							duty_slot, existing_delegation.message.proposer
+						);
+						existing_delegation
+					}
+					None => {
+						// Validated against the policy's gateways when the selection was loaded
+						let gateway = match selection.gateway_override(&duty_pubkey) {
+							Some(name) => self.state.delegation_policy.gateway(name).cloned(),
+							None => match self.best_offer(duty_slot, epoch).await {
+								Some(gateway) => Some(gateway),
+								None => self.state.delegation_policy.select(duty_slot, epoch).cloned(),
+							},
+						};
+						let Some(gateway) = gateway else {
+							warn!(
+								"No gateway is allowed to receive delegations in epoch {}, slot {} not delegated",
+								epoch, duty_slot
+							);
+							continue;
+						};
+						debug!("Delegating slot {} to gateway {}", duty_slot, gateway.name);
 
-				debug!("Signed and stored delegation for slot {}", duty_slot);
+						let signed_delegation = create_signed_delegation(
+							&mut self.state.signer_client.clone(),
+							&self.state.nonce_manager,
+							&duty_pubkey,
+							&gateway.public_key,
+							duty_slot,
+							&gateway.address,
+							&gateway.terms,
+							&self.state.module_signing_id,
+							&self.state.chain,
+						)
+						.await?;
 
-				// Post to relay
-				self.state.constraints_client.post_delegation(&signed_delegation).await?;
+						// Store before sending to prevent equivocation
+						self.state.db.store_delegation(&signed_delegation)?;
 
-				info!("Posted delegation for slot {}, key={:?}", duty_slot, duty_pubkey);
+						debug!("Signed and stored delegation for slot {}", duty_slot);
+						signed_delegation
+					}
+				};
 
-				count += 1;
+				if self.broadcast_delegation(&signed_delegation).await? {
+					info!("Delegated slot {}, key={:?}", duty_slot, duty_pubkey);
+					count += 1;
+				}
 			}
 		}
 
 		Ok(count)
 	}
+
+	/// The gateway of the best delegation offer for `slot` across the relays, if offers are enabled and one qualifies
+	/// Offers whose signature does not verify are dropped, a relay that fails to answer is skipped
+	async fn best_offer(&self, slot: u64, epoch: u64) -> Option<Gateway> {
+		if !self.state.delegation_offers.enabled {
+			return None;
+		}
+
+		let mut offers = Vec::new();
+		for client in &self.state.constraints_clients {
+			let relay_offers = match client.get_delegation_offers(slot).await {
+				Ok(relay_offers) => relay_offers,
+				Err(e) => {
+					warn!("Failed to get the slot {} delegation offers from relay {}: {}", slot, client.base_url, e);
+					continue;
+				}
+			};
+			for signed_offer in relay_offers {
+				match verify_delegation_offer(&signed_offer, &self.state.chain) {
+					Ok(()) => offers.push(signed_offer.message),
+					Err(e) => warn!(
+						"Ignoring the slot {} delegation offer of {} from relay {}: {}",
+						slot, signed_offer.message.delegate, client.base_url, e
+					),
+				}
+			}
+		}
+
+		let gateway = self.state.delegation_policy.select_offer(slot, epoch, &offers, &self.state.delegation_offers)?;
+		info!(
+			"Best of {} delegation offer(s) for slot {} is {}, bid={} gwei",
+			offers.len(),
+			slot,
+			gateway.name,
+			gateway.bid_gwei
+		);
+		Some(gateway)
+	}
+
+	/// Post a delegation to every relay that has not accepted it yet, recording each relay's answer
+	/// The slot is marked as delegated once the quorum of relays accepted it, returns whether it was reached
+	async fn broadcast_delegation(&self, signed_delegation: &SignedDelegation) -> Result<bool> {
+		let slot = signed_delegation.message.slot;
+		let mut acks = self.state.db.get_relay_acks(slot)?;
+
+		for client in &self.state.constraints_clients {
+			let relay = client.base_url.to_string();
+			if acks.iter().any(|ack| ack.relay == relay && ack.is_accepted()) {
+				continue;
+			}
+
+			let status = match client.post_delegation(signed_delegation).await {
+				Ok(receipt) => {
+					debug!("Relay {} accepted the slot {} delegation, receipt: {:?}", relay, slot, receipt);
+					RelayAckStatus::Accepted { receipt }
+				}
+				// The relay may have stored the delegation on an earlier post whose answer was lost
+				Err(ConstraintsError::Conflict(e)) => match held_delegation(client, signed_delegation).await {
+					Some(receipt) => {
+						debug!("Relay {} already holds the slot {} delegation", relay, slot);
+						RelayAckStatus::Accepted { receipt }
+					}
+					None => {
+						warn!("Relay {} holds another delegation for slot {}: {}", relay, slot, e);
+						RelayAckStatus::Rejected { error: e }
+					}
+				},
+				Err(e) => {
+					warn!("Failed to post the slot {} delegation to relay {}: {}", slot, relay, e);
+					RelayAckStatus::Rejected { error: e.to_string() }
+				}
+			};
+			acks.retain(|ack| ack.relay != relay);
+			acks.push(RelayAck { relay, status });
+		}
+		self.state.db.store_relay_acks(slot, &acks)?;
+
+		let accepted = acks.iter().filter(|ack| ack.is_accepted()).count();
+		if accepted < self.state.delegation_quorum {
+			warn!(
+				"Delegation for slot {} accepted by {} relay(s), below the quorum of {}",
+				slot, accepted, self.state.delegation_quorum
+			);
+			return Ok(false);
+		}
+
+		self.state.db.mark_delegated(slot)?;
+		Ok(true)
+	}
+}
+
+/// Receipt of a relay that already holds `signed_delegation`, `None` if it does not or can not be asked
+/// The inner option is the receipt itself, which a relay may not issue
+async fn held_delegation(
+	client: &HttpConstraintsClient,
+	signed_delegation: &SignedDelegation,
+) -> Option<Option<SignedReceipt>> {
+	let delegations = client.get_delegations(signed_delegation.message.slot).await.ok()?;
+	if !delegations.iter().any(|delegation| delegation.signature == signed_delegation.signature) {
+		return None;
+	}
+	let receipt = match get_delegation_signing_root(&signed_delegation.message) {
+		Ok(message_hash) => client.get_receipt(message_hash).await.ok().flatten(),
+		Err(_) => None,
+	};
+	Some(receipt)
 }
@@ -0,0 +1,98 @@
+//! Readiness of the proposer
+use std::time::Duration;
+
+use async_trait::async_trait;
+use common::health::{Readiness, ReadinessProbe};
+use constraints::client::{ConstraintsClient, HttpConstraintsClient};
+use eyre::eyre;
+use tokio::task::JoinSet;
+
+use crate::state::ProposerState;
+
+/// Slots without a successful proposer duties fetch before the lookahead counts as stale
+const MAX_LOOKAHEAD_AGE_SLOTS: u64 = 2;
+
+/// Time each relay is given to answer, the relays are checked at once within the readiness check timeout
+const RELAY_CHECK_TIMEOUT: Duration = Duration::from_millis(1_500);
+
+#[async_trait]
+impl ReadinessProbe for ProposerState {
+	async fn readiness(&self) -> Readiness {
+		let mut readiness = Readiness::default();
+		readiness.add("db", self.db.healthcheck().map_err(Into::into));
+		readiness.check("signer", async { self.signer_client.clone().consensus_public_keys().await.map(|_| ()) }).await;
+		readiness
+			.check("relays", async {
+				// Delegations are accepted as long as a quorum of relays is up
+				let healthy = healthy_relays(&self.constraints_clients, RELAY_CHECK_TIMEOUT).await;
+				match healthy >= self.delegation_quorum {
+					true => Ok(()),
+					false => Err(eyre!(
+						"{} relay(s) healthy, below the delegation quorum of {}",
+						healthy,
+						self.delegation_quorum
+					)),
+				}
+			})
+			.await;
+		readiness.add("lookahead", self.lookahead_cache.check_fresh(MAX_LOOKAHEAD_AGE_SLOTS));
+		readiness
+	}
+}
+
+/// Number of relays reporting they are up within `timeout`, checked at once so a slow relay does not hold up the
+/// others
+async fn healthy_relays(clients: &[HttpConstraintsClient], timeout: Duration) -> usize {
+	let mut checks = JoinSet::new();
+	for client in clients {
+		let client = client.clone();
+		checks.spawn(async move { matches!(tokio::time::timeout(timeout, client.health_check()).await, Ok(Ok(true))) });
+	}
+
+	let mut healthy = 0;
+	while let Some(check) = checks.join_next().await {
+		if matches!(check, Ok(true)) {
+			healthy += 1;
+		}
+	}
+	healthy
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use axum::{Router, http::StatusCode, routing::get};
+	use std::time::Instant;
+	use tokio::net::TcpListener;
+
+	async fn serve_relay(app: Router) -> u16 {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let port = listener.local_addr().unwrap().port();
+		tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+		port
+	}
+
+	#[tokio::test]
+	async fn test_healthy_relays_checks_relays_at_once() {
+		let up = serve_relay(Router::new().route("/health", get(|| async { StatusCode::OK }))).await;
+		let failing =
+			serve_relay(Router::new().route("/health", get(|| async { StatusCode::SERVICE_UNAVAILABLE }))).await;
+		let slow = || async {
+			tokio::time::sleep(Duration::from_secs(10)).await;
+			StatusCode::OK
+		};
+		let slow_first = serve_relay(Router::new().route("/health", get(slow))).await;
+		let slow_second = serve_relay(Router::new().route("/health", get(slow))).await;
+		let down = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+
+		let clients = [slow_first, up, slow_second, failing, down, up]
+			.into_iter()
+			.map(|port| HttpConstraintsClient::new("127.0.0.1".to_string(), port, None).unwrap())
+			.collect::<Vec<_>>();
+
+		// Both slow relays time out together, within one timeout rather than one each
+		let start = Instant::now();
+		assert_eq!(healthy_relays(&clients, Duration::from_millis(300)).await, 2);
+		assert!(start.elapsed() < Duration::from_millis(550), "took {:?}", start.elapsed());
+	}
+}
@@ -1,5 +1,11 @@
+pub mod auditor;
 pub mod config;
 pub mod delegation_manager;
+pub mod health;
+pub mod policy;
+pub mod reconciler;
 pub mod state;
 pub mod storage;
+pub mod types;
 pub mod utils;
+pub mod validators;
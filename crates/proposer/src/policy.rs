@@ -0,0 +1,361 @@
+//! Selection of the gateway each slot is delegated to
+use alloy::primitives::{Address, keccak256};
+use alloy::rpc::types::beacon::BlsPublicKey;
+use eyre::{Result, eyre};
+use serde::Deserialize;
+
+use common::utils::{decode_address, decode_pubkey};
+use constraints::metadata::DelegationMetadata;
+use constraints::types::DelegationOffer;
+
+/// A gateway slots can be delegated to
+#[derive(Debug, Clone, Deserialize)]
+pub struct GatewayConfig {
+	/// Name the gateway is referred to by in allowlists and logs
+	pub name: String,
+
+	/// Gateway delegate BLS public key
+	pub public_key: String,
+
+	/// Gateway committer EOA address
+	pub address: String,
+
+	/// Share of the slots the gateway gets with the `percentage_split` rule, relative to the other gateways
+	#[serde(default = "default_weight")]
+	pub weight: u64,
+
+	/// Fee the gateway bid per delegated slot, in gwei, used by the `highest_bid` rule
+	#[serde(default)]
+	pub bid_gwei: u64,
+
+	/// Terms signed into the metadata of the gateway's delegations, none if not set
+	#[serde(default)]
+	pub terms: DelegationMetadata,
+}
+
+/// How the delegate of a slot is chosen among the allowed gateways
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationRule {
+	/// Gateways take turns slot by slot
+	#[default]
+	RoundRobin,
+	/// The gateway with the highest bid, the first configured one on ties
+	HighestBid,
+	/// Each slot goes to a gateway with a probability proportional to its weight, picked deterministically by slot
+	PercentageSplit,
+}
+
+/// Gateways allowed to receive the delegations of a range of epochs
+#[derive(Debug, Clone, Deserialize)]
+pub struct EpochAllowlist {
+	pub from_epoch: u64,
+	/// Last epoch of the range, open ended if not set
+	#[serde(default)]
+	pub to_epoch: Option<u64>,
+	/// Names of the allowed gateways
+	pub gateways: Vec<String>,
+}
+
+/// Rules of the delegation policy
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DelegationPolicyConfig {
+	#[serde(default)]
+	pub rule: DelegationRule,
+
+	/// Epoch ranges restricted to a subset of the gateways, every gateway is allowed outside of them
+	/// The first range containing an epoch applies
+	#[serde(default)]
+	pub allowlists: Vec<EpochAllowlist>,
+}
+
+/// Delegation offers gateways publish to the relays, ranked before the delegation rule picks a gateway
+#[derive(Debug, Clone, Deserialize)]
+pub struct DelegationOffersConfig {
+	/// Fetch the offers of each duty's slot and delegate to the highest bidder, the rule picks if none qualifies
+	#[serde(default)]
+	pub enabled: bool,
+
+	/// Offers bidding less than this many gwei are ignored
+	#[serde(default)]
+	pub min_bid_gwei: u64,
+
+	/// Only accept offers from the configured gateways allowed in the slot's epoch, matched by key and address
+	#[serde(default = "default_known_gateways_only")]
+	pub known_gateways_only: bool,
+}
+
+impl Default for DelegationOffersConfig {
+	fn default() -> Self {
+		Self { enabled: false, min_bid_gwei: 0, known_gateways_only: default_known_gateways_only() }
+	}
+}
+
+/// A gateway with its decoded keys
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gateway {
+	pub name: String,
+	pub public_key: BlsPublicKey,
+	pub address: Address,
+	pub weight: u64,
+	pub bid_gwei: u64,
+	pub terms: DelegationMetadata,
+}
+
+impl TryFrom<&GatewayConfig> for Gateway {
+	type Error = eyre::Error;
+
+	fn try_from(config: &GatewayConfig) -> Result<Self> {
+		Ok(Self {
+			name: config.name.clone(),
+			public_key: decode_pubkey(&config.public_key)
+				.map_err(|e| eyre!("Invalid public key of gateway {}: {}", config.name, e))?,
+			address: decode_address(&config.address)
+				.map_err(|e| eyre!("Invalid address of gateway {}: {}", config.name, e))?,
+			weight: config.weight,
+			bid_gwei: config.bid_gwei,
+			terms: config.terms.clone(),
+		})
+	}
+}
+
+/// Picks the gateway each slot is delegated to
+#[derive(Debug, Clone)]
+pub struct DelegationPolicy {
+	rule: DelegationRule,
+	gateways: Vec<Gateway>,
+	allowlists: Vec<EpochAllowlist>,
+}
+
+impl DelegationPolicy {
+	pub fn new(config: DelegationPolicyConfig, gateways: Vec<Gateway>) -> Result<Self> {
+		if gateways.is_empty() {
+			return Err(eyre!("At least one gateway must be configured"));
+		}
+		for allowlist in &config.allowlists {
+			if let Some(unknown) = allowlist.gateways.iter().find(|name| !gateways.iter().any(|g| &g.name == *name)) {
+				return Err(eyre!("Allowlist from epoch {} names unknown gateway {}", allowlist.from_epoch, unknown));
+			}
+		}
+		if config.rule == DelegationRule::PercentageSplit && gateways.iter().all(|gateway| gateway.weight == 0) {
+			return Err(eyre!("The percentage split needs at least one gateway with a non-zero weight"));
+		}
+
+		Ok(Self { rule: config.rule, gateways, allowlists: config.allowlists })
+	}
+
+	/// Every configured gateway
+	pub fn gateways(&self) -> &[Gateway] {
+		&self.gateways
+	}
+
+	/// The gateway named `name`
+	pub fn gateway(&self, name: &str) -> Option<&Gateway> {
+		self.gateways.iter().find(|gateway| gateway.name == name)
+	}
+
+	/// Gateways allowed in `epoch`, in configuration order
+	fn candidates(&self, epoch: u64) -> Vec<&Gateway> {
+		let allowlist = self
+			.allowlists
+			.iter()
+			.find(|allowlist| allowlist.from_epoch <= epoch && allowlist.to_epoch.is_none_or(|to| epoch <= to));
+
+		match allowlist {
+			Some(allowlist) => {
+				self.gateways.iter().filter(|gateway| allowlist.gateways.contains(&gateway.name)).collect()
+			}
+			None => self.gateways.iter().collect(),
+		}
+	}
+
+	/// The gateway of the highest offer for `slot` of `epoch`, the first offer on ties, `None` if no offer qualifies
+	/// A configured gateway is only delegated to when allowed in the epoch, with its terms and the offered bid.
+	/// Other gateways are delegated to without terms, unless `known_gateways_only` is set
+	pub fn select_offer(
+		&self,
+		slot: u64,
+		epoch: u64,
+		offers: &[DelegationOffer],
+		config: &DelegationOffersConfig,
+	) -> Option<Gateway> {
+		let candidates = self.candidates(epoch);
+		let gateways = offers
+			.iter()
+			.filter(|offer| offer.slot == slot && offer.bid_gwei >= config.min_bid_gwei)
+			.filter_map(|offer| {
+				let known = |gateway: &&Gateway| gateway.public_key == offer.delegate;
+				if let Some(gateway) = candidates.iter().find(known) {
+					return (gateway.address == offer.committer)
+						.then(|| Gateway { bid_gwei: offer.bid_gwei, ..(*gateway).clone() });
+				}
+				if config.known_gateways_only || self.gateways.iter().any(|gateway| known(&gateway)) {
+					return None;
+				}
+				Some(Gateway {
+					name: format!("offer-{}", offer.delegate),
+					public_key: offer.delegate,
+					address: offer.committer,
+					weight: 0,
+					bid_gwei: offer.bid_gwei,
+					terms: DelegationMetadata::default(),
+				})
+			});
+
+		// max_by_key returns the last maximum, so search from the back to keep the first offer on ties
+		gateways.rev().max_by_key(|gateway| gateway.bid_gwei)
+	}
+
+	/// The gateway to delegate `slot` of `epoch` to, `None` if no gateway is allowed in the epoch
+	pub fn select(&self, slot: u64, epoch: u64) -> Option<&Gateway> {
+		let candidates = self.candidates(epoch);
+		if candidates.is_empty() {
+			return None;
+		}
+
+		match self.rule {
+			DelegationRule::RoundRobin => Some(candidates[(slot % candidates.len() as u64) as usize]),
+			DelegationRule::HighestBid => {
+				// max_by_key returns the last maximum, so search from the back to keep the first configured on ties
+				candidates.into_iter().rev().max_by_key(|gateway| gateway.bid_gwei)
+			}
+			DelegationRule::PercentageSplit => {
+				let total_weight = candidates.iter().map(|gateway| gateway.weight).sum::<u64>();
+				if total_weight == 0 {
+					return None;
+				}
+
+				// Hash the slot so the split does not follow the slot numbering, but is the same on every restart
+				let hash = keccak256(slot.to_be_bytes());
+				let mut point = u64::from_be_bytes(hash[..8].try_into().expect("hash is 32 bytes")) % total_weight;
+				candidates.into_iter().find(|gateway| {
+					if point < gateway.weight {
+						return true;
+					}
+					point -= gateway.weight;
+					false
+				})
+			}
+		}
+	}
+}
+
+fn default_weight() -> u64 {
+	1
+}
+
+fn default_known_gateways_only() -> bool {
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn gateway(name: &str, byte: u8, weight: u64, bid_gwei: u64) -> Gateway {
+		Gateway {
+			name: name.to_string(),
+			public_key: BlsPublicKey::from([byte; 48]),
+			address: Address::repeat_byte(byte),
+			weight,
+			bid_gwei,
+			terms: DelegationMetadata::default(),
+		}
+	}
+
+	fn policy(rule: DelegationRule, allowlists: Vec<EpochAllowlist>) -> DelegationPolicy {
+		let gateways = vec![gateway("a", 1, 3, 5), gateway("b", 2, 1, 9), gateway("c", 3, 0, 9)];
+		DelegationPolicy::new(DelegationPolicyConfig { rule, allowlists }, gateways).unwrap()
+	}
+
+	fn selected(policy: &DelegationPolicy, slot: u64, epoch: u64) -> Option<&str> {
+		policy.select(slot, epoch).map(|gateway| gateway.name.as_str())
+	}
+
+	#[test]
+	fn test_round_robin_and_highest_bid() {
+		let round_robin = policy(DelegationRule::RoundRobin, vec![]);
+		assert_eq!(selected(&round_robin, 0, 0), Some("a"));
+		assert_eq!(selected(&round_robin, 1, 0), Some("b"));
+		assert_eq!(selected(&round_robin, 5, 0), Some("c"));
+
+		// b and c bid the same, b is configured first
+		let highest_bid = policy(DelegationRule::HighestBid, vec![]);
+		assert_eq!(selected(&highest_bid, 0, 0), Some("b"));
+	}
+
+	#[test]
+	fn test_allowlist_restricts_gateways_per_epoch() {
+		let allowlists = vec![
+			EpochAllowlist { from_epoch: 10, to_epoch: Some(19), gateways: vec!["a".to_string()] },
+			EpochAllowlist { from_epoch: 20, to_epoch: None, gateways: vec![] },
+		];
+		let policy = policy(DelegationRule::HighestBid, allowlists);
+
+		assert_eq!(selected(&policy, 0, 9), Some("b"));
+		assert_eq!(selected(&policy, 320, 10), Some("a"));
+		assert_eq!(selected(&policy, 608, 19), Some("a"));
+		assert_eq!(selected(&policy, 640, 20), None);
+
+		let unknown = EpochAllowlist { from_epoch: 0, to_epoch: None, gateways: vec!["d".to_string()] };
+		let config = DelegationPolicyConfig { rule: DelegationRule::RoundRobin, allowlists: vec![unknown] };
+		assert!(DelegationPolicy::new(config, vec![gateway("a", 1, 1, 0)]).is_err());
+	}
+
+	#[test]
+	fn test_select_offer_ranks_by_bid() {
+		let round_robin = policy(DelegationRule::RoundRobin, vec![]);
+		let offer = |byte: u8, slot: u64, bid_gwei: u64| DelegationOffer {
+			delegate: BlsPublicKey::from([byte; 48]),
+			committer: Address::repeat_byte(byte),
+			slot,
+			bid_gwei,
+			constraint_types: vec![1],
+		};
+		let known_only = DelegationOffersConfig { enabled: true, ..Default::default() };
+		let ranked = |offers: &[DelegationOffer], config: &DelegationOffersConfig| {
+			round_robin.select_offer(7, 0, offers, config).map(|gateway| (gateway.name, gateway.bid_gwei))
+		};
+
+		// The highest bid wins with the offered bid, the first offer on ties, and offers for other slots are ignored
+		let offers = [offer(1, 7, 20), offer(2, 7, 30), offer(3, 7, 30), offer(1, 8, 99)];
+		assert_eq!(ranked(&offers, &known_only), Some(("b".to_string(), 30)));
+
+		// Offers below the minimum bid, or naming another committer than the configured one, do not qualify
+		let min_bid = DelegationOffersConfig { min_bid_gwei: 25, ..known_only.clone() };
+		assert_eq!(ranked(&[offer(1, 7, 20)], &min_bid), None);
+		let wrong_committer = DelegationOffer { committer: Address::repeat_byte(9), ..offer(1, 7, 20) };
+		assert_eq!(ranked(&[wrong_committer], &known_only), None);
+
+		// Unknown gateways are only accepted when not restricted to the configured ones
+		let offers = [offer(1, 7, 20), offer(4, 7, 50)];
+		assert_eq!(ranked(&offers, &known_only), Some(("a".to_string(), 20)));
+		let open = DelegationOffersConfig { known_gateways_only: false, ..known_only.clone() };
+		let gateway = round_robin.select_offer(7, 0, &offers, &open).unwrap();
+		assert_eq!(gateway.public_key, BlsPublicKey::from([4; 48]));
+		assert_eq!(gateway.terms, DelegationMetadata::default());
+
+		// A configured gateway outside of the epoch's allowlist never qualifies
+		let allowlist = EpochAllowlist { from_epoch: 0, to_epoch: None, gateways: vec!["a".to_string()] };
+		let restricted = policy(DelegationRule::RoundRobin, vec![allowlist]);
+		assert_eq!(restricted.select_offer(7, 0, &[offer(2, 7, 30)], &open), None);
+	}
+
+	#[test]
+	fn test_percentage_split_follows_weights() {
+		let policy = policy(DelegationRule::PercentageSplit, vec![]);
+
+		let mut counts = [0usize; 2];
+		for slot in 0..4_000 {
+			match selected(&policy, slot, 0) {
+				Some("a") => counts[0] += 1,
+				Some("b") => counts[1] += 1,
+				other => panic!("Unexpected gateway {:?}", other),
+			}
+		}
+		// a has three times the weight of b, c has none
+		assert!((2_800..3_200).contains(&counts[0]), "a got {} slots", counts[0]);
+		assert_eq!(counts[0] + counts[1], 4_000);
+		assert_eq!(selected(&policy, 42, 0), selected(&policy, 42, 1));
+	}
+}
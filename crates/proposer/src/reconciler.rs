@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use constraints::client::{ConstraintsClient, HttpConstraintsClient};
+use constraints::types::SignedDelegation;
+use eyre::Result;
+use tracing::{debug, error, info, warn};
+
+use crate::state::ProposerState;
+use crate::storage::DelegationsDbExt;
+use crate::types::{RelayAck, RelayAckStatus};
+
+/// Checks that the relays still hold the delegations signed for upcoming slots
+///
+/// A relay that lost a delegation, e.g. after its database was wiped, gets it posted again. A relay holding a
+/// delegation of one of our proposers that differs from the one we signed is reported, as it was not signed
+/// by this proposer or points at a different gateway.
+pub struct DelegationReconciler {
+	state: Arc<ProposerState>,
+}
+
+impl DelegationReconciler {
+	pub fn new(state: Arc<ProposerState>) -> Self {
+		Self { state }
+	}
+
+	/// Reconcile every `reconcile_interval_seconds`
+	pub async fn run(&self) -> Result<()> {
+		info!("Starting delegation reconciler with {}s interval", self.state.reconcile_interval_seconds);
+		let mut interval = tokio::time::interval(Duration::from_secs(self.state.reconcile_interval_seconds));
+
+		loop {
+			interval.tick().await;
+			if let Err(e) = self.reconcile().await {
+				error!("Error reconciling delegations with the relays: {}", e);
+			}
+		}
+	}
+
+	/// Compare the delegations stored for the rest of the current and the next epoch with every relay
+	pub async fn reconcile(&self) -> Result<()> {
+		let current_slot = self.state.slot_clock.current_slot();
+		let current_epoch = self.state.slot_clock.slot_to_epoch(current_slot);
+		let end_slot = self.state.slot_clock.epoch_to_last_slot(current_epoch + 1);
+
+		let delegations = self.state.db.get_delegations_in_range(current_slot + 1, end_slot)?;
+		debug!("Reconciling {} delegation(s) up to slot {}", delegations.len(), end_slot);
+
+		for (slot, delegation) in delegations {
+			for client in &self.state.constraints_clients {
+				if let Err(e) = self.reconcile_slot(client, slot, &delegation).await {
+					warn!("Failed to reconcile the slot {} delegation with relay {}: {}", slot, client.base_url, e);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn reconcile_slot(&self, client: &HttpConstraintsClient, slot: u64, signed: &SignedDelegation) -> Result<()> {
+		let held = client.get_delegations(slot).await?;
+		let ours = held.iter().filter(|delegation| delegation.message.proposer == signed.message.proposer);
+
+		let mut found = false;
+		for delegation in ours {
+			if delegation.signature == signed.signature {
+				found = true;
+			} else {
+				error!(
+					"Relay {} holds a delegation for slot {} that this proposer did not sign: delegate={}, committer={}",
+					client.base_url, slot, delegation.message.delegate, delegation.message.committer
+				);
+			}
+		}
+		if found {
+			return Ok(());
+		}
+
+		warn!("Relay {} is missing the slot {} delegation, posting it again", client.base_url, slot);
+		let status = match client.post_delegation(signed).await {
+			Ok(receipt) => RelayAckStatus::Accepted { receipt },
+			Err(e) => RelayAckStatus::Rejected { error: e.to_string() },
+		};
+
+		let relay = client.base_url.to_string();
+		let mut acks = self.state.db.get_relay_acks(slot)?;
+		acks.retain(|ack| ack.relay != relay);
+		acks.push(RelayAck { relay, status });
+		self.state.db.store_relay_acks(slot, &acks)?;
+		Ok(())
+	}
+}
@@ -1,85 +1,173 @@
-use alloy::hex;
-use alloy::primitives::{Address, B256};
-use alloy::rpc::types::beacon::BlsPublicKey;
-use commit_boost::prelude::{Chain, StartCommitModuleConfig, commit::client::SignerClient};
+use std::sync::Arc;
+
+use alloy::primitives::B256;
+use commit_boost::prelude::{Chain, StartCommitModuleConfig};
+use eyre::{Result, WrapErr, eyre};
 
 use common::storage::DatabaseContext;
 use common::utils::{decode_address, decode_pubkey};
 use constraints::client::HttpConstraintsClient;
 use lookahead::{
 	beacon_client::{BeaconApiClient, ReqwestClient},
+	cache::LookaheadCache,
+	slot_clock::SlotClock,
 	types::BeaconApiConfig,
 };
 use reqwest::Url;
+use signing::nonce::NonceManager;
+use signing::signer::SigningClient;
 
+use crate::auditor::ConstraintAuditConfig;
 use crate::config::ProposerConfig;
+use crate::policy::{DelegationOffersConfig, DelegationPolicy, Gateway};
+use crate::validators::ValidatorSelector;
 
 /// Server state that provides access to shared resources for proposer operations
 #[derive(Clone)]
 pub struct ProposerState {
 	/// Storage
 	pub db: DatabaseContext,
-	/// Signer client for calling the signer API
-	pub signer_client: SignerClient,
-	/// Constraints client for sending constraints to the relay
-	pub constraints_client: HttpConstraintsClient,
+	/// Signer client for calling the signer API, or the local signer
+	pub signer_client: SigningClient,
+	/// Allocates the nonces of signed delegations
+	pub nonce_manager: Arc<NonceManager>,
+	/// Constraints clients of every relay delegations are posted to, the configured relay first
+	pub constraints_clients: Vec<HttpConstraintsClient>,
+	/// Number of relays that must accept a delegation before its slot counts as delegated
+	pub delegation_quorum: usize,
 	/// Beacon client for fetching proposer duties
 	pub beacon_client: BeaconApiClient<ReqwestClient>,
-	/// Gateway delegate BLS public key
-	pub gateway_public_key: BlsPublicKey,
-	/// Gateway committer EOA address
-	pub gateway_address: Address,
+	/// Slot clock synced against the beacon node
+	pub slot_clock: Arc<SlotClock>,
+	/// Proposer duties prefetched from the beacon node
+	pub lookahead_cache: Arc<LookaheadCache>,
+	/// Picks the gateway each slot is delegated to
+	pub delegation_policy: DelegationPolicy,
+	/// Whether and which delegation offers of the gateways are ranked before the delegation policy
+	pub delegation_offers: DelegationOffersConfig,
+	/// Validators that delegate, reloaded when their file changes
+	pub validator_selector: Arc<ValidatorSelector>,
 	/// Module signing ID for inclusion preconfs
 	pub module_signing_id: B256,
 	/// Chain ID
 	pub chain: Chain,
 	/// How often to check for new delegations
 	pub lookahead_check_interval_seconds: u64,
+	/// How often to reconcile the stored delegations with the relays
+	pub reconcile_interval_seconds: u64,
+	/// Limits the constraints of delegated slots are audited against
+	pub constraint_audit: ConstraintAuditConfig,
 }
 
 impl ProposerState {
-	pub fn new(db: DatabaseContext, config: StartCommitModuleConfig<ProposerConfig>) -> Self {
-		// Create constraints client
-		let constraints_client = HttpConstraintsClient::new(
-			config.extra.relay_host,
-			config.extra.relay_port,
-			config.extra.relay_api_key.clone(),
-		);
+	pub fn new(db: DatabaseContext, config: StartCommitModuleConfig<ProposerConfig>) -> Result<Self> {
+		// Create constraints clients, the configured relay first followed by the additional relays
+		let relay_http = &config.extra.relay_http;
+		let mut constraints_clients = vec![
+			HttpConstraintsClient::with_http_config(
+				config.extra.relay_host,
+				config.extra.relay_port,
+				config.extra.relay_api_key.clone(),
+				relay_http,
+			)
+			.wrap_err("Failed to create the relay client")?,
+		];
+		for relay in &config.extra.additional_relays {
+			constraints_clients.push(
+				HttpConstraintsClient::with_http_config(
+					relay.host.clone(),
+					relay.port,
+					relay.api_key.clone(),
+					relay_http,
+				)
+				.wrap_err_with(|| format!("Failed to create the client of relay {}:{}", relay.host, relay.port))?,
+			);
+		}
+		let delegation_quorum = config.extra.delegation_quorum;
+		if !(1..=constraints_clients.len()).contains(&delegation_quorum) {
+			return Err(eyre!(
+				"Delegation quorum must be between 1 and the number of relays ({}), got {}",
+				constraints_clients.len(),
+				delegation_quorum
+			));
+		}
 
 		// Create beacon client
 		let beacon_client = BeaconApiClient::with_default_client(BeaconApiConfig {
 			primary_endpoint: Url::parse(
 				format!("http://{}:{}", config.extra.beacon_api_host, config.extra.beacon_api_port).as_str(),
 			)
-			.unwrap(),
+			.wrap_err_with(|| {
+				format!("Invalid beacon API address {}:{}", config.extra.beacon_api_host, config.extra.beacon_api_port)
+			})?,
 			fallback_endpoints: vec![],
 			request_timeout_secs: 30,
 			genesis_time: config.chain.genesis_time_sec(),
 		})
-		.expect("Failed to create beacon client");
+		.wrap_err("Failed to create beacon client")?;
+		let slot_clock = Arc::new(SlotClock::new(&config.chain).with_beacon_client(beacon_client.clone()));
+		let lookahead_cache = Arc::new(LookaheadCache::new(
+			beacon_client.clone(),
+			Arc::clone(&slot_clock),
+			config.extra.lookahead_prefetch_epochs,
+		));
 
-		let signer_client = config.signer_client.clone();
+		let signer_client =
+			SigningClient::new(config.signer_client.clone(), config.extra.local_signer.as_ref(), config.chain)
+				.wrap_err("Failed to load local signer keys")?;
+		let nonce_manager = Arc::new(NonceManager::new(db.clone()));
 
-		let gateway_public_key =
-			decode_pubkey(config.extra.gateway_public_key.as_str()).expect("Failed to decode gateway public key");
-		let gateway_address =
-			decode_address(config.extra.gateway_address.as_str()).expect("Failed to decode gateway address");
+		// The single gateway of older configs comes first, followed by the configured gateways
+		let mut gateways = Vec::with_capacity(config.extra.gateways.len() + 1);
+		match (&config.extra.gateway_public_key, &config.extra.gateway_address) {
+			(Some(public_key), Some(address)) => gateways.push(Gateway {
+				name: "default".to_string(),
+				public_key: decode_pubkey(public_key)
+					.wrap_err_with(|| format!("Invalid gateway_public_key {}", public_key))?,
+				address: decode_address(address).wrap_err_with(|| format!("Invalid gateway_address {}", address))?,
+				weight: 1,
+				bid_gwei: 0,
+				terms: Default::default(),
+			}),
+			(None, None) => {}
+			_ => return Err(eyre!("gateway_public_key and gateway_address must be set together")),
+		}
+		for gateway in &config.extra.gateways {
+			gateways.push(Gateway::try_from(gateway).wrap_err_with(|| format!("Invalid gateway {}", gateway.name))?);
+		}
+		let delegation_policy = DelegationPolicy::new(config.extra.delegation_policy.clone(), gateways)
+			.wrap_err("Invalid delegation policy")?;
+		let validator_selector = Arc::new(
+			ValidatorSelector::new(&config.extra.validators, delegation_policy.clone())
+				.wrap_err("Invalid validator selection")?,
+		);
 
 		let chain = config.chain;
-		let module_signing_id = B256::from_slice(
-			&hex::decode(config.extra.module_signing_id.as_str()).expect("Failed to decode module signing id"),
-		);
+		let module_signing_id = config
+			.extra
+			.module_signing_id
+			.parse::<B256>()
+			.wrap_err_with(|| format!("Invalid module_signing_id {}", config.extra.module_signing_id))?;
 		let lookahead_check_interval_seconds = config.extra.lookahead_check_interval_seconds;
-		Self {
+		let reconcile_interval_seconds = config.extra.reconcile_interval_seconds;
+		let constraint_audit = config.extra.constraint_audit.clone();
+		Ok(Self {
 			db,
 			signer_client,
-			constraints_client,
+			nonce_manager,
+			constraints_clients,
+			delegation_quorum,
 			beacon_client,
-			gateway_public_key,
-			gateway_address,
+			slot_clock,
+			lookahead_cache,
+			delegation_policy,
+			delegation_offers: config.extra.delegation_offers.clone(),
+			validator_selector,
 			module_signing_id,
 			chain,
 			lookahead_check_interval_seconds,
-		}
+			reconcile_interval_seconds,
+			constraint_audit,
+		})
 	}
 }
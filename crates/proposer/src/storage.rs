@@ -1,13 +1,19 @@
+use alloy::rpc::types::beacon::BlsPublicKey;
 use constraints::types::SignedDelegation;
-use eyre::Result;
 
 use common::storage::{
-	DatabaseContext,
-	db::{TypedDbExt, scan_slot_range_kind},
+	DatabaseContext, Result,
+	db::{DbOp, TypedDbExt, delete_slot_range_kind, scan_slot_range_kind},
 };
 
+use crate::types::{ConstraintAttestation, RelayAck};
+
 /// 1-byte table tags so everything shares the same RocksDB instance.
 const KIND_SIGNED_DELEGATION: u8 = b'A';
+const KIND_DELEGATE_DELEGATION: u8 = b'G';
+const KIND_RELAY_ACKS: u8 = b'T';
+const KIND_DELEGATED: u8 = b'U';
+const KIND_CONSTRAINT_ATTESTATIONS: u8 = b'V';
 
 /// Key for a single SignedDelegation.
 /// Layout: [ 'A' ][ slot_be ]
@@ -18,11 +24,72 @@ pub fn signed_delegation_key(slot: u64) -> [u8; 1 + 8] {
 	key
 }
 
+/// Key for a SignedDelegation to a specific delegate.
+/// Layout: [ 'G' ][ slot_be ][ delegate (48 bytes) ]
+pub fn delegate_delegation_key(slot: u64, delegate: &BlsPublicKey) -> [u8; 1 + 8 + 48] {
+	let mut key = [0u8; 1 + 8 + 48];
+	key[0] = KIND_DELEGATE_DELEGATION;
+	key[1..9].copy_from_slice(&slot.to_be_bytes());
+	key[9..].copy_from_slice(delegate.as_slice());
+	key
+}
+
+/// Key for the relay acknowledgments of a slot's delegation.
+/// Layout: [ 'T' ][ slot_be ]
+pub fn relay_acks_key(slot: u64) -> [u8; 1 + 8] {
+	let mut key = [0u8; 1 + 8];
+	key[0] = KIND_RELAY_ACKS;
+	key[1..].copy_from_slice(&slot.to_be_bytes());
+	key
+}
+
+/// Key for the flag set once a quorum of relays accepted a slot's delegation.
+/// Layout: [ 'U' ][ slot_be ]
+pub fn delegated_key(slot: u64) -> [u8; 1 + 8] {
+	let mut key = [0u8; 1 + 8];
+	key[0] = KIND_DELEGATED;
+	key[1..].copy_from_slice(&slot.to_be_bytes());
+	key
+}
+
+/// Key for the constraint attestations of a delegated slot.
+/// Layout: [ 'V' ][ slot_be ]
+pub fn constraint_attestations_key(slot: u64) -> [u8; 1 + 8] {
+	let mut key = [0u8; 1 + 8];
+	key[0] = KIND_CONSTRAINT_ATTESTATIONS;
+	key[1..].copy_from_slice(&slot.to_be_bytes());
+	key
+}
+
 pub trait DelegationsDbExt {
 	fn store_delegation(&self, delegation: &SignedDelegation) -> Result<()>;
 	fn get_delegation(&self, slot: u64) -> Result<Option<SignedDelegation>>;
 	fn get_delegations_in_range(&self, start_slot: u64, end_slot: u64) -> Result<Vec<(u64, SignedDelegation)>>;
+
+	/// Acknowledgment of the slot's delegation by each relay it was posted to
+	fn store_relay_acks(&self, slot: u64, acks: &[RelayAck]) -> Result<()>;
+	fn get_relay_acks(&self, slot: u64) -> Result<Vec<RelayAck>>;
+
+	/// A slot is delegated once a quorum of relays accepted its delegation, not when it is signed
+	fn mark_delegated(&self, slot: u64) -> Result<()>;
 	fn is_delegated(&self, slot: u64) -> Result<bool>;
+
+	/// Constraints served by each relay for the slot, as audited before the slot
+	fn store_constraint_attestations(&self, slot: u64, attestations: &[ConstraintAttestation]) -> Result<()>;
+	fn get_constraint_attestations(&self, slot: u64) -> Result<Vec<ConstraintAttestation>>;
+
+	/// Delegations keyed by delegate, used when a slot can be delegated to several gateways
+	fn store_delegate_delegation(&self, delegation: &SignedDelegation) -> Result<()>;
+	fn get_delegate_delegation(&self, slot: u64, delegate: &BlsPublicKey) -> Result<Option<SignedDelegation>>;
+	fn get_slot_delegations(&self, slot: u64) -> Result<Vec<SignedDelegation>>;
+	fn delete_delegate_delegation(&self, slot: u64, delegate: &BlsPublicKey) -> Result<()>;
+
+	/// Move the delegations a relay stored per slot under 'A', before slots could be delegated to several gateways,
+	/// under their per-delegate 'G' key. Only for relay databases, proposers keep their own delegations under 'A'
+	fn migrate_slot_delegations(&self) -> Result<usize>;
+
+	/// Delete all delegations before the given slot
+	fn prune_delegations_before_slot(&self, slot: u64) -> Result<usize>;
 }
 
 impl DelegationsDbExt for DatabaseContext {
@@ -41,15 +108,101 @@ impl DelegationsDbExt for DatabaseContext {
 		scan_slot_range_kind::<SignedDelegation>(self, KIND_SIGNED_DELEGATION, start_slot, end_slot)
 	}
 
+	fn store_relay_acks(&self, slot: u64, acks: &[RelayAck]) -> Result<()> {
+		let key = relay_acks_key(slot);
+		self.put_json(&key, &acks)
+	}
+
+	fn get_relay_acks(&self, slot: u64) -> Result<Vec<RelayAck>> {
+		let key = relay_acks_key(slot);
+		Ok(self.get_json(&key)?.unwrap_or_default())
+	}
+
+	fn mark_delegated(&self, slot: u64) -> Result<()> {
+		let key = delegated_key(slot);
+		self.put_json(&key, &true)
+	}
+
 	fn is_delegated(&self, slot: u64) -> Result<bool> {
-		Ok(self.get_delegation(slot)?.is_some())
+		let key = delegated_key(slot);
+		let flag: Option<bool> = self.get_json(&key)?;
+		Ok(flag.unwrap_or(false))
+	}
+
+	fn store_constraint_attestations(&self, slot: u64, attestations: &[ConstraintAttestation]) -> Result<()> {
+		let key = constraint_attestations_key(slot);
+		self.put_json(&key, &attestations)
+	}
+
+	fn get_constraint_attestations(&self, slot: u64) -> Result<Vec<ConstraintAttestation>> {
+		let key = constraint_attestations_key(slot);
+		Ok(self.get_json(&key)?.unwrap_or_default())
+	}
+
+	fn store_delegate_delegation(&self, delegation: &SignedDelegation) -> Result<()> {
+		let key = delegate_delegation_key(delegation.message.slot, &delegation.message.delegate);
+		self.put_json(&key, delegation)
+	}
+
+	fn get_delegate_delegation(&self, slot: u64, delegate: &BlsPublicKey) -> Result<Option<SignedDelegation>> {
+		let key = delegate_delegation_key(slot, delegate);
+		self.get_json(&key)
+	}
+
+	fn get_slot_delegations(&self, slot: u64) -> Result<Vec<SignedDelegation>> {
+		// Keys sort by delegate within a slot, so the result order is deterministic
+		let delegations = scan_slot_range_kind::<SignedDelegation>(self, KIND_DELEGATE_DELEGATION, slot, slot)?;
+		Ok(delegations.into_iter().map(|(_, delegation)| delegation).collect())
+	}
+
+	fn delete_delegate_delegation(&self, slot: u64, delegate: &BlsPublicKey) -> Result<()> {
+		let key = delegate_delegation_key(slot, delegate);
+		self.delete_raw(&key)
+	}
+
+	fn migrate_slot_delegations(&self) -> Result<usize> {
+		let legacy = scan_slot_range_kind::<SignedDelegation>(self, KIND_SIGNED_DELEGATION, 0, u64::MAX)?;
+		let mut ops = Vec::with_capacity(legacy.len() * 2);
+		for (slot, delegation) in &legacy {
+			let key = delegate_delegation_key(delegation.message.slot, &delegation.message.delegate);
+			// A delegation already stored per delegate was posted after the legacy one, keep it
+			if self.get_raw(&key)?.is_none() {
+				ops.push(DbOp::Put { key: key.to_vec(), value: serde_json::to_vec(delegation)? });
+			}
+			ops.push(DbOp::Delete { key: signed_delegation_key(*slot).to_vec() });
+		}
+		// Written at once, so an interrupted migration is simply run again at the next start
+		self.batch_write_raw(ops)?;
+		Ok(legacy.len())
+	}
+
+	fn prune_delegations_before_slot(&self, slot: u64) -> Result<usize> {
+		if slot == 0 {
+			return Ok(0);
+		}
+
+		let mut deleted = 0;
+		for kind in [
+			KIND_SIGNED_DELEGATION,
+			KIND_DELEGATE_DELEGATION,
+			KIND_RELAY_ACKS,
+			KIND_DELEGATED,
+			KIND_CONSTRAINT_ATTESTATIONS,
+		] {
+			deleted += delete_slot_range_kind(self, kind, 0, slot - 1)?;
+		}
+		Ok(deleted)
 	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::types::RelayAckStatus;
+	use alloy::primitives::{Address, B256, Bytes};
+	use alloy::rpc::types::beacon::BlsSignature;
 	use common::storage::db::slot_prefix;
+	use constraints::types::Delegation;
 	use eyre::Result;
 	use rocksdb::Options;
 	use serde::{Deserialize, Serialize};
@@ -91,6 +244,80 @@ mod tests {
 		assert_eq!(parsed, slot);
 	}
 
+	#[test]
+	fn delegate_delegation_key_layout_is_correct() {
+		let slot = 42u64;
+		let delegate = BlsPublicKey::from([0x22u8; 48]);
+		let key = delegate_delegation_key(slot, &delegate);
+
+		assert_eq!(key.len(), 1 + 8 + 48);
+		assert_eq!(key[0], KIND_DELEGATE_DELEGATION);
+
+		let mut slot_bytes = [0u8; 8];
+		slot_bytes.copy_from_slice(&key[1..9]);
+		assert_eq!(u64::from_be_bytes(slot_bytes), slot);
+		assert_eq!(&key[9..], delegate.as_slice());
+	}
+
+	#[test]
+	fn delegate_delegations_scan_returns_all_delegates_for_slot() -> Result<()> {
+		let db = new_temp_db()?;
+
+		// Two delegates at slot 10, one at slot 11
+		db.put_json(&delegate_delegation_key(10, &BlsPublicKey::from([0x02u8; 48])), &make_test_value(2))?;
+		db.put_json(&delegate_delegation_key(10, &BlsPublicKey::from([0x01u8; 48])), &make_test_value(1))?;
+		db.put_json(&delegate_delegation_key(11, &BlsPublicKey::from([0x03u8; 48])), &make_test_value(3))?;
+
+		let values = super::scan_slot_range_kind::<TestValue>(&db, KIND_DELEGATE_DELEGATION, 10, 10)?;
+		assert_eq!(values.len(), 2);
+		// Ordered by delegate key
+		assert_eq!(values[0].1, make_test_value(1));
+		assert_eq!(values[1].1, make_test_value(2));
+
+		Ok(())
+	}
+
+	fn make_delegation(slot: u64, delegate: u8) -> SignedDelegation {
+		SignedDelegation {
+			message: Delegation {
+				proposer: BlsPublicKey::from([0x01u8; 48]),
+				delegate: BlsPublicKey::from([delegate; 48]),
+				committer: Address::ZERO,
+				slot,
+				metadata: Bytes::new(),
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: BlsSignature::from([0u8; 96]),
+		}
+	}
+
+	#[test]
+	fn migrate_slot_delegations_moves_legacy_entries_per_delegate() -> Result<()> {
+		let db = new_temp_db()?;
+
+		// Slot 10 was only delegated before the upgrade, slot 11 was delegated again since
+		db.store_delegation(&make_delegation(10, 0x02))?;
+		db.store_delegation(&make_delegation(11, 0x03))?;
+		let mut reposted = make_delegation(11, 0x03);
+		reposted.nonce = 1;
+		db.store_delegate_delegation(&reposted)?;
+
+		assert_eq!(db.migrate_slot_delegations()?, 2);
+
+		let delegate = BlsPublicKey::from([0x02u8; 48]);
+		assert_eq!(db.get_delegate_delegation(10, &delegate)?.map(|d| d.message.slot), Some(10));
+		assert_eq!(db.get_slot_delegations(11)?.len(), 1);
+		assert_eq!(db.get_slot_delegations(11)?[0].nonce, 1);
+		assert!(db.get_delegation(10)?.is_none());
+		assert!(db.get_delegation(11)?.is_none());
+
+		// Nothing left to move on the next start
+		assert_eq!(db.migrate_slot_delegations()?, 0);
+
+		Ok(())
+	}
+
 	#[test]
 	fn slot_prefix_layout_is_correct() {
 		let slot = 1234u64;
@@ -145,4 +372,36 @@ mod tests {
 
 		Ok(())
 	}
+
+	#[test]
+	fn relay_acks_and_quorum_flag_roundtrip() -> Result<()> {
+		let db = new_temp_db()?;
+
+		assert!(db.get_relay_acks(7)?.is_empty());
+		assert!(!db.is_delegated(7)?);
+
+		let acks = vec![
+			RelayAck { relay: "http://relay-a:9998/".to_string(), status: RelayAckStatus::Accepted { receipt: None } },
+			RelayAck {
+				relay: "http://relay-b:9998/".to_string(),
+				status: RelayAckStatus::Rejected { error: "unreachable".to_string() },
+			},
+		];
+		db.store_relay_acks(7, &acks)?;
+		let stored = db.get_relay_acks(7)?;
+		assert_eq!(stored.len(), 2);
+		assert!(stored[0].is_accepted());
+		assert!(!stored[1].is_accepted());
+
+		db.mark_delegated(7)?;
+		assert!(db.is_delegated(7)?);
+		assert!(!db.is_delegated(8)?);
+
+		// Pruned along with the delegations
+		db.prune_delegations_before_slot(8)?;
+		assert!(db.get_relay_acks(7)?.is_empty());
+		assert!(!db.is_delegated(7)?);
+
+		Ok(())
+	}
 }
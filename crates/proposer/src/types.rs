@@ -0,0 +1,53 @@
+use alloy::rpc::types::beacon::BlsPublicKey;
+use constraints::types::{SignedConstraints, SignedReceipt};
+use serde::{Deserialize, Serialize};
+
+/// Outcome of posting a delegation to a relay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RelayAckStatus {
+	/// Accepted, with the relay's receipt if it issues receipts
+	Accepted { receipt: Option<SignedReceipt> },
+	/// Rejected or unreachable, the delegation is posted again on the next lookahead check
+	Rejected { error: String },
+}
+
+/// Acknowledgment of a delegation by one relay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayAck {
+	/// Base URL of the relay
+	pub relay: String,
+	pub status: RelayAckStatus,
+}
+
+impl RelayAck {
+	pub fn is_accepted(&self) -> bool {
+		matches!(self.status, RelayAckStatus::Accepted { .. })
+	}
+}
+
+/// Local record of the constraints one relay served for a delegated slot, checked before the slot started
+///
+/// Keeps the signed constraints as served, so the proposer can later show what the gateway committed it to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstraintAttestation {
+	pub slot: u64,
+	/// Base URL of the relay the constraints were fetched from
+	pub relay: String,
+	/// Delegate of the slot's delegation
+	pub delegate: BlsPublicKey,
+	/// Unix time in milliseconds at which the constraints were fetched
+	pub fetched_at_ms: u64,
+	pub constraints: Vec<SignedConstraints>,
+	/// Number of constraints across every message
+	pub constraint_count: usize,
+	/// Gas limit summed over the transactions of the inclusion constraints
+	pub gas: u64,
+	/// Every check the constraints failed, empty if they are within policy
+	pub violations: Vec<String>,
+}
+
+impl ConstraintAttestation {
+	pub fn is_clean(&self) -> bool {
+		self.violations.is_empty()
+	}
+}
@@ -1,19 +1,24 @@
-use alloy::primitives::{Address, B256, Bytes};
+use alloy::primitives::{Address, B256};
 use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
 use eyre::Result;
-use signing::signer;
+use signing::nonce::{NonceKind, NonceManager};
+use signing::signer::{self, SigningClient, verify_bls};
 
-use commit_boost::prelude::{Chain, commit::client::SignerClient};
-use constraints::types::{Delegation, SignedDelegation};
-use urc::utils::get_delegation_signing_root;
+use commit_boost::prelude::Chain;
+use constraints::metadata::DelegationMetadata;
+use constraints::types::{Delegation, SignedDelegation, SignedDelegationOffer};
+use urc::utils::{get_delegation_offer_signing_root, get_delegation_signing_root};
 
-/// Sign a delegation message using the consensus BLS key
+/// Sign a delegation message using the consensus BLS key, under the proposer's next delegation nonce
+/// The gateway's `terms` are encoded into the delegation metadata
 pub async fn create_signed_delegation(
-	signer_client: &mut SignerClient,
+	signer_client: &mut SigningClient,
+	nonce_manager: &NonceManager,
 	proposer_public_key: &BlsPublicKey,
 	gateway_public_key: &BlsPublicKey,
 	slot: u64,
 	gateway_address: &Address,
+	terms: &DelegationMetadata,
 	module_signing_id: &B256,
 	chain: &Chain,
 ) -> Result<SignedDelegation> {
@@ -22,10 +27,11 @@ pub async fn create_signed_delegation(
 		delegate: gateway_public_key.clone(),
 		committer: gateway_address.clone(),
 		slot,
-		metadata: Bytes::new(),
+		metadata: terms.encode(),
 	};
 
 	let signing_root = get_delegation_signing_root(&delegation)?;
+	let nonce = nonce_manager.next_nonce(proposer_public_key.as_slice(), NonceKind::Delegation)?;
 
 	// Sign using the signer client
 	let response = signer::call_bls_signer(
@@ -34,6 +40,7 @@ pub async fn create_signed_delegation(
 		proposer_public_key.clone(),
 		module_signing_id,
 		chain.clone(),
+		nonce,
 	)
 	.await?;
 
@@ -44,3 +51,55 @@ pub async fn create_signed_delegation(
 		signature: BlsSignature::new(response.signature.serialize()),
 	})
 }
+
+/// Verify that a delegation offer fetched from a relay was signed by the gateway it names as delegate
+pub fn verify_delegation_offer(signed_offer: &SignedDelegationOffer, chain: &Chain) -> Result<()> {
+	verify_bls(
+		chain.clone(),
+		&signed_offer.message.delegate,
+		&get_delegation_offer_signing_root(&signed_offer.message),
+		&signed_offer.signature,
+		&signed_offer.signing_id,
+		signed_offer.nonce,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use cb_common::types::BlsSecretKey;
+	use constraints::types::DelegationOffer;
+	use signing::local::LocalSigner;
+
+	#[test]
+	fn test_verify_delegation_offer() {
+		let mut signer = LocalSigner::new(Chain::Holesky);
+		let delegate = signer.add_bls_key(BlsSecretKey::random());
+		let offer = DelegationOffer {
+			delegate,
+			committer: Address::repeat_byte(1),
+			slot: 100,
+			bid_gwei: 1_000,
+			constraint_types: vec![1],
+		};
+		let signing_id = B256::repeat_byte(2);
+		let signature = signer.sign_bls(&delegate, get_delegation_offer_signing_root(&offer), &signing_id, 3).unwrap();
+		let signed_offer = SignedDelegationOffer {
+			message: offer,
+			nonce: 3,
+			signing_id,
+			signature: BlsSignature::new(signature.serialize()),
+		};
+		verify_delegation_offer(&signed_offer, &Chain::Holesky).unwrap();
+
+		// The signature covers the offer, its nonce and the chain
+		let mut raised = signed_offer.clone();
+		raised.message.bid_gwei += 1;
+		assert!(verify_delegation_offer(&raised, &Chain::Holesky).is_err());
+		assert!(
+			verify_delegation_offer(&SignedDelegationOffer { nonce: 4, ..signed_offer.clone() }, &Chain::Holesky)
+				.is_err()
+		);
+		assert!(verify_delegation_offer(&signed_offer, &Chain::Mainnet).is_err());
+	}
+}
@@ -0,0 +1,205 @@
+//! Selection of the validators that delegate, and of the gateway some of them delegate to
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use alloy::rpc::types::beacon::BlsPublicKey;
+use eyre::{Result, WrapErr, eyre};
+use serde::Deserialize;
+use tracing::info;
+
+use common::utils::decode_pubkey;
+
+use crate::policy::DelegationPolicy;
+
+/// Validators that delegate, keyed by BLS public key
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ValidatorLists {
+	/// Only these validators delegate, every validator of the signer if empty
+	#[serde(default)]
+	pub allowlist: Vec<String>,
+
+	/// Validators that never delegate, even if allowlisted
+	#[serde(default)]
+	pub denylist: Vec<String>,
+
+	/// Name of the gateway a validator always delegates to, instead of the one picked by the delegation policy
+	#[serde(default)]
+	pub gateway_overrides: HashMap<String, String>,
+}
+
+/// Which of the signer's validators delegate
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ValidatorSelectionConfig {
+	#[serde(flatten)]
+	pub lists: ValidatorLists,
+
+	/// TOML file with the `allowlist`, `denylist` and `gateway_overrides` to use instead of the ones above
+	/// The file is read again whenever it changes, so validators can be enabled without a restart
+	#[serde(default)]
+	pub file: Option<String>,
+}
+
+/// Decoded validator lists
+#[derive(Debug, Clone, Default)]
+pub struct ValidatorSelection {
+	allowlist: HashSet<BlsPublicKey>,
+	denylist: HashSet<BlsPublicKey>,
+	gateway_overrides: HashMap<BlsPublicKey, String>,
+}
+
+impl ValidatorSelection {
+	/// Decode the lists, every overriding gateway must be one of the policy's gateways
+	pub fn new(lists: &ValidatorLists, policy: &DelegationPolicy) -> Result<Self> {
+		let decode =
+			|key: &String| decode_pubkey(key).map_err(|e| eyre!("Invalid validator public key {}: {}", key, e));
+
+		let mut gateway_overrides = HashMap::with_capacity(lists.gateway_overrides.len());
+		for (key, gateway) in &lists.gateway_overrides {
+			if policy.gateway(gateway).is_none() {
+				return Err(eyre!("Gateway override of validator {} names unknown gateway {}", key, gateway));
+			}
+			gateway_overrides.insert(decode(key)?, gateway.clone());
+		}
+
+		Ok(Self {
+			allowlist: lists.allowlist.iter().map(decode).collect::<Result<_>>()?,
+			denylist: lists.denylist.iter().map(decode).collect::<Result<_>>()?,
+			gateway_overrides,
+		})
+	}
+
+	/// Whether the validator delegates its slots
+	pub fn is_enabled(&self, public_key: &BlsPublicKey) -> bool {
+		(self.allowlist.is_empty() || self.allowlist.contains(public_key)) && !self.denylist.contains(public_key)
+	}
+
+	/// Name of the gateway the validator always delegates to, if overridden
+	pub fn gateway_override(&self, public_key: &BlsPublicKey) -> Option<&str> {
+		self.gateway_overrides.get(public_key).map(String::as_str)
+	}
+}
+
+/// The current validator selection, reloaded from the configured file when it changes
+pub struct ValidatorSelector {
+	file: Option<PathBuf>,
+	policy: DelegationPolicy,
+	/// Modification time of the file when it was last read, with the selection read from it
+	current: RwLock<(Option<SystemTime>, Arc<ValidatorSelection>)>,
+}
+
+impl ValidatorSelector {
+	/// Fails if the configured lists or file are invalid
+	pub fn new(config: &ValidatorSelectionConfig, policy: DelegationPolicy) -> Result<Self> {
+		let selector = Self {
+			file: config.file.as_ref().map(PathBuf::from),
+			current: RwLock::new((None, Arc::new(ValidatorSelection::new(&config.lists, &policy)?))),
+			policy,
+		};
+		selector.reload_if_changed()?;
+		Ok(selector)
+	}
+
+	pub fn current(&self) -> Arc<ValidatorSelection> {
+		Arc::clone(&self.current.read().expect("validator selection lock poisoned").1)
+	}
+
+	/// Read the file again if it was modified since it was last read, returns whether the selection changed
+	/// An invalid file is an error and leaves the previous selection in place
+	pub fn reload_if_changed(&self) -> Result<bool> {
+		let Some(file) = &self.file else {
+			return Ok(false);
+		};
+
+		let modified = std::fs::metadata(file)
+			.and_then(|metadata| metadata.modified())
+			.wrap_err_with(|| format!("Failed to read the modification time of {}", file.display()))?;
+		if self.current.read().expect("validator selection lock poisoned").0 == Some(modified) {
+			return Ok(false);
+		}
+
+		let content = std::fs::read_to_string(file).wrap_err_with(|| format!("Failed to read {}", file.display()))?;
+		let lists: ValidatorLists =
+			toml::from_str(&content).wrap_err_with(|| format!("Failed to parse {}", file.display()))?;
+		let selection = ValidatorSelection::new(&lists, &self.policy)?;
+
+		info!(
+			"Loaded validator selection from {}: {} allowlisted, {} denylisted, {} gateway override(s)",
+			file.display(),
+			selection.allowlist.len(),
+			selection.denylist.len(),
+			selection.gateway_overrides.len()
+		);
+		*self.current.write().expect("validator selection lock poisoned") = (Some(modified), Arc::new(selection));
+		Ok(true)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::policy::{DelegationPolicyConfig, Gateway};
+	use alloy::primitives::Address;
+
+	fn policy() -> DelegationPolicy {
+		let gateway = Gateway {
+			name: "a".to_string(),
+			public_key: BlsPublicKey::repeat_byte(0xaa),
+			address: Address::repeat_byte(0xaa),
+			weight: 1,
+			bid_gwei: 0,
+			terms: Default::default(),
+		};
+		DelegationPolicy::new(DelegationPolicyConfig::default(), vec![gateway]).unwrap()
+	}
+
+	fn key(byte: u8) -> BlsPublicKey {
+		BlsPublicKey::repeat_byte(byte)
+	}
+
+	#[test]
+	fn test_selection_lists_and_reload() -> Result<()> {
+		let dir = tempfile::TempDir::new()?;
+		let path = dir.path().join("validators.toml");
+		std::fs::write(&path, format!("denylist = [\"{}\"]\n", key(2)))?;
+
+		// The file replaces the lists of the config
+		let config = ValidatorSelectionConfig {
+			lists: ValidatorLists { allowlist: vec![key(3).to_string()], ..Default::default() },
+			file: Some(path.to_string_lossy().to_string()),
+		};
+		let selector = ValidatorSelector::new(&config, policy())?;
+		let selection = selector.current();
+		assert!(selection.is_enabled(&key(1)));
+		assert!(!selection.is_enabled(&key(2)));
+		assert!(!selector.reload_if_changed()?);
+
+		let content = format!(
+			"allowlist = [\"{}\", \"{}\"]\ndenylist = [\"{}\"]\n\n[gateway_overrides]\n\"{}\" = \"a\"\n",
+			key(1),
+			key(2),
+			key(2),
+			key(1)
+		);
+		std::fs::write(&path, content)?;
+		let file = std::fs::File::options().write(true).open(&path)?;
+		file.set_modified(SystemTime::now() + std::time::Duration::from_secs(1))?;
+		assert!(selector.reload_if_changed()?);
+
+		let selection = selector.current();
+		assert!(selection.is_enabled(&key(1)));
+		assert!(!selection.is_enabled(&key(2)));
+		assert!(!selection.is_enabled(&key(3)));
+		assert_eq!(selection.gateway_override(&key(1)), Some("a"));
+		assert_eq!(selection.gateway_override(&key(3)), None);
+
+		// Unknown gateways are rejected and the previous selection stays
+		std::fs::write(&path, format!("[gateway_overrides]\n\"{}\" = \"b\"\n", key(1)))?;
+		file.set_modified(SystemTime::now() + std::time::Duration::from_secs(2))?;
+		assert!(selector.reload_if_changed().is_err());
+		assert_eq!(selector.current().gateway_override(&key(1)), Some("a"));
+
+		Ok(())
+	}
+}
@@ -0,0 +1,209 @@
+use alloy::consensus::{Transaction, TxEnvelope};
+use alloy::eips::eip2718::Encodable2718;
+use alloy::hex;
+use alloy::primitives::{Address, B256, Bloom, Bytes, U256};
+use alloy::rpc::types::beacon::relay::{BidTrace, ElectraSubmitBlockRequest, SubmitBlockRequest};
+use alloy::rpc::types::beacon::requests::ExecutionRequestsV4;
+use alloy::rpc::types::beacon::{BlsPublicKey as AlloyBlsPublicKey, BlsSignature as AlloyBlsSignature};
+use alloy::rpc::types::engine::{BlobsBundleV1, ExecutionPayloadV1, ExecutionPayloadV2, ExecutionPayloadV3};
+use cb_common::constants::APPLICATION_BUILDER_DOMAIN;
+use cb_common::types::BlsSecretKey;
+use commit_boost::prelude::Chain;
+use eyre::{Result, WrapErr, eyre};
+use urc::utils::{genesis_domain, sha256_pair};
+
+use crate::local::read_env;
+
+/// Gas limit of the blocks built by `build_block`
+pub const BLOCK_GAS_LIMIT: u64 = 36_000_000;
+
+/// Signs the bid traces of blocks submitted to a relay, with a builder BLS key held in memory
+pub struct BuilderSigner {
+	chain: Chain,
+	secret_key: BlsSecretKey,
+	public_key: AlloyBlsPublicKey,
+}
+
+impl BuilderSigner {
+	pub fn new(secret_key: BlsSecretKey, chain: Chain) -> Self {
+		let public_key = AlloyBlsPublicKey::new(secret_key.public_key().serialize());
+		Self { chain, secret_key, public_key }
+	}
+
+	/// Load the hex-encoded builder secret key from the environment variable `env`
+	pub fn from_env(env: &str, chain: Chain) -> Result<Self> {
+		let bytes = hex::decode(read_env(env)?.trim()).wrap_err_with(|| format!("Invalid BLS key in {}", env))?;
+		let secret_key = BlsSecretKey::deserialize(&bytes).map_err(|e| eyre!("Invalid BLS key in {}: {:?}", env, e))?;
+		Ok(Self::new(secret_key, chain))
+	}
+
+	/// Builder public key, the `builder_pubkey` of signed bid traces
+	pub fn public_key(&self) -> AlloyBlsPublicKey {
+		self.public_key
+	}
+
+	/// Sign a bid trace under the builder domain of the chain's genesis fork
+	pub fn sign_bid_trace(&self, bid_trace: &BidTrace) -> AlloyBlsSignature {
+		let signature = self.secret_key.sign(builder_signing_root(self.chain, bid_trace));
+		AlloyBlsSignature::new(signature.serialize())
+	}
+}
+
+/// Root a builder signs for a bid trace: its hash tree root under the builder domain
+pub fn builder_signing_root(chain: Chain, bid_trace: &BidTrace) -> B256 {
+	B256::from(sha256_pair(&bid_trace_root(bid_trace), &genesis_domain(chain, APPLICATION_BUILDER_DOMAIN)))
+}
+
+/// SSZ hash tree root of a bid trace, every field fits in one or two chunks
+fn bid_trace_root(bid_trace: &BidTrace) -> [u8; 32] {
+	let u64_leaf = |value: u64| {
+		let mut leaf = [0u8; 32];
+		leaf[..8].copy_from_slice(&value.to_le_bytes());
+		leaf
+	};
+	// A 48 byte public key is a vector of two chunks, the second zero padded
+	let pubkey_leaf = |public_key: &AlloyBlsPublicKey| {
+		let mut second = [0u8; 32];
+		second[..16].copy_from_slice(&public_key[32..]);
+		sha256_pair(&public_key[..32], &second)
+	};
+	let mut fee_recipient_leaf = [0u8; 32];
+	fee_recipient_leaf[..20].copy_from_slice(bid_trace.proposer_fee_recipient.as_slice());
+
+	let mut layer = vec![
+		u64_leaf(bid_trace.slot),
+		bid_trace.parent_hash.0,
+		bid_trace.block_hash.0,
+		pubkey_leaf(&bid_trace.builder_pubkey),
+		pubkey_leaf(&bid_trace.proposer_pubkey),
+		fee_recipient_leaf,
+		u64_leaf(bid_trace.gas_limit),
+		u64_leaf(bid_trace.gas_used),
+		bid_trace.value.to_le_bytes::<32>(),
+	];
+	// Nine fields are merkleized as sixteen leaves
+	layer.resize(16, [0u8; 32]);
+	while layer.len() > 1 {
+		layer = layer.chunks(2).map(|pair| sha256_pair(&pair[0], &pair[1])).collect();
+	}
+	layer[0]
+}
+
+/// A signed Electra bid for `slot` whose payload holds `transactions` in order
+/// Only the transactions and the bid trace are meaningful, the header fields the relay does not check are
+/// placeholders
+pub fn build_block(
+	slot: u64,
+	proposer: AlloyBlsPublicKey,
+	transactions: &[TxEnvelope],
+	builder: &BuilderSigner,
+) -> SubmitBlockRequest {
+	let gas_used = transactions.iter().map(|tx| tx.gas_limit()).sum();
+	let block_hash = B256::random();
+	let payload_inner = ExecutionPayloadV1 {
+		parent_hash: B256::random(),
+		fee_recipient: Address::ZERO,
+		state_root: B256::ZERO,
+		receipts_root: B256::ZERO,
+		logs_bloom: Bloom::ZERO,
+		prev_randao: B256::ZERO,
+		block_number: slot,
+		gas_limit: BLOCK_GAS_LIMIT,
+		gas_used,
+		timestamp: 0,
+		extra_data: Bytes::new(),
+		base_fee_per_gas: U256::from(1),
+		block_hash,
+		transactions: transactions.iter().map(|tx| Bytes::from(tx.encoded_2718())).collect(),
+	};
+	let execution_payload = ExecutionPayloadV3 {
+		payload_inner: ExecutionPayloadV2 { payload_inner, withdrawals: Vec::new() },
+		blob_gas_used: 0,
+		excess_blob_gas: 0,
+	};
+
+	let bid_trace = BidTrace {
+		slot,
+		parent_hash: execution_payload.payload_inner.payload_inner.parent_hash,
+		block_hash,
+		builder_pubkey: builder.public_key(),
+		proposer_pubkey: proposer,
+		proposer_fee_recipient: Address::ZERO,
+		gas_limit: BLOCK_GAS_LIMIT,
+		gas_used,
+		value: U256::ZERO,
+	};
+	let signature = builder.sign_bid_trace(&bid_trace);
+
+	SubmitBlockRequest::Electra(ElectraSubmitBlockRequest {
+		message: bid_trace,
+		execution_payload,
+		blobs_bundle: BlobsBundleV1 { commitments: Vec::new(), proofs: Vec::new(), blobs: Vec::new() },
+		execution_requests: ExecutionRequestsV4::default(),
+		signature,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::consensus::{Signed, TxLegacy};
+	use alloy::primitives::Signature;
+
+	#[test]
+	fn test_bid_trace_signature_verifies_under_builder_domain() {
+		let secret_key = BlsSecretKey::deserialize(&[7u8; 32]).unwrap();
+		let signer = BuilderSigner::new(BlsSecretKey::deserialize(&[7u8; 32]).unwrap(), Chain::Mainnet);
+		let mut bid_trace = BidTrace {
+			slot: 10,
+			parent_hash: B256::repeat_byte(1),
+			block_hash: B256::repeat_byte(2),
+			builder_pubkey: signer.public_key(),
+			proposer_pubkey: AlloyBlsPublicKey::repeat_byte(3),
+			proposer_fee_recipient: Address::repeat_byte(4),
+			gas_limit: 30_000_000,
+			gas_used: 21_000,
+			value: U256::from(1u64),
+		};
+
+		let signature = signer.sign_bid_trace(&bid_trace);
+		let root = builder_signing_root(Chain::Mainnet, &bid_trace);
+		let expected = secret_key.sign(root);
+		assert_eq!(signature.as_slice(), expected.serialize().as_slice());
+		assert!(expected.verify(&secret_key.public_key(), root));
+
+		// Every field is part of the root
+		bid_trace.value = U256::from(2u64);
+		assert_ne!(builder_signing_root(Chain::Mainnet, &bid_trace), root);
+	}
+
+	#[test]
+	fn test_build_block_holds_the_transactions_in_order() {
+		let signer = BuilderSigner::new(BlsSecretKey::deserialize(&[7u8; 32]).unwrap(), Chain::Mainnet);
+		let transactions = (0..3u64)
+			.map(|nonce| {
+				let tx = TxLegacy { nonce, gas_limit: 21_000 + nonce, ..Default::default() };
+				TxEnvelope::Legacy(Signed::new_unchecked(
+					tx,
+					Signature::test_signature(),
+					B256::repeat_byte(nonce as u8),
+				))
+			})
+			.collect::<Vec<_>>();
+		let proposer = AlloyBlsPublicKey::repeat_byte(3);
+
+		let SubmitBlockRequest::Electra(block) = build_block(12, proposer, &transactions, &signer) else {
+			panic!("expected an Electra block");
+		};
+		let payload = &block.execution_payload.payload_inner.payload_inner;
+		let encoded = transactions.iter().map(|tx| Bytes::from(tx.encoded_2718())).collect::<Vec<_>>();
+		assert_eq!(payload.transactions, encoded);
+		assert_eq!(payload.gas_used, 63_003);
+		assert_eq!(block.message.gas_used, payload.gas_used);
+		assert_eq!(block.message.block_hash, payload.block_hash);
+		assert_eq!(block.message.slot, 12);
+		assert_eq!(block.message.proposer_pubkey, proposer);
+		assert_eq!(block.message.builder_pubkey, signer.public_key());
+		assert_eq!(block.signature, signer.sign_bid_trace(&block.message));
+	}
+}
@@ -0,0 +1,126 @@
+use alloy::primitives::{Address, B256, Signature};
+use alloy::signers::Signer;
+use alloy::signers::aws::AwsSigner;
+use async_trait::async_trait;
+use aws_config::{BehaviorVersion, Region};
+use commit_boost::prelude::Chain;
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::local::commit_signing_root;
+use crate::signer::CommitterSigner;
+
+/// AWS KMS key of the committer, credentials are taken from the environment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KmsSignerConfig {
+	/// Key ID or ARN of an ECC_SECG_P256K1 signing key
+	pub key_id: String,
+	/// AWS region of the key, the environment's region if not set
+	#[serde(default)]
+	pub region: Option<String>,
+}
+
+/// Committer signer whose ECDSA key never leaves AWS KMS
+pub struct KmsSigner {
+	chain: Chain,
+	signer: AwsSigner,
+}
+
+impl KmsSigner {
+	/// Connect to KMS and fetch the public key of the configured key
+	pub async fn connect(config: &KmsSignerConfig, chain: Chain) -> Result<Self> {
+		let mut loader = aws_config::defaults(BehaviorVersion::latest());
+		if let Some(region) = &config.region {
+			loader = loader.region(Region::new(region.clone()));
+		}
+		let client = aws_sdk_kms::Client::new(&loader.load().await);
+		let signer = AwsSigner::new(client, config.key_id.clone(), None)
+			.await
+			.wrap_err_with(|| format!("Failed to load KMS key {}", config.key_id))?;
+		info!("Committer key {} is held in KMS, address {}", config.key_id, signer.address());
+		Ok(Self { chain, signer })
+	}
+}
+
+#[async_trait]
+impl CommitterSigner for KmsSigner {
+	fn address(&self) -> Address {
+		self.signer.address()
+	}
+
+	async fn sign(&self, message_hash: B256, module_signing_id: &B256, nonce: u64) -> Result<Signature> {
+		sign_commit_root(&self.signer, self.chain, message_hash, module_signing_id, nonce)
+			.await
+			.wrap_err("KMS signing request failed")
+	}
+}
+
+/// Sign the Commit-Boost signing root of a message hash with a remote `signer`
+async fn sign_commit_root<S: Signer>(
+	signer: &S,
+	chain: Chain,
+	message_hash: B256,
+	module_signing_id: &B256,
+	nonce: u64,
+) -> Result<Signature> {
+	let signing_root = commit_signing_root(chain, message_hash, module_signing_id, nonce);
+	Ok(signer.sign_hash(&signing_root).await?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::primitives::Bytes;
+	use alloy::signers::local::PrivateKeySigner;
+	use commitments::types::{Commitment, SignedCommitment};
+	use urc::utils::get_commitment_signing_root;
+
+	use crate::signer::verify_ecdsa_commitment;
+
+	fn commitment() -> Commitment {
+		Commitment {
+			commitment_type: 1,
+			payload: Bytes::from_static(&[1, 2, 3]),
+			request_hash: B256::random(),
+			slasher: Address::random(),
+		}
+	}
+
+	#[test]
+	fn test_config_region_is_optional() -> Result<()> {
+		let config: KmsSignerConfig = serde_json::from_str(r#"{"key_id": "alias/committer"}"#)?;
+		assert_eq!(config.key_id, "alias/committer");
+		assert!(config.region.is_none());
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn test_sign_commit_root_verifies_as_a_commitment() -> Result<()> {
+		// Any remote signer signs the same root as the other backends
+		let chain = Chain::Holesky;
+		let signer = PrivateKeySigner::random();
+		let signing_id = B256::random();
+		let commitment = commitment();
+		let signature =
+			sign_commit_root(&signer, chain, get_commitment_signing_root(&commitment), &signing_id, 0).await?;
+		let signed_commitment = SignedCommitment { commitment, nonce: 0, signing_id, signature };
+		verify_ecdsa_commitment(chain, &signed_commitment, &signer.address())
+	}
+
+	#[tokio::test]
+	#[ignore = "signs with a live KMS key, set FABRIC_TEST_KMS_KEY_ID and the AWS credentials"]
+	async fn test_kms_signature_verifies_as_a_commitment() -> Result<()> {
+		let config = KmsSignerConfig {
+			key_id: std::env::var("FABRIC_TEST_KMS_KEY_ID")?,
+			region: std::env::var("AWS_REGION").ok(),
+		};
+		let chain = Chain::Holesky;
+		let kms = KmsSigner::connect(&config, chain).await?;
+		let signing_id = B256::random();
+		let commitment = commitment();
+		let signature = kms.sign(get_commitment_signing_root(&commitment), &signing_id, 0).await?;
+		let signed_commitment = SignedCommitment { commitment, nonce: 0, signing_id, signature };
+		verify_ecdsa_commitment(chain, &signed_commitment, &kms.address())
+	}
+}
@@ -1 +1,5 @@
+pub mod builder;
+pub mod kms;
+pub mod local;
+pub mod nonce;
 pub mod signer;
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use alloy::hex;
+use alloy::primitives::{Address, B256, Signature};
+use alloy::rpc::types::beacon::BlsPublicKey as AlloyBlsPublicKey;
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use cb_common::types::{BlsSecretKey, BlsSignature};
+use commit_boost::prelude::Chain;
+use eth2_keystore::Keystore;
+use eyre::{Context, Result, eyre};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+pub use urc::utils::commit_signing_root;
+
+/// An encrypted keystore and the file holding its password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreFile {
+	pub path: PathBuf,
+	pub password_path: PathBuf,
+}
+
+/// Keys of the local signer, which signs in-process instead of calling the Commit-Boost signer service
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LocalSignerConfig {
+	/// EIP-2335 keystores of the BLS keys
+	#[serde(default)]
+	pub bls_keystores: Vec<KeystoreFile>,
+	/// Environment variables holding hex-encoded BLS secret keys
+	#[serde(default)]
+	pub bls_key_envs: Vec<String>,
+	/// Encrypted JSON (Web3 Secret Storage) keystores of the ECDSA committer keys
+	#[serde(default)]
+	pub ecdsa_keystores: Vec<KeystoreFile>,
+	/// Environment variables holding hex-encoded ECDSA private keys
+	#[serde(default)]
+	pub ecdsa_key_envs: Vec<String>,
+}
+
+/// Signs with keys held in memory, producing the same signatures as the Commit-Boost signer service
+///
+/// There are no proxy keys: consensus and proxy BLS requests are both signed with the BLS key of the
+/// requested public key, and ECDSA requests with the key of the requested address.
+pub struct LocalSigner {
+	chain: Chain,
+	bls_keys: HashMap<AlloyBlsPublicKey, BlsSecretKey>,
+	ecdsa_keys: HashMap<Address, PrivateKeySigner>,
+}
+
+impl LocalSigner {
+	/// A signer without keys
+	pub fn new(chain: Chain) -> Self {
+		Self { chain, bls_keys: HashMap::new(), ecdsa_keys: HashMap::new() }
+	}
+
+	/// Load the configured keystores and environment keys
+	pub fn load(config: &LocalSignerConfig, chain: Chain) -> Result<Self> {
+		let mut signer = Self::new(chain);
+		for keystore in &config.bls_keystores {
+			let password = read_password(&keystore.password_path)?;
+			let keypair = Keystore::from_json_file(&keystore.path)
+				.map_err(|e| eyre!("Failed to read BLS keystore {}: {:?}", keystore.path.display(), e))?
+				.decrypt_keypair(password.as_bytes())
+				.map_err(|e| eyre!("Failed to decrypt BLS keystore {}: {:?}", keystore.path.display(), e))?;
+			signer.add_bls_key(keypair.sk);
+		}
+		for env in &config.bls_key_envs {
+			let bytes = hex::decode(read_env(env)?.trim()).wrap_err_with(|| format!("Invalid BLS key in {}", env))?;
+			let secret_key =
+				BlsSecretKey::deserialize(&bytes).map_err(|e| eyre!("Invalid BLS key in {}: {:?}", env, e))?;
+			signer.add_bls_key(secret_key);
+		}
+		for keystore in &config.ecdsa_keystores {
+			let password = read_password(&keystore.password_path)?;
+			let key = PrivateKeySigner::decrypt_keystore(&keystore.path, password)
+				.wrap_err_with(|| format!("Failed to decrypt ECDSA keystore {}", keystore.path.display()))?;
+			signer.add_ecdsa_key(key);
+		}
+		for env in &config.ecdsa_key_envs {
+			let key = read_env(env)?
+				.trim()
+				.parse::<PrivateKeySigner>()
+				.wrap_err_with(|| format!("Invalid ECDSA key in {}", env))?;
+			signer.add_ecdsa_key(key);
+		}
+		info!("Loaded {} BLS and {} ECDSA keys into the local signer", signer.bls_keys.len(), signer.ecdsa_keys.len());
+		Ok(signer)
+	}
+
+	/// Add a BLS key, returns its public key
+	pub fn add_bls_key(&mut self, secret_key: BlsSecretKey) -> AlloyBlsPublicKey {
+		let public_key = AlloyBlsPublicKey::new(secret_key.public_key().serialize());
+		self.bls_keys.insert(public_key, secret_key);
+		public_key
+	}
+
+	/// Add an ECDSA key, returns its address
+	pub fn add_ecdsa_key(&mut self, signer: PrivateKeySigner) -> Address {
+		let address = signer.address();
+		self.ecdsa_keys.insert(address, signer);
+		address
+	}
+
+	/// Public keys of the BLS keys
+	pub fn bls_public_keys(&self) -> Vec<AlloyBlsPublicKey> {
+		self.bls_keys.keys().copied().collect()
+	}
+
+	/// Sign a message hash with the BLS key of `public_key`
+	pub fn sign_bls(
+		&self,
+		public_key: &AlloyBlsPublicKey,
+		message_hash: B256,
+		module_signing_id: &B256,
+		nonce: u64,
+	) -> Result<BlsSignature> {
+		let secret_key = self.bls_keys.get(public_key).ok_or_else(|| eyre!("No local BLS key for {}", public_key))?;
+		Ok(secret_key.sign(commit_signing_root(self.chain, message_hash, module_signing_id, nonce)))
+	}
+
+	/// Sign a message hash with the ECDSA key of `address`
+	pub fn sign_ecdsa(
+		&self,
+		address: &Address,
+		message_hash: B256,
+		module_signing_id: &B256,
+		nonce: u64,
+	) -> Result<Signature> {
+		let signer = self.ecdsa_keys.get(address).ok_or_else(|| eyre!("No local ECDSA key for {}", address))?;
+		let signing_root = commit_signing_root(self.chain, message_hash, module_signing_id, nonce);
+		Ok(signer.sign_hash_sync(&signing_root)?)
+	}
+}
+
+pub(crate) fn read_env(name: &str) -> Result<String> {
+	std::env::var(name).wrap_err_with(|| format!("Environment variable {} not set", name))
+}
+
+/// Keystore passwords are read without the trailing newline of the file
+fn read_password(path: &Path) -> Result<String> {
+	let password =
+		std::fs::read_to_string(path).wrap_err_with(|| format!("Failed to read password file {}", path.display()))?;
+	Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::signer::{verify_bls, verify_ecdsa};
+	use alloy::rpc::types::beacon::BlsSignature as AlloyBlsSignature;
+	use commit_boost::prelude::{
+		verify_proposer_commitment_signature_bls_for_message, verify_proposer_commitment_signature_ecdsa_for_message,
+	};
+
+	/// `commit_signing_root` is computed here rather than by Commit-Boost, so signatures over it must verify with
+	/// Commit-Boost's own verification on every chain, and only for the signed message, signing id and nonce
+	#[test]
+	fn test_commit_signing_root_matches_commit_boost() -> Result<()> {
+		let secret_key = BlsSecretKey::random();
+		let public_key = secret_key.public_key();
+		let ecdsa_key = PrivateKeySigner::random();
+		let message_hash = B256::random();
+		let module_signing_id = B256::random();
+
+		for chain in [Chain::Mainnet, Chain::Holesky, Chain::Sepolia, Chain::Hoodi] {
+			let signing_root = commit_signing_root(chain, message_hash, &module_signing_id, 3);
+
+			let signature = secret_key.sign(signing_root);
+			assert!(verify_proposer_commitment_signature_bls_for_message(
+				chain,
+				&public_key,
+				&message_hash,
+				&signature,
+				&module_signing_id,
+				3,
+			));
+			assert!(!verify_proposer_commitment_signature_bls_for_message(
+				chain,
+				&public_key,
+				&message_hash,
+				&signature,
+				&B256::random(),
+				3,
+			));
+
+			let signature = ecdsa_key.sign_hash_sync(&signing_root)?;
+			verify_proposer_commitment_signature_ecdsa_for_message(
+				chain,
+				&ecdsa_key.address(),
+				&message_hash,
+				&signature.into(),
+				&module_signing_id,
+				3,
+			)?;
+			assert!(
+				verify_proposer_commitment_signature_ecdsa_for_message(
+					chain,
+					&ecdsa_key.address(),
+					&message_hash,
+					&signature.into(),
+					&module_signing_id,
+					4
+				)
+				.is_err()
+			);
+		}
+
+		// Roots differ per chain, so a signature does not carry over
+		assert_ne!(
+			commit_signing_root(Chain::Mainnet, message_hash, &module_signing_id, 3),
+			commit_signing_root(Chain::Holesky, message_hash, &module_signing_id, 3)
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn test_local_signatures_verify_like_commit_boost() -> Result<()> {
+		let chain = Chain::Holesky;
+		let mut signer = LocalSigner::new(chain);
+		let bls_public_key = signer.add_bls_key(BlsSecretKey::random());
+		let address = signer.add_ecdsa_key(PrivateKeySigner::random());
+		let message_hash = B256::random();
+		let module_signing_id = B256::random();
+
+		let bls_signature = signer.sign_bls(&bls_public_key, message_hash, &module_signing_id, 7)?;
+		let bls_signature = AlloyBlsSignature::new(bls_signature.serialize());
+		verify_bls(chain, &bls_public_key, &message_hash, &bls_signature, &module_signing_id, 7)?;
+		assert!(verify_bls(chain, &bls_public_key, &message_hash, &bls_signature, &module_signing_id, 8).is_err());
+
+		let ecdsa_signature = signer.sign_ecdsa(&address, message_hash, &module_signing_id, 7)?;
+		verify_ecdsa(chain, &address, &message_hash, &ecdsa_signature.into(), &module_signing_id, 7)?;
+
+		assert!(signer.sign_ecdsa(&Address::random(), message_hash, &module_signing_id, 7).is_err());
+		Ok(())
+	}
+}
@@ -0,0 +1,109 @@
+use std::sync::Mutex;
+
+use common::storage::DatabaseContext;
+use eyre::{Result, eyre};
+
+/// 1-byte table tag, shared RocksDB instance with the other crates' tables
+const KIND_SIGNING_NONCE: u8 = b'R';
+
+/// Message types signed with a nonce, each has its own nonce sequence per signing key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonceKind {
+	Delegation = 1,
+	Commitment = 2,
+	Constraints = 3,
+	/// Receiver authentication on GET /constraints/{slot}
+	ReceiverAuth = 4,
+	/// Delegation offer published on POST /delegation_offers
+	DelegationOffer = 5,
+}
+
+/// Key for the last nonce allocated to a signing key and message type.
+/// Layout: [ 'R' ][ kind ][ signing key ]
+pub fn signing_nonce_key(signing_key: &[u8], kind: NonceKind) -> Vec<u8> {
+	let mut key = Vec::with_capacity(2 + signing_key.len());
+	key.push(KIND_SIGNING_NONCE);
+	key.push(kind as u8);
+	key.extend_from_slice(signing_key);
+	key
+}
+
+/// Allocates the nonces signed into delegations, commitments and constraints
+///
+/// Nonces increase monotonically per signing key and message type, starting at zero. Each allocation is
+/// persisted before it is returned, so a restart never reuses a nonce that may have been signed.
+pub struct NonceManager {
+	db: DatabaseContext,
+	/// Serializes the read-increment-write of allocations
+	lock: Mutex<()>,
+}
+
+impl NonceManager {
+	pub fn new(db: DatabaseContext) -> Self {
+		Self { db, lock: Mutex::new(()) }
+	}
+
+	/// Allocate the next nonce of a signing key and message type
+	pub fn next_nonce(&self, signing_key: &[u8], kind: NonceKind) -> Result<u64> {
+		let _guard = self.lock.lock().expect("nonce lock poisoned");
+		let key = signing_nonce_key(signing_key, kind);
+		let nonce = match self.last_nonce_at(&key)? {
+			Some(last) => last.checked_add(1).ok_or_else(|| eyre!("Nonces of {:?} are exhausted", kind))?,
+			None => 0,
+		};
+		self.db.put_raw(&key, &nonce.to_be_bytes())?;
+		Ok(nonce)
+	}
+
+	/// Last nonce allocated to a signing key and message type, if any
+	pub fn last_nonce(&self, signing_key: &[u8], kind: NonceKind) -> Result<Option<u64>> {
+		self.last_nonce_at(&signing_nonce_key(signing_key, kind))
+	}
+
+	fn last_nonce_at(&self, key: &[u8]) -> Result<Option<u64>> {
+		self.db
+			.get_raw(key)?
+			.map(|bytes| {
+				let bytes: [u8; 8] = bytes.try_into().map_err(|_| eyre!("Corrupt nonce entry"))?;
+				Ok(u64::from_be_bytes(bytes))
+			})
+			.transpose()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rocksdb::{DB, Options};
+	use std::sync::Arc;
+	use tempfile::TempDir;
+
+	fn open_db(path: &std::path::Path) -> Result<DatabaseContext> {
+		let mut opts = Options::default();
+		opts.create_if_missing(true);
+		Ok(DatabaseContext::new(Arc::new(DB::open(&opts, path)?)))
+	}
+
+	#[test]
+	fn test_nonces_are_monotonic_and_persisted() -> Result<()> {
+		let tmp_dir = TempDir::new()?;
+		let proposer = [1u8; 48];
+		let gateway = [2u8; 48];
+		{
+			let nonces = NonceManager::new(open_db(tmp_dir.path())?);
+			assert_eq!(nonces.last_nonce(&proposer, NonceKind::Delegation)?, None);
+			assert_eq!(nonces.next_nonce(&proposer, NonceKind::Delegation)?, 0);
+			assert_eq!(nonces.next_nonce(&proposer, NonceKind::Delegation)?, 1);
+
+			// Sequences are independent per signing key and message type
+			assert_eq!(nonces.next_nonce(&proposer, NonceKind::Constraints)?, 0);
+			assert_eq!(nonces.next_nonce(&gateway, NonceKind::Delegation)?, 0);
+		}
+
+		// Reopening the database continues where the sequence left off
+		let nonces = NonceManager::new(open_db(tmp_dir.path())?);
+		assert_eq!(nonces.last_nonce(&proposer, NonceKind::Delegation)?, Some(1));
+		assert_eq!(nonces.next_nonce(&proposer, NonceKind::Delegation)?, 2);
+		Ok(())
+	}
+}
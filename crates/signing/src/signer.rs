@@ -1,27 +1,115 @@
-use alloy::primitives::{Address, B256};
+use std::sync::Arc;
+
+use alloy::primitives::{Address, B256, Signature};
 use alloy::rpc::types::beacon::{BlsPublicKey as AlloyBlsPublicKey, BlsSignature as AlloyBlsSignature};
+use async_trait::async_trait;
+use blst::{BLST_ERROR, blst_scalar};
 use commit_boost::prelude::{
 	BlsPublicKey, BlsSignature, Chain, EcdsaSignature,
 	commit::{
 		client::SignerClient,
 		request::{SignConsensusRequest, SignProxyRequest},
-		response::{BlsSignResponse, EcdsaSignResponse},
 	},
 	verify_proposer_commitment_signature_bls_for_message, verify_proposer_commitment_signature_ecdsa_for_message,
 };
+use commitments::types::SignedCommitment;
 use eyre::{Context, Result, eyre};
 use tracing::{debug, error, info};
+use urc::utils::get_commitment_signing_root;
+
+use crate::local::{LocalSigner, LocalSignerConfig, commit_signing_root};
+
+/// Signs with the Commit-Boost signer service, or in-process with local keys when configured
+/// Committer ECDSA signatures go to the `CommitterSigner` instead when one is set
+#[derive(Clone)]
+pub struct SigningClient {
+	backend: SignerBackend,
+	committer: Option<Arc<dyn CommitterSigner>>,
+}
+
+#[derive(Clone)]
+pub enum SignerBackend {
+	CommitBoost(SignerClient),
+	Local(Arc<LocalSigner>),
+}
+
+/// Signer of the committer's ECDSA signatures whose key is held outside the host, e.g. in a KMS
+#[async_trait]
+pub trait CommitterSigner: Send + Sync {
+	/// Committer address of the key
+	fn address(&self) -> Address;
+
+	/// Sign the Commit-Boost signing root of a message hash
+	async fn sign(&self, message_hash: B256, module_signing_id: &B256, nonce: u64) -> Result<Signature>;
+}
+
+impl SigningClient {
+	/// The local signer if `local_signer` is configured, the Commit-Boost signer client otherwise
+	pub fn new(signer_client: SignerClient, local_signer: Option<&LocalSignerConfig>, chain: Chain) -> Result<Self> {
+		let backend = match local_signer {
+			Some(config) => SignerBackend::Local(Arc::new(LocalSigner::load(config, chain)?)),
+			None => SignerBackend::CommitBoost(signer_client),
+		};
+		Ok(Self { backend, committer: None })
+	}
+
+	/// Sign in-process with the keys already added to `local_signer`
+	pub fn local(local_signer: LocalSigner) -> Self {
+		Self { backend: SignerBackend::Local(Arc::new(local_signer)), committer: None }
+	}
+
+	/// Sign committer ECDSA messages with `committer` instead of the backend
+	pub fn with_committer(mut self, committer: Arc<dyn CommitterSigner>) -> Self {
+		self.committer = Some(committer);
+		self
+	}
+
+	/// Consensus BLS public keys the client signs for
+	pub async fn consensus_public_keys(&mut self) -> Result<Vec<AlloyBlsPublicKey>> {
+		match &mut self.backend {
+			SignerBackend::CommitBoost(signer_client) => {
+				let response = signer_client.get_pubkeys().await.context("Failed to get public keys from signer")?;
+				Ok(response.keys.iter().map(|map| AlloyBlsPublicKey::new(map.consensus.serialize())).collect())
+			}
+			SignerBackend::Local(local_signer) => Ok(local_signer.bls_public_keys()),
+		}
+	}
+}
+
+/// Signature of a message hash and the module signing id and nonce it was signed under
+#[derive(Debug, Clone)]
+pub struct SignResponse<S> {
+	pub signature: S,
+	pub module_signing_id: B256,
+	pub nonce: u64,
+}
 
 /// Calls the proxy_ecdsa signer to sign a hash
 pub async fn call_proxy_ecdsa_signer(
-	signer_client: &mut SignerClient,
+	signer_client: &mut SigningClient,
 	message_hash: B256,
 	committer: Address,
 	module_signing_id: &B256,
 	chain: Chain,
-) -> Result<EcdsaSignResponse> {
+) -> Result<SignResponse<Signature>> {
 	debug!("Calling proxy_ecdsa signer for message hash: {:?}", message_hash);
 
+	// Commitments are signed without a nonce
+	if let Some(committer_signer) = &signer_client.committer {
+		if committer_signer.address() != committer {
+			return Err(eyre!("Committer signer holds the key of {}, not {}", committer_signer.address(), committer));
+		}
+		let signature = committer_signer.sign(message_hash, module_signing_id, 0).await?;
+		return Ok(SignResponse { signature, module_signing_id: *module_signing_id, nonce: 0 });
+	}
+	let signer_client = match &mut signer_client.backend {
+		SignerBackend::CommitBoost(signer_client) => signer_client,
+		SignerBackend::Local(local_signer) => {
+			let signature = local_signer.sign_ecdsa(&committer, message_hash, module_signing_id, 0)?;
+			return Ok(SignResponse { signature, module_signing_id: *module_signing_id, nonce: 0 });
+		}
+	};
+
 	let proxy_request_ecdsa = SignProxyRequest::builder(committer).with_root(message_hash);
 
 	// Make the actual API call to the signer service
@@ -42,23 +130,36 @@ pub async fn call_proxy_ecdsa_signer(
 		Err(err) => error!(%err, "Signature verification failed"),
 	};
 
-	Ok(proxy_response_ecdsa)
+	Ok(SignResponse {
+		signature: proxy_response_ecdsa.signature.into(),
+		module_signing_id: proxy_response_ecdsa.module_signing_id,
+		nonce: proxy_response_ecdsa.nonce,
+	})
 }
 
-/// Calls the proxy_bls signer to sign a hash
+/// Calls the proxy_bls signer to sign a hash under `nonce`
 pub async fn call_proxy_bls_signer(
-	signer_client: &mut SignerClient,
+	signer_client: &mut SigningClient,
 	message_hash: B256,
 	bls_public_key: AlloyBlsPublicKey,
 	module_signing_id: &B256,
 	chain: Chain,
-) -> Result<BlsSignResponse> {
+	nonce: u64,
+) -> Result<SignResponse<BlsSignature>> {
 	debug!("Calling proxy_bls signer for message hash: {:?}", message_hash);
+	let signer_client = match &mut signer_client.backend {
+		SignerBackend::CommitBoost(signer_client) => signer_client,
+		SignerBackend::Local(local_signer) => {
+			let signature = local_signer.sign_bls(&bls_public_key, message_hash, module_signing_id, nonce)?;
+			return Ok(SignResponse { signature, module_signing_id: *module_signing_id, nonce });
+		}
+	};
+
 	// Convert the AlloyBlsPublicKey to a commit-boost BlsPublicKey
 	let bls_public_key = BlsPublicKey::deserialize(&bls_public_key.to_vec())
 		.map_err(|e| eyre!("Failed to deserialize BLS public key: {:?}", e))?;
 
-	let proxy_request_bls = SignProxyRequest::builder(bls_public_key.clone()).with_root(message_hash);
+	let proxy_request_bls = SignProxyRequest::builder(bls_public_key.clone()).with_root(message_hash).with_nonce(nonce);
 
 	// Make the actual API call to the signer service
 	let proxy_response_bls = signer_client
@@ -78,25 +179,38 @@ pub async fn call_proxy_bls_signer(
 		false => error!("Signature verification failed"),
 	};
 
-	Ok(proxy_response_bls)
+	Ok(SignResponse {
+		signature: proxy_response_bls.signature,
+		module_signing_id: proxy_response_bls.module_signing_id,
+		nonce: proxy_response_bls.nonce,
+	})
 }
 
-/// Calls the BLS signer to sign a hash using the consensus key (not proxy)
+/// Calls the BLS signer to sign a hash under `nonce` using the consensus key (not proxy)
 pub async fn call_bls_signer(
-	signer_client: &mut SignerClient,
+	signer_client: &mut SigningClient,
 	message_hash: B256,
 	bls_public_key: AlloyBlsPublicKey,
 	module_signing_id: &B256,
 	chain: Chain,
-) -> Result<BlsSignResponse> {
+	nonce: u64,
+) -> Result<SignResponse<BlsSignature>> {
 	debug!("Calling BLS signer for message hash: {:?} with consensus key", message_hash);
+	let signer_client = match &mut signer_client.backend {
+		SignerBackend::CommitBoost(signer_client) => signer_client,
+		SignerBackend::Local(local_signer) => {
+			let signature = local_signer.sign_bls(&bls_public_key, message_hash, module_signing_id, nonce)?;
+			return Ok(SignResponse { signature, module_signing_id: *module_signing_id, nonce });
+		}
+	};
 
 	// Convert the AlloyBlsPublicKey to a commit-boost BlsPublicKey
 	let bls_public_key = BlsPublicKey::deserialize(&bls_public_key.to_vec())
 		.map_err(|e| eyre!("Failed to deserialize BLS public key: {:?}", e))?;
 
 	// Build the consensus signature request
-	let consensus_request = SignConsensusRequest::builder(bls_public_key.clone()).with_root(message_hash);
+	let consensus_request =
+		SignConsensusRequest::builder(bls_public_key.clone()).with_root(message_hash).with_nonce(nonce);
 
 	// Make the actual API call to the signer service using consensus signature
 	let bls_response = signer_client
@@ -116,7 +230,11 @@ pub async fn call_bls_signer(
 		false => error!("Consensus signature verification failed"),
 	};
 
-	Ok(bls_response)
+	Ok(SignResponse {
+		signature: bls_response.signature,
+		module_signing_id: bls_response.module_signing_id,
+		nonce: bls_response.nonce,
+	})
 }
 
 /// Generates a proxy key using the signer client
@@ -186,6 +304,72 @@ pub fn verify_bls(
 	}
 }
 
+/// A BLS signature over a message hash, as checked by `verify_bls`
+#[derive(Debug, Clone)]
+pub struct BlsMessage {
+	pub public_key: AlloyBlsPublicKey,
+	pub message_hash: B256,
+	pub signature: AlloyBlsSignature,
+	pub module_signing_id: B256,
+	pub nonce: u64,
+}
+
+/// Domain separation tag of Ethereum BLS signatures
+const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// Verifies many BLS signatures with a single multi-pairing, much cheaper than one `verify_bls` each
+/// Errors if any signature is invalid without telling which, callers fall back to `verify_bls` to find out
+pub fn verify_bls_batch(chain: Chain, messages: &[BlsMessage]) -> Result<()> {
+	if messages.is_empty() {
+		return Ok(());
+	}
+
+	let signing_roots = messages
+		.iter()
+		.map(|message| commit_signing_root(chain, message.message_hash, &message.module_signing_id, message.nonce))
+		.collect::<Vec<_>>();
+	let public_keys = messages
+		.iter()
+		.map(|message| {
+			blst::min_pk::PublicKey::from_bytes(message.public_key.as_slice())
+				.map_err(|e| eyre!("Invalid BLS public key {}: {:?}", message.public_key, e))
+		})
+		.collect::<Result<Vec<_>>>()?;
+	let signatures = messages
+		.iter()
+		.map(|message| {
+			blst::min_pk::Signature::from_bytes(message.signature.as_slice())
+				.map_err(|e| eyre!("Invalid BLS signature by {}: {:?}", message.public_key, e))
+		})
+		.collect::<Result<Vec<_>>>()?;
+	// Each signature is weighted by a random non-zero scalar so invalid signatures cannot cancel each other out
+	let scalars = messages
+		.iter()
+		.map(|_| {
+			let mut scalar = blst_scalar::default();
+			scalar.b[..8].copy_from_slice(&rand::random::<u64>().max(1).to_le_bytes());
+			scalar
+		})
+		.collect::<Vec<_>>();
+
+	let msgs = signing_roots.iter().map(|root| root.as_slice()).collect::<Vec<_>>();
+	let public_keys = public_keys.iter().collect::<Vec<_>>();
+	let signatures = signatures.iter().collect::<Vec<_>>();
+	match blst::min_pk::Signature::verify_multiple_aggregate_signatures(
+		&msgs,
+		BLS_DST,
+		&public_keys,
+		true,
+		&signatures,
+		true,
+		&scalars,
+		64,
+	) {
+		BLST_ERROR::BLST_SUCCESS => Ok(()),
+		err => Err(eyre!("BLS batch verification of {} signatures failed: {:?}", messages.len(), err)),
+	}
+}
+
 /// Wrapper around the ECDSA signature verification function in Commit-Boost
 pub fn verify_ecdsa(
 	chain: Chain,
@@ -205,3 +389,155 @@ pub fn verify_ecdsa(
 	)
 	.context("Failed to verify ECDSA signature")?)
 }
+
+/// Recovers the committer of a signed commitment, the ECDSA signer of the Commit-Boost signing root of its
+/// `get_commitment_signing_root`, signing id and nonce
+pub fn recover_commitment_signer(chain: Chain, signed_commitment: &SignedCommitment) -> Result<Address> {
+	let signing_root = commit_signing_root(
+		chain,
+		get_commitment_signing_root(&signed_commitment.commitment),
+		&signed_commitment.signing_id,
+		signed_commitment.nonce,
+	);
+	signed_commitment
+		.signature
+		.recover_address_from_prehash(&signing_root)
+		.wrap_err("Failed to recover address from commitment signature")
+}
+
+/// Verifies that a signed commitment is signed by the expected committer
+pub fn verify_ecdsa_commitment(
+	chain: Chain,
+	signed_commitment: &SignedCommitment,
+	expected_committer: &Address,
+) -> Result<()> {
+	let committer = recover_commitment_signer(chain, signed_commitment)?;
+	if committer != *expected_committer {
+		return Err(eyre!("Commitment is signed by {}, expected committer {}", committer, expected_committer));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::primitives::Bytes;
+	use alloy::signers::SignerSync;
+	use alloy::signers::local::PrivateKeySigner;
+	use cb_common::types::BlsSecretKey;
+	use commitments::types::Commitment;
+
+	/// Committer signer holding its key outside the local signer, as a KMS does
+	struct RemoteCommitter {
+		chain: Chain,
+		signer: PrivateKeySigner,
+	}
+
+	#[async_trait]
+	impl CommitterSigner for RemoteCommitter {
+		fn address(&self) -> Address {
+			self.signer.address()
+		}
+
+		async fn sign(&self, message_hash: B256, module_signing_id: &B256, nonce: u64) -> Result<Signature> {
+			let signing_root = commit_signing_root(self.chain, message_hash, module_signing_id, nonce);
+			Ok(self.signer.sign_hash_sync(&signing_root)?)
+		}
+	}
+
+	#[tokio::test]
+	async fn test_committer_signer_signs_commitments() -> Result<()> {
+		let chain = Chain::Holesky;
+		let committer = RemoteCommitter { chain, signer: PrivateKeySigner::random() };
+		let address = committer.address();
+		let signing_id = B256::random();
+		let commitment = Commitment {
+			commitment_type: 1,
+			payload: Bytes::from_static(&[1, 2, 3]),
+			request_hash: B256::random(),
+			slasher: Address::random(),
+		};
+		let message_hash = get_commitment_signing_root(&commitment);
+
+		// The local backend does not hold the committer key, only the committer signer can sign
+		let mut signing_client = SigningClient::local(LocalSigner::new(chain));
+		assert!(call_proxy_ecdsa_signer(&mut signing_client, message_hash, address, &signing_id, chain).await.is_err());
+
+		let mut signing_client = signing_client.with_committer(Arc::new(committer));
+		let response = call_proxy_ecdsa_signer(&mut signing_client, message_hash, address, &signing_id, chain).await?;
+		assert_eq!(response.nonce, 0);
+		assert_eq!(response.module_signing_id, signing_id);
+		let signed_commitment =
+			SignedCommitment { commitment, nonce: response.nonce, signing_id, signature: response.signature };
+		verify_ecdsa_commitment(chain, &signed_commitment, &address)?;
+
+		// A committer the signer holds no key of is refused rather than signed by another key
+		let other = Address::random();
+		assert!(call_proxy_ecdsa_signer(&mut signing_client, message_hash, other, &signing_id, chain).await.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_verify_ecdsa_commitment() -> Result<()> {
+		let chain = Chain::Holesky;
+		let mut local_signer = LocalSigner::new(chain);
+		let committer = local_signer.add_ecdsa_key(PrivateKeySigner::random());
+		let signing_id = B256::random();
+		let commitment = Commitment {
+			commitment_type: 1,
+			payload: Bytes::from_static(&[1, 2, 3]),
+			request_hash: B256::random(),
+			slasher: Address::random(),
+		};
+		// Signed the way every signer backend signs, over the Commit-Boost signing root
+		let signature =
+			local_signer.sign_ecdsa(&committer, get_commitment_signing_root(&commitment), &signing_id, 7)?;
+		let signed_commitment = SignedCommitment { commitment, nonce: 7, signing_id, signature };
+
+		verify_ecdsa_commitment(chain, &signed_commitment, &committer)?;
+		assert!(verify_ecdsa_commitment(chain, &signed_commitment, &Address::random()).is_err());
+		assert!(verify_ecdsa_commitment(Chain::Mainnet, &signed_commitment, &committer).is_err());
+
+		// Tampering with the commitment or its nonce changes the recovered signer
+		let mut tampered = signed_commitment.clone();
+		tampered.commitment.payload = Bytes::from_static(&[4]);
+		assert!(verify_ecdsa_commitment(chain, &tampered, &committer).is_err());
+		let mut tampered = signed_commitment.clone();
+		tampered.nonce += 1;
+		assert!(verify_ecdsa_commitment(chain, &tampered, &committer).is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn test_verify_bls_batch() -> Result<()> {
+		let chain = Chain::Holesky;
+		let mut local_signer = LocalSigner::new(chain);
+		let public_keys = (0..3).map(|_| local_signer.add_bls_key(BlsSecretKey::random())).collect::<Vec<_>>();
+		let module_signing_id = B256::random();
+		let messages = public_keys
+			.iter()
+			.enumerate()
+			.map(|(nonce, public_key)| {
+				let message_hash = B256::random();
+				let signature =
+					local_signer.sign_bls(public_key, message_hash, &module_signing_id, nonce as u64)?.serialize();
+				Ok(BlsMessage {
+					public_key: *public_key,
+					message_hash,
+					signature: AlloyBlsSignature::new(signature),
+					module_signing_id,
+					nonce: nonce as u64,
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		verify_bls_batch(chain, &messages)?;
+		verify_bls_batch(chain, &[])?;
+
+		// A single invalid signature fails the whole batch
+		let mut tampered = messages.clone();
+		tampered[1].nonce += 1;
+		assert!(verify_bls_batch(chain, &tampered).is_err());
+		Ok(())
+	}
+}
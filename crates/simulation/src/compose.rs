@@ -0,0 +1,348 @@
+//! docker-compose.yml of the docker target, wired to the generated configs and .env files
+//!
+//! Each service waits for the services it talks to to pass their healthcheck, so `docker compose up` starts the
+//! stack in dependency order: signers, mocks and relay first, then the gateway and proposer, then the spammer.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::setup::Simulation;
+
+/// Service names, which are also the hostnames the services reach each other on
+pub const GATEWAY_SERVICE: &str = "gateway";
+pub const PROPOSER_SERVICE: &str = "proposer";
+pub const RELAY_SERVICE: &str = "constraints-server";
+pub const SPAMMER_SERVICE: &str = "spammer";
+pub const GATEWAY_SIGNER_SERVICE: &str = "gateway-signer";
+pub const PROPOSER_SIGNER_SERVICE: &str = "proposer-signer";
+pub const BEACON_MOCK_SERVICE: &str = "beacon-mock";
+pub const RELAY_MOCK_SERVICE: &str = "relay-mock";
+
+/// Hostname of the docker host, where the execution client and, without mocks, the beacon node and downstream relay
+/// run
+pub const DOCKER_HOST: &str = "host.docker.internal";
+
+/// Environment variable of the spammer holding the key it sends transactions from
+pub const SENDER_PRIVATE_KEY_ENV: &str = "SENDER_PRIVATE_KEY";
+
+/// Network the services are attached to
+const NETWORK: &str = "fabric";
+
+/// Working directory of the fabric images, relative paths of the configs resolve from it
+pub(crate) const CONTAINER_WORKDIR: &str = "/app";
+
+/// Status route of the Commit-Boost signer
+const SIGNER_STATUS: &str = "/status";
+const BEACON_SYNCING: &str = "/eth/v1/node/syncing";
+const BUILDER_STATUS: &str = "/eth/v1/builder/status";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComposeFile {
+	pub services: BTreeMap<String, ComposeService>,
+	pub networks: BTreeMap<String, ComposeNetwork>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComposeService {
+	pub image: String,
+	pub restart: String,
+	pub env_file: Vec<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub environment: Vec<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub volumes: Vec<String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub ports: Vec<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub healthcheck: Option<Healthcheck>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub depends_on: BTreeMap<String, Dependency>,
+	/// Resolves `host.docker.internal` on Linux hosts too
+	pub extra_hosts: Vec<String>,
+	pub networks: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Healthcheck {
+	pub test: Vec<String>,
+	pub interval: String,
+	pub timeout: String,
+	pub retries: u32,
+	pub start_period: String,
+	/// Port and path of the route checked, which the kurtosis package waits on too
+	#[serde(skip)]
+	pub port: u16,
+	#[serde(skip)]
+	pub path: String,
+}
+
+impl Healthcheck {
+	/// Healthy once `GET http://localhost:{port}{path}` succeeds
+	fn http(port: u16, path: &str) -> Self {
+		Self {
+			test: vec![
+				"CMD".to_string(),
+				"curl".to_string(),
+				"-fsS".to_string(),
+				format!("http://localhost:{}{}", port, path),
+			],
+			interval: "10s".to_string(),
+			timeout: "5s".to_string(),
+			retries: 6,
+			start_period: "10s".to_string(),
+			port,
+			path: path.to_string(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Dependency {
+	pub condition: String,
+}
+
+impl Dependency {
+	fn healthy() -> Self {
+		Self { condition: "service_healthy".to_string() }
+	}
+
+	fn started() -> Self {
+		Self { condition: "service_started".to_string() }
+	}
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ComposeNetwork {
+	#[serde(skip_serializing_if = "std::ops::Not::not")]
+	pub external: bool,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub name: Option<String>,
+}
+
+impl Simulation {
+	/// Compose file running every service of the docker target from its generated config and .env file
+	pub fn compose_file(&self) -> ComposeFile {
+		let setup = &self.setup;
+		let config = &setup.config;
+		let compose = &config.compose;
+		let paths = &setup.paths;
+
+		let service = |image: &str, env_file: &Path| ComposeService {
+			image: format!("fabric/{}:${{VERSION:-{}}}", image, compose.image_tag),
+			restart: "unless-stopped".to_string(),
+			env_file: vec![host_path(env_file)],
+			environment: Vec::new(),
+			volumes: Vec::new(),
+			ports: Vec::new(),
+			healthcheck: None,
+			depends_on: BTreeMap::new(),
+			extra_hosts: vec![format!("{}:host-gateway", DOCKER_HOST)],
+			networks: vec![NETWORK.to_string()],
+		};
+		let config_volume = |path: &Path| format!("{}:{}/config.toml:ro", host_path(path), CONTAINER_WORKDIR);
+		let db_volume = format!(
+			"{}/{}:{}",
+			compose.data_dir.trim_end_matches('/'),
+			relative(&config.db_path),
+			container_path(&config.db_path)
+		);
+		let mount = |host: &str, container: &str, mode: &str| format!("{}:{}{}", host, container_path(container), mode);
+
+		let mut services = BTreeMap::new();
+
+		let mut gateway_signer = service("signer", &paths.gateway_signer_env);
+		gateway_signer.volumes = vec![
+			config_volume(&paths.gateway_signer_config),
+			mount(&config.gateway_keys_path, &config.gateway_keys_path, ":ro"),
+			mount(&config.gateway_secrets_path, &config.gateway_secrets_path, ":ro"),
+			mount(&config.gateway_proxy_key_dir, &config.gateway_proxy_key_dir, ""),
+		];
+		gateway_signer.healthcheck = Some(Healthcheck::http(config.gateway_signer_port, SIGNER_STATUS));
+		services.insert(GATEWAY_SIGNER_SERVICE.to_string(), gateway_signer);
+
+		let mut proposer_signer = service("signer", &paths.proposer_signer_env);
+		proposer_signer.volumes = vec![
+			config_volume(&paths.proposer_signer_config),
+			mount(
+				compose.proposer_keys_dir.as_ref().unwrap_or(&config.proposer_keys_path),
+				&config.proposer_keys_path,
+				":ro",
+			),
+			mount(
+				compose.proposer_secrets_dir.as_ref().unwrap_or(&config.proposer_secrets_path),
+				&config.proposer_secrets_path,
+				":ro",
+			),
+			mount(&config.proposer_proxy_key_dir, &config.proposer_proxy_key_dir, ""),
+		];
+		proposer_signer.healthcheck = Some(Healthcheck::http(config.proposer_signer_port, SIGNER_STATUS));
+		services.insert(PROPOSER_SIGNER_SERVICE.to_string(), proposer_signer);
+
+		for node in &setup.relays {
+			let mut relay = service("relay", &node.env_file);
+			relay.volumes = vec![config_volume(&node.config_file), db_volume.clone()];
+			relay.ports = vec![format!("{0}:{0}", node.port)];
+			relay.healthcheck = Some(Healthcheck::http(node.port, common::health::HEALTHZ));
+			services.insert(node.service.clone(), relay);
+		}
+
+		for node in &setup.gateways {
+			let mut gateway = service("gateway", &node.env_file);
+			gateway.volumes = vec![config_volume(&node.config_file), db_volume.clone()];
+			gateway.ports = vec![format!("{0}:{0}", node.rpc_port)];
+			gateway.healthcheck = Some(Healthcheck::http(node.metrics_port, common::health::HEALTHZ));
+			gateway.depends_on = BTreeMap::from([
+				(GATEWAY_SIGNER_SERVICE.to_string(), Dependency::healthy()),
+				(setup.relay(&node.relay).service.clone(), Dependency::healthy()),
+			]);
+			services.insert(node.service.clone(), gateway);
+		}
+
+		let mut proposer = service("proposer", &paths.proposer_env);
+		proposer.volumes = vec![config_volume(&paths.proposer_config), db_volume];
+		proposer.healthcheck = Some(Healthcheck::http(compose.proposer_health_port, common::health::HEALTHZ));
+		proposer.depends_on = BTreeMap::from([(PROPOSER_SIGNER_SERVICE.to_string(), Dependency::healthy())]);
+		for relay in &setup.relays {
+			proposer.depends_on.insert(relay.service.clone(), Dependency::healthy());
+		}
+		services.insert(PROPOSER_SERVICE.to_string(), proposer);
+
+		let mut spammer = service("spammer", &paths.spammer_env);
+		spammer.volumes = vec![config_volume(&paths.spammer_config)];
+		// Passed through from the environment compose runs in, so no key is written to the generated files
+		spammer.environment = vec![SENDER_PRIVATE_KEY_ENV.to_string()];
+		if config.spammer_mode == "one-shot" {
+			spammer.restart = "no".to_string();
+		}
+		spammer.depends_on = BTreeMap::from([(setup.gateways[0].service.clone(), Dependency::healthy())]);
+		services.insert(SPAMMER_SERVICE.to_string(), spammer);
+
+		if compose.mocks {
+			let mut beacon_mock = service("beacon-mock", &paths.beacon_mock_env);
+			beacon_mock.healthcheck = Some(Healthcheck::http(config.beacon_port, BEACON_SYNCING));
+			services.insert(BEACON_MOCK_SERVICE.to_string(), beacon_mock);
+
+			// The mock builder posts blocks to the first relay, which only forwards to the mock once it is up
+			let mut relay_mock = service("relay-mock", &paths.relay_mock_env);
+			relay_mock.volumes = vec![config_volume(&paths.relay_mock_config)];
+			relay_mock.healthcheck = Some(Healthcheck::http(config.downstream_relay_port, BUILDER_STATUS));
+			relay_mock.depends_on = BTreeMap::from([(setup.relays[0].service.clone(), Dependency::started())]);
+			services.insert(RELAY_MOCK_SERVICE.to_string(), relay_mock);
+
+			let relays = setup.relays.iter().map(|relay| relay.service.as_str());
+			for name in relays.chain([PROPOSER_SERVICE]) {
+				if let Some(service) = services.get_mut(name) {
+					service.depends_on.insert(BEACON_MOCK_SERVICE.to_string(), Dependency::healthy());
+				}
+			}
+		}
+
+		let network = match &compose.network {
+			Some(name) => ComposeNetwork { external: true, name: Some(name.clone()) },
+			None => ComposeNetwork::default(),
+		};
+
+		ComposeFile { services, networks: BTreeMap::from([(NETWORK.to_string(), network)]) }
+	}
+}
+
+/// `path` as compose reads it, relative to the compose file at the root
+pub(crate) fn host_path(path: &Path) -> String {
+	match path.is_absolute() {
+		true => path.display().to_string(),
+		false => format!("./{}", path.display()),
+	}
+}
+
+fn relative(path: &str) -> &str {
+	path.trim_start_matches("./").trim_start_matches('/')
+}
+
+/// Where a path of a generated config points to inside the container
+pub(crate) fn container_path(path: &str) -> String {
+	match path.starts_with('/') {
+		true => path.to_string(),
+		false => format!("{}/{}", CONTAINER_WORKDIR, relative(path)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::setup::Target;
+	use crate::setup::tests::{multi_gateway_config, simulation_config, simulation_with};
+	use crate::templates::{render_toml, render_yaml};
+	use proposer::config::{PROPOSER_ENV_PREFIX, ProposerConfig};
+
+	#[test]
+	fn test_compose_file_orders_services_by_health() {
+		let mut config = simulation_config();
+		config.db_path = "./rocksdb".to_string();
+		config.compose.mocks = true;
+		config.compose.network = Some("kt-preconf-testnet".to_string());
+		let simulation = simulation_with(config, Target::Docker);
+
+		let rendered = render_yaml(&simulation.compose_file()).unwrap();
+		let compose: serde_yaml::Value = serde_yaml::from_str(&rendered).unwrap();
+		let services = &compose["services"];
+
+		assert_eq!(services["gateway"]["depends_on"]["gateway-signer"]["condition"], "service_healthy");
+		assert_eq!(services["gateway"]["depends_on"]["constraints-server"]["condition"], "service_healthy");
+		assert_eq!(services["proposer"]["depends_on"]["beacon-mock"]["condition"], "service_healthy");
+		assert_eq!(services["spammer"]["depends_on"]["gateway"]["condition"], "service_healthy");
+		assert_eq!(services["relay-mock"]["depends_on"]["constraints-server"]["condition"], "service_started");
+		assert!(services["spammer"]["healthcheck"].is_null());
+		assert_eq!(services["constraints-server"]["healthcheck"]["test"][3], "http://localhost:9998/healthz");
+		assert_eq!(services["gateway-signer"]["healthcheck"]["test"][3], "http://localhost:20001/status");
+
+		assert_eq!(services["gateway"]["env_file"][0], "./config/docker/gateway.env");
+		assert_eq!(services["gateway"]["volumes"][0], "./config/docker/gateway.toml:/app/config.toml:ro");
+		assert_eq!(services["gateway"]["volumes"][1], "/tmp/fabric/rocksdb:/app/rocksdb");
+		assert_eq!(services["gateway-signer"]["volumes"][1], "./data/keystores/keys:/app/data/keystores/keys:ro");
+		assert_eq!(services["proposer-signer"]["volumes"][1], "/tmp/keystores/keys:/tmp/keystores/keys:ro");
+		assert_eq!(compose["networks"]["fabric"]["name"], "kt-preconf-testnet");
+		assert_eq!(compose["networks"]["fabric"]["external"], true);
+
+		// Services reach the mocks by name instead of on the docker host
+		assert_eq!(simulation.relay_config(0).beacon_api_host, "beacon-mock");
+		assert_eq!(simulation.relay_config(0).downstream_relay_host, "relay-mock");
+	}
+
+	#[test]
+	fn test_compose_file_without_mocks_uses_the_docker_host() {
+		let simulation = simulation_with(simulation_config(), Target::Docker);
+		let compose = simulation.compose_file();
+
+		assert!(!compose.services.contains_key(BEACON_MOCK_SERVICE));
+		assert!(!compose.services.contains_key(RELAY_MOCK_SERVICE));
+		assert!(!compose.services[PROPOSER_SERVICE].depends_on.contains_key(BEACON_MOCK_SERVICE));
+		assert!(!compose.networks[NETWORK].external);
+		assert_eq!(compose.services[SPAMMER_SERVICE].environment, ["SENDER_PRIVATE_KEY"]);
+		assert_eq!(simulation.relay_config(0).beacon_api_host, DOCKER_HOST);
+		assert_eq!(simulation.relay_config(0).downstream_relay_host, "127.0.0.1");
+
+		// The proposer serves the health route compose waits on
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("proposer.toml");
+		std::fs::write(&path, render_toml(&simulation.proposer_config()).unwrap()).unwrap();
+		let proposer: ProposerConfig = config::load_module(&path, "proposer-module", PROPOSER_ENV_PREFIX).unwrap();
+		assert_eq!(proposer.health_port, Some(8003));
+	}
+
+	#[test]
+	fn test_compose_file_runs_every_gateway_and_relay() {
+		let simulation = simulation_with(multi_gateway_config(), Target::Docker);
+		let compose = simulation.compose_file();
+		let services = &compose.services;
+
+		assert_eq!(services["relay-2"].ports, ["9999:9999"]);
+		assert_eq!(services["relay-2"].env_file, ["./config/docker/relay-2.env"]);
+		assert!(services["gateway-2"].depends_on.contains_key("relay-2"));
+		assert!(services["gateway-3"].depends_on.contains_key(RELAY_SERVICE));
+		assert_eq!(services["gateway-3"].ports, ["8082:8082"]);
+		assert!(services[PROPOSER_SERVICE].depends_on.contains_key("relay-2"));
+		assert!(services[PROPOSER_SERVICE].depends_on.contains_key(RELAY_SERVICE));
+		assert!(services[SPAMMER_SERVICE].depends_on.contains_key(GATEWAY_SERVICE));
+	}
+}
@@ -0,0 +1,310 @@
+//! Central config of a simulation, from which the config of every service is generated
+use std::path::Path;
+
+use cb_common::types::Chain;
+use config::{ConfigErrors, Validate};
+use eyre::Result;
+use serde::Deserialize;
+
+/// Env prefix overriding fields of the simulation config
+pub const SIMULATION_ENV_PREFIX: &str = "FABRIC_SIMULATION";
+
+/// Name of the relay of the top-level relay fields, which additional gateways post constraints to by default
+pub const PRIMARY_RELAY: &str = "relay";
+
+/// Names of the generated files and compose services that additional gateways and relays can not take
+const RESERVED_NAMES: [&str; 9] = [
+	"gateway",
+	"proposer",
+	"relay",
+	"spammer",
+	"gateway-signer",
+	"proposer-signer",
+	"beacon-mock",
+	"relay-mock",
+	"constraints-server",
+];
+
+/// Pure data struct for simulation configuration loaded from TOML
+#[derive(Debug, Deserialize)]
+pub struct SimulationConfig {
+	// Chain spec
+	pub chain: Chain,
+
+	// Log level
+	pub log_level: String,
+
+	// Module name
+	pub gateway_module_name: String,
+	pub proposer_module_name: String,
+
+	// Module signing IDs
+	pub gateway_module_signing_id: String,
+	pub proposer_module_signing_id: String,
+
+	// Database paths
+	pub db_path: String,
+
+	// --- Key paths ----
+	pub gateway_default_bls_key: String,
+	pub gateway_proxy_key_dir: String,
+	pub gateway_keys_path: String,
+	pub gateway_secrets_path: String,
+
+	pub proposer_consensus_key: String,
+	pub proposer_proxy_key_dir: String,
+	pub proposer_keys_path: String,
+	pub proposer_secrets_path: String,
+
+	pub relay_bls_key: String,
+
+	// --- Service URLs ----
+	pub proposer_signer_host: String,
+	pub proposer_signer_port: u16,
+	pub gateway_signer_host: String,
+	pub gateway_signer_port: u16,
+	pub beacon_host: String,
+	pub beacon_port: u16,
+	pub execution_client_host: String,
+	pub execution_client_port: u16,
+	pub gateway_host: String,
+	pub gateway_port: u16,
+	pub gateway_metrics_host: String,
+	pub gateway_metrics_port: u16,
+	pub relay_host: String,
+	pub relay_port: u16,
+
+	// Gateway specific
+	pub delegation_check_interval_seconds: u64,
+	pub constraints_receivers: Vec<String>,
+
+	// Proposer specific
+	pub lookahead_check_interval_seconds: u64,
+
+	// Relay specific
+	pub lookahead_update_interval: u64,
+	pub downstream_relay_host: String,
+	pub downstream_relay_port: u16,
+
+	// Spammer specific
+	pub spammer_mode: String,
+	pub slasher_address: String,
+
+	// Multi-gateway and multi-relay topologies
+	/// Gateways next to the one of the top-level fields, all served by the gateway signer
+	#[serde(default)]
+	pub additional_gateways: Vec<AdditionalGatewayConfig>,
+	/// Relays next to the one of the top-level fields, the proposer posts its delegations to all of them
+	#[serde(default)]
+	pub additional_relays: Vec<AdditionalRelayConfig>,
+	/// Rule the proposer picks the delegate of each slot with among the gateways: `round_robin`, `highest_bid` or
+	/// `percentage_split`
+	#[serde(default)]
+	pub delegation_rule: Option<String>,
+	/// Number of relays that must accept a delegation, the proposer's default if not set
+	#[serde(default)]
+	pub delegation_quorum: Option<usize>,
+
+	// Docker compose stack of the docker target
+	#[serde(default)]
+	pub compose: ComposeConfig,
+}
+
+/// A further gateway, whose config, .env file, database and compose service are named after it
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdditionalGatewayConfig {
+	/// Module id of the gateway, also the name the proposer's delegation policy refers to it by
+	pub name: String,
+	pub module_signing_id: String,
+	/// Consensus key in the gateway signer's keystores the gateway's proxy keys are created for
+	pub default_bls_key: String,
+	pub rpc_port: u16,
+	pub metrics_port: u16,
+	/// Relay the gateway posts its constraints to, the primary relay if not set
+	#[serde(default)]
+	pub relay: Option<String>,
+	/// Share of the slots the gateway gets with the `percentage_split` rule
+	#[serde(default = "default_weight")]
+	pub weight: u64,
+	/// Bid per delegated slot used by the `highest_bid` rule, the primary gateway bids 0
+	#[serde(default)]
+	pub bid_gwei: u64,
+}
+
+fn default_weight() -> u64 {
+	1
+}
+
+/// A further relay, whose config, .env file, database and compose service are named after it
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdditionalRelayConfig {
+	pub name: String,
+	pub port: u16,
+	/// Downstream relay the relay forwards builder API requests to, `downstream_relay_port` if not set
+	#[serde(default)]
+	pub downstream_relay_port: Option<u16>,
+}
+
+/// The docker-compose.yml generated for the docker target
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ComposeConfig {
+	/// Tag of the fabric images, overridden by `VERSION` when compose runs
+	pub image_tag: String,
+	/// External network the services join, e.g. the kurtosis enclave's `kt-preconf-testnet`. A network of the stack's
+	/// own is created if not set
+	pub network: Option<String>,
+	/// Run the mock beacon node and the mock downstream relay and builder as part of the stack, instead of reaching
+	/// the beacon node and downstream relay on the docker host
+	pub mocks: bool,
+	/// Host directory of the service databases
+	pub data_dir: String,
+	/// Host directories of the proposer signer's keystores, `proposer_keys_path` and `proposer_secrets_path` if not set
+	pub proposer_keys_dir: Option<String>,
+	pub proposer_secrets_dir: Option<String>,
+	/// Port of the proposer's `/healthz`, which compose waits on
+	pub proposer_health_port: u16,
+}
+
+impl Default for ComposeConfig {
+	fn default() -> Self {
+		Self {
+			image_tag: "dev".to_string(),
+			network: None,
+			mocks: false,
+			data_dir: "/tmp/fabric".to_string(),
+			proposer_keys_dir: None,
+			proposer_secrets_dir: None,
+			proposer_health_port: 8003,
+		}
+	}
+}
+
+impl SimulationConfig {
+	fn validate_topology(&self, errors: &mut ConfigErrors) {
+		let mut ports = vec![
+			("gateway_port".to_string(), Some(self.gateway_port)),
+			("gateway_metrics_port".to_string(), Some(self.gateway_metrics_port)),
+			("relay_port".to_string(), Some(self.relay_port)),
+		];
+		for (index, relay) in self.additional_relays.iter().enumerate() {
+			let field = |name: &str| format!("additional_relays[{}].{}", index, name);
+			errors.port(&field("port"), relay.port);
+			if let Some(port) = relay.downstream_relay_port {
+				errors.port(&field("downstream_relay_port"), port);
+			}
+			ports.push((field("port"), Some(relay.port)));
+		}
+
+		for (index, gateway) in self.additional_gateways.iter().enumerate() {
+			let field = |name: &str| format!("additional_gateways[{}].{}", index, name);
+			errors.bytes32(&field("module_signing_id"), &gateway.module_signing_id);
+			errors.bls_public_key(&field("default_bls_key"), &gateway.default_bls_key);
+			errors.port(&field("rpc_port"), gateway.rpc_port);
+			errors.port(&field("metrics_port"), gateway.metrics_port);
+			if let Some(relay) = &gateway.relay {
+				errors.ensure(
+					relay == PRIMARY_RELAY || self.additional_relays.iter().any(|other| &other.name == relay),
+					&field("relay"),
+					format!("'{}' is not a relay of the simulation", relay),
+				);
+			}
+			ports.push((field("rpc_port"), Some(gateway.rpc_port)));
+			ports.push((field("metrics_port"), Some(gateway.metrics_port)));
+		}
+		let mut names = vec![self.gateway_module_name.as_str(), self.proposer_module_name.as_str(), PRIMARY_RELAY];
+		let additional_names =
+			self.additional_relays
+				.iter()
+				.enumerate()
+				.map(|(index, relay)| (format!("additional_relays[{}].name", index), relay.name.as_str()))
+				.chain(
+					self.additional_gateways.iter().enumerate().map(|(index, gateway)| {
+						(format!("additional_gateways[{}].name", index), gateway.name.as_str())
+					}),
+				);
+		for (field, name) in additional_names {
+			errors.not_empty(&field, name);
+			errors.ensure(!RESERVED_NAMES.contains(&name), &field, format!("'{}' is reserved", name));
+			errors.ensure(!names.contains(&name), &field, format!("'{}' is already used", name));
+			names.push(name);
+		}
+		errors.distinct_ports(&ports.iter().map(|(field, port)| (field.as_str(), *port)).collect::<Vec<_>>());
+
+		if let Some(rule) = &self.delegation_rule {
+			errors.ensure(
+				matches!(rule.as_str(), "round_robin" | "highest_bid" | "percentage_split"),
+				"delegation_rule",
+				format!("'{}' must be 'round_robin', 'highest_bid' or 'percentage_split'", rule),
+			);
+		}
+		if let Some(quorum) = self.delegation_quorum {
+			let relays = self.additional_relays.len() + 1;
+			errors.ensure(
+				(1..=relays).contains(&quorum),
+				"delegation_quorum",
+				format!("{} must be between 1 and the number of relays ({})", quorum, relays),
+			);
+		}
+	}
+}
+
+impl Validate for ComposeConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.not_empty("compose.image_tag", &self.image_tag);
+		if let Some(network) = &self.network {
+			errors.not_empty("compose.network", network);
+		}
+		errors.not_empty("compose.data_dir", &self.data_dir);
+		errors.port("compose.proposer_health_port", self.proposer_health_port);
+	}
+}
+
+impl SimulationConfig {
+	/// Read and validate the simulation config at `config_path`, overridden by the FABRIC_SIMULATION variables
+	pub fn load(config_path: impl AsRef<Path>) -> Result<Self> {
+		config::load_file(config_path, SIMULATION_ENV_PREFIX)
+	}
+}
+
+impl Validate for SimulationConfig {
+	fn validate(&self, errors: &mut ConfigErrors) {
+		errors.not_empty("gateway_module_name", &self.gateway_module_name);
+		errors.not_empty("proposer_module_name", &self.proposer_module_name);
+		errors.bytes32("gateway_module_signing_id", &self.gateway_module_signing_id);
+		errors.bytes32("proposer_module_signing_id", &self.proposer_module_signing_id);
+		errors.not_empty("db_path", &self.db_path);
+		errors.bls_public_key("gateway_default_bls_key", &self.gateway_default_bls_key);
+		errors.bls_public_key("proposer_consensus_key", &self.proposer_consensus_key);
+		errors.bls_public_key("relay_bls_key", &self.relay_bls_key);
+
+		for (host_field, host, port_field, port) in [
+			("proposer_signer_host", &self.proposer_signer_host, "proposer_signer_port", self.proposer_signer_port),
+			("gateway_signer_host", &self.gateway_signer_host, "gateway_signer_port", self.gateway_signer_port),
+			("beacon_host", &self.beacon_host, "beacon_port", self.beacon_port),
+			("execution_client_host", &self.execution_client_host, "execution_client_port", self.execution_client_port),
+			("gateway_host", &self.gateway_host, "gateway_port", self.gateway_port),
+			("gateway_metrics_host", &self.gateway_metrics_host, "gateway_metrics_port", self.gateway_metrics_port),
+			("relay_host", &self.relay_host, "relay_port", self.relay_port),
+			("downstream_relay_host", &self.downstream_relay_host, "downstream_relay_port", self.downstream_relay_port),
+		] {
+			errors.host(host_field, host);
+			errors.port(port_field, port);
+		}
+
+		errors.positive("delegation_check_interval_seconds", self.delegation_check_interval_seconds);
+		for (index, receiver) in self.constraints_receivers.iter().enumerate() {
+			errors.bls_public_key(&format!("constraints_receivers[{}]", index), receiver);
+		}
+		errors.positive("lookahead_check_interval_seconds", self.lookahead_check_interval_seconds);
+		errors.positive("lookahead_update_interval", self.lookahead_update_interval);
+		errors.ensure(
+			matches!(self.spammer_mode.as_str(), "one-shot" | "continuous"),
+			"spammer_mode",
+			format!("'{}' must be 'one-shot' or 'continuous'", self.spammer_mode),
+		);
+		errors.address("slasher_address", &self.slasher_address);
+		self.validate_topology(errors);
+		self.compose.validate(errors);
+	}
+}
@@ -0,0 +1,350 @@
+//! Kurtosis package of the docker target, running the services of the compose stack in a kurtosis enclave
+//!
+//! `main.star` adds the services in the order compose starts them, each once the services it depends on are ready,
+//! with the variables of its .env file. Kurtosis mounts files artifacts as directories, so each config is uploaded
+//! from the package, which is the config directory, and mounted under `/app/config`. The host directories compose
+//! mounts, the keystores and proxy keys, are uploaded to the enclave beforehand by `upload-files.sh`, and the
+//! databases are persistent directories of their service.
+//!
+//! Like compose, the services reach the execution client and, without mocks, the beacon node and downstream relay on
+//! `host.docker.internal`, which kurtosis containers resolve on Docker Desktop only.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use eyre::{Result, bail};
+
+use crate::compose::{CONTAINER_WORKDIR, ComposeService, container_path, host_path};
+use crate::setup::Simulation;
+use crate::templates::GENERATED_HEADER;
+
+/// Files of the package, in the config directory
+pub const KURTOSIS_MANIFEST: &str = "kurtosis.yml";
+pub const KURTOSIS_MAIN: &str = "main.star";
+pub const KURTOSIS_UPLOAD_SCRIPT: &str = "upload-files.sh";
+
+/// Name of the package, which kurtosis requires to be a repository locator
+const PACKAGE_NAME: &str = "github.com/eth-fabric/fabric/config/docker";
+
+/// Where the config of a service is mounted, relative paths resolve from the working directory
+const CONFIG_MOUNT: &str = "config";
+
+/// Variables pointing the services to their config
+const CONFIG_PATH_VARS: [&str; 2] = ["CB_CONFIG", "CONFIG_PATH"];
+
+/// Port of the route kurtosis waits on before adding the services that depend on a service
+const READY_PORT_ID: &str = "http";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KurtosisPackage {
+	/// Services in the order they are added
+	pub services: Vec<KurtosisService>,
+	/// Files artifacts uploaded before the package runs, by name, with the host path they are uploaded from
+	pub artifacts: BTreeMap<String, String>,
+	/// Image tag the package runs if not given the `version` argument
+	pub image_tag: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KurtosisService {
+	pub name: String,
+	/// Image without its tag
+	pub image: String,
+	/// File of the config in the package
+	pub config: Option<String>,
+	pub env_vars: Vec<(String, String)>,
+	/// Variables set from the package arguments, by the argument they are read from
+	pub arg_vars: Vec<(String, String)>,
+	/// Files artifacts mounted, by container directory
+	pub files: BTreeMap<String, String>,
+	/// Persistent directories, by container directory, with their key
+	pub persistent: BTreeMap<String, String>,
+	pub ports: BTreeMap<String, u16>,
+	/// Route the service is ready on, on the `http` port
+	pub ready_path: Option<String>,
+}
+
+impl Simulation {
+	/// Kurtosis package running the services of the compose file with the variables of their .env files
+	pub fn kurtosis_package(&self) -> Result<KurtosisPackage> {
+		let setup = &self.setup;
+		let compose = self.compose_file();
+		let env_files =
+			setup.env_files().into_iter().map(|(path, env)| (host_path(&path), env)).collect::<HashMap<_, _>>();
+		let db_dir = container_path(&setup.config.db_path);
+
+		let mut artifacts = BTreeMap::new();
+		let mut services = Vec::new();
+		for name in start_order(&compose.services)? {
+			let service = &compose.services[name];
+			let (image, _) = service.image.split_once(':').unwrap_or((service.image.as_str(), ""));
+			let mut kurtosis = KurtosisService {
+				name: name.to_string(),
+				image: image.to_string(),
+				config: None,
+				env_vars: Vec::new(),
+				arg_vars: Vec::new(),
+				files: BTreeMap::new(),
+				persistent: BTreeMap::new(),
+				ports: BTreeMap::new(),
+				ready_path: None,
+			};
+
+			for volume in &service.volumes {
+				let mut parts = volume.split(':');
+				let (Some(host), Some(container)) = (parts.next(), parts.next()) else {
+					bail!("Volume '{}' of {} is not host:container", volume, name);
+				};
+				if container == format!("{}/config.toml", CONTAINER_WORKDIR) {
+					let file = Path::new(host).file_name().map(|file| file.to_string_lossy().to_string());
+					kurtosis.config = file;
+				} else if container == db_dir {
+					kurtosis.persistent.insert(container.to_string(), format!("{}-{}", name, artifact_name(container)));
+				} else {
+					let artifact = artifact_name(host);
+					artifacts.insert(artifact.clone(), host.to_string());
+					kurtosis.files.insert(container.to_string(), artifact);
+				}
+			}
+
+			for env_file in &service.env_file {
+				let Some(env) = env_files.get(env_file) else {
+					bail!("{} of {} is not a generated .env file", env_file, name);
+				};
+				for (var, value) in env.vars() {
+					let value = match (CONFIG_PATH_VARS.contains(&var), &kurtosis.config) {
+						(true, Some(config)) => format!("{}/{}", CONFIG_MOUNT, config),
+						_ => value.to_string(),
+					};
+					kurtosis.env_vars.push((var.to_string(), value));
+				}
+			}
+			for var in &service.environment {
+				match var.split_once('=') {
+					Some((var, value)) => kurtosis.env_vars.push((var.to_string(), value.to_string())),
+					// Variables compose passes through from its environment are package arguments
+					None => kurtosis.arg_vars.push((var.clone(), var.to_lowercase())),
+				}
+			}
+
+			if let Some(healthcheck) = &service.healthcheck {
+				kurtosis.ports.insert(READY_PORT_ID.to_string(), healthcheck.port);
+				kurtosis.ready_path = Some(healthcheck.path.clone());
+			}
+			for port in &service.ports {
+				let port = port.split(':').next_back().unwrap_or(port).parse::<u16>()?;
+				if !kurtosis.ports.values().any(|published| *published == port) {
+					kurtosis.ports.insert(format!("port-{}", port), port);
+				}
+			}
+			services.push(kurtosis);
+		}
+
+		Ok(KurtosisPackage { services, artifacts, image_tag: setup.config.compose.image_tag.clone() })
+	}
+
+	/// Where the files of the kurtosis package are written
+	pub fn kurtosis_files(&self) -> [PathBuf; 3] {
+		let dir = &self.setup.paths.config_dir;
+		[dir.join(KURTOSIS_MANIFEST), dir.join(KURTOSIS_MAIN), dir.join(KURTOSIS_UPLOAD_SCRIPT)]
+	}
+}
+
+impl KurtosisPackage {
+	pub fn manifest(&self) -> String {
+		format!("{}\nname: {}\n", GENERATED_HEADER, PACKAGE_NAME)
+	}
+
+	/// `main.star`, taking the image tag as `version` and the variables compose passes through as arguments
+	pub fn main_star(&self) -> String {
+		let mut star = format!("{}\ndef run(plan, args):\n", GENERATED_HEADER);
+		star.push_str(&format!("    version = args.get(\"version\", {})\n", quote(&self.image_tag)));
+		let args = self.services.iter().flat_map(|service| &service.arg_vars).map(|(_, arg)| arg);
+		for arg in args.collect::<BTreeSet<_>>() {
+			star.push_str(&format!("    {0} = args[{1}]\n", arg, quote(arg)));
+		}
+
+		for service in &self.services {
+			let config_artifact = format!("{}-config", service.name);
+			if let Some(config) = &service.config {
+				star.push_str(&format!(
+					"\n    plan.upload_files(src = {}, name = {})\n",
+					quote(&format!("./{}", config)),
+					quote(&config_artifact)
+				));
+			} else {
+				star.push('\n');
+			}
+
+			star.push_str("    plan.add_service(\n");
+			star.push_str(&format!("        name = {},\n", quote(&service.name)));
+			star.push_str("        config = ServiceConfig(\n");
+			star.push_str(&format!("            image = {} + version,\n", quote(&format!("{}:", service.image))));
+
+			star.push_str("            ports = {\n");
+			for (id, port) in &service.ports {
+				star.push_str(&format!(
+					"                {}: PortSpec(number = {}, application_protocol = \"http\"),\n",
+					quote(id),
+					port
+				));
+			}
+			star.push_str("            },\n");
+
+			star.push_str("            files = {\n");
+			if service.config.is_some() {
+				let mount = format!("{}/{}", CONTAINER_WORKDIR, CONFIG_MOUNT);
+				star.push_str(&format!("                {}: {},\n", quote(&mount), quote(&config_artifact)));
+			}
+			for (dir, artifact) in &service.files {
+				star.push_str(&format!("                {}: {},\n", quote(dir), quote(artifact)));
+			}
+			for (dir, key) in &service.persistent {
+				star.push_str(&format!(
+					"                {}: Directory(persistent_key = {}),\n",
+					quote(dir),
+					quote(key)
+				));
+			}
+			star.push_str("            },\n");
+
+			star.push_str("            env_vars = {\n");
+			for (var, value) in &service.env_vars {
+				star.push_str(&format!("                {}: {},\n", quote(var), quote(value)));
+			}
+			for (var, arg) in &service.arg_vars {
+				star.push_str(&format!("                {}: {},\n", quote(var), arg));
+			}
+			star.push_str("            },\n");
+
+			if let Some(path) = &service.ready_path {
+				star.push_str("            ready_conditions = ReadyCondition(\n");
+				star.push_str(&format!(
+					"                recipe = GetHttpRequestRecipe(port_id = {}, endpoint = {}),\n",
+					quote(READY_PORT_ID),
+					quote(path)
+				));
+				star.push_str("                field = \"code\",\n");
+				star.push_str("                assertion = \"==\",\n");
+				star.push_str("                target_value = 200,\n");
+				star.push_str("            ),\n");
+			}
+			star.push_str("        ),\n");
+			star.push_str("    )\n");
+		}
+		star
+	}
+
+	/// Script uploading the files artifacts to the enclave given as its argument, run from the repository root
+	pub fn upload_script(&self) -> String {
+		let mut script = format!("#!/bin/sh\n{}\nset -e\nENCLAVE=\"${{1:?enclave name}}\"\n", GENERATED_HEADER);
+		for (artifact, host) in &self.artifacts {
+			script.push_str(&format!("kurtosis files upload \"$ENCLAVE\" {} --name {}\n", host, artifact));
+		}
+		script
+	}
+}
+
+/// Services in the order compose starts them, each after the services it depends on
+fn start_order(services: &BTreeMap<String, ComposeService>) -> Result<Vec<&str>> {
+	let mut order: Vec<&str> = Vec::new();
+	while order.len() < services.len() {
+		let next = services.iter().find(|(name, service)| {
+			!order.contains(&name.as_str())
+				&& service.depends_on.keys().all(|dependency| order.contains(&dependency.as_str()))
+		});
+		match next {
+			Some((name, _)) => order.push(name.as_str()),
+			None => bail!("Compose services depend on each other in a cycle"),
+		}
+	}
+	Ok(order)
+}
+
+/// Artifact or volume name of a path, e.g. `data-keystores-keys` for `./data/keystores/keys`
+fn artifact_name(path: &str) -> String {
+	path.split(|c: char| !c.is_ascii_alphanumeric())
+		.filter(|part| !part.is_empty())
+		.map(str::to_lowercase)
+		.collect::<Vec<_>>()
+		.join("-")
+}
+
+/// Starlark string literal
+fn quote(value: &str) -> String {
+	format!("{:?}", value)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compose::SENDER_PRIVATE_KEY_ENV;
+	use crate::setup::Target;
+	use crate::setup::tests::{simulation_config, simulation_with};
+
+	#[test]
+	fn test_kurtosis_package_runs_the_compose_services() {
+		let mut config = simulation_config();
+		config.db_path = "./rocksdb".to_string();
+		config.compose.mocks = true;
+		let simulation = simulation_with(config, Target::Docker);
+		let package = simulation.kurtosis_package().unwrap();
+		let service = |name: &str| package.services.iter().find(|service| service.name == name).unwrap();
+		let position = |name: &str| package.services.iter().position(|service| service.name == name).unwrap();
+
+		assert_eq!(package.services.len(), simulation.compose_file().services.len());
+		assert!(position("gateway-signer") < position("gateway"));
+		assert!(position("constraints-server") < position("gateway"));
+		assert!(position("beacon-mock") < position("constraints-server"));
+		assert!(position("constraints-server") < position("relay-mock"));
+		assert!(position("gateway") < position("spammer"));
+
+		let gateway = service("gateway");
+		assert_eq!(gateway.image, "fabric/gateway");
+		assert_eq!(gateway.config.as_deref(), Some("gateway.toml"));
+		assert!(gateway.env_vars.contains(&("CB_CONFIG".to_string(), "config/gateway.toml".to_string())));
+		assert!(gateway.env_vars.contains(&("CB_MODULE_ID".to_string(), "gateway-module".to_string())));
+		assert_eq!(gateway.persistent["/app/rocksdb"], "gateway-app-rocksdb");
+		assert_eq!(gateway.ports["http"], 8002);
+		assert_eq!(gateway.ports["port-8080"], 8080);
+		assert_eq!(gateway.ready_path.as_deref(), Some("/healthz"));
+		assert_eq!(
+			service("relay-mock").env_vars[0],
+			("CONFIG_PATH".to_string(), "config/relay-mock.toml".to_string())
+		);
+
+		// The spammer's key is an argument of the package rather than part of it
+		let spammer = service("spammer");
+		assert_eq!(spammer.arg_vars, [(SENDER_PRIVATE_KEY_ENV.to_string(), "sender_private_key".to_string())]);
+		assert!(spammer.ready_path.is_none());
+
+		let star = package.main_star();
+		assert!(star.contains("    version = args.get(\"version\", \"dev\")\n"));
+		assert!(star.contains("    sender_private_key = args[\"sender_private_key\"]\n"));
+		assert!(star.contains("plan.upload_files(src = \"./gateway.toml\", name = \"gateway-config\")"));
+		assert!(star.contains("                \"/app/config\": \"gateway-config\",\n"));
+		assert!(star.contains("                \"SENDER_PRIVATE_KEY\": sender_private_key,\n"));
+		assert!(
+			star.contains("recipe = GetHttpRequestRecipe(port_id = \"http\", endpoint = \"/eth/v1/node/syncing\")")
+		);
+		assert!(star.find("name = \"beacon-mock\"").unwrap() < star.find("name = \"constraints-server\"").unwrap());
+		assert!(package.manifest().ends_with("name: github.com/eth-fabric/fabric/config/docker\n"));
+	}
+
+	#[test]
+	fn test_kurtosis_package_uploads_the_mounted_host_directories() {
+		let simulation = simulation_with(simulation_config(), Target::Docker);
+		let package = simulation.kurtosis_package().unwrap();
+		let signer = package.services.iter().find(|service| service.name == "proposer-signer").unwrap();
+
+		assert_eq!(signer.files["/tmp/keystores/keys"], "tmp-keystores-keys");
+		assert_eq!(package.artifacts["tmp-keystores-keys"], "/tmp/keystores/keys");
+		assert_eq!(package.artifacts["data-keystores-keys"], "./data/keystores/keys");
+
+		let script = package.upload_script();
+		assert!(script.starts_with("#!/bin/sh\n"));
+		assert!(
+			script.contains("kurtosis files upload \"$ENCLAVE\" ./data/keystores/keys --name data-keystores-keys\n")
+		);
+		assert!(script.contains("kurtosis files upload \"$ENCLAVE\" /tmp/keystores/keys --name tmp-keystores-keys\n"));
+	}
+}
@@ -0,0 +1,13 @@
+//! Setup of a local or docker compose simulation of the fabric services
+//!
+//! From one [`SimulationConfig`] this generates the Commit-Boost configs of the signers and modules, the configs of
+//! the relay, spammer and relay mock, the .env file of every service and, for docker, a compose file and a kurtosis
+//! package running them.
+pub mod compose;
+pub mod config;
+pub mod kurtosis;
+pub mod setup;
+pub mod templates;
+
+pub use config::SimulationConfig;
+pub use setup::{Simulation, SimulationSetup, Target, run};
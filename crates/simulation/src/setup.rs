@@ -0,0 +1,1016 @@
+//! Generation of the config and .env files of every service of a simulation
+//!
+//! Setup runs in two stages, each a type of its own so no file is written with a value that is not known yet:
+//! a [`SimulationSetup`] writes the .env files and the signer configs, which the gateway signer needs to create the
+//! gateways' proxy keys, and the [`Simulation`] it becomes with those keys writes the service configs.
+//!
+//! A simulation runs one or several gateways and relays. The top-level fields of the [`SimulationConfig`] describe
+//! the primary ones, which keep the `gateway` and `relay` file names, and the additional ones are named after
+//! themselves.
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use alloy::hex;
+use alloy::primitives::B256;
+use cb_common::commit::client::SignerClient;
+use cb_common::config::StartSignerConfig;
+use cb_common::types::{BlsSecretKey, Jwt, ModuleId};
+use cb_common::utils::{bls_pubkey_from_hex, random_jwt_secret};
+use cb_signer::service::SigningService;
+use eyre::{Result, WrapErr};
+use inclusion::constants::{
+	BLOB_CONSTRAINT_TYPE, BUNDLE_CONSTRAINT_TYPE, EXCLUSION_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE,
+	ORDERING_CONSTRAINT_TYPE,
+};
+use reqwest::Url;
+use serde::Serialize;
+use tracing::info;
+
+use crate::compose::{
+	BEACON_MOCK_SERVICE, DOCKER_HOST, GATEWAY_SERVICE, GATEWAY_SIGNER_SERVICE, PROPOSER_SIGNER_SERVICE,
+	RELAY_MOCK_SERVICE, RELAY_SERVICE,
+};
+use crate::config::{PRIMARY_RELAY, SimulationConfig};
+use crate::templates::{
+	ChainSpec, CommitBoostConfig, DelegationPolicyEntry, EnvFile, GatewayModuleConfig, KeyLoaderConfig,
+	LocalSignerConfig, MetricsConfig, MockBuilderServiceConfig, ModuleConfig, NoModuleConfig, PbsConfig,
+	ProposerGatewayEntry, ProposerModuleConfig, ProxyStoreConfig, RelayEndpointEntry, RelayEntry,
+	RelayMockServiceConfig, RelayServiceConfig, SignerConfig, SpammerServiceConfig, render_toml, render_yaml,
+};
+
+/// Port of the PBS module in the Commit-Boost configs
+const PBS_PORT: u16 = 18850;
+
+/// Config path of every service inside its container
+const DOCKER_CONFIG_PATH: &str = "config.toml";
+
+/// Environment variable of the relay mock holding its builder key
+const BUILDER_KEY_ENV: &str = "BUILDER_BLS_KEY";
+
+/// How long the gateway signer is given to start before its proxy keys are requested
+const SIGNER_STARTUP_DELAY: Duration = Duration::from_secs(5);
+
+/// Where the services of the simulation run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+	/// On the host, files under `config/simulation`
+	Local,
+	/// In docker compose, files under `config/docker` and services reached by container name
+	Docker,
+}
+
+impl Target {
+	fn dir(&self) -> &'static str {
+		match self {
+			Target::Local => "config/simulation",
+			Target::Docker => "config/docker",
+		}
+	}
+
+	/// `docker` in docker compose, else `local`
+	fn pick<'a>(&self, local: &'a str, docker: &'a str) -> &'a str {
+		match self {
+			Target::Local => local,
+			Target::Docker => docker,
+		}
+	}
+}
+
+/// Signer services of the simulation, one for each module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignerRole {
+	Gateway,
+	Proposer,
+}
+
+/// Paths of the generated files and databases
+#[derive(Debug, Clone)]
+pub struct SimulationPaths {
+	pub config_dir: PathBuf,
+	pub gateway_config: PathBuf,
+	pub proposer_config: PathBuf,
+	pub gateway_signer_config: PathBuf,
+	pub proposer_signer_config: PathBuf,
+	pub relay_config: PathBuf,
+	pub spammer_config: PathBuf,
+	pub relay_mock_config: PathBuf,
+	pub gateway_env: PathBuf,
+	pub proposer_env: PathBuf,
+	pub gateway_signer_env: PathBuf,
+	pub proposer_signer_env: PathBuf,
+	pub relay_env: PathBuf,
+	pub spammer_env: PathBuf,
+	pub beacon_mock_env: PathBuf,
+	pub relay_mock_env: PathBuf,
+	/// Compose file of the docker target, at the root the setup runs from so the paths in it resolve
+	pub compose_file: PathBuf,
+	pub gateway_db: String,
+	pub proposer_db: String,
+	pub relay_db: String,
+}
+
+impl SimulationPaths {
+	fn new(config: &SimulationConfig, target: Target) -> Self {
+		let config_dir = PathBuf::from(target.dir());
+		let file = |name: &str| config_dir.join(name);
+		Self {
+			gateway_config: file("gateway.toml"),
+			proposer_config: file("proposer.toml"),
+			gateway_signer_config: file("gateway-signer.toml"),
+			proposer_signer_config: file("proposer-signer.toml"),
+			relay_config: file("relay.toml"),
+			spammer_config: file("spammer.toml"),
+			relay_mock_config: file("relay-mock.toml"),
+			gateway_env: file("gateway.env"),
+			proposer_env: file("proposer.env"),
+			gateway_signer_env: file("gateway-signer.env"),
+			proposer_signer_env: file("proposer-signer.env"),
+			relay_env: file("relay.env"),
+			spammer_env: file("spammer.env"),
+			beacon_mock_env: file("beacon-mock.env"),
+			relay_mock_env: file("relay-mock.env"),
+			compose_file: PathBuf::from("docker-compose.yml"),
+			gateway_db: format!("{}/gateway", config.db_path),
+			proposer_db: format!("{}/proposer", config.db_path),
+			relay_db: format!("{}/relay", config.db_path),
+			config_dir,
+		}
+	}
+}
+
+/// Secrets shared between the services, generated for each setup
+#[derive(Debug, Clone)]
+pub struct SimulationSecrets {
+	pub admin_jwt: Jwt,
+	pub gateway_jwt: Jwt,
+	/// JWTs of the additional gateways, in the order of `additional_gateways`
+	pub additional_gateway_jwts: Vec<Jwt>,
+	pub proposer_jwt: Jwt,
+	/// Hex-encoded BLS secret key of the relay mock's builder
+	pub builder_key: String,
+	/// Hex-encoded ECDSA keys the relays sign receipts with, the primary relay's first
+	pub relay_receipt_keys: Vec<String>,
+}
+
+impl SimulationSecrets {
+	pub fn generate(config: &SimulationConfig) -> Self {
+		Self {
+			admin_jwt: Jwt(random_jwt_secret()),
+			gateway_jwt: Jwt(random_jwt_secret()),
+			additional_gateway_jwts: config.additional_gateways.iter().map(|_| Jwt(random_jwt_secret())).collect(),
+			proposer_jwt: Jwt(random_jwt_secret()),
+			builder_key: hex::encode(BlsSecretKey::random().serialize()),
+			relay_receipt_keys: (0..=config.additional_relays.len()).map(|_| hex::encode(B256::random())).collect(),
+		}
+	}
+}
+
+/// A gateway of the topology, the one of the top-level fields first
+#[derive(Debug, Clone)]
+pub struct GatewayNode {
+	/// Module id, also the name the proposer's delegation policy refers to the gateway by
+	pub name: String,
+	pub signing_id: String,
+	/// Consensus key the gateway's proxy keys are created for
+	pub default_bls_key: String,
+	pub rpc_port: u16,
+	pub metrics_port: u16,
+	/// Relay the gateway posts its constraints to
+	pub relay: String,
+	pub weight: u64,
+	pub bid_gwei: u64,
+	pub jwt: Jwt,
+	pub config_file: PathBuf,
+	pub env_file: PathBuf,
+	pub db_path: String,
+	/// Compose service, and hostname in the docker target
+	pub service: String,
+}
+
+/// A relay of the topology, the one of the top-level fields first
+#[derive(Debug, Clone)]
+pub struct RelayNode {
+	pub name: String,
+	pub port: u16,
+	pub downstream_relay_port: u16,
+	pub config_file: PathBuf,
+	pub env_file: PathBuf,
+	pub db_path: String,
+	/// Compose service, and hostname in the docker target
+	pub service: String,
+}
+
+/// Keys the gateway signer creates for a gateway, which the gateway and proposer configs name
+#[derive(Debug, Clone)]
+pub struct GatewayKeys {
+	/// BLS proxy key the gateway signs constraints with
+	pub bls_proxy: String,
+	/// ECDSA proxy address the gateway signs commitments with
+	pub committer_address: String,
+}
+
+/// First stage of a setup, everything but the gateways' proxy keys is known
+#[derive(Debug)]
+pub struct SimulationSetup {
+	pub config: SimulationConfig,
+	pub target: Target,
+	pub paths: SimulationPaths,
+	pub secrets: SimulationSecrets,
+	pub gateways: Vec<GatewayNode>,
+	pub relays: Vec<RelayNode>,
+	pub proposer_module_id: ModuleId,
+	/// Relay URL of the PBS module, prefixed with the relay's BLS key
+	pub relay_url: Url,
+}
+
+impl SimulationSetup {
+	pub fn new(config: SimulationConfig, target: Target, secrets: SimulationSecrets) -> Result<Self> {
+		let relay_url =
+			Url::parse(&format!("http://{}@{}:{}", config.relay_bls_key, config.relay_host, config.relay_port))
+				.wrap_err("Invalid relay URL")?;
+		let paths = SimulationPaths::new(&config, target);
+
+		let mut gateways = vec![GatewayNode {
+			name: config.gateway_module_name.clone(),
+			signing_id: config.gateway_module_signing_id.clone(),
+			default_bls_key: config.gateway_default_bls_key.clone(),
+			rpc_port: config.gateway_port,
+			metrics_port: config.gateway_metrics_port,
+			relay: PRIMARY_RELAY.to_string(),
+			weight: 1,
+			bid_gwei: 0,
+			jwt: secrets.gateway_jwt.clone(),
+			config_file: paths.gateway_config.clone(),
+			env_file: paths.gateway_env.clone(),
+			db_path: paths.gateway_db.clone(),
+			service: GATEWAY_SERVICE.to_string(),
+		}];
+		for (gateway, jwt) in config.additional_gateways.iter().zip(&secrets.additional_gateway_jwts) {
+			gateways.push(GatewayNode {
+				name: gateway.name.clone(),
+				signing_id: gateway.module_signing_id.clone(),
+				default_bls_key: gateway.default_bls_key.clone(),
+				rpc_port: gateway.rpc_port,
+				metrics_port: gateway.metrics_port,
+				relay: gateway.relay.clone().unwrap_or_else(|| PRIMARY_RELAY.to_string()),
+				weight: gateway.weight,
+				bid_gwei: gateway.bid_gwei,
+				jwt: jwt.clone(),
+				config_file: paths.config_dir.join(format!("{}.toml", gateway.name)),
+				env_file: paths.config_dir.join(format!("{}.env", gateway.name)),
+				db_path: format!("{}/{}", config.db_path, gateway.name),
+				service: gateway.name.clone(),
+			});
+		}
+
+		let mut relays = vec![RelayNode {
+			name: PRIMARY_RELAY.to_string(),
+			port: config.relay_port,
+			downstream_relay_port: config.downstream_relay_port,
+			config_file: paths.relay_config.clone(),
+			env_file: paths.relay_env.clone(),
+			db_path: paths.relay_db.clone(),
+			service: RELAY_SERVICE.to_string(),
+		}];
+		for relay in &config.additional_relays {
+			relays.push(RelayNode {
+				name: relay.name.clone(),
+				port: relay.port,
+				downstream_relay_port: relay.downstream_relay_port.unwrap_or(config.downstream_relay_port),
+				config_file: paths.config_dir.join(format!("{}.toml", relay.name)),
+				env_file: paths.config_dir.join(format!("{}.env", relay.name)),
+				db_path: format!("{}/{}", config.db_path, relay.name),
+				service: relay.name.clone(),
+			});
+		}
+
+		Ok(Self {
+			proposer_module_id: ModuleId(config.proposer_module_name.clone()),
+			gateways,
+			relays,
+			paths,
+			relay_url,
+			secrets,
+			target,
+			config,
+		})
+	}
+
+	/// Relay named `name`, the primary relay if there is none
+	pub fn relay(&self, name: &str) -> &RelayNode {
+		self.relays.iter().find(|relay| relay.name == name).unwrap_or(&self.relays[0])
+	}
+
+	/// Host the services reach `relay`'s constraints API on
+	pub fn relay_host<'a>(&'a self, relay: &'a RelayNode) -> &'a str {
+		self.target.pick(&self.config.relay_host, &relay.service)
+	}
+
+	/// Create the config, key and database directories, then write the .env files and the signer configs
+	pub fn write_bootstrap(&self) -> Result<()> {
+		let config = &self.config;
+		let config_dir = self.paths.config_dir.display().to_string();
+		let dirs = [
+			&config_dir,
+			&config.gateway_proxy_key_dir,
+			&config.gateway_keys_path,
+			&config.gateway_secrets_path,
+			&config.proposer_proxy_key_dir,
+			&config.proposer_keys_path,
+			&config.proposer_secrets_path,
+			&self.paths.proposer_db,
+		]
+		.into_iter()
+		.chain(self.gateways.iter().map(|gateway| &gateway.db_path))
+		.chain(self.relays.iter().map(|relay| &relay.db_path));
+		for dir in dirs {
+			std::fs::create_dir_all(dir).wrap_err_with(|| format!("Failed to create {}", dir))?;
+		}
+
+		for (path, env) in self.env_files() {
+			write_file(&path, env.render())?;
+		}
+		for role in [SignerRole::Gateway, SignerRole::Proposer] {
+			let path = match role {
+				SignerRole::Gateway => &self.paths.gateway_signer_config,
+				SignerRole::Proposer => &self.paths.proposer_signer_config,
+			};
+			write_file(path, render_toml(&self.signer_config(role))?)?;
+		}
+		Ok(())
+	}
+
+	/// Start the gateway signer from its generated config and have it create the proxy keys of every gateway, in the
+	/// order of `gateways`
+	pub async fn generate_gateway_keys(&self) -> Result<Vec<GatewayKeys>> {
+		dotenv::from_filename(&self.paths.gateway_signer_env)?;
+		// The .env file names the config as the container sees it
+		// SAFETY: setup is single threaded until the signer is spawned below
+		unsafe {
+			std::env::set_var("CB_CONFIG", &self.paths.gateway_signer_config);
+		}
+		let signer_config = StartSignerConfig::load_from_env()?;
+		info!("Signer config loaded: {:?}", signer_config);
+
+		let signer = tokio::spawn(async move { SigningService::run(signer_config).await });
+		tokio::time::sleep(SIGNER_STARTUP_DELAY).await;
+		let mut keys = Vec::with_capacity(self.gateways.len());
+		for gateway in &self.gateways {
+			match self.request_gateway_keys(gateway).await {
+				Ok(gateway_keys) => keys.push(gateway_keys),
+				Err(e) => {
+					signer.abort();
+					return Err(e.wrap_err(format!("Failed to create the proxy keys of {}", gateway.name)));
+				}
+			}
+		}
+		signer.abort();
+		Ok(keys)
+	}
+
+	async fn request_gateway_keys(&self, gateway: &GatewayNode) -> Result<GatewayKeys> {
+		let signer_url =
+			Url::parse(&format!("http://{}:{}", self.config.gateway_signer_host, self.config.gateway_signer_port))?;
+		let mut client = SignerClient::new(signer_url, None, gateway.jwt.clone(), ModuleId(gateway.name.clone()))?;
+		let gateway_bls_key = bls_pubkey_from_hex(&gateway.default_bls_key)?;
+
+		let bls_proxy = client.generate_proxy_key_bls(gateway_bls_key.clone()).await?;
+		info!("BLS proxy of {}: {:?}", gateway.name, bls_proxy);
+		let ecdsa_proxy = client.generate_proxy_key_ecdsa(gateway_bls_key).await?;
+		info!("ECDSA proxy of {}: {:?}", gateway.name, ecdsa_proxy);
+
+		Ok(GatewayKeys {
+			bls_proxy: bls_proxy.message.proxy.to_string(),
+			committer_address: ecdsa_proxy.message.proxy.to_checksum(None),
+		})
+	}
+
+	/// Second stage of the setup, once the proxy keys of the gateways exist, in the order of `gateways`
+	pub fn with_gateway_keys(self, gateway_keys: Vec<GatewayKeys>) -> Result<Simulation> {
+		eyre::ensure!(
+			gateway_keys.len() == self.gateways.len(),
+			"Expected the keys of {} gateways, got {}",
+			self.gateways.len(),
+			gateway_keys.len()
+		);
+		Ok(Simulation { setup: self, gateway_keys })
+	}
+
+	/// The .env file of every service, with the path it is written to
+	pub fn env_files(&self) -> Vec<(PathBuf, EnvFile)> {
+		let config = &self.config;
+		let paths = &self.paths;
+		let config_path = |path: &Path| match self.target {
+			Target::Local => path.display().to_string(),
+			Target::Docker => DOCKER_CONFIG_PATH.to_string(),
+		};
+		let signer_url = |role: SignerRole| match (self.target, role) {
+			(Target::Local, SignerRole::Gateway) => {
+				format!("http://{}:{}/", config.gateway_signer_host, config.gateway_signer_port)
+			}
+			(Target::Local, SignerRole::Proposer) => {
+				format!("http://{}:{}/", config.proposer_signer_host, config.proposer_signer_port)
+			}
+			(Target::Docker, SignerRole::Gateway) => {
+				format!("http://{}:{}", GATEWAY_SIGNER_SERVICE, config.gateway_signer_port)
+			}
+			(Target::Docker, SignerRole::Proposer) => {
+				format!("http://{}:{}", PROPOSER_SIGNER_SERVICE, config.proposer_signer_port)
+			}
+		};
+		let module_jwts = self
+			.gateways
+			.iter()
+			.map(|gateway| format!("{}={}", gateway.name, gateway.jwt))
+			.chain([format!("{}={}", self.proposer_module_id, self.secrets.proposer_jwt)])
+			.collect::<Vec<_>>()
+			.join(",");
+		let module_env = |path: &Path, module_id: &str, jwt: &Jwt, role: SignerRole| {
+			EnvFile::default()
+				.var("CB_CONFIG", config_path(path))
+				.var("CB_MODULE_ID", module_id)
+				.var("CB_SIGNER_JWT", jwt)
+				.var("CB_SIGNER_URL", signer_url(role))
+				.var("RUST_LOG", &config.log_level)
+		};
+		let signer_env = |path: &Path| {
+			EnvFile::default()
+				.var("CB_CONFIG", config_path(path))
+				.var("CB_JWTS", &module_jwts)
+				.var("CB_SIGNER_ADMIN_JWT", &self.secrets.admin_jwt)
+				.var("RUST_LOG", &config.log_level)
+		};
+		let service_env =
+			|path: &Path| EnvFile::default().var("CONFIG_PATH", config_path(path)).var("RUST_LOG", &config.log_level);
+
+		let mut envs = self
+			.gateways
+			.iter()
+			.map(|gateway| {
+				let env = module_env(&gateway.config_file, &gateway.name, &gateway.jwt, SignerRole::Gateway);
+				(gateway.env_file.clone(), env)
+			})
+			.collect::<Vec<_>>();
+		envs.push((
+			paths.proposer_env.clone(),
+			module_env(
+				&paths.proposer_config,
+				&self.proposer_module_id.to_string(),
+				&self.secrets.proposer_jwt,
+				SignerRole::Proposer,
+			),
+		));
+		envs.push((paths.gateway_signer_env.clone(), signer_env(&paths.gateway_signer_config)));
+		envs.push((paths.proposer_signer_env.clone(), signer_env(&paths.proposer_signer_config)));
+		envs.extend(self.relays.iter().map(|relay| (relay.env_file.clone(), service_env(&relay.config_file))));
+		envs.push((paths.spammer_env.clone(), service_env(&paths.spammer_config)));
+		envs.push((
+			paths.beacon_mock_env.clone(),
+			EnvFile::default()
+				.var("BEACON_HOST", &config.beacon_host)
+				.var("BEACON_PORT", config.beacon_port)
+				.var("PROPOSER_KEY", &config.proposer_consensus_key)
+				.var("GENESIS_TIME", config.chain.genesis_time_sec())
+				.var("SECONDS_PER_SLOT", config.chain.slot_time_sec())
+				.var("RUST_LOG", &config.log_level),
+		));
+		envs.push((
+			paths.relay_mock_env.clone(),
+			EnvFile::default()
+				.var("CONFIG_PATH", config_path(&paths.relay_mock_config))
+				.var(BUILDER_KEY_ENV, &self.secrets.builder_key)
+				.var("RUST_LOG", &config.log_level),
+		));
+		envs
+	}
+
+	/// Commit-Boost config of a signer, which serves every gateway and the proposer
+	pub fn signer_config(&self, role: SignerRole) -> CommitBoostConfig<NoModuleConfig> {
+		let config = &self.config;
+		let modules = self
+			.gateways
+			.iter()
+			.map(|gateway| ModuleConfig::commit(&gateway.name, &gateway.signing_id, NoModuleConfig {}))
+			.chain([ModuleConfig::commit(
+				&config.proposer_module_name,
+				&config.proposer_module_signing_id,
+				NoModuleConfig {},
+			)])
+			.collect();
+		self.commit_boost_config(role, modules)
+	}
+
+	fn commit_boost_config<T>(&self, role: SignerRole, modules: Vec<ModuleConfig<T>>) -> CommitBoostConfig<T> {
+		let config = &self.config;
+		let (host, port, keys_path, secrets_path, proxy_dir) = match role {
+			SignerRole::Gateway => (
+				&config.gateway_signer_host,
+				config.gateway_signer_port,
+				&config.gateway_keys_path,
+				&config.gateway_secrets_path,
+				&config.gateway_proxy_key_dir,
+			),
+			SignerRole::Proposer => (
+				&config.proposer_signer_host,
+				config.proposer_signer_port,
+				&config.proposer_keys_path,
+				&config.proposer_secrets_path,
+				&config.proposer_proxy_key_dir,
+			),
+		};
+		CommitBoostConfig {
+			chain: ChainSpec::from(&config.chain),
+			pbs: PbsConfig { port: PBS_PORT, with_signer: true },
+			relays: vec![RelayEntry { url: self.relay_url.to_string() }],
+			metrics: MetricsConfig { enabled: true },
+			signer: SignerConfig {
+				host: host.clone(),
+				port,
+				local: LocalSignerConfig {
+					loader: KeyLoaderConfig {
+						format: "lighthouse".to_string(),
+						keys_path: keys_path.clone(),
+						secrets_path: secrets_path.clone(),
+					},
+					store: ProxyStoreConfig { proxy_dir: proxy_dir.clone() },
+				},
+			},
+			modules,
+		}
+	}
+}
+
+/// A setup whose gateway proxy keys exist, ready to write the service configs
+#[derive(Debug)]
+pub struct Simulation {
+	pub setup: SimulationSetup,
+	/// Keys of the gateways, in the order of `setup.gateways`
+	pub gateway_keys: Vec<GatewayKeys>,
+}
+
+impl Simulation {
+	/// Write the config of every service, and the compose file and kurtosis package of the docker target
+	pub fn write_service_configs(&self) -> Result<()> {
+		let paths = &self.setup.paths;
+		for (index, gateway) in self.setup.gateways.iter().enumerate() {
+			write_file(&gateway.config_file, render_toml(&self.gateway_config(index))?)?;
+		}
+		write_file(&paths.proposer_config, render_toml(&self.proposer_config())?)?;
+		for (index, relay) in self.setup.relays.iter().enumerate() {
+			write_file(&relay.config_file, render_toml(&self.relay_config(index))?)?;
+		}
+		write_file(&paths.spammer_config, render_toml(&self.spammer_config())?)?;
+		write_file(&paths.relay_mock_config, render_toml(&self.relay_mock_config())?)?;
+		if self.setup.target == Target::Docker {
+			write_file(&paths.compose_file, render_yaml(&self.compose_file())?)?;
+			let package = self.kurtosis_package()?;
+			let [manifest, main, upload_script] = self.kurtosis_files();
+			write_file(&manifest, package.manifest())?;
+			write_file(&main, package.main_star())?;
+			write_file(&upload_script, package.upload_script())?;
+		}
+		Ok(())
+	}
+
+	/// Host the proposer and relays reach the beacon node on
+	fn beacon_host(&self) -> &str {
+		let config = &self.setup.config;
+		match self.setup.target {
+			Target::Local => &config.beacon_host,
+			Target::Docker if config.compose.mocks => BEACON_MOCK_SERVICE,
+			Target::Docker => DOCKER_HOST,
+		}
+	}
+
+	/// Host the relays forward builder API requests to
+	fn downstream_relay_host(&self) -> &str {
+		let config = &self.setup.config;
+		match self.setup.target {
+			Target::Docker if config.compose.mocks => RELAY_MOCK_SERVICE,
+			_ => &config.downstream_relay_host,
+		}
+	}
+
+	/// Config of the gateway at `index` of `setup.gateways`
+	pub fn gateway_config(&self, index: usize) -> CommitBoostConfig<GatewayModuleConfig> {
+		let setup = &self.setup;
+		let config = &setup.config;
+		let gateway = &setup.gateways[index];
+		let relay = setup.relay(&gateway.relay);
+		let module = GatewayModuleConfig {
+			rpc_host: config.gateway_host.clone(),
+			rpc_port: gateway.rpc_port,
+			metrics_host: config.gateway_metrics_host.clone(),
+			metrics_port: gateway.metrics_port,
+			db_path: gateway.db_path.clone(),
+			relay_host: setup.relay_host(relay).to_string(),
+			relay_port: relay.port,
+			execution_client_host: setup.target.pick(&config.execution_client_host, DOCKER_HOST).to_string(),
+			execution_client_port: config.execution_client_port,
+			// Left empty until the builder can sign x-receiver headers
+			constraints_receivers: Vec::new(),
+			module_signing_id: gateway.signing_id.clone(),
+			log_level: "info".to_string(),
+			delegation_check_interval_seconds: config.delegation_check_interval_seconds,
+			gateway_public_key: self.gateway_keys[index].bls_proxy.clone(),
+		};
+		setup.commit_boost_config(
+			SignerRole::Gateway,
+			vec![ModuleConfig::commit(&gateway.name, &gateway.signing_id, module)],
+		)
+	}
+
+	pub fn proposer_config(&self) -> CommitBoostConfig<ProposerModuleConfig> {
+		let setup = &self.setup;
+		let config = &setup.config;
+		// A single gateway is delegated to directly, several through the delegation policy
+		let (gateway_public_key, gateway_address, gateways, delegation_policy) = match self.gateway_keys.as_slice() {
+			[keys] => (Some(keys.bls_proxy.clone()), Some(keys.committer_address.clone()), Vec::new(), None),
+			_ => (
+				None,
+				None,
+				setup
+					.gateways
+					.iter()
+					.zip(&self.gateway_keys)
+					.map(|(gateway, keys)| ProposerGatewayEntry {
+						name: gateway.name.clone(),
+						public_key: keys.bls_proxy.clone(),
+						address: keys.committer_address.clone(),
+						weight: gateway.weight,
+						bid_gwei: gateway.bid_gwei,
+					})
+					.collect(),
+				config.delegation_rule.clone().map(|rule| DelegationPolicyEntry { rule }),
+			),
+		};
+		let primary_relay = &setup.relays[0];
+		let module = ProposerModuleConfig {
+			db_path: setup.paths.proposer_db.clone(),
+			gateway_public_key,
+			gateway_address,
+			relay_host: setup.relay_host(primary_relay).to_string(),
+			relay_port: primary_relay.port,
+			delegation_quorum: config.delegation_quorum,
+			beacon_api_host: self.beacon_host().to_string(),
+			beacon_api_port: config.beacon_port,
+			lookahead_check_interval_seconds: config.lookahead_check_interval_seconds,
+			module_signing_id: config.proposer_module_signing_id.clone(),
+			// Served for the compose healthcheck
+			health_host: (setup.target == Target::Docker).then(|| "0.0.0.0".to_string()),
+			health_port: (setup.target == Target::Docker).then_some(config.compose.proposer_health_port),
+			gateways,
+			delegation_policy,
+			additional_relays: setup.relays[1..]
+				.iter()
+				.map(|relay| RelayEndpointEntry { host: setup.relay_host(relay).to_string(), port: relay.port })
+				.collect(),
+		};
+		setup.commit_boost_config(
+			SignerRole::Proposer,
+			vec![ModuleConfig::commit(&config.proposer_module_name, &config.proposer_module_signing_id, module)],
+		)
+	}
+
+	/// Config of the relay at `index` of `setup.relays`
+	pub fn relay_config(&self, index: usize) -> RelayServiceConfig {
+		let setup = &self.setup;
+		let config = &setup.config;
+		let relay = &setup.relays[index];
+		RelayServiceConfig {
+			chain: ChainSpec::from(&config.chain),
+			host: config.relay_host.clone(),
+			port: relay.port,
+			db_path: relay.db_path.clone(),
+			constraint_capabilities: vec![
+				INCLUSION_CONSTRAINT_TYPE,
+				EXCLUSION_CONSTRAINT_TYPE,
+				ORDERING_CONSTRAINT_TYPE,
+				BUNDLE_CONSTRAINT_TYPE,
+				BLOB_CONSTRAINT_TYPE,
+			],
+			beacon_api_host: self.beacon_host().to_string(),
+			beacon_api_port: config.beacon_port,
+			lookahead_update_interval: config.lookahead_update_interval,
+			downstream_relay_host: self.downstream_relay_host().to_string(),
+			downstream_relay_port: relay.downstream_relay_port,
+			receipt_signing_key: setup.secrets.relay_receipt_keys[index].clone(),
+		}
+	}
+
+	/// Config of the spammer, which sends its requests to the primary gateway
+	pub fn spammer_config(&self) -> SpammerServiceConfig {
+		let setup = &self.setup;
+		let config = &setup.config;
+		let gateway = &setup.gateways[0];
+		SpammerServiceConfig {
+			mode: config.spammer_mode.clone(),
+			chain: ChainSpec::from(&config.chain),
+			gateway_host: setup.target.pick(&config.gateway_host, &gateway.service).to_string(),
+			gateway_port: gateway.rpc_port,
+			execution_client_host: setup.target.pick(&config.execution_client_host, DOCKER_HOST).to_string(),
+			execution_client_port: config.execution_client_port,
+			slasher_address: config.slasher_address.clone(),
+		}
+	}
+
+	/// Config of the relay mock, whose builder posts its blocks to the primary relay
+	pub fn relay_mock_config(&self) -> RelayMockServiceConfig {
+		let setup = &self.setup;
+		let config = &setup.config;
+		let relay = &setup.relays[0];
+		RelayMockServiceConfig {
+			chain: ChainSpec::from(&config.chain),
+			host: setup.target.pick(&config.downstream_relay_host, "0.0.0.0").to_string(),
+			port: config.downstream_relay_port,
+			relay_public_key: config.relay_bls_key.clone(),
+			builder: MockBuilderServiceConfig {
+				relay_host: setup.relay_host(relay).to_string(),
+				relay_port: relay.port,
+				builder_key_env: BUILDER_KEY_ENV.to_string(),
+			},
+		}
+	}
+}
+
+/// Generate every file of a simulation: the .env files and signer configs, then the gateways' proxy keys from the
+/// gateway signer, then the service configs
+pub async fn run(config: SimulationConfig, target: Target) -> Result<Simulation> {
+	let secrets = SimulationSecrets::generate(&config);
+	let setup = SimulationSetup::new(config, target, secrets)?;
+	setup.write_bootstrap()?;
+	let gateway_keys = setup.generate_gateway_keys().await?;
+	let simulation = setup.with_gateway_keys(gateway_keys)?;
+	simulation.write_service_configs()?;
+	Ok(simulation)
+}
+
+fn write_file(path: &Path, content: impl AsRef<[u8]>) -> Result<()> {
+	std::fs::write(path, content).wrap_err_with(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use config::Validate;
+	use inclusion::gateway::config::{GATEWAY_ENV_PREFIX, GatewayConfig};
+	use inclusion::relay::config::{RELAY_ENV_PREFIX, RelayConfig};
+	use proposer::config::{PROPOSER_ENV_PREFIX, ProposerConfig};
+
+	const PROXY_KEY: &str = PROXY_KEYS[0];
+	const PROXY_KEYS: [&str; 3] = [
+		"0xb3a22e4a673ac7a153ab5b3c17a4dbef55f7e47210b20c0cbb0e66df5b36bb49ef808577610b034172e955d2312a61b9",
+		"0x883827193f7627cd04e621e1e8d56498362a52b2a30c9a1c72036eb935c4278dee23d38a24d2f7dda62689886f0c39f4",
+		"0xafa4c6985aa049fb79dd37010438cfebeb0f2bd42b115b89dd678dab0670c1de38da0c4e9138c9290a398ecd9a0b3110",
+	];
+	const ADDRESSES: [&str; 3] = [
+		"0x1234567890123456789012345678901234567890",
+		"0x2234567890123456789012345678901234567890",
+		"0x3234567890123456789012345678901234567890",
+	];
+
+	const SIMULATION_CONFIG: &str = r#"
+			chain = { genesis_time_secs = 1767656185, slot_time_secs = 12, genesis_fork_version = "0x10000038", chain_id = 3151908 }
+			log_level = "debug"
+			gateway_module_name = "gateway-module"
+			proposer_module_name = "proposer-module"
+			gateway_module_signing_id = "0x1111111111111111111111111111111111111111111111111111111111111111"
+			proposer_module_signing_id = "0x2222222222222222222222222222222222222222222222222222222222222222"
+			db_path = "/tmp/rocksdb"
+			gateway_default_bls_key = "0xb3a22e4a673ac7a153ab5b3c17a4dbef55f7e47210b20c0cbb0e66df5b36bb49ef808577610b034172e955d2312a61b9"
+			gateway_proxy_key_dir = "./data/proxy"
+			gateway_keys_path = "./data/keystores/keys"
+			gateway_secrets_path = "./data/keystores/secrets"
+			proposer_consensus_key = "0x883827193f7627cd04e621e1e8d56498362a52b2a30c9a1c72036eb935c4278dee23d38a24d2f7dda62689886f0c39f4"
+			proposer_proxy_key_dir = "./data/proxy"
+			proposer_keys_path = "/tmp/keystores/keys"
+			proposer_secrets_path = "/tmp/keystores/secrets"
+			relay_bls_key = "0xafa4c6985aa049fb79dd37010438cfebeb0f2bd42b115b89dd678dab0670c1de38da0c4e9138c9290a398ecd9a0b3110"
+			proposer_signer_host = "127.0.0.1"
+			proposer_signer_port = 20000
+			gateway_signer_host = "127.0.0.1"
+			gateway_signer_port = 20001
+			beacon_host = "127.0.0.1"
+			beacon_port = 5052
+			execution_client_host = "127.0.0.1"
+			execution_client_port = 8545
+			gateway_host = "127.0.0.1"
+			gateway_port = 8080
+			gateway_metrics_host = "127.0.0.1"
+			gateway_metrics_port = 8002
+			relay_host = "127.0.0.1"
+			relay_port = 9998
+			delegation_check_interval_seconds = 6
+			constraints_receivers = []
+			lookahead_check_interval_seconds = 12
+			lookahead_update_interval = 30
+			downstream_relay_host = "127.0.0.1"
+			downstream_relay_port = 18551
+			spammer_mode = "continuous"
+			slasher_address = "0x1234567890123456789012345678901234567890"
+			"#;
+
+	pub(crate) fn simulation_config() -> SimulationConfig {
+		toml::from_str(SIMULATION_CONFIG).unwrap()
+	}
+
+	fn simulation(target: Target) -> Simulation {
+		simulation_with(simulation_config(), target)
+	}
+
+	pub(crate) fn simulation_with(config: SimulationConfig, target: Target) -> Simulation {
+		let secrets = SimulationSecrets::generate(&config);
+		let setup = SimulationSetup::new(config, target, secrets).unwrap();
+		let keys = PROXY_KEYS
+			.iter()
+			.cycle()
+			.zip(ADDRESSES.iter().cycle())
+			.take(setup.gateways.len())
+			.map(|(bls_proxy, address)| GatewayKeys {
+				bls_proxy: bls_proxy.to_string(),
+				committer_address: address.to_string(),
+			})
+			.collect();
+		setup.with_gateway_keys(keys).unwrap()
+	}
+
+	pub(crate) fn multi_gateway_config() -> SimulationConfig {
+		toml::from_str(&format!(
+			r#"
+			{}
+			delegation_rule = "percentage_split"
+			delegation_quorum = 2
+
+			[[additional_relays]]
+			name = "relay-2"
+			port = 9999
+
+			[[additional_gateways]]
+			name = "gateway-2"
+			module_signing_id = "0x3333333333333333333333333333333333333333333333333333333333333333"
+			default_bls_key = "{}"
+			rpc_port = 8081
+			metrics_port = 8003
+			relay = "relay-2"
+			weight = 3
+
+			[[additional_gateways]]
+			name = "gateway-3"
+			module_signing_id = "0x4444444444444444444444444444444444444444444444444444444444444444"
+			default_bls_key = "{}"
+			rpc_port = 8082
+			metrics_port = 8004
+			"#,
+			SIMULATION_CONFIG, PROXY_KEYS[1], PROXY_KEYS[2]
+		))
+		.unwrap()
+	}
+
+	fn write_rendered<T: Serialize>(dir: &tempfile::TempDir, name: &str, config: &T) -> PathBuf {
+		let path = dir.path().join(name);
+		std::fs::write(&path, render_toml(config).unwrap()).unwrap();
+		path
+	}
+
+	#[test]
+	fn test_generated_service_configs_load_and_validate() {
+		let simulation = simulation(Target::Local);
+		let dir = tempfile::tempdir().unwrap();
+
+		let path = write_rendered(&dir, "gateway.toml", &simulation.gateway_config(0));
+		let gateway: GatewayConfig = config::load_module(&path, "gateway-module", GATEWAY_ENV_PREFIX).unwrap();
+		assert_eq!(gateway.gateway_public_key, PROXY_KEY);
+		assert_eq!(gateway.db_path, "/tmp/rocksdb/gateway");
+
+		let path = write_rendered(&dir, "proposer.toml", &simulation.proposer_config());
+		let proposer: ProposerConfig = config::load_module(&path, "proposer-module", PROPOSER_ENV_PREFIX).unwrap();
+		assert_eq!(proposer.gateway_public_key.as_deref(), Some(PROXY_KEY));
+		assert_eq!(proposer.relay_port, 9998);
+
+		let path = write_rendered(&dir, "relay.toml", &simulation.relay_config(0));
+		let relay: RelayConfig = config::load_file(&path, RELAY_ENV_PREFIX).unwrap();
+		assert_eq!(relay.downstream_relay_port, 18551);
+		assert_eq!(relay.chain.id(), 3151908);
+		assert_eq!(relay.constraint_capabilities.len(), 5);
+	}
+
+	#[test]
+	fn test_signer_config_lists_both_modules() {
+		let simulation = simulation(Target::Local);
+		let rendered = render_toml(&simulation.setup.signer_config(SignerRole::Proposer)).unwrap();
+		let table: toml::Table = toml::from_str(&rendered).unwrap();
+
+		assert_eq!(table["signer"]["port"].as_integer(), Some(20000));
+		assert_eq!(table["signer"]["local"]["loader"]["keys_path"].as_str(), Some("/tmp/keystores/keys"));
+		let modules = table["modules"].as_array().unwrap();
+		let ids = modules.iter().map(|module| module["id"].as_str().unwrap()).collect::<Vec<_>>();
+		assert_eq!(ids, ["gateway-module", "proposer-module"]);
+		assert_eq!(modules[0]["type"].as_str(), Some("commit"));
+		assert!(table["relays"][0]["url"].as_str().unwrap().contains("@127.0.0.1:9998"));
+	}
+
+	#[test]
+	fn test_docker_target_uses_container_paths_and_hosts() {
+		let local = simulation(Target::Local);
+		let docker = simulation(Target::Docker);
+
+		let env = |simulation: &Simulation, path: &Path| {
+			simulation.setup.env_files().into_iter().find(|(env_path, _)| env_path == path).unwrap().1
+		};
+		let local_env = env(&local, &local.setup.paths.gateway_env);
+		assert_eq!(local_env.get("CB_CONFIG"), Some("config/simulation/gateway.toml"));
+		assert_eq!(local_env.get("CB_SIGNER_URL"), Some("http://127.0.0.1:20001/"));
+		let docker_env = env(&docker, &docker.setup.paths.gateway_env);
+		assert_eq!(docker_env.get("CB_CONFIG"), Some("config.toml"));
+		assert_eq!(docker_env.get("CB_SIGNER_URL"), Some("http://gateway-signer:20001"));
+
+		assert_eq!(local.spammer_config().gateway_host, "127.0.0.1");
+		assert_eq!(docker.spammer_config().gateway_host, "gateway");
+		assert_eq!(docker.relay_mock_config().builder.relay_host, "constraints-server");
+		assert_eq!(docker.relay_mock_config().host, "0.0.0.0");
+	}
+
+	#[test]
+	fn test_env_files_share_the_module_jwts() {
+		let simulation = simulation(Target::Local);
+		let envs = simulation.setup.env_files();
+		let get = |path: &Path, name: &str| {
+			envs.iter().find(|(env_path, _)| env_path == path).unwrap().1.get(name).unwrap().to_string()
+		};
+		let paths = &simulation.setup.paths;
+
+		let jwts = get(&paths.gateway_signer_env, "CB_JWTS");
+		assert_eq!(jwts, get(&paths.proposer_signer_env, "CB_JWTS"));
+		assert!(jwts.contains(&format!("gateway-module={}", get(&paths.gateway_env, "CB_SIGNER_JWT"))));
+		assert!(jwts.contains(&format!("proposer-module={}", get(&paths.proposer_env, "CB_SIGNER_JWT"))));
+		assert_eq!(get(&paths.beacon_mock_env, "GENESIS_TIME"), "1767656185");
+		assert_eq!(get(&paths.relay_mock_env, BUILDER_KEY_ENV).len(), 64);
+	}
+
+	#[test]
+	fn test_multi_gateway_and_multi_relay_topology() {
+		let simulation = simulation_with(multi_gateway_config(), Target::Local);
+		let setup = &simulation.setup;
+		let dir = tempfile::tempdir().unwrap();
+
+		let names = setup.gateways.iter().map(|gateway| gateway.name.as_str()).collect::<Vec<_>>();
+		assert_eq!(names, ["gateway-module", "gateway-2", "gateway-3"]);
+		assert_eq!(setup.gateways[1].config_file, Path::new("config/simulation/gateway-2.toml"));
+		assert_eq!(setup.relays[1].db_path, "/tmp/rocksdb/relay-2");
+
+		// Each gateway posts to its own relay, the primary one if it names none
+		let path = write_rendered(&dir, "gateway-2.toml", &simulation.gateway_config(1));
+		let gateway: GatewayConfig = config::load_module(&path, "gateway-2", GATEWAY_ENV_PREFIX).unwrap();
+		assert_eq!(gateway.relay_port, 9999);
+		assert_eq!(gateway.rpc_port, 8081);
+		assert_eq!(gateway.gateway_public_key, PROXY_KEYS[1]);
+		assert_eq!(gateway.db_path, "/tmp/rocksdb/gateway-2");
+		assert_eq!(simulation.gateway_config(2).modules[0].config.relay_port, 9998);
+
+		// The proposer picks among every gateway and posts to every relay
+		let path = write_rendered(&dir, "proposer.toml", &simulation.proposer_config());
+		let proposer: ProposerConfig = config::load_module(&path, "proposer-module", PROPOSER_ENV_PREFIX).unwrap();
+		assert_eq!(proposer.gateway_public_key, None);
+		assert_eq!(proposer.gateways.len(), 3);
+		assert_eq!(proposer.gateways[1].name, "gateway-2");
+		assert_eq!(proposer.gateways[1].weight, 3);
+		assert_eq!(proposer.gateways[2].public_key, PROXY_KEYS[2]);
+		assert_eq!(proposer.additional_relays.len(), 1);
+		assert_eq!(proposer.additional_relays[0].port, 9999);
+		assert_eq!(proposer.delegation_quorum, 2);
+		assert_eq!(proposer.delegation_policy.rule, proposer::policy::DelegationRule::PercentageSplit);
+
+		let path = write_rendered(&dir, "relay-2.toml", &simulation.relay_config(1));
+		let relay: RelayConfig = config::load_file(&path, RELAY_ENV_PREFIX).unwrap();
+		assert_eq!(relay.port, 9999);
+		assert_eq!(relay.db_path, "/tmp/rocksdb/relay-2");
+
+		// One signer serves every gateway module
+		let envs = setup.env_files();
+		let jwts = envs.iter().find(|(path, _)| path == &setup.paths.gateway_signer_env).unwrap().1.get("CB_JWTS");
+		assert!(jwts.unwrap().contains(&format!("gateway-3={}", setup.gateways[2].jwt)));
+		assert!(envs.iter().any(|(path, _)| path == Path::new("config/simulation/relay-2.env")));
+		assert_eq!(setup.signer_config(SignerRole::Gateway).modules.len(), 4);
+	}
+
+	#[test]
+	fn test_topology_validation() {
+		let mut config = multi_gateway_config();
+		assert!(config.check("test").is_ok());
+
+		config.additional_gateways[1].name = "gateway-2".to_string();
+		config.additional_gateways[1].relay = Some("relay-3".to_string());
+		config.additional_relays[0].port = 8081;
+		config.delegation_quorum = Some(3);
+		let error = config.check("test").unwrap_err().to_string();
+		assert!(error.contains("additional_gateways[1].name: 'gateway-2' is already used"), "{}", error);
+		assert!(error.contains("additional_gateways[1].relay"), "{}", error);
+		assert!(error.contains("additional_gateways[0].rpc_port: port 8081 is already used"), "{}", error);
+		assert!(error.contains("delegation_quorum"), "{}", error);
+
+		let mut config = multi_gateway_config();
+		config.additional_relays[0].name = "spammer".to_string();
+		let error = config.check("test").unwrap_err().to_string();
+		assert!(error.contains("'spammer' is reserved"), "{}", error);
+	}
+}
@@ -0,0 +1,316 @@
+//! Typed shapes of the files generated for each service, serialized with serde
+use cb_common::types::Chain;
+use eyre::{Result, WrapErr};
+use serde::Serialize;
+
+/// First line of every generated file
+pub(crate) const GENERATED_HEADER: &str = "# This file is automatically generated by the simulation-setup binary\n";
+
+/// Render a config as TOML under the generated file header
+pub fn render_toml<T: Serialize>(config: &T) -> Result<String> {
+	let body = toml::to_string(config).wrap_err("Failed to serialize generated config")?;
+	Ok(format!("{}\n{}", GENERATED_HEADER, body))
+}
+
+/// Render a config as YAML under the generated file header
+pub fn render_yaml<T: Serialize>(config: &T) -> Result<String> {
+	let body = serde_yaml::to_string(config).wrap_err("Failed to serialize generated config")?;
+	Ok(format!("{}\n{}", GENERATED_HEADER, body))
+}
+
+/// A chain as the config loaders read it: a known network by name or the spec of a custom one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ChainSpec {
+	Named(&'static str),
+	Custom { genesis_time_secs: u64, slot_time_secs: u64, genesis_fork_version: String, chain_id: u64 },
+}
+
+impl From<&Chain> for ChainSpec {
+	fn from(chain: &Chain) -> Self {
+		match chain {
+			Chain::Mainnet => ChainSpec::Named("Mainnet"),
+			Chain::Holesky => ChainSpec::Named("Holesky"),
+			Chain::Sepolia => ChainSpec::Named("Sepolia"),
+			Chain::Helder => ChainSpec::Named("Helder"),
+			Chain::Hoodi => ChainSpec::Named("Hoodi"),
+			Chain::Custom { genesis_time_secs, slot_time_secs, genesis_fork_version, chain_id } => ChainSpec::Custom {
+				genesis_time_secs: *genesis_time_secs,
+				slot_time_secs: *slot_time_secs,
+				genesis_fork_version: format!("0x{}", alloy::hex::encode(genesis_fork_version)),
+				chain_id: *chain_id,
+			},
+		}
+	}
+}
+
+/// Commit-Boost config shared by the signers and the modules, with the modules it starts
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitBoostConfig<T> {
+	pub chain: ChainSpec,
+	pub pbs: PbsConfig,
+	/// Relays of the PBS module, required by the Commit-Boost config structure
+	pub relays: Vec<RelayEntry>,
+	pub metrics: MetricsConfig,
+	pub signer: SignerConfig,
+	pub modules: Vec<ModuleConfig<T>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PbsConfig {
+	pub port: u16,
+	pub with_signer: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayEntry {
+	pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsConfig {
+	pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignerConfig {
+	pub host: String,
+	pub port: u16,
+	pub local: LocalSignerConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalSignerConfig {
+	pub loader: KeyLoaderConfig,
+	pub store: ProxyStoreConfig,
+}
+
+/// Lighthouse keystores the local signer loads its consensus keys from
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyLoaderConfig {
+	pub format: String,
+	pub keys_path: String,
+	pub secrets_path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyStoreConfig {
+	pub proxy_dir: String,
+}
+
+/// A Commit-Boost module entry, the module's own config flattened into it
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleConfig<T> {
+	pub id: String,
+	pub signing_id: String,
+	#[serde(rename = "type")]
+	pub kind: String,
+	pub docker_image: String,
+	pub env_file: String,
+	#[serde(flatten)]
+	pub config: T,
+}
+
+impl<T> ModuleConfig<T> {
+	/// A commit module run outside of Commit-Boost's docker setup
+	pub fn commit(id: &str, signing_id: &str, config: T) -> Self {
+		Self {
+			id: id.to_string(),
+			signing_id: signing_id.to_string(),
+			kind: "commit".to_string(),
+			docker_image: "n/a".to_string(),
+			env_file: "n/a".to_string(),
+			config,
+		}
+	}
+}
+
+/// Module entry of a signer config, which only needs the module's identity
+#[derive(Debug, Clone, Serialize)]
+pub struct NoModuleConfig {}
+
+/// The gateway module's config, `inclusion::gateway::config::GatewayConfig`
+#[derive(Debug, Clone, Serialize)]
+pub struct GatewayModuleConfig {
+	pub rpc_host: String,
+	pub rpc_port: u16,
+	pub metrics_host: String,
+	pub metrics_port: u16,
+	pub db_path: String,
+	pub relay_host: String,
+	pub relay_port: u16,
+	pub execution_client_host: String,
+	pub execution_client_port: u16,
+	pub constraints_receivers: Vec<String>,
+	pub module_signing_id: String,
+	pub log_level: String,
+	pub delegation_check_interval_seconds: u64,
+	pub gateway_public_key: String,
+}
+
+/// The proposer module's config, `proposer::config::ProposerConfig`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProposerModuleConfig {
+	pub db_path: String,
+	/// Single gateway delegated to, unset when `gateways` lists several
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub gateway_public_key: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub gateway_address: Option<String>,
+	pub relay_host: String,
+	pub relay_port: u16,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub delegation_quorum: Option<usize>,
+	pub beacon_api_host: String,
+	pub beacon_api_port: u16,
+	pub lookahead_check_interval_seconds: u64,
+	pub module_signing_id: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub health_host: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub health_port: Option<u16>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub gateways: Vec<ProposerGatewayEntry>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub delegation_policy: Option<DelegationPolicyEntry>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub additional_relays: Vec<RelayEndpointEntry>,
+}
+
+/// A gateway the proposer's delegation policy picks from
+#[derive(Debug, Clone, Serialize)]
+pub struct ProposerGatewayEntry {
+	pub name: String,
+	pub public_key: String,
+	pub address: String,
+	pub weight: u64,
+	pub bid_gwei: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DelegationPolicyEntry {
+	pub rule: String,
+}
+
+/// A further relay the proposer posts its delegations to
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayEndpointEntry {
+	pub host: String,
+	pub port: u16,
+}
+
+/// The relay's config, `inclusion::relay::config::RelayConfig`
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayServiceConfig {
+	pub chain: ChainSpec,
+	pub host: String,
+	pub port: u16,
+	pub db_path: String,
+	pub constraint_capabilities: Vec<u64>,
+	pub beacon_api_host: String,
+	pub beacon_api_port: u16,
+	pub lookahead_update_interval: u64,
+	pub downstream_relay_host: String,
+	pub downstream_relay_port: u16,
+	pub receipt_signing_key: String,
+}
+
+/// The spammer's config
+#[derive(Debug, Clone, Serialize)]
+pub struct SpammerServiceConfig {
+	pub mode: String,
+	pub chain: ChainSpec,
+	pub gateway_host: String,
+	pub gateway_port: u16,
+	pub execution_client_host: String,
+	pub execution_client_port: u16,
+	pub slasher_address: String,
+}
+
+/// The relay mock's config, a downstream relay with a builder posting back to the fabric relay
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayMockServiceConfig {
+	pub chain: ChainSpec,
+	pub host: String,
+	pub port: u16,
+	pub relay_public_key: String,
+	pub builder: MockBuilderServiceConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MockBuilderServiceConfig {
+	pub relay_host: String,
+	pub relay_port: u16,
+	pub builder_key_env: String,
+}
+
+/// Variables of a service's .env file, in order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvFile {
+	vars: Vec<(&'static str, String)>,
+}
+
+impl EnvFile {
+	pub fn var(mut self, name: &'static str, value: impl ToString) -> Self {
+		self.vars.push((name, value.to_string()));
+		self
+	}
+
+	pub fn vars(&self) -> impl Iterator<Item = (&'static str, &str)> {
+		self.vars.iter().map(|(name, value)| (*name, value.as_str()))
+	}
+
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.as_str())
+	}
+
+	pub fn render(&self) -> String {
+		let mut content =
+			String::from("# Simulation environment variables\n# Generated by simulation-setup binary\n\n");
+		for (name, value) in &self.vars {
+			content.push_str(&format!("{}={}\n", name, value));
+		}
+		content
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde::Deserialize;
+
+	#[derive(Serialize)]
+	struct ChainOut {
+		chain: ChainSpec,
+	}
+
+	#[derive(Deserialize)]
+	struct ChainIn {
+		chain: Chain,
+	}
+
+	#[test]
+	fn test_chain_spec_reads_back_as_the_same_chain() {
+		let custom = Chain::Custom {
+			genesis_time_secs: 1_767_656_185,
+			slot_time_secs: 6,
+			genesis_fork_version: [0x10, 0, 0, 0x38],
+			chain_id: 3_151_908,
+		};
+		for chain in [Chain::Mainnet, Chain::Hoodi, custom] {
+			let rendered = render_toml(&ChainOut { chain: ChainSpec::from(&chain) }).unwrap();
+			let read: ChainIn = toml::from_str(&rendered).unwrap();
+			assert_eq!(read.chain.id(), chain.id());
+			assert_eq!(read.chain.genesis_time_sec(), chain.genesis_time_sec());
+			assert_eq!(read.chain.slot_time_sec(), chain.slot_time_sec());
+		}
+	}
+
+	#[test]
+	fn test_env_file_renders_variables_in_order() {
+		let env = EnvFile::default().var("CONFIG_PATH", "config.toml").var("RUST_LOG", "info");
+		assert_eq!(env.get("RUST_LOG"), Some("info"));
+		assert_eq!(env.get("CB_CONFIG"), None);
+		assert!(env.render().ends_with("\n\nCONFIG_PATH=config.toml\nRUST_LOG=info\n"));
+	}
+}
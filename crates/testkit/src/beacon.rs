@@ -0,0 +1,27 @@
+//! Mock beacon node serving the proposer duties of a single proposer
+use alloy::primitives::B256;
+use alloy::rpc::types::beacon::BlsPublicKey;
+use axum::extract::{Path, State};
+use axum::routing::get;
+use axum::{Json, Router};
+use lookahead::constants::PROPOSER_DUTIES_ROUTE;
+use lookahead::types::{ProposerDutiesResponse, ValidatorDuty};
+use lookahead::utils::{epoch_to_first_slot, epoch_to_last_slot};
+
+/// Router of a beacon node whose every slot is proposed by `proposer`
+/// Other beacon endpoints are not served, the services fall back to the chain's timing without them
+pub fn router(proposer: BlsPublicKey) -> Router {
+	Router::new().route(&format!("/{}/{{epoch}}", PROPOSER_DUTIES_ROUTE), get(proposer_duties)).with_state(proposer)
+}
+
+async fn proposer_duties(Path(epoch): Path<u64>, State(proposer): State<BlsPublicKey>) -> Json<ProposerDutiesResponse> {
+	let data = (epoch_to_first_slot(epoch)..=epoch_to_last_slot(epoch))
+		.map(|slot| ValidatorDuty {
+			validator_index: slot.to_string(),
+			pubkey: proposer.to_string(),
+			slot: slot.to_string(),
+		})
+		.collect();
+
+	Json(ProposerDutiesResponse { execution_optimistic: false, dependent_root: B256::ZERO, data })
+}
@@ -0,0 +1,45 @@
+//! Mock downstream relay recording the requests the relay forwards to it
+use std::sync::{Arc, Mutex};
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::http::{Method, StatusCode, Uri};
+use constraints::routes::LEGACY_SUBMIT_BLOCK;
+
+/// A request received by the mock downstream relay
+#[derive(Debug, Clone)]
+pub struct ForwardedRequest {
+	pub method: Method,
+	pub path: String,
+	pub body: Bytes,
+}
+
+/// Downstream relay that answers every request with 200 OK and keeps it
+#[derive(Clone, Default)]
+pub struct MockDownstreamRelay {
+	requests: Arc<Mutex<Vec<ForwardedRequest>>>,
+}
+
+impl MockDownstreamRelay {
+	pub fn router(&self) -> Router {
+		let requests = Arc::clone(&self.requests);
+		Router::new().fallback(move |method: Method, uri: Uri, body: Bytes| {
+			let requests = Arc::clone(&requests);
+			async move {
+				let request = ForwardedRequest { method, path: uri.path().to_string(), body };
+				requests.lock().expect("downstream requests lock poisoned").push(request);
+				StatusCode::OK
+			}
+		})
+	}
+
+	/// Every request received so far, health checks included
+	pub fn requests(&self) -> Vec<ForwardedRequest> {
+		self.requests.lock().expect("downstream requests lock poisoned").clone()
+	}
+
+	/// Blocks submitted through the builder API, in order
+	pub fn submitted_blocks(&self) -> Vec<ForwardedRequest> {
+		self.requests().into_iter().filter(|request| request.path == LEGACY_SUBMIT_BLOCK).collect()
+	}
+}
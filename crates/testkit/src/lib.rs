@@ -0,0 +1,18 @@
+//! In-process test network of the fabric services
+//!
+//! [`TestNet`] starts a relay, a gateway and a proposer in the test's own runtime, each with a temporary database
+//! and a free local port, against a mock beacon node and a mock downstream relay. Keys are held by local signers
+//! instead of a Commit-Boost signer service. Flows otherwise only covered by the docker simulation, from the
+//! delegation to the block with proofs, can then be asserted in `cargo test`:
+//!
+//! ```ignore
+//! let net = TestNet::new().with_relay().with_gateway().with_proposer().start().await?;
+//! net.delegate().await?;
+//! let slot = net.next_delegated_slot()?;
+//! net.wait_for_gateway_delegation(slot).await?;
+//! ```
+pub mod beacon;
+pub mod downstream;
+pub mod net;
+
+pub use net::{OFFER_BID_GWEI, RunningTestNet, TestNet};
@@ -0,0 +1,580 @@
+//! Builder and handle of the in-process test network
+use std::future::Future;
+use std::net::TcpListener as StdTcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::consensus::{SignableTransaction, TxEip1559, TxEnvelope};
+use alloy::network::TxSignerSync;
+use alloy::node_bindings::{Anvil, AnvilInstance};
+use alloy::primitives::{Address, B256, Bytes, TxKind, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::beacon::BlsPublicKey;
+use alloy::rpc::types::beacon::relay::SubmitBlockRequest;
+use alloy::signers::local::PrivateKeySigner;
+use axum::Router;
+use cb_common::commit::client::SignerClient;
+use cb_common::types::{BlsSecretKey, Jwt, ModuleId};
+use commit_boost::prelude::{Chain, StartCommitModuleConfig};
+use commitments::client::{CommitmentsClientConfig, CommitmentsHttpClient};
+use commitments::server::run_commitments_rpc_server;
+use common::health::health_router;
+use common::storage::{DatabaseContext, create_database};
+use constraints::client::{ConstraintsClient, HttpConstraintsClient};
+use constraints::server::build_constraints_router_with_proxy;
+use constraints::types::{SignedDelegationOffer, SubmitBlockRequestWithProofs};
+use eyre::{Result, eyre};
+use inclusion::constants::{
+	BLOB_CONSTRAINT_TYPE, BUNDLE_CONSTRAINT_TYPE, EXCLUSION_CONSTRAINT_TYPE, INCLUSION_CONSTRAINT_TYPE,
+	ORDERING_CONSTRAINT_TYPE,
+};
+use inclusion::gateway::config::GatewayConfig;
+use inclusion::gateway::services::constraint_manager::ConstraintManager;
+use inclusion::gateway::services::delegation_manager::DelegationManager as GatewayDelegationManager;
+use inclusion::gateway::services::rpc::GatewayRpc;
+use inclusion::gateway::state::GatewayState;
+use inclusion::proofs::prove_signed_constraints;
+use inclusion::relay::config::RelayConfig;
+use inclusion::relay::services::lookahead_manager::LookaheadManager;
+use inclusion::relay::services::server::RelayServer;
+use inclusion::relay::state::RelayState;
+use lookahead::clock::Clock;
+use lookahead::slot_clock::SlotClock;
+use proposer::config::ProposerConfig;
+use proposer::delegation_manager::DelegationManager as ProposerDelegationManager;
+use proposer::state::ProposerState;
+use proposer::storage::DelegationsDbExt;
+use reqwest::Url;
+use serde_json::json;
+use signing::builder::{BuilderSigner, build_block};
+use signing::local::LocalSigner;
+use signing::signer::SigningClient;
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::error;
+
+use crate::beacon;
+use crate::downstream::MockDownstreamRelay;
+
+/// Every service listens on the loopback interface
+const HOST: &str = "127.0.0.1";
+
+/// Module signing IDs of the gateway and the proposer, as in the docker configs
+const GATEWAY_MODULE_SIGNING_ID: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+const PROPOSER_MODULE_SIGNING_ID: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+
+/// How long the helpers wait for a service to catch up
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Slots looked at for a delegated slot, the proposer delegates the current and next epoch
+const DELEGATION_WINDOW_SLOTS: u64 = 64;
+
+/// Bid of the gateway's delegation offers, when enabled
+pub const OFFER_BID_GWEI: u64 = 1_000;
+
+/// Services to start, the mock beacon node and downstream relay are always started
+#[derive(Clone)]
+pub struct TestNet {
+	chain: Chain,
+	clock: Option<Arc<dyn Clock>>,
+	relay: bool,
+	gateway: bool,
+	proposer: bool,
+	anvil: bool,
+	delegation_offers: bool,
+	additional_gateway_key: bool,
+	unreachable_relays: usize,
+	delegation_quorum: Option<usize>,
+}
+
+impl Default for TestNet {
+	fn default() -> Self {
+		Self {
+			chain: Chain::Mainnet,
+			clock: None,
+			relay: false,
+			gateway: false,
+			proposer: false,
+			anvil: false,
+			delegation_offers: false,
+			additional_gateway_key: false,
+			unreachable_relays: 0,
+			delegation_quorum: None,
+		}
+	}
+}
+
+impl TestNet {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Chain whose genesis and signing domains the services use, mainnet by default
+	pub fn with_chain(mut self, chain: Chain) -> Self {
+		self.chain = chain;
+		self
+	}
+
+	/// Clock of every service instead of the host clock, e.g. a `ManualClock` to place them at a slot boundary
+	pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+		self.clock = Some(clock);
+		self
+	}
+
+	pub fn with_relay(mut self) -> Self {
+		self.relay = true;
+		self
+	}
+
+	pub fn with_gateway(mut self) -> Self {
+		self.gateway = true;
+		self
+	}
+
+	pub fn with_proposer(mut self) -> Self {
+		self.proposer = true;
+		self
+	}
+
+	/// Spawn an anvil node as the gateway's execution client
+	/// Without it commitment requests fail, as they are priced against the execution client
+	pub fn with_anvil(mut self) -> Self {
+		self.anvil = true;
+		self
+	}
+
+	/// The gateway offers every undelegated slot for `OFFER_BID_GWEI` and the relay trusts it to. The proposer
+	/// delegates to the best offer and otherwise to a gateway nobody runs, so only offers reach the gateway
+	pub fn with_delegation_offers(mut self) -> Self {
+		self.delegation_offers = true;
+		self
+	}
+
+	/// The gateway holds a second BLS key in `additional_public_keys` and the proposer delegates to that one
+	pub fn with_additional_gateway_key(mut self) -> Self {
+		self.additional_gateway_key = true;
+		self
+	}
+
+	/// The proposer also posts its delegations to `count` relays nobody runs, and counts a slot as delegated once
+	/// `quorum` relays accepted it
+	pub fn with_unreachable_relays(mut self, count: usize, quorum: usize) -> Self {
+		self.unreachable_relays = count;
+		self.delegation_quorum = Some(quorum);
+		self
+	}
+
+	/// Start the selected services and wait until they accept requests
+	pub async fn start(self) -> Result<RunningTestNet> {
+		let chain = self.chain;
+		let dir = tempfile::tempdir()?;
+		let mut tasks = Vec::new();
+		let shutdown = CancellationToken::new();
+
+		// Keys are held in-process, the proposer's by the proposer and the gateway's with the committer key
+		let mut proposer_signer = LocalSigner::new(chain);
+		let proposer_public_key = proposer_signer.add_bls_key(BlsSecretKey::random());
+		let mut gateway_signer = LocalSigner::new(chain);
+		let gateway_public_key = gateway_signer.add_bls_key(BlsSecretKey::random());
+		let delegate_public_key = match self.additional_gateway_key {
+			true => gateway_signer.add_bls_key(BlsSecretKey::random()),
+			false => gateway_public_key,
+		};
+		let committer_address = gateway_signer.add_ecdsa_key(PrivateKeySigner::random());
+
+		// Mocks of the beacon node and of the relay blocks are forwarded to
+		let beacon_port = free_port()?;
+		tasks.push(serve(beacon::router(proposer_public_key), beacon_port).await?);
+		let downstream = MockDownstreamRelay::default();
+		let downstream_port = free_port()?;
+		tasks.push(serve(downstream.router(), downstream_port).await?);
+
+		let anvil = match self.anvil {
+			true => Some(Anvil::new().chain_id(chain.id().to::<u64>()).try_spawn()?),
+			false => None,
+		};
+
+		let relay_port = free_port()?;
+		let relay = match self.relay {
+			true => {
+				let mut config = json!({
+					"chain": chain,
+					"host": HOST,
+					"port": relay_port,
+					"db_path": db_path(&dir, "relay")?,
+					"constraint_capabilities": [
+						INCLUSION_CONSTRAINT_TYPE,
+						EXCLUSION_CONSTRAINT_TYPE,
+						ORDERING_CONSTRAINT_TYPE,
+						BUNDLE_CONSTRAINT_TYPE,
+						BLOB_CONSTRAINT_TYPE,
+					],
+					"beacon_api_host": HOST,
+					"beacon_api_port": beacon_port,
+					"lookahead_update_interval": 1,
+					"downstream_relay_host": HOST,
+					"downstream_relay_port": downstream_port,
+					"receipt_signing_key": B256::random().to_string(),
+				});
+				if self.delegation_offers {
+					config["trusted_delegates"] = json!([gateway_public_key.to_string()]);
+				}
+				let config: RelayConfig = serde_json::from_value(config)?;
+				let state = Arc::new(RelayState::new(open_database(&config.db_path)?, config)?);
+				self.set_clock(&state.slot_clock);
+
+				// Delegations are only accepted for slots in the stored lookahead
+				let lookahead_manager = LookaheadManager::new(Arc::clone(&state));
+				lookahead_manager.process_lookahead().await?;
+				tasks.push(spawn_task("Relay lookahead manager", async move { lookahead_manager.run().await }));
+				tasks.push(
+					serve(
+						build_constraints_router_with_proxy(RelayServer::new(Arc::clone(&state)))
+							.merge(health_router(Arc::clone(&state))),
+						relay_port,
+					)
+					.await?,
+				);
+				Some(state)
+			}
+			false => None,
+		};
+
+		let mut commitments_url = None;
+		let gateway = match self.gateway {
+			true => {
+				// Without anvil the execution client points at a port nothing listens on
+				let execution_port = match &anvil {
+					Some(anvil) => anvil.port(),
+					None => free_port()?,
+				};
+				// The RPC and metrics servers bind ephemeral ports
+				let mut extra = json!({
+					"rpc_host": HOST,
+					"rpc_port": 0,
+					"metrics_host": HOST,
+					"metrics_port": 0,
+					"db_path": db_path(&dir, "gateway")?,
+					"relay_host": HOST,
+					"relay_port": relay_port,
+					"relay_api_key": null,
+					"execution_client_host": HOST,
+					"execution_client_port": execution_port,
+					"beacon_api_url": format!("http://{}:{}", HOST, beacon_port),
+					"constraints_receivers": [],
+					"module_signing_id": GATEWAY_MODULE_SIGNING_ID,
+					"log_level": "info",
+					"delegation_check_interval_seconds": 1,
+					"gateway_public_key": gateway_public_key.to_string(),
+					"committer_address": committer_address,
+				});
+				if self.additional_gateway_key {
+					extra["additional_public_keys"] = json!([delegate_public_key.to_string()]);
+				}
+				if self.delegation_offers {
+					extra["delegation_offer"] =
+						json!({ "bid_gwei": OFFER_BID_GWEI, "committer_address": committer_address });
+				}
+				let extra: GatewayConfig = serde_json::from_value(extra)?;
+				let db = open_database(&extra.db_path)?;
+				let mut state = GatewayState::new(db, module_config(chain, "gateway", extra)?)?;
+				state.signer_client = SigningClient::local(gateway_signer);
+				self.set_clock(&state.slot_clock);
+				let state = Arc::new(state);
+
+				let rpc = GatewayRpc::new(Arc::clone(&state));
+				let rpc_server = run_commitments_rpc_server(rpc, shutdown.clone()).await?;
+				commitments_url = Some(Url::parse(&format!("http://{}", rpc_server.server_addr))?);
+				tasks.push(tokio::spawn(rpc_server.stopped()));
+				let delegation_manager = GatewayDelegationManager::new(Arc::clone(&state));
+				tasks.push(spawn_task("Gateway delegation manager", async move { delegation_manager.run().await }));
+				let constraint_manager = Arc::new(ConstraintManager::new(Arc::clone(&state)));
+				let constraints_task = Arc::clone(&constraint_manager);
+				tasks.push(spawn_task("Gateway constraint manager", async move { constraints_task.run().await }));
+				Some((state, constraint_manager))
+			}
+			false => None,
+		};
+
+		let proposer = match self.proposer {
+			true => {
+				let mut extra = json!({
+					"db_path": db_path(&dir, "proposer")?,
+					"gateway_public_key": delegate_public_key.to_string(),
+					"gateway_address": committer_address.to_string(),
+					"relay_host": HOST,
+					"relay_port": relay_port,
+					"relay_api_key": null,
+					"beacon_api_host": HOST,
+					"beacon_api_port": beacon_port,
+					"lookahead_check_interval_seconds": 1,
+					"module_signing_id": PROPOSER_MODULE_SIGNING_ID,
+				});
+				if self.delegation_offers {
+					let decoy = BlsSecretKey::random().public_key();
+					extra["gateway_public_key"] = json!(BlsPublicKey::new(decoy.serialize()).to_string());
+					extra["gateway_address"] = json!(Address::random().to_string());
+					extra["delegation_offers"] = json!({ "enabled": true, "known_gateways_only": false });
+				}
+				if let Some(quorum) = self.delegation_quorum {
+					let relays = (0..self.unreachable_relays)
+						.map(|_| Ok(json!({ "host": HOST, "port": free_port()? })))
+						.collect::<Result<Vec<_>>>()?;
+					extra["additional_relays"] = json!(relays);
+					extra["delegation_quorum"] = json!(quorum);
+				}
+				let extra: ProposerConfig = serde_json::from_value(extra)?;
+				let db = open_database(&extra.db_path)?;
+				let mut state = ProposerState::new(db, module_config(chain, "proposer", extra)?)?;
+				state.signer_client = SigningClient::local(proposer_signer);
+				self.set_clock(&state.slot_clock);
+				Some(Arc::new(state))
+			}
+			false => None,
+		};
+
+		let net = RunningTestNet {
+			chain,
+			relay_port,
+			proposer_public_key,
+			gateway_public_key,
+			delegate_public_key,
+			committer_address,
+			builder: BuilderSigner::new(BlsSecretKey::random(), chain),
+			downstream,
+			relay,
+			gateway,
+			proposer,
+			anvil,
+			commitments_url,
+			tasks,
+			shutdown,
+			_dir: dir,
+		};
+		if net.relay.is_some() {
+			eventually("the relay", DEFAULT_TIMEOUT, || async {
+				net.relay_client().health_check().await.unwrap_or(false)
+			})
+			.await?;
+		}
+		Ok(net)
+	}
+
+	fn set_clock(&self, slot_clock: &SlotClock) {
+		if let Some(clock) = &self.clock {
+			slot_clock.set_clock(Arc::clone(clock));
+		}
+	}
+}
+
+/// Running test network, every service is stopped and its database removed on drop
+pub struct RunningTestNet {
+	pub chain: Chain,
+	relay_port: u16,
+	/// Proposer of every slot, its key is held by the proposer service
+	pub proposer_public_key: BlsPublicKey,
+	/// Gateway the proposer delegates to
+	pub gateway_public_key: BlsPublicKey,
+	/// Key of the gateway the proposer delegates to, `gateway_public_key` unless an additional key was added
+	pub delegate_public_key: BlsPublicKey,
+	/// Committer of the delegations, signing the gateway's commitments
+	pub committer_address: Address,
+	/// Builder signing the submitted blocks
+	pub builder: BuilderSigner,
+	/// Relay the blocks accepted by the relay are forwarded to
+	pub downstream: MockDownstreamRelay,
+	pub relay: Option<Arc<RelayState>>,
+	pub gateway: Option<(Arc<GatewayState>, Arc<ConstraintManager>)>,
+	pub proposer: Option<Arc<ProposerState>>,
+	pub anvil: Option<AnvilInstance>,
+	/// Bound address of the gateway's Commitments RPC server
+	commitments_url: Option<Url>,
+	tasks: Vec<JoinHandle<()>>,
+	/// Stops the servers that shut down gracefully, the other tasks are aborted
+	shutdown: CancellationToken,
+	_dir: TempDir,
+}
+
+impl RunningTestNet {
+	/// Client of the relay, available whether or not the relay was started
+	pub fn relay_client(&self) -> HttpConstraintsClient {
+		HttpConstraintsClient::new(HOST.to_string(), self.relay_port, None).expect("Failed to create the relay client")
+	}
+
+	/// Client of the gateway's Commitments RPC server
+	pub fn commitments_client(&self) -> Result<CommitmentsHttpClient> {
+		let url = self.commitments_url.clone().ok_or_else(|| eyre!("The gateway was not started"))?;
+		Ok(CommitmentsHttpClient::new(CommitmentsClientConfig::new(url))?)
+	}
+
+	/// Sign and post the delegations of the proposer's upcoming slots, as its lookahead task does
+	pub async fn delegate(&self) -> Result<()> {
+		ProposerDelegationManager::new(Arc::clone(self.proposer()?)).process_lookahead().await
+	}
+
+	/// First slot delegated by the proposer that is still far enough ahead to take commitments
+	pub fn next_delegated_slot(&self) -> Result<u64> {
+		let proposer = self.proposer()?;
+		let first_slot = proposer.slot_clock.current_slot() + 2;
+		for slot in first_slot..first_slot + DELEGATION_WINDOW_SLOTS {
+			if proposer.db.is_delegated(slot)? {
+				return Ok(slot);
+			}
+		}
+		Err(eyre!("No slot delegated from slot {}", first_slot))
+	}
+
+	/// Current slot of the relay's clock
+	pub fn current_slot(&self) -> Result<u64> {
+		let relay = self.relay.as_ref().ok_or_else(|| eyre!("The relay was not started"))?;
+		Ok(relay.slot_clock.current_slot())
+	}
+
+	/// Wait until the relay serves the gateway's delegation offer for `slot`
+	pub async fn wait_for_delegation_offer(&self, slot: u64) -> Result<SignedDelegationOffer> {
+		let client = self.relay_client();
+		eventually(&format!("the delegation offer of slot {}", slot), DEFAULT_TIMEOUT, || async {
+			client.get_delegation_offers(slot).await.is_ok_and(|offers| !offers.is_empty())
+		})
+		.await?;
+		let offers = client.get_delegation_offers(slot).await?;
+		offers
+			.into_iter()
+			.find(|offer| offer.message.delegate == self.gateway_public_key)
+			.ok_or_else(|| eyre!("No delegation offer of the gateway for slot {}", slot))
+	}
+
+	/// Wait until the gateway has stored the delegation of `slot`
+	pub async fn wait_for_gateway_delegation(&self, slot: u64) -> Result<()> {
+		let (gateway, _) = self.gateway()?;
+		eventually(&format!("the gateway delegation of slot {}", slot), DEFAULT_TIMEOUT, || async {
+			gateway.db.get_delegation(slot).is_ok_and(|delegation| delegation.is_some())
+		})
+		.await
+	}
+
+	/// Post the gateway's constraints of `slot` to the relay without waiting for the trigger time
+	pub async fn post_constraints(&self, slot: u64) -> Result<()> {
+		let (_, constraint_manager) = self.gateway()?;
+		constraint_manager.force_post(slot).await
+	}
+
+	/// A block of `transactions` for `slot`, signed by the test builder
+	pub fn build_block(&self, slot: u64, transactions: &[TxEnvelope]) -> SubmitBlockRequest {
+		build_block(slot, self.proposer_public_key, transactions, &self.builder)
+	}
+
+	/// Prove the constraints the relay serves for `slot` against a block of `transactions` and submit it
+	pub async fn submit_block(&self, slot: u64, transactions: &[TxEnvelope]) -> Result<()> {
+		let client = self.relay_client();
+		let signed_constraints = client.get_constraints(slot).await?;
+		let message = self.build_block(slot, transactions);
+		let proofs = prove_signed_constraints(&message, &signed_constraints)?.into_proofs()?;
+		client.post_blocks_with_proofs(&SubmitBlockRequestWithProofs { message, proofs }).await?;
+		Ok(())
+	}
+
+	/// A transfer from a funded anvil account paying the committer, priced for the next block
+	pub async fn signed_transfer(&self, value: U256) -> Result<TxEnvelope> {
+		let anvil = self.anvil.as_ref().ok_or_else(|| eyre!("Anvil was not started"))?;
+		let signer = PrivateKeySigner::from(anvil.keys()[0].clone());
+		let provider = ProviderBuilder::new().connect_http(anvil.endpoint_url());
+
+		let nonce = provider.get_transaction_count(signer.address()).await?;
+		let max_priority_fee_per_gas = 1_000_000_000;
+		let max_fee_per_gas = provider.get_gas_price().await? * 2 + max_priority_fee_per_gas;
+		let mut tx = TxEip1559 {
+			chain_id: anvil.chain_id(),
+			nonce,
+			gas_limit: 21_000,
+			max_fee_per_gas,
+			max_priority_fee_per_gas,
+			to: TxKind::Call(self.committer_address),
+			value,
+			access_list: Default::default(),
+			input: Bytes::new(),
+		};
+		let signature = signer.sign_transaction_sync(&mut tx)?;
+		Ok(TxEnvelope::Eip1559(tx.into_signed(signature)))
+	}
+
+	fn gateway(&self) -> Result<&(Arc<GatewayState>, Arc<ConstraintManager>)> {
+		self.gateway.as_ref().ok_or_else(|| eyre!("The gateway was not started"))
+	}
+
+	fn proposer(&self) -> Result<&Arc<ProposerState>> {
+		self.proposer.as_ref().ok_or_else(|| eyre!("The proposer was not started"))
+	}
+}
+
+impl Drop for RunningTestNet {
+	fn drop(&mut self) {
+		self.shutdown.cancel();
+		for task in &self.tasks {
+			task.abort();
+		}
+	}
+}
+
+/// Poll `check` until it returns true, failing after `timeout`
+pub async fn eventually<F, Fut>(what: &str, timeout: Duration, mut check: F) -> Result<()>
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = bool>,
+{
+	let deadline = tokio::time::Instant::now() + timeout;
+	loop {
+		if check().await {
+			return Ok(());
+		}
+		if tokio::time::Instant::now() >= deadline {
+			return Err(eyre!("Timed out after {:?} waiting for {}", timeout, what));
+		}
+		tokio::time::sleep(POLL_INTERVAL).await;
+	}
+}
+
+/// Module config as Commit-Boost would load it
+/// Its signer client is never called, the services sign with local signers
+fn module_config<T>(chain: Chain, module_id: &str, extra: T) -> Result<StartCommitModuleConfig<T>> {
+	let id = ModuleId(module_id.to_string());
+	let signer_url = Url::parse(&format!("http://{}:{}", HOST, free_port()?))?;
+	let signer_client = SignerClient::new(signer_url, None, Jwt(String::new()), id.clone())?;
+	Ok(StartCommitModuleConfig { id, chain, signer_client, extra })
+}
+
+fn db_path(dir: &TempDir, name: &str) -> Result<String> {
+	let path = dir.path().join(name);
+	path.to_str().map(str::to_string).ok_or_else(|| eyre!("Invalid database path {}", path.display()))
+}
+
+fn open_database(path: &str) -> Result<DatabaseContext> {
+	create_database(path).map_err(|e| eyre!("Failed to create database: {}", e))
+}
+
+/// A port nothing listens on, released for the service about to bind it
+fn free_port() -> Result<u16> {
+	Ok(StdTcpListener::bind((HOST, 0))?.local_addr()?.port())
+}
+
+async fn serve(router: Router, port: u16) -> Result<JoinHandle<()>> {
+	let listener = tokio::net::TcpListener::bind((HOST, port)).await?;
+	Ok(tokio::spawn(async move {
+		if let Err(e) = axum::serve(listener, router).await {
+			error!("Test server on port {} exited with error: {}", port, e);
+		}
+	}))
+}
+
+fn spawn_task<F>(name: &'static str, task: F) -> JoinHandle<()>
+where
+	F: Future<Output = Result<()>> + Send + 'static,
+{
+	tokio::spawn(async move {
+		if let Err(e) = task.await {
+			error!("{} exited with error: {:?}", name, e);
+		}
+	})
+}
@@ -0,0 +1,191 @@
+use alloy::primitives::B256;
+use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
+use cb_common::types::BlsSecretKey;
+use constraints::types::{ReceiverUpdate, SignedReceiverUpdate};
+use eyre::Result;
+use fabric_testkit::{RunningTestNet, TestNet};
+use inclusion::gateway::services::admin::{
+	ADMIN_PAUSE, ADMIN_RECEIVERS, ADMIN_RELAY, ADMIN_RESUME, AddReceiverRequest, RECEIVER_UPDATE, ReceiversResponse,
+	RelayResponse, RelayRotationRequest, SlotReceiversRequest, SlotReceiversResponse, build_admin_router,
+};
+use inclusion::storage::InclusionDbExt;
+use inclusion::types::RelayEndpoint;
+use reqwest::StatusCode;
+use signing::local::LocalSigner;
+use urc::utils::get_receiver_update_signing_root;
+
+const API_KEY: &str = "admin-secret";
+
+/// Serve the gateway's admin API on a free local port, returning its base URL
+async fn serve_admin(net: &RunningTestNet) -> Result<String> {
+	let (state, constraint_manager) = net.gateway.clone().ok_or_else(|| eyre::eyre!("gateway not started"))?;
+	let router = build_admin_router(state, constraint_manager, API_KEY.to_string());
+	let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+	let addr = listener.local_addr()?;
+	tokio::spawn(async move { axum::serve(listener, router).await });
+	Ok(format!("http://{addr}"))
+}
+
+#[tokio::test]
+async fn test_admin_requires_the_api_key() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().start().await?;
+	let url = serve_admin(&net).await?;
+	let client = reqwest::Client::new();
+
+	let response = client.get(format!("{url}{ADMIN_RELAY}")).send().await?;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let response = client.get(format!("{url}{ADMIN_RELAY}")).bearer_auth("admin-secreT").send().await?;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let response = client.get(format!("{url}{ADMIN_RELAY}")).bearer_auth("admin").send().await?;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let response = client.get(format!("{url}{ADMIN_RELAY}")).header("Authorization", API_KEY).send().await?;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let response = client.get(format!("{url}{ADMIN_RELAY}")).bearer_auth(API_KEY).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	let relay: RelayResponse = response.json().await?;
+	assert!(relay.accepting_commitments);
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_admin_pause_is_persisted() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().start().await?;
+	let url = serve_admin(&net).await?;
+	let client = reqwest::Client::new();
+	let (gateway, _) = net.gateway.as_ref().unwrap();
+
+	let response = client.post(format!("{url}{ADMIN_PAUSE}")).bearer_auth(API_KEY).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(!gateway.is_accepting_commitments());
+	assert!(gateway.db.get_admin_overrides()?.paused);
+
+	let response = client.post(format!("{url}{ADMIN_RESUME}")).bearer_auth(API_KEY).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(gateway.is_accepting_commitments());
+	assert!(!gateway.db.get_admin_overrides()?.paused);
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_admin_relay_rotation_is_persisted() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().start().await?;
+	let url = serve_admin(&net).await?;
+	let client = reqwest::Client::new();
+	let (gateway, _) = net.gateway.as_ref().unwrap();
+
+	let rotation = RelayRotationRequest { host: "127.0.0.2".to_string(), port: 4000, api_key: Some("key".to_string()) };
+	let response = client.put(format!("{url}{ADMIN_RELAY}")).bearer_auth(API_KEY).json(&rotation).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(gateway.constraints_client().base_url.as_str(), "http://127.0.0.2:4000/");
+	assert_eq!(
+		gateway.db.get_admin_overrides()?.relay,
+		Some(RelayEndpoint { host: "127.0.0.2".to_string(), port: 4000, api_key: Some("key".to_string()) })
+	);
+
+	// A pause keeps the rotated relay
+	client.post(format!("{url}{ADMIN_PAUSE}")).bearer_auth(API_KEY).send().await?;
+	let overrides = gateway.db.get_admin_overrides()?;
+	assert!(overrides.paused);
+	assert_eq!(overrides.relay.map(|relay| relay.port), Some(4000));
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_admin_receivers_are_added_and_removed() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().start().await?;
+	let url = serve_admin(&net).await?;
+	let client = reqwest::Client::new();
+	let (gateway, _) = net.gateway.as_ref().unwrap();
+	let receiver = BlsPublicKey::repeat_byte(0x11);
+	let receiver_url = format!("{url}{ADMIN_RECEIVERS}/{receiver}");
+
+	let add = AddReceiverRequest { public_key: receiver };
+	let response = client.post(format!("{url}{ADMIN_RECEIVERS}")).bearer_auth(API_KEY).json(&add).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	let receivers: ReceiversResponse =
+		client.get(format!("{url}{ADMIN_RECEIVERS}")).bearer_auth(API_KEY).send().await?.json().await?;
+	assert_eq!(receivers.added, vec![receiver]);
+	let slot = gateway.slot_clock.current_slot() + 5;
+	assert!(gateway.constraints_receivers(slot)?.contains(&receiver));
+
+	// A slot override replaces the receivers of that slot only
+	let slot_url = format!("{url}/admin/slots/{slot}/receivers");
+	let other = BlsPublicKey::repeat_byte(0x22);
+	let body = SlotReceiversRequest { receivers: vec![other] };
+	let response = client.put(&slot_url).bearer_auth(API_KEY).json(&body).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	let slot_receivers: SlotReceiversResponse = client.get(&slot_url).bearer_auth(API_KEY).send().await?.json().await?;
+	assert!(slot_receivers.overridden);
+	assert_eq!(slot_receivers.receivers, vec![other]);
+	assert!(gateway.constraints_receivers(slot + 1)?.contains(&receiver));
+	let response = client.delete(&slot_url).bearer_auth(API_KEY).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(gateway.constraints_receivers(slot)?.contains(&receiver));
+
+	// Started slots cannot be overridden
+	let started_url = format!("{url}/admin/slots/{}/receivers", gateway.slot_clock.current_slot());
+	let response = client.put(&started_url).bearer_auth(API_KEY).json(&body).send().await?;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	let response = client.delete(&receiver_url).bearer_auth(API_KEY).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(gateway.db.get_constraints_receivers()?.is_empty());
+	let response = client.delete(&receiver_url).bearer_auth(API_KEY).send().await?;
+	assert_eq!(response.status(), StatusCode::NOT_FOUND);
+	let response = client.delete(format!("{url}{ADMIN_RECEIVERS}/0x1234")).bearer_auth(API_KEY).send().await?;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_signed_receiver_updates() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().start().await?;
+	let url = serve_admin(&net).await?;
+	let client = reqwest::Client::new();
+	let (gateway, _) = net.gateway.as_ref().unwrap();
+
+	let mut builder = LocalSigner::new(gateway.chain);
+	let receiver = builder.add_bls_key(BlsSecretKey::random());
+	let signing_id = B256::repeat_byte(0x01);
+	let sign = |message: ReceiverUpdate| -> Result<SignedReceiverUpdate> {
+		let signing_root = get_receiver_update_signing_root(&message);
+		let signature = builder.sign_bls(&receiver, signing_root, &signing_id, 1)?;
+		Ok(SignedReceiverUpdate { message, nonce: 1, signing_id, signature: BlsSignature::new(signature.serialize()) })
+	};
+	let update = ReceiverUpdate {
+		receiver,
+		gateway: gateway.gateway_public_key,
+		add: true,
+		expiry_slot: gateway.slot_clock.current_slot() + 10,
+	};
+
+	// Signed updates need no admin token
+	let added = sign(update.clone())?;
+	let response = client.post(format!("{url}{RECEIVER_UPDATE}")).json(&added).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(gateway.db.get_constraints_receivers()?, vec![receiver]);
+
+	// A removal needs its own signature
+	let mut forged = added.clone();
+	forged.message.add = false;
+	let response = client.post(format!("{url}{RECEIVER_UPDATE}")).json(&forged).send().await?;
+	assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+	let other_gateway = sign(ReceiverUpdate { gateway: BlsPublicKey::repeat_byte(0x33), ..update.clone() })?;
+	let response = client.post(format!("{url}{RECEIVER_UPDATE}")).json(&other_gateway).send().await?;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	let expired = sign(ReceiverUpdate { expiry_slot: gateway.slot_clock.current_slot() - 1, ..update.clone() })?;
+	let response = client.post(format!("{url}{RECEIVER_UPDATE}")).json(&expired).send().await?;
+	assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+	let removed = sign(ReceiverUpdate { add: false, ..update })?;
+	let response = client.post(format!("{url}{RECEIVER_UPDATE}")).json(&removed).send().await?;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(gateway.db.get_constraints_receivers()?.is_empty());
+	Ok(())
+}
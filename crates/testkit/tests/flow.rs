@@ -0,0 +1,183 @@
+use alloy::eips::eip2718::Encodable2718;
+use alloy::primitives::{Address, U256};
+use commitments::types::CommitmentRequest;
+use constraints::client::ConstraintsClient;
+use constraints::error::ConstraintsError;
+use constraints::types::{ConstraintProofs, ConstraintsMessage, SubmitBlockRequestWithProofs};
+use eyre::Result;
+use fabric_testkit::{OFFER_BID_GWEI, TestNet};
+use inclusion::constants::INCLUSION_COMMITMENT_TYPE;
+use inclusion::gateway::utils::sign_constraints_message;
+use inclusion::types::InclusionPayload;
+use proposer::storage::DelegationsDbExt;
+use proposer::utils::verify_delegation_offer;
+use signing::signer::verify_ecdsa_commitment;
+
+#[tokio::test]
+async fn test_delegation_reaches_relay_and_gateway() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().with_proposer().start().await?;
+
+	net.delegate().await?;
+	let slot = net.next_delegated_slot()?;
+
+	let delegations = net.relay_client().get_delegations(slot).await?;
+	assert_eq!(delegations.len(), 1);
+	assert_eq!(delegations[0].message.proposer, net.proposer_public_key);
+	assert_eq!(delegations[0].message.delegate, net.gateway_public_key);
+	assert_eq!(delegations[0].message.committer, net.committer_address);
+
+	net.wait_for_gateway_delegation(slot).await?;
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_delegation_to_an_additional_gateway_key() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().with_proposer().with_additional_gateway_key().start().await?;
+	assert_ne!(net.delegate_public_key, net.gateway_public_key);
+
+	net.delegate().await?;
+	let slot = net.next_delegated_slot()?;
+	let delegations = net.relay_client().get_delegations(slot).await?;
+	assert_eq!(delegations[0].message.delegate, net.delegate_public_key);
+	net.wait_for_gateway_delegation(slot).await?;
+	let (gateway, _) = net.gateway.as_ref().unwrap();
+	assert!(gateway.is_gateway_key(&net.delegate_public_key));
+
+	// The gateway's signer holds the additional key, the relay only accepts constraints signed by the delegate
+	let message = ConstraintsMessage {
+		proposer: net.proposer_public_key,
+		delegate: net.delegate_public_key,
+		slot,
+		constraints: vec![],
+		receivers: vec![],
+	};
+	let signed_constraints = sign_constraints_message(
+		&message,
+		&mut gateway.signer_client.clone(),
+		&gateway.nonce_manager,
+		net.delegate_public_key,
+		&gateway.module_signing_id,
+		gateway.chain,
+	)
+	.await?;
+	net.relay_client().post_constraints(&signed_constraints).await?;
+	let constraints = net.relay_client().get_constraints(slot).await?;
+	assert_eq!(constraints.len(), 1);
+	assert_eq!(constraints[0].message.delegate, net.delegate_public_key);
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_delegation_goes_to_the_best_offer() -> Result<()> {
+	let net =
+		TestNet::new().with_relay().with_gateway().with_proposer().with_delegation_offers().start().await?;
+	let slot = net.current_slot()? + 3;
+
+	// The gateway signs and publishes an offer for the undelegated slot
+	let offer = net.wait_for_delegation_offer(slot).await?;
+	assert_eq!(offer.message.committer, net.committer_address);
+	assert_eq!(offer.message.bid_gwei, OFFER_BID_GWEI);
+	verify_delegation_offer(&offer, &net.chain)?;
+
+	// Offers are published once per slot, a later check does not sign another one
+	tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+	let offers = net.relay_client().get_delegation_offers(slot).await?;
+	assert_eq!(offers.len(), 1);
+	assert_eq!(offers[0].nonce, offer.nonce);
+
+	// The proposer's own gateway is not run, the slot can only go to the offering gateway
+	net.delegate().await?;
+	let delegations = net.relay_client().get_delegations(slot).await?;
+	assert_eq!(delegations.len(), 1);
+	assert_eq!(delegations[0].message.delegate, net.gateway_public_key);
+	assert_eq!(delegations[0].message.committer, net.committer_address);
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_delegation_needs_a_quorum_of_relays() -> Result<()> {
+	// One of two relays is down, a quorum of two is never reached
+	let net = TestNet::new().with_relay().with_gateway().with_proposer().with_unreachable_relays(1, 2).start().await?;
+	net.delegate().await?;
+	assert!(net.next_delegated_slot().is_err());
+
+	let proposer = net.proposer.as_ref().unwrap();
+	let slot = proposer.slot_clock.current_slot() + 2;
+	let acks = proposer.db.get_relay_acks(slot)?;
+	assert_eq!(acks.len(), 2);
+	assert_eq!(acks.iter().filter(|ack| ack.is_accepted()).count(), 1);
+	assert_eq!(net.relay_client().get_delegations(slot).await?.len(), 1);
+
+	// A quorum of one is reached by the running relay
+	let net = TestNet::new().with_relay().with_gateway().with_proposer().with_unreachable_relays(1, 1).start().await?;
+	net.delegate().await?;
+	net.next_delegated_slot()?;
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_delegation_held_by_the_relay_counts_as_accepted() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().with_proposer().with_unreachable_relays(1, 2).start().await?;
+	net.delegate().await?;
+	let proposer = net.proposer.as_ref().unwrap();
+	let slot = proposer.slot_clock.current_slot() + 2;
+
+	// The relay's acceptance was lost, posting the same delegation again is answered with a conflict
+	proposer.db.store_relay_acks(slot, &[])?;
+	net.delegate().await?;
+	let acks = proposer.db.get_relay_acks(slot)?;
+	assert_eq!(acks.iter().filter(|ack| ack.is_accepted()).count(), 1);
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_block_without_constraints_is_not_forwarded() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().with_proposer().start().await?;
+	net.delegate().await?;
+	let slot = net.next_delegated_slot()?;
+
+	assert!(net.submit_block(slot, &[]).await.is_err());
+	assert!(net.downstream.submitted_blocks().is_empty());
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_dry_run_rejects_blocks_like_the_submission() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().with_proposer().start().await?;
+	let (gateway, _) = net.gateway.as_ref().unwrap();
+	let slot = gateway.slot_clock.current_slot() - 2;
+	let block = SubmitBlockRequestWithProofs { message: net.build_block(slot, &[]), proofs: ConstraintProofs::default() };
+
+	// A stale block is rejected before its proofs are looked at, by the dry-run as by the submission
+	let client = net.relay_client();
+	assert!(matches!(client.validate_blocks_with_proofs(&block).await, Err(ConstraintsError::InvalidRequest(_))));
+	assert!(matches!(client.post_blocks_with_proofs(&block).await, Err(ConstraintsError::InvalidRequest(_))));
+	assert!(net.downstream.submitted_blocks().is_empty());
+	Ok(())
+}
+
+#[tokio::test]
+#[ignore = "spawns anvil, which must be on the PATH"]
+async fn test_commitment_to_block_with_proofs() -> Result<()> {
+	let net = TestNet::new().with_relay().with_gateway().with_proposer().with_anvil().start().await?;
+	net.delegate().await?;
+	let slot = net.next_delegated_slot()?;
+	net.wait_for_gateway_delegation(slot).await?;
+
+	// Commitment to include a transfer paying the committer
+	let tx = net.signed_transfer(U256::from(10u64.pow(16))).await?;
+	let payload = InclusionPayload { slot, signed_tx: tx.encoded_2718().into() }.abi_encode()?;
+	let request = CommitmentRequest { commitment_type: INCLUSION_COMMITMENT_TYPE, payload, slasher: Address::random() };
+	let commitment = net.commitments_client()?.commitment_request(request).await?;
+	verify_ecdsa_commitment(net.chain, &commitment, &net.committer_address)?;
+
+	// The gateway's constraints are served by the relay and proven by the block
+	net.post_constraints(slot).await?;
+	let constraints = net.relay_client().get_constraints(slot).await?;
+	assert_eq!(constraints.len(), 1);
+	assert_eq!(constraints[0].message.delegate, net.gateway_public_key);
+
+	net.submit_block(slot, &[tx]).await?;
+	assert_eq!(net.downstream.submitted_blocks().len(), 1);
+	Ok(())
+}
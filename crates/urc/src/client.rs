@@ -0,0 +1,356 @@
+use alloy::network::EthereumWallet;
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::transports::http::reqwest::Url;
+use eyre::{Result, eyre};
+
+use crate::bindings::i_registry::IRegistry::{self, IRegistryInstance, OperatorRegistered};
+use crate::utils::{get_signed_registration_sol_type, operator_record};
+use crate::{OperatorRecord, URCRegisterInputs};
+
+/// Outcome of a register() transaction
+#[derive(Debug, Clone)]
+pub struct RegistrationReceipt {
+	/// Root of the registration merkle tree, identifies the operator in the URC
+	pub registration_root: B256,
+	/// Collateral locked with the registration
+	pub collateral_wei: U256,
+	pub tx_hash: B256,
+	pub block_number: Option<u64>,
+}
+
+/// Stage of an operator's exit from the registry
+///
+/// Exiting takes two transactions: unregister() starts the unregistration delay, after which
+/// claimCollateral() returns the collateral and deletes the registration. The registry measures the
+/// delay in blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitState {
+	/// No registration under the root
+	NotRegistered,
+	/// Registered, unregister() starts the exit
+	Registered,
+	/// Unregistered, the collateral can be claimed from `claimable_at_block`
+	Unregistering { claimable_at_block: u64 },
+	/// The unregistration delay has passed, claimCollateral() returns the collateral
+	Claimable,
+	/// Slashed, the collateral cannot be claimed
+	Slashed,
+	/// The collateral was claimed and the registration deleted
+	Exited,
+}
+
+impl ExitState {
+	/// Exit stage of an operator at `current_block`, given the registry's unregistration delay
+	pub fn new(operator: &OperatorRecord, unregistration_delay: u64, current_block: u64) -> Self {
+		if operator.deleted {
+			return Self::Exited;
+		}
+		if operator.registered_at.is_none() {
+			return Self::NotRegistered;
+		}
+		if operator.slashed_at.is_some() {
+			return Self::Slashed;
+		}
+		match operator.unregistered_at {
+			None => Self::Registered,
+			Some(unregistered_at) => {
+				let claimable_at_block = unregistered_at.saturating_add(unregistration_delay);
+				if current_block >= claimable_at_block {
+					Self::Claimable
+				} else {
+					Self::Unregistering { claimable_at_block }
+				}
+			}
+		}
+	}
+}
+
+/// Client submitting URC transactions signed by a local key
+pub struct UrcClient {
+	registry: IRegistryInstance<DynProvider>,
+	sender: Address,
+}
+
+impl UrcClient {
+	/// Create a client for the registry at `registry_address`, sending transactions from `signer`
+	pub fn new(rpc_url: Url, registry_address: Address, signer: PrivateKeySigner) -> Self {
+		let sender = signer.address();
+		let provider = ProviderBuilder::new().wallet(EthereumWallet::from(signer)).connect_http(rpc_url).erased();
+		Self::with_provider(provider, registry_address, sender)
+	}
+
+	/// Create a client for the registry at `registry_address` over a provider signing the transactions of `sender`
+	pub fn with_provider(provider: DynProvider, registry_address: Address, sender: Address) -> Self {
+		Self { registry: IRegistry::new(registry_address, provider), sender }
+	}
+
+	/// Address paying for the transactions and the collateral
+	pub fn sender(&self) -> Address {
+		self.sender
+	}
+
+	/// Minimum collateral the registry accepts with a registration
+	pub async fn min_collateral_wei(&self) -> Result<U256> {
+		let config = self.registry.getConfig().call().await?;
+		Ok(U256::from(config.minCollateralWei))
+	}
+
+	/// Send register() with `collateral_wei` attached and wait for its receipt
+	/// Errors before sending if the collateral is below the registry minimum or the sender cannot pay it
+	pub async fn register(&self, inputs: &URCRegisterInputs, collateral_wei: U256) -> Result<RegistrationReceipt> {
+		let min_collateral_wei = self.min_collateral_wei().await?;
+		if collateral_wei < min_collateral_wei {
+			return Err(eyre!(
+				"Collateral of {} wei is below the registry minimum of {} wei",
+				collateral_wei,
+				min_collateral_wei
+			));
+		}
+		let balance = self.registry.provider().get_balance(self.sender).await?;
+		if balance < collateral_wei {
+			return Err(eyre!("Sender {} holds {} wei, cannot attach {} wei", self.sender, balance, collateral_wei));
+		}
+
+		let registrations =
+			inputs.registrations.iter().map(get_signed_registration_sol_type).collect::<Result<Vec<_>>>()?;
+		let receipt = self
+			.registry
+			.register(registrations, inputs.owner, inputs.signing_id)
+			.value(collateral_wei)
+			.send()
+			.await?
+			.get_receipt()
+			.await?;
+		if !receipt.status() {
+			return Err(eyre!("register() transaction {} reverted", receipt.transaction_hash));
+		}
+
+		let registered = receipt
+			.decoded_log::<OperatorRegistered>()
+			.ok_or_else(|| eyre!("No OperatorRegistered event in transaction {}", receipt.transaction_hash))?;
+		Ok(RegistrationReceipt {
+			registration_root: registered.registrationRoot,
+			collateral_wei: registered.collateralWei,
+			tx_hash: receipt.transaction_hash,
+			block_number: receipt.block_number,
+		})
+	}
+
+	/// Send addCollateral() for an existing registration and wait for its receipt, returns the transaction hash
+	pub async fn add_collateral(&self, registration_root: B256, collateral_wei: U256) -> Result<B256> {
+		let receipt =
+			self.registry.addCollateral(registration_root).value(collateral_wei).send().await?.get_receipt().await?;
+		if !receipt.status() {
+			return Err(eyre!("addCollateral() transaction {} reverted", receipt.transaction_hash));
+		}
+		Ok(receipt.transaction_hash)
+	}
+
+	/// Operator data of a registration
+	pub async fn operator(&self, registration_root: B256) -> Result<OperatorRecord> {
+		Ok(operator_record(&self.registry.getOperatorData(registration_root).call().await?))
+	}
+
+	/// Exit stage of a registration at the latest block
+	pub async fn exit_state(&self, registration_root: B256) -> Result<ExitState> {
+		let operator = self.operator(registration_root).await?;
+		let config = self.registry.getConfig().call().await?;
+		let current_block = self.registry.provider().get_block_number().await?;
+		Ok(ExitState::new(&operator, config.unregistrationDelay.into(), current_block))
+	}
+
+	/// Send unregister() to start the unregistration delay and wait for its receipt, returns the transaction hash
+	/// Errors before sending unless the registration is owned by the sender and not already exiting
+	pub async fn unregister(&self, registration_root: B256) -> Result<B256> {
+		self.check_owner(registration_root).await?;
+		let state = self.exit_state(registration_root).await?;
+		if state != ExitState::Registered {
+			return Err(eyre!("Cannot unregister {}, exit state is {:?}", registration_root, state));
+		}
+
+		let receipt = self.registry.unregister(registration_root).send().await?.get_receipt().await?;
+		if !receipt.status() {
+			return Err(eyre!("unregister() transaction {} reverted", receipt.transaction_hash));
+		}
+		Ok(receipt.transaction_hash)
+	}
+
+	/// Send claimCollateral() and wait for its receipt, returns the transaction hash
+	/// Errors before sending unless the registration is owned by the sender and the unregistration delay has passed
+	pub async fn claim_collateral(&self, registration_root: B256) -> Result<B256> {
+		self.check_owner(registration_root).await?;
+		match self.exit_state(registration_root).await? {
+			ExitState::Claimable => {}
+			ExitState::Unregistering { claimable_at_block } => {
+				return Err(eyre!(
+					"Collateral of {} is claimable from block {}",
+					registration_root,
+					claimable_at_block
+				));
+			}
+			state => {
+				return Err(eyre!("Cannot claim collateral of {}, exit state is {:?}", registration_root, state));
+			}
+		}
+
+		let receipt = self.registry.claimCollateral(registration_root).send().await?.get_receipt().await?;
+		if !receipt.status() {
+			return Err(eyre!("claimCollateral() transaction {} reverted", receipt.transaction_hash));
+		}
+		Ok(receipt.transaction_hash)
+	}
+
+	/// Errors unless the sender owns the registration, only the owner can unregister and claim
+	async fn check_owner(&self, registration_root: B256) -> Result<()> {
+		let owner = self.operator(registration_root).await?.owner;
+		if owner != self.sender {
+			return Err(eyre!(
+				"Registration {} is owned by {}, not the sender {}",
+				registration_root,
+				owner,
+				self.sender
+			));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::primitives::aliases::{U48, U80};
+	use alloy::primitives::{Bytes, U64};
+	use alloy::sol_types::SolCall;
+	use alloy::transports::mock::Asserter;
+
+	use crate::bindings::i_registry::IRegistry::{Config, OperatorData, getConfigCall, getOperatorDataCall};
+
+	const SENDER: Address = Address::repeat_byte(0x11);
+
+	/// Client over a mocked provider answering with the responses queued on the returned asserter, in order
+	fn mocked_client() -> (UrcClient, Asserter) {
+		let asserter = Asserter::new();
+		let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone()).erased();
+		(UrcClient::with_provider(provider, Address::repeat_byte(0x22), SENDER), asserter)
+	}
+
+	fn push_config(asserter: &Asserter, min_collateral_wei: u64, unregistration_delay: u32) {
+		let config = Config {
+			minCollateralWei: U80::from(min_collateral_wei),
+			fraudProofWindow: 7200,
+			unregistrationDelay: unregistration_delay,
+			slashWindow: 7200,
+			optInDelay: 7200,
+			signingDomain: B256::ZERO,
+			chainId: B256::ZERO,
+		};
+		asserter.push_success(&Bytes::from(getConfigCall::abi_encode_returns(&config)));
+	}
+
+	fn push_operator(asserter: &Asserter, owner: Address, unregistered_at: Option<u64>) {
+		let operator = OperatorData {
+			owner,
+			collateralWei: U80::from(1_000u64),
+			numKeys: 1,
+			registeredAt: U48::from(100u64),
+			unregisteredAt: unregistered_at.map_or(U48::MAX, U48::from),
+			slashedAt: U48::ZERO,
+			deleted: false,
+			equivocated: false,
+		};
+		asserter.push_success(&Bytes::from(getOperatorDataCall::abi_encode_returns(&operator)));
+	}
+
+	fn inputs() -> URCRegisterInputs {
+		URCRegisterInputs { registrations: vec![], owner: SENDER, signing_id: B256::ZERO }
+	}
+
+	#[tokio::test]
+	async fn test_register_checks_the_collateral_before_sending() {
+		let (client, asserter) = mocked_client();
+		push_config(&asserter, 1_000, 50);
+		let error = client.register(&inputs(), U256::from(999)).await.unwrap_err();
+		assert!(error.to_string().contains("below the registry minimum of 1000 wei"));
+
+		push_config(&asserter, 1_000, 50);
+		asserter.push_success(&U256::from(500));
+		let error = client.register(&inputs(), U256::from(1_000)).await.unwrap_err();
+		assert!(error.to_string().contains("holds 500 wei, cannot attach 1000 wei"));
+		assert!(asserter.read_q().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_exit_state_at_the_latest_block() {
+		let (client, asserter) = mocked_client();
+		push_operator(&asserter, SENDER, Some(200));
+		push_config(&asserter, 1_000, 50);
+		asserter.push_success(&U64::from(249));
+		assert_eq!(client.exit_state(B256::ZERO).await.unwrap(), ExitState::Unregistering { claimable_at_block: 250 });
+
+		push_operator(&asserter, SENDER, Some(200));
+		push_config(&asserter, 1_000, 50);
+		asserter.push_success(&U64::from(250));
+		assert_eq!(client.exit_state(B256::ZERO).await.unwrap(), ExitState::Claimable);
+	}
+
+	#[tokio::test]
+	async fn test_exits_are_checked_before_sending() {
+		let (client, asserter) = mocked_client();
+
+		// Only the owner can exit
+		push_operator(&asserter, Address::repeat_byte(0x33), None);
+		let error = client.unregister(B256::ZERO).await.unwrap_err();
+		assert!(error.to_string().contains("not the sender"));
+
+		// An exiting registration is not unregistered again
+		push_operator(&asserter, SENDER, Some(200));
+		push_operator(&asserter, SENDER, Some(200));
+		push_config(&asserter, 1_000, 50);
+		asserter.push_success(&U64::from(210));
+		let error = client.unregister(B256::ZERO).await.unwrap_err();
+		assert!(error.to_string().contains("exit state is Unregistering"));
+
+		// The collateral is claimable once the unregistration delay has passed
+		push_operator(&asserter, SENDER, Some(200));
+		push_operator(&asserter, SENDER, Some(200));
+		push_config(&asserter, 1_000, 50);
+		asserter.push_success(&U64::from(210));
+		let error = client.claim_collateral(B256::ZERO).await.unwrap_err();
+		assert!(error.to_string().contains("claimable from block 250"));
+
+		push_operator(&asserter, SENDER, None);
+		push_operator(&asserter, SENDER, None);
+		push_config(&asserter, 1_000, 50);
+		asserter.push_success(&U64::from(210));
+		let error = client.claim_collateral(B256::ZERO).await.unwrap_err();
+		assert!(error.to_string().contains("exit state is Registered"));
+		assert!(asserter.read_q().is_empty());
+	}
+
+	#[test]
+	fn test_exit_state() {
+		let registered = OperatorRecord {
+			owner: Address::ZERO,
+			collateral_wei: U256::from(1),
+			registered_at: Some(100),
+			unregistered_at: None,
+			slashed_at: None,
+			deleted: false,
+		};
+		assert_eq!(ExitState::new(&registered, 50, 120), ExitState::Registered);
+
+		let unregistering = OperatorRecord { unregistered_at: Some(200), ..registered.clone() };
+		assert_eq!(ExitState::new(&unregistering, 50, 249), ExitState::Unregistering { claimable_at_block: 250 });
+		assert_eq!(ExitState::new(&unregistering, 50, 250), ExitState::Claimable);
+
+		let slashed = OperatorRecord { slashed_at: Some(210), ..unregistering.clone() };
+		assert_eq!(ExitState::new(&slashed, 50, 300), ExitState::Slashed);
+		assert_eq!(ExitState::new(&OperatorRecord { deleted: true, ..unregistering }, 50, 300), ExitState::Exited);
+		assert_eq!(
+			ExitState::new(&OperatorRecord { registered_at: None, ..registered }, 50, 300),
+			ExitState::NotRegistered
+		);
+	}
+}
@@ -0,0 +1,309 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, B256, U256, keccak256};
+use alloy::providers::ext::DebugApi;
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::types::beacon::BlsPublicKey;
+use alloy::rpc::types::trace::geth::{CallConfig, CallFrame, GethDebugTracingOptions};
+use alloy::sol_types::{SolCall, SolValue};
+use alloy::transports::http::reqwest::Url;
+use eyre::{Result, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::i_registry::BLS::G1Point;
+use crate::bindings::i_registry::IRegistry::{self, IRegistryInstance, OperatorRegistered, registerCall};
+use crate::utils::{convert_pubkey_to_g1_point, operator_record};
+
+/// Number of blocks queried per eth_getLogs request
+const LOG_QUERY_BLOCK_RANGE: u64 = 10_000;
+
+/// URC registry the relay and gateway check operators against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrcConfig {
+	/// Execution client RPC URL the registry is read from
+	pub rpc_url: String,
+	/// Address of the URC registry contract
+	pub registry_address: Address,
+	/// Block the registry was deployed at, registrations are indexed from here
+	#[serde(default)]
+	pub start_block: u64,
+	/// Slasher operators must be opted into
+	pub slasher: Address,
+	/// Minimum collateral in wei a registration must hold
+	#[serde(default)]
+	pub min_collateral_wei: U256,
+	/// How often to index new registrations, in seconds
+	#[serde(default = "default_sync_interval_secs")]
+	pub sync_interval_secs: u64,
+	/// Blocks behind the head registrations are indexed at, so a reorg can not undo an indexed registration
+	#[serde(default = "default_confirmations")]
+	pub confirmations: u64,
+}
+
+fn default_sync_interval_secs() -> u64 {
+	12
+}
+
+fn default_confirmations() -> u64 {
+	64
+}
+
+/// Registration root and owner of a registered BLS key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRegistration {
+	pub registration_root: B256,
+	pub owner: Address,
+}
+
+/// On-chain state of an operator, read from the registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorStatus {
+	pub collateral_wei: U256,
+	pub registered: bool,
+	pub unregistered: bool,
+	pub slashed: bool,
+	pub opted_in: bool,
+}
+
+impl OperatorStatus {
+	/// Errors unless the operator is registered, not exiting or slashed, opted into the slasher and holds
+	/// at least `min_collateral_wei`
+	pub fn validate(&self, min_collateral_wei: U256) -> Result<()> {
+		if !self.registered {
+			return Err(eyre!("Operator is not registered"));
+		}
+		if self.unregistered {
+			return Err(eyre!("Operator is unregistering"));
+		}
+		if self.slashed {
+			return Err(eyre!("Operator was slashed"));
+		}
+		if !self.opted_in {
+			return Err(eyre!("Operator is not opted into the slasher"));
+		}
+		if self.collateral_wei < min_collateral_wei {
+			return Err(eyre!(
+				"Operator collateral of {} wei is below the minimum of {} wei",
+				self.collateral_wei,
+				min_collateral_wei
+			));
+		}
+		Ok(())
+	}
+}
+
+/// Index of the BLS keys registered in the URC
+/// Registered keys are only in the calldata of register(), so each OperatorRegistered event is resolved to
+/// the register() call of its transaction, traced when the registry was called through another contract
+pub struct UrcIndexer {
+	config: UrcConfig,
+	registry: IRegistryInstance<DynProvider>,
+	/// Registrations keyed by the hash of the key's G1 point
+	registrations: RwLock<HashMap<B256, KeyRegistration>>,
+	next_block: AtomicU64,
+}
+
+impl UrcIndexer {
+	pub fn new(config: UrcConfig) -> Result<Self> {
+		let rpc_url = Url::parse(&config.rpc_url)?;
+		let provider = ProviderBuilder::new().connect_http(rpc_url).erased();
+		let registry = IRegistry::new(config.registry_address, provider);
+		let next_block = AtomicU64::new(config.start_block);
+		Ok(Self { config, registry, registrations: RwLock::new(HashMap::new()), next_block })
+	}
+
+	pub fn config(&self) -> &UrcConfig {
+		&self.config
+	}
+
+	/// Index the registrations made since the last sync up to `confirmations` blocks behind the head, returns the
+	/// number of keys indexed. A range is only marked indexed once every registration in it was, so a failed sync
+	/// is retried from the same block
+	pub async fn sync(&self) -> Result<usize> {
+		let head = self.registry.provider().get_block_number().await?;
+		let Some(latest) = head.checked_sub(self.config.confirmations) else {
+			return Ok(0);
+		};
+		let mut from = self.next_block.load(Ordering::SeqCst);
+		let mut indexed = 0;
+		while from <= latest {
+			let to = latest.min(from + LOG_QUERY_BLOCK_RANGE - 1);
+			let events =
+				self.registry.event_filter::<OperatorRegistered>().from_block(from).to_block(to).query().await?;
+			// The register() calls of the transaction of the previous event, in the order they emitted their events
+			let mut calls: Option<(B256, VecDeque<registerCall>)> = None;
+			for (event, log) in events {
+				let tx_hash = log.transaction_hash.ok_or_else(|| eyre!("Registration log without transaction hash"))?;
+				if calls.as_ref().is_none_or(|(hash, _)| *hash != tx_hash) {
+					calls = Some((tx_hash, self.register_calls(tx_hash).await?.into()));
+				}
+				let call = calls.as_mut().and_then(|(_, calls)| calls.pop_front()).ok_or_else(|| {
+					eyre!("Registration {} has no register() call in {}", event.registrationRoot, tx_hash)
+				})?;
+				if call.owner != event.owner {
+					return Err(eyre!(
+						"Registration {} does not match the register() call of {}",
+						event.registrationRoot,
+						tx_hash
+					));
+				}
+
+				let registration = KeyRegistration { registration_root: event.registrationRoot, owner: event.owner };
+				let mut registrations =
+					self.registrations.write().map_err(|_| eyre!("URC registrations lock poisoned"))?;
+				for signed in &call.registrations {
+					registrations.insert(g1_point_key(&signed.pubkey), registration);
+					indexed += 1;
+				}
+			}
+			from = to + 1;
+			self.next_block.store(from, Ordering::SeqCst);
+		}
+		Ok(indexed)
+	}
+
+	/// Successful register() calls to the registry made by a transaction, in call order
+	/// Decoded from the calldata when the transaction calls the registry, traced otherwise
+	async fn register_calls(&self, tx_hash: B256) -> Result<Vec<registerCall>> {
+		let provider = self.registry.provider();
+		let tx = provider
+			.get_transaction_by_hash(tx_hash)
+			.await?
+			.ok_or_else(|| eyre!("Registration transaction {} not found", tx_hash))?;
+		if tx.to() == Some(*self.registry.address())
+			&& let Ok(call) = registerCall::abi_decode(tx.input())
+		{
+			return Ok(vec![call]);
+		}
+
+		let trace = provider
+			.debug_trace_transaction(tx_hash, GethDebugTracingOptions::call_tracer(CallConfig::default()))
+			.await
+			.map_err(|e| eyre!("Failed to trace registration transaction {}: {}", tx_hash, e))?
+			.try_into_call_frame()
+			.map_err(|e| eyre!("Unexpected trace of registration transaction {}: {}", tx_hash, e))?;
+		let mut calls = Vec::new();
+		collect_register_calls(&trace, *self.registry.address(), &mut calls);
+		Ok(calls)
+	}
+
+	/// Latest registration of a BLS key, if any
+	pub fn registration(&self, pubkey: &BlsPublicKey) -> Result<Option<KeyRegistration>> {
+		let key = g1_point_key(&convert_pubkey_to_g1_point(pubkey)?);
+		let registrations = self.registrations.read().map_err(|_| eyre!("URC registrations lock poisoned"))?;
+		Ok(registrations.get(&key).copied())
+	}
+
+	/// On-chain state of a registration with respect to the configured slasher
+	pub async fn operator_status(&self, registration_root: B256) -> Result<OperatorStatus> {
+		let operator = operator_record(&self.registry.getOperatorData(registration_root).call().await?);
+		let opted_in = self.registry.isOptedIntoSlasher(registration_root, self.config.slasher).call().await?;
+		Ok(OperatorStatus {
+			collateral_wei: operator.collateral_wei,
+			registered: operator.registered_at.is_some() && !operator.deleted,
+			unregistered: operator.unregistered_at.is_some(),
+			slashed: operator.slashed_at.is_some(),
+			opted_in,
+		})
+	}
+
+	/// Committer the registration opted in with for the configured slasher, errors if it is not opted in
+	pub async fn slasher_committer(&self, registration_root: B256) -> Result<Address> {
+		let commitment = self.registry.getSlasherCommitment(registration_root, self.config.slasher).call().await?;
+		if commitment.optedInAt.is_zero() || commitment.optedOutAt >= commitment.optedInAt {
+			return Err(eyre!("Registration {} is not opted into slasher {}", registration_root, self.config.slasher));
+		}
+		Ok(commitment.committer)
+	}
+
+	/// Errors unless the key is registered by an operator in good standing, opted into the configured slasher
+	/// and holding the configured minimum collateral
+	pub async fn check_operator(&self, pubkey: &BlsPublicKey) -> Result<KeyRegistration> {
+		let registration =
+			self.registration(pubkey)?.ok_or_else(|| eyre!("Key {} is not registered in the URC", pubkey))?;
+		self.operator_status(registration.registration_root)
+			.await?
+			.validate(self.config.min_collateral_wei)
+			.map_err(|e| eyre!("URC registration {} of key {}: {}", registration.registration_root, pubkey, e))?;
+		Ok(registration)
+	}
+}
+
+/// Index key of a G1 point
+fn g1_point_key(point: &G1Point) -> B256 {
+	keccak256(point.abi_encode())
+}
+
+/// Collect the register() calls to `registry` in a call tree, skipping reverted calls and everything under them
+fn collect_register_calls(frame: &CallFrame, registry: Address, calls: &mut Vec<registerCall>) {
+	if frame.error.is_some() {
+		return;
+	}
+	if frame.to == Some(registry)
+		&& let Ok(call) = registerCall::abi_decode(&frame.input)
+	{
+		calls.push(call);
+	}
+	for inner in &frame.calls {
+		collect_register_calls(inner, registry, calls);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_operator_status_validate() {
+		let status = OperatorStatus {
+			collateral_wei: U256::from(1_000),
+			registered: true,
+			unregistered: false,
+			slashed: false,
+			opted_in: true,
+		};
+		assert!(status.validate(U256::from(1_000)).is_ok());
+		assert!(status.validate(U256::from(1_001)).is_err());
+		assert!(OperatorStatus { registered: false, ..status.clone() }.validate(U256::ZERO).is_err());
+		assert!(OperatorStatus { unregistered: true, ..status.clone() }.validate(U256::ZERO).is_err());
+		assert!(OperatorStatus { slashed: true, ..status.clone() }.validate(U256::ZERO).is_err());
+		assert!(OperatorStatus { opted_in: false, ..status }.validate(U256::ZERO).is_err());
+	}
+
+	#[test]
+	fn test_collect_register_calls() {
+		let registry = Address::repeat_byte(1);
+		let register =
+			|byte: u8| registerCall { registrations: vec![], owner: Address::repeat_byte(byte), signingId: B256::ZERO };
+		let frame = |to: Address, input: Vec<u8>, calls: Vec<CallFrame>| CallFrame {
+			to: Some(to),
+			input: input.into(),
+			calls,
+			..Default::default()
+		};
+
+		// A batching contract registering twice, once in a call that reverted
+		let reverted = CallFrame {
+			error: Some("execution reverted".to_string()),
+			..frame(registry, register(3).abi_encode(), vec![])
+		};
+		let trace = frame(
+			Address::repeat_byte(9),
+			vec![],
+			vec![
+				frame(registry, register(2).abi_encode(), vec![]),
+				reverted,
+				frame(Address::repeat_byte(8), register(4).abi_encode(), vec![]),
+				frame(Address::repeat_byte(7), vec![], vec![frame(registry, register(5).abi_encode(), vec![])]),
+			],
+		);
+
+		let mut calls = Vec::new();
+		collect_register_calls(&trace, registry, &mut calls);
+		let owners = calls.iter().map(|call| call.owner).collect::<Vec<_>>();
+		assert_eq!(owners, vec![Address::repeat_byte(2), Address::repeat_byte(5)]);
+	}
+}
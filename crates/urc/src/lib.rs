@@ -1,9 +1,13 @@
 #![allow(warnings)]
 mod bindings;
+pub mod client;
+pub mod indexer;
+pub mod test_vectors;
 pub mod utils;
 
 use alloy::primitives::{Address, B256, U256};
 use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
+use serde::{Deserialize, Serialize};
 
 /// Binding of the MessageType enum, defined here:
 /// https://github.com/eth-fabric/urc/blob/304e59f967dd8fdf4342c2f776f789e7c99b8ef9/src/IRegistry.sol#L99
@@ -15,6 +19,10 @@ enum MessageType {
 	Delegation = 2,
 	Commitment = 3,
 	Constraints = 4,
+	/// Not part of the URC, offers are only exchanged through relays
+	DelegationOffer = 5,
+	/// Not part of the URC, receiver updates are only sent to gateways
+	ReceiverUpdate = 6,
 }
 
 impl MessageType {
@@ -24,17 +32,30 @@ impl MessageType {
 }
 
 /// URC registration message
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Registration {
 	pub owner: Address,
 }
 
 /// Signed registration used for URC.register
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignedRegistration {
 	pub pubkey: BlsPublicKey,
 	pub signature: BlsSignature,
 	pub nonce: u64,
 }
 
+/// Operator data of a registration, block numbers are `None` when unset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorRecord {
+	pub owner: Address,
+	pub collateral_wei: U256,
+	pub registered_at: Option<u64>,
+	pub unregistered_at: Option<u64>,
+	pub slashed_at: Option<u64>,
+	pub deleted: bool,
+}
+
 /// Container for URC register() call parameters
 pub struct URCRegisterInputs {
 	pub registrations: Vec<SignedRegistration>,
@@ -0,0 +1,121 @@
+//! Golden test vectors for the signing roots of the URC messages
+//!
+//! `test-vectors/signing_roots.json` pairs message inputs with the roots the URC computes for them,
+//! `keccak256(abi.encode(messageType, message))`, and the commitment request hash. The file is plain JSON so
+//! builders and relays in other languages can check their encoding against the same vectors, Rust callers load
+//! it with [`bundled`], [`load`] or [`load_file`] and check this crate with [`SigningRootVectors::verify`].
+//!
+//! The roots were produced by this crate, so `cargo test` alone only guards against regressions.
+//! `test-vectors/check_with_cast.sh` recomputes the commitment request, commitment and registration roots with
+//! Foundry's `cast` from the URC's Solidity struct signatures, independently of this crate. Delegation and
+//! constraints message roots embed uncompressed G1 points that `cast` cannot derive, and are checked here only.
+use std::path::Path;
+
+use alloy::primitives::B256;
+use commitments::types::{Commitment, CommitmentRequest};
+use constraints::types::{ConstraintsMessage, Delegation};
+use eyre::{Result, WrapErr, eyre};
+use serde::{Deserialize, Serialize};
+
+use crate::Registration;
+use crate::utils::{
+	get_commitment_request_signing_root, get_commitment_signing_root, get_constraints_message_signing_root,
+	get_delegation_signing_root, get_registration_signing_root,
+};
+
+/// The vectors shipped with this crate
+pub const SIGNING_ROOTS_JSON: &str = include_str!("../test-vectors/signing_roots.json");
+
+/// A message and its expected signing root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestVector<T> {
+	pub name: String,
+	pub input: T,
+	pub signing_root: B256,
+}
+
+/// Signing root vectors by message type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SigningRootVectors {
+	pub commitment_requests: Vec<TestVector<CommitmentRequest>>,
+	pub commitments: Vec<TestVector<Commitment>>,
+	pub delegations: Vec<TestVector<Delegation>>,
+	pub constraints_messages: Vec<TestVector<ConstraintsMessage>>,
+	pub registrations: Vec<TestVector<Registration>>,
+}
+
+impl SigningRootVectors {
+	/// Compute every signing root with this crate, an error lists each vector whose root differs
+	pub fn verify(&self) -> Result<()> {
+		let mut mismatches = Vec::new();
+		let mut check = |kind: &str, vector_name: &str, computed: Result<B256>, expected: B256| match computed {
+			Ok(computed) if computed == expected => {}
+			Ok(computed) => {
+				mismatches.push(format!("{} {}: expected {}, computed {}", kind, vector_name, expected, computed))
+			}
+			Err(e) => mismatches.push(format!("{} {}: {}", kind, vector_name, e)),
+		};
+
+		for vector in &self.commitment_requests {
+			let root = Ok(get_commitment_request_signing_root(&vector.input));
+			check("commitment request", &vector.name, root, vector.signing_root);
+		}
+		for vector in &self.commitments {
+			check("commitment", &vector.name, Ok(get_commitment_signing_root(&vector.input)), vector.signing_root);
+		}
+		for vector in &self.delegations {
+			check("delegation", &vector.name, get_delegation_signing_root(&vector.input), vector.signing_root);
+		}
+		for vector in &self.constraints_messages {
+			let root = get_constraints_message_signing_root(&vector.input);
+			check("constraints message", &vector.name, root, vector.signing_root);
+		}
+		for vector in &self.registrations {
+			check("registration", &vector.name, Ok(get_registration_signing_root(&vector.input)), vector.signing_root);
+		}
+
+		if mismatches.is_empty() {
+			return Ok(());
+		}
+		Err(eyre!("{} signing root(s) differ: {}", mismatches.len(), mismatches.join("; ")))
+	}
+}
+
+/// The vectors shipped with this crate
+pub fn bundled() -> SigningRootVectors {
+	load(SIGNING_ROOTS_JSON).expect("bundled signing root vectors are valid")
+}
+
+/// Parse vectors in the format of `test-vectors/signing_roots.json`
+pub fn load(json: &str) -> Result<SigningRootVectors> {
+	serde_json::from_str(json).wrap_err("Invalid signing root vectors")
+}
+
+/// Read vectors in the format of `test-vectors/signing_roots.json` from a file
+pub fn load_file(path: &Path) -> Result<SigningRootVectors> {
+	let json = std::fs::read_to_string(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+	load(&json)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_bundled_vectors_match() -> Result<()> {
+		let vectors = bundled();
+		assert!(!vectors.delegations.is_empty() && !vectors.constraints_messages.is_empty());
+		vectors.verify()
+	}
+
+	#[test]
+	fn test_verify_reports_mismatches() {
+		let mut vectors = bundled();
+		vectors.delegations[0].signing_root = B256::ZERO;
+		vectors.registrations[0].input.owner = vectors.registrations[1].input.owner;
+
+		let error = vectors.verify().unwrap_err().to_string();
+		assert!(error.starts_with("2 signing root(s) differ"));
+		assert!(error.contains(&format!("delegation {}", vectors.delegations[0].name)));
+	}
+}
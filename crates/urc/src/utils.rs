@@ -1,25 +1,31 @@
-use alloy::primitives::{B256, Bytes, keccak256};
+use alloy::primitives::{B256, Bytes, U256, aliases::U48, keccak256};
 use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
 use alloy::sol;
 use alloy::sol_types::{SolCall, SolValue};
 use blst::{
 	BLST_ERROR, blst_bendian_from_fp, blst_fp, blst_p1_affine, blst_p1_uncompress, blst_p2_affine, blst_p2_uncompress,
 };
-// use commit_boost::prelude::{BlsPublicKey, BlsSignature};
+use cb_common::constants::COMMIT_BOOST_DOMAIN;
+use cb_common::types::Chain;
 use eyre::{Result, eyre};
+use sha2::{Digest, Sha256};
 
 use crate::bindings::i_registry::{
 	BLS::{G1Point, G2Point},
-	IRegistry::{SignedRegistration as SolSignedRegistration, registerCall as SolRegisterCall},
+	IRegistry::{
+		OperatorData as SolOperatorData, SignedRegistration as SolSignedRegistration,
+		claimCollateralCall as SolClaimCollateralCall, registerCall as SolRegisterCall,
+		unregisterCall as SolUnregisterCall,
+	},
 	ISlasher::{Commitment as SolCommitment, Delegation as SolDelegation},
 };
 
-use crate::{MessageType, Registration, SignedRegistration, URCRegisterInputs};
+use crate::{MessageType, OperatorRecord, Registration, SignedRegistration, URCRegisterInputs};
 use commitments::types::{Commitment, CommitmentRequest};
-use constraints::types::{ConstraintsMessage, Delegation};
+use constraints::types::{ConstraintsMessage, Delegation, DelegationOffer, Receipt, ReceiverUpdate};
 
 /// Converts a pubkey to its corresponding affine G1 point form for EVM precompile usage
-fn convert_pubkey_to_g1_point(pubkey: &BlsPublicKey) -> Result<G1Point> {
+pub(crate) fn convert_pubkey_to_g1_point(pubkey: &BlsPublicKey) -> Result<G1Point> {
 	let mut pubkey_affine = blst_p1_affine::default();
 	let uncompress_result = unsafe { blst_p1_uncompress(&mut pubkey_affine, pubkey.as_ptr()) };
 	match uncompress_result {
@@ -153,6 +159,44 @@ pub fn get_constraints_message_signing_root(constraints: &ConstraintsMessage) ->
 	Ok(keccak256((MessageType::Constraints.to_uint256(), constraints_message_evm).abi_encode_params()))
 }
 
+/// Hashes a relay receipt as keccak256(abi.encode(message_hash, timestamp_ms))
+pub fn get_receipt_signing_root(receipt: &Receipt) -> B256 {
+	keccak256((receipt.message_hash, receipt.timestamp_ms).abi_encode_params())
+}
+
+/// Hashes a gateway's delegation offer as
+/// keccak256(abi.encode(MessageType.DelegationOffer, delegate, committer, slot, bid_gwei, constraint_types))
+/// Offers never reach the slasher, so the delegate key is encoded as its compressed bytes
+pub fn get_delegation_offer_signing_root(offer: &DelegationOffer) -> B256 {
+	keccak256(
+		(
+			MessageType::DelegationOffer.to_uint256(),
+			Bytes::copy_from_slice(offer.delegate.as_slice()),
+			offer.committer,
+			offer.slot,
+			offer.bid_gwei,
+			offer.constraint_types.clone(),
+		)
+			.abi_encode_params(),
+	)
+}
+
+/// Hashes a builder's receiver update as
+/// keccak256(abi.encode(MessageType.ReceiverUpdate, receiver, gateway, add, expiry_slot))
+/// Like offers, updates never reach the slasher, so the keys are encoded as their compressed bytes
+pub fn get_receiver_update_signing_root(update: &ReceiverUpdate) -> B256 {
+	keccak256(
+		(
+			MessageType::ReceiverUpdate.to_uint256(),
+			Bytes::copy_from_slice(update.receiver.as_slice()),
+			Bytes::copy_from_slice(update.gateway.as_slice()),
+			update.add,
+			update.expiry_slot,
+		)
+			.abi_encode_params(),
+	)
+}
+
 pub fn get_registration_signing_root(registration: &Registration) -> B256 {
 	sol! {
 		struct SolRegistration {
@@ -163,7 +207,36 @@ pub fn get_registration_signing_root(registration: &Registration) -> B256 {
 	keccak256((MessageType::Registration.to_uint256(), registration_evm).abi_encode_params())
 }
 
-fn get_signed_registration_sol_type(registration: &SignedRegistration) -> Result<SolSignedRegistration> {
+/// Root the Commit-Boost signer signs for a module: the hash tree root of the message hash, module signing id,
+/// nonce and chain id, signed under the Commit-Boost domain of the chain's genesis fork
+pub fn commit_signing_root(chain: Chain, message_hash: B256, module_signing_id: &B256, nonce: u64) -> B256 {
+	let mut nonce_leaf = [0u8; 32];
+	nonce_leaf[..8].copy_from_slice(&nonce.to_le_bytes());
+	let chain_id_leaf = chain.id().to_le_bytes::<32>();
+	let object_root = sha256_pair(
+		&sha256_pair(message_hash.as_slice(), module_signing_id.as_slice()),
+		&sha256_pair(&nonce_leaf, &chain_id_leaf),
+	);
+
+	B256::from(sha256_pair(&object_root, &genesis_domain(chain, COMMIT_BOOST_DOMAIN)))
+}
+
+/// Domain of `domain_type` at the chain's genesis fork, with an empty genesis validators root
+pub fn genesis_domain(chain: Chain, domain_type: [u8; 4]) -> [u8; 32] {
+	let mut fork_version_leaf = [0u8; 32];
+	fork_version_leaf[..4].copy_from_slice(&chain.genesis_fork_version());
+	let fork_data_root = sha256_pair(&fork_version_leaf, &[0u8; 32]);
+	let mut domain = [0u8; 32];
+	domain[..4].copy_from_slice(&domain_type);
+	domain[4..].copy_from_slice(&fork_data_root[..28]);
+	domain
+}
+
+pub fn sha256_pair(left: &[u8], right: &[u8]) -> [u8; 32] {
+	Sha256::new().chain_update(left).chain_update(right).finalize().into()
+}
+
+pub(crate) fn get_signed_registration_sol_type(registration: &SignedRegistration) -> Result<SolSignedRegistration> {
 	let pubkey = convert_pubkey_to_g1_point(&registration.pubkey)?;
 	let signature = convert_signature_to_g2_point(&registration.signature)?;
 	let signed_registration = SolSignedRegistration { pubkey, signature, nonce: registration.nonce };
@@ -179,6 +252,32 @@ pub fn abi_encode_urc_register_inputs(inputs: &URCRegisterInputs) -> Result<Byte
 	Ok(Bytes::from(encoded))
 }
 
+/// ABI-encodes URC.unregister(), which starts the unregistration delay of a registration
+pub fn abi_encode_unregister(registration_root: B256) -> Bytes {
+	Bytes::from(SolUnregisterCall { registrationRoot: registration_root }.abi_encode())
+}
+
+/// ABI-encodes URC.claimCollateral(), which returns the collateral once the unregistration delay has passed
+pub fn abi_encode_claim_collateral(registration_root: B256) -> Bytes {
+	Bytes::from(SolClaimCollateralCall { registrationRoot: registration_root }.abi_encode())
+}
+
+/// Block number of an operator event, the registry marks unset ones as zero or the maximum uint48
+fn block_number(value: U48) -> Option<u64> {
+	(!value.is_zero() && value != U48::MAX).then(|| value.to::<u64>())
+}
+
+pub(crate) fn operator_record(operator: &SolOperatorData) -> OperatorRecord {
+	OperatorRecord {
+		owner: operator.owner,
+		collateral_wei: U256::from(operator.collateralWei),
+		registered_at: block_number(operator.registeredAt),
+		unregistered_at: block_number(operator.unregisteredAt),
+		slashed_at: block_number(operator.slashedAt),
+		deleted: operator.deleted,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -187,10 +286,37 @@ mod tests {
 	use constraints::types::Constraint;
 	use eyre::Result;
 
+	use crate::test_vectors::{self, TestVector};
+
 	fn bls_pubkey_from_hex(hex_str: &str) -> BlsPublicKey {
 		decode_pubkey(hex_str).expect("Failed to decode public key")
 	}
 
+	/// Root of the bundled vector with the given name, so the expected hashes live only in the vectors file
+	fn vector_root<T>(vectors: &[TestVector<T>], name: &str) -> B256 {
+		vectors.iter().find(|vector| vector.name == name).map(|vector| vector.signing_root).expect("vector exists")
+	}
+
+	#[test]
+	fn test_operator_record_unset_block_numbers() {
+		let operator = SolOperatorData {
+			owner: Address::repeat_byte(1),
+			collateralWei: alloy::primitives::aliases::U80::from(1_000u64),
+			numKeys: 1,
+			registeredAt: U48::from(10u64),
+			unregisteredAt: U48::MAX,
+			slashedAt: U48::ZERO,
+			deleted: false,
+			equivocated: false,
+		};
+		let record = operator_record(&operator);
+		assert_eq!(record.registered_at, Some(10));
+		assert_eq!(record.unregistered_at, None);
+		assert_eq!(record.slashed_at, None);
+
+		let unregistered = operator_record(&SolOperatorData { unregisteredAt: U48::from(20u64), ..operator });
+		assert_eq!(unregistered.unregistered_at, Some(20));
+	}
 	#[test]
 	fn test_message_type_to_uint256() {
 		assert_eq!(MessageType::Reserved.to_uint256(), U256::from(0));
@@ -198,6 +324,8 @@ mod tests {
 		assert_eq!(MessageType::Delegation.to_uint256(), U256::from(2));
 		assert_eq!(MessageType::Commitment.to_uint256(), U256::from(3));
 		assert_eq!(MessageType::Constraints.to_uint256(), U256::from(4));
+		assert_eq!(MessageType::DelegationOffer.to_uint256(), U256::from(5));
+		assert_eq!(MessageType::ReceiverUpdate.to_uint256(), U256::from(6));
 	}
 
 	#[test]
@@ -205,8 +333,8 @@ mod tests {
 		let commitment_request =
 			CommitmentRequest { commitment_type: 1, payload: Bytes::new(), slasher: Address::ZERO };
 		assert_eq!(
-			get_commitment_request_signing_root(&commitment_request).to_string(),
-			"0xf61a6130b6ebfffcb3738e03fe820e4b883b623ec3ab7657ffbf385b2e94edba"
+			get_commitment_request_signing_root(&commitment_request),
+			vector_root(&test_vectors::bundled().commitment_requests, "inclusion-empty-payload")
 		);
 		Ok(())
 	}
@@ -217,12 +345,69 @@ mod tests {
 			Commitment { commitment_type: 1, payload: Bytes::new(), request_hash: B256::ZERO, slasher: Address::ZERO };
 
 		assert_eq!(
-			get_commitment_signing_root(&commitment).to_string(),
-			"0x9770f15c80e37efd7af931b39a8b67e01003b923ee5d808b5a87619ebdf30da1"
+			get_commitment_signing_root(&commitment),
+			vector_root(&test_vectors::bundled().commitments, "empty-payload")
 		);
 		Ok(())
 	}
 
+	#[test]
+	fn test_get_receipt_signing_root() -> Result<()> {
+		let receipt = Receipt { message_hash: B256::repeat_byte(0x11), timestamp_ms: 1_700_000_000_000 };
+
+		// Matches keccak256(abi.encode(bytes32, uint64)) computed by hand
+		let mut encoded = [0u8; 64];
+		encoded[..32].copy_from_slice(receipt.message_hash.as_slice());
+		encoded[56..].copy_from_slice(&receipt.timestamp_ms.to_be_bytes());
+		assert_eq!(get_receipt_signing_root(&receipt), keccak256(encoded));
+
+		// The timestamp is covered by the signing root
+		let later = Receipt { timestamp_ms: receipt.timestamp_ms + 1, ..receipt.clone() };
+		assert_ne!(get_receipt_signing_root(&receipt), get_receipt_signing_root(&later));
+		Ok(())
+	}
+
+	#[test]
+	fn test_get_delegation_offer_signing_root() {
+		let offer = DelegationOffer {
+			delegate: BlsPublicKey::repeat_byte(0x22),
+			committer: hex!("0x1111111111111111111111111111111111111111").into(),
+			slot: 5,
+			bid_gwei: 1_000,
+			constraint_types: vec![1, 2],
+		};
+		let root = get_delegation_offer_signing_root(&offer);
+
+		// Every field is covered by the signing root
+		let higher_bid = DelegationOffer { bid_gwei: 1_001, ..offer.clone() };
+		let other_slot = DelegationOffer { slot: 6, ..offer.clone() };
+		let fewer_types = DelegationOffer { constraint_types: vec![1], ..offer.clone() };
+		let other_delegate = DelegationOffer { delegate: BlsPublicKey::repeat_byte(0x33), ..offer.clone() };
+		for other in [higher_bid, other_slot, fewer_types, other_delegate] {
+			assert_ne!(root, get_delegation_offer_signing_root(&other));
+		}
+	}
+
+	#[test]
+	fn test_get_receiver_update_signing_root() {
+		let update = ReceiverUpdate {
+			receiver: BlsPublicKey::repeat_byte(0x22),
+			gateway: BlsPublicKey::repeat_byte(0x33),
+			add: true,
+			expiry_slot: 5,
+		};
+		let root = get_receiver_update_signing_root(&update);
+
+		// Every field is covered by the signing root
+		let removal = ReceiverUpdate { add: false, ..update.clone() };
+		let later = ReceiverUpdate { expiry_slot: 6, ..update.clone() };
+		let other_gateway = ReceiverUpdate { gateway: BlsPublicKey::repeat_byte(0x44), ..update.clone() };
+		let swapped = ReceiverUpdate { receiver: update.gateway, gateway: update.receiver, ..update.clone() };
+		for other in [removal, later, other_gateway, swapped] {
+			assert_ne!(root, get_receiver_update_signing_root(&other));
+		}
+	}
+
 	#[test]
 	fn test_get_delegation_signing_root() -> Result<()> {
 		let proposer = bls_pubkey_from_hex(
@@ -241,8 +426,8 @@ mod tests {
 			metadata: Bytes::from("some-metadata-here"),
 		};
 		assert_eq!(
-			get_delegation_signing_root(&delegation).unwrap().to_string(),
-			"0xcd9aca062121f6f50df1bfd7e74e2b023a5a0d9e1387447568a2119db5022e1b"
+			get_delegation_signing_root(&delegation)?,
+			vector_root(&test_vectors::bundled().delegations, "with-metadata")
 		);
 		Ok(())
 	}
@@ -273,9 +458,22 @@ mod tests {
 		};
 
 		assert_eq!(
-			get_constraints_message_signing_root(&constraints_message).unwrap().to_string(),
-			"0xb27bb26406c8fe6cf9e5bb1723d7dd2b06e4d32efc0cb0419dc57cc6c4b0ca87"
+			get_constraints_message_signing_root(&constraints_message)?,
+			vector_root(&test_vectors::bundled().constraints_messages, "two-constraints-one-receiver")
 		);
 		Ok(())
 	}
+
+	#[test]
+	fn test_abi_encode_exit_calls() {
+		let registration_root = B256::repeat_byte(0x22);
+
+		let unregister = abi_encode_unregister(registration_root);
+		assert_eq!(unregister[..4], SolUnregisterCall::SELECTOR);
+		assert_eq!(SolUnregisterCall::abi_decode(&unregister).unwrap().registrationRoot, registration_root);
+
+		let claim = abi_encode_claim_collateral(registration_root);
+		assert_eq!(claim[..4], SolClaimCollateralCall::SELECTOR);
+		assert_eq!(SolClaimCollateralCall::abi_decode(&claim).unwrap().registrationRoot, registration_root);
+	}
 }
@@ -0,0 +1,51 @@
+#!/usr/bin/env bash
+# Recompute the signing roots in signing_roots.json with Foundry's cast, which ABI encodes the messages from their
+# Solidity signatures (the structs of the URC's ISlasher and IRegistry interfaces in vendor/urc) without any of this
+# repo's code.
+#
+# Covers the messages that are plain ABI: commitment requests, commitments and registrations. Delegations and
+# constraints messages embed BLS pubkeys as uncompressed G1 points, which cast cannot decompress, so their roots are
+# only checked by `cargo test -p urc`.
+set -euo pipefail
+
+VECTORS="${1:-$(dirname "$0")/signing_roots.json}"
+
+# MessageType values from the URC
+REGISTRATION=1
+COMMITMENT=3
+
+failures=0
+
+check() {
+  local kind="$1" name="$2" expected="$3" computed="$4"
+  if [[ "$computed" == "$expected" ]]; then
+    echo "ok   $kind $name"
+  else
+    echo "FAIL $kind $name: expected $expected, computed $computed"
+    failures=$((failures + 1))
+  fi
+}
+
+while IFS=$'\t' read -r name commitment_type payload slasher expected; do
+  encoded=$(cast abi-encode "f((uint64,bytes,address))" "($commitment_type,$payload,$slasher)")
+  check "commitment request" "$name" "$expected" "$(cast keccak "$encoded")"
+done < <(jq -r '.commitment_requests[]
+  | [.name, .input.commitment_type, .input.payload, .input.slasher, .signing_root] | @tsv' "$VECTORS")
+
+while IFS=$'\t' read -r name commitment_type payload request_hash slasher expected; do
+  encoded=$(cast abi-encode "f(uint256,(uint64,bytes,bytes32,address))" \
+    "$COMMITMENT" "($commitment_type,$payload,$request_hash,$slasher)")
+  check "commitment" "$name" "$expected" "$(cast keccak "$encoded")"
+done < <(jq -r '.commitments[]
+  | [.name, .input.commitment_type, .input.payload, .input.request_hash, .input.slasher, .signing_root] | @tsv' \
+  "$VECTORS")
+
+while IFS=$'\t' read -r name owner expected; do
+  encoded=$(cast abi-encode "f(uint256,(address))" "$REGISTRATION" "($owner)")
+  check "registration" "$name" "$expected" "$(cast keccak "$encoded")"
+done < <(jq -r '.registrations[] | [.name, .input.owner, .signing_root] | @tsv' "$VECTORS")
+
+if [[ "$failures" -ne 0 ]]; then
+  echo "$failures signing root(s) differ"
+  exit 1
+fi
@@ -0,0 +1,40 @@
+//! Typed errors of the verification functions.
+
+use alloy::primitives::{Address, B256};
+use alloy::rpc::types::beacon::BlsPublicKey;
+use inclusion::proofs::ProofError;
+
+/// Why a commitment, its constraints or its inclusion proof failed verification
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+	/// The commitment's request hash is not the hash of the request it carries
+	#[error("request hash mismatch: commitment carries {actual}, its request hashes to {expected}")]
+	RequestHashMismatch { expected: B256, actual: B256 },
+	/// No signer can be recovered from the commitment signature
+	#[error("invalid commitment signature: {0}")]
+	InvalidSignature(String),
+	/// The commitment is signed by another key than the expected committer
+	#[error("commitment is signed by {actual}, expected committer {expected}")]
+	WrongCommitter { expected: Address, actual: Address },
+	/// The commitment payload does not decode as its commitment type, or the type is unknown
+	#[error("invalid commitment payload: {0}")]
+	InvalidPayload(String),
+	/// The signed constraints are for another slot than the commitment
+	#[error("signed constraints are for slot {actual}, expected slot {expected}")]
+	WrongSlot { expected: u64, actual: u64 },
+	/// The signed constraints name another delegate than the slot's
+	#[error("signed constraints name delegate {actual}, expected delegate {expected}")]
+	WrongDelegate { expected: BlsPublicKey, actual: BlsPublicKey },
+	/// The BLS signature of the constraints does not verify against their delegate
+	#[error("invalid constraints signature: {0}")]
+	InvalidConstraintsSignature(String),
+	/// Constraints of the commitment missing from the signed constraints
+	#[error("{missing} of the {expected} constraints of the commitment are missing from the signed constraints")]
+	MissingConstraints { missing: usize, expected: usize },
+	/// The inclusion proof does not prove the transaction against the header
+	#[error(transparent)]
+	Proof(#[from] ProofError),
+}
+
+/// Result of the verification functions
+pub type Result<T, E = VerifyError> = std::result::Result<T, E>;
@@ -0,0 +1,300 @@
+//! Verification of a commitment from the recipient's side, with no database, signer or network access.
+//!
+//! A wallet or searcher holding a `SignedCommitment` checks that the expected committer signed it, that the
+//! `SignedConstraints` posted for its slot carry the commitment's constraints, and that the block proposed in the
+//! slot includes the committed transaction, proven against the block header.
+//!
+//! Signatures are checked over the Commit-Boost signing root every fabric signer signs, which binds the chain, the
+//! signing id and the nonce. The crate only depends on the type modules of the other fabric crates, and BLS
+//! verification of constraints is behind the default `bls` feature.
+
+pub mod error;
+
+use alloy::consensus::Header;
+use alloy::primitives::Address;
+#[cfg(feature = "bls")]
+use alloy::rpc::types::beacon::BlsPublicKey;
+use commitments::types::{CommitmentRequest, SignedCommitment};
+use constraints::types::{ConstraintProofs, SignedConstraints};
+use inclusion::proofs::{InclusionProof, ProofVerifier, verify_inclusion};
+use inclusion::types::commitment_constraints;
+#[cfg(feature = "bls")]
+use urc::utils::get_constraints_message_signing_root;
+use urc::utils::{commit_signing_root, get_commitment_request_signing_root, get_commitment_signing_root};
+
+pub use cb_common::types::Chain;
+pub use error::{Result, VerifyError};
+
+/// Domain separation tag of Ethereum BLS signatures
+#[cfg(feature = "bls")]
+const BLS_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// Recovers the committer of a signed commitment, the ECDSA signer of the Commit-Boost signing root of its
+/// `get_commitment_signing_root`, signing id and nonce
+pub fn recover_committer(chain: Chain, signed_commitment: &SignedCommitment) -> Result<Address> {
+	let signing_root = commit_signing_root(
+		chain,
+		get_commitment_signing_root(&signed_commitment.commitment),
+		&signed_commitment.signing_id,
+		signed_commitment.nonce,
+	);
+	signed_commitment
+		.signature
+		.recover_address_from_prehash(&signing_root)
+		.map_err(|e| VerifyError::InvalidSignature(e.to_string()))
+}
+
+/// Verifies that a signed commitment commits to its own request and is signed by `expected_committer`
+pub fn verify_commitment(
+	chain: Chain,
+	signed_commitment: &SignedCommitment,
+	expected_committer: &Address,
+) -> Result<()> {
+	let commitment = &signed_commitment.commitment;
+	let request = CommitmentRequest {
+		commitment_type: commitment.commitment_type,
+		payload: commitment.payload.clone(),
+		slasher: commitment.slasher,
+	};
+	let request_hash = get_commitment_request_signing_root(&request);
+	if request_hash != commitment.request_hash {
+		return Err(VerifyError::RequestHashMismatch { expected: request_hash, actual: commitment.request_hash });
+	}
+
+	let committer = recover_committer(chain, signed_commitment)?;
+	if committer != *expected_committer {
+		return Err(VerifyError::WrongCommitter { expected: *expected_committer, actual: committer });
+	}
+	Ok(())
+}
+
+/// Verifies that the signed constraints carry every constraint the gateway posts for the commitment
+/// Constraint payloads embed their slot, so matching payloads also pin the slot. The BLS signature of the
+/// constraints is checked by `verify_constraints_signature`, the relay only serves constraints signed by the
+/// slot's delegate
+pub fn verify_constraints_cover(
+	signed_constraints: &SignedConstraints,
+	signed_commitment: &SignedCommitment,
+) -> Result<()> {
+	let commitment = &signed_commitment.commitment;
+	let expected = commitment_constraints(commitment.commitment_type, &commitment.payload)
+		.map_err(|e| VerifyError::InvalidPayload(e.to_string()))?;
+	let signed = &signed_constraints.message.constraints;
+	let missing = expected
+		.iter()
+		.filter(|constraint| {
+			!signed.iter().any(|signed| {
+				signed.constraint_type == constraint.constraint_type && signed.payload == constraint.payload
+			})
+		})
+		.count();
+	if missing > 0 {
+		return Err(VerifyError::MissingConstraints { missing, expected: expected.len() });
+	}
+	Ok(())
+}
+
+/// Verifies that the signed constraints are for `slot`, name `delegate` and are signed by it
+#[cfg(feature = "bls")]
+pub fn verify_constraints_signature(
+	signed_constraints: &SignedConstraints,
+	delegate: &BlsPublicKey,
+	slot: u64,
+	chain: Chain,
+) -> Result<()> {
+	let message = &signed_constraints.message;
+	if message.slot != slot {
+		return Err(VerifyError::WrongSlot { expected: slot, actual: message.slot });
+	}
+	if message.delegate != *delegate {
+		return Err(VerifyError::WrongDelegate { expected: *delegate, actual: message.delegate });
+	}
+
+	let message_hash = get_constraints_message_signing_root(message)
+		.map_err(|e| VerifyError::InvalidConstraintsSignature(e.to_string()))?;
+	let signing_root =
+		commit_signing_root(chain, message_hash, &signed_constraints.signing_id, signed_constraints.nonce);
+	let public_key = blst::min_pk::PublicKey::from_bytes(delegate.as_slice())
+		.map_err(|e| VerifyError::InvalidConstraintsSignature(format!("invalid delegate key: {:?}", e)))?;
+	let signature = blst::min_pk::Signature::from_bytes(signed_constraints.signature.as_slice())
+		.map_err(|e| VerifyError::InvalidConstraintsSignature(format!("invalid signature: {:?}", e)))?;
+	match signature.verify(true, signing_root.as_slice(), BLS_DST, &[], &public_key, true) {
+		blst::BLST_ERROR::BLST_SUCCESS => Ok(()),
+		err => Err(VerifyError::InvalidConstraintsSignature(format!("{:?}", err))),
+	}
+}
+
+/// Verifies that the proven transaction is at the claimed index of the block with `header`
+pub fn verify_inclusion_proof(header: &Header, inclusion_proof: &InclusionProof) -> Result<()> {
+	let verifier = ProofVerifier::new(header.transactions_root, &[], &ConstraintProofs::default())?;
+	verify_inclusion(&verifier, inclusion_proof)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use alloy::primitives::B256;
+	use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
+	use alloy::signers::local::PrivateKeySigner;
+	use commitments::types::Commitment;
+	use constraints::types::{Constraint, ConstraintsMessage};
+	use inclusion::constants::{BUNDLE_COMMITMENT_TYPE, INCLUSION_COMMITMENT_TYPE, INCLUSION_CONSTRAINT_TYPE};
+	use inclusion::proofs::TransactionTrieBuilder;
+	use inclusion::types::{BundlePayload, InclusionPayload};
+	use signing::local::LocalSigner;
+
+	const CHAIN: Chain = Chain::Holesky;
+
+	/// A commitment signed by `committer` the way the gateway builds it from a request and signs it
+	fn signed_commitment(signer: &LocalSigner, committer: &Address, request: &CommitmentRequest) -> SignedCommitment {
+		let commitment = Commitment {
+			commitment_type: request.commitment_type,
+			payload: request.payload.clone(),
+			request_hash: get_commitment_request_signing_root(request),
+			slasher: request.slasher,
+		};
+		let signing_id = B256::repeat_byte(9);
+		let signature = signer.sign_ecdsa(committer, get_commitment_signing_root(&commitment), &signing_id, 3).unwrap();
+		SignedCommitment { commitment, nonce: 3, signing_id, signature }
+	}
+
+	fn signed_constraints(constraints: Vec<Constraint>) -> SignedConstraints {
+		SignedConstraints {
+			message: ConstraintsMessage {
+				proposer: BlsPublicKey::repeat_byte(1),
+				delegate: BlsPublicKey::repeat_byte(2),
+				slot: 100,
+				constraints,
+				receivers: vec![],
+			},
+			nonce: 0,
+			signing_id: B256::ZERO,
+			signature: BlsSignature::ZERO,
+		}
+	}
+
+	#[test]
+	fn test_verify_commitment() {
+		let mut signer = LocalSigner::new(CHAIN);
+		let committer = signer.add_ecdsa_key(PrivateKeySigner::random());
+		let request = CommitmentRequest {
+			commitment_type: INCLUSION_COMMITMENT_TYPE,
+			payload: InclusionPayload::random().abi_encode().unwrap(),
+			slasher: Address::repeat_byte(1),
+		};
+		let signed = signed_commitment(&signer, &committer, &request);
+
+		verify_commitment(CHAIN, &signed, &committer).unwrap();
+		assert_eq!(recover_committer(CHAIN, &signed).unwrap(), committer);
+		assert!(matches!(
+			verify_commitment(CHAIN, &signed, &Address::random()),
+			Err(VerifyError::WrongCommitter { .. })
+		));
+
+		// The signing root binds the chain, the signing id and the nonce
+		assert!(verify_commitment(Chain::Mainnet, &signed, &committer).is_err());
+		let mut renonced = signed.clone();
+		renonced.nonce += 1;
+		assert!(verify_commitment(CHAIN, &renonced, &committer).is_err());
+		let mut resigned = signed.clone();
+		resigned.signing_id = B256::ZERO;
+		assert!(verify_commitment(CHAIN, &resigned, &committer).is_err());
+
+		// A commitment whose request hash is not its own request's is rejected before the signature is checked
+		let mut mismatched = signed.clone();
+		mismatched.commitment.slasher = Address::repeat_byte(2);
+		assert!(matches!(
+			verify_commitment(CHAIN, &mismatched, &committer),
+			Err(VerifyError::RequestHashMismatch { .. })
+		));
+	}
+
+	#[cfg(feature = "bls")]
+	#[test]
+	fn test_verify_constraints_signature() {
+		let mut signer = LocalSigner::new(CHAIN);
+		let delegate = signer.add_bls_key(cb_common::types::BlsSecretKey::random());
+		let constraint = Constraint {
+			constraint_type: INCLUSION_CONSTRAINT_TYPE,
+			payload: InclusionPayload::random().abi_encode().unwrap(),
+		};
+		let mut signed = signed_constraints(vec![constraint]);
+		signed.message.delegate = delegate;
+		signed.signing_id = B256::repeat_byte(9);
+		signed.nonce = 4;
+		let message_hash = get_constraints_message_signing_root(&signed.message).unwrap();
+		let signature = signer.sign_bls(&delegate, message_hash, &signed.signing_id, signed.nonce).unwrap();
+		signed.signature = BlsSignature::new(signature.serialize());
+
+		verify_constraints_signature(&signed, &delegate, 100, CHAIN).unwrap();
+		assert!(matches!(
+			verify_constraints_signature(&signed, &delegate, 101, CHAIN),
+			Err(VerifyError::WrongSlot { expected: 101, actual: 100 })
+		));
+		assert!(matches!(
+			verify_constraints_signature(&signed, &BlsPublicKey::repeat_byte(2), 100, CHAIN),
+			Err(VerifyError::WrongDelegate { .. })
+		));
+		assert!(matches!(
+			verify_constraints_signature(&signed, &delegate, 100, Chain::Mainnet),
+			Err(VerifyError::InvalidConstraintsSignature(_))
+		));
+
+		// Constraints changed after signing no longer verify
+		let mut tampered = signed.clone();
+		tampered.message.constraints.clear();
+		assert!(matches!(
+			verify_constraints_signature(&tampered, &delegate, 100, CHAIN),
+			Err(VerifyError::InvalidConstraintsSignature(_))
+		));
+	}
+
+	#[test]
+	fn test_verify_constraints_cover() {
+		let mut signer = LocalSigner::new(CHAIN);
+		let committer = signer.add_ecdsa_key(PrivateKeySigner::random());
+		let first = InclusionPayload::random();
+		let second = InclusionPayload::random();
+		let bundle = BundlePayload { slot: 100, signed_txs: vec![first.signed_tx.clone(), second.signed_tx.clone()] };
+		let request = CommitmentRequest {
+			commitment_type: BUNDLE_COMMITMENT_TYPE,
+			payload: bundle.abi_encode().unwrap(),
+			slasher: Address::repeat_byte(1),
+		};
+		let signed = signed_commitment(&signer, &committer, &request);
+
+		// The bundle constraint and one inclusion constraint per transaction, among other commitments' constraints
+		let mut constraints = commitment_constraints(BUNDLE_COMMITMENT_TYPE, &request.payload).unwrap();
+		assert_eq!(constraints.len(), 3);
+		let other = Constraint {
+			constraint_type: INCLUSION_CONSTRAINT_TYPE,
+			payload: InclusionPayload::random().abi_encode().unwrap(),
+		};
+		constraints.insert(1, other.clone());
+		verify_constraints_cover(&signed_constraints(constraints.clone()), &signed).unwrap();
+
+		constraints.pop();
+		assert!(matches!(
+			verify_constraints_cover(&signed_constraints(constraints), &signed),
+			Err(VerifyError::MissingConstraints { missing: 1, expected: 3 })
+		));
+		assert!(verify_constraints_cover(&signed_constraints(vec![other]), &signed).is_err());
+	}
+
+	#[test]
+	fn test_verify_inclusion_proof() {
+		let transactions = (0..4).map(|_| InclusionPayload::random().decode_transaction().unwrap()).collect::<Vec<_>>();
+		let trie = TransactionTrieBuilder::build(&transactions).unwrap();
+		let header = Header { transactions_root: trie.root().unwrap(), ..Default::default() };
+		let proof = InclusionProof::new(&trie, *transactions[2].tx_hash()).unwrap();
+
+		verify_inclusion_proof(&header, &proof).unwrap();
+
+		// A proof checked against another block's header, or claiming another index, fails
+		let other = Header { transactions_root: B256::repeat_byte(1), ..Default::default() };
+		assert!(verify_inclusion_proof(&other, &proof).is_err());
+		let moved = InclusionProof { tx_index: 1, ..proof };
+		assert!(verify_inclusion_proof(&header, &moved).is_err());
+	}
+}